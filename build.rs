@@ -0,0 +1,4 @@
+fn main() {
+  // Generates the gRPC server and client of `src/grpc`.
+  tonic_build::compile_protos("proto/degen.proto").expect("Failed to compile the gRPC protos");
+}
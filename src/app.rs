@@ -1,52 +1,260 @@
-use axum::Router;
-use http::header;
+use axum::{extract::DefaultBodyLimit, middleware, Router};
+use http::{header, HeaderName, HeaderValue, Method};
+use std::sync::Arc;
+use std::time::Duration;
 use tower_http::{
-  compression::CompressionLayer, cors::CorsLayer, propagate_header::PropagateHeaderLayer,
-  sensitive_headers::SetSensitiveHeadersLayer, trace,
+  compression::{
+    predicate::{NotForContentType, Predicate, SizeAbove},
+    CompressionLayer,
+  },
+  cors::CorsLayer,
+  sensitive_headers::SetSensitiveHeadersLayer,
+  trace,
 };
 
+use crate::grpc;
 use crate::logger;
+use crate::migrations;
 use crate::models;
 use crate::routes;
+use crate::services::{digest, jobs, scheduler, watcher, webhooks};
+use crate::settings::{self, SETTINGS};
+use crate::state::AppState;
+use crate::utils::api_version;
+use crate::utils::audit;
+use crate::utils::authenticate_request::API_KEY_HEADER;
+use crate::utils::binary_format;
+use crate::utils::body_limit;
+use crate::utils::casing;
+use crate::utils::idempotency;
+use crate::utils::maintenance;
+use crate::utils::metrics;
+use crate::utils::rate_limit;
+use crate::utils::request_id;
+use crate::utils::route_table::RouteTable;
+use crate::utils::shutdown::SHUTDOWN;
+use crate::utils::telemetry;
+use crate::utils::timeout;
+use crate::utils::usage;
 
 pub async fn create_app() -> Router {
   logger::setup();
+  metrics::setup();
 
   models::sync_indexes()
     .await
     .expect("Failed to sync database indexes");
+  migrations::run()
+    .await
+    .expect("Failed to run database migrations");
 
-  Router::new()
+  let state = AppState::new(Arc::new(SETTINGS.clone()));
+
+  // Workers are tracked so the shutdown waits for their work in progress.
+  if state.settings.watcher.enabled {
+    SHUTDOWN.track(watcher::spawn(state.clone(), SHUTDOWN.subscribe()));
+  }
+
+  if state.settings.webhooks.enabled {
+    let settings = state.settings.webhooks.clone();
+    SHUTDOWN.track(webhooks::spawn(settings, SHUTDOWN.subscribe()));
+  }
+
+  if state.settings.digest.enabled {
+    if let Some(email) = state.notifiers.email() {
+      let settings = state.settings.digest.clone();
+      SHUTDOWN.track(digest::spawn(settings, email.clone(), SHUTDOWN.subscribe()));
+    }
+  }
+
+  if state.settings.job_queue.enabled {
+    SHUTDOWN.track(jobs::spawn(state.clone(), SHUTDOWN.subscribe()));
+  }
+
+  if state.settings.scheduler.enabled {
+    SHUTDOWN.track(scheduler::spawn(state.clone(), SHUTDOWN.subscribe()));
+  }
+
+  if state.settings.grpc.enabled {
+    SHUTDOWN.track(grpc::spawn(state.clone(), SHUTDOWN.subscribe()));
+  }
+
+  create_router(state)
+}
+
+/// Routes of the API with their middlewares, sharing `state`. Doesn't touch
+/// the database or start the workers, see `create_app`.
+pub fn create_router(state: AppState) -> Router {
+  let compression = compression_layer(&state.settings.compression);
+  let cors = cors_layer(&state.settings.cors);
+
+  let routes = RouteTable::new()
     .merge(routes::status::create_route())
-    .merge(routes::user::create_route())
+    .merge(routes::live::create_route())
+    .merge(routes::graphql::create_route())
+    .merge(metrics::create_route())
+    // Versions of the API are served side by side, see `utils::api_version`.
+    .nest("/v1", v1_routes());
+
+  Router::new()
+    .merge(routes::docs::create_route())
     .merge(
-      Router::new().nest(
-        "/v1",
-        // All public v1 routes will be nested here.
-        Router::new()
-          .merge(routes::cat::create_route())
-          .merge(routes::arkham::create_route()),
-      ),
+      routes
+        .into_router()
+        // Replays the responses of retried requests, see `Idempotency-Key`.
+        .layer(middleware::from_fn_with_state(
+          state.clone(),
+          idempotency::replay_requests,
+        ))
+        // Meters the requests of users against the monthly quotas of their
+        // plan, see `utils::usage`.
+        .layer(middleware::from_fn_with_state(
+          state.clone(),
+          usage::meter_requests,
+        ))
+        // Added to the routes themselves, so the matched path is known.
+        // Wraps the usage metering, so throttled requests are not billed.
+        .layer(middleware::from_fn_with_state(
+          state.clone(),
+          rate_limit::limit_requests,
+        ))
+        // Rejects request bodies over the limit of the route with a 413.
+        // Outside of the idempotency middleware, which reads the bodies.
+        .layer(middleware::from_fn_with_state(
+          state.clone(),
+          body_limit::limit_body_size,
+        ))
+        // The limits of the extractors are replaced by the one above.
+        .layer(DefaultBodyLimit::disable())
+        // Responds with a 504 once the route timeout has passed.
+        .layer(middleware::from_fn_with_state(
+          state.clone(),
+          timeout::limit_duration,
+        ))
+        // Records the route in the request span, see `telemetry::make_span`.
+        .layer(middleware::from_fn(telemetry::record_route))
+        // Counts requests and measures their latency by route.
+        .layer(middleware::from_fn(metrics::track_requests))
+        // Records the changes made by requests in the audit logs.
+        .layer(middleware::from_fn(audit::audit_requests))
+        // Announces the removal of deprecated API versions.
+        .layer(middleware::from_fn_with_state(
+          state.clone(),
+          api_version::add_deprecation_headers,
+        ))
+        // Answers 503 in maintenance, except to the health and admin routes.
+        .layer(middleware::from_fn_with_state(
+          state.clone(),
+          maintenance::reject_during_maintenance,
+        ))
+        .with_state(state),
     )
+    // Rewrite JSON response bodies to the casing requested by the client
+    .layer(middleware::from_fn(casing::convert_response_casing))
     // High level logging of requests and responses
     .layer(
       trace::TraceLayer::new_for_http()
-        .make_span_with(trace::DefaultMakeSpan::new().include_headers(true))
+        .make_span_with(telemetry::make_span)
         .on_request(trace::DefaultOnRequest::new().level(tracing::Level::INFO))
-        .on_response(trace::DefaultOnResponse::new().level(tracing::Level::INFO)),
+        .on_response(telemetry::on_response),
     )
-    // Mark the `Authorization` request header as sensitive so it doesn't
-    // show in logs.
-    .layer(SetSensitiveHeadersLayer::new(std::iter::once(
+    // Mark the `Authorization` and `X-Api-Key` request headers as sensitive
+    // so they don't show in logs.
+    .layer(SetSensitiveHeadersLayer::new([
       header::AUTHORIZATION,
-    )))
-    // Compress responses
-    .layer(CompressionLayer::new())
-    // Propagate `X-Request-Id`s from requests to responses
-    .layer(PropagateHeaderLayer::new(header::HeaderName::from_static(
-      "x-request-id",
-    )))
-    // CORS configuration. This should probably be more restrictive in
-    // production.
-    .layer(CorsLayer::permissive())
+      header::HeaderName::from_static(API_KEY_HEADER),
+    ]))
+    // Compress large responses with the encodings clients accept
+    .layer(compression)
+    // Answer preflight requests and allow the configured origins.
+    .layer(cors)
+    // Send the JSON bodies as MessagePack or CBOR to the clients accepting
+    // them, see `utils::binary_format`.
+    .layer(middleware::from_fn(binary_format::negotiate))
+    // Identify requests by their `X-Request-Id`, generated when missing.
+    // Outermost, so every log line and error body carries it.
+    .layer(middleware::from_fn(request_id::assign_request_id))
+}
+
+/// Public routes of the first version of the API.
+fn v1_routes() -> RouteTable<AppState> {
+  RouteTable::new()
+    .merge(routes::address::create_route())
+    .merge(routes::address_note::create_route())
+    .merge(routes::admin::create_route())
+    .merge(routes::alert::create_route())
+    .merge(routes::api_key::create_route())
+    .merge(routes::approvals::create_route())
+    .merge(routes::auth::create_route())
+    .merge(routes::balances::create_route())
+    .merge(routes::bitcoin::create_route())
+    .merge(routes::cat::create_route())
+    .merge(routes::data_export::create_route())
+    .merge(routes::degen_score::create_route())
+    .merge(routes::dune::create_route())
+    .merge(routes::gas::create_route())
+    .merge(routes::jobs::create_route())
+    .merge(routes::label::create_route())
+    .merge(routes::mfa::create_route())
+    .merge(routes::nfts::create_route())
+    .merge(routes::notification::create_route())
+    .merge(routes::organization::create_route())
+    .merge(routes::portfolio::create_route())
+    .merge(routes::prices::create_route())
+    .merge(routes::risk::create_route())
+    .merge(routes::session::create_route())
+    .merge(routes::stats::create_route())
+    .merge(routes::transactions::create_route())
+    .merge(routes::usage::create_route())
+    .merge(routes::user::create_route())
+    .merge(routes::watchlist::create_route())
+    .merge(routes::webhook::create_route())
+    .merge(routes::arkham::create_route())
+}
+
+/// Compresses responses larger than the configured size with the enabled
+/// encodings. Images and event streams are never compressed, the former
+/// already are and the latter must be flushed as they go.
+fn compression_layer(settings: &settings::Compression) -> CompressionLayer<impl Predicate> {
+  let predicate = SizeAbove::new(settings.min_size_bytes)
+    .and(NotForContentType::GRPC)
+    .and(NotForContentType::IMAGES)
+    .and(NotForContentType::SSE);
+
+  CompressionLayer::new()
+    .gzip(settings.enabled && settings.gzip)
+    .br(settings.enabled && settings.br)
+    .compress_when(predicate)
+}
+
+/// Allows browsers to call the API from the configured origins, or from any
+/// origin in permissive mode. Values are validated with the settings, so
+/// invalid ones never make it here.
+fn cors_layer(settings: &settings::Cors) -> CorsLayer {
+  if settings.permissive {
+    return CorsLayer::permissive();
+  }
+
+  let origins = settings
+    .allowed_origins
+    .iter()
+    .filter_map(|origin| HeaderValue::from_str(origin).ok())
+    .collect::<Vec<HeaderValue>>();
+  let methods = settings
+    .allowed_methods
+    .iter()
+    .filter_map(|method| Method::from_bytes(method.as_bytes()).ok())
+    .collect::<Vec<Method>>();
+  let headers = settings
+    .allowed_headers
+    .iter()
+    .filter_map(|header| HeaderName::from_bytes(header.as_bytes()).ok())
+    .collect::<Vec<HeaderName>>();
+
+  CorsLayer::new()
+    .allow_origin(origins)
+    .allow_methods(methods)
+    .allow_headers(headers)
+    .allow_credentials(settings.allow_credentials)
+    .max_age(Duration::from_secs(settings.max_age_secs))
 }
@@ -1,18 +1,104 @@
 use async_once::AsyncOnce;
 use lazy_static::lazy_static;
-use mongodb::Database;
+use mongodb::bson::doc;
+use mongodb::options::{ClientOptions, ReadPreference, ReadPreferenceOptions, SelectionCriteria};
+use mongodb::{Client, Database};
+use std::time::Duration;
+use tokio::time::sleep;
+use tracing::{error, info, warn};
 use wither::mongodb;
 
-use crate::settings::SETTINGS;
+use crate::errors::Error;
+use crate::settings;
+use crate::settings::{ReadPreferenceMode, SETTINGS};
 
 lazy_static! {
   pub static ref CONNECTION: AsyncOnce<Database> = AsyncOnce::new(async {
-    let db_uri = SETTINGS.database.uri.as_str();
-    let db_name = SETTINGS.database.name.as_str();
-
-    mongodb::Client::with_uri_str(db_uri)
+    connect(&SETTINGS.database)
       .await
       .expect("Failed to initialize MongoDB connection")
-      .database(db_name)
   });
 }
+
+/// Connects to MongoDB and pings the server, retrying with an exponential
+/// backoff. The database is frequently not ready yet when the app boots
+/// (e.g. containers starting at the same time), so giving up on the first
+/// failure would crash-loop the server.
+pub async fn connect(settings: &settings::Database) -> Result<Database, Error> {
+  let options = client_options(settings).await?;
+  let attempts = settings.connect_attempts.max(1);
+  let mut delay = Duration::from_millis(settings.connect_retry_delay_ms);
+  let mut attempt = 1;
+
+  loop {
+    match ping(options.clone(), &settings.name).await {
+      Ok(database) => {
+        info!("Connected to MongoDB database {}", &settings.name);
+        return Ok(database);
+      }
+      Err(err) if attempt < attempts => {
+        warn!(
+          "MongoDB connection attempt {}/{} failed: {}. Retrying in {:?}",
+          attempt, attempts, err, delay
+        );
+        sleep(delay).await;
+        delay *= 2;
+        attempt += 1;
+      }
+      Err(err) => {
+        error!(
+          "MongoDB connection attempt {}/{} failed: {}. Giving up",
+          attempt, attempts, err
+        );
+        return Err(err);
+      }
+    }
+  }
+}
+
+/// Options of the URI, overridden by the ones set in the settings.
+pub async fn client_options(settings: &settings::Database) -> Result<ClientOptions, Error> {
+  let mut options = ClientOptions::parse(&settings.uri).await?;
+  options.min_pool_size = settings.min_pool_size.or(options.min_pool_size);
+  options.max_pool_size = settings.max_pool_size.or(options.max_pool_size);
+  options.server_selection_timeout = settings
+    .server_selection_timeout_ms
+    .map(Duration::from_millis)
+    .or(options.server_selection_timeout);
+  options.connect_timeout = settings
+    .connect_timeout_ms
+    .map(Duration::from_millis)
+    .or(options.connect_timeout);
+  options.retry_writes = settings.retry_writes.or(options.retry_writes);
+  if let Some(mode) = settings.read_preference {
+    options.selection_criteria = Some(SelectionCriteria::ReadPreference(read_preference(mode)));
+  }
+
+  Ok(options)
+}
+
+fn read_preference(mode: ReadPreferenceMode) -> ReadPreference {
+  let options = ReadPreferenceOptions::default();
+  match mode {
+    ReadPreferenceMode::Primary => ReadPreference::Primary,
+    ReadPreferenceMode::PrimaryPreferred => ReadPreference::PrimaryPreferred { options },
+    ReadPreferenceMode::Secondary => ReadPreference::Secondary { options },
+    ReadPreferenceMode::SecondaryPreferred => ReadPreference::SecondaryPreferred { options },
+    ReadPreferenceMode::Nearest => ReadPreference::Nearest { options },
+  }
+}
+
+/// Pings the server over the shared connection.
+pub async fn check() -> Result<(), Error> {
+  let database = CONNECTION.get().await;
+  database.run_command(doc! { "ping": 1 }, None).await?;
+
+  Ok(())
+}
+
+async fn ping(options: ClientOptions, name: &str) -> Result<Database, Error> {
+  let database = Client::with_options(options)?.database(name);
+  database.run_command(doc! { "ping": 1 }, None).await?;
+
+  Ok(database)
+}
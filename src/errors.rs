@@ -1,13 +1,19 @@
 use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
-use axum::Json;
 use bcrypt::BcryptError;
-use serde_json::json;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
 use tokio::task::JoinError;
+use utoipa::ToSchema;
+use validator::ValidationErrors;
 use wither::bson;
 use wither::mongodb::error::Error as MongoError;
 use wither::WitherError;
 
+use crate::utils::json::Json;
+use crate::utils::redact::{redact, redact_value};
+use crate::utils::request_id;
+
 #[derive(thiserror::Error, Debug)]
 #[error("...")]
 pub enum Error {
@@ -32,6 +38,47 @@ pub enum Error {
   #[error("{0}")]
   NotFound(#[from] NotFound),
 
+  #[error("{0}")]
+  Conflict(#[from] Conflict),
+
+  #[error("{0}")]
+  PreconditionRequired(#[from] PreconditionRequired),
+
+  #[error("{0}")]
+  TooManyRequests(#[from] TooManyRequests),
+
+  // Name of the exhausted quota, `requests` or `upstream_credits`.
+  #[error("Monthly {0} quota exceeded")]
+  QuotaExceeded(&'static str),
+
+  #[error("Invalid address {0}")]
+  InvalidAddress(String),
+
+  #[error("{0}")]
+  InvalidPayload(String),
+
+  #[error("Invalid request body")]
+  Validation(#[from] ValidationErrors),
+
+  #[error("{0}")]
+  MalformedPayload(String),
+
+  // Limit of the request body, in bytes.
+  #[error("Request body is larger than {0} bytes")]
+  PayloadTooLarge(usize),
+
+  #[error("{0}")]
+  InvalidQuery(String),
+
+  // A sort or filter field missing from the whitelist of the model, see
+  // `utils::query_fields`.
+  #[error("Unsupported {parameter} field `{field}`, expected one of: {}", .allowed.join(", "))]
+  UnsupportedField {
+    parameter: &'static str,
+    field: String,
+    allowed: Vec<String>,
+  },
+
   #[error("{0}")]
   RunSyncTask(#[from] JoinError),
 
@@ -41,32 +88,133 @@ pub enum Error {
   #[error("{0}")]
   ReqwestError(#[from] reqwest::Error),
 
+  #[error("{service} is unavailable")]
+  UpstreamUnavailable {
+    service: &'static str,
+    // Status code returned by the service, `None` when it couldn't be
+    // reached.
+    status: Option<u16>,
+  },
+
+  #[error("{service} timed out")]
+  UpstreamTimeout { service: &'static str },
+
+  #[error("Invalid upstream response: {0}")]
+  UpstreamInvalidResponse(String),
+
+  // The route didn't respond within its timeout, see `utils::timeout`.
+  #[error("Request timed out")]
+  RequestTimeout,
+
+  // The API is in maintenance mode, see `utils::maintenance`.
+  #[error("The API is under maintenance")]
+  Maintenance { retry_after_secs: u64 },
+
   #[error("{0}")]
   General(String),
 }
 
 impl Error {
-  fn get_codes(&self) -> (StatusCode, u16) {
+  pub fn status_code(&self) -> StatusCode {
     match *self {
       // 4XX Errors
-      Error::ParseObjectID(_) => (StatusCode::BAD_REQUEST, 40001),
-      Error::BadRequest(_) => (StatusCode::BAD_REQUEST, 40002),
-      Error::NotFound(_) => (StatusCode::NOT_FOUND, 40003),
-      Error::Authenticate(AuthenticateError::WrongCredentials) => (StatusCode::UNAUTHORIZED, 40004),
-      Error::Authenticate(AuthenticateError::InvalidToken) => (StatusCode::UNAUTHORIZED, 40005),
-      Error::Authenticate(AuthenticateError::Locked) => (StatusCode::LOCKED, 40006),
+      Error::ParseObjectID(_) => StatusCode::BAD_REQUEST,
+      Error::BadRequest(_) => StatusCode::BAD_REQUEST,
+      Error::NotFound(_) => StatusCode::NOT_FOUND,
+      Error::Authenticate(AuthenticateError::WrongCredentials) => StatusCode::UNAUTHORIZED,
+      Error::Authenticate(AuthenticateError::InvalidToken) => StatusCode::UNAUTHORIZED,
+      Error::Authenticate(AuthenticateError::Locked) => StatusCode::LOCKED,
+      Error::Authenticate(AuthenticateError::MfaRequired) => StatusCode::UNAUTHORIZED,
+      Error::InvalidPayload(_) => StatusCode::UNPROCESSABLE_ENTITY,
+      Error::Validation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+      Error::MalformedPayload(_) => StatusCode::BAD_REQUEST,
+      Error::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+      Error::Authenticate(AuthenticateError::Forbidden) => StatusCode::FORBIDDEN,
+      Error::InvalidAddress(_) => StatusCode::BAD_REQUEST,
+      Error::InvalidQuery(_) => StatusCode::BAD_REQUEST,
+      Error::UnsupportedField { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+      Error::Conflict(_) => StatusCode::CONFLICT,
+      Error::PreconditionRequired(_) => StatusCode::PRECONDITION_REQUIRED,
+      Error::TooManyRequests(_) => StatusCode::TOO_MANY_REQUESTS,
+      Error::QuotaExceeded(_) => StatusCode::TOO_MANY_REQUESTS,
 
       // 5XX Errors
-      Error::Authenticate(AuthenticateError::TokenCreation) => {
-        (StatusCode::INTERNAL_SERVER_ERROR, 5001)
+      Error::Authenticate(AuthenticateError::TokenCreation) => StatusCode::INTERNAL_SERVER_ERROR,
+      Error::Wither(_) => StatusCode::INTERNAL_SERVER_ERROR,
+      Error::Mongo(_) => StatusCode::INTERNAL_SERVER_ERROR,
+      Error::SerializeMongoResponse(_) => StatusCode::INTERNAL_SERVER_ERROR,
+      Error::RunSyncTask(_) => StatusCode::INTERNAL_SERVER_ERROR,
+      Error::HashPassword(_) => StatusCode::INTERNAL_SERVER_ERROR,
+      Error::General(_) => StatusCode::INTERNAL_SERVER_ERROR,
+      Error::ReqwestError(_) => StatusCode::SERVICE_UNAVAILABLE,
+      Error::UpstreamUnavailable { .. } => StatusCode::SERVICE_UNAVAILABLE,
+      Error::UpstreamInvalidResponse(_) => StatusCode::BAD_GATEWAY,
+      Error::UpstreamTimeout { .. } => StatusCode::GATEWAY_TIMEOUT,
+      Error::RequestTimeout => StatusCode::GATEWAY_TIMEOUT,
+      Error::Maintenance { .. } => StatusCode::SERVICE_UNAVAILABLE,
+    }
+  }
+
+  /// Stable machine readable code of the error. Clients should match on it
+  /// rather than on the status code or the message, which may change.
+  pub fn code(&self) -> &'static str {
+    match *self {
+      Error::ParseObjectID(_) => "invalid_id",
+      Error::BadRequest(_) => "bad_request",
+      Error::NotFound(_) => "not_found",
+      Error::Authenticate(AuthenticateError::WrongCredentials) => "invalid_credentials",
+      Error::Authenticate(AuthenticateError::InvalidToken) => "invalid_token",
+      Error::Authenticate(AuthenticateError::Locked) => "user_locked",
+      Error::Authenticate(AuthenticateError::MfaRequired) => "mfa_required",
+      Error::Authenticate(AuthenticateError::Forbidden) => "forbidden",
+      Error::Authenticate(AuthenticateError::TokenCreation) => "internal_error",
+      Error::InvalidPayload(_) | Error::Validation(_) => "validation_failed",
+      Error::MalformedPayload(_) => "malformed_payload",
+      Error::PayloadTooLarge(_) => "payload_too_large",
+      Error::InvalidAddress(_) => "invalid_address",
+      Error::InvalidQuery(_) => "invalid_query",
+      Error::UnsupportedField { .. } => "unsupported_field",
+      Error::Conflict(_) => "conflict",
+      Error::PreconditionRequired(_) => "precondition_required",
+      Error::TooManyRequests(_) => "rate_limited",
+      Error::QuotaExceeded(_) => "quota_exceeded",
+      Error::Wither(_) | Error::Mongo(_) | Error::SerializeMongoResponse(_) => "database_error",
+      Error::RunSyncTask(_) | Error::HashPassword(_) | Error::General(_) => "internal_error",
+      Error::ReqwestError(_) | Error::UpstreamUnavailable { .. } => "upstream_unavailable",
+      Error::UpstreamInvalidResponse(_) => "upstream_invalid_response",
+      Error::UpstreamTimeout { .. } => "upstream_timeout",
+      Error::RequestTimeout => "request_timeout",
+      Error::Maintenance { .. } => "maintenance",
+    }
+  }
+
+  /// Structured context of the error, when there is more to it than the
+  /// message.
+  pub fn details(&self) -> Option<Value> {
+    match self {
+      Error::ParseObjectID(id) => Some(json!({ "id": id })),
+      Error::InvalidAddress(address) => Some(json!({ "address": address })),
+      Error::QuotaExceeded(quota) => Some(json!({ "quota": quota })),
+      Error::PayloadTooLarge(limit) => Some(json!({ "limit_bytes": limit })),
+      Error::UpstreamUnavailable { service, status } => Some(json!({
+        "service": service,
+        "status": status
+      })),
+      Error::UpstreamTimeout { service } => Some(json!({ "service": service })),
+      Error::Maintenance { retry_after_secs } => {
+        Some(json!({ "retry_after_secs": retry_after_secs }))
       }
-      Error::Wither(_) => (StatusCode::INTERNAL_SERVER_ERROR, 5002),
-      Error::Mongo(_) => (StatusCode::INTERNAL_SERVER_ERROR, 5003),
-      Error::SerializeMongoResponse(_) => (StatusCode::INTERNAL_SERVER_ERROR, 5004),
-      Error::RunSyncTask(_) => (StatusCode::INTERNAL_SERVER_ERROR, 5005),
-      Error::HashPassword(_) => (StatusCode::INTERNAL_SERVER_ERROR, 5006),
-      Error::ReqwestError(_) => (StatusCode::INTERNAL_SERVER_ERROR, 5007),
-      Error::General(_) => (StatusCode::INTERNAL_SERVER_ERROR, 5008),
+      Error::Validation(errors) => Some(json!({ "fields": field_errors(errors) })),
+      Error::UnsupportedField {
+        parameter,
+        field,
+        allowed,
+      } => Some(json!({
+        "parameter": parameter,
+        "field": field,
+        "allowed": allowed
+      })),
+      _ => None,
     }
   }
 
@@ -77,15 +225,79 @@ impl Error {
   pub fn not_found() -> Self {
     Error::NotFound(NotFound {})
   }
+
+  pub fn conflict() -> Self {
+    Error::Conflict(Conflict {})
+  }
+
+  pub fn precondition_required() -> Self {
+    Error::PreconditionRequired(PreconditionRequired {})
+  }
+
+  pub fn too_many_requests() -> Self {
+    Error::TooManyRequests(TooManyRequests {})
+  }
+
+  pub fn upstream_unavailable(service: &'static str, status: Option<u16>) -> Self {
+    Error::UpstreamUnavailable { service, status }
+  }
+
+  /// Error of a request to an upstream service that failed before getting
+  /// a response, telling apart the ones that timed out.
+  pub fn upstream_request(service: &'static str, err: reqwest::Error) -> Self {
+    if err.is_timeout() {
+      Error::UpstreamTimeout { service }
+    } else {
+      Error::ReqwestError(err)
+    }
+  }
+}
+
+/// Errors of the invalid fields, by field name, e.g.
+/// `{ "name": [{ "code": "length", "message": "..." }] }`.
+fn field_errors(errors: &ValidationErrors) -> Map<String, Value> {
+  errors
+    .field_errors()
+    .into_iter()
+    .map(|(field, errors)| {
+      let errors = errors
+        .iter()
+        .map(|error| json!({ "code": error.code, "message": error.message }))
+        .collect();
+
+      (field.to_string(), Value::Array(errors))
+    })
+    .collect()
 }
 
 impl IntoResponse for Error {
   fn into_response(self) -> Response {
-    let (status_code, code) = self.get_codes();
-    let message = self.to_string();
-    let body = Json(json!({ "code": code, "message": message }));
+    let body = Json(ErrorResponse::from(&self));
+
+    (self.status_code(), body).into_response()
+  }
+}
+
+/// Body of every error response.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ErrorResponse {
+  // Stable code of the error, e.g. `not_found`, see `Error::code`.
+  pub code: String,
+  pub message: String,
+  pub details: Option<Value>,
+  // ID of the failed request, to be quoted when reporting the error.
+  pub request_id: Option<String>,
+}
 
-    (status_code, body).into_response()
+impl From<&Error> for ErrorResponse {
+  fn from(error: &Error) -> Self {
+    Self {
+      code: error.code().to_owned(),
+      // Upstream errors may quote URLs or bodies containing API keys.
+      message: redact(&error.to_string()).into_owned(),
+      details: error.details().map(redact_value),
+      request_id: request_id::current(),
+    }
   }
 }
 
@@ -100,6 +312,10 @@ pub enum AuthenticateError {
   InvalidToken,
   #[error("User is locked")]
   Locked,
+  #[error("Two-factor authentication code required")]
+  MfaRequired,
+  #[error("User is not allowed to perform this action")]
+  Forbidden,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -109,3 +325,15 @@ pub struct BadRequest {}
 #[derive(thiserror::Error, Debug)]
 #[error("Not found")]
 pub struct NotFound {}
+
+#[derive(thiserror::Error, Debug)]
+#[error("Resource was modified by another request")]
+pub struct Conflict {}
+
+#[derive(thiserror::Error, Debug)]
+#[error("Missing If-Match header")]
+pub struct PreconditionRequired {}
+
+#[derive(thiserror::Error, Debug)]
+#[error("Too many requests")]
+pub struct TooManyRequests {}
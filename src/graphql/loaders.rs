@@ -0,0 +1,96 @@
+use async_graphql::dataloader::{DataLoader, Loader};
+use async_trait::async_trait;
+use futures::future::BoxFuture;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+use tracing::Instrument;
+use wither::bson::{doc, oid::ObjectId, Document};
+use wither::mongodb::options::FindOptions;
+
+use crate::errors::Error;
+use crate::models::address_label::AddressLabel;
+use crate::models::watched_address::WatchedAddress;
+use crate::utils::models::ModelExt;
+
+/// Loaders of a request, batching the lookups of nested fields into a query
+/// per field instead of one per parent.
+pub struct Loaders {
+  pub addresses: DataLoader<AddressesLoader>,
+  pub labels: DataLoader<LabelsLoader>,
+}
+
+impl Loaders {
+  /// Loaders are created per request since they cache their results, and
+  /// labels depend on the organizations of the user.
+  pub fn new(visible_labels: Document) -> Self {
+    Self {
+      addresses: DataLoader::new(AddressesLoader, spawn),
+      labels: DataLoader::new(LabelsLoader { visible_labels }, spawn),
+    }
+  }
+}
+
+// Batches run in their own task, kept in the span of the request so their
+// queries are traced along it.
+fn spawn(batch: BoxFuture<'static, ()>) -> JoinHandle<()> {
+  tokio::spawn(batch.in_current_span())
+}
+
+/// Addresses of watchlists, by watchlist id.
+pub struct AddressesLoader;
+
+#[async_trait]
+impl Loader<ObjectId> for AddressesLoader {
+  type Value = Vec<WatchedAddress>;
+  // Shared with every key of the failed batch.
+  type Error = Arc<Error>;
+
+  async fn load(&self, keys: &[ObjectId]) -> Result<HashMap<ObjectId, Self::Value>, Self::Error> {
+    let options = FindOptions::builder()
+      .sort(doc! { "created_at": 1_i32 })
+      .build();
+    let addresses =
+      WatchedAddress::find(doc! { "watchlist": { "$in": keys.to_vec() } }, options).await?;
+
+    let mut by_watchlist = HashMap::<ObjectId, Self::Value>::new();
+    for address in addresses {
+      by_watchlist
+        .entry(address.watchlist)
+        .or_default()
+        .push(address);
+    }
+
+    Ok(by_watchlist)
+  }
+}
+
+/// Labels visible to the user, by lowercased address.
+pub struct LabelsLoader {
+  visible_labels: Document,
+}
+
+#[async_trait]
+impl Loader<String> for LabelsLoader {
+  type Value = Vec<AddressLabel>;
+  type Error = Arc<Error>;
+
+  async fn load(&self, keys: &[String]) -> Result<HashMap<String, Self::Value>, Self::Error> {
+    let mut query = self.visible_labels.clone();
+    query.insert("eth_address", doc! { "$in": keys.to_vec() });
+    let options = FindOptions::builder()
+      .sort(doc! { "created_at": 1_i32 })
+      .build();
+    let labels = AddressLabel::find(query, options).await?;
+
+    let mut by_address = HashMap::<String, Self::Value>::new();
+    for label in labels {
+      by_address
+        .entry(label.eth_address.clone())
+        .or_default()
+        .push(label);
+    }
+
+    Ok(by_address)
+  }
+}
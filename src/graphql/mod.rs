@@ -0,0 +1,292 @@
+use async_graphql::{
+  Context, EmptyMutation, EmptySubscription, ErrorExtensions, Json, Object, Schema, ID,
+};
+use std::sync::Arc;
+use wither::bson::{doc, oid::ObjectId};
+use wither::mongodb::options::FindOptions;
+
+use crate::errors::Error;
+use crate::models::address_label::AddressLabel;
+use crate::models::cat::Cat;
+use crate::models::watched_address::WatchedAddress;
+use crate::models::watchlist::Watchlist;
+use crate::routes::arkham::ArkhamResponse;
+use crate::services::address_intelligence::IntelligenceProvider;
+use crate::services::ownership::Ownership;
+use crate::settings::SETTINGS;
+use crate::utils::address::{normalize_evm_address, to_checksum_address};
+use crate::utils::date::Date;
+use crate::utils::models::ModelExt;
+use crate::utils::to_object_id::to_object_id;
+
+pub mod loaders;
+
+use loaders::Loaders;
+
+// Deeper queries are rejected, nesting is bounded by the object graph
+// anyway (watchlist → addresses → labels).
+const MAX_DEPTH: usize = 8;
+// Cats returned when no limit is requested.
+const CATS_LIMIT: i64 = 20;
+
+pub type GraphqlSchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+/// Read only GraphQL schema over the models, see `routes::graphql`. Requests
+/// must provide their `Ownership` and `Loaders` as data.
+pub fn build_schema(address_intelligence: Arc<dyn IntelligenceProvider>) -> GraphqlSchema {
+  Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+    .data(address_intelligence)
+    .limit_depth(MAX_DEPTH)
+    .finish()
+}
+
+/// Errors carry the same code as in REST responses, in their extensions.
+impl ErrorExtensions for Error {
+  fn extend(&self) -> async_graphql::Error {
+    async_graphql::Error::new(self.to_string()).extend_with(|_, extensions| {
+      extensions.set("code", self.code());
+    })
+  }
+}
+
+fn to_id(id: Option<ObjectId>) -> ID {
+  ID(id.map(|id| id.to_hex()).unwrap_or_default())
+}
+
+fn to_rfc3339(date: &Date) -> String {
+  date.try_to_rfc3339_string().unwrap_or_default()
+}
+
+fn parse_id(id: &ID) -> async_graphql::Result<ObjectId> {
+  to_object_id(id.as_str()).map_err(|err| err.extend())
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+  /// Cats of the user and their organizations, newest first.
+  async fn cats(
+    &self,
+    ctx: &Context<'_>,
+    limit: Option<i64>,
+  ) -> async_graphql::Result<Vec<CatObject>> {
+    let max_limit = SETTINGS.pagination.max_limit.max(1) as i64;
+    let limit = limit.unwrap_or(CATS_LIMIT).clamp(1, max_limit);
+    let options = FindOptions::builder()
+      .sort(doc! { "created_at": -1_i32, "_id": -1_i32 })
+      .limit(limit)
+      .build();
+
+    let query = ctx.data_unchecked::<Ownership>().readable();
+    let cats = Cat::find(query, options)
+      .await
+      .map_err(|err| err.extend())?;
+
+    Ok(cats.into_iter().map(CatObject).collect())
+  }
+
+  async fn cat(&self, ctx: &Context<'_>, id: ID) -> async_graphql::Result<Option<CatObject>> {
+    let mut query = ctx.data_unchecked::<Ownership>().readable();
+    query.insert("_id", parse_id(&id)?);
+    let cat = Cat::find_one(query, None)
+      .await
+      .map_err(|err| err.extend())?;
+
+    Ok(cat.map(CatObject))
+  }
+
+  /// Watchlists of the user and their organizations, oldest first.
+  async fn watchlists(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<WatchlistObject>> {
+    let options = FindOptions::builder()
+      .sort(doc! { "created_at": 1_i32 })
+      .build();
+    let query = ctx.data_unchecked::<Ownership>().readable();
+    let watchlists = Watchlist::find(query, options)
+      .await
+      .map_err(|err| err.extend())?;
+
+    Ok(watchlists.into_iter().map(WatchlistObject).collect())
+  }
+
+  async fn watchlist(
+    &self,
+    ctx: &Context<'_>,
+    id: ID,
+  ) -> async_graphql::Result<Option<WatchlistObject>> {
+    let mut query = ctx.data_unchecked::<Ownership>().readable();
+    query.insert("_id", parse_id(&id)?);
+    let watchlist = Watchlist::find_one(query, None)
+      .await
+      .map_err(|err| err.extend())?;
+
+    Ok(watchlist.map(WatchlistObject))
+  }
+
+  /// Public labels of an address and the ones of the user organizations.
+  async fn labels(
+    &self,
+    ctx: &Context<'_>,
+    address: String,
+  ) -> async_graphql::Result<Vec<LabelObject>> {
+    let address = normalize_evm_address(address).map_err(|err| err.extend())?;
+    load_labels(ctx, address).await
+  }
+
+  /// Arkham intelligence of an address, served from the cache when fresh.
+  async fn arkham(
+    &self,
+    ctx: &Context<'_>,
+    address: String,
+  ) -> async_graphql::Result<Json<ArkhamResponse>> {
+    let address = normalize_evm_address(address).map_err(|err| err.extend())?;
+    let address_intelligence = ctx.data_unchecked::<Arc<dyn IntelligenceProvider>>();
+    let arkham_data = address_intelligence
+      .lookup_address(&address)
+      .await
+      .map_err(|err| err.extend())?;
+
+    Ok(Json(arkham_data))
+  }
+}
+
+async fn load_labels(
+  ctx: &Context<'_>,
+  address: String,
+) -> async_graphql::Result<Vec<LabelObject>> {
+  let labels = ctx
+    .data_unchecked::<Loaders>()
+    .labels
+    .load_one(address)
+    .await
+    .map_err(|err| err.extend())?
+    .unwrap_or_default();
+
+  Ok(labels.into_iter().map(LabelObject).collect())
+}
+
+pub struct CatObject(Cat);
+
+#[Object(name = "Cat")]
+impl CatObject {
+  async fn id(&self) -> ID {
+    to_id(self.0.id)
+  }
+
+  async fn user(&self) -> ID {
+    to_id(Some(self.0.user))
+  }
+
+  async fn organization(&self) -> Option<ID> {
+    self.0.organization.map(|id| to_id(Some(id)))
+  }
+
+  async fn name(&self) -> &str {
+    &self.0.name
+  }
+
+  async fn tags(&self) -> Vec<String> {
+    self.0.tags.clone()
+  }
+
+  async fn updated_at(&self) -> String {
+    to_rfc3339(&self.0.updated_at)
+  }
+
+  async fn created_at(&self) -> String {
+    to_rfc3339(&self.0.created_at)
+  }
+}
+
+pub struct WatchlistObject(Watchlist);
+
+#[Object(name = "Watchlist")]
+impl WatchlistObject {
+  async fn id(&self) -> ID {
+    to_id(self.0.id)
+  }
+
+  async fn organization(&self) -> Option<ID> {
+    self.0.organization.map(|id| to_id(Some(id)))
+  }
+
+  async fn name(&self) -> &str {
+    &self.0.name
+  }
+
+  async fn created_at(&self) -> String {
+    to_rfc3339(&self.0.created_at)
+  }
+
+  /// Addresses of the watchlist, oldest first. Batched across watchlists.
+  async fn addresses(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<WatchedAddressObject>> {
+    let addresses = ctx
+      .data_unchecked::<Loaders>()
+      .addresses
+      .load_one(self.0.id.unwrap())
+      .await
+      .map_err(|err| err.extend())?
+      .unwrap_or_default();
+
+    Ok(addresses.into_iter().map(WatchedAddressObject).collect())
+  }
+}
+
+pub struct WatchedAddressObject(WatchedAddress);
+
+#[Object(name = "WatchedAddress")]
+impl WatchedAddressObject {
+  async fn id(&self) -> ID {
+    to_id(self.0.id)
+  }
+
+  async fn address(&self) -> String {
+    to_checksum_address(&self.0.address)
+  }
+
+  async fn chain(&self) -> &str {
+    &self.0.chain
+  }
+
+  async fn nickname(&self) -> Option<&str> {
+    self.0.nickname.as_deref()
+  }
+
+  async fn created_at(&self) -> String {
+    to_rfc3339(&self.0.created_at)
+  }
+
+  /// Labels of the address visible to the user. Batched across addresses.
+  async fn labels(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<LabelObject>> {
+    load_labels(ctx, self.0.address.clone()).await
+  }
+}
+
+pub struct LabelObject(AddressLabel);
+
+#[Object(name = "AddressLabel")]
+impl LabelObject {
+  async fn id(&self) -> ID {
+    to_id(self.0.id)
+  }
+
+  async fn organization(&self) -> Option<ID> {
+    self.0.organization.map(|id| to_id(Some(id)))
+  }
+
+  async fn eth_address(&self) -> String {
+    to_checksum_address(&self.0.eth_address)
+  }
+
+  async fn name(&self) -> &str {
+    &self.0.name
+  }
+
+  async fn source(&self) -> &str {
+    &self.0.source
+  }
+
+  async fn created_at(&self) -> String {
+    to_rfc3339(&self.0.created_at)
+  }
+}
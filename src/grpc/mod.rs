@@ -0,0 +1,123 @@
+use axum::extract::FromRequestParts;
+use axum::http::{Method, Request, StatusCode};
+use std::net::SocketAddr;
+use tokio::task::JoinHandle;
+use tonic::metadata::{MetadataMap, MetadataValue};
+use tonic::transport::Server;
+use tonic::{Code, Status};
+use tracing::{error, info};
+
+use crate::errors::Error;
+use crate::models::user::Role;
+use crate::state::AppState;
+use crate::utils::authenticate_request::{require_role, API_KEY_HEADER};
+use crate::utils::redact::redact;
+use crate::utils::shutdown::ShutdownSignal;
+use crate::utils::token::TokenUser;
+
+pub mod services;
+
+/// Messages and services of `proto/degen.proto`, generated by `build.rs`.
+pub mod proto {
+  tonic::include_proto!("degen.v1");
+}
+
+use proto::address_intelligence_server::AddressIntelligenceServer;
+use proto::labels_server::LabelsServer;
+use proto::watchlists_server::WatchlistsServer;
+use services::{IntelligenceService, LabelService, WatchlistService};
+
+// Metadata carrying the code of the failed requests, see `Error::code`.
+const ERROR_CODE_METADATA: &str = "x-error-code";
+
+/// Serves the gRPC API on `grpc.port` of the server host until the shutdown.
+/// The services share the service layer and the authentication of the HTTP
+/// API, see `proto/degen.proto`.
+pub fn spawn(state: AppState, mut shutdown: ShutdownSignal) -> JoinHandle<()> {
+  let host = state
+    .settings
+    .server
+    .address()
+    .expect("Failed to resolve server address")
+    .ip();
+  let address = SocketAddr::new(host, state.settings.grpc.port);
+  info!("gRPC server listening on {}", address);
+
+  tokio::spawn(async move {
+    let result = Server::builder()
+      .add_service(AddressIntelligenceServer::new(IntelligenceService::new(
+        state.clone(),
+      )))
+      .add_service(LabelsServer::new(LabelService))
+      .add_service(WatchlistsServer::new(WatchlistService::new(state)))
+      .serve_with_shutdown(address, shutdown.requested())
+      .await;
+
+    if let Err(err) = result {
+      error!("gRPC server failed: {}", err);
+    }
+    info!("gRPC server stopped");
+  })
+}
+
+/// Authenticates a request like the HTTP API, from the `authorization` or
+/// `x-api-key` metadata. `method` tells reads (`GET`) from writes, which
+/// read only users and API keys can't make.
+pub async fn authenticate(metadata: &MetadataMap, method: Method) -> Result<TokenUser, Status> {
+  let mut request = Request::builder().method(method.clone());
+  for name in ["authorization", API_KEY_HEADER] {
+    if let Some(value) = metadata.get(name).and_then(|value| value.to_str().ok()) {
+      request = request.header(name, value);
+    }
+  }
+
+  let (mut parts, _) = request
+    .body(())
+    .map_err(|_| Status::unauthenticated("Invalid authentication credentials"))?
+    .into_parts();
+  let user = TokenUser::from_request_parts(&mut parts, &()).await?;
+  // Every write of the service changes resources, like the routes taking a
+  // `MemberUser`.
+  if !method.is_safe() {
+    require_role(&user, Role::Member)?;
+  }
+
+  Ok(user)
+}
+
+/// Same as `authenticate` for reads open to anonymous users, `None` when
+/// the request has no credentials.
+pub async fn authenticate_optional(metadata: &MetadataMap) -> Result<Option<TokenUser>, Status> {
+  if metadata.get("authorization").is_none() && metadata.get(API_KEY_HEADER).is_none() {
+    return Ok(None);
+  }
+
+  authenticate(metadata, Method::GET).await.map(Some)
+}
+
+/// Errors carry the status closest to the one of REST responses, and the
+/// same code in their metadata.
+impl From<Error> for Status {
+  fn from(err: Error) -> Self {
+    let code = match err.status_code() {
+      StatusCode::BAD_REQUEST | StatusCode::UNPROCESSABLE_ENTITY => Code::InvalidArgument,
+      StatusCode::PAYLOAD_TOO_LARGE => Code::InvalidArgument,
+      StatusCode::UNAUTHORIZED => Code::Unauthenticated,
+      StatusCode::FORBIDDEN | StatusCode::LOCKED => Code::PermissionDenied,
+      StatusCode::NOT_FOUND => Code::NotFound,
+      StatusCode::CONFLICT => Code::AlreadyExists,
+      StatusCode::PRECONDITION_REQUIRED => Code::FailedPrecondition,
+      StatusCode::TOO_MANY_REQUESTS => Code::ResourceExhausted,
+      StatusCode::BAD_GATEWAY | StatusCode::SERVICE_UNAVAILABLE => Code::Unavailable,
+      StatusCode::GATEWAY_TIMEOUT => Code::DeadlineExceeded,
+      _ => Code::Internal,
+    };
+
+    // Upstream errors may quote URLs or bodies containing API keys.
+    let mut status = Status::new(code, redact(&err.to_string()).into_owned());
+    status
+      .metadata_mut()
+      .insert(ERROR_CODE_METADATA, MetadataValue::from_static(err.code()));
+    status
+  }
+}
@@ -0,0 +1,271 @@
+use axum::http::Method;
+use futures::stream::{BoxStream, StreamExt};
+use tonic::{Request, Response, Status};
+use wither::bson::{doc, Document};
+use wither::mongodb::options::FindOptions;
+
+use crate::errors::Error;
+use crate::grpc::proto;
+use crate::grpc::proto::address_intelligence_server::AddressIntelligence;
+use crate::grpc::proto::labels_server::Labels;
+use crate::grpc::proto::watchlists_server::Watchlists;
+use crate::grpc::{authenticate, authenticate_optional};
+use crate::models::address_label::AddressLabel;
+use crate::models::watched_address::WatchedAddress;
+use crate::models::watchlist::Watchlist;
+use crate::routes::label::labels_visible_to;
+use crate::routes::watchlist::{insert_watchlist, watch_address, AddWatchedAddress};
+use crate::services::ownership::Ownership;
+use crate::settings::SETTINGS;
+use crate::state::AppState;
+use crate::utils::address::{normalize_evm_address, to_checksum_address};
+use crate::utils::date::Date;
+use crate::utils::models::ModelExt;
+use crate::utils::to_object_id::to_object_id;
+use crate::utils::token::TokenUser;
+
+type ResponseStream<T> = BoxStream<'static, Result<T, Status>>;
+
+pub struct IntelligenceService {
+  state: AppState,
+}
+
+impl IntelligenceService {
+  pub fn new(state: AppState) -> Self {
+    Self { state }
+  }
+}
+
+#[tonic::async_trait]
+impl AddressIntelligence for IntelligenceService {
+  async fn lookup_address(
+    &self,
+    request: Request<proto::LookupAddressRequest>,
+  ) -> Result<Response<proto::LookupAddressResponse>, Status> {
+    let user = authenticate(request.metadata(), Method::GET).await?;
+    let address = normalize_evm_address(&request.get_ref().address)?;
+
+    let labels = labels_of(Some(&user), &address).await?;
+    let arkham_data = self
+      .state
+      .address_intelligence
+      .lookup_address(&address)
+      .await?;
+    let arkham_json = serde_json::to_string(&arkham_data)
+      .map_err(|err| Error::General(format!("Failed to serialize Arkham data: {}", err)))?;
+
+    Ok(Response::new(proto::LookupAddressResponse {
+      address: to_checksum_address(&address),
+      labels,
+      arkham_json,
+    }))
+  }
+}
+
+pub struct LabelService;
+
+#[tonic::async_trait]
+impl Labels for LabelService {
+  type SearchLabelsStream = ResponseStream<proto::Label>;
+
+  async fn list_labels(
+    &self,
+    request: Request<proto::ListLabelsRequest>,
+  ) -> Result<Response<proto::ListLabelsResponse>, Status> {
+    let user = authenticate_optional(request.metadata()).await?;
+    let address = normalize_evm_address(&request.get_ref().address)?;
+    let labels = labels_of(user.as_ref(), &address).await?;
+
+    Ok(Response::new(proto::ListLabelsResponse { labels }))
+  }
+
+  async fn search_labels(
+    &self,
+    request: Request<proto::SearchLabelsRequest>,
+  ) -> Result<Response<Self::SearchLabelsStream>, Status> {
+    let user = authenticate_optional(request.metadata()).await?;
+    let search = request.into_inner();
+    let text = search.query.trim();
+    if text.is_empty() {
+      return Err(Error::bad_request().into());
+    }
+
+    let max_limit = SETTINGS.pagination.max_limit;
+    let limit = match search.limit {
+      0 => max_limit,
+      limit => limit.min(max_limit),
+    };
+    let options = FindOptions::builder()
+      .projection(doc! { "score": { "$meta": "textScore" } })
+      .sort(doc! { "score": { "$meta": "textScore" }, "created_at": -1_i32 })
+      .limit(limit as i64)
+      .build();
+
+    let mut query_filter = visible_labels(user.as_ref()).await?;
+    query_filter.insert("$text", doc! { "$search": text });
+    let labels = AddressLabel::cursor(query_filter, options)
+      .await?
+      .map(|label| label.map(proto::Label::from).map_err(Into::into))
+      .boxed();
+
+    Ok(Response::new(labels))
+  }
+}
+
+pub struct WatchlistService {
+  state: AppState,
+}
+
+impl WatchlistService {
+  pub fn new(state: AppState) -> Self {
+    Self { state }
+  }
+}
+
+#[tonic::async_trait]
+impl Watchlists for WatchlistService {
+  type ListWatchedAddressesStream = ResponseStream<proto::WatchedAddress>;
+
+  async fn list_watchlists(
+    &self,
+    request: Request<proto::ListWatchlistsRequest>,
+  ) -> Result<Response<proto::ListWatchlistsResponse>, Status> {
+    let user = authenticate(request.metadata(), Method::GET).await?;
+    let options = FindOptions::builder()
+      .sort(doc! { "created_at": 1_i32 })
+      .build();
+    let query_filter = Ownership::load(user.id).await?.readable();
+    let watchlists = Watchlist::find(query_filter, options)
+      .await?
+      .into_iter()
+      .map(proto::Watchlist::from)
+      .collect();
+
+    Ok(Response::new(proto::ListWatchlistsResponse { watchlists }))
+  }
+
+  async fn create_watchlist(
+    &self,
+    request: Request<proto::CreateWatchlistRequest>,
+  ) -> Result<Response<proto::Watchlist>, Status> {
+    let user = authenticate(request.metadata(), Method::POST).await?;
+    let payload = request.into_inner();
+    let organization = payload
+      .organization
+      .as_deref()
+      .map(to_object_id)
+      .transpose()?;
+    let watchlist = insert_watchlist(&user, &payload.name, organization).await?;
+
+    Ok(Response::new(watchlist.into()))
+  }
+
+  async fn list_watched_addresses(
+    &self,
+    request: Request<proto::ListWatchedAddressesRequest>,
+  ) -> Result<Response<Self::ListWatchedAddressesStream>, Status> {
+    let user = authenticate(request.metadata(), Method::GET).await?;
+    let watchlist_id = to_object_id(&request.get_ref().watchlist)?;
+
+    let mut query_filter = Ownership::load(user.id).await?.readable();
+    query_filter.insert("_id", watchlist_id);
+    if !Watchlist::exists(query_filter).await? {
+      return Err(Error::not_found().into());
+    }
+
+    let options = FindOptions::builder()
+      .sort(doc! { "created_at": 1_i32 })
+      .build();
+    let addresses = WatchedAddress::cursor(doc! { "watchlist": watchlist_id }, options)
+      .await?
+      .map(|watched| watched.map(proto::WatchedAddress::from).map_err(Into::into))
+      .boxed();
+
+    Ok(Response::new(addresses))
+  }
+
+  async fn add_watched_address(
+    &self,
+    request: Request<proto::AddWatchedAddressRequest>,
+  ) -> Result<Response<proto::WatchedAddress>, Status> {
+    let user = authenticate(request.metadata(), Method::POST).await?;
+    let payload = request.into_inner();
+    let watchlist_id = to_object_id(&payload.watchlist)?;
+    let watched = AddWatchedAddress {
+      address: payload.address,
+      chain: payload.chain,
+      nickname: payload.nickname,
+    };
+    let watched = watch_address(&self.state, &user, watchlist_id, watched).await?;
+
+    Ok(Response::new(watched.into()))
+  }
+}
+
+/// Filter matching the labels visible to the user, see
+/// `routes::label::labels_visible_to`.
+async fn visible_labels(user: Option<&TokenUser>) -> Result<Document, Error> {
+  let ownership = match user {
+    Some(user) => Some(Ownership::load(user.id).await?),
+    None => None,
+  };
+
+  labels_visible_to(ownership.as_ref()).await
+}
+
+/// Labels of an address visible to the user, newest first.
+async fn labels_of(user: Option<&TokenUser>, address: &str) -> Result<Vec<proto::Label>, Error> {
+  let mut query_filter = visible_labels(user).await?;
+  query_filter.insert("eth_address", address);
+  let options = FindOptions::builder()
+    .sort(doc! { "created_at": -1_i32, "_id": -1_i32 })
+    .limit(SETTINGS.pagination.max_limit as i64)
+    .build();
+
+  let labels = AddressLabel::find(query_filter, options).await?;
+  Ok(labels.into_iter().map(proto::Label::from).collect())
+}
+
+fn to_rfc3339(date: &Date) -> String {
+  date.try_to_rfc3339_string().unwrap_or_default()
+}
+
+impl From<AddressLabel> for proto::Label {
+  fn from(label: AddressLabel) -> Self {
+    Self {
+      id: label.id.map(|id| id.to_hex()).unwrap_or_default(),
+      organization: label.organization.map(|id| id.to_hex()),
+      eth_address: label.eth_address,
+      name: label.name,
+      source: label.source,
+      created_at: to_rfc3339(&label.created_at),
+    }
+  }
+}
+
+impl From<Watchlist> for proto::Watchlist {
+  fn from(watchlist: Watchlist) -> Self {
+    Self {
+      id: watchlist.id.map(|id| id.to_hex()).unwrap_or_default(),
+      user: watchlist.user.to_hex(),
+      organization: watchlist.organization.map(|id| id.to_hex()),
+      name: watchlist.name,
+      version: watchlist.version,
+      updated_at: to_rfc3339(&watchlist.updated_at),
+      created_at: to_rfc3339(&watchlist.created_at),
+    }
+  }
+}
+
+impl From<WatchedAddress> for proto::WatchedAddress {
+  fn from(watched: WatchedAddress) -> Self {
+    Self {
+      id: watched.id.map(|id| id.to_hex()).unwrap_or_default(),
+      watchlist: watched.watchlist.to_hex(),
+      address: watched.address,
+      chain: watched.chain,
+      nickname: watched.nickname,
+      created_at: to_rfc3339(&watched.created_at),
+    }
+  }
+}
@@ -1,6 +1,18 @@
-use std::env;
+use chrono::{SecondsFormat, Utc};
+use serde_json::{Map, Value};
+use std::{env, fmt};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::fmt::format::{JsonFields, Writer};
+use tracing_subscriber::fmt::{FmtContext, FormatEvent, FormatFields, FormattedFields};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::EnvFilter;
 
-use crate::settings::SETTINGS;
+use crate::settings::{LogFormat, SETTINGS};
+use crate::utils::redact::RedactingWriter;
+use crate::utils::telemetry;
 
 pub fn setup() {
   if env::var_os("RUST_LOG").is_none() {
@@ -10,5 +22,119 @@ pub fn setup() {
     env::set_var("RUST_LOG", env);
   }
 
-  tracing_subscriber::fmt::init();
+  let (text, json) = match SETTINGS.logger.format {
+    LogFormat::Text => (
+      Some(tracing_subscriber::fmt::layer().with_writer(RedactingWriter)),
+      None,
+    ),
+    LogFormat::Json => (
+      None,
+      Some(
+        tracing_subscriber::fmt::layer()
+          .fmt_fields(JsonFields::new())
+          .event_format(JsonFormat)
+          .with_writer(RedactingWriter),
+      ),
+    ),
+  };
+
+  // Spans are also exported when telemetry is enabled, filtered like logs.
+  let telemetry = SETTINGS
+    .telemetry
+    .enabled
+    .then(|| telemetry::layer(&SETTINGS.telemetry));
+
+  tracing_subscriber::registry()
+    .with(EnvFilter::from_default_env())
+    .with(text)
+    .with(json)
+    .with(telemetry)
+    .init();
+}
+
+/// Formats each event as a flat JSON object on a single line, with its
+/// `timestamp`, `level`, `target` and `message`, then the fields of the spans
+/// it happened in, e.g. the `request_id` of the request span, and its own
+/// fields, e.g. the `latency_ms` of the responses. Dotted field names are
+/// written with underscores, e.g. `user.id` as `user_id`, so every log line
+/// uses the same keys.
+pub struct JsonFormat;
+
+impl<S, N> FormatEvent<S, N> for JsonFormat
+where
+  S: Subscriber + for<'a> LookupSpan<'a>,
+  N: for<'a> FormatFields<'a> + 'static,
+{
+  fn format_event(
+    &self,
+    ctx: &FmtContext<'_, S, N>,
+    mut writer: Writer<'_>,
+    event: &Event<'_>,
+  ) -> fmt::Result {
+    let metadata = event.metadata();
+    let mut fields = Map::new();
+    fields.insert(
+      "timestamp".to_owned(),
+      Utc::now()
+        .to_rfc3339_opts(SecondsFormat::Millis, true)
+        .into(),
+    );
+    fields.insert("level".to_owned(), metadata.level().as_str().into());
+    fields.insert("target".to_owned(), metadata.target().into());
+
+    // Outermost spans first, so the fields of the inner ones take precedence.
+    if let Some(scope) = ctx.event_scope() {
+      for span in scope.from_root() {
+        let extensions = span.extensions();
+        let span_fields: Option<Map<String, Value>> = extensions
+          .get::<FormattedFields<N>>()
+          .and_then(|formatted| serde_json::from_str(&formatted.fields).ok());
+        for (name, value) in span_fields.unwrap_or_default() {
+          fields.insert(field_key(&name), value);
+        }
+      }
+    }
+
+    event.record(&mut JsonVisitor(&mut fields));
+
+    writeln!(writer, "{}", Value::Object(fields))
+  }
+}
+
+struct JsonVisitor<'a>(&'a mut Map<String, Value>);
+
+impl<'a> JsonVisitor<'a> {
+  fn insert(&mut self, field: &Field, value: Value) {
+    self.0.insert(field_key(field.name()), value);
+  }
+}
+
+impl<'a> Visit for JsonVisitor<'a> {
+  fn record_f64(&mut self, field: &Field, value: f64) {
+    self.insert(field, value.into());
+  }
+
+  fn record_i64(&mut self, field: &Field, value: i64) {
+    self.insert(field, value.into());
+  }
+
+  fn record_u64(&mut self, field: &Field, value: u64) {
+    self.insert(field, value.into());
+  }
+
+  fn record_bool(&mut self, field: &Field, value: bool) {
+    self.insert(field, value.into());
+  }
+
+  fn record_str(&mut self, field: &Field, value: &str) {
+    self.insert(field, value.into());
+  }
+
+  fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+    self.insert(field, format!("{:?}", value).into());
+  }
+}
+
+fn field_key(name: &str) -> String {
+  name.replace('.', "_")
 }
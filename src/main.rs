@@ -1,13 +1,23 @@
+use std::env;
 use std::net::SocketAddr;
-use tracing::info;
+use std::process;
+use std::time::Duration;
+use tokio::time::sleep;
+use tracing::{error, info, warn};
 
 mod app;
 mod database;
 mod errors;
+mod graphql;
+mod grpc;
 mod logger;
+mod migrations;
 mod models;
+mod notifications;
 mod routes;
+mod services;
 mod settings;
+mod state;
 mod utils;
 
 // There are a couple approaches to take when implementing E2E tests. This
@@ -20,18 +30,112 @@ mod tests;
 
 use errors::Error;
 use settings::SETTINGS;
+use utils::shutdown::{self, SHUTDOWN};
 
 #[tokio::main]
 async fn main() {
+  // Load the .env file before anything reads the environment. This is the
+  // only place where it happens.
   dotenv::dotenv().ok();
+
+  // `rustapi migrate ...` manages the database migrations instead of
+  // starting the server.
+  let args = env::args().skip(1).collect::<Vec<String>>();
+  if args.first().map(String::as_str) == Some("migrate") {
+    migrate(&args[1..]).await;
+    return;
+  }
+  // `rustapi export ...` dumps a collection to JSON.
+  if args.first().map(String::as_str) == Some("export") {
+    export(&args[1..]).await;
+    return;
+  }
+  // `rustapi --dedupe` removes the duplicated labels, which keep the unique
+  // index of labels from being created.
+  if args.first().map(String::as_str) == Some("--dedupe") {
+    dedupe_labels().await;
+    return;
+  }
+
   let app = app::create_app().await;
 
-  let port = SETTINGS.server.port;
-  let address = SocketAddr::from(([0, 0, 0, 0], port));
+  let address = SETTINGS
+    .server
+    .address()
+    .expect("Failed to resolve server address");
 
   info!("Server listening on {}", &address);
-  axum::Server::bind(&address)
-    .serve(app.into_make_service())
-    .await
-    .expect("Failed to start server");
+  let server = axum::Server::bind(&address)
+    .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+    .with_graceful_shutdown(async {
+      shutdown::signal().await;
+      SHUTDOWN.request();
+    });
+
+  // On shutdown the server stops accepting connections and waits for the
+  // in-flight requests, long lived ones (streams, sockets) are cut after the
+  // timeout.
+  let timeout = Duration::from_secs(SETTINGS.server.shutdown_timeout_secs);
+  tokio::select! {
+    result = server => result.expect("Failed to start server"),
+    _ = async {
+      SHUTDOWN.requested().await;
+      sleep(timeout).await;
+    } => warn!("Requests still in flight after {:?}, dropping them", timeout),
+  }
+
+  SHUTDOWN.drain(timeout).await;
+  // The MongoDB driver has no explicit shutdown in this version, its pool is
+  // closed with the process once nothing uses it anymore.
+  utils::telemetry::shutdown();
+  info!("Server stopped");
+}
+
+async fn migrate(args: &[String]) {
+  let command = match migrations::Command::parse(args) {
+    Some(command) => command,
+    None => {
+      eprintln!("{}", migrations::USAGE);
+      process::exit(2);
+    }
+  };
+
+  logger::setup();
+  if let Err(err) = command.execute().await {
+    error!("Failed to run the migrations: {}", err);
+    process::exit(1);
+  }
+}
+
+async fn export(args: &[String]) {
+  let command = match services::export::Command::parse(args) {
+    Some(command) => command,
+    None => {
+      eprintln!("{}", services::export::USAGE);
+      process::exit(2);
+    }
+  };
+
+  // No logger, logs are written on stdout along with the export.
+  if let Err(err) = command.execute().await {
+    eprintln!("Failed to export the collection: {}", err);
+    process::exit(1);
+  }
+}
+
+async fn dedupe_labels() {
+  logger::setup();
+  let removed = match routes::label::remove_duplicates().await {
+    Ok(removed) => removed,
+    Err(err) => {
+      error!("Failed to remove the duplicated labels: {}", err);
+      process::exit(1);
+    }
+  };
+  println!("Removed {} duplicated labels", removed);
+
+  if let Err(err) = models::address_label::AddressLabel::sync_indexes().await {
+    error!("Failed to sync the label indexes: {}", err);
+    process::exit(1);
+  }
 }
@@ -0,0 +1,46 @@
+use async_trait::async_trait;
+use tracing::info;
+use wither::bson::{doc, Bson};
+
+use crate::errors::Error;
+use crate::migrations::Migration;
+use crate::models::cat::Cat;
+use crate::utils::models::ModelExt;
+
+/// Cats created before soft deletes lack the `deleted_at` field. Queries
+/// match a missing field with `null` already, the field is backfilled so
+/// every document has the same shape.
+pub struct BackfillCatDeletedAt;
+
+#[async_trait]
+impl Migration for BackfillCatDeletedAt {
+  fn name(&self) -> &'static str {
+    "0001_backfill_cat_deleted_at"
+  }
+
+  async fn up(&self) -> Result<(), Error> {
+    let result = Cat::update_many(
+      doc! { "deleted_at": { "$exists": false } },
+      doc! { "$set": { "deleted_at": Bson::Null } },
+      None,
+    )
+    .await?;
+
+    info!(
+      "Backfilled the deleted_at of {} cats",
+      result.modified_count
+    );
+    Ok(())
+  }
+
+  async fn down(&self) -> Result<(), Error> {
+    Cat::update_many(
+      doc! { "deleted_at": { "$type": "null" } },
+      doc! { "$unset": { "deleted_at": "" } },
+      None,
+    )
+    .await?;
+
+    Ok(())
+  }
+}
@@ -0,0 +1,50 @@
+use async_trait::async_trait;
+use tracing::info;
+use wither::bson::doc;
+
+use crate::errors::Error;
+use crate::migrations::Migration;
+use crate::models::cat::Cat;
+use crate::models::watchlist::Watchlist;
+use crate::utils::models::ModelExt;
+
+/// Cats and watchlists created before optimistic concurrency control lack
+/// the `version` field, which updates filter on. They start at version 1,
+/// like new documents.
+pub struct BackfillVersions;
+
+#[async_trait]
+impl Migration for BackfillVersions {
+  fn name(&self) -> &'static str {
+    "0002_backfill_versions"
+  }
+
+  async fn up(&self) -> Result<(), Error> {
+    // Filtering on `deleted_at` includes the removed cats too, they all have
+    // the field since `0001_backfill_cat_deleted_at`.
+    let cats = Cat::update_many(
+      doc! { "version": { "$exists": false }, "deleted_at": { "$exists": true } },
+      doc! { "$set": { "version": 1_i64 } },
+      None,
+    )
+    .await?;
+    let watchlists = Watchlist::update_many(
+      doc! { "version": { "$exists": false } },
+      doc! { "$set": { "version": 1_i64 } },
+      None,
+    )
+    .await?;
+
+    info!(
+      "Backfilled the version of {} cats and {} watchlists",
+      cats.modified_count, watchlists.modified_count
+    );
+    Ok(())
+  }
+
+  // Backfilled versions can't be told apart from the ones set since, they
+  // are kept.
+  async fn down(&self) -> Result<(), Error> {
+    Ok(())
+  }
+}
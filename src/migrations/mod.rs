@@ -0,0 +1,145 @@
+use async_trait::async_trait;
+use std::collections::HashSet;
+use tracing::info;
+use wither::bson::doc;
+
+use crate::errors::Error;
+use crate::models::applied_migration::AppliedMigration;
+use crate::utils::models::ModelExt;
+
+mod backfill_cat_deleted_at;
+mod backfill_versions;
+
+pub const USAGE: &str = "Usage: rustapi migrate [run | rollback [steps] | status]";
+
+/// Change of the stored documents that the code reading them can't make,
+/// e.g. backfilling or renaming a field. Migrations are applied once, in
+/// order, and are recorded in the `applied_migrations` collection. They must
+/// be safe to apply again, an instance may stop before recording one.
+#[async_trait]
+pub trait Migration: Send + Sync {
+  /// Unique name, prefixed by the position of the migration, e.g.
+  /// `0001_backfill_cat_deleted_at`.
+  fn name(&self) -> &'static str;
+  async fn up(&self) -> Result<(), Error>;
+  async fn down(&self) -> Result<(), Error>;
+}
+
+/// Every migration, in the order they are applied. New migrations are
+/// appended, applied ones are never changed.
+fn migrations() -> Vec<Box<dyn Migration>> {
+  vec![
+    Box::new(backfill_cat_deleted_at::BackfillCatDeletedAt),
+    Box::new(backfill_versions::BackfillVersions),
+  ]
+}
+
+/// Applies the pending migrations, returning their names. Runs at startup.
+pub async fn run() -> Result<Vec<&'static str>, Error> {
+  let applied = applied_names().await?;
+  let mut names = Vec::new();
+  for migration in migrations() {
+    if applied.contains(migration.name()) {
+      continue;
+    }
+
+    info!("Applying migration {}", migration.name());
+    migration.up().await?;
+    AppliedMigration::create(AppliedMigration::new(migration.name().to_owned())).await?;
+    names.push(migration.name());
+  }
+
+  Ok(names)
+}
+
+/// Rolls back the last `steps` applied migrations, most recent first,
+/// returning their names.
+pub async fn rollback(steps: usize) -> Result<Vec<&'static str>, Error> {
+  let applied = applied_names().await?;
+  let migrations = migrations()
+    .into_iter()
+    .rev()
+    .filter(|migration| applied.contains(migration.name()))
+    .take(steps);
+
+  let mut names = Vec::new();
+  for migration in migrations {
+    info!("Rolling back migration {}", migration.name());
+    migration.down().await?;
+    AppliedMigration::delete_one(doc! { "name": migration.name() }).await?;
+    names.push(migration.name());
+  }
+
+  Ok(names)
+}
+
+/// Every migration with whether it is applied, in order.
+pub async fn status() -> Result<Vec<(&'static str, bool)>, Error> {
+  let applied = applied_names().await?;
+  let status = migrations()
+    .iter()
+    .map(|migration| (migration.name(), applied.contains(migration.name())))
+    .collect();
+
+  Ok(status)
+}
+
+async fn applied_names() -> Result<HashSet<String>, Error> {
+  let applied = AppliedMigration::find(doc! {}, None).await?;
+  let names = applied
+    .into_iter()
+    .map(|migration| migration.name)
+    .collect();
+  Ok(names)
+}
+
+/// Subcommand of `rustapi migrate`, to manage the migrations without
+/// starting the server.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Command {
+  Run,
+  // Number of migrations rolled back, 1 by default.
+  Rollback(usize),
+  Status,
+}
+
+impl Command {
+  /// Parses the arguments following `migrate`, `None` when they are invalid.
+  pub fn parse(args: &[String]) -> Option<Self> {
+    let args = args.iter().map(String::as_str).collect::<Vec<&str>>();
+    match args.as_slice() {
+      [] | ["run"] => Some(Self::Run),
+      ["rollback"] => Some(Self::Rollback(1)),
+      ["rollback", steps] => steps.parse().ok().map(Self::Rollback),
+      ["status"] => Some(Self::Status),
+      _ => None,
+    }
+  }
+
+  pub async fn execute(self) -> Result<(), Error> {
+    match self {
+      Self::Run => {
+        let names = run().await?;
+        println!("Applied {} migrations", names.len());
+        for name in names {
+          println!("  {}", name);
+        }
+      }
+      Self::Rollback(steps) => {
+        let names = rollback(steps).await?;
+        println!("Rolled back {} migrations", names.len());
+        for name in names {
+          println!("  {}", name);
+        }
+      }
+      Self::Status => {
+        for (name, applied) in status().await? {
+          let state = if applied { "applied" } else { "pending" };
+          println!("{:<8} {}", state, name);
+        }
+      }
+    }
+
+    Ok(())
+  }
+}
@@ -0,0 +1,78 @@
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+use wither::bson::{doc, oid::ObjectId};
+use wither::Model as WitherModel;
+
+use crate::utils::date;
+use crate::utils::date::Date;
+use crate::utils::models::ModelExt;
+use crate::utils::secret;
+
+const TOKEN_LENGTH: usize = 48;
+
+impl ModelExt for AccountToken {
+  type T = AccountToken;
+  const AUDITED: bool = false;
+}
+
+/// Token emailed to a user to reset their password or verify their email,
+/// or confirming the deletion of their account. Tokens are single use, they
+/// are deleted once used. Only the hash of the token is stored, and MongoDB
+/// removes the expired ones.
+#[derive(Debug, Clone, Serialize, Deserialize, WitherModel, Validate)]
+#[model(
+  index(keys = r#"doc!{ "hash": 1 }"#, options = r#"doc!{ "unique": true }"#),
+  index(keys = r#"doc!{ "user": 1, "purpose": 1 }"#),
+  index(
+    keys = r#"doc!{ "expires_at": 1 }"#,
+    options = r#"doc!{ "expireAfterSeconds": 0 }"#
+  )
+)]
+pub struct AccountToken {
+  #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+  pub id: Option<ObjectId>,
+  pub user: ObjectId,
+  pub purpose: TokenPurpose,
+  pub hash: String,
+  pub expires_at: Date,
+  pub created_at: Date,
+}
+
+/// What an `AccountToken` was issued for, it can't be used for anything
+/// else.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenPurpose {
+  PasswordReset,
+  EmailVerification,
+  AccountDeletion,
+}
+
+impl TokenPurpose {
+  /// Stored value of the purpose, for queries.
+  pub fn as_str(self) -> &'static str {
+    match self {
+      TokenPurpose::PasswordReset => "password_reset",
+      TokenPurpose::EmailVerification => "email_verification",
+      TokenPurpose::AccountDeletion => "account_deletion",
+    }
+  }
+}
+
+impl AccountToken {
+  /// Generates a token, returned along the model since it can't be recovered
+  /// from the stored hash.
+  pub fn generate(user: ObjectId, purpose: TokenPurpose, expires_at: Date) -> (Self, String) {
+    let token = secret::generate(TOKEN_LENGTH);
+    let account_token = Self {
+      id: None,
+      user,
+      purpose,
+      hash: secret::hash(&token),
+      expires_at,
+      created_at: date::now(),
+    };
+
+    (account_token, token)
+  }
+}
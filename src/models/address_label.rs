@@ -0,0 +1,166 @@
+use axum::http::Method;
+use bson::serde_helpers::bson_datetime_as_rfc3339_string;
+use bson::serde_helpers::serialize_object_id_as_hex_string;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+use wither::bson::{doc, oid::ObjectId};
+use wither::mongodb::options::{Collation, CollationStrength};
+use wither::Model as WitherModel;
+
+use crate::utils::address::to_checksum_address;
+use crate::utils::date;
+use crate::utils::date::Date;
+use crate::utils::links::{LinkBuilder, Links};
+use crate::utils::models::ModelExt;
+use crate::utils::query_fields::FieldType;
+use crate::utils::serde_helpers::serialize_checksum_address;
+use crate::utils::serde_helpers::serialize_optional_object_id_as_hex_string;
+
+impl ModelExt for AddressLabel {
+  type T = AddressLabel;
+}
+
+/// Name given to an EVM address by a source, e.g. an exchange hot wallet
+/// labelled by Arkham. Labels are public, unless they belong to an
+/// organization and are only visible to its members.
+#[derive(Debug, Clone, Serialize, Deserialize, WitherModel, Validate)]
+#[model(
+  index(keys = r#"doc!{ "eth_address": 1, "created_at": 1 }"#),
+  // Backs the removal of the replaced dataset versions.
+  index(keys = r#"doc!{ "dataset": 1 }"#),
+  // A source labels an address once, per organization and dataset version.
+  // Duplicates stored before are removed with `rustapi --dedupe`.
+  index(
+    keys = r#"doc!{ "eth_address": 1, "source": 1, "organization": 1, "dataset": 1 }"#,
+    options = r#"doc!{ "unique": true }"#
+  ),
+  // Backs the label search, MongoDB allows a single text index by collection.
+  index(keys = r#"doc!{ "name": "text", "source": "text" }"#),
+  // Backs the label suggestions, which query with `name_collation` so the
+  // index is used.
+  index(
+    keys = r#"doc!{ "name": 1 }"#,
+    options = r#"doc!{ "collation": { "locale": "en", "strength": 2 } }"#
+  )
+)]
+pub struct AddressLabel {
+  #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+  pub id: Option<ObjectId>,
+  #[serde(default)]
+  pub organization: Option<ObjectId>,
+  // Always stored lowercased, see `normalize_evm_address`.
+  pub eth_address: String,
+  pub name: String,
+  pub source: String,
+  // Version of the label dataset the label was loaded from, see
+  // `services::label_dataset`, unset for the labels created through the API.
+  #[serde(default)]
+  pub dataset: Option<ObjectId>,
+  pub updated_at: Date,
+  pub created_at: Date,
+}
+
+/// Collation comparing label names regardless of their case, e.g. `bin` is
+/// a prefix of `Binance`.
+pub fn name_collation() -> Collation {
+  Collation::builder()
+    .locale("en")
+    .strength(CollationStrength::Secondary)
+    .build()
+}
+
+impl AddressLabel {
+  pub fn new(eth_address: String, name: String, source: String) -> Self {
+    let now = date::now();
+    Self {
+      id: None,
+      organization: None,
+      eth_address,
+      name,
+      source,
+      dataset: None,
+      updated_at: now,
+      created_at: now,
+    }
+  }
+}
+
+/// Fields labels can be sorted by with `?sort=`.
+pub const ADDRESS_LABEL_SORT_FIELDS: &[&str] = &["created_at"];
+
+/// Fields labels can be filtered on with `?filter[field]=`, see `Filters`.
+pub const ADDRESS_LABEL_FILTER_FIELDS: &[(&str, FieldType)] = &[
+  ("organization", FieldType::ObjectId),
+  ("name", FieldType::String),
+  ("source", FieldType::String),
+  ("updated_at", FieldType::Date),
+  ("created_at", FieldType::Date),
+];
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[schema(as = AddressLabel)]
+pub struct PublicAddressLabel {
+  #[serde(alias = "_id", serialize_with = "serialize_object_id_as_hex_string")]
+  #[schema(value_type = String)]
+  pub id: ObjectId,
+  #[serde(default, serialize_with = "serialize_optional_object_id_as_hex_string")]
+  #[schema(value_type = Option<String>)]
+  pub organization: Option<ObjectId>,
+  #[serde(serialize_with = "serialize_checksum_address")]
+  pub eth_address: String,
+  pub name: String,
+  pub source: String,
+  #[serde(with = "bson_datetime_as_rfc3339_string")]
+  #[schema(value_type = String, format = DateTime)]
+  pub updated_at: Date,
+  #[serde(with = "bson_datetime_as_rfc3339_string")]
+  #[schema(value_type = String, format = DateTime)]
+  pub created_at: Date,
+  // Routes of the label and of the other labels of its address, see
+  // `LinkBuilder`. Labels can't be fetched one by one, so there is no `self`.
+  #[serde(default)]
+  #[schema(value_type = BTreeMap<String, Link>)]
+  pub links: Links,
+}
+
+impl From<AddressLabel> for PublicAddressLabel {
+  fn from(label: AddressLabel) -> Self {
+    let id = label.id.unwrap();
+    let links = LinkBuilder::new(format!("/labels/{}", id))
+      .link("delete", Method::DELETE)
+      .related("labels", &format!("/labels/{}", label.eth_address))
+      .build();
+
+    Self {
+      id,
+      organization: label.organization,
+      eth_address: label.eth_address,
+      name: label.name,
+      source: label.source,
+      updated_at: label.updated_at,
+      created_at: label.created_at,
+      links,
+    }
+  }
+}
+
+/// Label whose name starts with the text typed by the user, see
+/// `GET /labels/suggest`.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct LabelSuggestion {
+  pub name: String,
+  // Checksummed, like the addresses of `PublicAddressLabel`.
+  pub address: String,
+  pub source: String,
+}
+
+impl From<AddressLabel> for LabelSuggestion {
+  fn from(label: AddressLabel) -> Self {
+    Self {
+      name: label.name,
+      address: to_checksum_address(&label.eth_address),
+      source: label.source,
+    }
+  }
+}
@@ -0,0 +1,35 @@
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+use wither::bson::{doc, oid::ObjectId};
+use wither::Model as WitherModel;
+
+use crate::utils::date::Date;
+use crate::utils::models::ModelExt;
+
+impl ModelExt for AddressQuery {
+  type T = AddressQuery;
+  const AUDITED: bool = false;
+}
+
+/// Lookups of an address through the Arkham endpoint in a day, counted by
+/// `services::stats`. Anonymous lookups are counted without a user.
+#[derive(Debug, Clone, Serialize, Deserialize, WitherModel, Validate)]
+#[model(
+  index(
+    keys = r#"doc!{ "user": 1, "address": 1, "day": 1 }"#,
+    options = r#"doc!{ "unique": true }"#
+  ),
+  index(keys = r#"doc!{ "day": 1 }"#)
+)]
+pub struct AddressQuery {
+  #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+  pub id: Option<ObjectId>,
+  #[serde(default)]
+  pub user: Option<ObjectId>,
+  pub address: String,
+  // Day of the lookups in UTC, e.g. `2024-05-21`.
+  pub day: String,
+  pub count: i64,
+  pub updated_at: Date,
+  pub created_at: Date,
+}
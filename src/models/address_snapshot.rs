@@ -0,0 +1,71 @@
+use bson::serde_helpers::bson_datetime_as_rfc3339_string;
+use bson::serde_helpers::serialize_object_id_as_hex_string;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+use wither::bson::{doc, oid::ObjectId};
+use wither::Model as WitherModel;
+
+use crate::routes::arkham::ArkhamResponse;
+use crate::utils::date;
+use crate::utils::date::Date;
+use crate::utils::models::ModelExt;
+use crate::utils::serde_helpers::serialize_checksum_address;
+
+impl ModelExt for AddressSnapshot {
+  type T = AddressSnapshot;
+  const AUDITED: bool = false;
+}
+
+/// Arkham data of a watched address, recorded by the watcher every time it
+/// changes. The latest snapshot of an address is the one it is compared to.
+#[derive(Debug, Clone, Serialize, Deserialize, WitherModel, Validate)]
+#[model(
+  index(keys = r#"doc!{ "address": 1, "created_at": -1 }"#),
+  // Backs the removal of the snapshots past their retention.
+  index(keys = r#"doc!{ "created_at": 1 }"#)
+)]
+pub struct AddressSnapshot {
+  #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+  pub id: Option<ObjectId>,
+  // Always stored lowercased, see `normalize_evm_address`.
+  pub address: String,
+  pub data: ArkhamResponse,
+  pub created_at: Date,
+}
+
+impl AddressSnapshot {
+  pub fn new(address: String, data: ArkhamResponse) -> Self {
+    Self {
+      id: None,
+      address,
+      data,
+      created_at: date::now(),
+    }
+  }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[schema(as = AddressSnapshot)]
+pub struct PublicAddressSnapshot {
+  #[serde(alias = "_id", serialize_with = "serialize_object_id_as_hex_string")]
+  #[schema(value_type = String)]
+  pub id: ObjectId,
+  #[serde(serialize_with = "serialize_checksum_address")]
+  pub address: String,
+  pub data: ArkhamResponse,
+  #[serde(with = "bson_datetime_as_rfc3339_string")]
+  #[schema(value_type = String, format = DateTime)]
+  pub created_at: Date,
+}
+
+impl From<AddressSnapshot> for PublicAddressSnapshot {
+  fn from(snapshot: AddressSnapshot) -> Self {
+    Self {
+      id: snapshot.id.unwrap(),
+      address: snapshot.address,
+      data: snapshot.data,
+      created_at: snapshot.created_at,
+    }
+  }
+}
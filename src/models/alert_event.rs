@@ -0,0 +1,161 @@
+use bson::serde_helpers::bson_datetime_as_rfc3339_string;
+use bson::serde_helpers::serialize_object_id_as_hex_string;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+use wither::bson::{doc, oid::ObjectId};
+use wither::Model as WitherModel;
+
+use crate::models::alert_rule::AlertCondition;
+use crate::utils::date;
+use crate::utils::date::Date;
+use crate::utils::models::ModelExt;
+use crate::utils::query_fields::FieldType;
+use crate::utils::serde_helpers::serialize_optional_checksum_address;
+
+impl ModelExt for AlertEvent {
+  type T = AlertEvent;
+  const AUDITED: bool = false;
+}
+
+/// Alert raised when an `AlertRule` matches a change or a transfer of a
+/// watched address, or the price of a token.
+#[derive(Debug, Clone, Serialize, Deserialize, WitherModel, Validate)]
+#[model(
+  index(keys = r#"doc!{ "user": 1, "created_at": -1 }"#),
+  index(keys = r#"doc!{ "rule": 1, "transaction_hash": 1 }"#),
+  // Backs the removal of the events past their retention.
+  index(keys = r#"doc!{ "created_at": 1 }"#)
+)]
+pub struct AlertEvent {
+  #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+  pub id: Option<ObjectId>,
+  pub user: ObjectId,
+  pub rule: ObjectId,
+  pub condition: AlertCondition,
+  // Watched address of the alert, none for price alerts.
+  pub address: Option<String>,
+  pub chain: Option<String>,
+  // CoinGecko id of the token of price alerts.
+  #[serde(default)]
+  pub token: Option<String>,
+  // Human readable description of the change.
+  pub message: String,
+  // Arkham entity of the address when the alert was raised.
+  #[serde(default)]
+  pub entity: Option<String>,
+  // Transfer of `WhaleTransfer` alerts.
+  #[serde(default)]
+  pub transaction_hash: Option<String>,
+  // Label of the other side of the transfer, or its address when it has
+  // none.
+  #[serde(default)]
+  pub counterparty: Option<String>,
+  pub created_at: Date,
+}
+
+impl AlertEvent {
+  pub fn new(
+    user: ObjectId,
+    rule: ObjectId,
+    condition: AlertCondition,
+    address: String,
+    chain: String,
+    message: String,
+  ) -> Self {
+    Self {
+      id: None,
+      user,
+      rule,
+      condition,
+      address: Some(address),
+      chain: Some(chain),
+      token: None,
+      message,
+      entity: None,
+      transaction_hash: None,
+      counterparty: None,
+      created_at: date::now(),
+    }
+  }
+
+  /// Alert on the price of a token.
+  pub fn price(
+    user: ObjectId,
+    rule: ObjectId,
+    condition: AlertCondition,
+    token: String,
+    message: String,
+  ) -> Self {
+    Self {
+      id: None,
+      user,
+      rule,
+      condition,
+      address: None,
+      chain: None,
+      token: Some(token),
+      message,
+      entity: None,
+      transaction_hash: None,
+      counterparty: None,
+      created_at: date::now(),
+    }
+  }
+}
+
+/// Fields alerts can be sorted by with `?sort=`.
+pub const ALERT_EVENT_SORT_FIELDS: &[&str] = &["created_at"];
+
+/// Fields alerts can be filtered on with `?filter[field]=`, see `Filters`.
+pub const ALERT_EVENT_FILTER_FIELDS: &[(&str, FieldType)] = &[
+  ("rule", FieldType::ObjectId),
+  ("condition", FieldType::String),
+  ("address", FieldType::Address),
+  ("chain", FieldType::String),
+  ("token", FieldType::String),
+  ("entity", FieldType::String),
+  ("transaction_hash", FieldType::String),
+  ("created_at", FieldType::Date),
+];
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[schema(as = AlertEvent)]
+pub struct PublicAlertEvent {
+  #[serde(alias = "_id", serialize_with = "serialize_object_id_as_hex_string")]
+  #[schema(value_type = String)]
+  pub id: ObjectId,
+  #[serde(serialize_with = "serialize_object_id_as_hex_string")]
+  #[schema(value_type = String)]
+  pub rule: ObjectId,
+  pub condition: AlertCondition,
+  #[serde(serialize_with = "serialize_optional_checksum_address")]
+  pub address: Option<String>,
+  pub chain: Option<String>,
+  pub token: Option<String>,
+  pub message: String,
+  pub entity: Option<String>,
+  pub transaction_hash: Option<String>,
+  pub counterparty: Option<String>,
+  #[serde(with = "bson_datetime_as_rfc3339_string")]
+  #[schema(value_type = String, format = DateTime)]
+  pub created_at: Date,
+}
+
+impl From<AlertEvent> for PublicAlertEvent {
+  fn from(event: AlertEvent) -> Self {
+    Self {
+      id: event.id.unwrap(),
+      rule: event.rule,
+      condition: event.condition,
+      address: event.address,
+      chain: event.chain,
+      token: event.token,
+      message: event.message,
+      entity: event.entity,
+      transaction_hash: event.transaction_hash,
+      counterparty: event.counterparty,
+      created_at: event.created_at,
+    }
+  }
+}
@@ -0,0 +1,160 @@
+use bson::serde_helpers::bson_datetime_as_rfc3339_string;
+use bson::serde_helpers::serialize_object_id_as_hex_string;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+use wither::bson::{doc, oid::ObjectId};
+use wither::Model as WitherModel;
+
+use crate::utils::date;
+use crate::utils::date::Date;
+use crate::utils::models::ModelExt;
+use crate::utils::serde_helpers::serialize_optional_checksum_address;
+
+impl ModelExt for AlertRule {
+  type T = AlertRule;
+}
+
+/// Condition a user is alerted on when the watcher finds a change in one of
+/// their watched addresses, a large transfer of one of them, or when the
+/// scheduler finds a token price crossing a threshold.
+#[derive(Debug, Clone, Serialize, Deserialize, WitherModel, Validate)]
+#[model(
+  index(keys = r#"doc!{ "user": 1, "created_at": 1 }"#),
+  index(keys = r#"doc!{ "condition": 1 }"#)
+)]
+pub struct AlertRule {
+  #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+  pub id: Option<ObjectId>,
+  pub user: ObjectId,
+  pub condition: AlertCondition,
+  // Restricts the rule to a single watched address, otherwise it applies to
+  // every address watched by the user.
+  pub address: Option<String>,
+  // USD value from which transfers raise a `WhaleTransfer` alert, only set
+  // for these rules.
+  #[serde(default)]
+  pub min_usd: Option<f64>,
+  // CoinGecko id of the token of price rules.
+  #[serde(default)]
+  pub token: Option<String>,
+  // Threshold of `PriceAbove` and `PriceBelow` rules.
+  #[serde(default)]
+  pub price_usd: Option<f64>,
+  // Change of `PriceChange` rules over `window_secs`, negative for drops.
+  #[serde(default)]
+  pub change_percent: Option<f64>,
+  #[serde(default)]
+  pub window_secs: Option<u64>,
+  // Time after raising an alert before the rule can raise another one,
+  // `price_alerts.cooldown_secs` when not set.
+  #[serde(default)]
+  pub cooldown_secs: Option<u64>,
+  #[serde(default)]
+  pub last_triggered_at: Option<Date>,
+  pub updated_at: Date,
+  pub created_at: Date,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertCondition {
+  // The Arkham entity or label name of the address changed.
+  EntityLabelChanged,
+  // The address became flagged as a contract.
+  FlaggedAsContract,
+  // A new Arkham entity was attached to the address.
+  EntityAttached,
+  // The address sent or received a transfer worth at least the `min_usd` of
+  // the rule.
+  WhaleTransfer,
+  // The price of the token rose above the `price_usd` of the rule.
+  PriceAbove,
+  // The price of the token fell below the `price_usd` of the rule.
+  PriceBelow,
+  // The price of the token changed by `change_percent` over the last
+  // `window_secs`.
+  PriceChange,
+}
+
+impl AlertCondition {
+  /// Whether the condition is evaluated on token prices rather than on
+  /// watched addresses.
+  pub fn is_price(self) -> bool {
+    matches!(
+      self,
+      AlertCondition::PriceAbove | AlertCondition::PriceBelow | AlertCondition::PriceChange
+    )
+  }
+}
+
+impl AlertRule {
+  pub fn new(user: ObjectId, condition: AlertCondition, address: Option<String>) -> Self {
+    let now = date::now();
+    Self {
+      id: None,
+      user,
+      condition,
+      address,
+      min_usd: None,
+      token: None,
+      price_usd: None,
+      change_percent: None,
+      window_secs: None,
+      cooldown_secs: None,
+      last_triggered_at: None,
+      updated_at: now,
+      created_at: now,
+    }
+  }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[schema(as = AlertRule)]
+pub struct PublicAlertRule {
+  #[serde(alias = "_id", serialize_with = "serialize_object_id_as_hex_string")]
+  #[schema(value_type = String)]
+  pub id: ObjectId,
+  #[serde(serialize_with = "serialize_object_id_as_hex_string")]
+  #[schema(value_type = String)]
+  pub user: ObjectId,
+  pub condition: AlertCondition,
+  #[serde(serialize_with = "serialize_optional_checksum_address")]
+  pub address: Option<String>,
+  pub min_usd: Option<f64>,
+  pub token: Option<String>,
+  pub price_usd: Option<f64>,
+  pub change_percent: Option<f64>,
+  pub window_secs: Option<u64>,
+  pub cooldown_secs: Option<u64>,
+  #[schema(value_type = Option<String>, format = DateTime)]
+  pub last_triggered_at: Option<String>,
+  #[serde(with = "bson_datetime_as_rfc3339_string")]
+  #[schema(value_type = String, format = DateTime)]
+  pub updated_at: Date,
+  #[serde(with = "bson_datetime_as_rfc3339_string")]
+  #[schema(value_type = String, format = DateTime)]
+  pub created_at: Date,
+}
+
+impl From<AlertRule> for PublicAlertRule {
+  fn from(rule: AlertRule) -> Self {
+    Self {
+      id: rule.id.unwrap(),
+      user: rule.user,
+      condition: rule.condition,
+      address: rule.address,
+      min_usd: rule.min_usd,
+      token: rule.token,
+      price_usd: rule.price_usd,
+      change_percent: rule.change_percent,
+      window_secs: rule.window_secs,
+      cooldown_secs: rule.cooldown_secs,
+      last_triggered_at: rule
+        .last_triggered_at
+        .map(|date| date.try_to_rfc3339_string().unwrap()),
+      updated_at: rule.updated_at,
+      created_at: rule.created_at,
+    }
+  }
+}
@@ -0,0 +1,120 @@
+use bson::serde_helpers::bson_datetime_as_rfc3339_string;
+use bson::serde_helpers::serialize_object_id_as_hex_string;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+use wither::bson::{doc, oid::ObjectId};
+use wither::Model as WitherModel;
+
+use crate::utils::date;
+use crate::utils::date::Date;
+use crate::utils::models::ModelExt;
+use crate::utils::secret;
+
+// Keys look like `dgn_<prefix>_<secret>`. The prefix identifies the key and
+// is shown in listings, the secret is only known by the client.
+const KEY_PREFIX: &str = "dgn";
+const PREFIX_LENGTH: usize = 8;
+const SECRET_LENGTH: usize = 32;
+
+impl ModelExt for ApiKey {
+  type T = ApiKey;
+}
+
+/// Key authenticating server-to-server requests on behalf of a user. Only
+/// the hash of the key is stored.
+#[derive(Debug, Clone, Serialize, Deserialize, WitherModel, Validate)]
+#[model(
+  index(keys = r#"doc!{ "hash": 1 }"#, options = r#"doc!{ "unique": true }"#),
+  index(keys = r#"doc!{ "user": 1, "created_at": 1 }"#)
+)]
+pub struct ApiKey {
+  #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+  pub id: Option<ObjectId>,
+  pub user: ObjectId,
+  #[validate(length(min = 1))]
+  pub name: String,
+  pub prefix: String,
+  pub hash: String,
+  pub scopes: Vec<ApiKeyScope>,
+  pub last_used_at: Option<Date>,
+  pub updated_at: Date,
+  pub created_at: Date,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiKeyScope {
+  // Safe requests, e.g. lookups and listings.
+  Read,
+  // Requests changing data.
+  Write,
+}
+
+impl ApiKey {
+  /// Generates a key, returned along the model since it can't be recovered
+  /// from the stored hash.
+  pub fn generate(user: ObjectId, name: String, scopes: Vec<ApiKeyScope>) -> (Self, String) {
+    let prefix = secret::generate(PREFIX_LENGTH);
+    let key = format!(
+      "{}_{}_{}",
+      KEY_PREFIX,
+      prefix,
+      secret::generate(SECRET_LENGTH)
+    );
+
+    let now = date::now();
+    let api_key = Self {
+      id: None,
+      user,
+      name,
+      prefix,
+      hash: secret::hash(&key),
+      scopes,
+      last_used_at: None,
+      updated_at: now,
+      created_at: now,
+    };
+
+    (api_key, key)
+  }
+
+  pub fn has_scope(&self, scope: ApiKeyScope) -> bool {
+    self.scopes.contains(&scope)
+  }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[schema(as = ApiKey)]
+pub struct PublicApiKey {
+  #[serde(alias = "_id", serialize_with = "serialize_object_id_as_hex_string")]
+  #[schema(value_type = String)]
+  pub id: ObjectId,
+  pub name: String,
+  pub prefix: String,
+  pub scopes: Vec<ApiKeyScope>,
+  #[schema(value_type = Option<String>, format = DateTime)]
+  pub last_used_at: Option<String>,
+  #[serde(with = "bson_datetime_as_rfc3339_string")]
+  #[schema(value_type = String, format = DateTime)]
+  pub updated_at: Date,
+  #[serde(with = "bson_datetime_as_rfc3339_string")]
+  #[schema(value_type = String, format = DateTime)]
+  pub created_at: Date,
+}
+
+impl From<ApiKey> for PublicApiKey {
+  fn from(api_key: ApiKey) -> Self {
+    Self {
+      id: api_key.id.unwrap(),
+      name: api_key.name,
+      prefix: api_key.prefix,
+      scopes: api_key.scopes,
+      last_used_at: api_key
+        .last_used_at
+        .map(|date| date.try_to_rfc3339_string().unwrap()),
+      updated_at: api_key.updated_at,
+      created_at: api_key.created_at,
+    }
+  }
+}
@@ -0,0 +1,34 @@
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+use wither::bson::{doc, oid::ObjectId};
+use wither::Model as WitherModel;
+
+use crate::utils::date;
+use crate::utils::date::Date;
+use crate::utils::models::ModelExt;
+
+impl ModelExt for AppliedMigration {
+  type T = AppliedMigration;
+  const AUDITED: bool = false;
+}
+
+/// Migration applied to the database, see `migrations`. Removed when the
+/// migration is rolled back.
+#[derive(Debug, Clone, Serialize, Deserialize, WitherModel, Validate)]
+#[model(index(keys = r#"doc!{ "name": 1 }"#, options = r#"doc!{ "unique": true }"#))]
+pub struct AppliedMigration {
+  #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+  pub id: Option<ObjectId>,
+  pub name: String,
+  pub applied_at: Date,
+}
+
+impl AppliedMigration {
+  pub fn new(name: String) -> Self {
+    Self {
+      id: None,
+      name,
+      applied_at: date::now(),
+    }
+  }
+}
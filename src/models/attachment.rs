@@ -0,0 +1,74 @@
+use bson::serde_helpers::bson_datetime_as_rfc3339_string;
+use bson::serde_helpers::serialize_object_id_as_hex_string;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use wither::bson::oid::ObjectId;
+
+use crate::utils::date;
+use crate::utils::date::Date;
+
+/// File attached to a resource, e.g. a screenshot of a cat. The content is
+/// stored in GridFS, see `services::gridfs`, the resource only keeps this
+/// description of it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Attachment {
+  // Id of the GridFS file. Not `_id`, so the attachments of the resource
+  // selected with `?fields=` have the shape of `PublicAttachment`.
+  pub id: ObjectId,
+  pub filename: String,
+  pub content_type: String,
+  // Size in bytes.
+  pub size: i64,
+  // User who uploaded the file.
+  pub user: ObjectId,
+  pub created_at: Date,
+}
+
+impl Attachment {
+  pub fn new(
+    id: ObjectId,
+    filename: String,
+    content_type: String,
+    size: u64,
+    user: ObjectId,
+  ) -> Self {
+    Self {
+      id,
+      filename,
+      content_type,
+      size: size as i64,
+      user,
+      created_at: date::now(),
+    }
+  }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[schema(as = Attachment)]
+pub struct PublicAttachment {
+  #[serde(serialize_with = "serialize_object_id_as_hex_string")]
+  #[schema(value_type = String)]
+  pub id: ObjectId,
+  pub filename: String,
+  pub content_type: String,
+  pub size: i64,
+  #[serde(serialize_with = "serialize_object_id_as_hex_string")]
+  #[schema(value_type = String)]
+  pub user: ObjectId,
+  #[serde(with = "bson_datetime_as_rfc3339_string")]
+  #[schema(value_type = String, format = DateTime)]
+  pub created_at: Date,
+}
+
+impl From<Attachment> for PublicAttachment {
+  fn from(attachment: Attachment) -> Self {
+    Self {
+      id: attachment.id,
+      filename: attachment.filename,
+      content_type: attachment.content_type,
+      size: attachment.size,
+      user: attachment.user,
+      created_at: attachment.created_at,
+    }
+  }
+}
@@ -0,0 +1,169 @@
+use bson::serde_helpers::bson_datetime_as_rfc3339_string;
+use bson::serde_helpers::serialize_object_id_as_hex_string;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use utoipa::ToSchema;
+use validator::Validate;
+use wither::bson::{doc, oid::ObjectId, Bson, Document};
+use wither::Model as WitherModel;
+
+use crate::utils::date;
+use crate::utils::date::Date;
+use crate::utils::fields::to_public_json;
+use crate::utils::models::ModelExt;
+use crate::utils::serde_helpers::serialize_optional_object_id_as_hex_string;
+
+// Fields never copied to the audit logs.
+const REDACTED_FIELDS: &[&str] = &["password", "hash", "secret"];
+// Bookkeeping fields left out of update diffs, updates only changing them are
+// not recorded.
+const IGNORED_FIELDS: &[&str] = &["updated_at", "last_used_at", "version"];
+
+impl ModelExt for AuditLog {
+  type T = AuditLog;
+  const AUDITED: bool = false;
+}
+
+/// Change of a document made by an API request, see `utils::audit`.
+#[derive(Debug, Clone, Serialize, Deserialize, WitherModel, Validate)]
+#[model(
+  index(keys = r#"doc!{ "created_at": -1 }"#),
+  index(keys = r#"doc!{ "actor": 1, "created_at": -1 }"#),
+  index(keys = r#"doc!{ "resource": 1, "created_at": -1 }"#)
+)]
+pub struct AuditLog {
+  #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+  pub id: Option<ObjectId>,
+  // User of the request, none for anonymous requests (e.g. sign ups).
+  pub actor: Option<ObjectId>,
+  // Method and matched path of the request, e.g. `DELETE /v1/cats/:id`.
+  pub route: String,
+  // Collection of the document.
+  pub resource: String,
+  pub document: Option<ObjectId>,
+  pub action: AuditAction,
+  // Changed fields only for updates, the whole document otherwise.
+  pub before: Option<Document>,
+  pub after: Option<Document>,
+  pub created_at: Date,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditAction {
+  Create,
+  Update,
+  Delete,
+}
+
+impl AuditLog {
+  pub fn new(
+    actor: Option<ObjectId>,
+    route: String,
+    resource: &str,
+    action: AuditAction,
+    before: Option<Document>,
+    after: Option<Document>,
+  ) -> Self {
+    let document = before
+      .as_ref()
+      .or(after.as_ref())
+      .and_then(|document| document.get_object_id("_id").ok());
+
+    Self {
+      id: None,
+      actor,
+      route,
+      resource: resource.to_owned(),
+      document,
+      action,
+      before: before.map(redact),
+      after: after.map(redact),
+      created_at: date::now(),
+    }
+  }
+
+  /// Fields that differ between two versions of a document, as the before
+  /// and after values. None when only bookkeeping fields changed.
+  pub fn diff(before: &Document, after: &Document) -> Option<(Document, Document)> {
+    let mut changed_before = Document::new();
+    let mut changed_after = Document::new();
+
+    let keys = before
+      .keys()
+      .chain(after.keys().filter(|key| !before.contains_key(key)));
+    for key in keys {
+      if key == "_id" || IGNORED_FIELDS.contains(&key.as_str()) {
+        continue;
+      }
+
+      let old = before.get(key).cloned().unwrap_or(Bson::Null);
+      let new = after.get(key).cloned().unwrap_or(Bson::Null);
+      if old != new {
+        changed_before.insert(key, old);
+        changed_after.insert(key, new);
+      }
+    }
+
+    if changed_after.is_empty() {
+      return None;
+    }
+
+    // Kept to know the changed document.
+    if let Some(id) = after.get("_id") {
+      changed_before.insert("_id", id.clone());
+      changed_after.insert("_id", id.clone());
+    }
+
+    Some((changed_before, changed_after))
+  }
+}
+
+fn redact(mut document: Document) -> Document {
+  for field in REDACTED_FIELDS {
+    if document.contains_key(field) {
+      document.insert(*field, "[redacted]");
+    }
+  }
+  document
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[schema(as = AuditLog)]
+pub struct PublicAuditLog {
+  #[serde(alias = "_id", serialize_with = "serialize_object_id_as_hex_string")]
+  #[schema(value_type = String)]
+  pub id: ObjectId,
+  #[serde(serialize_with = "serialize_optional_object_id_as_hex_string")]
+  #[schema(value_type = Option<String>)]
+  pub actor: Option<ObjectId>,
+  pub route: String,
+  pub resource: String,
+  #[serde(serialize_with = "serialize_optional_object_id_as_hex_string")]
+  #[schema(value_type = Option<String>)]
+  pub document: Option<ObjectId>,
+  pub action: AuditAction,
+  #[schema(value_type = Option<Object>)]
+  pub before: Option<Value>,
+  #[schema(value_type = Option<Object>)]
+  pub after: Option<Value>,
+  #[serde(with = "bson_datetime_as_rfc3339_string")]
+  #[schema(value_type = String, format = DateTime)]
+  pub created_at: Date,
+}
+
+impl From<AuditLog> for PublicAuditLog {
+  fn from(log: AuditLog) -> Self {
+    Self {
+      id: log.id.unwrap(),
+      actor: log.actor,
+      route: log.route,
+      resource: log.resource,
+      document: log.document,
+      action: log.action,
+      before: log.before.map(|before| to_public_json(before.into())),
+      after: log.after.map(|after| to_public_json(after.into())),
+      created_at: log.created_at,
+    }
+  }
+}
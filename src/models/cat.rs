@@ -1,25 +1,53 @@
+use axum::http::Method;
 use bson::serde_helpers::bson_datetime_as_rfc3339_string;
 use bson::serde_helpers::serialize_object_id_as_hex_string;
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use validator::Validate;
 use wither::bson::{doc, oid::ObjectId};
 use wither::Model as WitherModel;
 
+use crate::models::attachment::{Attachment, PublicAttachment};
 use crate::utils::date;
 use crate::utils::date::Date;
+use crate::utils::links::{LinkBuilder, Links};
 use crate::utils::models::ModelExt;
+use crate::utils::query_fields::FieldType;
+use crate::utils::serde_helpers::serialize_optional_object_id_as_hex_string;
 
 impl ModelExt for Cat {
   type T = Cat;
+  const SOFT_DELETE: bool = true;
+  const VERSIONED: bool = true;
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, WitherModel, Validate)]
-#[model(index(keys = r#"doc!{ "user": 1, "created_at": 1 }"#))]
+#[model(
+  index(keys = r#"doc!{ "user": 1, "created_at": 1 }"#),
+  index(keys = r#"doc!{ "organization": 1, "created_at": 1 }"#),
+  // Backs the cat search.
+  index(keys = r#"doc!{ "name": "text" }"#)
+)]
 pub struct Cat {
   #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
   pub id: Option<ObjectId>,
   pub user: ObjectId,
+  // Organization sharing the cat, see `Ownership`.
+  #[serde(default)]
+  pub organization: Option<ObjectId>,
   pub name: String,
+  #[serde(default)]
+  pub tags: Vec<String>,
+  // Files attached to the cat, see `routes::cat::upload_cat_attachment`.
+  #[serde(default)]
+  pub attachments: Vec<Attachment>,
+  // Incremented on every update, used for optimistic concurrency control.
+  #[serde(default)]
+  pub version: i64,
+  // Set when the cat is removed, it can be restored until it is hard deleted
+  // by an admin.
+  #[serde(default)]
+  pub deleted_at: Option<Date>,
   pub updated_at: Date,
   pub created_at: Date,
 }
@@ -30,34 +58,116 @@ impl Cat {
     Self {
       id: None,
       user,
+      organization: None,
       name,
+      tags: Vec::new(),
+      attachments: Vec::new(),
+      version: 1,
+      deleted_at: None,
       updated_at: now,
       created_at: now,
     }
   }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Trims and lowercases tags, dropping empty and repeated ones while keeping
+/// the original order.
+pub fn normalize_tags(tags: Vec<String>) -> Vec<String> {
+  let mut normalized: Vec<String> = Vec::with_capacity(tags.len());
+
+  for tag in tags {
+    let tag = tag.trim().to_lowercase();
+    if !tag.is_empty() && !normalized.contains(&tag) {
+      normalized.push(tag);
+    }
+  }
+
+  normalized
+}
+
+/// Fields of `PublicCat`, clients can select some of them with `?fields=`.
+pub const PUBLIC_CAT_FIELDS: &[&str] = &[
+  "id",
+  "user",
+  "organization",
+  "name",
+  "tags",
+  "attachments",
+  "version",
+  "updated_at",
+  "created_at",
+];
+
+/// Fields cats can be sorted by with `?sort=`.
+pub const CAT_SORT_FIELDS: &[&str] = &["created_at", "updated_at", "name"];
+
+/// Fields cats can be filtered on with `?filter[field]=`, see `Filters`.
+pub const CAT_FILTER_FIELDS: &[(&str, FieldType)] = &[
+  ("organization", FieldType::ObjectId),
+  ("name", FieldType::String),
+  ("tags", FieldType::String),
+  ("version", FieldType::Integer),
+  ("updated_at", FieldType::Date),
+  ("created_at", FieldType::Date),
+];
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[schema(as = Cat)]
 pub struct PublicCat {
   #[serde(alias = "_id", serialize_with = "serialize_object_id_as_hex_string")]
+  #[schema(value_type = String)]
   pub id: ObjectId,
   #[serde(serialize_with = "serialize_object_id_as_hex_string")]
+  #[schema(value_type = String)]
   pub user: ObjectId,
+  #[serde(default, serialize_with = "serialize_optional_object_id_as_hex_string")]
+  #[schema(value_type = Option<String>)]
+  pub organization: Option<ObjectId>,
   pub name: String,
+  pub tags: Vec<String>,
+  #[serde(default)]
+  pub attachments: Vec<PublicAttachment>,
+  pub version: i64,
   #[serde(with = "bson_datetime_as_rfc3339_string")]
+  #[schema(value_type = String, format = DateTime)]
   pub updated_at: Date,
   #[serde(with = "bson_datetime_as_rfc3339_string")]
+  #[schema(value_type = String, format = DateTime)]
   pub created_at: Date,
+  // Routes of the cat and its sub-resources, see `LinkBuilder`.
+  #[serde(default)]
+  #[schema(value_type = BTreeMap<String, Link>)]
+  pub links: Links,
 }
 
 impl From<Cat> for PublicCat {
   fn from(cat: Cat) -> Self {
+    let id = cat.id.unwrap();
+    let links = LinkBuilder::new(format!("/cats/{}", id))
+      .link("self", Method::GET)
+      .link("update", Method::PUT)
+      .link("patch", Method::PATCH)
+      .link("delete", Method::DELETE)
+      .sub("tags", Method::POST, "/tags")
+      .sub("shares", Method::POST, "/shares")
+      .sub("attachments", Method::POST, "/attachments")
+      .build();
+
     Self {
-      id: cat.id.unwrap(),
+      id,
       user: cat.user,
+      organization: cat.organization,
       name: cat.name.clone(),
+      tags: cat.tags,
+      attachments: cat
+        .attachments
+        .into_iter()
+        .map(PublicAttachment::from)
+        .collect(),
+      version: cat.version,
       updated_at: cat.updated_at,
       created_at: cat.created_at,
+      links,
     }
   }
 }
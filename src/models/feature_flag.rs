@@ -0,0 +1,52 @@
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+use wither::bson::{doc, oid::ObjectId};
+use wither::Model as WitherModel;
+
+use crate::utils::date::Date;
+use crate::utils::models::ModelExt;
+
+impl ModelExt for FeatureFlag {
+  type T = FeatureFlag;
+}
+
+/// Flag of a feature set by admins, overriding its configured default, see
+/// `services::feature_flags`.
+#[derive(Debug, Clone, Serialize, Deserialize, WitherModel, Validate)]
+#[model(index(keys = r#"doc!{ "name": 1 }"#, options = r#"doc!{ "unique": true }"#))]
+pub struct FeatureFlag {
+  #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+  pub id: Option<ObjectId>,
+  // Name of the gated feature, e.g. `risk_score`.
+  pub name: String,
+  // Disabled features are off for every user, whatever their rollout.
+  pub enabled: bool,
+  // Share of the users the feature is enabled for, between 0 and 100.
+  #[validate(range(max = 100))]
+  pub rollout_percent: u8,
+  // Users the feature is enabled for regardless of the rollout, e.g. the
+  // testers of a new endpoint.
+  #[serde(default)]
+  pub users: Vec<ObjectId>,
+  pub updated_at: Date,
+  pub created_at: Date,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublicFeatureFlag {
+  pub name: String,
+  pub enabled: bool,
+  pub rollout_percent: u8,
+  pub users: Vec<String>,
+}
+
+impl From<FeatureFlag> for PublicFeatureFlag {
+  fn from(flag: FeatureFlag) -> Self {
+    Self {
+      name: flag.name,
+      enabled: flag.enabled,
+      rollout_percent: flag.rollout_percent,
+      users: flag.users.iter().map(ObjectId::to_hex).collect(),
+    }
+  }
+}
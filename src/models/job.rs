@@ -0,0 +1,143 @@
+use bson::serde_helpers::bson_datetime_as_rfc3339_string;
+use bson::serde_helpers::serialize_object_id_as_hex_string;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+use wither::bson::{doc, oid::ObjectId, Document};
+use wither::Model as WitherModel;
+
+use crate::utils::date;
+use crate::utils::date::Date;
+use crate::utils::models::ModelExt;
+
+impl ModelExt for Job {
+  type T = Job;
+  const AUDITED: bool = false;
+}
+
+/// Expensive task queued by a request and run by the workers of
+/// `services::jobs`, so it survives restarts and can be retried. Clients poll
+/// its status with `GET /jobs/:id`.
+#[derive(Debug, Clone, Serialize, Deserialize, WitherModel, Validate)]
+#[model(
+  index(keys = r#"doc!{ "status": 1, "run_at": 1 }"#),
+  index(keys = r#"doc!{ "user": 1, "created_at": -1 }"#)
+)]
+pub struct Job {
+  #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+  pub id: Option<ObjectId>,
+  pub user: ObjectId,
+  pub kind: JobKind,
+  pub status: JobStatus,
+  // Input of the job, its shape depends on the kind.
+  pub payload: Document,
+  pub result: Option<Document>,
+  pub attempts: u32,
+  // When a queued job is due. Pushed back by the lease of running jobs, so
+  // the jobs of a stopped server are claimed again once it expires.
+  pub run_at: Date,
+  pub last_error: Option<String>,
+  pub started_at: Option<Date>,
+  pub finished_at: Option<Date>,
+  pub updated_at: Date,
+  pub created_at: Date,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum JobKind {
+  LabelImport,
+  GraphExpansion,
+  // Deletion of the account of the job user, see `services::account`.
+  AccountDeletion,
+  // Archive of the data of the job user, see `services::data_export`.
+  DataExport,
+}
+
+impl JobKind {
+  /// Stored value of the kind, for queries.
+  pub fn as_str(self) -> &'static str {
+    match self {
+      JobKind::LabelImport => "label_import",
+      JobKind::GraphExpansion => "graph_expansion",
+      JobKind::AccountDeletion => "account_deletion",
+      JobKind::DataExport => "data_export",
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+  Queued,
+  Running,
+  Succeeded,
+  Failed,
+}
+
+impl Job {
+  pub fn new(user: ObjectId, kind: JobKind, payload: Document) -> Self {
+    let now = date::now();
+    Self {
+      id: None,
+      user,
+      kind,
+      status: JobStatus::Queued,
+      payload,
+      result: None,
+      attempts: 0,
+      run_at: now,
+      last_error: None,
+      started_at: None,
+      finished_at: None,
+      updated_at: now,
+      created_at: now,
+    }
+  }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[schema(as = Job)]
+pub struct PublicJob {
+  #[serde(alias = "_id", serialize_with = "serialize_object_id_as_hex_string")]
+  #[schema(value_type = String)]
+  pub id: ObjectId,
+  pub kind: JobKind,
+  pub status: JobStatus,
+  // Output of a succeeded job, e.g. the summary of a label import.
+  #[schema(value_type = Option<Object>)]
+  pub result: Option<Document>,
+  pub attempts: u32,
+  pub last_error: Option<String>,
+  #[schema(value_type = Option<String>, format = DateTime)]
+  pub started_at: Option<String>,
+  #[schema(value_type = Option<String>, format = DateTime)]
+  pub finished_at: Option<String>,
+  #[serde(with = "bson_datetime_as_rfc3339_string")]
+  #[schema(value_type = String, format = DateTime)]
+  pub updated_at: Date,
+  #[serde(with = "bson_datetime_as_rfc3339_string")]
+  #[schema(value_type = String, format = DateTime)]
+  pub created_at: Date,
+}
+
+impl From<Job> for PublicJob {
+  fn from(job: Job) -> Self {
+    Self {
+      id: job.id.unwrap(),
+      kind: job.kind,
+      status: job.status,
+      result: job.result,
+      attempts: job.attempts,
+      last_error: job.last_error,
+      started_at: job
+        .started_at
+        .map(|date| date.try_to_rfc3339_string().unwrap()),
+      finished_at: job
+        .finished_at
+        .map(|date| date.try_to_rfc3339_string().unwrap()),
+      updated_at: job.updated_at,
+      created_at: job.created_at,
+    }
+  }
+}
@@ -0,0 +1,80 @@
+use bson::serde_helpers::bson_datetime_as_rfc3339_string;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+use wither::bson::{doc, oid::ObjectId};
+use wither::Model as WitherModel;
+
+use crate::models::address_label::AddressLabel;
+use crate::utils::date;
+use crate::utils::date::Date;
+use crate::utils::models::ModelExt;
+use crate::utils::serde_helpers::serialize_checksum_address;
+use crate::utils::serde_helpers::serialize_optional_object_id_as_hex_string;
+
+impl ModelExt for LabelChange {
+  type T = LabelChange;
+  const AUDITED: bool = false;
+}
+
+/// Change of the name a source gives to an address, kept to tell what the
+/// address was labelled as at any time, see `services::label_history`.
+#[derive(Debug, Clone, Serialize, Deserialize, WitherModel, Validate)]
+#[model(index(keys = r#"doc!{ "eth_address": 1, "changed_at": -1 }"#))]
+pub struct LabelChange {
+  #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+  pub id: Option<ObjectId>,
+  #[serde(default)]
+  pub organization: Option<ObjectId>,
+  // Always stored lowercased, like the labels.
+  pub eth_address: String,
+  pub source: String,
+  // Unset when the label was created.
+  pub old_name: Option<String>,
+  // Unset when the label was removed.
+  pub new_name: Option<String>,
+  pub changed_at: Date,
+}
+
+impl LabelChange {
+  pub fn new(label: &AddressLabel, old_name: Option<String>, new_name: Option<String>) -> Self {
+    Self {
+      id: None,
+      organization: label.organization,
+      eth_address: label.eth_address.clone(),
+      source: label.source.clone(),
+      old_name,
+      new_name,
+      changed_at: date::now(),
+    }
+  }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[schema(as = LabelChange)]
+pub struct PublicLabelChange {
+  #[serde(default, serialize_with = "serialize_optional_object_id_as_hex_string")]
+  #[schema(value_type = Option<String>)]
+  pub organization: Option<ObjectId>,
+  #[serde(serialize_with = "serialize_checksum_address")]
+  pub eth_address: String,
+  pub source: String,
+  pub old_name: Option<String>,
+  pub new_name: Option<String>,
+  #[serde(with = "bson_datetime_as_rfc3339_string")]
+  #[schema(value_type = String, format = DateTime)]
+  pub changed_at: Date,
+}
+
+impl From<LabelChange> for PublicLabelChange {
+  fn from(change: LabelChange) -> Self {
+    Self {
+      organization: change.organization,
+      eth_address: change.eth_address,
+      source: change.source,
+      old_name: change.old_name,
+      new_name: change.new_name,
+      changed_at: change.changed_at,
+    }
+  }
+}
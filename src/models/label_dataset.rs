@@ -0,0 +1,27 @@
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+use wither::bson::{doc, oid::ObjectId};
+use wither::Model as WitherModel;
+
+use crate::utils::date::Date;
+use crate::utils::models::ModelExt;
+
+impl ModelExt for LabelDataset {
+  type T = LabelDataset;
+  const AUDITED: bool = false;
+}
+
+/// Version of the label dataset loaded by a reload, see
+/// `services::label_dataset`. Its labels reference it, and are only visible
+/// once it's the active version, the last one loaded.
+#[derive(Debug, Clone, Serialize, Deserialize, WitherModel, Validate)]
+#[model(index(keys = r#"doc!{ "loaded_at": -1 }"#))]
+pub struct LabelDataset {
+  #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+  pub id: Option<ObjectId>,
+  // Path or URL the dataset was read from.
+  pub source: String,
+  // Set once every label is stored, which activates the version.
+  pub loaded_at: Option<Date>,
+  pub created_at: Date,
+}
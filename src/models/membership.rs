@@ -0,0 +1,85 @@
+use bson::serde_helpers::bson_datetime_as_rfc3339_string;
+use bson::serde_helpers::serialize_object_id_as_hex_string;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+use wither::bson::{doc, oid::ObjectId};
+use wither::Model as WitherModel;
+
+use crate::models::user::Role;
+use crate::utils::date;
+use crate::utils::date::Date;
+use crate::utils::models::ModelExt;
+
+impl ModelExt for Membership {
+  type T = Membership;
+}
+
+/// Member of an organization. The role applies to the resources of the
+/// organization only: admins manage the members, members change the shared
+/// resources and read only members look them up.
+#[derive(Debug, Clone, Serialize, Deserialize, WitherModel, Validate)]
+#[model(
+  index(
+    keys = r#"doc!{ "organization": 1, "user": 1 }"#,
+    options = r#"doc!{ "unique": true }"#
+  ),
+  index(keys = r#"doc!{ "user": 1 }"#)
+)]
+pub struct Membership {
+  #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+  pub id: Option<ObjectId>,
+  pub organization: ObjectId,
+  pub user: ObjectId,
+  pub role: Role,
+  pub updated_at: Date,
+  pub created_at: Date,
+}
+
+impl Membership {
+  pub fn new(organization: ObjectId, user: ObjectId, role: Role) -> Self {
+    let now = date::now();
+    Self {
+      id: None,
+      organization,
+      user,
+      role,
+      updated_at: now,
+      created_at: now,
+    }
+  }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[schema(as = Membership)]
+pub struct PublicMembership {
+  #[serde(alias = "_id", serialize_with = "serialize_object_id_as_hex_string")]
+  #[schema(value_type = String)]
+  pub id: ObjectId,
+  #[serde(serialize_with = "serialize_object_id_as_hex_string")]
+  #[schema(value_type = String)]
+  pub organization: ObjectId,
+  #[serde(serialize_with = "serialize_object_id_as_hex_string")]
+  #[schema(value_type = String)]
+  pub user: ObjectId,
+  pub role: Role,
+  #[serde(with = "bson_datetime_as_rfc3339_string")]
+  #[schema(value_type = String, format = DateTime)]
+  pub updated_at: Date,
+  #[serde(with = "bson_datetime_as_rfc3339_string")]
+  #[schema(value_type = String, format = DateTime)]
+  pub created_at: Date,
+}
+
+impl From<Membership> for PublicMembership {
+  fn from(membership: Membership) -> Self {
+    Self {
+      id: membership.id.unwrap(),
+      organization: membership.organization,
+      user: membership.user,
+      role: membership.role,
+      updated_at: membership.updated_at,
+      created_at: membership.created_at,
+    }
+  }
+}
@@ -1,12 +1,76 @@
+pub mod account_token;
+pub mod address_label;
+pub mod address_query;
+pub mod address_snapshot;
+pub mod alert_event;
+pub mod alert_rule;
+pub mod api_key;
+pub mod applied_migration;
+pub mod attachment;
+pub mod audit_log;
 pub mod cat;
+pub mod feature_flag;
+pub mod job;
+pub mod label_change;
+pub mod label_dataset;
+pub mod membership;
+pub mod notification_channel;
+pub mod organization;
+pub mod organization_invite;
+pub mod price_sample;
+pub mod refresh_token;
+pub mod session;
+pub mod share;
+pub mod siwe_nonce;
+pub mod totp_factor;
+pub mod usage;
 pub mod user;
+pub mod user_address_note;
+pub mod watched_address;
+pub mod watchlist;
+pub mod webhook_dead_letter;
+pub mod webhook_delivery;
+pub mod webhook_endpoint;
 
+use crate::services::gridfs;
 use crate::utils::models::ModelExt;
 use crate::Error;
 
 pub async fn sync_indexes() -> Result<(), Error> {
   user::User::sync_indexes().await?;
   cat::Cat::sync_indexes().await?;
+  address_label::AddressLabel::sync_indexes().await?;
+  api_key::ApiKey::sync_indexes().await?;
+  address_snapshot::AddressSnapshot::sync_indexes().await?;
+  alert_rule::AlertRule::sync_indexes().await?;
+  alert_event::AlertEvent::sync_indexes().await?;
+  watchlist::Watchlist::sync_indexes().await?;
+  watched_address::WatchedAddress::sync_indexes().await?;
+  webhook_endpoint::WebhookEndpoint::sync_indexes().await?;
+  webhook_delivery::WebhookDelivery::sync_indexes().await?;
+  webhook_dead_letter::WebhookDeadLetter::sync_indexes().await?;
+  notification_channel::NotificationChannel::sync_indexes().await?;
+  siwe_nonce::SiweNonce::sync_indexes().await?;
+  refresh_token::RefreshToken::sync_indexes().await?;
+  session::Session::sync_indexes().await?;
+  totp_factor::TotpFactor::sync_indexes().await?;
+  account_token::AccountToken::sync_indexes().await?;
+  organization::Organization::sync_indexes().await?;
+  membership::Membership::sync_indexes().await?;
+  organization_invite::OrganizationInvite::sync_indexes().await?;
+  audit_log::AuditLog::sync_indexes().await?;
+  applied_migration::AppliedMigration::sync_indexes().await?;
+  usage::Usage::sync_indexes().await?;
+  job::Job::sync_indexes().await?;
+  share::Share::sync_indexes().await?;
+  user_address_note::UserAddressNote::sync_indexes().await?;
+  feature_flag::FeatureFlag::sync_indexes().await?;
+  label_dataset::LabelDataset::sync_indexes().await?;
+  label_change::LabelChange::sync_indexes().await?;
+  price_sample::PriceSample::sync_indexes().await?;
+  address_query::AddressQuery::sync_indexes().await?;
+  // Attached files aren't a model, see `services::gridfs`.
+  gridfs::create_indexes().await?;
 
   Ok(())
 }
@@ -0,0 +1,89 @@
+use bson::serde_helpers::bson_datetime_as_rfc3339_string;
+use bson::serde_helpers::serialize_object_id_as_hex_string;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+use wither::bson::{doc, oid::ObjectId};
+use wither::Model as WitherModel;
+
+use crate::utils::date;
+use crate::utils::date::Date;
+use crate::utils::models::ModelExt;
+
+impl ModelExt for NotificationChannel {
+  type T = NotificationChannel;
+}
+
+/// Destination the alerts of a user are pushed to, through the `Notifier`
+/// of its kind.
+#[derive(Debug, Clone, Serialize, Deserialize, WitherModel, Validate)]
+#[model(index(keys = r#"doc!{ "user": 1, "created_at": 1 }"#))]
+pub struct NotificationChannel {
+  #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+  pub id: Option<ObjectId>,
+  pub user: ObjectId,
+  pub kind: ChannelKind,
+  // Where the notifier sends the alerts, e.g. a Telegram chat id.
+  pub target: String,
+  // Muted channels are kept but don't receive alerts.
+  #[serde(default = "default_enabled")]
+  pub enabled: bool,
+  pub updated_at: Date,
+  pub created_at: Date,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ChannelKind {
+  Telegram,
+  Discord,
+}
+
+fn default_enabled() -> bool {
+  true
+}
+
+impl NotificationChannel {
+  pub fn new(user: ObjectId, kind: ChannelKind, target: String) -> Self {
+    let now = date::now();
+    Self {
+      id: None,
+      user,
+      kind,
+      target,
+      enabled: true,
+      updated_at: now,
+      created_at: now,
+    }
+  }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[schema(as = NotificationChannel)]
+pub struct PublicNotificationChannel {
+  #[serde(alias = "_id", serialize_with = "serialize_object_id_as_hex_string")]
+  #[schema(value_type = String)]
+  pub id: ObjectId,
+  pub kind: ChannelKind,
+  pub target: String,
+  pub enabled: bool,
+  #[serde(with = "bson_datetime_as_rfc3339_string")]
+  #[schema(value_type = String, format = DateTime)]
+  pub updated_at: Date,
+  #[serde(with = "bson_datetime_as_rfc3339_string")]
+  #[schema(value_type = String, format = DateTime)]
+  pub created_at: Date,
+}
+
+impl From<NotificationChannel> for PublicNotificationChannel {
+  fn from(channel: NotificationChannel) -> Self {
+    Self {
+      id: channel.id.unwrap(),
+      kind: channel.kind,
+      target: channel.target,
+      enabled: channel.enabled,
+      updated_at: channel.updated_at,
+      created_at: channel.created_at,
+    }
+  }
+}
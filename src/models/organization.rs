@@ -0,0 +1,71 @@
+use bson::serde_helpers::bson_datetime_as_rfc3339_string;
+use bson::serde_helpers::serialize_object_id_as_hex_string;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+use wither::bson::{doc, oid::ObjectId};
+use wither::Model as WitherModel;
+
+use crate::utils::date;
+use crate::utils::date::Date;
+use crate::utils::models::ModelExt;
+
+impl ModelExt for Organization {
+  type T = Organization;
+}
+
+/// Team sharing watchlists, labels and cats between its members, see
+/// `Membership`.
+#[derive(Debug, Clone, Serialize, Deserialize, WitherModel, Validate)]
+pub struct Organization {
+  #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+  pub id: Option<ObjectId>,
+  #[validate(length(min = 1))]
+  pub name: String,
+  pub created_by: ObjectId,
+  pub updated_at: Date,
+  pub created_at: Date,
+}
+
+impl Organization {
+  pub fn new(name: String, created_by: ObjectId) -> Self {
+    let now = date::now();
+    Self {
+      id: None,
+      name,
+      created_by,
+      updated_at: now,
+      created_at: now,
+    }
+  }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[schema(as = Organization)]
+pub struct PublicOrganization {
+  #[serde(alias = "_id", serialize_with = "serialize_object_id_as_hex_string")]
+  #[schema(value_type = String)]
+  pub id: ObjectId,
+  pub name: String,
+  #[serde(serialize_with = "serialize_object_id_as_hex_string")]
+  #[schema(value_type = String)]
+  pub created_by: ObjectId,
+  #[serde(with = "bson_datetime_as_rfc3339_string")]
+  #[schema(value_type = String, format = DateTime)]
+  pub updated_at: Date,
+  #[serde(with = "bson_datetime_as_rfc3339_string")]
+  #[schema(value_type = String, format = DateTime)]
+  pub created_at: Date,
+}
+
+impl From<Organization> for PublicOrganization {
+  fn from(organization: Organization) -> Self {
+    Self {
+      id: organization.id.unwrap(),
+      name: organization.name,
+      created_by: organization.created_by,
+      updated_at: organization.updated_at,
+      created_at: organization.created_at,
+    }
+  }
+}
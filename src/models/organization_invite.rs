@@ -0,0 +1,103 @@
+use bson::serde_helpers::bson_datetime_as_rfc3339_string;
+use bson::serde_helpers::serialize_object_id_as_hex_string;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+use wither::bson::{doc, oid::ObjectId};
+use wither::Model as WitherModel;
+
+use crate::models::user::Role;
+use crate::utils::date;
+use crate::utils::date::Date;
+use crate::utils::models::ModelExt;
+use crate::utils::secret;
+
+const TOKEN_LENGTH: usize = 32;
+
+impl ModelExt for OrganizationInvite {
+  type T = OrganizationInvite;
+}
+
+/// Invite to join an organization, accepted by the user with the invited
+/// email. Only the hash of the invite token is stored, and MongoDB removes
+/// the expired invites.
+#[derive(Debug, Clone, Serialize, Deserialize, WitherModel, Validate)]
+#[model(
+  index(keys = r#"doc!{ "hash": 1 }"#, options = r#"doc!{ "unique": true }"#),
+  index(keys = r#"doc!{ "organization": 1, "created_at": 1 }"#),
+  index(
+    keys = r#"doc!{ "expires_at": 1 }"#,
+    options = r#"doc!{ "expireAfterSeconds": 0 }"#
+  )
+)]
+pub struct OrganizationInvite {
+  #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+  pub id: Option<ObjectId>,
+  pub organization: ObjectId,
+  // Always stored lowercased.
+  #[validate(email)]
+  pub email: String,
+  pub role: Role,
+  pub hash: String,
+  pub invited_by: ObjectId,
+  pub expires_at: Date,
+  pub created_at: Date,
+}
+
+impl OrganizationInvite {
+  /// Generates an invite, returned along its token since it can't be
+  /// recovered from the stored hash.
+  pub fn generate(
+    organization: ObjectId,
+    email: String,
+    role: Role,
+    invited_by: ObjectId,
+    expires_at: Date,
+  ) -> (Self, String) {
+    let token = secret::generate(TOKEN_LENGTH);
+    let invite = Self {
+      id: None,
+      organization,
+      email,
+      role,
+      hash: secret::hash(&token),
+      invited_by,
+      expires_at,
+      created_at: date::now(),
+    };
+
+    (invite, token)
+  }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[schema(as = OrganizationInvite)]
+pub struct PublicOrganizationInvite {
+  #[serde(alias = "_id", serialize_with = "serialize_object_id_as_hex_string")]
+  #[schema(value_type = String)]
+  pub id: ObjectId,
+  #[serde(serialize_with = "serialize_object_id_as_hex_string")]
+  #[schema(value_type = String)]
+  pub organization: ObjectId,
+  pub email: String,
+  pub role: Role,
+  #[serde(with = "bson_datetime_as_rfc3339_string")]
+  #[schema(value_type = String, format = DateTime)]
+  pub expires_at: Date,
+  #[serde(with = "bson_datetime_as_rfc3339_string")]
+  #[schema(value_type = String, format = DateTime)]
+  pub created_at: Date,
+}
+
+impl From<OrganizationInvite> for PublicOrganizationInvite {
+  fn from(invite: OrganizationInvite) -> Self {
+    Self {
+      id: invite.id.unwrap(),
+      organization: invite.organization,
+      email: invite.email,
+      role: invite.role,
+      expires_at: invite.expires_at,
+      created_at: invite.created_at,
+    }
+  }
+}
@@ -0,0 +1,46 @@
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+use wither::bson::{doc, oid::ObjectId};
+use wither::Model as WitherModel;
+
+use crate::utils::date;
+use crate::utils::date::Date;
+use crate::utils::models::ModelExt;
+
+impl ModelExt for PriceSample {
+  type T = PriceSample;
+  const AUDITED: bool = false;
+}
+
+/// Price of a token users are alerted on, recorded every time the price
+/// alerts are evaluated. Price change rules compare the current price to the
+/// sample at the start of their window, and MongoDB removes the expired ones.
+#[derive(Debug, Clone, Serialize, Deserialize, WitherModel, Validate)]
+#[model(
+  index(keys = r#"doc!{ "token": 1, "created_at": -1 }"#),
+  index(
+    keys = r#"doc!{ "expires_at": 1 }"#,
+    options = r#"doc!{ "expireAfterSeconds": 0 }"#
+  )
+)]
+pub struct PriceSample {
+  #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+  pub id: Option<ObjectId>,
+  // CoinGecko id of the token.
+  pub token: String,
+  pub usd: f64,
+  pub expires_at: Date,
+  pub created_at: Date,
+}
+
+impl PriceSample {
+  pub fn new(token: String, usd: f64, expires_at: Date) -> Self {
+    Self {
+      id: None,
+      token,
+      usd,
+      expires_at,
+      created_at: date::now(),
+    }
+  }
+}
@@ -0,0 +1,61 @@
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+use wither::bson::{doc, oid::ObjectId};
+use wither::Model as WitherModel;
+
+use crate::utils::date;
+use crate::utils::date::Date;
+use crate::utils::models::ModelExt;
+use crate::utils::secret;
+
+const TOKEN_LENGTH: usize = 48;
+
+impl ModelExt for RefreshToken {
+  type T = RefreshToken;
+  const AUDITED: bool = false;
+}
+
+/// Refresh token handed out along an access token. Tokens are single use,
+/// refreshing marks the token as used and issues the next one of its family.
+/// A used token coming back means it leaked, so the whole family is revoked.
+/// Only the hash of the token is stored, and MongoDB removes the expired
+/// ones.
+#[derive(Debug, Clone, Serialize, Deserialize, WitherModel, Validate)]
+#[model(
+  index(keys = r#"doc!{ "hash": 1 }"#, options = r#"doc!{ "unique": true }"#),
+  index(keys = r#"doc!{ "family": 1 }"#),
+  index(
+    keys = r#"doc!{ "expires_at": 1 }"#,
+    options = r#"doc!{ "expireAfterSeconds": 0 }"#
+  )
+)]
+pub struct RefreshToken {
+  #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+  pub id: Option<ObjectId>,
+  pub user: ObjectId,
+  // Tokens rotated from the same sign in.
+  pub family: ObjectId,
+  pub hash: String,
+  pub used_at: Option<Date>,
+  pub expires_at: Date,
+  pub created_at: Date,
+}
+
+impl RefreshToken {
+  /// Generates a token, returned along the model since it can't be recovered
+  /// from the stored hash.
+  pub fn generate(user: ObjectId, family: ObjectId, expires_at: Date) -> (Self, String) {
+    let token = secret::generate(TOKEN_LENGTH);
+    let refresh_token = Self {
+      id: None,
+      user,
+      family,
+      hash: secret::hash(&token),
+      used_at: None,
+      expires_at,
+      created_at: date::now(),
+    };
+
+    (refresh_token, token)
+  }
+}
@@ -0,0 +1,75 @@
+use bson::serde_helpers::bson_datetime_as_rfc3339_string;
+use bson::serde_helpers::serialize_object_id_as_hex_string;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+use wither::bson::{doc, oid::ObjectId};
+use wither::Model as WitherModel;
+
+use crate::utils::date::Date;
+use crate::utils::models::ModelExt;
+
+impl ModelExt for Session {
+  type T = Session;
+  const AUDITED: bool = false;
+}
+
+/// Sign in of a user, with the refresh token family of the same id. Access
+/// tokens carry the id of their session and are rejected once it is gone,
+/// so deleting a session revokes both its refresh and access tokens. It
+/// expires with its last refresh token, MongoDB removing it then.
+#[derive(Debug, Clone, Serialize, Deserialize, WitherModel, Validate)]
+#[model(
+  index(keys = r#"doc!{ "user": 1, "last_used_at": -1 }"#),
+  index(
+    keys = r#"doc!{ "expires_at": 1 }"#,
+    options = r#"doc!{ "expireAfterSeconds": 0 }"#
+  )
+)]
+pub struct Session {
+  #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+  pub id: Option<ObjectId>,
+  pub user: ObjectId,
+  // User agent of the client, as of the last sign in or refresh.
+  pub device: Option<String>,
+  pub ip: Option<String>,
+  pub last_used_at: Date,
+  pub expires_at: Date,
+  pub created_at: Date,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[schema(as = Session)]
+pub struct PublicSession {
+  #[serde(alias = "_id", serialize_with = "serialize_object_id_as_hex_string")]
+  #[schema(value_type = String)]
+  pub id: ObjectId,
+  pub device: Option<String>,
+  pub ip: Option<String>,
+  // Whether the request was authenticated by this session.
+  #[serde(default)]
+  pub current: bool,
+  #[serde(with = "bson_datetime_as_rfc3339_string")]
+  #[schema(value_type = String, format = DateTime)]
+  pub last_used_at: Date,
+  #[serde(with = "bson_datetime_as_rfc3339_string")]
+  #[schema(value_type = String, format = DateTime)]
+  pub expires_at: Date,
+  #[serde(with = "bson_datetime_as_rfc3339_string")]
+  #[schema(value_type = String, format = DateTime)]
+  pub created_at: Date,
+}
+
+impl From<Session> for PublicSession {
+  fn from(session: Session) -> Self {
+    Self {
+      id: session.id.unwrap(),
+      device: session.device,
+      ip: session.ip,
+      current: false,
+      last_used_at: session.last_used_at,
+      expires_at: session.expires_at,
+      created_at: session.created_at,
+    }
+  }
+}
@@ -0,0 +1,133 @@
+use bson::serde_helpers::bson_datetime_as_rfc3339_string;
+use bson::serde_helpers::serialize_object_id_as_hex_string;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+use wither::bson::{doc, oid::ObjectId};
+use wither::Model as WitherModel;
+
+use crate::models::user::Role;
+use crate::utils::date;
+use crate::utils::date::Date;
+use crate::utils::models::ModelExt;
+
+impl ModelExt for Share {
+  type T = Share;
+}
+
+/// Access to a cat or watchlist granted by its owner to another user, see
+/// `Ownership`. A user has at most one grant per resource.
+#[derive(Debug, Clone, Serialize, Deserialize, WitherModel, Validate)]
+#[model(
+  index(
+    keys = r#"doc!{ "resource": 1, "user": 1 }"#,
+    options = r#"doc!{ "unique": true }"#
+  ),
+  index(keys = r#"doc!{ "user": 1 }"#)
+)]
+pub struct Share {
+  #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+  pub id: Option<ObjectId>,
+  pub resource_type: SharedResource,
+  pub resource: ObjectId,
+  // User the resource is shared with.
+  pub user: ObjectId,
+  pub granted_by: ObjectId,
+  pub access: ShareAccess,
+  pub updated_at: Date,
+  pub created_at: Date,
+}
+
+impl Share {
+  pub fn new(
+    resource_type: SharedResource,
+    resource: ObjectId,
+    user: ObjectId,
+    granted_by: ObjectId,
+    access: ShareAccess,
+  ) -> Self {
+    let now = date::now();
+    Self {
+      id: None,
+      resource_type,
+      resource,
+      user,
+      granted_by,
+      access,
+      updated_at: now,
+      created_at: now,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SharedResource {
+  Cat,
+  Watchlist,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ShareAccess {
+  Read,
+  ReadWrite,
+}
+
+impl ShareAccess {
+  /// Stored value of the access, for queries.
+  pub fn as_str(self) -> &'static str {
+    match self {
+      ShareAccess::Read => "read",
+      ShareAccess::ReadWrite => "read_write",
+    }
+  }
+
+  /// Organization role with the same permissions on the resource.
+  pub fn role(self) -> Role {
+    match self {
+      ShareAccess::Read => Role::ReadOnly,
+      ShareAccess::ReadWrite => Role::Member,
+    }
+  }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[schema(as = Share)]
+pub struct PublicShare {
+  #[serde(alias = "_id", serialize_with = "serialize_object_id_as_hex_string")]
+  #[schema(value_type = String)]
+  pub id: ObjectId,
+  pub resource_type: SharedResource,
+  #[serde(serialize_with = "serialize_object_id_as_hex_string")]
+  #[schema(value_type = String)]
+  pub resource: ObjectId,
+  #[serde(serialize_with = "serialize_object_id_as_hex_string")]
+  #[schema(value_type = String)]
+  pub user: ObjectId,
+  #[serde(serialize_with = "serialize_object_id_as_hex_string")]
+  #[schema(value_type = String)]
+  pub granted_by: ObjectId,
+  pub access: ShareAccess,
+  #[serde(with = "bson_datetime_as_rfc3339_string")]
+  #[schema(value_type = String, format = DateTime)]
+  pub updated_at: Date,
+  #[serde(with = "bson_datetime_as_rfc3339_string")]
+  #[schema(value_type = String, format = DateTime)]
+  pub created_at: Date,
+}
+
+impl From<Share> for PublicShare {
+  fn from(share: Share) -> Self {
+    Self {
+      id: share.id.unwrap(),
+      resource_type: share.resource_type,
+      resource: share.resource,
+      user: share.user,
+      granted_by: share.granted_by,
+      access: share.access,
+      updated_at: share.updated_at,
+      created_at: share.created_at,
+    }
+  }
+}
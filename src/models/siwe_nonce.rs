@@ -0,0 +1,42 @@
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+use wither::bson::{doc, oid::ObjectId};
+use wither::Model as WitherModel;
+
+use crate::utils::date;
+use crate::utils::date::Date;
+use crate::utils::models::ModelExt;
+
+impl ModelExt for SiweNonce {
+  type T = SiweNonce;
+  const AUDITED: bool = false;
+}
+
+/// Nonce handed out for a Sign-In With Ethereum message. It is deleted once
+/// used, and MongoDB removes the expired ones.
+#[derive(Debug, Clone, Serialize, Deserialize, WitherModel, Validate)]
+#[model(
+  index(keys = r#"doc!{ "nonce": 1 }"#, options = r#"doc!{ "unique": true }"#),
+  index(
+    keys = r#"doc!{ "expires_at": 1 }"#,
+    options = r#"doc!{ "expireAfterSeconds": 0 }"#
+  )
+)]
+pub struct SiweNonce {
+  #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+  pub id: Option<ObjectId>,
+  pub nonce: String,
+  pub expires_at: Date,
+  pub created_at: Date,
+}
+
+impl SiweNonce {
+  pub fn new(nonce: String, expires_at: Date) -> Self {
+    Self {
+      id: None,
+      nonce,
+      expires_at,
+      created_at: date::now(),
+    }
+  }
+}
@@ -0,0 +1,50 @@
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+use wither::bson::{doc, oid::ObjectId};
+use wither::Model as WitherModel;
+
+use crate::utils::date;
+use crate::utils::date::Date;
+use crate::utils::models::ModelExt;
+
+// Not audited, the changes would copy the secret into the audit logs.
+impl ModelExt for TotpFactor {
+  type T = TotpFactor;
+  const AUDITED: bool = false;
+}
+
+/// TOTP second factor of a user, see `services::mfa`. It is pending until
+/// the user confirms it with a first code, which enables two-factor
+/// authentication on the user.
+#[derive(Debug, Clone, Serialize, Deserialize, WitherModel, Validate)]
+#[model(index(keys = r#"doc!{ "user": 1 }"#, options = r#"doc!{ "unique": true }"#))]
+pub struct TotpFactor {
+  #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+  pub id: Option<ObjectId>,
+  pub user: ObjectId,
+  // Base32 encoded, as shared with the authenticator app.
+  pub secret: String,
+  // Hashes of the unused recovery codes, each one can replace a code once.
+  pub recovery_codes: Vec<String>,
+  // Time step of the last accepted code, so codes can't be replayed.
+  pub last_step: Option<i64>,
+  pub confirmed_at: Option<Date>,
+  pub updated_at: Date,
+  pub created_at: Date,
+}
+
+impl TotpFactor {
+  pub fn new(user: ObjectId, secret: String) -> Self {
+    let now = date::now();
+    Self {
+      id: None,
+      user,
+      secret,
+      recovery_codes: Vec::new(),
+      last_step: None,
+      confirmed_at: None,
+      updated_at: now,
+      created_at: now,
+    }
+  }
+}
@@ -0,0 +1,35 @@
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+use wither::bson::{doc, oid::ObjectId};
+use wither::Model as WitherModel;
+
+use crate::utils::date::Date;
+use crate::utils::models::ModelExt;
+
+impl ModelExt for Usage {
+  type T = Usage;
+  const AUDITED: bool = false;
+}
+
+/// Requests of a user in a calendar month, metered by `utils::usage`.
+/// Created by the first request of the month.
+#[derive(Debug, Clone, Serialize, Deserialize, WitherModel, Validate)]
+#[model(
+  collection_name = "usage",
+  index(
+    keys = r#"doc!{ "user": 1, "period": 1 }"#,
+    options = r#"doc!{ "unique": true }"#
+  )
+)]
+pub struct Usage {
+  #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+  pub id: Option<ObjectId>,
+  pub user: ObjectId,
+  // Month of the usage in UTC, e.g. `2024-05`.
+  pub period: String,
+  pub requests: i64,
+  // Requests made to the paid upstream APIs on behalf of the user.
+  pub upstream_credits: i64,
+  pub updated_at: Date,
+  pub created_at: Date,
+}
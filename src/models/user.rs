@@ -2,6 +2,7 @@ use bson::serde_helpers::bson_datetime_as_rfc3339_string;
 use bson::serde_helpers::serialize_object_id_as_hex_string;
 use serde::{Deserialize, Serialize};
 use tokio::task;
+use utoipa::ToSchema;
 use validator::Validate;
 use wither::bson::{doc, oid::ObjectId};
 use wither::Model as WitherModel;
@@ -16,7 +17,13 @@ impl ModelExt for User {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, WitherModel, Validate)]
-#[model(index(keys = r#"doc!{ "email": 1 }"#, options = r#"doc!{ "unique": true }"#))]
+#[model(
+  index(keys = r#"doc!{ "email": 1 }"#, options = r#"doc!{ "unique": true }"#),
+  index(
+    keys = r#"doc!{ "wallet": 1 }"#,
+    options = r#"doc!{ "unique": true, "sparse": true }"#
+  )
+)]
 pub struct User {
   #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
   pub id: Option<ObjectId>,
@@ -28,6 +35,94 @@ pub struct User {
   pub updated_at: Date,
   pub created_at: Date,
   pub locked_at: Option<Date>,
+  #[serde(default)]
+  pub role: Role,
+  // Plan setting the monthly quotas of the user, see `utils::usage`.
+  #[serde(default)]
+  pub plan: Plan,
+  // Address of the users signing in with Ethereum, lowercased. Left out of
+  // the document when unset, so the sparse unique index ignores it.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub wallet: Option<String>,
+  #[serde(default)]
+  pub email_preferences: EmailPreferences,
+  // When the last weekly digest was emailed.
+  #[serde(default)]
+  pub digest_sent_at: Option<Date>,
+  // Signing in requires a code of the confirmed `TotpFactor` of the user.
+  #[serde(default)]
+  pub mfa_enabled: bool,
+  // Set once the user opens the link of the verification email, see
+  // `services::account`.
+  #[serde(default)]
+  pub email_verified_at: Option<Date>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+  Admin,
+  #[default]
+  Member,
+  // Can look data up but not change it.
+  ReadOnly,
+}
+
+impl Role {
+  /// Stored value of the role, for queries.
+  pub fn as_str(self) -> &'static str {
+    match self {
+      Role::Admin => "admin",
+      Role::Member => "member",
+      Role::ReadOnly => "readonly",
+    }
+  }
+
+  fn level(self) -> u8 {
+    match self {
+      Role::ReadOnly => 0,
+      Role::Member => 1,
+      Role::Admin => 2,
+    }
+  }
+
+  /// Whether the role grants the permissions of `required`. Each role has
+  /// the permissions of the roles below it.
+  pub fn grants(self, required: Role) -> bool {
+    self.level() >= required.level()
+  }
+}
+
+/// Plan of a user, each with its own monthly quotas.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Plan {
+  #[default]
+  Free,
+  Pro,
+  Enterprise,
+}
+
+impl Plan {
+  /// Stored value of the plan, for queries.
+  pub fn as_str(self) -> &'static str {
+    match self {
+      Plan::Free => "free",
+      Plan::Pro => "pro",
+      Plan::Enterprise => "enterprise",
+    }
+  }
+}
+
+/// Emails the user opted into. Users created before the preferences existed
+/// receive none.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ToSchema)]
+pub struct EmailPreferences {
+  // Email every alert as it is raised.
+  #[serde(default)]
+  pub alerts: bool,
+  #[serde(default)]
+  pub weekly_digest: bool,
 }
 
 impl User {
@@ -46,23 +141,67 @@ impl User {
       updated_at: now,
       created_at: now,
       locked_at: None,
+      role: Role::Member,
+      plan: Plan::Free,
+      wallet: None,
+      email_preferences: EmailPreferences::default(),
+      digest_sent_at: None,
+      mfa_enabled: false,
+      email_verified_at: None,
     }
   }
 
+  /// Creates a user signing in with Ethereum. Wallet users have no password
+  /// and an address under the reserved `.invalid` domain as placeholder
+  /// email, so they can never authenticate with an email and password.
+  pub fn new_wallet(address: &str) -> Self {
+    let mut user = Self::new(address, format!("{}@{}", address, WALLET_EMAIL_DOMAIN), "");
+    user.wallet = Some(address.to_owned());
+    user
+  }
+
   pub fn is_password_match(&self, password: &str) -> bool {
     bcrypt::verify(password, self.password.as_ref()).unwrap_or(false)
   }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+// Domain of the placeholder emails of wallet users, reserved by RFC 2606.
+pub const WALLET_EMAIL_DOMAIN: &str = "wallet.invalid";
+
+/// Whether the email is under the domain of wallet users, which nobody else
+/// can take: the placeholder email of a wallet signing in later would be
+/// taken already.
+pub fn is_wallet_email(email: &str) -> bool {
+  match email.trim().rsplit_once('@') {
+    Some((_, domain)) => domain.eq_ignore_ascii_case(WALLET_EMAIL_DOMAIN),
+    None => false,
+  }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[schema(as = User)]
 pub struct PublicUser {
   #[serde(alias = "_id", serialize_with = "serialize_object_id_as_hex_string")]
+  #[schema(value_type = String)]
   pub id: ObjectId,
   pub name: String,
   pub email: String,
+  pub role: Role,
+  #[serde(default)]
+  pub plan: Plan,
+  #[serde(default)]
+  pub wallet: Option<String>,
+  #[serde(default)]
+  pub locked_at: Option<String>,
+  #[serde(default)]
+  pub mfa_enabled: bool,
+  #[serde(default)]
+  pub email_verified: bool,
   #[serde(with = "bson_datetime_as_rfc3339_string")]
+  #[schema(value_type = String, format = DateTime)]
   pub updated_at: Date,
   #[serde(with = "bson_datetime_as_rfc3339_string")]
+  #[schema(value_type = String, format = DateTime)]
   pub created_at: Date,
 }
 
@@ -72,6 +211,14 @@ impl From<User> for PublicUser {
       id: user.id.unwrap(),
       name: user.name.clone(),
       email: user.email.clone(),
+      role: user.role,
+      plan: user.plan,
+      wallet: user.wallet.clone(),
+      locked_at: user
+        .locked_at
+        .map(|date| date.try_to_rfc3339_string().unwrap()),
+      mfa_enabled: user.mfa_enabled,
+      email_verified: user.email_verified_at.is_some(),
       updated_at: user.updated_at,
       created_at: user.created_at,
     }
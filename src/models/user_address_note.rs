@@ -0,0 +1,78 @@
+use bson::serde_helpers::bson_datetime_as_rfc3339_string;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+use wither::bson::{doc, oid::ObjectId};
+use wither::Model as WitherModel;
+
+use crate::utils::date;
+use crate::utils::date::Date;
+use crate::utils::models::ModelExt;
+use crate::utils::serde_helpers::serialize_checksum_address;
+
+impl ModelExt for UserAddressNote {
+  type T = UserAddressNote;
+}
+
+/// Private note and custom name a user attached to an address, only visible
+/// to them. A user has at most one note per address.
+#[derive(Debug, Clone, Serialize, Deserialize, WitherModel, Validate)]
+#[model(index(
+  keys = r#"doc!{ "user": 1, "address": 1 }"#,
+  options = r#"doc!{ "unique": true }"#
+))]
+pub struct UserAddressNote {
+  #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+  pub id: Option<ObjectId>,
+  pub user: ObjectId,
+  // Always stored lowercased.
+  pub address: String,
+  pub name: Option<String>,
+  pub note: Option<String>,
+  pub updated_at: Date,
+  pub created_at: Date,
+}
+
+impl UserAddressNote {
+  pub fn new(user: ObjectId, address: String) -> Self {
+    let now = date::now();
+    Self {
+      id: None,
+      user,
+      address,
+      name: None,
+      note: None,
+      updated_at: now,
+      created_at: now,
+    }
+  }
+}
+
+/// Note of the authenticated user, supplied by them rather than by Arkham.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[schema(as = UserAddressNote)]
+pub struct PublicUserAddressNote {
+  #[serde(serialize_with = "serialize_checksum_address")]
+  pub address: String,
+  // Custom name of the address.
+  pub name: Option<String>,
+  pub note: Option<String>,
+  #[serde(with = "bson_datetime_as_rfc3339_string")]
+  #[schema(value_type = String, format = DateTime)]
+  pub updated_at: Date,
+  #[serde(with = "bson_datetime_as_rfc3339_string")]
+  #[schema(value_type = String, format = DateTime)]
+  pub created_at: Date,
+}
+
+impl From<UserAddressNote> for PublicUserAddressNote {
+  fn from(note: UserAddressNote) -> Self {
+    Self {
+      address: note.address,
+      name: note.name,
+      note: note.note,
+      updated_at: note.updated_at,
+      created_at: note.created_at,
+    }
+  }
+}
@@ -0,0 +1,93 @@
+use bson::serde_helpers::bson_datetime_as_rfc3339_string;
+use bson::serde_helpers::serialize_object_id_as_hex_string;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+use wither::bson::{doc, oid::ObjectId};
+use wither::Model as WitherModel;
+
+use crate::utils::date;
+use crate::utils::date::Date;
+use crate::utils::models::ModelExt;
+use crate::utils::serde_helpers::serialize_checksum_address;
+
+impl ModelExt for WatchedAddress {
+  type T = WatchedAddress;
+}
+
+/// Address added to a `Watchlist`. The owner of the watchlist is copied here
+/// so addresses can be scoped to a user without looking up the watchlist.
+#[derive(Debug, Clone, Serialize, Deserialize, WitherModel, Validate)]
+#[model(index(
+  keys = r#"doc!{ "watchlist": 1, "chain": 1, "address": 1 }"#,
+  options = r#"doc!{ "unique": true }"#
+))]
+pub struct WatchedAddress {
+  #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+  pub id: Option<ObjectId>,
+  pub watchlist: ObjectId,
+  pub user: ObjectId,
+  // Always stored lowercased, see `normalize_evm_address`.
+  pub address: String,
+  pub chain: String,
+  pub nickname: Option<String>,
+  pub updated_at: Date,
+  pub created_at: Date,
+}
+
+impl WatchedAddress {
+  pub fn new(
+    watchlist: ObjectId,
+    user: ObjectId,
+    address: String,
+    chain: String,
+    nickname: Option<String>,
+  ) -> Self {
+    let now = date::now();
+    Self {
+      id: None,
+      watchlist,
+      user,
+      address,
+      chain,
+      nickname,
+      updated_at: now,
+      created_at: now,
+    }
+  }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[schema(as = WatchedAddress)]
+pub struct PublicWatchedAddress {
+  #[serde(alias = "_id", serialize_with = "serialize_object_id_as_hex_string")]
+  #[schema(value_type = String)]
+  pub id: ObjectId,
+  #[serde(serialize_with = "serialize_object_id_as_hex_string")]
+  #[schema(value_type = String)]
+  pub watchlist: ObjectId,
+  #[serde(serialize_with = "serialize_checksum_address")]
+  pub address: String,
+  pub chain: String,
+  pub nickname: Option<String>,
+  #[serde(with = "bson_datetime_as_rfc3339_string")]
+  #[schema(value_type = String, format = DateTime)]
+  pub updated_at: Date,
+  #[serde(with = "bson_datetime_as_rfc3339_string")]
+  #[schema(value_type = String, format = DateTime)]
+  pub created_at: Date,
+}
+
+impl From<WatchedAddress> for PublicWatchedAddress {
+  fn from(watched: WatchedAddress) -> Self {
+    Self {
+      id: watched.id.unwrap(),
+      watchlist: watched.watchlist,
+      address: watched.address,
+      chain: watched.chain,
+      nickname: watched.nickname,
+      updated_at: watched.updated_at,
+      created_at: watched.created_at,
+    }
+  }
+}
@@ -0,0 +1,105 @@
+use axum::http::Method;
+use bson::serde_helpers::bson_datetime_as_rfc3339_string;
+use bson::serde_helpers::serialize_object_id_as_hex_string;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+use wither::bson::{doc, oid::ObjectId};
+use wither::Model as WitherModel;
+
+use crate::utils::date;
+use crate::utils::date::Date;
+use crate::utils::links::{LinkBuilder, Links};
+use crate::utils::models::ModelExt;
+use crate::utils::serde_helpers::serialize_optional_object_id_as_hex_string;
+
+impl ModelExt for Watchlist {
+  type T = Watchlist;
+  const VERSIONED: bool = true;
+}
+
+/// Named group of addresses a user keeps an eye on, see `WatchedAddress`.
+#[derive(Debug, Clone, Serialize, Deserialize, WitherModel, Validate)]
+#[model(
+  index(keys = r#"doc!{ "user": 1, "created_at": 1 }"#),
+  index(keys = r#"doc!{ "organization": 1, "created_at": 1 }"#)
+)]
+pub struct Watchlist {
+  #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+  pub id: Option<ObjectId>,
+  pub user: ObjectId,
+  // Organization sharing the watchlist, see `Ownership`.
+  #[serde(default)]
+  pub organization: Option<ObjectId>,
+  pub name: String,
+  // Incremented on every update, used for optimistic concurrency control.
+  #[serde(default)]
+  pub version: i64,
+  pub updated_at: Date,
+  pub created_at: Date,
+}
+
+impl Watchlist {
+  pub fn new(user: ObjectId, name: String) -> Self {
+    let now = date::now();
+    Self {
+      id: None,
+      user,
+      organization: None,
+      name,
+      version: 1,
+      updated_at: now,
+      created_at: now,
+    }
+  }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[schema(as = Watchlist)]
+pub struct PublicWatchlist {
+  #[serde(alias = "_id", serialize_with = "serialize_object_id_as_hex_string")]
+  #[schema(value_type = String)]
+  pub id: ObjectId,
+  #[serde(serialize_with = "serialize_object_id_as_hex_string")]
+  #[schema(value_type = String)]
+  pub user: ObjectId,
+  #[serde(default, serialize_with = "serialize_optional_object_id_as_hex_string")]
+  #[schema(value_type = Option<String>)]
+  pub organization: Option<ObjectId>,
+  pub name: String,
+  pub version: i64,
+  #[serde(with = "bson_datetime_as_rfc3339_string")]
+  #[schema(value_type = String, format = DateTime)]
+  pub updated_at: Date,
+  #[serde(with = "bson_datetime_as_rfc3339_string")]
+  #[schema(value_type = String, format = DateTime)]
+  pub created_at: Date,
+  // Routes of the watchlist and its sub-resources, see `LinkBuilder`.
+  #[serde(default)]
+  #[schema(value_type = BTreeMap<String, Link>)]
+  pub links: Links,
+}
+
+impl From<Watchlist> for PublicWatchlist {
+  fn from(watchlist: Watchlist) -> Self {
+    let id = watchlist.id.unwrap();
+    let links = LinkBuilder::new(format!("/watchlists/{}", id))
+      .link("self", Method::GET)
+      .link("update", Method::PUT)
+      .sub("addresses", Method::POST, "/addresses")
+      .sub("history", Method::GET, "/history")
+      .sub("shares", Method::GET, "/shares")
+      .build();
+
+    Self {
+      id,
+      user: watchlist.user,
+      organization: watchlist.organization,
+      name: watchlist.name,
+      version: watchlist.version,
+      updated_at: watchlist.updated_at,
+      created_at: watchlist.created_at,
+      links,
+    }
+  }
+}
@@ -0,0 +1,84 @@
+use bson::serde_helpers::bson_datetime_as_rfc3339_string;
+use bson::serde_helpers::serialize_object_id_as_hex_string;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+use wither::bson::{doc, oid::ObjectId};
+use wither::Model as WitherModel;
+
+use crate::models::webhook_delivery::WebhookDelivery;
+use crate::utils::date;
+use crate::utils::date::Date;
+use crate::utils::models::ModelExt;
+
+impl ModelExt for WebhookDeadLetter {
+  type T = WebhookDeadLetter;
+  const AUDITED: bool = false;
+}
+
+/// Webhook delivery that permanently failed, kept for inspection.
+#[derive(Debug, Clone, Serialize, Deserialize, WitherModel, Validate)]
+#[model(
+  index(keys = r#"doc!{ "endpoint": 1, "created_at": -1 }"#),
+  // Backs the removal of the dead letters past their retention.
+  index(keys = r#"doc!{ "created_at": 1 }"#)
+)]
+pub struct WebhookDeadLetter {
+  #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+  pub id: Option<ObjectId>,
+  pub user: ObjectId,
+  pub endpoint: ObjectId,
+  pub event: ObjectId,
+  pub attempts: u32,
+  pub error: String,
+  // Creation date of the failed delivery.
+  pub queued_at: Date,
+  pub created_at: Date,
+}
+
+impl WebhookDeadLetter {
+  pub fn new(delivery: WebhookDelivery, error: String) -> Self {
+    Self {
+      id: None,
+      user: delivery.user,
+      endpoint: delivery.endpoint,
+      event: delivery.event,
+      attempts: delivery.attempts,
+      error,
+      queued_at: delivery.created_at,
+      created_at: date::now(),
+    }
+  }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[schema(as = WebhookDeadLetter)]
+pub struct PublicWebhookDeadLetter {
+  #[serde(alias = "_id", serialize_with = "serialize_object_id_as_hex_string")]
+  #[schema(value_type = String)]
+  pub id: ObjectId,
+  #[serde(serialize_with = "serialize_object_id_as_hex_string")]
+  #[schema(value_type = String)]
+  pub event: ObjectId,
+  pub attempts: u32,
+  pub error: String,
+  #[serde(with = "bson_datetime_as_rfc3339_string")]
+  #[schema(value_type = String, format = DateTime)]
+  pub queued_at: Date,
+  #[serde(with = "bson_datetime_as_rfc3339_string")]
+  #[schema(value_type = String, format = DateTime)]
+  pub created_at: Date,
+}
+
+impl From<WebhookDeadLetter> for PublicWebhookDeadLetter {
+  fn from(dead_letter: WebhookDeadLetter) -> Self {
+    Self {
+      id: dead_letter.id.unwrap(),
+      event: dead_letter.event,
+      attempts: dead_letter.attempts,
+      error: dead_letter.error,
+      queued_at: dead_letter.queued_at,
+      created_at: dead_letter.created_at,
+    }
+  }
+}
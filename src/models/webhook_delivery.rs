@@ -0,0 +1,118 @@
+use bson::serde_helpers::bson_datetime_as_rfc3339_string;
+use bson::serde_helpers::serialize_object_id_as_hex_string;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+use wither::bson::{doc, oid::ObjectId};
+use wither::Model as WitherModel;
+
+use crate::utils::date;
+use crate::utils::date::Date;
+use crate::utils::models::ModelExt;
+use crate::utils::query_fields::FieldType;
+
+impl ModelExt for WebhookDelivery {
+  type T = WebhookDelivery;
+  const AUDITED: bool = false;
+}
+
+/// Alert event queued for delivery to a webhook endpoint. Deliveries that
+/// keep failing are moved to the `WebhookDeadLetter` collection.
+#[derive(Debug, Clone, Serialize, Deserialize, WitherModel, Validate)]
+#[model(
+  index(keys = r#"doc!{ "status": 1, "next_attempt_at": 1 }"#),
+  index(keys = r#"doc!{ "endpoint": 1, "created_at": -1 }"#),
+  // Backs the removal of the deliveries past their retention.
+  index(keys = r#"doc!{ "status": 1, "created_at": 1 }"#)
+)]
+pub struct WebhookDelivery {
+  #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+  pub id: Option<ObjectId>,
+  pub user: ObjectId,
+  pub endpoint: ObjectId,
+  pub event: ObjectId,
+  pub status: DeliveryStatus,
+  pub attempts: u32,
+  pub next_attempt_at: Date,
+  pub last_error: Option<String>,
+  pub delivered_at: Option<Date>,
+  pub updated_at: Date,
+  pub created_at: Date,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum DeliveryStatus {
+  Pending,
+  Delivered,
+}
+
+impl WebhookDelivery {
+  pub fn new(user: ObjectId, endpoint: ObjectId, event: ObjectId) -> Self {
+    let now = date::now();
+    Self {
+      id: None,
+      user,
+      endpoint,
+      event,
+      status: DeliveryStatus::Pending,
+      attempts: 0,
+      next_attempt_at: now,
+      last_error: None,
+      delivered_at: None,
+      updated_at: now,
+      created_at: now,
+    }
+  }
+}
+
+/// Fields deliveries can be sorted by with `?sort=`.
+pub const WEBHOOK_DELIVERY_SORT_FIELDS: &[&str] = &["created_at"];
+
+/// Fields deliveries can be filtered on with `?filter[field]=`, see
+/// `Filters`.
+pub const WEBHOOK_DELIVERY_FILTER_FIELDS: &[(&str, FieldType)] = &[
+  ("event", FieldType::ObjectId),
+  ("status", FieldType::String),
+  ("attempts", FieldType::Integer),
+  ("delivered_at", FieldType::Date),
+  ("created_at", FieldType::Date),
+];
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[schema(as = WebhookDelivery)]
+pub struct PublicWebhookDelivery {
+  #[serde(alias = "_id", serialize_with = "serialize_object_id_as_hex_string")]
+  #[schema(value_type = String)]
+  pub id: ObjectId,
+  #[serde(serialize_with = "serialize_object_id_as_hex_string")]
+  #[schema(value_type = String)]
+  pub event: ObjectId,
+  pub status: DeliveryStatus,
+  pub attempts: u32,
+  #[serde(with = "bson_datetime_as_rfc3339_string")]
+  #[schema(value_type = String, format = DateTime)]
+  pub next_attempt_at: Date,
+  pub last_error: Option<String>,
+  #[serde(with = "bson_datetime_as_rfc3339_string")]
+  #[schema(value_type = String, format = DateTime)]
+  pub updated_at: Date,
+  #[serde(with = "bson_datetime_as_rfc3339_string")]
+  #[schema(value_type = String, format = DateTime)]
+  pub created_at: Date,
+}
+
+impl From<WebhookDelivery> for PublicWebhookDelivery {
+  fn from(delivery: WebhookDelivery) -> Self {
+    Self {
+      id: delivery.id.unwrap(),
+      event: delivery.event,
+      status: delivery.status,
+      attempts: delivery.attempts,
+      next_attempt_at: delivery.next_attempt_at,
+      last_error: delivery.last_error,
+      updated_at: delivery.updated_at,
+      created_at: delivery.created_at,
+    }
+  }
+}
@@ -0,0 +1,97 @@
+use bson::serde_helpers::bson_datetime_as_rfc3339_string;
+use bson::serde_helpers::serialize_object_id_as_hex_string;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+use wither::bson::{doc, oid::ObjectId};
+use wither::Model as WitherModel;
+
+use crate::models::alert_rule::AlertCondition;
+use crate::utils::date;
+use crate::utils::date::Date;
+use crate::utils::models::ModelExt;
+use crate::utils::secret;
+
+// Length of the generated signing secrets.
+const SECRET_LENGTH: usize = 32;
+
+impl ModelExt for WebhookEndpoint {
+  type T = WebhookEndpoint;
+}
+
+/// URL the alert events of a user are posted to.
+#[derive(Debug, Clone, Serialize, Deserialize, WitherModel, Validate)]
+#[model(index(keys = r#"doc!{ "user": 1, "created_at": 1 }"#))]
+pub struct WebhookEndpoint {
+  #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+  pub id: Option<ObjectId>,
+  pub user: ObjectId,
+  #[validate(url)]
+  pub url: String,
+  // Conditions of the alert events posted to the endpoint, every event when
+  // empty.
+  #[serde(default)]
+  pub events: Vec<AlertCondition>,
+  // Key of the HMAC signature of the payloads. Endpoints created before
+  // signatures existed have none until it is rotated, their payloads are not
+  // signed.
+  #[serde(default)]
+  pub secret: Option<String>,
+  pub updated_at: Date,
+  pub created_at: Date,
+}
+
+impl WebhookEndpoint {
+  pub fn new(user: ObjectId, url: String, events: Vec<AlertCondition>) -> Self {
+    let now = date::now();
+    Self {
+      id: None,
+      user,
+      url,
+      events,
+      secret: Some(Self::generate_secret()),
+      updated_at: now,
+      created_at: now,
+    }
+  }
+
+  /// Random key of the payload signatures.
+  pub fn generate_secret() -> String {
+    secret::generate(SECRET_LENGTH)
+  }
+
+  /// Whether alert events with the given condition are posted to the
+  /// endpoint.
+  pub fn accepts(&self, condition: AlertCondition) -> bool {
+    self.events.is_empty() || self.events.contains(&condition)
+  }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[schema(as = WebhookEndpoint)]
+pub struct PublicWebhookEndpoint {
+  #[serde(alias = "_id", serialize_with = "serialize_object_id_as_hex_string")]
+  #[schema(value_type = String)]
+  pub id: ObjectId,
+  pub url: String,
+  #[serde(default)]
+  pub events: Vec<AlertCondition>,
+  #[serde(with = "bson_datetime_as_rfc3339_string")]
+  #[schema(value_type = String, format = DateTime)]
+  pub updated_at: Date,
+  #[serde(with = "bson_datetime_as_rfc3339_string")]
+  #[schema(value_type = String, format = DateTime)]
+  pub created_at: Date,
+}
+
+impl From<WebhookEndpoint> for PublicWebhookEndpoint {
+  fn from(endpoint: WebhookEndpoint) -> Self {
+    Self {
+      id: endpoint.id.unwrap(),
+      url: endpoint.url,
+      events: endpoint.events,
+      updated_at: endpoint.updated_at,
+      created_at: endpoint.created_at,
+    }
+  }
+}
@@ -0,0 +1,119 @@
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use tracing::{debug, error};
+
+use crate::errors::Error;
+use crate::models::alert_event::AlertEvent;
+use crate::notifications::{alert_url, Notifier};
+use crate::settings;
+use crate::utils::telemetry;
+
+// Color of the embed sidebar, as a RGB integer.
+const EMBED_COLOR: u32 = 0xF5A623;
+
+/// Sends alerts as rich embeds to Discord webhooks. Targets are the webhook
+/// URLs users create in their Discord server settings.
+pub struct DiscordNotifier {
+  http_client: reqwest::Client,
+  webhook_hosts: Vec<String>,
+}
+
+impl DiscordNotifier {
+  pub fn new(http_client: reqwest::Client, settings: &settings::Discord) -> Self {
+    Self {
+      http_client,
+      webhook_hosts: settings.webhook_hosts.clone(),
+    }
+  }
+}
+
+#[async_trait]
+impl Notifier for DiscordNotifier {
+  fn validate_target(&self, target: &str) -> Result<(), Error> {
+    // Only Discord hosts are accepted, so the server can't be used to post
+    // to arbitrary URLs.
+    let is_webhook = reqwest::Url::parse(target)
+      .map(|url| {
+        matches!(url.scheme(), "http" | "https")
+          && url
+            .host_str()
+            .is_some_and(|host| self.webhook_hosts.iter().any(|allowed| allowed == host))
+          && url.path().starts_with("/api/webhooks/")
+      })
+      .unwrap_or(false);
+
+    if !is_webhook {
+      debug!("Invalid Discord webhook URL, returning 400 status code");
+      return Err(Error::bad_request());
+    }
+
+    Ok(())
+  }
+
+  async fn notify(&self, target: &str, event: &AlertEvent) -> Result<(), Error> {
+    let res = self
+      .http_client
+      .post(target)
+      .headers(telemetry::trace_headers())
+      .json(&json!({ "embeds": [embed(event)] }))
+      .send()
+      .await
+      .map_err(|err| Error::upstream_request("Discord", err))?;
+
+    let status = res.status();
+    if !status.is_success() {
+      let body = res.text().await.unwrap_or_default();
+      error!("Discord returned a {} error: {}", status, body);
+      return Err(Error::upstream_unavailable(
+        "Discord",
+        Some(status.as_u16()),
+      ));
+    }
+
+    Ok(())
+  }
+}
+
+/// Formats an alert as a Discord embed.
+pub fn embed(event: &AlertEvent) -> Value {
+  let mut fields = Vec::new();
+  if let Some(address) = &event.address {
+    fields.push(json!({
+      "name": "Address",
+      "value": format!("`{}`", address),
+      "inline": false
+    }));
+  }
+  if let Some(entity) = &event.entity {
+    fields.push(json!({ "name": "Entity", "value": entity, "inline": true }));
+  }
+  if let Some(chain) = &event.chain {
+    fields.push(json!({ "name": "Chain", "value": chain, "inline": true }));
+  }
+  if let Some(token) = &event.token {
+    fields.push(json!({ "name": "Token", "value": token, "inline": true }));
+  }
+  if let Some(counterparty) = &event.counterparty {
+    fields.push(json!({ "name": "Counterparty", "value": counterparty, "inline": true }));
+  }
+  if let Some(hash) = &event.transaction_hash {
+    fields.push(json!({
+      "name": "Transaction",
+      "value": format!("`{}`", hash),
+      "inline": false
+    }));
+  }
+
+  let mut embed = json!({
+    "title": "Degen alert",
+    "description": event.message,
+    "color": EMBED_COLOR,
+    "fields": fields,
+    "timestamp": event.created_at.try_to_rfc3339_string().ok()
+  });
+  if let Some(url) = alert_url(event) {
+    embed["url"] = Value::String(url);
+  }
+
+  embed
+}
@@ -0,0 +1,289 @@
+use async_trait::async_trait;
+use lettre::message::{Mailbox, MultiPart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use std::fmt::Display;
+use std::sync::Arc;
+
+use crate::errors::Error;
+use crate::models::alert_event::AlertEvent;
+use crate::models::user::User;
+use crate::notifications::alert_url;
+use crate::settings;
+
+/// Sends emails. Implemented by every lettre transport, so tests can use a
+/// stub transport instead of an SMTP server.
+#[async_trait]
+pub trait Mailer: Send + Sync {
+  async fn send_email(&self, message: Message) -> Result<(), Error>;
+}
+
+#[async_trait]
+impl<T> Mailer for T
+where
+  T: AsyncTransport + Send + Sync,
+  T::Error: Display,
+{
+  async fn send_email(&self, message: Message) -> Result<(), Error> {
+    self
+      .send(message)
+      .await
+      .map(|_| ())
+      .map_err(|err| Error::General(format!("Failed to send email: {}", err)))
+  }
+}
+
+/// Emails alerts and weekly digests to the users who opted into them, see
+/// `EmailPreferences`.
+pub struct EmailNotifier {
+  mailer: Arc<dyn Mailer>,
+  from: Mailbox,
+}
+
+impl EmailNotifier {
+  pub fn new<M: Mailer + 'static>(mailer: M, from: Mailbox) -> Self {
+    Self {
+      mailer: Arc::new(mailer),
+      from,
+    }
+  }
+
+  /// Creates a notifier sending through the configured SMTP server. The
+  /// connection is only opened when the first email is sent.
+  pub fn smtp(settings: &settings::Smtp) -> Result<Self, Error> {
+    let builder = if settings.starttls {
+      AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&settings.host)
+        .map_err(|err| Error::General(format!("Invalid SMTP host: {}", err)))?
+    } else {
+      AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&settings.host)
+    };
+
+    let mut builder = builder.port(settings.port);
+    if !settings.username.is_empty() {
+      builder = builder.credentials(Credentials::new(
+        settings.username.clone(),
+        settings.password.clone(),
+      ));
+    }
+
+    let from = settings
+      .from
+      .parse::<Mailbox>()
+      .map_err(|err| Error::General(format!("Invalid SMTP sender: {}", err)))?;
+
+    Ok(Self::new(builder.build(), from))
+  }
+
+  /// Emails alert events to their user, in a single email.
+  pub async fn send_alerts(&self, user: &User, events: &[AlertEvent]) -> Result<(), Error> {
+    self.send(user, alert_email(events)).await
+  }
+
+  /// Emails the alerts of the past week to a user. `total` is the number of
+  /// alerts raised, which can be more than the listed events.
+  pub async fn send_digest(
+    &self,
+    user: &User,
+    events: &[AlertEvent],
+    total: u64,
+  ) -> Result<(), Error> {
+    self.send(user, digest_email(events, total)).await
+  }
+
+  /// Emails the link resetting the password of a user.
+  pub async fn send_password_reset(&self, user: &User, link: &str) -> Result<(), Error> {
+    self.send(user, password_reset_email(link)).await
+  }
+
+  /// Emails the link verifying the email of a user.
+  pub async fn send_email_verification(&self, user: &User, link: &str) -> Result<(), Error> {
+    self.send(user, email_verification_email(link)).await
+  }
+
+  pub async fn send_email_change(&self, user: &User, email: &str, link: &str) -> Result<(), Error> {
+    self.send(user, email_change_email(email, link)).await
+  }
+
+  async fn send(&self, user: &User, email: Email) -> Result<(), Error> {
+    let address = user
+      .email
+      .parse()
+      .map_err(|_| Error::General(format!("Invalid email address {}", user.email)))?;
+    let to = Mailbox::new(Some(user.name.clone()), address);
+
+    let message = Message::builder()
+      .from(self.from.clone())
+      .to(to)
+      .subject(email.subject)
+      .multipart(MultiPart::alternative_plain_html(email.text, email.html))
+      .map_err(|err| Error::General(format!("Failed to build email: {}", err)))?;
+
+    self.mailer.send_email(message).await
+  }
+}
+
+/// Rendered email, with plain text and HTML bodies.
+#[derive(Debug)]
+pub struct Email {
+  pub subject: String,
+  pub text: String,
+  pub html: String,
+}
+
+pub fn alert_email(events: &[AlertEvent]) -> Email {
+  let subject = match events {
+    [event] => format!("Degen alert: {}", event.message),
+    _ => format!("{} Degen alerts", events.len()),
+  };
+
+  Email {
+    text: render_text("Degen alerts", events, None),
+    html: render_html("Degen alerts", events, None),
+    subject,
+  }
+}
+
+pub fn digest_email(events: &[AlertEvent], total: u64) -> Email {
+  let title = "Your weekly Degen digest";
+  // Listed events are capped, tell the user how many were left out.
+  let remaining = total.saturating_sub(events.len() as u64);
+  let footer = (remaining > 0).then(|| format!("And {} more alerts.", remaining));
+
+  Email {
+    subject: format!("{}: {} alerts", title, total),
+    text: render_text(title, events, footer.as_deref()),
+    html: render_html(title, events, footer.as_deref()),
+  }
+}
+
+pub fn password_reset_email(link: &str) -> Email {
+  render_link(
+    "Reset your Degen password",
+    "Someone asked to reset the password of your Degen account. Open this link to choose a new one, or ignore this email if it wasn't you.",
+    link,
+  )
+}
+
+pub fn email_verification_email(link: &str) -> Email {
+  render_link(
+    "Verify your Degen email",
+    "Welcome to Degen! Open this link to verify your email.",
+    link,
+  )
+}
+
+pub fn email_change_email(email: &str, link: &str) -> Email {
+  let message = format!(
+    "The email of your Degen account was changed to {}. If it wasn't you, open this link to choose a new password and sign out everywhere.",
+    email
+  );
+  render_link("Your Degen email was changed", &message, link)
+}
+
+/// Email with a single link, the account emails.
+fn render_link(title: &str, message: &str, link: &str) -> Email {
+  Email {
+    subject: title.to_owned(),
+    text: format!("{}\n\n{}\n{}\n", title, message, link),
+    html: format!(
+      "<!DOCTYPE html>\n<html>\n<body style=\"font-family: sans-serif\">\n<h2>{}</h2>\n<p>{}</p>\n<p><a href=\"{}\">{}</a></p>\n</body>\n</html>\n",
+      escape_html(title),
+      escape_html(message),
+      escape_html(link),
+      escape_html(link)
+    ),
+  }
+}
+
+fn render_text(title: &str, events: &[AlertEvent], footer: Option<&str>) -> String {
+  let mut text = format!("{}\n", title);
+  for event in events {
+    text.push_str(&format!("\n{}\n", event.message));
+    if let Some(address) = &event.address {
+      text.push_str(&format!("Address: {}\n", address));
+    }
+    if let Some(chain) = &event.chain {
+      text.push_str(&format!("Chain: {}\n", chain));
+    }
+    if let Some(token) = &event.token {
+      text.push_str(&format!("Token: {}\n", token));
+    }
+    if let Some(entity) = &event.entity {
+      text.push_str(&format!("Entity: {}\n", entity));
+    }
+    if let Some(counterparty) = &event.counterparty {
+      text.push_str(&format!("Counterparty: {}\n", counterparty));
+    }
+    if let Some(hash) = &event.transaction_hash {
+      text.push_str(&format!("Transaction: {}\n", hash));
+    }
+    if let Some(url) = alert_url(event) {
+      text.push_str(&format!("{}\n", url));
+    }
+  }
+  if let Some(footer) = footer {
+    text.push_str(&format!("\n{}\n", footer));
+  }
+
+  text
+}
+
+fn render_html(title: &str, events: &[AlertEvent], footer: Option<&str>) -> String {
+  let mut html = format!(
+    "<!DOCTYPE html>\n<html>\n<body style=\"font-family: sans-serif\">\n<h2>{}</h2>\n",
+    escape_html(title)
+  );
+  for event in events {
+    html.push_str(&format!(
+      "<p>\n<strong>{}</strong>",
+      escape_html(&event.message)
+    ));
+    if let Some(address) = &event.address {
+      let address = match alert_url(event) {
+        Some(url) => format!(
+          "<a href=\"{}\"><code>{}</code></a>",
+          escape_html(&url),
+          escape_html(address)
+        ),
+        None => format!("<code>{}</code>", escape_html(address)),
+      };
+      html.push_str(&format!("<br>\nAddress: {}", address));
+    }
+    if let Some(chain) = &event.chain {
+      html.push_str(&format!("<br>\nChain: {}", escape_html(chain)));
+    }
+    if let Some(token) = &event.token {
+      html.push_str(&format!("<br>\nToken: {}", escape_html(token)));
+    }
+    if let Some(entity) = &event.entity {
+      html.push_str(&format!("<br>\nEntity: {}", escape_html(entity)));
+    }
+    if let Some(counterparty) = &event.counterparty {
+      html.push_str(&format!(
+        "<br>\nCounterparty: {}",
+        escape_html(counterparty)
+      ));
+    }
+    if let Some(hash) = &event.transaction_hash {
+      html.push_str(&format!(
+        "<br>\nTransaction: <code>{}</code>",
+        escape_html(hash)
+      ));
+    }
+    html.push_str("\n</p>\n");
+  }
+  if let Some(footer) = footer {
+    html.push_str(&format!("<p>{}</p>\n", escape_html(footer)));
+  }
+  html.push_str("</body>\n</html>\n");
+
+  html
+}
+
+fn escape_html(text: &str) -> String {
+  text
+    .replace('&', "&amp;")
+    .replace('<', "&lt;")
+    .replace('>', "&gt;")
+    .replace('"', "&quot;")
+}
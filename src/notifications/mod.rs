@@ -0,0 +1,171 @@
+pub mod discord;
+pub mod email;
+pub mod telegram;
+
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::{debug, error, warn};
+use wither::bson::{doc, oid::ObjectId};
+
+use crate::errors::Error;
+use crate::models::alert_event::AlertEvent;
+use crate::models::notification_channel::{ChannelKind, NotificationChannel};
+use crate::models::user::User;
+use crate::settings::Settings;
+use crate::utils::models::ModelExt;
+
+/// Pushes alert events to an external service. Each `ChannelKind` has one
+/// notifier, users register the targets it sends to.
+#[async_trait]
+pub trait Notifier: Send + Sync {
+  /// Checks a target before a channel is registered with it.
+  fn validate_target(&self, target: &str) -> Result<(), Error>;
+
+  async fn notify(&self, target: &str, event: &AlertEvent) -> Result<(), Error>;
+}
+
+/// Notifiers of the channel kinds configured on this server.
+#[derive(Default)]
+pub struct Notifiers {
+  notifiers: HashMap<ChannelKind, Arc<dyn Notifier>>,
+  // Emails go to the address of the user instead of a registered channel.
+  email: Option<Arc<email::EmailNotifier>>,
+}
+
+impl Notifiers {
+  pub fn new(http_client: reqwest::Client, settings: &Settings) -> Self {
+    let mut notifiers = Self::default();
+
+    // Telegram needs a bot, servers without one can't push to it.
+    if !settings.telegram.bot_token.is_empty() {
+      notifiers = notifiers.with(
+        ChannelKind::Telegram,
+        telegram::TelegramNotifier::new(http_client.clone(), &settings.telegram),
+      );
+    }
+
+    if !settings.smtp.host.is_empty() {
+      match email::EmailNotifier::smtp(&settings.smtp) {
+        Ok(notifier) => notifiers = notifiers.with_email(notifier),
+        Err(err) => error!("Failed to set up the email notifier: {}", err),
+      }
+    }
+
+    notifiers.with(
+      ChannelKind::Discord,
+      discord::DiscordNotifier::new(http_client, &settings.discord),
+    )
+  }
+
+  pub fn with<N: Notifier + 'static>(mut self, kind: ChannelKind, notifier: N) -> Self {
+    self.notifiers.insert(kind, Arc::new(notifier));
+    self
+  }
+
+  pub fn with_email(mut self, notifier: email::EmailNotifier) -> Self {
+    self.email = Some(Arc::new(notifier));
+    self
+  }
+
+  pub fn get(&self, kind: ChannelKind) -> Option<&Arc<dyn Notifier>> {
+    self.notifiers.get(&kind)
+  }
+
+  pub fn email(&self) -> Option<&Arc<email::EmailNotifier>> {
+    self.email.as_ref()
+  }
+
+  /// Pushes the events to the channels of their users, and emails them to
+  /// the users who opted into alert emails. Failures are logged
+  /// and don't stop the other notifications, alerts stay available through
+  /// the alerts route and webhooks.
+  pub async fn dispatch(&self, events: &[AlertEvent]) -> Result<(), Error> {
+    if events.is_empty() {
+      return Ok(());
+    }
+
+    let users = events
+      .iter()
+      .map(|event| event.user)
+      .collect::<Vec<ObjectId>>();
+    let channels = NotificationChannel::find(
+      doc! {
+        "user": { "$in": users.clone() },
+        // Channels created before the toggle existed have no `enabled` field.
+        "enabled": { "$ne": false }
+      },
+      None,
+    )
+    .await?;
+
+    for channel in channels {
+      let notifier = match self.get(channel.kind) {
+        Some(notifier) => notifier,
+        None => {
+          debug!(
+            "No {:?} notifier configured, skipping channel",
+            channel.kind
+          );
+          continue;
+        }
+      };
+
+      for event in events.iter().filter(|event| event.user == channel.user) {
+        if let Err(err) = notifier.notify(&channel.target, event).await {
+          warn!(
+            "Failed to send {:?} notification to channel {}: {}",
+            channel.kind,
+            channel.id.unwrap(),
+            err
+          );
+        }
+      }
+    }
+
+    if let Some(email) = &self.email {
+      let recipients = User::find(
+        doc! { "_id": { "$in": users }, "email_preferences.alerts": true },
+        None,
+      )
+      .await?;
+
+      for user in recipients {
+        let user_events = events
+          .iter()
+          .filter(|event| Some(event.user) == user.id)
+          .cloned()
+          .collect::<Vec<AlertEvent>>();
+        if let Err(err) = email.send_alerts(&user, &user_events).await {
+          warn!(
+            "Failed to email alerts to user {}: {}",
+            user.id.unwrap(),
+            err
+          );
+        }
+      }
+    }
+
+    Ok(())
+  }
+}
+
+/// Link to the address of an alert on the block explorer of its chain.
+pub fn alert_url(event: &AlertEvent) -> Option<String> {
+  explorer_url(event.chain.as_deref()?, event.address.as_deref()?)
+}
+
+/// Link to the address on the block explorer of the chain.
+pub fn explorer_url(chain: &str, address: &str) -> Option<String> {
+  let explorer = match chain {
+    "ethereum" => "https://etherscan.io",
+    "bsc" => "https://bscscan.com",
+    "polygon" => "https://polygonscan.com",
+    "arbitrum_one" => "https://arbiscan.io",
+    "avalanche" => "https://snowtrace.io",
+    "optimism" => "https://optimistic.etherscan.io",
+    _ => return None,
+  };
+
+  Some(format!("{}/address/{}", explorer, address))
+}
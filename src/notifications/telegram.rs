@@ -0,0 +1,107 @@
+use async_trait::async_trait;
+use serde_json::json;
+use tracing::{debug, error};
+
+use crate::errors::Error;
+use crate::models::alert_event::AlertEvent;
+use crate::notifications::Notifier;
+use crate::settings;
+use crate::utils::telemetry;
+
+/// Sends alerts as messages of a Telegram bot. Targets are chat ids, or
+/// `@username` for public channels.
+pub struct TelegramNotifier {
+  http_client: reqwest::Client,
+  api_url: String,
+  bot_token: String,
+}
+
+impl TelegramNotifier {
+  pub fn new(http_client: reqwest::Client, settings: &settings::Telegram) -> Self {
+    Self {
+      http_client,
+      api_url: settings.api_url.clone(),
+      bot_token: settings.bot_token.clone(),
+    }
+  }
+}
+
+#[async_trait]
+impl Notifier for TelegramNotifier {
+  fn validate_target(&self, target: &str) -> Result<(), Error> {
+    let is_chat_id = target.parse::<i64>().is_ok();
+    let is_username = target
+      .strip_prefix('@')
+      .map(|name| !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'))
+      .unwrap_or(false);
+
+    if !is_chat_id && !is_username {
+      debug!("Invalid Telegram chat id, returning 400 status code");
+      return Err(Error::bad_request());
+    }
+
+    Ok(())
+  }
+
+  async fn notify(&self, target: &str, event: &AlertEvent) -> Result<(), Error> {
+    let url = format!("{}/bot{}/sendMessage", self.api_url, self.bot_token);
+    let res = self
+      .http_client
+      .post(url)
+      .headers(telemetry::trace_headers())
+      .json(&json!({
+        "chat_id": target,
+        "text": format_message(event),
+        "parse_mode": "HTML",
+        "disable_web_page_preview": true
+      }))
+      .send()
+      .await
+      .map_err(|err| Error::upstream_request("Telegram", err))?;
+
+    let status = res.status();
+    if !status.is_success() {
+      let body = res.text().await.unwrap_or_default();
+      error!("Telegram returned a {} error: {}", status, body);
+      return Err(Error::upstream_unavailable(
+        "Telegram",
+        Some(status.as_u16()),
+      ));
+    }
+
+    Ok(())
+  }
+}
+
+/// Formats an alert as a Telegram HTML message.
+pub fn format_message(event: &AlertEvent) -> String {
+  let mut message = format!("<b>Degen alert</b>\n{}\n", escape_html(&event.message));
+  if let Some(address) = &event.address {
+    message.push_str(&format!("\nAddress: <code>{}</code>", escape_html(address)));
+  }
+  if let Some(chain) = &event.chain {
+    message.push_str(&format!("\nChain: {}", escape_html(chain)));
+  }
+  if let Some(token) = &event.token {
+    message.push_str(&format!("\nToken: {}", escape_html(token)));
+  }
+  if let Some(counterparty) = &event.counterparty {
+    message.push_str(&format!("\nCounterparty: {}", escape_html(counterparty)));
+  }
+  if let Some(hash) = &event.transaction_hash {
+    message.push_str(&format!(
+      "\nTransaction: <code>{}</code>",
+      escape_html(hash)
+    ));
+  }
+
+  message
+}
+
+// Telegram only requires these characters to be escaped in HTML messages.
+fn escape_html(text: &str) -> String {
+  text
+    .replace('&', "&amp;")
+    .replace('<', "&lt;")
+    .replace('>', "&gt;")
+}
@@ -0,0 +1,117 @@
+use bson::doc;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+use utoipa::{IntoParams, OpenApi, ToSchema};
+
+use crate::errors::{Error, ErrorResponse};
+use crate::models::watched_address::WatchedAddress;
+use crate::models::watchlist::Watchlist;
+use crate::services::history::{self, FieldChange};
+use crate::services::ownership::Ownership;
+use crate::state::AppState;
+use crate::utils::address::EvmAddress;
+use crate::utils::date::Date;
+use crate::utils::json::Json;
+use crate::utils::models::ModelExt;
+use crate::utils::query::Query;
+use crate::utils::route_table::RouteTable;
+use crate::utils::serde_helpers::{deserialize_rfc3339, serialize_checksum_address};
+use crate::utils::token::TokenUser;
+
+#[derive(OpenApi)]
+#[openapi(
+  paths(query_address_diff),
+  components(schemas(AddressDiff, FieldChange))
+)]
+pub struct ApiDoc;
+
+pub fn create_route() -> RouteTable<AppState> {
+  RouteTable::new().get("/addresses/:address/diff", query_address_diff)
+}
+
+/// What changed in the Arkham data of an address between two dates, e.g.
+/// its balances, entity or labels. Each date is matched with the latest
+/// snapshot recorded at or before it, so only addresses in a watchlist of
+/// the user have a history.
+#[utoipa::path(
+  get,
+  path = "/v1/addresses/{address}/diff",
+  params(("address" = String, Path, description = "EVM address"), DiffQuery),
+  responses(
+    (status = 200, description = "Changed fields of the address", body = AddressDiff),
+    (status = 400, description = "Invalid address or dates", body = ErrorResponse),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 404, description = "Address not watched, or no snapshot before `to`", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []))
+)]
+async fn query_address_diff(
+  user: TokenUser,
+  EvmAddress(address): EvmAddress,
+  Query(query): Query<DiffQuery>,
+) -> Result<Json<AddressDiff>, Error> {
+  if query.from > query.to {
+    debug!("Diff starts after it ends, returning 400 status code");
+    return Err(Error::bad_request());
+  }
+
+  let watchlists = Watchlist::find(Ownership::load(user.id).await?.readable(), None)
+    .await?
+    .into_iter()
+    .filter_map(|watchlist| watchlist.id)
+    .collect::<Vec<_>>();
+  let is_watched = WatchedAddress::exists(doc! {
+    "watchlist": { "$in": watchlists },
+    "address": &address
+  })
+  .await?;
+  if !is_watched {
+    debug!("Address not watched, returning 404 status code");
+    return Err(Error::not_found());
+  }
+
+  let to = match history::snapshot_at(&address, query.to).await? {
+    Some(snapshot) => snapshot,
+    None => {
+      debug!("No snapshot of the address, returning 404 status code");
+      return Err(Error::not_found());
+    }
+  };
+  // Everything is new when the address had no snapshot yet at `from`.
+  let from = history::snapshot_at(&address, query.from).await?;
+
+  let changes = history::diff(from.as_ref().map(|from| &from.data), &to.data);
+  let res = AddressDiff {
+    address,
+    from_snapshot_at: from.map(|from| from.created_at.try_to_rfc3339_string().unwrap()),
+    to_snapshot_at: to.created_at.try_to_rfc3339_string().unwrap(),
+    changes,
+  };
+
+  debug!("Returning address diff");
+  Ok(Json(res))
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct DiffQuery {
+  /// Start of the diff, an RFC 3339 date.
+  #[serde(deserialize_with = "deserialize_rfc3339")]
+  #[param(value_type = String, format = DateTime)]
+  from: Date,
+  /// End of the diff, an RFC 3339 date.
+  #[serde(deserialize_with = "deserialize_rfc3339")]
+  #[param(value_type = String, format = DateTime)]
+  to: Date,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct AddressDiff {
+  #[serde(serialize_with = "serialize_checksum_address")]
+  pub address: String,
+  // Dates of the compared snapshots. There is no `from` snapshot when the
+  // address was watched after that date.
+  pub from_snapshot_at: Option<String>,
+  pub to_snapshot_at: String,
+  pub changes: Vec<FieldChange>,
+}
@@ -0,0 +1,215 @@
+use axum::http::StatusCode;
+use bson::{doc, Bson};
+use serde::Deserialize;
+use tracing::debug;
+use utoipa::{OpenApi, ToSchema};
+use validator::Validate;
+use wither::mongodb::options::{FindOptions, UpdateOptions};
+
+use crate::errors::{Error, ErrorResponse};
+use crate::models::user_address_note::{PublicUserAddressNote, UserAddressNote};
+use crate::state::AppState;
+use crate::utils::address::EvmAddress;
+use crate::utils::custom_response::{CustomResponse, CustomResponseBuilder};
+use crate::utils::date;
+use crate::utils::json::{Json, ValidJson};
+use crate::utils::models::ModelExt;
+use crate::utils::route_table::RouteTable;
+use crate::utils::token::{MemberUser, TokenUser};
+
+#[derive(OpenApi)]
+#[openapi(
+  paths(
+    query_address_notes,
+    get_address_note,
+    update_address_note,
+    remove_address_note
+  ),
+  components(schemas(PublicUserAddressNote, UpdateAddressNote))
+)]
+pub struct ApiDoc;
+
+// Longest custom name and note.
+const MAX_NAME_LENGTH: u64 = 100;
+const MAX_NOTE_LENGTH: u64 = 2000;
+
+pub fn create_route() -> RouteTable<AppState> {
+  RouteTable::new()
+    .get("/address-notes", query_address_notes)
+    .get("/address-notes/:address", get_address_note)
+    .put("/address-notes/:address", update_address_note)
+    .delete("/address-notes/:address", remove_address_note)
+}
+
+/// Notes of the user, most recently updated first.
+#[utoipa::path(
+  get,
+  path = "/v1/address-notes",
+  responses(
+    (status = 200, description = "Address notes of the user", body = [PublicUserAddressNote]),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []), ("apiKey" = []))
+)]
+async fn query_address_notes(user: TokenUser) -> Result<Json<Vec<PublicUserAddressNote>>, Error> {
+  let options = FindOptions::builder()
+    .sort(doc! { "updated_at": -1_i32 })
+    .build();
+  let notes = UserAddressNote::find(doc! { "user": &user.id }, options)
+    .await?
+    .into_iter()
+    .map(Into::into)
+    .collect::<Vec<PublicUserAddressNote>>();
+
+  debug!("Returning address notes");
+  Ok(Json(notes))
+}
+
+#[utoipa::path(
+  get,
+  path = "/v1/address-notes/{address}",
+  params(("address" = String, Path, description = "EVM address")),
+  responses(
+    (status = 200, description = "Note of the user on the address", body = PublicUserAddressNote),
+    (status = 400, description = "Invalid address", body = ErrorResponse),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 404, description = "Address note not found", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []), ("apiKey" = []))
+)]
+async fn get_address_note(
+  user: TokenUser,
+  EvmAddress(address): EvmAddress,
+) -> Result<Json<PublicUserAddressNote>, Error> {
+  let note = UserAddressNote::find_one(doc! { "user": &user.id, "address": &address }, None)
+    .await?
+    .map(PublicUserAddressNote::from);
+
+  let note = match note {
+    Some(note) => note,
+    None => {
+      debug!("Address note not found, returning 404 status code");
+      return Err(Error::not_found());
+    }
+  };
+
+  debug!("Returning address note");
+  Ok(Json(note))
+}
+
+/// Sets the custom name and note of the user on an address, replacing the
+/// previous ones. Only visible to the user, and returned along with the
+/// Arkham data of the address.
+#[utoipa::path(
+  put,
+  path = "/v1/address-notes/{address}",
+  params(("address" = String, Path, description = "EVM address")),
+  request_body = UpdateAddressNote,
+  responses(
+    (status = 200, description = "Address note saved", body = PublicUserAddressNote),
+    (status = 400, description = "Invalid address, or empty name and note", body = ErrorResponse),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 422, description = "Invalid request body", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []), ("apiKey" = []))
+)]
+async fn update_address_note(
+  MemberUser(user): MemberUser,
+  EvmAddress(address): EvmAddress,
+  ValidJson(payload): ValidJson<UpdateAddressNote>,
+) -> Result<Json<PublicUserAddressNote>, Error> {
+  let name = non_empty(payload.name);
+  let note = non_empty(payload.note);
+  if name.is_none() && note.is_none() {
+    debug!("Empty address note, returning 400 status code");
+    return Err(Error::bad_request());
+  }
+
+  let query = doc! { "user": &user.id, "address": &address };
+  let now = date::now();
+  let options = UpdateOptions::builder().upsert(true).build();
+  UserAddressNote::update_one(
+    query.clone(),
+    doc! {
+      "$set": {
+        "name": name.map_or(Bson::Null, Bson::String),
+        "note": note.map_or(Bson::Null, Bson::String),
+        "updated_at": now
+      },
+      "$setOnInsert": { "created_at": now }
+    },
+    options,
+  )
+  .await?;
+
+  let note = UserAddressNote::find_one(query, None)
+    .await?
+    .map(PublicUserAddressNote::from)
+    .ok_or_else(|| Error::General("Upserted address note not found".to_owned()))?;
+
+  debug!("Returning address note");
+  Ok(Json(note))
+}
+
+#[utoipa::path(
+  delete,
+  path = "/v1/address-notes/{address}",
+  params(("address" = String, Path, description = "EVM address")),
+  responses(
+    (status = 204, description = "Address note removed"),
+    (status = 400, description = "Invalid address", body = ErrorResponse),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 404, description = "Address note not found", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []), ("apiKey" = []))
+)]
+async fn remove_address_note(
+  MemberUser(user): MemberUser,
+  EvmAddress(address): EvmAddress,
+) -> Result<CustomResponse<()>, Error> {
+  let delete_result =
+    UserAddressNote::delete_one(doc! { "user": &user.id, "address": &address }).await?;
+
+  if delete_result.deleted_count == 0 {
+    debug!("Address note not found, returning 404 status code");
+    return Err(Error::not_found());
+  }
+
+  let res = CustomResponseBuilder::new()
+    .status_code(StatusCode::NO_CONTENT)
+    .build();
+
+  Ok(res)
+}
+
+/// Note of the user on the address, merged into the Arkham lookups of
+/// authenticated users.
+pub async fn find_note(
+  user: Option<&TokenUser>,
+  address: &str,
+) -> Result<Option<PublicUserAddressNote>, Error> {
+  let user = match user {
+    Some(user) => user,
+    None => return Ok(None),
+  };
+
+  let note = UserAddressNote::find_one(doc! { "user": &user.id, "address": address }, None)
+    .await?
+    .map(PublicUserAddressNote::from);
+  Ok(note)
+}
+
+fn non_empty(value: Option<String>) -> Option<String> {
+  value
+    .map(|value| value.trim().to_owned())
+    .filter(|value| !value.is_empty())
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+struct UpdateAddressNote {
+  // Custom name of the address, e.g. `My cold wallet`.
+  #[validate(length(max = MAX_NAME_LENGTH))]
+  name: Option<String>,
+  #[validate(length(max = MAX_NOTE_LENGTH))]
+  note: Option<String>,
+}
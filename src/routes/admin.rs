@@ -0,0 +1,624 @@
+use axum::extract::{OriginalUri, Path, State};
+use axum::http::StatusCode;
+use bson::{doc, Bson};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info};
+use validator::Validate;
+use wither::mongodb::options::{FindOneOptions, FindOptions, UpdateOptions};
+
+use crate::errors::Error;
+use crate::models::alert_event::AlertEvent;
+use crate::models::alert_rule::AlertRule;
+use crate::models::api_key::ApiKey;
+use crate::models::audit_log::{AuditLog, PublicAuditLog};
+use crate::models::cat::{Cat, PublicCat};
+use crate::models::feature_flag::{FeatureFlag, PublicFeatureFlag};
+use crate::models::notification_channel::NotificationChannel;
+use crate::models::refresh_token::RefreshToken;
+use crate::models::user::{Plan, PublicUser, Role, User};
+use crate::models::watched_address::WatchedAddress;
+use crate::models::watchlist::Watchlist;
+use crate::models::webhook_endpoint::WebhookEndpoint;
+use crate::routes::cat::cat_attachment_files;
+use crate::routes::stats::StatsQuery;
+use crate::services::label_dataset::{self, DatasetReload};
+use crate::services::retention::{self, Cleanup, RetentionPolicy};
+use crate::services::scheduler::JobStatus;
+use crate::services::stats::{self, Stats};
+use crate::services::{account, gridfs};
+use crate::state::AppState;
+use crate::utils::custom_response::{CustomResponse, CustomResponseBuilder};
+use crate::utils::date;
+use crate::utils::json::{Json, ValidJson};
+use crate::utils::maintenance::MaintenanceStatus;
+use crate::utils::models::{CountStrategy, ModelExt};
+use crate::utils::pagination::{Cursor, Pagination};
+use crate::utils::query::Query;
+use crate::utils::request_query::RequestQuery;
+use crate::utils::route_table::RouteTable;
+use crate::utils::to_object_id::to_object_id;
+use crate::utils::token::AdminUser;
+
+pub fn create_route() -> RouteTable<AppState> {
+  RouteTable::new()
+    .get("/admin/audit", query_audit_logs)
+    .delete("/admin/cats", remove_cats)
+    .delete("/admin/cats/:id", remove_cat_by_id)
+    .put("/admin/cats/:id/owner", update_cat_owner)
+    .get("/admin/users", query_users)
+    .delete("/admin/users/:id", remove_user_by_id)
+    .put("/admin/users/:id/disabled", update_user_disabled)
+    .put("/admin/users/:id/role", update_user_role)
+    .put("/admin/users/:id/plan", update_user_plan)
+    .get("/admin/users/:id/usage", get_user_usage)
+    .get("/admin/stats", query_stats)
+    .get("/admin/jobs", query_jobs)
+    .get("/admin/feature-flags", query_feature_flags)
+    .put("/admin/feature-flags/:name", update_feature_flag)
+    .get("/admin/maintenance", get_maintenance)
+    .put("/admin/maintenance", update_maintenance)
+    .post("/admin/labels/reload", reload_labels)
+    .get("/admin/retention", query_retention)
+    .post("/admin/retention/cleanup", clean_up_retention)
+}
+
+/// Lists the changes made through the API, newest first.
+async fn query_audit_logs(
+  _admin: AdminUser,
+  Query(query): Query<RequestQuery>,
+  OriginalUri(uri): OriginalUri,
+  Query(filter): Query<AuditFilter>,
+) -> Result<CustomResponse<Vec<PublicAuditLog>>, Error> {
+  let sort = query.sort(&["created_at"])?;
+
+  let mut query_filter = doc! {};
+  query.filter_created(&mut query_filter);
+  if let Some(user) = filter.user {
+    query_filter.insert("actor", to_object_id(user)?);
+  }
+  if let Some(resource) = filter.resource {
+    query_filter.insert("resource", resource);
+  }
+
+  let pagination = Pagination::build_from_request_query(query).uri(uri);
+  let options = FindOptions::builder()
+    .sort(sort)
+    .skip(pagination.offset)
+    .limit(pagination.limit as i64)
+    .build();
+
+  // Audit logs pile up, unfiltered listings estimate their count.
+  let (logs, count) = AuditLog::find_page_and_count_with(
+    query_filter,
+    pagination.after_cursor(),
+    options,
+    CountStrategy::Estimated,
+  )
+  .await?;
+  let pagination =
+    pagination.next_cursor(&logs, |log| Cursor::new(log.created_at, log.id.unwrap()));
+  let logs = logs
+    .into_iter()
+    .map(Into::into)
+    .collect::<Vec<PublicAuditLog>>();
+
+  let res = CustomResponseBuilder::new()
+    .body(logs)
+    .pagination(pagination.count(count).build())
+    .build();
+
+  debug!("Returning audit logs");
+  Ok(res)
+}
+
+async fn remove_cats(
+  _admin: AdminUser,
+  Query(query): Query<RemoveCatsQuery>,
+) -> Result<Json<RemoveCatsResponse>, Error> {
+  // Never run an unfiltered delete, it would wipe the whole collection.
+  let user_id = match query.user.as_deref().map(str::trim) {
+    Some(user) if !user.is_empty() => to_object_id(user)?,
+    _ => {
+      debug!("Missing user filter, returning 400 status code");
+      return Err(Error::bad_request());
+    }
+  };
+
+  let files = cat_attachment_files(doc! { "user": user_id }).await?;
+  let deleted_count = Cat::delete_many(doc! { "user": user_id }).await?;
+  gridfs::delete_many(files).await?;
+
+  debug!("Removed {} cats", deleted_count);
+  Ok(Json(RemoveCatsResponse { deleted_count }))
+}
+
+/// Deletes a cat for good, including removed ones that users could still
+/// restore.
+async fn remove_cat_by_id(
+  _admin: AdminUser,
+  Path(id): Path<String>,
+) -> Result<CustomResponse<()>, Error> {
+  let cat_id = to_object_id(id)?;
+  let files = cat_attachment_files(doc! { "_id": cat_id }).await?;
+  let delete_result = Cat::delete_one(doc! { "_id": cat_id }).await?;
+
+  if delete_result.deleted_count == 0 {
+    debug!("Cat not found, returning 404 status code");
+    return Err(Error::not_found());
+  }
+  gridfs::delete_many(files).await?;
+
+  let res = CustomResponseBuilder::new()
+    .status_code(StatusCode::NO_CONTENT)
+    .build();
+
+  Ok(res)
+}
+
+async fn update_cat_owner(
+  _admin: AdminUser,
+  Path(id): Path<String>,
+  Json(body): Json<UpdateCatOwner>,
+) -> Result<Json<PublicCat>, Error> {
+  let cat_id = to_object_id(id)?;
+  let user_id = to_object_id(body.user)?;
+
+  if !User::exists(doc! { "_id": &user_id }).await? {
+    debug!("Target user not found, returning 404 status code");
+    return Err(Error::not_found());
+  }
+
+  let cat = Cat::find_one_and_update(
+    doc! { "_id": &cat_id },
+    doc! { "$set": { "user": &user_id, "updated_at": date::now() } },
+  )
+  .await?
+  .map(PublicCat::from);
+
+  let cat = match cat {
+    Some(cat) => cat,
+    None => {
+      debug!("Cat not found, returning 404 status code");
+      return Err(Error::not_found());
+    }
+  };
+
+  debug!("Returning cat with new owner");
+  Ok(Json(cat))
+}
+
+async fn query_users(
+  _admin: AdminUser,
+  Query(query): Query<RequestQuery>,
+  OriginalUri(uri): OriginalUri,
+  Query(filter): Query<UserFilter>,
+) -> Result<CustomResponse<Vec<PublicUser>>, Error> {
+  let sort = query.sort(&["created_at"])?;
+
+  let mut query_filter = doc! {};
+  query.filter_created(&mut query_filter);
+  if let Some(role) = filter.role {
+    query_filter.insert("role", role.as_str());
+  }
+  if let Some(disabled) = filter.disabled {
+    let locked_at = if disabled {
+      doc! { "$ne": Bson::Null }
+    } else {
+      doc! { "$eq": Bson::Null }
+    };
+    query_filter.insert("locked_at", locked_at);
+  }
+
+  let pagination = Pagination::build_from_request_query(query).uri(uri);
+  let options = FindOptions::builder()
+    .sort(sort)
+    .skip(pagination.offset)
+    .limit(pagination.limit as i64)
+    .build();
+
+  let (users, count) = User::find_page_and_count_with(
+    query_filter,
+    pagination.after_cursor(),
+    options,
+    CountStrategy::Estimated,
+  )
+  .await?;
+  let pagination = pagination.next_cursor(&users, |user| {
+    Cursor::new(user.created_at, user.id.unwrap())
+  });
+  let users = users
+    .into_iter()
+    .map(Into::into)
+    .collect::<Vec<PublicUser>>();
+
+  let res = CustomResponseBuilder::new()
+    .body(users)
+    .pagination(pagination.count(count).build())
+    .build();
+
+  debug!("Returning users");
+  Ok(res)
+}
+
+/// Deletes a user along everything they own.
+async fn remove_user_by_id(
+  AdminUser(admin): AdminUser,
+  Path(id): Path<String>,
+) -> Result<CustomResponse<()>, Error> {
+  let user_id = to_object_id(id)?;
+  if user_id == admin.id {
+    debug!("Admin removing themselves, returning 400 status code");
+    return Err(Error::bad_request());
+  }
+
+  if !account::delete_user(user_id).await? {
+    debug!("User not found, returning 404 status code");
+    return Err(Error::not_found());
+  }
+  info!("User {} removed by admin {}", user_id, admin.id);
+
+  let res = CustomResponseBuilder::new()
+    .status_code(StatusCode::NO_CONTENT)
+    .build();
+
+  Ok(res)
+}
+
+/// Disables or enables a user. Disabled users can't authenticate, refresh
+/// their tokens or use their API keys, issued access tokens stay valid until
+/// they expire.
+async fn update_user_disabled(
+  AdminUser(admin): AdminUser,
+  Path(id): Path<String>,
+  Json(body): Json<UpdateUserDisabled>,
+) -> Result<Json<PublicUser>, Error> {
+  let user_id = to_object_id(id)?;
+  if user_id == admin.id {
+    debug!("Admin disabling themselves, returning 400 status code");
+    return Err(Error::bad_request());
+  }
+
+  let now = date::now();
+  let locked_at = if body.disabled {
+    Bson::DateTime(now)
+  } else {
+    Bson::Null
+  };
+  let user = User::find_one_and_update(
+    doc! { "_id": &user_id },
+    doc! { "$set": { "locked_at": locked_at, "updated_at": now } },
+  )
+  .await?;
+
+  let user = match user {
+    Some(user) => user,
+    None => {
+      debug!("User not found, returning 404 status code");
+      return Err(Error::not_found());
+    }
+  };
+
+  if body.disabled {
+    RefreshToken::delete_many(doc! { "user": &user_id }).await?;
+  }
+
+  debug!("Returning updated user");
+  Ok(Json(PublicUser::from(user)))
+}
+
+/// Changes the role of a user. Access tokens carry the role, so the change
+/// applies once the user refreshes their token.
+async fn update_user_role(
+  AdminUser(admin): AdminUser,
+  Path(id): Path<String>,
+  Json(body): Json<UpdateUserRole>,
+) -> Result<Json<PublicUser>, Error> {
+  let user_id = to_object_id(id)?;
+  // Admins can't demote themselves, so there is always one admin left.
+  if user_id == admin.id && body.role != Role::Admin {
+    debug!("Admin demoting themselves, returning 400 status code");
+    return Err(Error::bad_request());
+  }
+
+  let user = User::find_one_and_update(
+    doc! { "_id": &user_id },
+    doc! { "$set": { "role": body.role.as_str(), "updated_at": date::now() } },
+  )
+  .await?;
+
+  let user = match user {
+    Some(user) => user,
+    None => {
+      debug!("User not found, returning 404 status code");
+      return Err(Error::not_found());
+    }
+  };
+
+  debug!("Returning updated user");
+  Ok(Json(PublicUser::from(user)))
+}
+
+/// Changes the plan of a user. Like the role, the new quotas apply once the
+/// user refreshes their token.
+async fn update_user_plan(
+  _admin: AdminUser,
+  Path(id): Path<String>,
+  Json(body): Json<UpdateUserPlan>,
+) -> Result<Json<PublicUser>, Error> {
+  let user_id = to_object_id(id)?;
+  let user = User::find_one_and_update(
+    doc! { "_id": &user_id },
+    doc! { "$set": { "plan": body.plan.as_str(), "updated_at": date::now() } },
+  )
+  .await?;
+
+  let user = match user {
+    Some(user) => user,
+    None => {
+      debug!("User not found, returning 404 status code");
+      return Err(Error::not_found());
+    }
+  };
+
+  debug!("Returning updated user");
+  Ok(Json(PublicUser::from(user)))
+}
+
+async fn get_user_usage(
+  _admin: AdminUser,
+  Path(id): Path<String>,
+) -> Result<Json<UserUsage>, Error> {
+  let user_id = to_object_id(id)?;
+  if !User::exists(doc! { "_id": &user_id }).await? {
+    debug!("User not found, returning 404 status code");
+    return Err(Error::not_found());
+  }
+
+  let query = doc! { "user": &user_id };
+  let last_api_key = ApiKey::find_one(
+    doc! { "user": &user_id, "last_used_at": { "$ne": Bson::Null } },
+    FindOneOptions::builder()
+      .sort(doc! { "last_used_at": -1_i32 })
+      .build(),
+  )
+  .await?;
+
+  let usage = UserUsage {
+    cats: Cat::count(query.clone()).await?,
+    watchlists: Watchlist::count(query.clone()).await?,
+    watched_addresses: WatchedAddress::count(query.clone()).await?,
+    alert_rules: AlertRule::count(query.clone()).await?,
+    alert_events: AlertEvent::count(query.clone()).await?,
+    notification_channels: NotificationChannel::count(query.clone()).await?,
+    webhooks: WebhookEndpoint::count(query.clone()).await?,
+    api_keys: ApiKey::count(query).await?,
+    api_key_last_used_at: last_api_key
+      .and_then(|api_key| api_key.last_used_at)
+      .map(|date| date.try_to_rfc3339_string().unwrap()),
+  };
+
+  debug!("Returning user usage");
+  Ok(Json(usage))
+}
+
+/// Statistics of every user over the last days, see `stats::compute`.
+async fn query_stats(
+  _admin: AdminUser,
+  Query(query): Query<StatsQuery>,
+) -> Result<Json<Stats>, Error> {
+  let stats = stats::compute(None, query.days()?).await?;
+
+  debug!("Returning stats");
+  Ok(Json(stats))
+}
+
+/// Lists the scheduled jobs of this instance with the outcome of their last
+/// run.
+async fn query_jobs(
+  _admin: AdminUser,
+  State(state): State<AppState>,
+) -> Result<Json<Vec<JobStatus>>, Error> {
+  let jobs = state.scheduler.statuses();
+
+  debug!("Returning scheduled jobs");
+  Ok(Json(jobs))
+}
+
+/// Lists the configured and stored feature flags.
+async fn query_feature_flags(
+  _admin: AdminUser,
+  State(state): State<AppState>,
+) -> Result<Json<Vec<PublicFeatureFlag>>, Error> {
+  let flags = state.feature_flags.list().await?;
+
+  debug!("Returning feature flags");
+  Ok(Json(flags))
+}
+
+/// Sets a feature flag, overriding its configured default. The change
+/// applies at once on this instance, and within the flags cache TTL on the
+/// others unless the cache is shared.
+async fn update_feature_flag(
+  _admin: AdminUser,
+  State(state): State<AppState>,
+  Path(name): Path<String>,
+  ValidJson(body): ValidJson<UpdateFeatureFlag>,
+) -> Result<Json<PublicFeatureFlag>, Error> {
+  let users = body
+    .users
+    .iter()
+    .map(to_object_id)
+    .collect::<Result<Vec<_>, _>>()?;
+
+  let query = doc! { "name": &name };
+  let now = date::now();
+  let options = UpdateOptions::builder().upsert(true).build();
+  FeatureFlag::update_one(
+    query.clone(),
+    doc! {
+      "$set": {
+        "enabled": body.enabled,
+        "rollout_percent": body.rollout_percent as i32,
+        "users": users,
+        "updated_at": now
+      },
+      "$setOnInsert": { "created_at": now }
+    },
+    options,
+  )
+  .await?;
+  state.feature_flags.invalidate(&name).await;
+
+  let flag = FeatureFlag::find_one(query, None)
+    .await?
+    .map(PublicFeatureFlag::from)
+    .ok_or_else(|| Error::General("Upserted feature flag not found".to_owned()))?;
+
+  info!(
+    "Feature flag {} set to {}% (enabled: {})",
+    flag.name, flag.rollout_percent, flag.enabled
+  );
+  Ok(Json(flag))
+}
+
+async fn get_maintenance(
+  _admin: AdminUser,
+  State(state): State<AppState>,
+) -> Json<MaintenanceStatus> {
+  debug!("Returning maintenance status");
+  Json(state.maintenance.status())
+}
+
+/// Turns the maintenance mode of this instance on or off, e.g. around
+/// database migrations.
+async fn update_maintenance(
+  _admin: AdminUser,
+  State(state): State<AppState>,
+  Json(body): Json<MaintenanceStatus>,
+) -> Result<Json<MaintenanceStatus>, Error> {
+  if body.retry_after_secs == 0 {
+    debug!("Invalid Retry-After, returning 400 status code");
+    return Err(Error::bad_request());
+  }
+
+  state.maintenance.set(body);
+  info!("Maintenance mode set to {}", body.enabled);
+
+  Ok(Json(state.maintenance.status()))
+}
+
+/// Replaces the dataset labels with the configured dataset, without waiting
+/// for the `label_dataset_sync` job. Queries keep seeing the previous labels
+/// until the new ones are all stored.
+async fn reload_labels(
+  _admin: AdminUser,
+  State(state): State<AppState>,
+) -> Result<Json<DatasetReload>, Error> {
+  let source = state.settings.label_dataset.url.clone();
+  if source.is_empty() {
+    debug!("No label dataset configured, returning 404 status code");
+    return Err(Error::not_found());
+  }
+
+  // Run apart from the request, so a disconnecting client doesn't stop the
+  // reload halfway and the swapped labels aren't recorded one by one in the
+  // audit logs.
+  let reload = tokio::spawn(async move { label_dataset::reload(&source).await }).await??;
+
+  Ok(Json(reload))
+}
+
+/// Lists the retention of the collections with the number of documents past
+/// it.
+async fn query_retention(
+  _admin: AdminUser,
+  State(state): State<AppState>,
+) -> Result<Json<Vec<RetentionPolicy>>, Error> {
+  let policies = retention::policies(&state.settings.retention).await?;
+
+  debug!("Returning retention policies");
+  Ok(Json(policies))
+}
+
+/// Removes the documents past their retention, without waiting for the
+/// `retention_cleanup` job.
+async fn clean_up_retention(
+  _admin: AdminUser,
+  State(state): State<AppState>,
+) -> Result<Json<Vec<Cleanup>>, Error> {
+  let settings = state.settings.retention.clone();
+  // Run apart from the request, so a disconnecting client doesn't stop the
+  // cleanup halfway.
+  let cleanups = tokio::spawn(async move { retention::clean_up(&settings).await }).await??;
+
+  Ok(Json(cleanups))
+}
+
+#[derive(Debug, Deserialize)]
+struct AuditFilter {
+  // Id of the user who made the changes.
+  user: Option<String>,
+  // Collection of the changed documents, e.g. `cats`.
+  resource: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RemoveCatsQuery {
+  user: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RemoveCatsResponse {
+  pub deleted_count: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct UpdateCatOwner {
+  user: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct UserFilter {
+  role: Option<Role>,
+  disabled: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+struct UpdateUserDisabled {
+  disabled: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct UpdateUserRole {
+  role: Role,
+}
+
+#[derive(Debug, Deserialize)]
+struct UpdateUserPlan {
+  plan: Plan,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+struct UpdateFeatureFlag {
+  enabled: bool,
+  #[validate(range(max = 100))]
+  rollout_percent: u8,
+  // Ids of the users the feature is enabled for regardless of the rollout.
+  #[serde(default)]
+  users: Vec<String>,
+}
+
+/// Number of documents owned by a user.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UserUsage {
+  pub cats: u64,
+  pub watchlists: u64,
+  pub watched_addresses: u64,
+  pub alert_rules: u64,
+  pub alert_events: u64,
+  pub notification_channels: u64,
+  pub webhooks: u64,
+  pub api_keys: u64,
+  // When an API key of the user was last used.
+  pub api_key_last_used_at: Option<String>,
+}
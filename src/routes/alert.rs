@@ -0,0 +1,314 @@
+use axum::extract::{OriginalUri, Path, State};
+use axum::http::StatusCode;
+use bson::doc;
+use serde::Deserialize;
+use tracing::debug;
+use utoipa::{OpenApi, ToSchema};
+use wither::mongodb::options::FindOptions;
+
+use crate::errors::{Error, ErrorResponse};
+use crate::models::alert_event::{
+  AlertEvent, PublicAlertEvent, ALERT_EVENT_FILTER_FIELDS, ALERT_EVENT_SORT_FIELDS,
+};
+use crate::models::alert_rule::{AlertCondition, AlertRule, PublicAlertRule};
+use crate::routes::prices::parse_token_id;
+use crate::settings;
+use crate::state::AppState;
+use crate::utils::address::normalize_evm_address;
+use crate::utils::custom_response::{CustomResponse, CustomResponseBuilder};
+use crate::utils::json::Json;
+use crate::utils::models::ModelExt;
+use crate::utils::pagination::{Cursor, Pagination};
+use crate::utils::query::Query;
+use crate::utils::query_fields::Filters;
+use crate::utils::request_query::RequestQuery;
+use crate::utils::route_table::RouteTable;
+use crate::utils::to_object_id::to_object_id;
+use crate::utils::token::{MemberUser, TokenUser};
+
+#[derive(OpenApi)]
+#[openapi(
+  paths(
+    query_alerts,
+    create_alert_rule,
+    query_alert_rules,
+    remove_alert_rule_by_id
+  ),
+  components(schemas(PublicAlertEvent, PublicAlertRule, AlertCondition, CreateAlertRule))
+)]
+pub struct ApiDoc;
+
+pub fn create_route() -> RouteTable<AppState> {
+  RouteTable::new()
+    .get("/alerts", query_alerts)
+    .post("/alerts/rules", create_alert_rule)
+    .get("/alerts/rules", query_alert_rules)
+    .delete("/alerts/rules/:id", remove_alert_rule_by_id)
+}
+
+#[utoipa::path(
+  get,
+  path = "/v1/alerts",
+  params(RequestQuery),
+  responses(
+    (
+      status = 200,
+      description = "Paginated alerts of the user, newest first",
+      body = [PublicAlertEvent],
+      headers(
+        ("x-pagination-count" = u64, description = "Total number of alerts"),
+        ("x-pagination-offset" = u64, description = "Offset of the returned page"),
+        ("x-pagination-limit" = u64, description = "Size of the returned page"),
+        ("x-pagination-total-pages" = u64, description = "Total number of pages"),
+        ("x-pagination-has-next" = bool, description = "Whether there are alerts after the returned page"),
+        ("x-pagination-next-cursor" = String, description = "Cursor of the next page, only sent for full pages")
+      )
+    ),
+    (status = 400, description = "Invalid query parameters", body = ErrorResponse),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 422, description = "Unsupported sort or filter field", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []))
+)]
+async fn query_alerts(
+  user: TokenUser,
+  Query(query): Query<RequestQuery>,
+  OriginalUri(uri): OriginalUri,
+  filters: Filters,
+) -> Result<CustomResponse<Vec<PublicAlertEvent>>, Error> {
+  let sort = query.sort(ALERT_EVENT_SORT_FIELDS)?;
+  let mut query_filter = doc! { "user": &user.id };
+  query.filter_created(&mut query_filter);
+  filters.apply(ALERT_EVENT_FILTER_FIELDS, &mut query_filter)?;
+  let pagination = Pagination::build_from_request_query(query).uri(uri);
+
+  let options = FindOptions::builder()
+    .sort(sort)
+    .skip(pagination.offset)
+    .limit(pagination.limit as i64)
+    .build();
+
+  let (alerts, count) =
+    AlertEvent::find_page_and_count(query_filter, pagination.after_cursor(), options).await?;
+  let pagination = pagination.next_cursor(&alerts, |alert| {
+    Cursor::new(alert.created_at, alert.id.unwrap())
+  });
+  let alerts = alerts
+    .into_iter()
+    .map(Into::into)
+    .collect::<Vec<PublicAlertEvent>>();
+
+  let res = CustomResponseBuilder::new()
+    .body(alerts)
+    .pagination(pagination.count(count).build())
+    .build();
+
+  debug!("Returning alerts");
+  Ok(res)
+}
+
+#[utoipa::path(
+  post,
+  path = "/v1/alerts/rules",
+  request_body = CreateAlertRule,
+  responses(
+    (status = 201, description = "Alert rule created", body = PublicAlertRule),
+    (status = 400, description = "Invalid address or token id", body = ErrorResponse),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 422, description = "Invalid request body", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []))
+)]
+async fn create_alert_rule(
+  MemberUser(user): MemberUser,
+  State(state): State<AppState>,
+  Json(payload): Json<CreateAlertRule>,
+) -> Result<CustomResponse<PublicAlertRule>, Error> {
+  let address = payload
+    .address
+    .clone()
+    .map(normalize_evm_address)
+    .transpose()?;
+  validate_rule(&payload, &state.settings.price_alerts)?;
+  let token = payload.token.as_deref().map(parse_token_id).transpose()?;
+
+  let mut rule = AlertRule::new(user.id, payload.condition, address);
+  rule.min_usd = payload.min_usd;
+  rule.token = token;
+  rule.price_usd = payload.price_usd;
+  rule.change_percent = payload.change_percent;
+  rule.window_secs = payload.window_secs;
+  rule.cooldown_secs = payload.cooldown_secs;
+  let rule = AlertRule::create(rule).await?;
+  let res = PublicAlertRule::from(rule);
+
+  let res = CustomResponseBuilder::new()
+    .body(res)
+    .status_code(StatusCode::CREATED)
+    .build();
+
+  Ok(res)
+}
+
+#[utoipa::path(
+  get,
+  path = "/v1/alerts/rules",
+  responses(
+    (status = 200, description = "Alert rules of the user", body = [PublicAlertRule]),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []))
+)]
+async fn query_alert_rules(user: TokenUser) -> Result<Json<Vec<PublicAlertRule>>, Error> {
+  let options = FindOptions::builder()
+    .sort(doc! { "created_at": 1_i32 })
+    .build();
+  let rules = AlertRule::find(doc! { "user": &user.id }, options)
+    .await?
+    .into_iter()
+    .map(Into::into)
+    .collect::<Vec<PublicAlertRule>>();
+
+  debug!("Returning alert rules");
+  Ok(Json(rules))
+}
+
+#[utoipa::path(
+  delete,
+  path = "/v1/alerts/rules/{id}",
+  params(("id" = String, Path, description = "Alert rule id")),
+  responses(
+    (status = 204, description = "Alert rule removed"),
+    (status = 400, description = "Invalid alert rule id", body = ErrorResponse),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 404, description = "Alert rule not found", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []))
+)]
+async fn remove_alert_rule_by_id(
+  MemberUser(user): MemberUser,
+  Path(id): Path<String>,
+) -> Result<CustomResponse<()>, Error> {
+  let rule_id = to_object_id(id)?;
+  let delete_result = AlertRule::delete_one(doc! { "_id": rule_id, "user": &user.id }).await?;
+
+  if delete_result.deleted_count == 0 {
+    debug!("Alert rule not found, returning 404 status code");
+    return Err(Error::not_found());
+  }
+
+  let res = CustomResponseBuilder::new()
+    .status_code(StatusCode::NO_CONTENT)
+    .build();
+
+  Ok(res)
+}
+
+/// Checks the rule sets the fields its condition requires, and only them.
+fn validate_rule(payload: &CreateAlertRule, settings: &settings::PriceAlerts) -> Result<(), Error> {
+  // Only whale transfer rules have a threshold, and they require one.
+  match (payload.condition, payload.min_usd) {
+    (AlertCondition::WhaleTransfer, Some(min_usd)) if min_usd.is_finite() && min_usd > 0.0 => {}
+    (AlertCondition::WhaleTransfer, _) => {
+      return Err(Error::InvalidPayload(
+        "`min_usd` must be a positive number for whale transfer rules".to_owned(),
+      ));
+    }
+    (_, Some(_)) => {
+      return Err(Error::InvalidPayload(
+        "`min_usd` is only supported by whale transfer rules".to_owned(),
+      ));
+    }
+    (_, None) => {}
+  }
+
+  if !payload.condition.is_price() {
+    let has_price_fields = payload.token.is_some()
+      || payload.price_usd.is_some()
+      || payload.change_percent.is_some()
+      || payload.window_secs.is_some()
+      || payload.cooldown_secs.is_some();
+    if has_price_fields {
+      return Err(Error::InvalidPayload(
+        "`token`, `price_usd`, `change_percent`, `window_secs` and `cooldown_secs` are only supported by price rules".to_owned(),
+      ));
+    }
+    return Ok(());
+  }
+
+  if payload.address.is_some() {
+    return Err(Error::InvalidPayload(
+      "`address` is not supported by price rules".to_owned(),
+    ));
+  }
+  if payload.token.is_none() {
+    return Err(Error::InvalidPayload(
+      "`token` is required for price rules".to_owned(),
+    ));
+  }
+
+  if payload.condition == AlertCondition::PriceChange {
+    match payload.change_percent {
+      Some(change) if change.is_finite() && change != 0.0 && change > -100.0 => {}
+      _ => {
+        return Err(Error::InvalidPayload(
+          "`change_percent` must be a non-zero number above -100 for price change rules".to_owned(),
+        ));
+      }
+    }
+    match payload.window_secs {
+      Some(window) if (60..=settings.max_window_secs).contains(&window) => {}
+      _ => {
+        return Err(Error::InvalidPayload(format!(
+          "`window_secs` must be between 60 and {} for price change rules",
+          settings.max_window_secs
+        )));
+      }
+    }
+    if payload.price_usd.is_some() {
+      return Err(Error::InvalidPayload(
+        "`price_usd` is only supported by price threshold rules".to_owned(),
+      ));
+    }
+  } else {
+    match payload.price_usd {
+      Some(price) if price.is_finite() && price > 0.0 => {}
+      _ => {
+        return Err(Error::InvalidPayload(
+          "`price_usd` must be a positive number for price threshold rules".to_owned(),
+        ));
+      }
+    }
+    if payload.change_percent.is_some() || payload.window_secs.is_some() {
+      return Err(Error::InvalidPayload(
+        "`change_percent` and `window_secs` are only supported by price change rules".to_owned(),
+      ));
+    }
+  }
+
+  Ok(())
+}
+
+#[derive(Deserialize, ToSchema)]
+struct CreateAlertRule {
+  condition: AlertCondition,
+  // Applies the rule to every watched address when not sent, not supported
+  // by price rules.
+  address: Option<String>,
+  // USD value from which transfers raise an alert, required for
+  // `whale_transfer` rules.
+  min_usd: Option<f64>,
+  // CoinGecko id of the token, required for price rules, e.g. `ethereum`.
+  token: Option<String>,
+  // Price the token must cross, required for `price_above` and
+  // `price_below` rules.
+  price_usd: Option<f64>,
+  // Change of the price over `window_secs` in percent, negative for drops,
+  // required for `price_change` rules, e.g. `-10` over `3600` for a 10% drop
+  // in an hour.
+  change_percent: Option<f64>,
+  window_secs: Option<u64>,
+  // Time after raising an alert before the rule can raise another one,
+  // `price_alerts.cooldown_secs` when not sent.
+  cooldown_secs: Option<u64>,
+}
@@ -0,0 +1,150 @@
+use axum::extract::Path;
+use axum::http::StatusCode;
+use bson::doc;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+use utoipa::{OpenApi, ToSchema};
+use wither::mongodb::options::FindOptions;
+
+use crate::errors::{Error, ErrorResponse};
+use crate::models::api_key::{ApiKey, ApiKeyScope, PublicApiKey};
+use crate::state::AppState;
+use crate::utils::custom_response::{CustomResponse, CustomResponseBuilder};
+use crate::utils::json::Json;
+use crate::utils::models::ModelExt;
+use crate::utils::route_table::RouteTable;
+use crate::utils::to_object_id::to_object_id;
+use crate::utils::token::{MemberUser, TokenUser};
+
+#[derive(OpenApi)]
+#[openapi(
+  paths(create_api_key, query_api_keys, remove_api_key_by_id),
+  components(schemas(PublicApiKey, ApiKeyScope, CreatedApiKey, CreateApiKey))
+)]
+pub struct ApiDoc;
+
+pub fn create_route() -> RouteTable<AppState> {
+  RouteTable::new()
+    .post("/api-keys", create_api_key)
+    .get("/api-keys", query_api_keys)
+    .delete("/api-keys/:id", remove_api_key_by_id)
+}
+
+/// Creates an API key. The key is only returned by this request.
+#[utoipa::path(
+  post,
+  path = "/v1/api-keys",
+  request_body = CreateApiKey,
+  responses(
+    (status = 201, description = "API key created", body = CreatedApiKey),
+    (status = 400, description = "Empty name or scopes", body = ErrorResponse),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 422, description = "Invalid request body", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []), ("apiKey" = []))
+)]
+async fn create_api_key(
+  MemberUser(user): MemberUser,
+  Json(payload): Json<CreateApiKey>,
+) -> Result<CustomResponse<CreatedApiKey>, Error> {
+  let name = payload.name.trim();
+  if name.is_empty() {
+    debug!("Empty API key name, returning 400 status code");
+    return Err(Error::bad_request());
+  }
+
+  let mut scopes = Vec::new();
+  for scope in payload.scopes.unwrap_or_else(|| vec![ApiKeyScope::Read]) {
+    if !scopes.contains(&scope) {
+      scopes.push(scope);
+    }
+  }
+  if scopes.is_empty() {
+    debug!("API key without scopes, returning 400 status code");
+    return Err(Error::bad_request());
+  }
+
+  let (api_key, key) = ApiKey::generate(user.id, name.to_owned(), scopes);
+  let api_key = ApiKey::create(api_key).await?;
+  let res = CreatedApiKey {
+    api_key: PublicApiKey::from(api_key),
+    key,
+  };
+
+  let res = CustomResponseBuilder::new()
+    .body(res)
+    .status_code(StatusCode::CREATED)
+    .build();
+
+  Ok(res)
+}
+
+#[utoipa::path(
+  get,
+  path = "/v1/api-keys",
+  responses(
+    (status = 200, description = "API keys of the user", body = [PublicApiKey]),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []), ("apiKey" = []))
+)]
+async fn query_api_keys(user: TokenUser) -> Result<Json<Vec<PublicApiKey>>, Error> {
+  let options = FindOptions::builder()
+    .sort(doc! { "created_at": 1_i32 })
+    .build();
+  let api_keys = ApiKey::find(doc! { "user": &user.id }, options)
+    .await?
+    .into_iter()
+    .map(Into::into)
+    .collect::<Vec<PublicApiKey>>();
+
+  debug!("Returning API keys");
+  Ok(Json(api_keys))
+}
+
+/// Revokes an API key, requests using it are rejected right away.
+#[utoipa::path(
+  delete,
+  path = "/v1/api-keys/{id}",
+  params(("id" = String, Path, description = "API key id")),
+  responses(
+    (status = 204, description = "API key revoked"),
+    (status = 400, description = "Invalid API key id", body = ErrorResponse),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 404, description = "API key not found", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []), ("apiKey" = []))
+)]
+async fn remove_api_key_by_id(
+  MemberUser(user): MemberUser,
+  Path(id): Path<String>,
+) -> Result<CustomResponse<()>, Error> {
+  let api_key_id = to_object_id(id)?;
+  let delete_result = ApiKey::delete_one(doc! { "_id": api_key_id, "user": &user.id }).await?;
+
+  if delete_result.deleted_count == 0 {
+    debug!("API key not found, returning 404 status code");
+    return Err(Error::not_found());
+  }
+
+  let res = CustomResponseBuilder::new()
+    .status_code(StatusCode::NO_CONTENT)
+    .build();
+
+  Ok(res)
+}
+
+#[derive(Deserialize, ToSchema)]
+struct CreateApiKey {
+  name: String,
+  // Defaults to read only.
+  scopes: Option<Vec<ApiKeyScope>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CreatedApiKey {
+  #[serde(flatten)]
+  pub api_key: PublicApiKey,
+  // Sent in the `X-Api-Key` header. It can't be retrieved later.
+  pub key: String,
+}
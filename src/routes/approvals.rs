@@ -0,0 +1,72 @@
+use axum::extract::State;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+use utoipa::{IntoParams, OpenApi, ToSchema};
+
+use crate::errors::{Error, ErrorResponse};
+use crate::services::approvals::{Approval, Severity, TokenStandard};
+use crate::state::AppState;
+use crate::utils::address::EvmAddress;
+use crate::utils::json::Json;
+use crate::utils::query::Query;
+use crate::utils::route_table::RouteTable;
+use crate::utils::serde_helpers::serialize_checksum_address;
+
+// Chain scanned when none is requested.
+const DEFAULT_CHAIN: &str = "ethereum";
+
+#[derive(OpenApi)]
+#[openapi(
+  paths(query_approvals),
+  components(schemas(ApprovalReport, Approval, TokenStandard, Severity))
+)]
+pub struct ApiDoc;
+
+pub fn create_route() -> RouteTable<AppState> {
+  RouteTable::new().get("/approvals/:address", query_approvals)
+}
+
+#[utoipa::path(
+  get,
+  path = "/v1/approvals/{address}",
+  params(("address" = String, Path, description = "EVM address"), ApprovalsQuery),
+  responses(
+    (status = 200, description = "Outstanding ERC-20 and ERC-721 approvals of the address, most severe first", body = ApprovalReport),
+    (status = 400, description = "Unsupported chain or invalid address", body = ErrorResponse),
+    (status = 503, description = "RPC provider request failed", body = ErrorResponse),
+    (status = 502, description = "RPC provider returned an invalid response", body = ErrorResponse)
+  )
+)]
+async fn query_approvals(
+  State(state): State<AppState>,
+  EvmAddress(address): EvmAddress,
+  Query(query): Query<ApprovalsQuery>,
+) -> Result<Json<ApprovalReport>, Error> {
+  let chain = query.chain.unwrap_or_else(|| DEFAULT_CHAIN.to_owned());
+  let scan = state.approvals.fetch_approvals(&chain, &address).await?;
+
+  debug!("Returning approvals");
+  Ok(Json(ApprovalReport {
+    chain,
+    address,
+    approvals: scan.approvals,
+    unchecked: scan.unchecked,
+  }))
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct ApprovalsQuery {
+  /// Chain name, `ethereum` by default.
+  chain: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ApprovalReport {
+  pub chain: String,
+  #[serde(serialize_with = "serialize_checksum_address")]
+  pub address: String,
+  pub approvals: Vec<Approval>,
+  // Number of approvals found in the logs whose state couldn't be read.
+  pub unchecked: usize,
+}
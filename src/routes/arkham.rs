@@ -1,66 +1,807 @@
-use axum::{extract::Path, routing::get, Json, Router};
-use serde::{Deserialize, Serialize};
-use std::env;
-use tracing::{debug, error, info};
-
-use crate::errors::Error;
-
-pub fn create_route() -> Router {
-  info!("Creating /arkham/:address route");
-  Router::new().route("/arkham/:address", get(query_arkham))
-}
-
-async fn query_arkham(Path(address): Path<String>) -> Result<Json<ArkhamResponse>, Error> {
-  info!("Querying arkham with address: {}", &address);
-  let arkham_api_key = env::var("ARKHAM_API_KEY").expect("ARKHAM_API_KEY must be set");
-  let client = reqwest::Client::new();
-  let res = client
-    .get(format!(
-      "https://api.arkhamintelligence.com/intelligence/address/{}/all",
-      address
-    ))
-    .header("API-Key", arkham_api_key)
-    .send()
+use axum::{
+  extract::{Path, State},
+  http::header::{self, HeaderMap, HeaderName, HeaderValue},
+  response::sse::{Event, KeepAlive, Sse},
+  response::{IntoResponse, Response},
+};
+use futures::future;
+use futures::stream::{self, Stream, StreamExt};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::convert::Infallible;
+use std::time::Duration;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::time::{interval_at, Instant, Interval};
+use tracing::{debug, error, info, warn};
+use utoipa::openapi::{ObjectBuilder, Ref, RefOr, Schema};
+use utoipa::{IntoParams, OpenApi, ToSchema};
+
+use crate::errors::{AuthenticateError, Error, ErrorResponse};
+use crate::models::job::{JobKind, PublicJob};
+use crate::models::user_address_note::PublicUserAddressNote;
+use crate::routes::address_note::find_note;
+use crate::routes::jobs::{accepted, respond_async};
+use crate::services::ens;
+use crate::services::feature_flags::{AddressGraphFeature, RequireFeature};
+use crate::services::graph::{self, AddressGraph, GraphEdge, GraphNode, NodeKind, Relation};
+use crate::services::jobs::{self, GraphExpansion};
+use crate::services::stats;
+use crate::services::watcher::AddressChange;
+use crate::settings::IntelligenceBackend;
+use crate::state::AppState;
+use crate::utils::address::{
+  normalize_address, AddressChain, ChainAddress, ChainQuery, EvmAddress,
+};
+use crate::utils::cache::{CacheEntry, CacheStats};
+use crate::utils::date;
+use crate::utils::json::Json;
+use crate::utils::proxy::{self, RawQuery};
+use crate::utils::query::Query;
+use crate::utils::route_table::RouteTable;
+use crate::utils::serde_helpers::{deserialize_optional_number, serialize_checksum_address};
+use crate::utils::token::TokenUser;
+
+// Expansions of the related address graphs when none is requested.
+const DEFAULT_GRAPH_DEPTH: usize = 2;
+
+#[derive(OpenApi)]
+#[openapi(
+  paths(
+    query_arkham,
+    query_arkham_batch,
+    query_arkham_entity,
+    get_cache_stats,
+    query_arkham_transfers,
+    query_arkham_portfolio,
+    query_arkham_graph,
+    stream_arkham
+  ),
+  components(schemas(
+    AddressLookup,
+    PublicUserAddressNote,
+    ArkhamTransfers,
+    ArkhamTransfer,
+    ArkhamTransferAddress,
+    ArkhamPortfolio,
+    ArkhamChainPortfolio,
+    ArkhamHolding,
+    ArkhamCacheStats,
+    CacheStats,
+    ArkhamResponse,
+    ArkhamChainData,
+    AddressChain,
+    ArkhamEntity,
+    ArkhamEntityDetail,
+    ArkhamTag,
+    ArkhamLabel,
+    BatchEntry,
+    AddressGraph,
+    GraphNode,
+    GraphEdge,
+    NodeKind,
+    Relation
+  ))
+)]
+pub struct ApiDoc;
+
+pub fn create_route() -> RouteTable<AppState> {
+  RouteTable::new()
+    .post("/arkham/batch", query_arkham_batch)
+    .get("/arkham/cache/stats", get_cache_stats)
+    .get("/arkham/entity/:id", query_arkham_entity)
+    .get("/arkham/:address", query_arkham)
+    .get("/arkham/:address/transfers", query_arkham_transfers)
+    .get("/arkham/:address/portfolio", query_arkham_portfolio)
+    .get("/arkham/:address/graph", query_arkham_graph)
+    .get("/arkham/:address/stream", stream_arkham)
+}
+
+/// Arkham data of an address. Anonymous lookups are the public tier, rate
+/// limited by IP and reduced to who the address belongs to. Authenticated
+/// users get the full data and their own note on it, see
+/// `PUT /address-notes/:address`.
+#[utoipa::path(
+  get,
+  path = "/v1/arkham/{address}",
+  params(
+    ("address" = String, Path, description = "EVM, Solana or Bitcoin address, or ENS name, e.g. `vitalik.eth`"),
+    ArkhamQuery
+  ),
+  responses(
+    (
+      status = 200,
+      description = "Arkham intelligence for the address. Without authentication, only the entity name, id and type, the label name and chain type, and the contract flag are set",
+      body = AddressLookup,
+      headers(
+        ("cache-control" = String, description = "Caching policy aligned with the server cache, private for authenticated users"),
+        ("x-ratelimit-limit" = u32, description = "Requests allowed in a burst, lower for anonymous clients, which are limited by IP"),
+        ("last-modified" = String, description = "When the data was fetched from Arkham"),
+        ("x-cache" = String, description = "HIT when served from the server cache, STALE when Arkham is unavailable and expired data is served, MISS otherwise")
+      )
+    ),
+    (status = 400, description = "Invalid address or ENS name", body = ErrorResponse),
+    (status = 404, description = "ENS name does not resolve to an address", body = ErrorResponse),
+    (status = 429, description = "Rate limit exceeded", body = ErrorResponse),
+    (status = 503, description = "Arkham or ENS request failed", body = ErrorResponse),
+    (status = 502, description = "Arkham returned an invalid response", body = ErrorResponse),
+    (status = 504, description = "Arkham request timed out", body = ErrorResponse)
+  )
+)]
+async fn query_arkham(
+  State(state): State<AppState>,
+  user: Option<TokenUser>,
+  Path(address): Path<String>,
+  Query(query): Query<ArkhamQuery>,
+) -> Result<impl IntoResponse, Error> {
+  let (address, ens_name) = resolve_address(&state, &address, query.chain).await?;
+  let (cache_status, entry) = lookup_address(&state, &address, query.fresh).await?;
+  let user_note = find_note(user.as_ref(), &address).await?;
+  tokio::spawn(stats::record_address_query(
+    user.as_ref().map(|user| user.id),
+    address.clone(),
+  ));
+
+  // Let clients and CDNs cache the response for as long as we do. Responses
+  // of authenticated users may carry their private note.
+  let visibility = if user.is_some() { "private" } else { "public" };
+  let cache_control = format!(
+    "{}, max-age={}",
+    visibility,
+    entry.remaining_ttl().as_secs()
+  );
+  let headers = [
+    (
+      header::CACHE_CONTROL,
+      HeaderValue::from_str(&cache_control).unwrap(),
+    ),
+    (
+      header::LAST_MODIFIED,
+      HeaderValue::from_str(&date::to_http_date(entry.cached_at)).unwrap(),
+    ),
+    (
+      HeaderName::from_static("x-cache"),
+      HeaderValue::from_static(cache_status.as_str()),
+    ),
+  ];
+
+  let data = match user {
+    Some(_) => entry.value,
+    None => entry.value.summary(),
+  };
+  let res = AddressLookup {
+    address,
+    ens_name,
+    user_note,
+    chains: data.chain_names(),
+    data,
+  };
+
+  Ok((headers, Json(res)))
+}
+
+#[utoipa::path(
+  get,
+  path = "/v1/arkham/{address}/transfers",
+  params(
+    ("address" = String, Path, description = "EVM, Solana or Bitcoin address"),
+    ChainQuery,
+    TransfersQuery,
+    RawQuery
+  ),
+  responses(
+    (status = 200, description = "Transfers sent or received by the address. Arkham's own response with `raw`", body = ArkhamTransfers),
+    (status = 400, description = "Invalid address or query parameters", body = ErrorResponse),
+    (status = 503, description = "Arkham request failed", body = ErrorResponse),
+    (status = 502, description = "Arkham returned an invalid response", body = ErrorResponse),
+    (status = 504, description = "Arkham request timed out", body = ErrorResponse)
+  )
+)]
+async fn query_arkham_transfers(
+  State(state): State<AppState>,
+  ChainAddress { address, .. }: ChainAddress,
+  Query(query): Query<TransfersQuery>,
+  Query(raw): Query<RawQuery>,
+) -> Result<Response, Error> {
+  if let (Some(time_gte), Some(time_lte)) = (query.time_gte, query.time_lte) {
+    if time_gte > time_lte {
+      debug!("Invalid transfers time range, returning 400 status code");
+      return Err(Error::bad_request());
+    }
+  }
+
+  let query = TransfersQuery {
+    // Same bounds as the pagination of the API's own collections.
+    limit: query
+      .limit
+      .map(|limit| limit.clamp(1, state.settings.pagination.max_limit)),
+    ..query
+  };
+
+  // Only Arkham has a response to pass through, the other providers answer
+  // with the usual JSON.
+  if raw.raw && state.settings.arkham.provider == IntelligenceBackend::Arkham {
+    let res = state.arkham.stream_transfers(&address, &query).await?;
+    debug!("Streaming Arkham transfers");
+    return Ok(proxy::stream_response(
+      "Arkham",
+      res,
+      state.settings.proxy.chunk_bytes,
+    ));
+  }
+
+  let transfers = state
+    .address_intelligence
+    .transfers(&address, &query)
     .await?;
+  Ok(Json(transfers).into_response())
+}
+
+/// Tokens held by an address on each chain, with their USD value. Cached
+/// like the lookups of the address.
+#[utoipa::path(
+  get,
+  path = "/v1/arkham/{address}/portfolio",
+  params(
+    ("address" = String, Path, description = "EVM, Solana or Bitcoin address"),
+    ChainQuery
+  ),
+  responses(
+    (status = 200, description = "Holdings of the address by chain, largest first", body = ArkhamPortfolio),
+    (status = 400, description = "Invalid address", body = ErrorResponse),
+    (status = 503, description = "Arkham request failed", body = ErrorResponse),
+    (status = 502, description = "Arkham returned an invalid response", body = ErrorResponse),
+    (status = 504, description = "Arkham request timed out", body = ErrorResponse)
+  )
+)]
+async fn query_arkham_portfolio(
+  State(state): State<AppState>,
+  ChainAddress { address, .. }: ChainAddress,
+) -> Result<Json<ArkhamPortfolio>, Error> {
+  if let Some(entry) = state.portfolio_cache.get(&address).await {
+    debug!("Returning cached Arkham portfolio: {}", &address);
+    return Ok(Json(entry.value));
+  }
+
+  let portfolio = state.address_intelligence.portfolio(&address).await?;
+  let entry = state.portfolio_cache.insert(address, portfolio).await;
+
+  Ok(Json(entry.value))
+}
+
+/// Graph of the addresses related to an address, through its Arkham entity
+/// and its public labels, for visualization. Authenticated users can send
+/// `Prefer: respond-async` to have deep graphs built by a job instead, whose
+/// result is the graph.
+#[utoipa::path(
+  get,
+  path = "/v1/arkham/{address}/graph",
+  params(
+    ("address" = String, Path, description = "EVM address"),
+    ("Prefer" = Option<String>, Header, description = "`respond-async` to build the graph in a job"),
+    GraphQuery
+  ),
+  responses(
+    (status = 200, description = "Nodes and edges of the related addresses", body = AddressGraph),
+    (status = 202, description = "Graph queued, its job is polled at the `Location` URL", body = PublicJob),
+    (status = 400, description = "Invalid address or depth", body = ErrorResponse),
+    (status = 401, description = "Asynchronous request without a valid authentication token", body = ErrorResponse),
+    (status = 404, description = "Address graphs disabled", body = ErrorResponse),
+    (status = 503, description = "Arkham request failed", body = ErrorResponse),
+    (status = 502, description = "Arkham returned an invalid response", body = ErrorResponse),
+    (status = 504, description = "Arkham request timed out", body = ErrorResponse)
+  )
+)]
+async fn query_arkham_graph(
+  State(state): State<AppState>,
+  user: Option<TokenUser>,
+  _feature: RequireFeature<AddressGraphFeature>,
+  headers: HeaderMap,
+  EvmAddress(address): EvmAddress,
+  Query(query): Query<GraphQuery>,
+) -> Result<Response, Error> {
+  let depth = query.depth.unwrap_or(DEFAULT_GRAPH_DEPTH);
+  if depth < 1 || depth > state.settings.arkham.graph_max_depth {
+    debug!("Invalid graph depth, returning 400 status code");
+    return Err(Error::bad_request());
+  }
+
+  if respond_async(&headers) {
+    // Jobs are only visible to the user who queued them.
+    let user = user.ok_or(AuthenticateError::InvalidToken)?;
+    let payload = GraphExpansion { address, depth };
+    let job = jobs::enqueue(user.id, JobKind::GraphExpansion, &payload).await?;
+
+    debug!("Queued graph expansion");
+    return Ok(accepted(job));
+  }
+
+  let graph = graph::build_graph(&state, &address, depth).await?;
+  Ok(Json(graph).into_response())
+}
+
+/// Streams the Arkham data of an address as Server-Sent Events, for clients
+/// that can't use WebSockets. A `snapshot` event is sent first, then a
+/// `change` event whenever a refresh or the watcher finds different data.
+#[utoipa::path(
+  get,
+  path = "/v1/arkham/{address}/stream",
+  params(
+    ("address" = String, Path, description = "EVM, Solana or Bitcoin address"),
+    ChainQuery
+  ),
+  responses(
+    (status = 200, description = "Event stream of the Arkham data of the address", content_type = "text/event-stream", body = ArkhamResponse),
+    (status = 400, description = "Invalid address", body = ErrorResponse),
+    (status = 503, description = "Arkham request failed", body = ErrorResponse),
+    (status = 502, description = "Arkham returned an invalid response", body = ErrorResponse),
+    (status = 504, description = "Arkham request timed out", body = ErrorResponse)
+  )
+)]
+async fn stream_arkham(
+  State(state): State<AppState>,
+  ChainAddress { address, .. }: ChainAddress,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, Error> {
+  // Subscribed before the first lookup, so no change is missed.
+  let changes = state.address_changes.subscribe();
+  // The first lookup happens before streaming, so its failures are returned
+  // as regular error responses.
+  let (_, entry) = lookup_address(&state, &address, false).await?;
+
+  let period = Duration::from_secs(state.settings.arkham.stream_refresh_secs);
+  let keep_alive = Duration::from_secs(state.settings.arkham.stream_keep_alive_secs);
+  let snapshot = address_stream_event("snapshot", &entry.value);
+  let address_stream = AddressStream {
+    state,
+    address,
+    ticks: interval_at(Instant::now() + period, period),
+    changes,
+    last: Some(entry.value),
+  };
+  let updates = stream::unfold(address_stream, |mut address_stream| async move {
+    let event = address_stream.next_event().await;
+    Some((Ok::<_, Infallible>(event), address_stream))
+  });
+  let events = stream::once(future::ready(Ok(snapshot))).chain(updates);
+
+  Ok(Sse::new(events).keep_alive(KeepAlive::new().interval(keep_alive)))
+}
+
+#[utoipa::path(
+  post,
+  path = "/v1/arkham/batch",
+  request_body = Vec<String>,
+  responses(
+    (status = 200, description = "Lookup result by address", body = HashMap<String, BatchEntry>),
+    (status = 400, description = "Empty or oversized batch, or malformed body", body = ErrorResponse),
+    (status = 422, description = "Body is not an array of addresses", body = ErrorResponse)
+  )
+)]
+async fn query_arkham_batch(
+  State(state): State<AppState>,
+  Json(addresses): Json<Vec<String>>,
+) -> Result<Json<HashMap<String, BatchEntry>>, Error> {
+  if addresses.is_empty() || addresses.len() > state.settings.arkham.batch_max_addresses {
+    debug!(
+      "Batch size {} out of bounds, returning 400 status code",
+      addresses.len()
+    );
+    return Err(Error::bad_request());
+  }
+
+  let addresses = addresses.into_iter().collect::<HashSet<String>>();
+  info!("Querying arkham with {} addresses", addresses.len());
+
+  let state = &state;
+  let lookups = addresses.into_iter().map(|address| async move {
+    let result = match normalize_address(&address, None) {
+      Ok(normalized) => {
+        // The semaphore is never closed, so acquiring a permit can't fail.
+        let _permit = state.batch_permits.acquire().await.unwrap();
+        lookup_address(state, &normalized, false)
+          .await
+          .map(|(_, entry)| entry.value)
+      }
+      Err(err) => Err(err),
+    };
+    (address, BatchEntry::from(result))
+  });
+
+  let entries = future::join_all(lookups).await;
+  Ok(Json(entries.into_iter().collect()))
+}
+
+#[utoipa::path(
+  get,
+  path = "/v1/arkham/entity/{id}",
+  params(("id" = String, Path, description = "Arkham entity id")),
+  responses(
+    (status = 200, description = "Arkham entity", body = ArkhamEntityDetail),
+    (status = 400, description = "Invalid entity id", body = ErrorResponse),
+    (status = 404, description = "Entity not found", body = ErrorResponse),
+    (status = 503, description = "Arkham request failed", body = ErrorResponse),
+    (status = 502, description = "Arkham returned an invalid response", body = ErrorResponse),
+    (status = 504, description = "Arkham request timed out", body = ErrorResponse)
+  )
+)]
+async fn query_arkham_entity(
+  State(state): State<AppState>,
+  Path(id): Path<String>,
+) -> Result<Json<ArkhamEntityDetail>, Error> {
+  // Entity ids are slugs, e.g. `binance` or `jump-trading`.
+  let is_valid_id = !id.is_empty()
+    && id
+      .chars()
+      .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+  if !is_valid_id {
+    debug!("Invalid entity id, returning 400 status code");
+    return Err(Error::bad_request());
+  }
+
+  if let Some(entry) = state.entity_cache.get(&id).await {
+    debug!("Returning cached Arkham entity: {}", &id);
+    return Ok(Json(entry.value));
+  }
+
+  let entity = state.address_intelligence.lookup_entity(&id).await?;
+  let entry = state.entity_cache.insert(id, entity).await;
+
+  Ok(Json(entry.value))
+}
+
+#[utoipa::path(
+  get,
+  path = "/v1/arkham/cache/stats",
+  responses(
+    (status = 200, description = "Hit and miss counters of the Arkham caches", body = ArkhamCacheStats)
+  )
+)]
+async fn get_cache_stats(State(state): State<AppState>) -> Json<ArkhamCacheStats> {
+  Json(ArkhamCacheStats {
+    address: state.address_cache.stats(),
+    entity: state.entity_cache.stats(),
+    portfolio: state.portfolio_cache.stats(),
+  })
+}
+
+/// How a lookup was served, sent in the `x-cache` header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheStatus {
+  Hit,
+  Miss,
+  Stale,
+}
+
+impl CacheStatus {
+  fn as_str(&self) -> &'static str {
+    match self {
+      CacheStatus::Hit => "HIT",
+      CacheStatus::Miss => "MISS",
+      CacheStatus::Stale => "STALE",
+    }
+  }
+}
+
+/// Resolves a lookup input, either an EVM, Solana or Bitcoin address or an
+/// ENS name, to a normalized address and its ENS name. The primary name of
+/// addresses is only a nice to have, so failing to look it up doesn't fail
+/// the request.
+async fn resolve_address(
+  state: &AppState,
+  input: &str,
+  chain: Option<AddressChain>,
+) -> Result<(String, Option<String>), Error> {
+  if chain.is_none() && ens::is_name(input) {
+    let name = ens::normalize_name(input)?;
+    let address = state.ens.resolve(&name).await?;
+    return Ok((address, Some(name)));
+  }
+
+  let address = normalize_address(input, chain)?;
+  // ENS only names EVM addresses.
+  if !address.starts_with("0x") {
+    return Ok((address, None));
+  }
+
+  let ens_name = match state.ens.lookup_name(&address).await {
+    Ok(name) => name,
+    Err(err) => {
+      warn!("Failed to look up the ENS name of {}: {}", address, err);
+      None
+    }
+  };
+
+  Ok((address, ens_name))
+}
+
+/// Looks up an address in the cache and then in the address intelligence
+/// providers. `fresh` skips the cache read, the result is still cached.
+pub async fn lookup_address(
+  state: &AppState,
+  address: &str,
+  fresh: bool,
+) -> Result<(CacheStatus, CacheEntry<ArkhamResponse>), Error> {
+  let cache = &state.address_cache;
+  if !fresh {
+    if let Some(entry) = cache.get(address).await {
+      debug!("Returning cached Arkham data for address: {}", address);
+      return Ok((CacheStatus::Hit, entry));
+    }
+  }
+
+  let arkham_data = state.address_intelligence.lookup_address(address).await?;
+
+  // Providers that fetch fresh data record it in the cache. When the entry is
+  // still expired, the data was served by the cached fallback.
+  let entry = match cache.get_stale(address).await {
+    Some(entry) if entry.is_expired() => return Ok((CacheStatus::Stale, entry)),
+    Some(entry) => entry,
+    None => cache.insert(address, arkham_data).await,
+  };
+
+  Ok((CacheStatus::Miss, entry))
+}
+
+/// Parses the upstream payload one chain at a time, so a malformed chain is
+/// left out instead of discarding the chains that parsed fine. Every chain
+/// is kept, including the ones Arkham adds over time.
+pub fn parse_arkham_response(payload: Value) -> Result<ArkhamResponse, Error> {
+  let chains = match payload {
+    Value::Object(chains) => chains,
+    _ => {
+      error!("Received an Arkham response that is not an object");
+      return Err(Error::UpstreamInvalidResponse(
+        "expected a JSON object".to_owned(),
+      ));
+    }
+  };
+
+  let chains = chains
+    .into_iter()
+    // Chains the address is unknown on may be sent as `null`.
+    .filter(|(_, data)| !data.is_null())
+    .filter_map(|(chain, data)| match serde_json::from_value(data) {
+      Ok(data) => Some((chain, data)),
+      Err(err) => {
+        warn!("Failed to parse Arkham {} chain data: {}", chain, err);
+        None
+      }
+    })
+    .collect();
+
+  Ok(ArkhamResponse { chains })
+}
+
+/// Parses the payload of the Arkham balances endpoint, holdings by chain
+/// name. As with the lookups, a malformed chain is left out instead of
+/// discarding the chains that parsed fine.
+pub fn parse_arkham_portfolio(payload: Value) -> Result<ArkhamPortfolio, Error> {
+  let balances = match payload.get("balances") {
+    Some(Value::Object(balances)) => balances.clone(),
+    _ => {
+      error!("Received an Arkham portfolio without balances");
+      return Err(Error::UpstreamInvalidResponse(
+        "expected a balances object".to_owned(),
+      ));
+    }
+  };
+
+  let chains = balances
+    .into_iter()
+    // Chains without holdings may be sent as `null`.
+    .filter(|(_, holdings)| !holdings.is_null())
+    .filter_map(
+      |(chain, holdings)| match serde_json::from_value::<Vec<ArkhamHolding>>(holdings) {
+        Ok(holdings) => Some((chain, ArkhamChainPortfolio::new(holdings))),
+        Err(err) => {
+          warn!("Failed to parse Arkham {} chain holdings: {}", chain, err);
+          None
+        }
+      },
+    )
+    .filter(|(_, portfolio)| !portfolio.holdings.is_empty())
+    .collect();
+
+  Ok(ArkhamPortfolio::new(chains))
+}
+
+/// State of an address event stream.
+struct AddressStream {
+  state: AppState,
+  address: String,
+  ticks: Interval,
+  changes: broadcast::Receiver<AddressChange>,
+  // Last data sent to the client.
+  last: Option<ArkhamResponse>,
+}
+
+impl AddressStream {
+  /// Waits for the next refresh or watcher change with different data.
+  /// Failed refreshes are sent as `error` events, the next refresh may work.
+  async fn next_event(&mut self) -> Event {
+    loop {
+      let data = tokio::select! {
+        _ = self.ticks.tick() => match lookup_address(&self.state, &self.address, false).await {
+          Ok((_, entry)) => entry.value,
+          Err(err) => {
+            warn!("Failed to refresh streamed address {}: {}", self.address, err);
+            return address_stream_event("error", &json!({ "message": err.to_string() }));
+          }
+        },
+        change = self.changes.recv() => match change {
+          Ok(change) if change.address == self.address => change.current,
+          // Missed changes are picked up by the next refresh.
+          Ok(_) | Err(RecvError::Lagged(_)) => continue,
+          // The sender lives in the state held by this stream.
+          Err(RecvError::Closed) => unreachable!("Address changes channel closed"),
+        },
+      };
+
+      if self.last.as_ref() == Some(&data) {
+        continue;
+      }
+
+      let event = address_stream_event("change", &data);
+      self.last = Some(data);
+      return event;
+    }
+  }
+}
+
+fn address_stream_event<T: Serialize>(name: &str, data: &T) -> Event {
+  // Arkham data and error messages always serialize to JSON.
+  Event::default().event(name).json_data(data).unwrap()
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct ArkhamQuery {
+  /// Bypass the server cache and query Arkham.
+  #[serde(default)]
+  fresh: bool,
+  /// Chain of the address, detected from its format by default.
+  chain: Option<AddressChain>,
+}
+
+/// Query parameters of the transfers route, sent as is to Arkham. Times are
+/// unix timestamps in milliseconds.
+#[derive(Serialize, Deserialize, Debug, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TransfersQuery {
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub limit: Option<u64>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub offset: Option<u64>,
+  #[serde(rename(serialize = "timeGte"), skip_serializing_if = "Option::is_none")]
+  pub time_gte: Option<i64>,
+  #[serde(rename(serialize = "timeLte"), skip_serializing_if = "Option::is_none")]
+  pub time_lte: Option<i64>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct GraphQuery {
+  /// Number of expansions from the address, 2 by default.
+  depth: Option<usize>,
+}
+
+/// Arkham data of an address, along with the ENS name it was looked up
+/// with, or else its primary ENS name. Authenticated users also get their
+/// own note on the address, which doesn't come from Arkham.
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct AddressLookup {
+  #[serde(serialize_with = "serialize_checksum_address")]
+  pub address: String,
+  pub ens_name: Option<String>,
+  // User-supplied, see `UserAddressNote`. Omitted when the user has none.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub user_note: Option<PublicUserAddressNote>,
+  // Names of the chains in `data`, sorted.
+  #[serde(default)]
+  pub chains: Vec<String>,
+  #[serde(flatten)]
+  pub data: ArkhamResponse,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct ArkhamCacheStats {
+  pub address: CacheStats,
+  pub entity: CacheStats,
+  pub portfolio: CacheStats,
+}
+
+/// Result of a single address lookup in a batch request. Failed lookups are
+/// reported per address instead of failing the whole batch.
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct BatchEntry {
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub data: Option<ArkhamResponse>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub error: Option<ErrorResponse>,
+}
+
+impl From<Result<ArkhamResponse, Error>> for BatchEntry {
+  fn from(result: Result<ArkhamResponse, Error>) -> Self {
+    match result {
+      Ok(data) => Self {
+        data: Some(data),
+        error: None,
+      },
+      Err(err) => Self {
+        data: None,
+        error: Some(ErrorResponse::from(&err)),
+      },
+    }
+  }
+}
+
+/// Arkham data of an address by chain name, e.g. `ethereum` or `base`.
+/// Chains the address is unknown on are left out, and chains Arkham adds
+/// are kept without any change here.
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+#[serde(transparent)]
+pub struct ArkhamResponse {
+  chains: BTreeMap<String, ArkhamChainData>,
+}
 
-  debug!("Received response with status: {}", res.status());
-
-  if res.status().is_success() {
-    let arkham_data: ArkhamResponse = res.json().await?;
-    info!("Successfully retrieved Arkham data");
-    Ok(Json(arkham_data))
-  } else {
-    let status = res.status();
-    let body = res
-      .text()
-      .await
-      .unwrap_or_else(|_| String::from("Could not retrieve response body"));
-    error!("Received a {} error: {}", status, body);
-    Err(Error::General(format!(
-      "Received a {} error: {}",
-      status, body
-    )))
-  }
-}
-
-#[derive(Serialize, Deserialize, Debug)]
-struct ArkhamResponse {
-  #[serde(rename = "bsc")]
-  bsc: ArkhamChainData,
-  #[serde(rename = "ethereum")]
-  ethereum: ArkhamChainData,
-  #[serde(rename = "polygon")]
-  polygon: ArkhamChainData,
-  #[serde(rename = "arbitrum_one")]
-  arbitrum_one: ArkhamChainData,
-  #[serde(rename = "avalanche")]
-  avalanche: ArkhamChainData,
-  #[serde(rename = "optimism")]
-  optimism: ArkhamChainData,
-}
-
-#[derive(Serialize, Deserialize, Debug)]
-struct ArkhamChainData {
+impl<'de> Deserialize<'de> for ArkhamResponse {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    // Missing chains used to be stored as `null`, e.g. in snapshots.
+    let chains = BTreeMap::<String, Option<ArkhamChainData>>::deserialize(deserializer)?;
+    let chains = chains
+      .into_iter()
+      .filter_map(|(chain, data)| data.map(|data| (chain, data)))
+      .collect();
+
+    Ok(Self { chains })
+  }
+}
+
+impl<'s> ToSchema<'s> for ArkhamResponse {
+  fn schema() -> (&'s str, RefOr<Schema>) {
+    let chain_data = RefOr::Ref(Ref::from_schema_name("ArkhamChainData"));
+    let schema = ObjectBuilder::new()
+      .description(Some("Arkham data of the address by chain name"))
+      .additional_properties(Some(chain_data))
+      .build();
+
+    ("ArkhamResponse", RefOr::T(Schema::Object(schema)))
+  }
+}
+
+impl ArkhamResponse {
+  /// Data of the chains the address is known on, by chain name.
+  pub fn chains(&self) -> Vec<(&str, &ArkhamChainData)> {
+    self
+      .chains
+      .iter()
+      .map(|(chain, data)| (chain.as_str(), data))
+      .collect()
+  }
+
+  pub fn chain(&self, chain: &str) -> Option<&ArkhamChainData> {
+    self.chains.get(chain)
+  }
+
+  /// Names of the chains the address is known on, sorted.
+  pub fn chain_names(&self) -> Vec<String> {
+    self.chains.keys().cloned().collect()
+  }
+
+  /// Reduced detail of the public tier, see `ArkhamChainData::summary`.
+  pub fn summary(self) -> Self {
+    let chains = self
+      .chains
+      .into_iter()
+      .map(|(chain, data)| (chain, data.summary()))
+      .collect();
+
+    Self { chains }
+  }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct ArkhamChainData {
   address: Option<String>,
   chain: Option<String>,
   #[serde(rename = "arkhamEntity")]
@@ -70,9 +811,74 @@ struct ArkhamChainData {
   #[serde(rename = "isUserAddress")]
   is_user_address: Option<bool>,
   contract: Option<bool>,
+  // Arkham sends amounts either as numbers or as numeric strings.
+  #[serde(default, deserialize_with = "deserialize_optional_number")]
+  balance: Option<f64>,
+  #[serde(
+    default,
+    alias = "usdValue",
+    deserialize_with = "deserialize_optional_number"
+  )]
+  usd_value: Option<f64>,
+}
+
+impl ArkhamChainData {
+  pub fn entity_id(&self) -> Option<&str> {
+    self.arkham_entity.as_ref()?.id.as_deref()
+  }
+
+  pub fn entity_name(&self) -> Option<&str> {
+    self.arkham_entity.as_ref()?.name.as_deref()
+  }
+
+  pub fn entity_type(&self) -> Option<&str> {
+    self.arkham_entity.as_ref()?.entity_type.as_deref()
+  }
+
+  pub fn label_name(&self) -> Option<&str> {
+    self.arkham_label.as_ref()?.name.as_deref()
+  }
+
+  pub fn is_contract(&self) -> bool {
+    self.contract == Some(true)
+  }
+
+  /// Who the address belongs to, without the balances and the entity
+  /// profile: the entity name, id and type, the label name and chain type,
+  /// and whether the address is a contract.
+  pub fn summary(self) -> Self {
+    let arkham_entity = self.arkham_entity.map(|entity| ArkhamEntity {
+      name: entity.name,
+      note: None,
+      id: entity.id,
+      entity_type: entity.entity_type,
+      service: None,
+      addresses: None,
+      website: None,
+      twitter: None,
+      crunchbase: None,
+      linkedin: None,
+    });
+    let arkham_label = self.arkham_label.map(|label| ArkhamLabel {
+      name: label.name,
+      address: None,
+      chain_type: label.chain_type,
+    });
+
+    Self {
+      address: self.address,
+      chain: self.chain,
+      arkham_entity,
+      arkham_label,
+      is_user_address: None,
+      contract: self.contract,
+      balance: None,
+      usd_value: None,
+    }
+  }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
 struct ArkhamEntity {
   name: Option<String>,
   note: Option<String>,
@@ -87,10 +893,218 @@ struct ArkhamEntity {
   linkedin: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
 struct ArkhamLabel {
   name: Option<String>,
   address: Option<String>,
   #[serde(rename = "chainType")]
   chain_type: Option<String>,
 }
+
+/// Entity profile returned by the Arkham entity endpoint.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct ArkhamEntityDetail {
+  pub id: Option<String>,
+  pub name: Option<String>,
+  note: Option<String>,
+  #[serde(rename = "type")]
+  entity_type: Option<String>,
+  service: Option<String>,
+  addresses: Option<Vec<String>>,
+  website: Option<String>,
+  twitter: Option<String>,
+  crunchbase: Option<String>,
+  linkedin: Option<String>,
+  #[serde(rename = "populatedTags")]
+  populated_tags: Option<Vec<ArkhamTag>>,
+}
+
+impl ArkhamEntityDetail {
+  pub fn addresses(&self) -> &[String] {
+    self.addresses.as_deref().unwrap_or_default()
+  }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+struct ArkhamTag {
+  id: Option<String>,
+  label: Option<String>,
+}
+
+/// Page of transfers returned by the Arkham transfers endpoint.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct ArkhamTransfers {
+  #[serde(default)]
+  pub transfers: Vec<ArkhamTransfer>,
+  pub count: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct ArkhamTransfer {
+  pub id: Option<String>,
+  #[serde(rename = "transactionHash")]
+  pub transaction_hash: Option<String>,
+  #[serde(rename = "fromAddress")]
+  from_address: Option<ArkhamTransferAddress>,
+  #[serde(rename = "toAddress")]
+  to_address: Option<ArkhamTransferAddress>,
+  #[serde(rename = "tokenAddress")]
+  token_address: Option<String>,
+  #[serde(rename = "tokenSymbol")]
+  token_symbol: Option<String>,
+  #[serde(rename = "blockTimestamp")]
+  block_timestamp: Option<String>,
+  #[serde(rename = "blockNumber")]
+  block_number: Option<u64>,
+  chain: Option<String>,
+  #[serde(
+    default,
+    rename = "unitValue",
+    deserialize_with = "deserialize_optional_number"
+  )]
+  unit_value: Option<f64>,
+  #[serde(
+    default,
+    rename = "historicalUSD",
+    deserialize_with = "deserialize_optional_number"
+  )]
+  historical_usd: Option<f64>,
+}
+
+/// Tokens held by an address by chain name. Chains without holdings are
+/// left out.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, ToSchema)]
+pub struct ArkhamPortfolio {
+  // Sum of the USD values of the holdings on every chain.
+  pub total_usd: f64,
+  pub chains: BTreeMap<String, ArkhamChainPortfolio>,
+}
+
+impl ArkhamPortfolio {
+  pub fn new(chains: BTreeMap<String, ArkhamChainPortfolio>) -> Self {
+    let total_usd = chains.values().map(|chain| chain.total_usd).sum();
+    Self { total_usd, chains }
+  }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct ArkhamChainPortfolio {
+  // Sum of the USD values of the holdings on the chain.
+  pub total_usd: f64,
+  // Largest USD value first.
+  pub holdings: Vec<ArkhamHolding>,
+}
+
+impl ArkhamChainPortfolio {
+  pub fn new(mut holdings: Vec<ArkhamHolding>) -> Self {
+    holdings.sort_by(|a, b| b.usd().total_cmp(&a.usd()));
+    let total_usd = holdings.iter().map(ArkhamHolding::usd).sum();
+    Self {
+      total_usd,
+      holdings,
+    }
+  }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct ArkhamHolding {
+  // Arkham token id, e.g. `ethereum` or `usd-coin`.
+  pub id: Option<String>,
+  pub name: Option<String>,
+  pub symbol: Option<String>,
+  // Contract of the token, none for the native token of the chain.
+  #[serde(default, alias = "tokenAddress", alias = "ethereumAddress")]
+  pub token_address: Option<String>,
+  // Arkham sends amounts either as numbers or as numeric strings.
+  #[serde(default, deserialize_with = "deserialize_optional_number")]
+  pub balance: Option<f64>,
+  #[serde(default, deserialize_with = "deserialize_optional_number")]
+  pub price: Option<f64>,
+  #[serde(
+    default,
+    alias = "usd",
+    deserialize_with = "deserialize_optional_number"
+  )]
+  pub usd_value: Option<f64>,
+}
+
+impl ArkhamHolding {
+  fn usd(&self) -> f64 {
+    self.usd_value.unwrap_or_default()
+  }
+}
+
+impl ArkhamTransfer {
+  pub fn chain(&self) -> Option<&str> {
+    self.chain.as_deref()
+  }
+
+  pub fn token_symbol(&self) -> Option<&str> {
+    self.token_symbol.as_deref()
+  }
+
+  /// RFC 3339 time of the block the transfer was made in.
+  pub fn block_timestamp(&self) -> Option<&str> {
+    self.block_timestamp.as_deref()
+  }
+
+  /// USD value of the transfer when it was made.
+  pub fn usd_value(&self) -> Option<f64> {
+    self.historical_usd
+  }
+
+  pub fn is_sent_by(&self, address: &str) -> bool {
+    self
+      .from_address
+      .as_ref()
+      .and_then(|from| from.address.as_deref())
+      .is_some_and(|from| from.eq_ignore_ascii_case(address))
+  }
+
+  /// Side of the transfer of `address`.
+  pub fn side(&self, address: &str) -> Option<&ArkhamTransferAddress> {
+    if self.is_sent_by(address) {
+      self.from_address.as_ref()
+    } else {
+      self.to_address.as_ref()
+    }
+  }
+
+  /// Other side of the transfer from `address`.
+  pub fn counterparty(&self, address: &str) -> Option<&ArkhamTransferAddress> {
+    if self.is_sent_by(address) {
+      self.to_address.as_ref()
+    } else {
+      self.from_address.as_ref()
+    }
+  }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct ArkhamTransferAddress {
+  address: Option<String>,
+  chain: Option<String>,
+  #[serde(rename = "arkhamEntity")]
+  arkham_entity: Option<ArkhamEntity>,
+  #[serde(rename = "arkhamLabel")]
+  arkham_label: Option<ArkhamLabel>,
+}
+
+impl ArkhamTransferAddress {
+  pub fn address(&self) -> Option<&str> {
+    self.address.as_deref()
+  }
+
+  pub fn entity_name(&self) -> Option<&str> {
+    self.arkham_entity.as_ref()?.name.as_deref()
+  }
+
+  /// Arkham label of the address, or else the name of its entity.
+  pub fn display_name(&self) -> Option<&str> {
+    let label = self
+      .arkham_label
+      .as_ref()
+      .and_then(|label| label.name.as_deref());
+    label.or_else(|| self.entity_name())
+  }
+}
@@ -0,0 +1,477 @@
+use axum::extract::State;
+use axum::http::StatusCode;
+use bson::oid::ObjectId;
+use bson::{doc, Bson};
+use serde::{Deserialize, Serialize};
+use siwe::VerificationOpts;
+use std::time::Duration;
+use tracing::{debug, error, warn};
+use utoipa::{OpenApi, ToSchema};
+use wither::mongodb::options::UpdateOptions;
+
+use crate::errors::{AuthenticateError, Error, ErrorResponse};
+use crate::models::refresh_token::RefreshToken;
+use crate::models::session::Session;
+use crate::models::siwe_nonce::SiweNonce;
+use crate::models::user::{PublicUser, User};
+use crate::routes::user::AuthenticateResponse;
+use crate::services::{account, mfa};
+use crate::settings::SETTINGS;
+use crate::state::AppState;
+use crate::utils::client::ClientInfo;
+use crate::utils::custom_response::{CustomResponse, CustomResponseBuilder};
+use crate::utils::date;
+use crate::utils::json::Json;
+use crate::utils::models::ModelExt;
+use crate::utils::route_table::RouteTable;
+use crate::utils::secret;
+use crate::utils::token;
+use crate::utils::token::TokenUser;
+
+#[derive(OpenApi)]
+#[openapi(
+  paths(
+    create_siwe_nonce,
+    verify_siwe,
+    refresh_token,
+    logout,
+    forgot_password,
+    reset_password,
+    verify_email,
+    resend_email_verification
+  ),
+  components(schemas(
+    NonceResponse,
+    RefreshBody,
+    VerifyBody,
+    ForgotPasswordBody,
+    ResetPasswordBody,
+    VerifyEmailBody
+  ))
+)]
+pub struct ApiDoc;
+
+// EIP-4361 requires at least 8 alphanumeric characters.
+const NONCE_LENGTH: usize = 17;
+
+pub fn create_route() -> RouteTable<AppState> {
+  RouteTable::new()
+    .post("/auth/siwe/nonce", create_siwe_nonce)
+    .post("/auth/siwe/verify", verify_siwe)
+    .post("/auth/refresh", refresh_token)
+    .post("/auth/logout", logout)
+    .post("/auth/forgot-password", forgot_password)
+    .post("/auth/reset-password", reset_password)
+    .post("/auth/verify-email", verify_email)
+    .post("/auth/verify-email/resend", resend_email_verification)
+}
+
+/// Issues the tokens of a user signing in, starting a new session and its
+/// refresh token family.
+pub async fn sign_in(user: User, client: &ClientInfo) -> Result<AuthenticateResponse, Error> {
+  issue_tokens(user, ObjectId::new(), client).await
+}
+
+/// Revokes a session: its refresh tokens are deleted and its access tokens
+/// rejected from now on.
+pub async fn revoke_session(family: ObjectId) -> Result<(), Error> {
+  RefreshToken::delete_many(doc! { "family": family }).await?;
+  Session::delete_one(doc! { "_id": family }).await?;
+
+  Ok(())
+}
+
+/// Revokes the sessions of the user but `current`, all of them without one.
+pub async fn revoke_other_sessions(user: ObjectId, current: Option<ObjectId>) -> Result<(), Error> {
+  // Refresh tokens first, so no session is started again from them.
+  RefreshToken::delete_many(doc! { "user": user, "family": { "$ne": current } }).await?;
+  Session::delete_many(doc! { "user": user, "_id": { "$ne": current } }).await?;
+
+  Ok(())
+}
+
+async fn issue_tokens(
+  user: User,
+  family: ObjectId,
+  client: &ClientInfo,
+) -> Result<AuthenticateResponse, Error> {
+  let user_id = user.id.unwrap();
+  let access_token = token::create(user.clone(), Some(family), SETTINGS.auth.secret.as_str())
+    .map_err(|_| Error::Authenticate(AuthenticateError::TokenCreation))?;
+
+  let ttl = Duration::from_secs(SETTINGS.auth.refresh_token_ttl_secs);
+  let expires_at = date::after(ttl);
+  let (model, refresh_token) = RefreshToken::generate(user_id, family, expires_at);
+  RefreshToken::create(model).await?;
+
+  // The session lives as long as its last refresh token. Families rotated
+  // from before sessions existed get one on their next refresh.
+  let now = date::now();
+  let options = UpdateOptions::builder().upsert(true).build();
+  Session::update_one(
+    doc! { "_id": family, "user": user_id },
+    doc! {
+      "$set": {
+        "device": client.device.clone(),
+        "ip": client.ip.map(|ip| ip.to_string()),
+        "last_used_at": now,
+        "expires_at": expires_at,
+      },
+      "$setOnInsert": { "created_at": now },
+    },
+    options,
+  )
+  .await?;
+
+  Ok(AuthenticateResponse {
+    access_token,
+    refresh_token,
+    user: PublicUser::from(user),
+  })
+}
+
+/// Exchanges a refresh token for a new access token and refresh token. Each
+/// refresh token can be used once, using it again revokes every token of its
+/// family.
+#[utoipa::path(
+  post,
+  path = "/v1/auth/refresh",
+  request_body = RefreshBody,
+  responses(
+    (status = 200, description = "Tokens refreshed", body = AuthenticateResponse),
+    (status = 401, description = "Invalid, expired or reused refresh token", body = ErrorResponse),
+    (status = 423, description = "User is locked", body = ErrorResponse)
+  )
+)]
+async fn refresh_token(
+  client: ClientInfo,
+  Json(body): Json<RefreshBody>,
+) -> Result<Json<AuthenticateResponse>, Error> {
+  let hash = secret::hash(&body.refresh_token);
+
+  // Marked as used atomically, so concurrent refreshes can't both succeed.
+  let token = RefreshToken::find_one_and_update(
+    doc! {
+      "hash": &hash,
+      "used_at": Bson::Null,
+      "expires_at": { "$gt": date::now() }
+    },
+    doc! { "$set": { "used_at": date::now() } },
+  )
+  .await?;
+
+  let token = match token {
+    Some(token) => token,
+    None => {
+      if let Some(reused) = RefreshToken::find_one(doc! { "hash": &hash }, None).await? {
+        if reused.used_at.is_some() {
+          warn!(
+            "Refresh token reused, revoking the tokens of user {}",
+            reused.user
+          );
+          revoke_session(reused.family).await?;
+        }
+      }
+
+      debug!("Invalid refresh token, returning 401 status code");
+      return Err(Error::Authenticate(AuthenticateError::InvalidToken));
+    }
+  };
+
+  let user = User::find_by_id(&token.user)
+    .await?
+    .ok_or(AuthenticateError::InvalidToken)?;
+  if user.locked_at.is_some() {
+    debug!("User is locked, returning 423 status code");
+    return Err(Error::Authenticate(AuthenticateError::Locked));
+  }
+
+  let res = issue_tokens(user, token.family, &client).await?;
+
+  debug!("Returning refreshed tokens");
+  Ok(Json(res))
+}
+
+/// Revokes the session of the refresh token: every token rotated from the
+/// same sign in, and the access tokens issued along them.
+#[utoipa::path(
+  post,
+  path = "/v1/auth/logout",
+  request_body = RefreshBody,
+  responses((status = 204, description = "Session revoked"))
+)]
+async fn logout(Json(body): Json<RefreshBody>) -> Result<CustomResponse<()>, Error> {
+  let hash = secret::hash(&body.refresh_token);
+  if let Some(token) = RefreshToken::find_one(doc! { "hash": hash }, None).await? {
+    revoke_session(token.family).await?;
+  }
+
+  let res = CustomResponseBuilder::new()
+    .status_code(StatusCode::NO_CONTENT)
+    .build();
+
+  Ok(res)
+}
+
+/// Emails a password reset link to the user with this email. The response
+/// is the same whether the email is registered or not.
+#[utoipa::path(
+  post,
+  path = "/v1/auth/forgot-password",
+  request_body = ForgotPasswordBody,
+  responses((status = 204, description = "Reset link emailed if the email is registered"))
+)]
+async fn forgot_password(
+  State(state): State<AppState>,
+  Json(body): Json<ForgotPasswordBody>,
+) -> Result<CustomResponse<()>, Error> {
+  match state.notifiers.email() {
+    // Sent in the background, so the response time doesn't tell whether the
+    // email is registered.
+    Some(notifier) => {
+      let notifier = notifier.clone();
+      tokio::spawn(async move {
+        let result =
+          account::request_password_reset(&body.email, &notifier, &state.settings.account).await;
+        if let Err(err) = result {
+          error!("Failed to send password reset email: {}", err);
+        }
+      });
+    }
+    None => warn!("No SMTP server configured, can't send password reset emails"),
+  }
+
+  let res = CustomResponseBuilder::new()
+    .status_code(StatusCode::NO_CONTENT)
+    .build();
+
+  Ok(res)
+}
+
+/// Sets a new password with the token of a password reset email. Every
+/// session of the user is revoked.
+#[utoipa::path(
+  post,
+  path = "/v1/auth/reset-password",
+  request_body = ResetPasswordBody,
+  responses(
+    (status = 204, description = "Password reset"),
+    (status = 400, description = "Missing password", body = ErrorResponse),
+    (status = 404, description = "Unknown, used or expired token", body = ErrorResponse)
+  )
+)]
+async fn reset_password(Json(body): Json<ResetPasswordBody>) -> Result<CustomResponse<()>, Error> {
+  if body.password.is_empty() {
+    debug!("Missing password, returning 400 status code");
+    return Err(Error::bad_request());
+  }
+
+  if !account::reset_password(&body.token, body.password).await? {
+    debug!("Invalid password reset token, returning 404 status code");
+    return Err(Error::not_found());
+  }
+
+  let res = CustomResponseBuilder::new()
+    .status_code(StatusCode::NO_CONTENT)
+    .build();
+
+  Ok(res)
+}
+
+/// Verifies the email of a user with the token of the email sent on signup.
+#[utoipa::path(
+  post,
+  path = "/v1/auth/verify-email",
+  request_body = VerifyEmailBody,
+  responses(
+    (status = 204, description = "Email verified"),
+    (status = 404, description = "Unknown, used or expired token", body = ErrorResponse)
+  )
+)]
+async fn verify_email(Json(body): Json<VerifyEmailBody>) -> Result<CustomResponse<()>, Error> {
+  if !account::verify_email(&body.token).await? {
+    debug!("Invalid email verification token, returning 404 status code");
+    return Err(Error::not_found());
+  }
+
+  let res = CustomResponseBuilder::new()
+    .status_code(StatusCode::NO_CONTENT)
+    .build();
+
+  Ok(res)
+}
+
+/// Emails a new verification link to the user, the previous one can't be
+/// used anymore.
+#[utoipa::path(
+  post,
+  path = "/v1/auth/verify-email/resend",
+  responses(
+    (status = 204, description = "Verification link emailed"),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 409, description = "Email already verified", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []), ("apiKey" = []))
+)]
+async fn resend_email_verification(
+  State(state): State<AppState>,
+  user: TokenUser,
+) -> Result<CustomResponse<()>, Error> {
+  let user = match User::find_by_id(&user.id).await? {
+    Some(user) if user.email_verified_at.is_some() || user.wallet.is_some() => {
+      // Wallet users have a placeholder email, there is nothing to verify.
+      debug!("Email already verified, returning 409 status code");
+      return Err(Error::conflict());
+    }
+    Some(user) => user,
+    None => return Err(Error::not_found()),
+  };
+
+  let notifier = state
+    .notifiers
+    .email()
+    .ok_or_else(|| Error::General("No SMTP server configured".to_owned()))?;
+  account::send_email_verification(&user, notifier, &state.settings.account).await?;
+
+  let res = CustomResponseBuilder::new()
+    .status_code(StatusCode::NO_CONTENT)
+    .build();
+
+  Ok(res)
+}
+
+/// Hands out a nonce for the client to include in the message it signs. Each
+/// nonce can be used once, until it expires.
+#[utoipa::path(
+  post,
+  path = "/v1/auth/siwe/nonce",
+  responses((status = 200, description = "Nonce to sign", body = NonceResponse))
+)]
+async fn create_siwe_nonce(State(state): State<AppState>) -> Result<Json<NonceResponse>, Error> {
+  let nonce = secret::generate(NONCE_LENGTH);
+  let ttl = Duration::from_secs(state.settings.siwe.nonce_ttl_secs);
+  SiweNonce::create(SiweNonce::new(nonce.clone(), date::after(ttl))).await?;
+
+  debug!("Returning Sign-In With Ethereum nonce");
+  Ok(Json(NonceResponse { nonce }))
+}
+
+/// Verifies a signed EIP-4361 message and authenticates its signer, creating
+/// a user for addresses signing in for the first time.
+#[utoipa::path(
+  post,
+  path = "/v1/auth/siwe/verify",
+  request_body = VerifyBody,
+  responses(
+    (status = 200, description = "Signer authenticated", body = AuthenticateResponse),
+    (status = 400, description = "Malformed message or signature", body = ErrorResponse),
+    (status = 401, description = "Invalid signature, domain, nonce or two-factor authentication code", body = ErrorResponse),
+    (status = 423, description = "User is locked", body = ErrorResponse)
+  )
+)]
+async fn verify_siwe(
+  State(state): State<AppState>,
+  client: ClientInfo,
+  Json(body): Json<VerifyBody>,
+) -> Result<Json<AuthenticateResponse>, Error> {
+  let message = body.message.parse::<siwe::Message>().map_err(|err| {
+    Error::MalformedPayload(format!("Invalid Sign-In With Ethereum message: {}", err))
+  })?;
+  let signature = body
+    .signature
+    .strip_prefix("0x")
+    .and_then(|signature| hex::decode(signature).ok())
+    .ok_or_else(|| Error::MalformedPayload("Invalid signature".to_owned()))?;
+
+  if message.domain.as_str() != state.settings.siwe.domain {
+    debug!("Message issued for another domain, returning 401 status code");
+    return Err(Error::Authenticate(AuthenticateError::WrongCredentials));
+  }
+
+  // Checks the signature and the validity period of the message.
+  if let Err(err) = message
+    .verify(&signature, &VerificationOpts::default())
+    .await
+  {
+    debug!(
+      "Invalid message signature ({}), returning 401 status code",
+      err
+    );
+    return Err(Error::Authenticate(AuthenticateError::WrongCredentials));
+  }
+
+  // Consumed only once the signature is valid, so a forged message can't
+  // burn the nonce of a legit client.
+  let nonce = SiweNonce::find_one_and_delete(doc! {
+    "nonce": &message.nonce,
+    "expires_at": { "$gt": date::now() }
+  })
+  .await?;
+  if nonce.is_none() {
+    debug!("Unknown or expired nonce, returning 401 status code");
+    return Err(Error::Authenticate(AuthenticateError::WrongCredentials));
+  }
+
+  let address = format!("0x{}", hex::encode(message.address));
+  let user = match User::find_one(doc! { "wallet": &address }, None).await? {
+    Some(user) => user,
+    None => User::create(User::new_wallet(&address)).await?,
+  };
+
+  if user.locked_at.is_some() {
+    debug!("User is locked, returning 423 status code");
+    return Err(Error::Authenticate(AuthenticateError::Locked));
+  }
+
+  mfa::verify_sign_in(&user, body.mfa_code.as_deref()).await?;
+
+  let res = sign_in(user, &client).await?;
+
+  debug!("Returning Sign-In With Ethereum token");
+  Ok(Json(res))
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct NonceResponse {
+  pub nonce: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[schema(as = RefreshTokens)]
+struct RefreshBody {
+  refresh_token: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[schema(as = VerifySiwe)]
+struct VerifyBody {
+  // EIP-4361 message, as signed by the wallet.
+  message: String,
+  // Hex encoded EIP-191 signature of the message.
+  signature: String,
+  // TOTP or recovery code, required when two-factor authentication is
+  // enabled. The nonce is used even when it is missing.
+  #[serde(default)]
+  mfa_code: Option<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[schema(as = ForgotPassword)]
+struct ForgotPasswordBody {
+  email: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[schema(as = ResetPassword)]
+struct ResetPasswordBody {
+  // Token of the password reset email.
+  token: String,
+  password: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[schema(as = VerifyEmail)]
+struct VerifyEmailBody {
+  // Token of the email verification email.
+  token: String,
+}
@@ -0,0 +1,56 @@
+use axum::extract::State;
+use chrono::Utc;
+use std::time::Instant;
+use tracing::debug;
+use utoipa::OpenApi;
+
+use crate::errors::{Error, ErrorResponse};
+use crate::services::balances::{AddressBalances, ChainBalances, TokenBalance};
+use crate::state::AppState;
+use crate::utils::address::{ChainQuery, WalletAddress};
+use crate::utils::custom_response::{CustomResponse, CustomResponseBuilder, ResponseMeta};
+use crate::utils::route_table::RouteTable;
+
+#[derive(OpenApi)]
+#[openapi(
+  paths(query_balances),
+  components(schemas(AddressBalances, ChainBalances, TokenBalance, ResponseMeta))
+)]
+pub struct ApiDoc;
+
+pub fn create_route() -> RouteTable<AppState> {
+  RouteTable::new().get("/balances/:address", query_balances)
+}
+
+#[utoipa::path(
+  get,
+  path = "/v1/balances/{address}",
+  params(
+    ("address" = String, Path, description = "EVM, Solana or Bitcoin address, or Bitcoin extended public key"),
+    ChainQuery
+  ),
+  responses(
+    (status = 200, description = "Native and token balances of the address by chain, failed chains carry their error and a warning. Sent with a `meta` object and a `warnings` array", body = AddressBalances),
+    (status = 400, description = "Invalid address", body = ErrorResponse)
+  )
+)]
+async fn query_balances(
+  State(state): State<AppState>,
+  WalletAddress { address, chain }: WalletAddress,
+) -> Result<CustomResponse<AddressBalances>, Error> {
+  let started_at = Instant::now();
+  let balances = state.balances.fetch_balances(&address, chain).await;
+  let meta = ResponseMeta {
+    upstream_latency_ms: Some(started_at.elapsed().as_millis() as u64),
+    fresh_at: Some(Utc::now()),
+    ..Default::default()
+  };
+
+  let mut res = CustomResponseBuilder::new().meta(meta);
+  for failed in balances.chains.iter().filter(|chain| chain.error.is_some()) {
+    res = res.warning(format!("{} balances unavailable", failed.chain));
+  }
+
+  debug!("Returning balances");
+  Ok(res.body(balances).build())
+}
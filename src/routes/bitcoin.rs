@@ -0,0 +1,94 @@
+use axum::extract::State;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+use utoipa::{IntoParams, OpenApi, ToSchema};
+
+use crate::errors::{Error, ErrorResponse};
+use crate::services::bitcoin::BitcoinTransaction;
+use crate::state::AppState;
+use crate::utils::address::{AddressChain, WalletAddress};
+use crate::utils::json::Json;
+use crate::utils::query::Query;
+use crate::utils::route_table::RouteTable;
+
+// Page size when no limit is requested.
+const DEFAULT_LIMIT: u64 = 25;
+
+#[derive(OpenApi)]
+#[openapi(
+  paths(query_bitcoin_transactions),
+  components(schemas(BitcoinTransactionPage, BitcoinTransaction))
+)]
+pub struct ApiDoc;
+
+pub fn create_route() -> RouteTable<AppState> {
+  RouteTable::new().get("/bitcoin/:address/transactions", query_bitcoin_transactions)
+}
+
+#[utoipa::path(
+  get,
+  path = "/v1/bitcoin/{address}/transactions",
+  params(
+    ("address" = String, Path, description = "Bitcoin address, or extended public key (`xpub`, `ypub` or `zpub`) for every address of a wallet"),
+    BitcoinTransactionsQuery
+  ),
+  responses(
+    (status = 200, description = "Page of the transactions of the address, newest first", body = BitcoinTransactionPage),
+    (status = 400, description = "Invalid address or page", body = ErrorResponse),
+    (status = 503, description = "Blockchair request failed", body = ErrorResponse),
+    (status = 502, description = "Blockchair returned an invalid response", body = ErrorResponse)
+  )
+)]
+async fn query_bitcoin_transactions(
+  State(state): State<AppState>,
+  WalletAddress { address, chain }: WalletAddress,
+  Query(query): Query<BitcoinTransactionsQuery>,
+) -> Result<Json<BitcoinTransactionPage>, Error> {
+  if chain != AddressChain::Bitcoin {
+    debug!("Not a Bitcoin address, returning 400 status code");
+    return Err(Error::InvalidAddress(address));
+  }
+
+  let page = query.page.unwrap_or(1);
+  let limit = query
+    .limit
+    .unwrap_or(DEFAULT_LIMIT)
+    .clamp(1, state.settings.pagination.max_limit);
+
+  if page == 0 {
+    debug!("Transactions page out of bounds, returning 400 status code");
+    return Err(Error::bad_request());
+  }
+
+  let offset = (page - 1).saturating_mul(limit);
+  let wallet = state.bitcoin.fetch_wallet(&address, limit, offset).await?;
+
+  debug!("Returning Bitcoin transactions");
+  Ok(Json(BitcoinTransactionPage {
+    has_next: offset.saturating_add(limit) < wallet.transaction_count,
+    address,
+    page,
+    limit,
+    transaction_count: wallet.transaction_count,
+    transactions: wallet.transactions,
+  }))
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct BitcoinTransactionsQuery {
+  /// Page number, starting at 1.
+  page: Option<u64>,
+  /// Number of transactions per page.
+  limit: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BitcoinTransactionPage {
+  pub address: String,
+  pub page: u64,
+  pub limit: u64,
+  pub has_next: bool,
+  pub transaction_count: u64,
+  pub transactions: Vec<BitcoinTransaction>,
+}
@@ -1,37 +1,145 @@
-use axum::http::StatusCode;
-use axum::{
-  extract::{Path, Query},
-  routing::{delete, get, post, put},
-  Json, Router,
-};
-use bson::doc;
+use axum::body::StreamBody;
+use axum::extract::{Multipart, OriginalUri, Path, State};
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use bson::oid::ObjectId;
+use bson::{doc, Bson, Document};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::time::Duration;
 use tracing::debug;
+use utoipa::{IntoParams, OpenApi, ToSchema};
+use validator::{Validate, ValidationError};
 use wither::mongodb::options::FindOptions;
 
-use crate::errors::Error;
-use crate::models::cat::{Cat, PublicCat};
+use crate::errors::{Error, ErrorResponse};
+use crate::models::attachment::{Attachment, PublicAttachment};
+use crate::models::cat::{
+  normalize_tags, Cat, PublicCat, CAT_FILTER_FIELDS, CAT_SORT_FIELDS, PUBLIC_CAT_FIELDS,
+};
+use crate::models::share::{PublicShare, ShareAccess, SharedResource};
+use crate::models::user::Role;
+use crate::services::gridfs;
+use crate::services::ownership::Ownership;
+use crate::services::sharing::{self, CreateShare};
+use crate::settings::SETTINGS;
+use crate::state::AppState;
 use crate::utils::custom_response::{CustomResponse, CustomResponseBuilder};
-use crate::utils::models::ModelExt;
-use crate::utils::pagination::Pagination;
-use crate::utils::request_query::RequestQuery;
+use crate::utils::fields::{Fields, Sparse};
+use crate::utils::json::{Json, ValidJson};
+use crate::utils::links::Link;
+use crate::utils::merge_patch;
+use crate::utils::models::{CountStrategy, ModelExt};
+use crate::utils::multipart::field_content;
+use crate::utils::ndjson::stream_ndjson;
+use crate::utils::pagination::{Cursor, Pagination};
+use crate::utils::query::Query;
+use crate::utils::query_fields::Filters;
+use crate::utils::request_query::{escape_regex, RequestQuery};
+use crate::utils::response_format::{FormatQuery, ResponseFormat};
+use crate::utils::route_table::RouteTable;
 use crate::utils::to_object_id::to_object_id;
-use crate::utils::token::TokenUser;
+use crate::utils::token::{MemberUser, TokenUser};
+use crate::utils::validation::{self, not_blank};
+use crate::utils::version::expected_version;
+
+#[derive(OpenApi)]
+#[openapi(
+  paths(
+    create_cat,
+    create_cats,
+    remove_cats,
+    query_cats,
+    export_cats,
+    search_cats,
+    query_cat_tags,
+    get_cat_by_id,
+    remove_cat_by_id,
+    restore_cat_by_id,
+    update_cat_by_id,
+    patch_cat_by_id,
+    add_cat_tags,
+    remove_cat_tag,
+    create_cat_share,
+    query_cat_shares,
+    remove_cat_share,
+    upload_cat_attachment,
+    download_cat_attachment,
+    remove_cat_attachment
+  ),
+  components(schemas(
+    PublicCat,
+    Link,
+    CreateCat,
+    UpdateCat,
+    CatPatch,
+    AddCatTags,
+    TagCount,
+    RemoveCats,
+    BulkCreateResult,
+    BulkRemoveResult,
+    PublicShare,
+    CreateShare,
+    SharedResource,
+    ShareAccess,
+    PublicAttachment,
+    AttachmentUpload
+  ))
+)]
+pub struct ApiDoc;
 
-pub fn create_route() -> Router {
-  Router::new()
-    .route("/cats", post(create_cat))
-    .route("/cats", get(query_cats))
-    .route("/cats/:id", get(get_cat_by_id))
-    .route("/cats/:id", delete(remove_cat_by_id))
-    .route("/cats/:id", put(update_cat_by_id))
+// Items accepted by each bulk request.
+const MAX_BULK_SIZE: usize = 100;
+
+pub fn create_route() -> RouteTable<AppState> {
+  RouteTable::new()
+    .post("/cats", create_cat)
+    .post("/cats/bulk", create_cats)
+    .delete("/cats/bulk", remove_cats)
+    .get("/cats", query_cats)
+    .get("/cats/export", export_cats)
+    .get("/cats/search", search_cats)
+    .get("/cats/tags", query_cat_tags)
+    .get("/cats/:id", get_cat_by_id)
+    .delete("/cats/:id", remove_cat_by_id)
+    .post("/cats/:id/restore", restore_cat_by_id)
+    .put("/cats/:id", update_cat_by_id)
+    .patch("/cats/:id", patch_cat_by_id)
+    .post("/cats/:id/tags", add_cat_tags)
+    .delete("/cats/:id/tags/:tag", remove_cat_tag)
+    .post("/cats/:id/shares", create_cat_share)
+    .get("/cats/:id/shares", query_cat_shares)
+    .delete("/cats/:id/shares/:user", remove_cat_share)
+    .post("/cats/:id/attachments", upload_cat_attachment)
+    .get("/cats/:id/attachments/:attachment", download_cat_attachment)
+    .delete("/cats/:id/attachments/:attachment", remove_cat_attachment)
 }
 
+#[utoipa::path(
+  post,
+  path = "/v1/cats",
+  params(
+    ("Idempotency-Key" = Option<String>, Header, description = "Key replaying the first response when the request is retried")
+  ),
+  request_body = CreateCat,
+  responses(
+    (status = 201, description = "Cat created", body = PublicCat),
+    (status = 400, description = "Invalid organization id", body = ErrorResponse),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 403, description = "User can't change the organization resources", body = ErrorResponse),
+    (status = 404, description = "Organization not found", body = ErrorResponse),
+    (status = 409, description = "Request with the same idempotency key still running", body = ErrorResponse),
+    (status = 422, description = "Invalid request body", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []))
+)]
 async fn create_cat(
-  user: TokenUser,
-  Json(payload): Json<CreateCat>,
+  MemberUser(user): MemberUser,
+  ValidJson(payload): ValidJson<CreateCat>,
 ) -> Result<CustomResponse<PublicCat>, Error> {
-  let cat = Cat::new(user.id, payload.name);
+  let ownership = Ownership::load(user.id).await?;
+  let cat = new_cat(&user, &ownership, payload)?;
   let cat = Cat::create(cat).await?;
   let res = PublicCat::from(cat);
 
@@ -43,33 +151,382 @@ async fn create_cat(
   Ok(res)
 }
 
+/// Creates several cats in a single insert. Items are checked one by one,
+/// the results are returned in the order of the payload with the status the
+/// single cat route would have returned.
+#[utoipa::path(
+  post,
+  path = "/v1/cats/bulk",
+  request_body = [CreateCat],
+  responses(
+    (status = 200, description = "Result of each item", body = [BulkCreateResult]),
+    (status = 400, description = "Empty or oversized payload", body = ErrorResponse),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 422, description = "Invalid request body", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []))
+)]
+async fn create_cats(
+  MemberUser(user): MemberUser,
+  Json(payload): Json<Vec<CreateCat>>,
+) -> Result<Json<Vec<BulkCreateResult>>, Error> {
+  check_bulk_size(payload.len())?;
+
+  let ownership = Ownership::load(user.id).await?;
+  let mut results = Vec::with_capacity(payload.len());
+  let mut cats = Vec::new();
+  for item in payload {
+    let cat = item
+      .validate()
+      .map_err(Error::from)
+      .and_then(|()| new_cat(&user, &ownership, item));
+    match cat {
+      Ok(cat) => {
+        cats.push(cat);
+        results.push(None);
+      }
+      Err(err) => results.push(Some(BulkCreateResult::error(&err))),
+    }
+  }
+
+  let mut created = Cat::create_many(cats).await?.into_iter();
+  let results = results
+    .into_iter()
+    .map(|result| {
+      result.unwrap_or_else(|| BulkCreateResult {
+        status: StatusCode::CREATED.as_u16(),
+        cat: created.next().map(PublicCat::from),
+        error: None,
+      })
+    })
+    .collect::<Vec<BulkCreateResult>>();
+
+  debug!("Returning bulk created cats");
+  Ok(Json(results))
+}
+
+/// Builds a cat from a creation payload, checking the user can add it to the
+/// organization.
+fn new_cat(user: &TokenUser, ownership: &Ownership, payload: CreateCat) -> Result<Cat, Error> {
+  let organization = payload.organization.map(to_object_id).transpose()?;
+  ownership.check(organization, Role::Member)?;
+
+  let mut cat = Cat::new(user.id, payload.name);
+  cat.organization = organization;
+  cat.tags = normalize_tags(payload.tags);
+  cat.validate().map_err(|_error| Error::bad_request())?;
+
+  Ok(cat)
+}
+
+/// Removes several cats in a single update. Results are returned in the
+/// order of the payload, removed cats with a 204 status.
+#[utoipa::path(
+  delete,
+  path = "/v1/cats/bulk",
+  request_body = RemoveCats,
+  responses(
+    (status = 200, description = "Result of each id", body = [BulkRemoveResult]),
+    (status = 400, description = "Empty or oversized payload", body = ErrorResponse),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 422, description = "Invalid request body", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []))
+)]
+async fn remove_cats(
+  MemberUser(user): MemberUser,
+  Json(payload): Json<RemoveCats>,
+) -> Result<Json<Vec<BulkRemoveResult>>, Error> {
+  check_bulk_size(payload.ids.len())?;
+
+  let ids = payload
+    .ids
+    .iter()
+    .map(to_object_id)
+    .collect::<Vec<Result<ObjectId, Error>>>();
+  let valid_ids = ids
+    .iter()
+    .filter_map(|id| id.as_ref().ok().copied())
+    .collect::<Vec<ObjectId>>();
+
+  let mut query_filter = Ownership::load(user.id).await?.owned();
+  query_filter.insert("_id", doc! { "$in": &valid_ids });
+  let found = Cat::find(query_filter.clone(), None)
+    .await?
+    .into_iter()
+    .filter_map(|cat| cat.id)
+    .collect::<HashSet<ObjectId>>();
+  Cat::soft_delete_many(query_filter).await?;
+
+  let results = payload
+    .ids
+    .into_iter()
+    .zip(ids)
+    .map(|(id, parsed)| {
+      let result = match parsed {
+        Ok(parsed) if found.contains(&parsed) => Ok(()),
+        Ok(_) => Err(Error::not_found()),
+        Err(err) => Err(err),
+      };
+      BulkRemoveResult::new(id, result)
+    })
+    .collect::<Vec<BulkRemoveResult>>();
+
+  debug!("Returning bulk removed cats");
+  Ok(Json(results))
+}
+
+fn check_bulk_size(size: usize) -> Result<(), Error> {
+  if size == 0 || size > MAX_BULK_SIZE {
+    debug!(
+      "Bulk request with {} items, returning 400 status code",
+      size
+    );
+    return Err(Error::bad_request());
+  }
+
+  Ok(())
+}
+
+/// Lists the cats of the user, newest first. Cats can also be sorted by
+/// `updated_at` or `name`, filtered with `?filter[field]=` (see
+/// `CAT_FILTER_FIELDS`), and `?fields=` selects the returned fields. Pages
+/// are exported as CSV with `Accept: text/csv` or `?format=csv`.
+#[utoipa::path(
+  get,
+  path = "/v1/cats",
+  params(RequestQuery, CatFilter, FormatQuery),
+  responses(
+    (
+      status = 200,
+      description = "Paginated user cats",
+      body = [PublicCat],
+      content_type = ["application/json", "text/csv"],
+      headers(
+        ("x-pagination-count" = u64, description = "Total number of cats"),
+        ("x-pagination-offset" = u64, description = "Offset of the returned page"),
+        ("x-pagination-limit" = u64, description = "Size of the returned page"),
+        ("x-pagination-total-pages" = u64, description = "Total number of pages"),
+        ("x-pagination-has-next" = bool, description = "Whether there are cats after the returned page"),
+        ("x-pagination-next-cursor" = String, description = "Cursor of the next page, only sent for full pages")
+      )
+    ),
+    (status = 400, description = "Invalid query parameters", body = ErrorResponse),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 422, description = "Unsupported sort or filter field", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []))
+)]
 async fn query_cats(
   user: TokenUser,
   Query(query): Query<RequestQuery>,
-) -> Result<CustomResponse<Vec<PublicCat>>, Error> {
-  let pagination = Pagination::build_from_request_query(query);
+  OriginalUri(uri): OriginalUri,
+  Query(filter): Query<CatFilter>,
+  filters: Filters,
+  format: ResponseFormat,
+) -> Result<CustomResponse<Vec<Sparse<PublicCat>>>, Error> {
+  let sort = query.sort(CAT_SORT_FIELDS)?;
 
-  let options = FindOptions::builder()
-    .sort(doc! { "created_at": -1_i32 })
+  let mut query_filter = Ownership::load(user.id).await?.readable();
+  query.filter_created(&mut query_filter);
+  let tags = filter.tags.as_deref().unwrap_or_default().split(',');
+  let tags = tags.chain(filter.tag.as_deref());
+  let tags = normalize_tags(tags.map(str::to_owned).collect());
+  if !tags.is_empty() {
+    // Cats must have every requested tag.
+    query_filter.insert("tags", doc! { "$all": tags });
+  }
+  let name = filter.name_contains.as_deref().unwrap_or_default().trim();
+  if !name.is_empty() {
+    query_filter.insert(
+      "name",
+      doc! { "$regex": escape_regex(name), "$options": "i" },
+    );
+  }
+  filters.apply(CAT_FILTER_FIELDS, &mut query_filter)?;
+
+  let fields = query.fields(PUBLIC_CAT_FIELDS)?;
+  let pagination = Pagination::build_from_request_query(query).uri(uri);
+  let mut options = FindOptions::builder()
+    .sort(sort)
     .skip(pagination.offset)
     .limit(pagination.limit as i64)
     .build();
+  options.projection = fields.as_ref().map(Fields::projection);
 
-  let (cats, count) = Cat::find_and_count(doc! { "user": &user.id }, options).await?;
-  let cats = cats.into_iter().map(Into::into).collect::<Vec<PublicCat>>();
+  let after = pagination.after_cursor();
+  // Counting every page is slow on large collections, the count of a filter
+  // is cached while paging through it with cursors. Their next page doesn't
+  // depend on the count, unlike offset pages which are counted exactly.
+  let count_strategy = match after {
+    Some(_) => CountStrategy::Cached(Duration::from_secs(
+      SETTINGS.pagination.count_cache_ttl_secs,
+    )),
+    None => CountStrategy::Exact,
+  };
+  let (cats, pagination, count) = match fields {
+    Some(fields) => {
+      let (cats, count) =
+        Cat::find_documents_page_and_count_with(query_filter, after, options, count_strategy)
+          .await?;
+      let pagination = pagination.next_cursor(&cats, Fields::cursor);
+      let cats = cats
+        .into_iter()
+        .map(|cat| Sparse::Partial(fields.select(cat)))
+        .collect::<Vec<Sparse<PublicCat>>>();
+      (cats, pagination, count)
+    }
+    None => {
+      let (cats, count) =
+        Cat::find_page_and_count_with(query_filter, after, options, count_strategy).await?;
+      let pagination =
+        pagination.next_cursor(&cats, |cat| Cursor::new(cat.created_at, cat.id.unwrap()));
+      let cats = cats
+        .into_iter()
+        .map(|cat| Sparse::Full(PublicCat::from(cat)))
+        .collect::<Vec<Sparse<PublicCat>>>();
+      (cats, pagination, count)
+    }
+  };
 
   let res = CustomResponseBuilder::new()
     .body(cats)
     .pagination(pagination.count(count).build())
+    .format(format)
     .build();
 
   debug!("Returning cats");
   Ok(res)
 }
 
+/// Searches the cats of the user by name, most relevant first. Names are
+/// matched by whole words, e.g. `tigrin` finds `Tigrin the Brave`. Searches
+/// matching no word fall back to the names starting with the text, so
+/// partially typed names are still found.
+#[utoipa::path(
+  get,
+  path = "/v1/cats/search",
+  params(CatSearch, RequestQuery, FormatQuery),
+  responses(
+    (
+      status = 200,
+      description = "Paginated user cats matching the search",
+      body = [PublicCat],
+      content_type = ["application/json", "text/csv"],
+      headers(
+        ("x-pagination-count" = u64, description = "Total number of matching cats"),
+        ("x-pagination-offset" = u64, description = "Offset of the returned page"),
+        ("x-pagination-limit" = u64, description = "Size of the returned page"),
+        ("x-pagination-total-pages" = u64, description = "Total number of pages"),
+        ("x-pagination-has-next" = bool, description = "Whether there are cats after the returned page")
+      )
+    ),
+    (status = 400, description = "Missing search text or invalid query parameters", body = ErrorResponse),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []))
+)]
+async fn search_cats(
+  user: TokenUser,
+  Query(search): Query<CatSearch>,
+  Query(query): Query<RequestQuery>,
+  OriginalUri(uri): OriginalUri,
+  format: ResponseFormat,
+) -> Result<CustomResponse<Vec<PublicCat>>, Error> {
+  let text = search.q.trim();
+  if text.is_empty() {
+    debug!("Empty cat search, returning 400 status code");
+    return Err(Error::bad_request());
+  }
+  // Results are sorted by relevance, which cursors can't resume from.
+  if query.cursor.is_some() {
+    debug!("Cursor on cat search, returning 400 status code");
+    return Err(Error::InvalidQuery(
+      "Cat search doesn't support cursors".to_owned(),
+    ));
+  }
+
+  let mut query_filter = Ownership::load(user.id).await?.readable();
+  query.filter_created(&mut query_filter);
+  let pagination = Pagination::build_from_request_query(query).uri(uri);
+
+  let mut text_filter = query_filter.clone();
+  text_filter.insert("$text", doc! { "$search": text });
+  let options = FindOptions::builder()
+    .projection(doc! { "score": { "$meta": "textScore" } })
+    .sort(doc! { "score": { "$meta": "textScore" }, "created_at": -1_i32 })
+    .skip(pagination.offset)
+    .limit(pagination.limit as i64)
+    .build();
+  let (cats, count) = match Cat::find_and_count(text_filter, options).await? {
+    (_, 0) => {
+      query_filter.insert(
+        "name",
+        doc! { "$regex": format!("^{}", escape_regex(text)), "$options": "i" },
+      );
+      let options = FindOptions::builder()
+        .sort(doc! { "name": 1_i32, "_id": 1_i32 })
+        .skip(pagination.offset)
+        .limit(pagination.limit as i64)
+        .build();
+      Cat::find_and_count(query_filter, options).await?
+    }
+    found => found,
+  };
+
+  let cats = cats
+    .into_iter()
+    .map(PublicCat::from)
+    .collect::<Vec<PublicCat>>();
+  let res = CustomResponseBuilder::new()
+    .body(cats)
+    .pagination(pagination.count(count).build())
+    .format(format)
+    .build();
+
+  debug!("Returning searched cats");
+  Ok(res)
+}
+
+/// Streams every cat of the user as NDJSON, oldest first, without
+/// pagination.
+#[utoipa::path(
+  get,
+  path = "/v1/cats/export",
+  responses(
+    (status = 200, description = "User cats, one JSON object per line", content_type = "application/x-ndjson", body = PublicCat),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []))
+)]
+async fn export_cats(user: TokenUser) -> Result<Response, Error> {
+  let query_filter = Ownership::load(user.id).await?.readable();
+  let options = FindOptions::builder()
+    .sort(doc! { "created_at": 1_i32, "_id": 1_i32 })
+    .build();
+  let cursor = Cat::cursor(query_filter, options).await?;
+
+  debug!("Streaming cats export");
+  Ok(stream_ndjson(cursor, PublicCat::from))
+}
+
+#[utoipa::path(
+  get,
+  path = "/v1/cats/{id}",
+  params(("id" = String, Path, description = "Cat id")),
+  responses(
+    (status = 200, description = "Cat", body = PublicCat),
+    (status = 400, description = "Invalid cat id", body = ErrorResponse),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 404, description = "Cat not found", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []))
+)]
 async fn get_cat_by_id(user: TokenUser, Path(id): Path<String>) -> Result<Json<PublicCat>, Error> {
   let cat_id = to_object_id(id)?;
-  let cat = Cat::find_one(doc! { "_id": cat_id, "user": &user.id }, None)
+  let mut query_filter = Ownership::load(user.id).await?.readable();
+  query_filter.insert("_id", cat_id);
+  let cat = Cat::find_one(query_filter, None)
     .await?
     .map(PublicCat::from);
 
@@ -85,16 +542,44 @@ async fn get_cat_by_id(user: TokenUser, Path(id): Path<String>) -> Result<Json<P
   Ok(Json(cat))
 }
 
+/// Removes a cat. Removed cats are kept until an admin deletes them, so they
+/// can be restored.
+#[utoipa::path(
+  delete,
+  path = "/v1/cats/{id}",
+  params(
+    ("id" = String, Path, description = "Cat id"),
+    ("return" = Option<bool>, Query, description = "Return the removed cat")
+  ),
+  responses(
+    (status = 200, description = "Cat removed, returned when `return=true`", body = PublicCat),
+    (status = 204, description = "Cat removed"),
+    (status = 400, description = "Invalid cat id", body = ErrorResponse),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 404, description = "Cat not found", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []))
+)]
 async fn remove_cat_by_id(
-  user: TokenUser,
+  MemberUser(user): MemberUser,
   Path(id): Path<String>,
-) -> Result<CustomResponse<()>, Error> {
+  Query(query): Query<RemoveCatQuery>,
+) -> Result<CustomResponse<PublicCat>, Error> {
   let cat_id = to_object_id(id)?;
-  let delete_result = Cat::delete_one(doc! { "_id": cat_id, "user": &user.id }).await?;
+  let mut query_filter = Ownership::load(user.id).await?.owned();
+  query_filter.insert("_id", cat_id);
 
-  if delete_result.deleted_count == 0 {
-    debug!("Cat not found, returning 404 status code");
-    return Err(Error::not_found());
+  let cat = match Cat::soft_delete(query_filter).await? {
+    Some(cat) => PublicCat::from(cat),
+    None => {
+      debug!("Cat not found, returning 404 status code");
+      return Err(Error::not_found());
+    }
+  };
+
+  if query.return_removed {
+    debug!("Returning removed cat");
+    return Ok(CustomResponseBuilder::new().body(cat).build());
   }
 
   let res = CustomResponseBuilder::new()
@@ -104,20 +589,255 @@ async fn remove_cat_by_id(
   Ok(res)
 }
 
+/// Restores a removed cat.
+#[utoipa::path(
+  post,
+  path = "/v1/cats/{id}/restore",
+  params(("id" = String, Path, description = "Cat id")),
+  responses(
+    (status = 200, description = "Cat restored", body = PublicCat),
+    (status = 400, description = "Invalid cat id", body = ErrorResponse),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 404, description = "Removed cat not found", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []))
+)]
+async fn restore_cat_by_id(
+  MemberUser(user): MemberUser,
+  Path(id): Path<String>,
+) -> Result<Json<PublicCat>, Error> {
+  let cat_id = to_object_id(id)?;
+  let mut query_filter = Ownership::load(user.id).await?.owned();
+  query_filter.insert("_id", cat_id);
+
+  let cat = match Cat::restore(query_filter).await? {
+    Some(cat) => PublicCat::from(cat),
+    None => {
+      debug!("Removed cat not found, returning 404 status code");
+      return Err(Error::not_found());
+    }
+  };
+
+  debug!("Returning restored cat");
+  Ok(Json(cat))
+}
+
+#[utoipa::path(
+  put,
+  path = "/v1/cats/{id}",
+  params(
+    ("id" = String, Path, description = "Cat id"),
+    ("If-Match" = String, Header, description = "Version of the cat being updated")
+  ),
+  request_body = UpdateCat,
+  responses(
+    (status = 200, description = "Updated cat", body = PublicCat),
+    (status = 400, description = "Invalid cat id or version", body = ErrorResponse),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 404, description = "Cat not found", body = ErrorResponse),
+    (status = 409, description = "Cat was modified by another request", body = ErrorResponse),
+    (status = 422, description = "Invalid request body", body = ErrorResponse),
+    (status = 428, description = "Missing If-Match header", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []))
+)]
 async fn update_cat_by_id(
-  user: TokenUser,
+  MemberUser(user): MemberUser,
   Path(id): Path<String>,
-  Json(payload): Json<UpdateCat>,
+  headers: HeaderMap,
+  ValidJson(payload): ValidJson<UpdateCat>,
 ) -> Result<Json<PublicCat>, Error> {
   let cat_id = to_object_id(id)?;
+  let version = expected_version(&headers)?;
+  let payload = UpdateCat {
+    tags: payload.tags.map(normalize_tags),
+    ..payload
+  };
   let update = bson::to_document(&payload).unwrap();
+  let mut query_filter = Ownership::load(user.id).await?.writable();
+  query_filter.insert("_id", cat_id);
 
-  let cat = Cat::find_one_and_update(
-    doc! { "_id": &cat_id, "user": &user.id },
-    doc! { "$set": update },
-  )
-  .await?
-  .map(PublicCat::from);
+  let cat = update_cat(query_filter, version, update).await?;
+
+  debug!("Returning cat");
+  Ok(Json(cat))
+}
+
+/// Updates the fields sent in a JSON merge patch (RFC 7396), leaving the
+/// others untouched. `null` clears a field: tags are emptied, while the name
+/// can't be cleared.
+#[utoipa::path(
+  patch,
+  path = "/v1/cats/{id}",
+  params(
+    ("id" = String, Path, description = "Cat id"),
+    ("If-Match" = String, Header, description = "Version of the cat being updated")
+  ),
+  request_body(content = CatPatch, content_type = "application/merge-patch+json"),
+  responses(
+    (status = 200, description = "Updated cat", body = PublicCat),
+    (status = 400, description = "Invalid cat id, version or JSON", body = ErrorResponse),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 404, description = "Cat not found", body = ErrorResponse),
+    (status = 409, description = "Cat was modified by another request", body = ErrorResponse),
+    (status = 422, description = "Patched cat is invalid, or the patch has unknown fields", body = ErrorResponse),
+    (status = 428, description = "Missing If-Match header", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []))
+)]
+async fn patch_cat_by_id(
+  MemberUser(user): MemberUser,
+  Path(id): Path<String>,
+  headers: HeaderMap,
+  Json(patch): Json<Value>,
+) -> Result<Json<PublicCat>, Error> {
+  let cat_id = to_object_id(id)?;
+  let version = expected_version(&headers)?;
+  let mut query_filter = Ownership::load(user.id).await?.writable();
+  query_filter.insert("_id", cat_id);
+
+  let cat = match Cat::find_one(query_filter.clone(), None).await? {
+    Some(cat) => cat,
+    None => {
+      debug!("Cat not found, returning 404 status code");
+      return Err(Error::not_found());
+    }
+  };
+  if cat.version != version {
+    debug!("Cat version mismatch, returning 409 status code");
+    return Err(Error::conflict());
+  }
+
+  let patched = merge_patch::apply(&CatPatch::from(cat), patch)?;
+  let patched = CatPatch {
+    tags: normalize_tags(patched.tags),
+    ..patched
+  };
+  let update = bson::to_document(&patched).unwrap();
+  let cat = update_cat(query_filter, version, update).await?;
+
+  debug!("Returning cat");
+  Ok(Json(cat))
+}
+
+/// Sets the fields of `update` on the cat if it is still at `version`.
+async fn update_cat(
+  query_filter: Document,
+  version: i64,
+  update: Document,
+) -> Result<PublicCat, Error> {
+  let cat = Cat::update_versioned(query_filter, version, doc! { "$set": update }).await?;
+
+  match cat {
+    Some(cat) => Ok(PublicCat::from(cat)),
+    None => {
+      debug!("Cat not found, returning 404 status code");
+      Err(Error::not_found())
+    }
+  }
+}
+
+/// Distinct tags of the cats of the user, with the number of cats having
+/// each, most used first.
+#[utoipa::path(
+  get,
+  path = "/v1/cats/tags",
+  responses(
+    (status = 200, description = "Tags of the user cats", body = [TagCount]),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []))
+)]
+async fn query_cat_tags(user: TokenUser) -> Result<Json<Vec<TagCount>>, Error> {
+  let mut query_filter = Ownership::load(user.id).await?.readable();
+  // Aggregations don't exclude the removed cats.
+  query_filter.insert("deleted_at", Bson::Null);
+  let pipeline = vec![
+    doc! { "$match": query_filter },
+    doc! { "$unwind": "$tags" },
+    doc! { "$group": { "_id": "$tags", "count": { "$sum": 1 } } },
+    doc! { "$sort": { "count": -1, "_id": 1 } },
+    doc! { "$project": { "_id": 0, "tag": "$_id", "count": 1 } },
+  ];
+  let tags = Cat::aggregate::<TagCount>(pipeline).await?;
+
+  debug!("Returning cat tags");
+  Ok(Json(tags))
+}
+
+/// Adds tags to a cat, keeping the ones it already has.
+#[utoipa::path(
+  post,
+  path = "/v1/cats/{id}/tags",
+  params(("id" = String, Path, description = "Cat id")),
+  request_body = AddCatTags,
+  responses(
+    (status = 200, description = "Tagged cat", body = PublicCat),
+    (status = 400, description = "Invalid cat id", body = ErrorResponse),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 404, description = "Cat not found", body = ErrorResponse),
+    (status = 409, description = "Cat was modified by another request", body = ErrorResponse),
+    (status = 422, description = "Invalid request body, or too many tags", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []))
+)]
+async fn add_cat_tags(
+  MemberUser(user): MemberUser,
+  Path(id): Path<String>,
+  ValidJson(payload): ValidJson<AddCatTags>,
+) -> Result<Json<PublicCat>, Error> {
+  let cat_id = to_object_id(id)?;
+  let mut query_filter = Ownership::load(user.id).await?.writable();
+  query_filter.insert("_id", cat_id);
+
+  let cat = match Cat::find_one(query_filter.clone(), None).await? {
+    Some(cat) => cat,
+    None => {
+      debug!("Cat not found, returning 404 status code");
+      return Err(Error::not_found());
+    }
+  };
+
+  let version = cat.version;
+  let mut tagged = CatPatch::from(cat);
+  tagged.tags.extend(payload.tags);
+  tagged.tags = normalize_tags(tagged.tags);
+  // The cat may end up with too many tags.
+  tagged.validate()?;
+
+  let cat = update_cat(query_filter, version, doc! { "tags": tagged.tags }).await?;
+
+  debug!("Returning cat");
+  Ok(Json(cat))
+}
+
+#[utoipa::path(
+  delete,
+  path = "/v1/cats/{id}/tags/{tag}",
+  params(
+    ("id" = String, Path, description = "Cat id"),
+    ("tag" = String, Path, description = "Removed tag")
+  ),
+  responses(
+    (status = 200, description = "Untagged cat", body = PublicCat),
+    (status = 400, description = "Invalid cat id", body = ErrorResponse),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 404, description = "Cat not found", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []))
+)]
+async fn remove_cat_tag(
+  MemberUser(user): MemberUser,
+  Path((id, tag)): Path<(String, String)>,
+) -> Result<Json<PublicCat>, Error> {
+  let cat_id = to_object_id(id)?;
+  let mut query_filter = Ownership::load(user.id).await?.writable();
+  query_filter.insert("_id", cat_id);
+
+  let tag = tag.trim().to_lowercase();
+  let cat = Cat::find_one_and_update(query_filter, doc! { "$pull": { "tags": tag } })
+    .await?
+    .map(PublicCat::from);
 
   let cat = match cat {
     Some(cat) => cat,
@@ -131,12 +851,532 @@ async fn update_cat_by_id(
   Ok(Json(cat))
 }
 
-#[derive(Deserialize)]
+/// Shares the cat with another user, or changes the access they were
+/// granted. Only the owners of the cat can share it, not the users it is
+/// shared with.
+#[utoipa::path(
+  post,
+  path = "/v1/cats/{id}/shares",
+  params(("id" = String, Path, description = "Cat id")),
+  request_body = CreateShare,
+  responses(
+    (status = 200, description = "Access of the user changed", body = PublicShare),
+    (status = 201, description = "Cat shared", body = PublicShare),
+    (status = 400, description = "Invalid cat id, or cat shared with its owner", body = ErrorResponse),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 404, description = "Cat or user not found", body = ErrorResponse),
+    (status = 422, description = "Invalid request body", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []))
+)]
+async fn create_cat_share(
+  MemberUser(user): MemberUser,
+  Path(id): Path<String>,
+  ValidJson(payload): ValidJson<CreateShare>,
+) -> Result<CustomResponse<PublicShare>, Error> {
+  let cat_id = to_object_id(id)?;
+  check_cat_owner(user.id, cat_id).await?;
+
+  let (share, created) = sharing::grant(SharedResource::Cat, cat_id, user.id, payload).await?;
+  let status_code = if created {
+    StatusCode::CREATED
+  } else {
+    StatusCode::OK
+  };
+
+  let res = CustomResponseBuilder::new()
+    .body(share)
+    .status_code(status_code)
+    .build();
+
+  Ok(res)
+}
+
+#[utoipa::path(
+  get,
+  path = "/v1/cats/{id}/shares",
+  params(("id" = String, Path, description = "Cat id")),
+  responses(
+    (status = 200, description = "Users the cat is shared with", body = [PublicShare]),
+    (status = 400, description = "Invalid cat id", body = ErrorResponse),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 404, description = "Cat not found", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []))
+)]
+async fn query_cat_shares(
+  user: TokenUser,
+  Path(id): Path<String>,
+) -> Result<Json<Vec<PublicShare>>, Error> {
+  let cat_id = to_object_id(id)?;
+  check_cat_owner(user.id, cat_id).await?;
+
+  let shares = sharing::list(cat_id).await?;
+
+  debug!("Returning cat shares");
+  Ok(Json(shares))
+}
+
+#[utoipa::path(
+  delete,
+  path = "/v1/cats/{id}/shares/{user}",
+  params(
+    ("id" = String, Path, description = "Cat id"),
+    ("user" = String, Path, description = "Id of the user the cat is shared with")
+  ),
+  responses(
+    (status = 204, description = "Share revoked"),
+    (status = 400, description = "Invalid cat or user id", body = ErrorResponse),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 404, description = "Cat or share not found", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []))
+)]
+async fn remove_cat_share(
+  MemberUser(user): MemberUser,
+  Path((id, shared_with)): Path<(String, String)>,
+) -> Result<CustomResponse<()>, Error> {
+  let cat_id = to_object_id(id)?;
+  let shared_with = to_object_id(shared_with)?;
+  check_cat_owner(user.id, cat_id).await?;
+
+  if !sharing::revoke(cat_id, shared_with).await? {
+    debug!("Share not found, returning 404 status code");
+    return Err(Error::not_found());
+  }
+
+  let res = CustomResponseBuilder::new()
+    .status_code(StatusCode::NO_CONTENT)
+    .build();
+
+  Ok(res)
+}
+
+/// Attaches a file to a cat, sent as the `file` field of a
+/// `multipart/form-data` body. Only the media types listed in the
+/// `attachments` settings are accepted, up to `attachments.max_size_bytes`.
+#[utoipa::path(
+  post,
+  path = "/v1/cats/{id}/attachments",
+  params(("id" = String, Path, description = "Cat id")),
+  request_body(content = AttachmentUpload, content_type = "multipart/form-data"),
+  responses(
+    (status = 201, description = "File attached", body = PublicAttachment),
+    (status = 400, description = "Invalid cat id or multipart body", body = ErrorResponse),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 404, description = "Cat not found", body = ErrorResponse),
+    (status = 413, description = "File too large", body = ErrorResponse),
+    (status = 422, description = "Missing file, or unsupported file type", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []))
+)]
+async fn upload_cat_attachment(
+  MemberUser(user): MemberUser,
+  State(state): State<AppState>,
+  Path(id): Path<String>,
+  mut multipart: Multipart,
+) -> Result<CustomResponse<PublicAttachment>, Error> {
+  let cat_id = to_object_id(id)?;
+  let mut query_filter = Ownership::load(user.id).await?.writable();
+  query_filter.insert("_id", cat_id);
+  if !Cat::exists(query_filter.clone()).await? {
+    debug!("Cat not found, returning 404 status code");
+    return Err(Error::not_found());
+  }
+
+  let field = loop {
+    match multipart.next_field().await? {
+      Some(field) if field.name() == Some("file") => break field,
+      Some(_) => continue,
+      None => {
+        debug!("Missing attachment file, returning 422 status code");
+        return Err(Error::InvalidPayload("Missing `file` field".to_owned()));
+      }
+    }
+  };
+
+  let settings = &state.settings.attachments;
+  let content_type = field
+    .content_type()
+    .and_then(|content_type| content_type.split(';').next())
+    .unwrap_or_default()
+    .trim()
+    .to_ascii_lowercase();
+  if !settings.content_types.contains(&content_type) {
+    debug!("Unsupported attachment type, returning 422 status code");
+    return Err(Error::InvalidPayload(format!(
+      "Unsupported file type `{}`, expected one of: {}",
+      content_type,
+      settings.content_types.join(", ")
+    )));
+  }
+  let filename = attachment_filename(field.file_name());
+
+  let file = gridfs::upload(
+    &filename,
+    &content_type,
+    field_content(field),
+    settings.max_size_bytes,
+  )
+  .await?;
+  let attachment = Attachment::new(file.id, filename, content_type, file.length, user.id);
+
+  let update = doc! { "$push": { "attachments": bson::to_bson(&attachment).unwrap() } };
+  let attached = match Cat::find_one_and_update(query_filter, update).await {
+    Ok(cat) => cat.is_some(),
+    Err(err) => {
+      gridfs::delete(file.id).await?;
+      return Err(err);
+    }
+  };
+  // The cat was removed during the upload.
+  if !attached {
+    gridfs::delete(file.id).await?;
+    debug!("Cat not found, returning 404 status code");
+    return Err(Error::not_found());
+  }
+
+  let res = CustomResponseBuilder::new()
+    .body(PublicAttachment::from(attachment))
+    .status_code(StatusCode::CREATED)
+    .build();
+
+  Ok(res)
+}
+
+/// Streams the content of a file attached to a cat. It is always sent as a
+/// download, never displayed inline.
+#[utoipa::path(
+  get,
+  path = "/v1/cats/{id}/attachments/{attachment}",
+  params(
+    ("id" = String, Path, description = "Cat id"),
+    ("attachment" = String, Path, description = "Attachment id")
+  ),
+  responses(
+    (status = 200, description = "Attached file", content_type = "application/octet-stream", body = Vec<u8>),
+    (status = 400, description = "Invalid cat or attachment id", body = ErrorResponse),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 404, description = "Cat or attachment not found", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []))
+)]
+async fn download_cat_attachment(
+  user: TokenUser,
+  Path((id, attachment_id)): Path<(String, String)>,
+) -> Result<Response, Error> {
+  let cat_id = to_object_id(id)?;
+  let attachment_id = to_object_id(attachment_id)?;
+  let mut query_filter = Ownership::load(user.id).await?.readable();
+  query_filter.insert("_id", cat_id);
+
+  let attachment = Cat::find_one(query_filter, None).await?.and_then(|cat| {
+    cat
+      .attachments
+      .into_iter()
+      .find(|attachment| attachment.id == attachment_id)
+  });
+  let attachment = match attachment {
+    Some(attachment) => attachment,
+    None => {
+      debug!("Attachment not found, returning 404 status code");
+      return Err(Error::not_found());
+    }
+  };
+  let content = match gridfs::download(attachment.id).await? {
+    Some(content) => content,
+    None => {
+      debug!("Attachment file not found, returning 404 status code");
+      return Err(Error::not_found());
+    }
+  };
+
+  let content_type = HeaderValue::from_str(&attachment.content_type)
+    .unwrap_or_else(|_| HeaderValue::from_static("application/octet-stream"));
+  let disposition = format!(
+    "attachment; filename=\"{}\"",
+    header_filename(&attachment.filename)
+  );
+  let headers = [
+    (header::CONTENT_TYPE, content_type),
+    (header::CONTENT_LENGTH, HeaderValue::from(attachment.size)),
+    (
+      header::CONTENT_DISPOSITION,
+      HeaderValue::from_str(&disposition).unwrap(),
+    ),
+    // Browsers must not guess another type, e.g. HTML, from the content.
+    (
+      header::X_CONTENT_TYPE_OPTIONS,
+      HeaderValue::from_static("nosniff"),
+    ),
+  ];
+
+  debug!("Streaming cat attachment");
+  Ok((headers, StreamBody::new(content)).into_response())
+}
+
+#[utoipa::path(
+  delete,
+  path = "/v1/cats/{id}/attachments/{attachment}",
+  params(
+    ("id" = String, Path, description = "Cat id"),
+    ("attachment" = String, Path, description = "Attachment id")
+  ),
+  responses(
+    (status = 204, description = "Attachment removed"),
+    (status = 400, description = "Invalid cat or attachment id", body = ErrorResponse),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 404, description = "Cat or attachment not found", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []))
+)]
+async fn remove_cat_attachment(
+  MemberUser(user): MemberUser,
+  Path((id, attachment_id)): Path<(String, String)>,
+) -> Result<CustomResponse<()>, Error> {
+  let cat_id = to_object_id(id)?;
+  let attachment_id = to_object_id(attachment_id)?;
+  let mut query_filter = Ownership::load(user.id).await?.writable();
+  query_filter.insert("_id", cat_id);
+  query_filter.insert("attachments.id", attachment_id);
+
+  let update = doc! { "$pull": { "attachments": { "id": attachment_id } } };
+  if Cat::find_one_and_update(query_filter, update)
+    .await?
+    .is_none()
+  {
+    debug!("Attachment not found, returning 404 status code");
+    return Err(Error::not_found());
+  }
+  gridfs::delete(attachment_id).await?;
+
+  let res = CustomResponseBuilder::new()
+    .status_code(StatusCode::NO_CONTENT)
+    .build();
+
+  Ok(res)
+}
+
+/// Ids of the files attached to the cats matching the query, removed cats
+/// included, so they can be deleted along with the cats.
+pub async fn cat_attachment_files(query: Document) -> Result<Vec<ObjectId>, Error> {
+  let pipeline = vec![
+    doc! { "$match": query },
+    doc! { "$unwind": "$attachments" },
+    doc! { "$project": { "_id": "$attachments.id" } },
+  ];
+  let files = Cat::aggregate::<Document>(pipeline)
+    .await?
+    .into_iter()
+    .filter_map(|file| file.get_object_id("_id").ok())
+    .collect();
+
+  Ok(files)
+}
+
+/// Name of an uploaded file, without the directories some browsers send
+/// along.
+fn attachment_filename(filename: Option<&str>) -> String {
+  let filename = filename
+    .unwrap_or_default()
+    .rsplit(['/', '\\'])
+    .next()
+    .unwrap_or_default()
+    .chars()
+    .filter(|c| !c.is_control())
+    .take(MAX_FILENAME_LENGTH)
+    .collect::<String>();
+  let filename = filename.trim();
+
+  if filename.is_empty() {
+    "attachment".to_owned()
+  } else {
+    filename.to_owned()
+  }
+}
+
+/// Filename quoted in `Content-Disposition`, with the characters headers
+/// can't carry replaced.
+fn header_filename(filename: &str) -> String {
+  filename
+    .chars()
+    .map(|c| match c {
+      ' '..='~' if c != '"' && c != '\\' => c,
+      _ => '_',
+    })
+    .collect()
+}
+
+/// Fails with a 404 unless the user owns the cat, see `Ownership::owned`.
+async fn check_cat_owner(user: ObjectId, cat_id: ObjectId) -> Result<(), Error> {
+  let mut query_filter = Ownership::load(user).await?.owned();
+  query_filter.insert("_id", cat_id);
+  if !Cat::exists(query_filter).await? {
+    debug!("Cat not found, returning 404 status code");
+    return Err(Error::not_found());
+  }
+
+  Ok(())
+}
+
+// Longest cat name and tag, and most tags of a cat.
+const MAX_NAME_LENGTH: u64 = 100;
+const MAX_TAG_LENGTH: usize = 50;
+const MAX_TAGS: usize = 20;
+// Longest name of an attached file, longer ones are cut.
+const MAX_FILENAME_LENGTH: usize = 255;
+
+#[derive(Deserialize, ToSchema, Validate)]
 struct CreateCat {
+  #[validate(length(max = MAX_NAME_LENGTH), custom(function = "not_blank"))]
   name: String,
+  #[serde(default)]
+  #[validate(custom(function = "valid_tags"))]
+  tags: Vec<String>,
+  // Shares the cat with the members of the organization.
+  organization: Option<String>,
+}
+
+#[derive(Deserialize, ToSchema)]
+struct RemoveCats {
+  ids: Vec<String>,
+}
+
+/// Result of an item of a bulk creation, with the created cat or the error
+/// the single cat route would have returned.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BulkCreateResult {
+  pub status: u16,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub cat: Option<PublicCat>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub error: Option<ErrorResponse>,
 }
 
-#[derive(Serialize, Deserialize)]
+impl BulkCreateResult {
+  fn error(error: &Error) -> Self {
+    Self {
+      status: error.status_code().as_u16(),
+      cat: None,
+      error: Some(ErrorResponse::from(error)),
+    }
+  }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BulkRemoveResult {
+  pub id: String,
+  pub status: u16,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub error: Option<ErrorResponse>,
+}
+
+impl BulkRemoveResult {
+  fn new(id: String, result: Result<(), Error>) -> Self {
+    match result {
+      Ok(()) => Self {
+        id,
+        status: StatusCode::NO_CONTENT.as_u16(),
+        error: None,
+      },
+      Err(err) => Self {
+        id,
+        status: err.status_code().as_u16(),
+        error: Some(ErrorResponse::from(&err)),
+      },
+    }
+  }
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct CatFilter {
+  /// Comma separated list of tags, only cats with all of them are returned.
+  tags: Option<String>,
+  /// Only returns cats with this tag, along with the ones of `tags`.
+  tag: Option<String>,
+  /// Only returns cats whose name contains this text, ignoring case.
+  name_contains: Option<String>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct CatSearch {
+  /// Words to look for in the cat names, e.g. `tigrin`.
+  q: String,
+}
+
+/// Body of `POST /cats/:id/attachments`, only used by the OpenAPI docs.
+#[derive(ToSchema)]
+#[allow(dead_code)]
+struct AttachmentUpload {
+  #[schema(value_type = String, format = Binary)]
+  file: Vec<u8>,
+}
+
+#[derive(Deserialize)]
+struct RemoveCatQuery {
+  #[serde(rename = "return", default)]
+  return_removed: bool,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Validate)]
 struct UpdateCat {
+  #[validate(length(max = MAX_NAME_LENGTH), custom(function = "not_blank"))]
   name: String,
+  // Tags are left untouched when not sent.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  #[validate(custom(function = "valid_tags"))]
+  tags: Option<Vec<String>>,
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+struct AddCatTags {
+  #[validate(length(min = 1), custom(function = "valid_tags"))]
+  tags: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct TagCount {
+  pub tag: String,
+  // Number of cats with the tag.
+  pub count: u64,
+}
+
+/// Updatable fields of a cat, patched by `PATCH /cats/:id`.
+#[derive(Serialize, Deserialize, ToSchema, Validate)]
+#[serde(deny_unknown_fields)]
+struct CatPatch {
+  #[validate(length(max = MAX_NAME_LENGTH), custom(function = "not_blank"))]
+  name: String,
+  #[serde(default)]
+  #[validate(custom(function = "valid_tags"))]
+  tags: Vec<String>,
+}
+
+impl From<Cat> for CatPatch {
+  fn from(cat: Cat) -> Self {
+    Self {
+      name: cat.name,
+      tags: cat.tags,
+    }
+  }
+}
+
+fn valid_tags(tags: &[String]) -> Result<(), ValidationError> {
+  if tags.len() > MAX_TAGS {
+    return Err(validation::error("length", "must have at most 20 tags"));
+  }
+
+  if tags
+    .iter()
+    .any(|tag| tag.trim().chars().count() > MAX_TAG_LENGTH)
+  {
+    return Err(validation::error(
+      "length",
+      "must be at most 50 characters long",
+    ));
+  }
+
+  Ok(())
 }
@@ -0,0 +1,142 @@
+use axum::body::StreamBody;
+use axum::extract::Path;
+use axum::http::header::{self, HeaderValue};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use bson::doc;
+use serde::Deserialize;
+use tracing::{debug, info};
+use utoipa::{IntoParams, OpenApi};
+
+use crate::errors::{Error, ErrorResponse};
+use crate::models::job::{Job, JobKind, JobStatus, PublicJob};
+use crate::services::data_export::{DataArchive, DataExport};
+use crate::services::export::Format;
+use crate::services::{gridfs, jobs};
+use crate::state::AppState;
+use crate::utils::json::Json;
+use crate::utils::models::ModelExt;
+use crate::utils::query::Query;
+use crate::utils::route_table::RouteTable;
+use crate::utils::to_object_id::to_object_id;
+use crate::utils::token::TokenUser;
+
+#[derive(OpenApi)]
+#[openapi(
+  paths(create_data_export, get_data_export),
+  components(schemas(Format))
+)]
+pub struct ApiDoc;
+
+pub fn create_route() -> RouteTable<AppState> {
+  RouteTable::new()
+    .post("/me/export", create_data_export)
+    .get("/me/export/:id", get_data_export)
+}
+
+/// Queues the export of the data of the authenticated user: profile, cats,
+/// watchlists, address notes, alerts and usage. The archive is fetched from
+/// the returned location once the job succeeds.
+#[utoipa::path(
+  post,
+  path = "/v1/me/export",
+  params(DataExportQuery),
+  responses(
+    (status = 202, description = "Export queued", body = PublicJob),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []), ("apiKey" = []))
+)]
+async fn create_data_export(
+  user: TokenUser,
+  Query(query): Query<DataExportQuery>,
+) -> Result<Response, Error> {
+  let payload = DataExport {
+    format: query.format.unwrap_or(Format::Ndjson),
+  };
+  let job = jobs::enqueue(user.id, JobKind::DataExport, &payload).await?;
+  info!("Queued the data export of user {}", user.id);
+
+  let location = format!("/v1/me/export/{}", job.id.unwrap());
+  let headers = [(header::LOCATION, HeaderValue::from_str(&location).unwrap())];
+
+  Ok((StatusCode::ACCEPTED, headers, Json(PublicJob::from(job))).into_response())
+}
+
+/// Archive of a data export once its job succeeded. Until then, the job is
+/// returned with a 202 to keep polling, or with a 200 when it failed.
+#[utoipa::path(
+  get,
+  path = "/v1/me/export/{id}",
+  params(("id" = String, Path, description = "Export job id")),
+  responses(
+    (status = 200, description = "Archive, or the job when it failed", body = PublicJob),
+    (status = 202, description = "Export in progress", body = PublicJob),
+    (status = 400, description = "Invalid export id", body = ErrorResponse),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 404, description = "Export not found", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []), ("apiKey" = []))
+)]
+async fn get_data_export(user: TokenUser, Path(id): Path<String>) -> Result<Response, Error> {
+  let job_id = to_object_id(id)?;
+  let query = doc! {
+    "_id": job_id,
+    "user": &user.id,
+    "kind": JobKind::DataExport.as_str()
+  };
+  let job = match Job::find_one(query, None).await? {
+    Some(job) => job,
+    None => {
+      debug!("Data export not found, returning 404 status code");
+      return Err(Error::not_found());
+    }
+  };
+
+  match job.status {
+    JobStatus::Queued | JobStatus::Running => {
+      debug!("Data export in progress, returning 202 status code");
+      return Ok((StatusCode::ACCEPTED, Json(PublicJob::from(job))).into_response());
+    }
+    JobStatus::Failed => {
+      debug!("Data export failed, returning job");
+      return Ok(Json(PublicJob::from(job)).into_response());
+    }
+    JobStatus::Succeeded => {}
+  }
+
+  let archive = match DataArchive::of(&job) {
+    Some(archive) => archive,
+    None => return Err(Error::General("Invalid data export result".to_owned())),
+  };
+  let content = match gridfs::download(archive.file).await? {
+    Some(content) => content,
+    None => {
+      debug!("Data export file not found, returning 404 status code");
+      return Err(Error::not_found());
+    }
+  };
+
+  let disposition = format!("attachment; filename=\"{}\"", archive.filename());
+  let headers = [
+    (
+      header::CONTENT_TYPE,
+      HeaderValue::from_static(archive.content_type()),
+    ),
+    (header::CONTENT_LENGTH, HeaderValue::from(archive.size)),
+    (
+      header::CONTENT_DISPOSITION,
+      HeaderValue::from_str(&disposition).unwrap(),
+    ),
+  ];
+
+  debug!("Streaming data export");
+  Ok((headers, StreamBody::new(content)).into_response())
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DataExportQuery {
+  /// Format of the archive, `ndjson` by default.
+  format: Option<Format>,
+}
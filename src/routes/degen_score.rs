@@ -0,0 +1,62 @@
+use axum::extract::State;
+use serde::Deserialize;
+use tracing::debug;
+use utoipa::{IntoParams, OpenApi};
+
+use crate::errors::{Error, ErrorResponse};
+use crate::services::degen_score::{DegenFactor, DegenFactorKind, DegenScoreReport, DegenTier};
+use crate::state::AppState;
+use crate::utils::address::EvmAddress;
+use crate::utils::json::Json;
+use crate::utils::query::Query;
+use crate::utils::route_table::RouteTable;
+
+// Chain whose transactions and approvals are read when none is requested.
+const DEFAULT_CHAIN: &str = "ethereum";
+
+#[derive(OpenApi)]
+#[openapi(
+  paths(query_degen_score),
+  components(schemas(DegenScoreReport, DegenFactor, DegenFactorKind, DegenTier))
+)]
+pub struct ApiDoc;
+
+pub fn create_route() -> RouteTable<AppState> {
+  RouteTable::new().get("/degen-score/:address", query_degen_score)
+}
+
+#[utoipa::path(
+  get,
+  path = "/v1/degen-score/{address}",
+  params(("address" = String, Path, description = "EVM address"), DegenScoreQuery),
+  responses(
+    (status = 200, description = "Degen score of the address, from 0 to 100, with the breakdown of its factors", body = DegenScoreReport),
+    (status = 400, description = "Unsupported chain or invalid address", body = ErrorResponse)
+  )
+)]
+async fn query_degen_score(
+  State(state): State<AppState>,
+  EvmAddress(address): EvmAddress,
+  Query(query): Query<DegenScoreQuery>,
+) -> Result<Json<DegenScoreReport>, Error> {
+  let chain = query.chain.unwrap_or_else(|| DEFAULT_CHAIN.to_owned());
+  if !state.rpc.supports_chain(&chain) || !state.explorer.supports_chain(&chain) {
+    debug!(
+      "No RPC provider or explorer for chain {}, returning 400 status code",
+      chain
+    );
+    return Err(Error::bad_request());
+  }
+
+  let report = state.degen_score.score(&chain, &address).await;
+
+  debug!("Returning degen score");
+  Ok(Json(report))
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct DegenScoreQuery {
+  /// Chain whose transactions and approvals are read, `ethereum` by default.
+  chain: Option<String>,
+}
@@ -0,0 +1,94 @@
+use axum::response::Redirect;
+use axum::routing::get;
+use axum::{Json, Router};
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+use utoipa_swagger_ui::SwaggerUi;
+
+use crate::errors::ErrorResponse;
+use crate::routes;
+
+#[derive(OpenApi)]
+#[openapi(
+  info(title = "v1 REST API", description = "v1 REST API"),
+  components(schemas(ErrorResponse)),
+  modifiers(&SecurityAddon)
+)]
+struct ApiDoc;
+
+struct SecurityAddon;
+
+impl Modify for SecurityAddon {
+  fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+    let components = openapi.components.get_or_insert_with(Default::default);
+    components.add_security_scheme(
+      "bearerAuth",
+      SecurityScheme::Http(
+        HttpBuilder::new()
+          .scheme(HttpAuthScheme::Bearer)
+          .bearer_format("JWT")
+          .build(),
+      ),
+    );
+    components.add_security_scheme(
+      "apiKey",
+      SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("x-api-key"))),
+    );
+  }
+}
+
+/// Serves the spec at `/openapi.json` and the Swagger UI at `/docs`. The
+/// spec is still served at its former path for existing consumers, and the
+/// former Swagger UI path redirects to the new one.
+pub fn create_route() -> Router {
+  let spec = openapi();
+
+  Router::new()
+    .merge(SwaggerUi::new("/docs").url("/openapi.json", spec.clone()))
+    .route("/api-docs/openapi.json", get(|| async { Json(spec) }))
+    .route("/swagger-ui", get(redirect_to_docs))
+    .route("/swagger-ui/", get(redirect_to_docs))
+    .route("/swagger-ui/*path", get(redirect_to_docs))
+}
+
+async fn redirect_to_docs() -> Redirect {
+  Redirect::permanent("/docs/")
+}
+
+/// OpenAPI spec generated from the route handler annotations.
+pub fn openapi() -> utoipa::openapi::OpenApi {
+  let mut openapi = ApiDoc::openapi();
+  openapi.merge(routes::status::ApiDoc::openapi());
+  openapi.merge(routes::user::ApiDoc::openapi());
+  openapi.merge(routes::auth::ApiDoc::openapi());
+  openapi.merge(routes::cat::ApiDoc::openapi());
+  openapi.merge(routes::arkham::ApiDoc::openapi());
+  openapi.merge(routes::label::ApiDoc::openapi());
+  openapi.merge(routes::watchlist::ApiDoc::openapi());
+  openapi.merge(routes::alert::ApiDoc::openapi());
+  openapi.merge(routes::webhook::ApiDoc::openapi());
+  openapi.merge(routes::notification::ApiDoc::openapi());
+  openapi.merge(routes::api_key::ApiDoc::openapi());
+  openapi.merge(routes::organization::ApiDoc::openapi());
+  openapi.merge(routes::prices::ApiDoc::openapi());
+  openapi.merge(routes::transactions::ApiDoc::openapi());
+  openapi.merge(routes::balances::ApiDoc::openapi());
+  openapi.merge(routes::bitcoin::ApiDoc::openapi());
+  openapi.merge(routes::nfts::ApiDoc::openapi());
+  openapi.merge(routes::approvals::ApiDoc::openapi());
+  openapi.merge(routes::gas::ApiDoc::openapi());
+  openapi.merge(routes::portfolio::ApiDoc::openapi());
+  openapi.merge(routes::dune::ApiDoc::openapi());
+  openapi.merge(routes::risk::ApiDoc::openapi());
+  openapi.merge(routes::degen_score::ApiDoc::openapi());
+  openapi.merge(routes::usage::ApiDoc::openapi());
+  openapi.merge(routes::stats::ApiDoc::openapi());
+  openapi.merge(routes::data_export::ApiDoc::openapi());
+  openapi.merge(routes::session::ApiDoc::openapi());
+  openapi.merge(routes::mfa::ApiDoc::openapi());
+  openapi.merge(routes::jobs::ApiDoc::openapi());
+  openapi.merge(routes::address_note::ApiDoc::openapi());
+  openapi.merge(routes::address::ApiDoc::openapi());
+
+  openapi
+}
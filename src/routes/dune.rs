@@ -0,0 +1,123 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use tracing::debug;
+use utoipa::{OpenApi, ToSchema};
+
+use crate::errors::{Error, ErrorResponse};
+use crate::services::dune::{DuneColumn, DuneExecution, ExecutionState};
+use crate::state::AppState;
+use crate::utils::custom_response::{CustomResponse, CustomResponseBuilder};
+use crate::utils::json::Json;
+use crate::utils::proxy::{self, RawQuery};
+use crate::utils::query::Query;
+use crate::utils::route_table::RouteTable;
+use crate::utils::token::{MemberUser, TokenUser};
+
+#[derive(OpenApi)]
+#[openapi(
+  paths(execute_dune_query, query_dune_execution),
+  components(schemas(ExecuteQuery, DuneExecution, DuneColumn, ExecutionState))
+)]
+pub struct ApiDoc;
+
+pub fn create_route() -> RouteTable<AppState> {
+  RouteTable::new()
+    .post("/dune/query/:query_id/execute", execute_dune_query)
+    .get("/dune/execution/:id", query_dune_execution)
+}
+
+#[utoipa::path(
+  post,
+  path = "/v1/dune/query/{query_id}/execute",
+  params(("query_id" = u64, Path, description = "Dune query ID")),
+  request_body = ExecuteQuery,
+  responses(
+    (status = 202, description = "Execution started, to be polled for its results", body = DuneExecution),
+    (status = 400, description = "Invalid query ID or parameters", body = ErrorResponse),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 404, description = "Query not found", body = ErrorResponse),
+    (status = 503, description = "Dune request failed", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []))
+)]
+async fn execute_dune_query(
+  _user: MemberUser,
+  State(state): State<AppState>,
+  Path(query_id): Path<String>,
+  Json(payload): Json<ExecuteQuery>,
+) -> Result<CustomResponse<DuneExecution>, Error> {
+  let query_id = match query_id.parse::<u64>() {
+    Ok(query_id) => query_id,
+    Err(_) => {
+      debug!("Invalid Dune query ID, returning 400 status code");
+      return Err(Error::bad_request());
+    }
+  };
+
+  let execution = state
+    .dune
+    .execute_query(query_id, &payload.parameters)
+    .await?;
+
+  let res = CustomResponseBuilder::new()
+    .body(execution)
+    .status_code(StatusCode::ACCEPTED)
+    .build();
+
+  debug!("Returning Dune execution");
+  Ok(res)
+}
+
+#[utoipa::path(
+  get,
+  path = "/v1/dune/execution/{id}",
+  params(("id" = String, Path, description = "Dune execution ID"), RawQuery),
+  responses(
+    (status = 200, description = "State of the execution, with its rows once completed. Dune's own response with `raw`", body = DuneExecution),
+    (status = 400, description = "Invalid execution ID", body = ErrorResponse),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 404, description = "Execution not found", body = ErrorResponse),
+    (status = 503, description = "Dune request failed", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []))
+)]
+async fn query_dune_execution(
+  _user: TokenUser,
+  State(state): State<AppState>,
+  Path(id): Path<String>,
+  Query(query): Query<RawQuery>,
+) -> Result<Response, Error> {
+  // Execution IDs are ULIDs, anything else is kept out of the Dune URL.
+  if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric()) {
+    debug!("Invalid Dune execution ID, returning 400 status code");
+    return Err(Error::bad_request());
+  }
+
+  // Results of large queries are passed through rather than buffered.
+  if query.raw {
+    let res = state.dune.stream_execution(&id).await?;
+    debug!("Streaming Dune execution");
+    return Ok(proxy::stream_response(
+      "Dune",
+      res,
+      state.settings.proxy.chunk_bytes,
+    ));
+  }
+
+  let execution = state.dune.fetch_execution(&id).await?;
+
+  debug!("Returning Dune execution");
+  Ok(Json(execution).into_response())
+}
+
+#[derive(Debug, Default, Deserialize, ToSchema)]
+pub struct ExecuteQuery {
+  /// Values of the query parameters, by parameter name.
+  #[serde(default)]
+  #[schema(value_type = Object)]
+  pub parameters: HashMap<String, Value>,
+}
@@ -0,0 +1,41 @@
+use axum::extract::State;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+use utoipa::{OpenApi, ToSchema};
+
+use crate::errors::Error;
+use crate::services::gas::{ChainGas, PriorityFees};
+use crate::state::AppState;
+use crate::utils::json::Json;
+use crate::utils::route_table::RouteTable;
+
+#[derive(OpenApi)]
+#[openapi(
+  paths(query_gas),
+  components(schemas(GasPrices, ChainGas, PriorityFees))
+)]
+pub struct ApiDoc;
+
+pub fn create_route() -> RouteTable<AppState> {
+  RouteTable::new().get("/gas", query_gas)
+}
+
+#[utoipa::path(
+  get,
+  path = "/v1/gas",
+  responses(
+    (status = 200, description = "Gas fee suggestions by chain, failed chains carry their error", body = GasPrices)
+  )
+)]
+async fn query_gas(State(state): State<AppState>) -> Result<Json<GasPrices>, Error> {
+  let chains = state.gas.fetch_gas().await;
+
+  debug!("Returning gas fees");
+  Ok(Json(GasPrices { chains }))
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct GasPrices {
+  // Chains sorted by name.
+  pub chains: Vec<ChainGas>,
+}
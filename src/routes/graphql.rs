@@ -0,0 +1,28 @@
+use async_graphql_axum::{GraphQLRequest, GraphQLResponse};
+use axum::extract::State;
+
+use crate::errors::Error;
+use crate::graphql::loaders::Loaders;
+use crate::routes::label::labels_visible_to;
+use crate::services::ownership::Ownership;
+use crate::state::AppState;
+use crate::utils::route_table::RouteTable;
+use crate::utils::token::TokenUser;
+
+pub fn create_route() -> RouteTable<AppState> {
+  RouteTable::new().post("/graphql", execute_graphql)
+}
+
+/// Runs a GraphQL query of the authenticated user, see `graphql`. Nested
+/// fields are batched by the loaders of the request.
+async fn execute_graphql(
+  State(state): State<AppState>,
+  user: TokenUser,
+  request: GraphQLRequest,
+) -> Result<GraphQLResponse, Error> {
+  let ownership = Ownership::load(user.id).await?;
+  let loaders = Loaders::new(labels_visible_to(Some(&ownership)).await?);
+
+  let request = request.into_inner().data(ownership).data(loaders);
+  Ok(state.graphql.execute(request).await.into())
+}
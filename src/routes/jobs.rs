@@ -0,0 +1,91 @@
+use axum::extract::Path;
+use axum::http::header::{self, HeaderMap, HeaderValue};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use bson::doc;
+use tracing::debug;
+use utoipa::OpenApi;
+
+use crate::errors::{Error, ErrorResponse};
+use crate::models::job::{Job, JobKind, JobStatus, PublicJob};
+use crate::state::AppState;
+use crate::utils::json::Json;
+use crate::utils::models::ModelExt;
+use crate::utils::route_table::RouteTable;
+use crate::utils::to_object_id::to_object_id;
+use crate::utils::token::TokenUser;
+
+#[derive(OpenApi)]
+#[openapi(
+  paths(get_job_by_id),
+  components(schemas(PublicJob, JobKind, JobStatus))
+)]
+pub struct ApiDoc;
+
+// Preference of clients willing to poll the result of long requests, see
+// RFC 7240.
+const RESPOND_ASYNC: &str = "respond-async";
+
+pub fn create_route() -> RouteTable<AppState> {
+  RouteTable::new().get("/jobs/:id", get_job_by_id)
+}
+
+/// Status of a job queued by the user, e.g. by a request sent with
+/// `Prefer: respond-async`. Succeeded jobs carry their result.
+#[utoipa::path(
+  get,
+  path = "/v1/jobs/{id}",
+  params(("id" = String, Path, description = "Job id")),
+  responses(
+    (status = 200, description = "Job", body = PublicJob),
+    (status = 400, description = "Invalid job id", body = ErrorResponse),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 404, description = "Job not found", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []), ("apiKey" = []))
+)]
+async fn get_job_by_id(user: TokenUser, Path(id): Path<String>) -> Result<Json<PublicJob>, Error> {
+  let job_id = to_object_id(id)?;
+  let job = Job::find_one(doc! { "_id": job_id, "user": &user.id }, None)
+    .await?
+    .map(PublicJob::from);
+
+  let job = match job {
+    Some(job) => job,
+    None => {
+      debug!("Job not found, returning 404 status code");
+      return Err(Error::not_found());
+    }
+  };
+
+  debug!("Returning job");
+  Ok(Json(job))
+}
+
+/// Whether the client prefers a queued job to waiting for the response, with
+/// a `Prefer: respond-async` header.
+pub fn respond_async(headers: &HeaderMap) -> bool {
+  headers
+    .get_all(header::HeaderName::from_static("prefer"))
+    .iter()
+    .filter_map(|value| value.to_str().ok())
+    .flat_map(|value| value.split(','))
+    .any(|preference| {
+      let name = preference.split([';', '=']).next().unwrap_or_default();
+      name.trim().eq_ignore_ascii_case(RESPOND_ASYNC)
+    })
+}
+
+/// 202 response to a request queued as a job, pointing to the job to poll.
+pub fn accepted(job: Job) -> Response {
+  let location = format!("/v1/jobs/{}", job.id.unwrap());
+  let headers = [
+    (header::LOCATION, HeaderValue::from_str(&location).unwrap()),
+    (
+      header::HeaderName::from_static("preference-applied"),
+      HeaderValue::from_static(RESPOND_ASYNC),
+    ),
+  ];
+
+  (StatusCode::ACCEPTED, headers, Json(PublicJob::from(job))).into_response()
+}
@@ -0,0 +1,906 @@
+use axum::extract::{BodyStream, OriginalUri, Path, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use bson::oid::ObjectId;
+use bson::{doc, Bson, Document};
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tracing::{debug, warn};
+use utoipa::{IntoParams, OpenApi, ToSchema};
+use wither::mongodb::options::FindOptions;
+
+use crate::errors::{Error, ErrorResponse};
+use crate::models::address_label::{
+  self, AddressLabel, LabelSuggestion, PublicAddressLabel, ADDRESS_LABEL_FILTER_FIELDS,
+  ADDRESS_LABEL_SORT_FIELDS,
+};
+use crate::models::job::{JobKind, PublicJob};
+use crate::models::label_change::{LabelChange, PublicLabelChange};
+use crate::models::user::Role;
+use crate::models::user_address_note::UserAddressNote;
+use crate::routes::jobs::{accepted, respond_async};
+use crate::services::jobs::{self, LabelImport};
+use crate::services::label_dataset;
+use crate::services::label_history;
+use crate::services::label_merge::{self, Candidates, MergedLabels, SourcedValue};
+use crate::services::ownership::Ownership;
+use crate::state::AppState;
+use crate::utils::address::{normalize_evm_address, EvmAddress};
+use crate::utils::authenticate_request::require_role;
+use crate::utils::body_limit;
+use crate::utils::custom_response::{CustomResponse, CustomResponseBuilder};
+use crate::utils::date::{self, Date};
+use crate::utils::json::Json;
+use crate::utils::links::Link;
+use crate::utils::models::ModelExt;
+use crate::utils::ndjson::stream_ndjson;
+use crate::utils::pagination::{Cursor, Pagination};
+use crate::utils::query::Query;
+use crate::utils::query_fields::Filters;
+use crate::utils::request_query::RequestQuery;
+use crate::utils::response_format::{FormatQuery, ResponseFormat};
+use crate::utils::route_table::RouteTable;
+use crate::utils::serde_helpers::deserialize_optional_rfc3339;
+use crate::utils::to_object_id::to_object_id;
+use crate::utils::token::{AdminUser, MemberUser, TokenUser};
+
+#[derive(OpenApi)]
+#[openapi(
+  paths(
+    search_labels,
+    suggest_labels,
+    export_labels,
+    query_labels_by_address,
+    query_merged_labels,
+    query_label_history,
+    create_label,
+    import_labels,
+    remove_label_by_id
+  ),
+  components(schemas(
+    PublicAddressLabel,
+    LabelSuggestion,
+    Link,
+    CreateLabel,
+    ImportSummary,
+    ImportFailure,
+    MergedLabels,
+    SourcedValue,
+    PublicLabelChange
+  ))
+)]
+pub struct ApiDoc;
+
+// Number of labels sent to MongoDB by each insert of an import.
+const IMPORT_BATCH_SIZE: usize = 500;
+// Failed lines beyond this are only counted, so a malformed file doesn't
+// produce a response as large as itself.
+const MAX_REPORTED_FAILURES: usize = 100;
+// Queued imports are stored in their job, below the 16MB limit of MongoDB
+// documents.
+const MAX_QUEUED_IMPORT_BYTES: usize = 15 * 1024 * 1024;
+
+pub fn create_route() -> RouteTable<AppState> {
+  RouteTable::new()
+    .post("/labels", create_label)
+    .post("/labels/import", import_labels)
+    .get("/labels/search", search_labels)
+    .get("/labels/suggest", suggest_labels)
+    .get("/labels/export", export_labels)
+    .get("/labels/:address", query_labels_by_address)
+    .get("/labels/:address/merged", query_merged_labels)
+    .get("/labels/:address/history", query_label_history)
+    // axum requires routes sharing a path to name its parameters the same,
+    // the segment holds the label id here.
+    .delete("/labels/:address", remove_label_by_id)
+}
+
+#[utoipa::path(
+  post,
+  path = "/v1/labels",
+  request_body = CreateLabel,
+  responses(
+    (status = 201, description = "Label created", body = PublicAddressLabel),
+    (status = 400, description = "Invalid address, organization id or empty name", body = ErrorResponse),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 403, description = "User is not an admin of the app or a member of the organization", body = ErrorResponse),
+    (status = 404, description = "Organization not found", body = ErrorResponse),
+    (status = 409, description = "The source already labelled the address", body = ErrorResponse),
+    (status = 422, description = "Invalid request body", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []))
+)]
+async fn create_label(
+  MemberUser(user): MemberUser,
+  Json(payload): Json<CreateLabel>,
+) -> Result<CustomResponse<PublicAddressLabel>, Error> {
+  let organization = payload
+    .organization
+    .as_deref()
+    .map(to_object_id)
+    .transpose()?;
+  check_label_owner(&user, organization).await?;
+
+  let mut label = payload.into_label().map_err(|reason| {
+    debug!("Invalid label ({}), returning 400 status code", reason);
+    Error::bad_request()
+  })?;
+  label.organization = organization;
+
+  let query = doc! {
+    "eth_address": &label.eth_address,
+    "source": &label.source,
+    "organization": organization,
+    "dataset": Bson::Null,
+  };
+  if AddressLabel::exists(query).await? {
+    debug!("Label already stored, returning 409 status code");
+    return Err(Error::conflict());
+  }
+
+  let label = AddressLabel::create(label).await?;
+  let change = LabelChange::new(&label, None, Some(label.name.clone()));
+  label_history::record(vec![change]).await;
+  let res = PublicAddressLabel::from(label);
+
+  let res = CustomResponseBuilder::new()
+    .body(res)
+    .status_code(StatusCode::CREATED)
+    .build();
+
+  Ok(res)
+}
+
+/// Imports newline-delimited JSON labels, one `CreateLabel` object per line.
+/// Imported labels are public, the `organization` of the lines is ignored.
+/// The body is streamed and inserted in batches, so large files are never
+/// held in memory, unless an `Idempotency-Key` is sent. Invalid lines are reported and don't abort the import.
+/// Labels are upserted on their address and source: labels already stored
+/// with the same name are skipped, the others are renamed.
+/// With `Prefer: respond-async`, the import is queued as a job whose result
+/// is the summary, for files up to 15MB.
+#[utoipa::path(
+  post,
+  path = "/v1/labels/import",
+  params(
+    ("Idempotency-Key" = Option<String>, Header, description = "Key replaying the first response when the import is retried"),
+    ("Prefer" = Option<String>, Header, description = "`respond-async` to queue the import as a job")
+  ),
+  request_body(content = String, content_type = "application/x-ndjson"),
+  responses(
+    (status = 200, description = "Import summary", body = ImportSummary),
+    (status = 202, description = "Import queued, its job is polled at the `Location` URL", body = PublicJob),
+    (status = 400, description = "Request body could not be read", body = ErrorResponse),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 403, description = "User is not an admin", body = ErrorResponse),
+    (status = 409, description = "Import with the same idempotency key still running", body = ErrorResponse),
+    (status = 413, description = "Request body is too large, or too large to be queued", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []))
+)]
+async fn import_labels(
+  AdminUser(admin): AdminUser,
+  headers: HeaderMap,
+  body: BodyStream,
+) -> Result<Response, Error> {
+  if respond_async(&headers) {
+    let body = read_queued_import(body).await?;
+    let job = jobs::enqueue(admin.id, JobKind::LabelImport, &LabelImport { body }).await?;
+
+    debug!("Queued label import");
+    return Ok(accepted(job));
+  }
+
+  let summary = import_stream(body).await?;
+  debug!(
+    "Imported labels: {} inserted, {} updated, {} skipped, {} failed",
+    summary.inserted, summary.updated, summary.skipped, summary.failed
+  );
+  Ok(Json(summary).into_response())
+}
+
+async fn import_stream(mut body: BodyStream) -> Result<ImportSummary, Error> {
+  let mut summary = ImportSummary::default();
+  let mut batch = Vec::with_capacity(IMPORT_BATCH_SIZE);
+  let mut buffer = Vec::new();
+  let mut line_number = 0;
+
+  while let Some(chunk) = body.next().await {
+    let chunk = chunk.map_err(read_error)?;
+    buffer.extend_from_slice(&chunk);
+
+    while let Some(position) = buffer.iter().position(|byte| *byte == b'\n') {
+      let line = buffer.drain(..=position).collect::<Vec<u8>>();
+      line_number += 1;
+      summary.parse_line(line_number, &line, &mut batch);
+
+      if batch.len() >= IMPORT_BATCH_SIZE {
+        insert_batch(&mut batch, &mut summary, None).await?;
+      }
+    }
+  }
+
+  // The last line is not required to end with a newline.
+  if !buffer.is_empty() {
+    summary.parse_line(line_number + 1, &buffer, &mut batch);
+  }
+  insert_batch(&mut batch, &mut summary, None).await?;
+
+  Ok(summary)
+}
+
+async fn read_queued_import(mut body: BodyStream) -> Result<String, Error> {
+  let mut buffer = Vec::new();
+  while let Some(chunk) = body.next().await {
+    let chunk = chunk.map_err(read_error)?;
+    buffer.extend_from_slice(&chunk);
+
+    if buffer.len() > MAX_QUEUED_IMPORT_BYTES {
+      debug!("Import too large to be queued, returning 413 status code");
+      return Err(Error::PayloadTooLarge(MAX_QUEUED_IMPORT_BYTES));
+    }
+  }
+
+  String::from_utf8(buffer).map_err(|_| {
+    debug!("Import is not UTF-8, returning 400 status code");
+    Error::bad_request()
+  })
+}
+
+/// Error of an import body that couldn't be read, a 413 when it went over
+/// the body size limit of the route.
+fn read_error(err: axum::Error) -> Error {
+  if let Some(limit) = body_limit::too_large(&err) {
+    debug!("Import over {} bytes, returning 413 status code", limit);
+    return Error::PayloadTooLarge(limit);
+  }
+
+  debug!("Failed to read import body: {}", err);
+  Error::bad_request()
+}
+
+/// Imports newline-delimited JSON labels held in memory, like
+/// `import_labels` does with request bodies. Labels of a dataset version are
+/// only checked against the labels of that version, see
+/// `services::label_dataset`.
+pub async fn import_ndjson(body: &[u8], dataset: Option<ObjectId>) -> Result<ImportSummary, Error> {
+  let mut summary = ImportSummary::default();
+  let mut batch = Vec::with_capacity(IMPORT_BATCH_SIZE);
+
+  // A trailing newline doesn't start a blank line.
+  let body = body.strip_suffix(b"\n").unwrap_or(body);
+  for (index, line) in body.split(|byte| *byte == b'\n').enumerate() {
+    summary.parse_line(index as u64 + 1, line, &mut batch);
+
+    if batch.len() >= IMPORT_BATCH_SIZE {
+      insert_batch(&mut batch, &mut summary, dataset).await?;
+    }
+  }
+  insert_batch(&mut batch, &mut summary, dataset).await?;
+
+  Ok(summary)
+}
+
+/// Stores the labels of the batch, emptying it. A source labels an address
+/// once: labels already stored with the same name are skipped, the ones with
+/// another name are renamed. The last line of a label wins over the previous
+/// ones of the batch, which are skipped.
+async fn insert_batch(
+  batch: &mut Vec<AddressLabel>,
+  summary: &mut ImportSummary,
+  dataset: Option<ObjectId>,
+) -> Result<(), Error> {
+  if batch.is_empty() {
+    return Ok(());
+  }
+
+  let batch_size = batch.len();
+  let mut labels: Vec<AddressLabel> = Vec::with_capacity(batch_size);
+  let mut positions = HashMap::new();
+  for label in batch.drain(..) {
+    let key = (label.eth_address.clone(), label.source.clone());
+    match positions.get(&key) {
+      Some(&position) => labels[position] = label,
+      None => {
+        positions.insert(key, labels.len());
+        labels.push(label);
+      }
+    }
+  }
+  summary.skipped += (batch_size - labels.len()) as u64;
+
+  let addresses = labels
+    .iter()
+    .map(|label| label.eth_address.as_str())
+    .collect::<Vec<&str>>();
+  let query = doc! {
+    "eth_address": { "$in": addresses },
+    "organization": Bson::Null,
+    "dataset": dataset,
+  };
+  let stored = AddressLabel::find(query, None)
+    .await?
+    .into_iter()
+    .map(|label| ((label.eth_address, label.source), label.name))
+    .collect::<HashMap<_, _>>();
+
+  let mut inserted = Vec::new();
+  let mut changes = Vec::new();
+  for label in labels {
+    let key = (label.eth_address.clone(), label.source.clone());
+    match stored.get(&key) {
+      None => inserted.push(AddressLabel { dataset, ..label }),
+      Some(name) if *name == label.name => summary.skipped += 1,
+      Some(name) => {
+        changes.push(LabelChange::new(
+          &label,
+          Some(name.clone()),
+          Some(label.name.clone()),
+        ));
+        let query = doc! {
+          "eth_address": &label.eth_address,
+          "source": &label.source,
+          "organization": Bson::Null,
+          "dataset": dataset,
+        };
+        let update = doc! { "$set": { "name": &label.name, "updated_at": date::now() } };
+        AddressLabel::update_one(query, update, None).await?;
+        summary.updated += 1;
+      }
+    }
+  }
+  changes.extend(
+    inserted
+      .iter()
+      .map(|label| LabelChange::new(label, None, Some(label.name.clone()))),
+  );
+  summary.inserted += AddressLabel::insert_many(inserted).await?;
+
+  // The changes of a dataset version are recorded once it's activated.
+  if dataset.is_none() {
+    label_history::record(changes).await;
+  }
+
+  Ok(())
+}
+
+/// Removes the labels stored more than once by a source for an address,
+/// keeping the last updated one, so the unique index of labels can be
+/// created. Run by `rustapi --dedupe`, returns the number of removed labels.
+pub async fn remove_duplicates() -> Result<u64, Error> {
+  // Missing fields are grouped with null ones, like the unique index does.
+  let pipeline = vec![
+    doc! { "$sort": { "updated_at": -1, "_id": -1 } },
+    doc! { "$group": {
+      "_id": {
+        "eth_address": "$eth_address",
+        "source": "$source",
+        "organization": { "$ifNull": ["$organization", Bson::Null] },
+        "dataset": { "$ifNull": ["$dataset", Bson::Null] }
+      },
+      "ids": { "$push": "$_id" }
+    } },
+    doc! { "$match": { "ids.1": { "$exists": true } } },
+  ];
+  let duplicates = AddressLabel::aggregate::<DuplicatedLabels>(pipeline)
+    .await?
+    .into_iter()
+    .flat_map(|duplicated| duplicated.ids.into_iter().skip(1))
+    .collect::<Vec<ObjectId>>();
+
+  let mut removed = 0;
+  for ids in duplicates.chunks(IMPORT_BATCH_SIZE) {
+    removed += AddressLabel::delete_many(doc! { "_id": { "$in": ids } }).await?;
+  }
+
+  Ok(removed)
+}
+
+/// Full-text search on the label names and sources, most relevant labels
+/// first.
+#[utoipa::path(
+  get,
+  path = "/v1/labels/search",
+  params(LabelSearch, RequestQuery, FormatQuery),
+  responses(
+    (
+      status = 200,
+      description = "Paginated labels matching the search",
+      body = [PublicAddressLabel],
+      content_type = ["application/json", "text/csv"],
+      headers(
+        ("x-pagination-count" = u64, description = "Total number of matching labels"),
+        ("x-pagination-offset" = u64, description = "Offset of the returned page"),
+        ("x-pagination-limit" = u64, description = "Size of the returned page"),
+        ("x-pagination-total-pages" = u64, description = "Total number of pages"),
+        ("x-pagination-has-next" = bool, description = "Whether there are labels after the returned page")
+      )
+    ),
+    (status = 400, description = "Missing search text or invalid query parameters", body = ErrorResponse)
+  )
+)]
+async fn search_labels(
+  user: Option<TokenUser>,
+  Query(search): Query<LabelSearch>,
+  Query(query): Query<RequestQuery>,
+  OriginalUri(uri): OriginalUri,
+  format: ResponseFormat,
+) -> Result<CustomResponse<Vec<PublicAddressLabel>>, Error> {
+  let text = search.q.trim();
+  if text.is_empty() {
+    debug!("Empty label search, returning 400 status code");
+    return Err(Error::bad_request());
+  }
+  // Results are sorted by relevance, which cursors can't resume from.
+  if query.cursor.is_some() {
+    debug!("Cursor on label search, returning 400 status code");
+    return Err(Error::InvalidQuery(
+      "Label search doesn't support cursors".to_owned(),
+    ));
+  }
+
+  let pagination = Pagination::build_from_request_query(query).uri(uri);
+  let options = FindOptions::builder()
+    .projection(doc! { "score": { "$meta": "textScore" } })
+    .sort(doc! { "score": { "$meta": "textScore" }, "created_at": -1_i32 })
+    .skip(pagination.offset)
+    .limit(pagination.limit as i64)
+    .build();
+
+  let mut query_filter = visible_labels(user).await?;
+  query_filter.insert("$text", doc! { "$search": text });
+  let (labels, count) = AddressLabel::find_and_count(query_filter, options).await?;
+  let labels = labels
+    .into_iter()
+    .map(Into::into)
+    .collect::<Vec<PublicAddressLabel>>();
+
+  let res = CustomResponseBuilder::new()
+    .body(labels)
+    .pagination(pagination.count(count).build())
+    .format(format)
+    .build();
+
+  debug!("Returning searched labels");
+  Ok(res)
+}
+
+/// Labels whose name starts with the text regardless of its case, sorted
+/// by name, for search-as-you-type UIs. Suggestions are cached for a short
+/// while, see `label_suggest.cache_ttl_secs`.
+#[utoipa::path(
+  get,
+  path = "/v1/labels/suggest",
+  params(LabelSuggestQuery),
+  responses(
+    (status = 200, description = "Labels whose name starts with the text", body = [LabelSuggestion]),
+    (status = 400, description = "Missing text", body = ErrorResponse)
+  )
+)]
+async fn suggest_labels(
+  State(state): State<AppState>,
+  user: Option<TokenUser>,
+  Query(query): Query<LabelSuggestQuery>,
+) -> Result<Json<Vec<LabelSuggestion>>, Error> {
+  let prefix = query.q.trim();
+  if prefix.is_empty() {
+    debug!("Empty label suggestion text, returning 400 status code");
+    return Err(Error::bad_request());
+  }
+
+  let settings = &state.settings.label_suggest;
+  let limit = query
+    .limit
+    .unwrap_or(settings.default_limit)
+    .clamp(1, settings.max_limit);
+  let mut query_filter = visible_labels(user).await?;
+  // Users see different labels, the filter is part of the key.
+  let key = format!("{}:{}:{}", query_filter, limit, prefix.to_lowercase());
+  if let Some(entry) = state.label_suggestions.get(&key).await {
+    debug!("Returning cached label suggestions");
+    return Ok(Json(entry.value));
+  }
+
+  // U+FFFF sorts after every character in collations, the range holds every
+  // name starting with the prefix.
+  query_filter.insert(
+    "name",
+    doc! { "$gte": prefix, "$lt": format!("{}\u{FFFF}", prefix) },
+  );
+  let options = FindOptions::builder()
+    .collation(address_label::name_collation())
+    .sort(doc! { "name": 1_i32 })
+    .limit(limit as i64)
+    .build();
+  let suggestions = AddressLabel::find(query_filter, options)
+    .await?
+    .into_iter()
+    .map(Into::into)
+    .collect::<Vec<LabelSuggestion>>();
+  state
+    .label_suggestions
+    .insert(key, suggestions.clone())
+    .await;
+
+  debug!("Returning label suggestions");
+  Ok(Json(suggestions))
+}
+
+/// Streams every label visible to the user as NDJSON, oldest first, without
+/// pagination. Meant for full dumps, which can hold millions of labels.
+#[utoipa::path(
+  get,
+  path = "/v1/labels/export",
+  responses(
+    (status = 200, description = "Visible labels, one JSON object per line", content_type = "application/x-ndjson", body = PublicAddressLabel)
+  )
+)]
+async fn export_labels(user: Option<TokenUser>) -> Result<Response, Error> {
+  let query_filter = visible_labels(user).await?;
+  let options = FindOptions::builder()
+    .sort(doc! { "created_at": 1_i32, "_id": 1_i32 })
+    .build();
+  let cursor = AddressLabel::cursor(query_filter, options).await?;
+
+  debug!("Streaming labels export");
+  Ok(stream_ndjson(cursor, PublicAddressLabel::from))
+}
+
+#[utoipa::path(
+  get,
+  path = "/v1/labels/{address}",
+  params(("address" = String, Path, description = "EVM address"), RequestQuery, FormatQuery),
+  responses(
+    (
+      status = 200,
+      description = "Paginated labels of the address",
+      body = [PublicAddressLabel],
+      content_type = ["application/json", "text/csv"],
+      headers(
+        ("x-pagination-count" = u64, description = "Total number of labels"),
+        ("x-pagination-offset" = u64, description = "Offset of the returned page"),
+        ("x-pagination-limit" = u64, description = "Size of the returned page"),
+        ("x-pagination-total-pages" = u64, description = "Total number of pages"),
+        ("x-pagination-has-next" = bool, description = "Whether there are labels after the returned page"),
+        ("x-pagination-next-cursor" = String, description = "Cursor of the next page, only sent for full pages")
+      )
+    ),
+    (status = 400, description = "Invalid address or query parameters", body = ErrorResponse),
+    (status = 422, description = "Unsupported sort or filter field", body = ErrorResponse)
+  )
+)]
+async fn query_labels_by_address(
+  user: Option<TokenUser>,
+  EvmAddress(eth_address): EvmAddress,
+  Query(query): Query<RequestQuery>,
+  OriginalUri(uri): OriginalUri,
+  filters: Filters,
+  format: ResponseFormat,
+) -> Result<CustomResponse<Vec<PublicAddressLabel>>, Error> {
+  let sort = query.sort(ADDRESS_LABEL_SORT_FIELDS)?;
+
+  let mut query_filter = visible_labels(user).await?;
+  query_filter.insert("eth_address", &eth_address);
+  query.filter_created(&mut query_filter);
+  filters.apply(ADDRESS_LABEL_FILTER_FIELDS, &mut query_filter)?;
+
+  let pagination = Pagination::build_from_request_query(query).uri(uri);
+  let options = FindOptions::builder()
+    .sort(sort)
+    .skip(pagination.offset)
+    .limit(pagination.limit as i64)
+    .build();
+
+  let (labels, count) =
+    AddressLabel::find_page_and_count(query_filter, pagination.after_cursor(), options).await?;
+  let pagination = pagination.next_cursor(&labels, |label| {
+    Cursor::new(label.created_at, label.id.unwrap())
+  });
+  let labels = labels
+    .into_iter()
+    .map(Into::into)
+    .collect::<Vec<PublicAddressLabel>>();
+
+  let res = CustomResponseBuilder::new()
+    .body(labels)
+    .pagination(pagination.count(count).build())
+    .format(format)
+    .build();
+
+  debug!("Returning labels");
+  Ok(res)
+}
+
+/// Consolidated view of an address across the Arkham intelligence, the
+/// stored labels and the note of the user, each field taken from the most
+/// trusted source that has it, see `settings::LabelMerge`. Arkham being
+/// unavailable doesn't fail the request, it's reported instead.
+#[utoipa::path(
+  get,
+  path = "/v1/labels/{address}/merged",
+  params(("address" = String, Path, description = "EVM address")),
+  responses(
+    (status = 200, description = "Merged labels of the address", body = MergedLabels),
+    (status = 400, description = "Invalid address", body = ErrorResponse)
+  )
+)]
+async fn query_merged_labels(
+  State(state): State<AppState>,
+  user: Option<TokenUser>,
+  EvmAddress(eth_address): EvmAddress,
+) -> Result<Json<MergedLabels>, Error> {
+  let user_id = user.as_ref().map(|user| user.id);
+  let mut query_filter = visible_labels(user).await?;
+  query_filter.insert("eth_address", &eth_address);
+  let options = FindOptions::builder()
+    .sort(doc! { "created_at": -1_i32 })
+    .build();
+  let note = async {
+    match user_id {
+      Some(user_id) => {
+        let query = doc! { "user": user_id, "address": &eth_address };
+        UserAddressNote::find_one(query, None).await
+      }
+      None => Ok(None),
+    }
+  };
+
+  let (arkham, labels, note) = tokio::join!(
+    state.address_intelligence.lookup_address(&eth_address),
+    AddressLabel::find(query_filter, options),
+    note,
+  );
+
+  let mut candidates = Candidates::default();
+  let mut unavailable_sources = Vec::new();
+  match arkham {
+    Ok(arkham) => candidates.add_arkham(&arkham),
+    // Addresses unknown to Arkham have no entity or label.
+    Err(Error::NotFound(_)) => {}
+    Err(err) => {
+      warn!("Failed to look up {} on Arkham: {}", eth_address, err);
+      unavailable_sources.push(label_merge::ARKHAM_SOURCE.to_owned());
+    }
+  }
+  candidates.add_labels(labels?);
+  if let Some(note) = note? {
+    candidates.add_note(&note);
+  }
+
+  let priority = &state.settings.label_merge.priority;
+  let merged = label_merge::merge(priority, eth_address, candidates, unavailable_sources);
+
+  debug!("Returning merged labels");
+  Ok(Json(merged))
+}
+
+/// Changes of the labels of an address visible to the user, newest first:
+/// labels created, renamed by imports and removed. With `as_of`, what the
+/// address was labelled as at that time instead, as the last change of each
+/// of its labels until then.
+#[utoipa::path(
+  get,
+  path = "/v1/labels/{address}/history",
+  params(("address" = String, Path, description = "EVM address"), HistoryQuery),
+  responses(
+    (status = 200, description = "Label changes of the address", body = [PublicLabelChange]),
+    (status = 400, description = "Invalid address or date", body = ErrorResponse)
+  )
+)]
+async fn query_label_history(
+  user: Option<TokenUser>,
+  EvmAddress(eth_address): EvmAddress,
+  Query(query): Query<HistoryQuery>,
+) -> Result<Json<Vec<PublicLabelChange>>, Error> {
+  let ownership = load_ownership(user).await?;
+  let query_filter = doc! { "organization": organizations_visible_to(ownership.as_ref()) };
+
+  let changes = match query.as_of {
+    Some(as_of) => label_history::labels_as_of(&eth_address, query_filter, as_of).await?,
+    None => label_history::changes(&eth_address, query_filter).await?,
+  };
+  let changes = changes
+    .into_iter()
+    .map(Into::into)
+    .collect::<Vec<PublicLabelChange>>();
+
+  debug!("Returning label history");
+  Ok(Json(changes))
+}
+
+#[utoipa::path(
+  delete,
+  path = "/v1/labels/{id}",
+  params(("id" = String, Path, description = "Label id")),
+  responses(
+    (status = 204, description = "Label removed"),
+    (status = 400, description = "Invalid label id", body = ErrorResponse),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 403, description = "User is not an admin of the app or a member of the organization", body = ErrorResponse),
+    (status = 404, description = "Label not found", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []))
+)]
+async fn remove_label_by_id(
+  MemberUser(user): MemberUser,
+  Path(id): Path<String>,
+) -> Result<CustomResponse<()>, Error> {
+  let label_id = to_object_id(id)?;
+  let label = match AddressLabel::find_by_id(&label_id).await? {
+    Some(label) => label,
+    None => {
+      debug!("Label not found, returning 404 status code");
+      return Err(Error::not_found());
+    }
+  };
+  check_label_owner(&user, label.organization).await?;
+
+  AddressLabel::delete_one(doc! { "_id": label_id }).await?;
+  let change = LabelChange::new(&label, Some(label.name.clone()), None);
+  label_history::record(vec![change]).await;
+
+  let res = CustomResponseBuilder::new()
+    .status_code(StatusCode::NO_CONTENT)
+    .build();
+
+  Ok(res)
+}
+
+/// Public labels are managed by admins, labels of an organization by its
+/// members.
+async fn check_label_owner(user: &TokenUser, organization: Option<ObjectId>) -> Result<(), Error> {
+  match organization {
+    Some(_) => Ownership::load(user.id)
+      .await?
+      .check(organization, Role::Member),
+    None => require_role(user, Role::Admin),
+  }
+}
+
+/// Filter matching the public labels and, for authenticated requests, the
+/// labels of the organizations of the user.
+async fn visible_labels(user: Option<TokenUser>) -> Result<Document, Error> {
+  let ownership = load_ownership(user).await?;
+
+  labels_visible_to(ownership.as_ref()).await
+}
+
+/// Same as `visible_labels`, for callers that already loaded the ownership.
+pub async fn labels_visible_to(ownership: Option<&Ownership>) -> Result<Document, Error> {
+  Ok(doc! {
+    "organization": organizations_visible_to(ownership),
+    "dataset": label_dataset::visible_datasets().await?,
+  })
+}
+
+async fn load_ownership(user: Option<TokenUser>) -> Result<Option<Ownership>, Error> {
+  match user {
+    Some(user) => Ok(Some(Ownership::load(user.id).await?)),
+    None => Ok(None),
+  }
+}
+
+/// Filter on the `organization` of the labels visible to the user: public
+/// ones and the ones of their organizations.
+pub fn organizations_visible_to(ownership: Option<&Ownership>) -> Document {
+  let mut organizations = vec![Bson::Null];
+  if let Some(ownership) = ownership {
+    organizations.extend(
+      ownership
+        .organizations(Role::ReadOnly)
+        .into_iter()
+        .map(Bson::ObjectId),
+    );
+  }
+
+  doc! { "$in": organizations }
+}
+
+#[derive(Deserialize, ToSchema)]
+struct CreateLabel {
+  eth_address: String,
+  name: String,
+  source: String,
+  // Makes the label only visible to the members of the organization.
+  organization: Option<String>,
+}
+
+impl CreateLabel {
+  fn into_label(self) -> Result<AddressLabel, String> {
+    let eth_address =
+      normalize_evm_address(&self.eth_address).map_err(|_| String::from("invalid address"))?;
+    let name = self.name.trim();
+    let source = self.source.trim();
+    if name.is_empty() || source.is_empty() {
+      return Err(String::from("empty name or source"));
+    }
+
+    Ok(AddressLabel::new(
+      eth_address,
+      name.to_owned(),
+      source.to_owned(),
+    ))
+  }
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct HistoryQuery {
+  /// Returns the labels of the address at this RFC 3339 date instead of
+  /// its changes.
+  #[serde(default, deserialize_with = "deserialize_optional_rfc3339")]
+  #[param(value_type = Option<String>, format = DateTime)]
+  as_of: Option<Date>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct LabelSearch {
+  /// Words to look for in the label names and sources, e.g. `binance`.
+  q: String,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct LabelSuggestQuery {
+  /// Start of the label names, e.g. `bin`.
+  q: String,
+  /// Maximum number of suggestions, 10 by default and at most 25.
+  limit: Option<u64>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema)]
+pub struct ImportSummary {
+  pub inserted: u64,
+  // Labels already stored whose name changed.
+  pub updated: u64,
+  // Blank lines, labels already stored with the same name and lines
+  // repeating a label.
+  pub skipped: u64,
+  pub failed: u64,
+  // The first failed lines, see `MAX_REPORTED_FAILURES`.
+  pub failures: Vec<ImportFailure>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ImportFailure {
+  // 1-based line number in the request body.
+  pub line: u64,
+  pub reason: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct DuplicatedLabels {
+  // Newest label first.
+  ids: Vec<ObjectId>,
+}
+
+impl ImportSummary {
+  /// Parses an import line, adding the label to the batch or recording why
+  /// the line was rejected.
+  fn parse_line(&mut self, line_number: u64, line: &[u8], batch: &mut Vec<AddressLabel>) {
+    let line = line.trim_ascii();
+    if line.is_empty() {
+      self.skipped += 1;
+      return;
+    }
+
+    let label = serde_json::from_slice::<CreateLabel>(line)
+      .map_err(|err| format!("invalid JSON: {}", err))
+      .and_then(CreateLabel::into_label);
+
+    match label {
+      Ok(label) => batch.push(label),
+      Err(reason) => {
+        self.failed += 1;
+        if self.failures.len() < MAX_REPORTED_FAILURES {
+          self.failures.push(ImportFailure {
+            line: line_number,
+            reason,
+          });
+        }
+      }
+    }
+  }
+}
@@ -0,0 +1,116 @@
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{State, TypedHeader};
+use axum::headers::{authorization::Bearer, Authorization};
+use axum::response::Response;
+use serde::Deserialize;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::{debug, warn};
+
+use crate::errors::{AuthenticateError, Error};
+use crate::services::live::{LiveEvent, LiveMessage, Subscription, SubscriptionFilter};
+use crate::state::AppState;
+use crate::utils::authenticate_request::decode_user;
+use crate::utils::query::Query;
+use crate::utils::route_table::RouteTable;
+use crate::utils::telemetry;
+
+pub fn create_route() -> RouteTable<AppState> {
+  RouteTable::new().get("/ws", connect)
+}
+
+/// Upgrades to a WebSocket streaming the alerts and watchlist updates of the
+/// user. The connection is authenticated and its filters are checked before
+/// upgrading, so failures are returned as regular error responses.
+async fn connect(
+  State(state): State<AppState>,
+  bearer: Option<TypedHeader<Authorization<Bearer>>>,
+  Query(query): Query<ConnectQuery>,
+  ws: WebSocketUpgrade,
+) -> Result<Response, Error> {
+  // Browsers can't set headers on WebSocket handshakes, so the token can also
+  // be sent as a query parameter.
+  let token = match (&bearer, &query.token) {
+    (Some(TypedHeader(Authorization(bearer))), _) => bearer.token(),
+    (None, Some(token)) => token.as_str(),
+    (None, None) => {
+      debug!("Missing access token, returning 401 status code");
+      return Err(Error::Authenticate(AuthenticateError::InvalidToken));
+    }
+  };
+  let user = decode_user(token).await?;
+  telemetry::record_user(user.id);
+
+  let filter = SubscriptionFilter::from_query(query.watchlists.as_deref(), query.chains.as_deref());
+  let subscription = Subscription::new(user.id, filter).await?;
+  // Subscribed before upgrading, so no event is missed during the handshake.
+  let events = state.live_events.subscribe();
+
+  debug!("Upgrading to a WebSocket for user {}", user.id);
+  Ok(ws.on_upgrade(move |socket| stream_events(socket, subscription, events)))
+}
+
+async fn stream_events(
+  mut socket: WebSocket,
+  mut subscription: Subscription,
+  mut events: broadcast::Receiver<LiveEvent>,
+) {
+  loop {
+    tokio::select! {
+      event = events.recv() => {
+        let message = match event {
+          Ok(event) if subscription.accepts(&event) => event.to_message(),
+          Ok(_) => continue,
+          Err(RecvError::Lagged(skipped)) => {
+            warn!("WebSocket client lagging behind, skipped {} events", skipped);
+            LiveMessage::Lagged { skipped }
+          }
+          Err(RecvError::Closed) => break,
+        };
+
+        if send(&mut socket, &message).await.is_err() {
+          break;
+        }
+      }
+      message = socket.recv() => match message {
+        // Text messages replace the subscription filters.
+        Some(Ok(Message::Text(text))) => {
+          let user = subscription.user();
+          let result = match SubscriptionFilter::from_message(&text) {
+            Ok(filter) => Subscription::new(user, filter).await,
+            Err(err) => Err(err),
+          };
+
+          match result {
+            Ok(updated) => subscription = updated,
+            Err(err) => {
+              let message = LiveMessage::Error { message: err.to_string() };
+              if send(&mut socket, &message).await.is_err() {
+                break;
+              }
+            }
+          }
+        }
+        Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+        // Pings are answered by axum.
+        Some(Ok(_)) => {}
+      }
+    }
+  }
+
+  debug!("WebSocket closed");
+}
+
+async fn send(socket: &mut WebSocket, message: &LiveMessage) -> Result<(), axum::Error> {
+  // Live messages only hold strings and ids, serializing them can't fail.
+  let text = serde_json::to_string(message).unwrap();
+  socket.send(Message::Text(text)).await
+}
+
+#[derive(Debug, Deserialize)]
+struct ConnectQuery {
+  token: Option<String>,
+  // Comma separated watchlist ids.
+  watchlists: Option<String>,
+  // Comma separated chains, e.g. `ethereum,bsc`.
+  chains: Option<String>,
+}
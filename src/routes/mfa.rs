@@ -0,0 +1,149 @@
+use axum::extract::State;
+use axum::http::StatusCode;
+use serde::Deserialize;
+use tracing::debug;
+use utoipa::{OpenApi, ToSchema};
+
+use crate::errors::{Error, ErrorResponse};
+use crate::services::mfa::{self, RecoveryCodes, TotpEnrollment};
+use crate::state::AppState;
+use crate::utils::custom_response::{CustomResponse, CustomResponseBuilder};
+use crate::utils::json::Json;
+use crate::utils::route_table::RouteTable;
+use crate::utils::token::TokenUser;
+
+#[derive(OpenApi)]
+#[openapi(
+  paths(enroll_totp, confirm_totp, disable_totp, regenerate_recovery_codes),
+  components(schemas(TotpEnrollment, RecoveryCodes, CodeBody))
+)]
+pub struct ApiDoc;
+
+pub fn create_route() -> RouteTable<AppState> {
+  RouteTable::new()
+    .post("/me/mfa/totp", enroll_totp)
+    .post("/me/mfa/totp/confirm", confirm_totp)
+    .post("/me/mfa/totp/disable", disable_totp)
+    .post("/me/mfa/recovery-codes", regenerate_recovery_codes)
+}
+
+/// Starts enrolling a TOTP authenticator app. Two-factor authentication is
+/// only enabled once a first code confirms it, see
+/// `POST /me/mfa/totp/confirm`.
+#[utoipa::path(
+  post,
+  path = "/v1/me/mfa/totp",
+  responses(
+    (status = 201, description = "TOTP factor pending confirmation", body = TotpEnrollment),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 409, description = "Two-factor authentication already enabled", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []), ("apiKey" = []))
+)]
+async fn enroll_totp(
+  user: TokenUser,
+  State(state): State<AppState>,
+) -> Result<CustomResponse<TotpEnrollment>, Error> {
+  let enrollment = mfa::enroll(user.id, &state.settings.mfa).await?;
+
+  let res = CustomResponseBuilder::new()
+    .body(enrollment)
+    .status_code(StatusCode::CREATED)
+    .build();
+
+  debug!("Returning TOTP enrollment");
+  Ok(res)
+}
+
+/// Confirms the pending TOTP factor with a code of the authenticator app,
+/// enabling two-factor authentication. The recovery codes are only returned
+/// by this request.
+#[utoipa::path(
+  post,
+  path = "/v1/me/mfa/totp/confirm",
+  request_body = CodeBody,
+  responses(
+    (status = 200, description = "Two-factor authentication enabled", body = RecoveryCodes),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 404, description = "No TOTP factor pending confirmation", body = ErrorResponse),
+    (status = 422, description = "Invalid code", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []), ("apiKey" = []))
+)]
+async fn confirm_totp(
+  user: TokenUser,
+  State(state): State<AppState>,
+  Json(body): Json<CodeBody>,
+) -> Result<Json<RecoveryCodes>, Error> {
+  match mfa::confirm(user.id, &body.code, &state.settings.mfa).await? {
+    Some(recovery_codes) => Ok(Json(recovery_codes)),
+    None => {
+      debug!("No pending TOTP factor, returning 404 status code");
+      Err(Error::not_found())
+    }
+  }
+}
+
+/// Disables two-factor authentication, given a TOTP or recovery code.
+#[utoipa::path(
+  post,
+  path = "/v1/me/mfa/totp/disable",
+  request_body = CodeBody,
+  responses(
+    (status = 204, description = "Two-factor authentication disabled"),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 404, description = "Two-factor authentication not enabled", body = ErrorResponse),
+    (status = 422, description = "Invalid code", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []), ("apiKey" = []))
+)]
+async fn disable_totp(
+  user: TokenUser,
+  Json(body): Json<CodeBody>,
+) -> Result<CustomResponse<()>, Error> {
+  if !mfa::disable(user.id, &body.code).await? {
+    debug!("Two-factor authentication not enabled, returning 404 status code");
+    return Err(Error::not_found());
+  }
+
+  let res = CustomResponseBuilder::new()
+    .status_code(StatusCode::NO_CONTENT)
+    .build();
+
+  Ok(res)
+}
+
+/// Replaces the recovery codes, given a TOTP or recovery code. The new codes
+/// are only returned by this request.
+#[utoipa::path(
+  post,
+  path = "/v1/me/mfa/recovery-codes",
+  request_body = CodeBody,
+  responses(
+    (status = 200, description = "New recovery codes", body = RecoveryCodes),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 404, description = "Two-factor authentication not enabled", body = ErrorResponse),
+    (status = 422, description = "Invalid code", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []), ("apiKey" = []))
+)]
+async fn regenerate_recovery_codes(
+  user: TokenUser,
+  State(state): State<AppState>,
+  Json(body): Json<CodeBody>,
+) -> Result<Json<RecoveryCodes>, Error> {
+  match mfa::regenerate_recovery_codes(user.id, &body.code, &state.settings.mfa).await? {
+    Some(recovery_codes) => Ok(Json(recovery_codes)),
+    None => {
+      debug!("Two-factor authentication not enabled, returning 404 status code");
+      Err(Error::not_found())
+    }
+  }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[schema(as = MfaCode)]
+struct CodeBody {
+  // TOTP code of the authenticator app, or a recovery code where accepted.
+  code: String,
+}
@@ -1,4 +1,35 @@
+pub mod address;
+pub mod address_note;
+pub mod admin;
+pub mod alert;
+pub mod api_key;
+pub mod approvals;
 pub mod arkham;
+pub mod auth;
+pub mod balances;
+pub mod bitcoin;
 pub mod cat;
+pub mod data_export;
+pub mod degen_score;
+pub mod docs;
+pub mod dune;
+pub mod gas;
+pub mod graphql;
+pub mod jobs;
+pub mod label;
+pub mod live;
+pub mod mfa;
+pub mod nfts;
+pub mod notification;
+pub mod organization;
+pub mod portfolio;
+pub mod prices;
+pub mod risk;
+pub mod session;
+pub mod stats;
 pub mod status;
+pub mod transactions;
+pub mod usage;
 pub mod user;
+pub mod watchlist;
+pub mod webhook;
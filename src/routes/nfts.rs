@@ -0,0 +1,112 @@
+use axum::extract::State;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+use utoipa::{IntoParams, OpenApi, ToSchema};
+
+use crate::errors::{Error, ErrorResponse};
+use crate::services::nfts::{Nft, NftCollection};
+use crate::state::AppState;
+use crate::utils::address::EvmAddress;
+use crate::utils::json::Json;
+use crate::utils::query::Query;
+use crate::utils::route_table::RouteTable;
+use crate::utils::serde_helpers::serialize_checksum_address;
+
+// Page size when no limit is requested.
+const DEFAULT_LIMIT: u64 = 25;
+
+#[derive(OpenApi)]
+#[openapi(paths(query_nfts), components(schemas(NftPage, NftCollection, Nft)))]
+pub struct ApiDoc;
+
+pub fn create_route() -> RouteTable<AppState> {
+  RouteTable::new().get("/nfts/:address", query_nfts)
+}
+
+#[utoipa::path(
+  get,
+  path = "/v1/nfts/{address}",
+  params(("address" = String, Path, description = "EVM address"), NftsQuery),
+  responses(
+    (status = 200, description = "Page of the NFT collections held by the address, failed chains are listed as unavailable", body = NftPage),
+    (status = 400, description = "Unsupported chain, invalid address or page", body = ErrorResponse)
+  )
+)]
+async fn query_nfts(
+  State(state): State<AppState>,
+  EvmAddress(address): EvmAddress,
+  Query(query): Query<NftsQuery>,
+) -> Result<Json<NftPage>, Error> {
+  let page = query.page.unwrap_or(1);
+  let limit = query
+    .limit
+    .unwrap_or(DEFAULT_LIMIT)
+    .clamp(1, state.settings.pagination.max_limit);
+
+  if page == 0 {
+    debug!("NFTs page out of bounds, returning 400 status code");
+    return Err(Error::bad_request());
+  }
+
+  if let Some(chain) = &query.chain {
+    if !state.nfts.supports_chain(chain) {
+      debug!("No NFT API for chain {}, returning 400 status code", chain);
+      return Err(Error::bad_request());
+    }
+  }
+
+  let holdings = state
+    .nfts
+    .fetch_holdings(&address, query.chain.as_deref())
+    .await;
+
+  // Collections are paginated here, as the provider pages can't be merged
+  // across chains.
+  let total = holdings.collections.len() as u64;
+  let collections = holdings
+    .collections
+    .into_iter()
+    .skip(((page - 1).saturating_mul(limit)) as usize)
+    .take(limit as usize)
+    .collect();
+
+  debug!("Returning NFTs");
+  Ok(Json(NftPage {
+    address,
+    page,
+    limit,
+    total,
+    has_next: page.saturating_mul(limit) < total,
+    collections,
+    unavailable_chains: holdings.unavailable_chains,
+    truncated: holdings.truncated,
+  }))
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct NftsQuery {
+  /// Only returns the NFTs of this chain, e.g. `ethereum` or `polygon`.
+  chain: Option<String>,
+  /// Page number, starting at 1.
+  page: Option<u64>,
+  /// Number of collections per page.
+  limit: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct NftPage {
+  #[serde(serialize_with = "serialize_checksum_address")]
+  pub address: String,
+  pub page: u64,
+  pub limit: u64,
+  // Number of collections held on the available chains.
+  pub total: u64,
+  pub has_next: bool,
+  // Collections sorted by chain and contract address.
+  pub collections: Vec<NftCollection>,
+  // Chains whose NFTs couldn't be fetched.
+  pub unavailable_chains: Vec<String>,
+  // Whether some chain holds more NFTs than fetched.
+  pub truncated: bool,
+}
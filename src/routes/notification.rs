@@ -0,0 +1,273 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use bson::doc;
+use serde::Deserialize;
+use tracing::debug;
+use utoipa::{OpenApi, ToSchema};
+use wither::mongodb::options::FindOptions;
+
+use crate::errors::{Error, ErrorResponse};
+use crate::models::notification_channel::{
+  ChannelKind, NotificationChannel, PublicNotificationChannel,
+};
+use crate::models::user::{EmailPreferences, User};
+use crate::state::AppState;
+use crate::utils::custom_response::{CustomResponse, CustomResponseBuilder};
+use crate::utils::date;
+use crate::utils::json::Json;
+use crate::utils::models::ModelExt;
+use crate::utils::route_table::RouteTable;
+use crate::utils::to_object_id::to_object_id;
+use crate::utils::token::{MemberUser, TokenUser};
+
+#[derive(OpenApi)]
+#[openapi(
+  paths(
+    create_notification_channel,
+    query_notification_channels,
+    update_notification_channel_by_id,
+    remove_notification_channel_by_id,
+    get_email_preferences,
+    update_email_preferences
+  ),
+  components(schemas(
+    PublicNotificationChannel,
+    ChannelKind,
+    CreateNotificationChannel,
+    UpdateNotificationChannel,
+    EmailPreferences
+  ))
+)]
+pub struct ApiDoc;
+
+pub fn create_route() -> RouteTable<AppState> {
+  RouteTable::new()
+    .post("/notifications/channels", create_notification_channel)
+    .get("/notifications/channels", query_notification_channels)
+    .put(
+      "/notifications/channels/:id",
+      update_notification_channel_by_id,
+    )
+    .delete(
+      "/notifications/channels/:id",
+      remove_notification_channel_by_id,
+    )
+    .get("/notifications/email", get_email_preferences)
+    .put("/notifications/email", update_email_preferences)
+}
+
+#[utoipa::path(
+  post,
+  path = "/v1/notifications/channels",
+  request_body = CreateNotificationChannel,
+  responses(
+    (status = 201, description = "Notification channel created", body = PublicNotificationChannel),
+    (status = 400, description = "Invalid target or channel kind not configured", body = ErrorResponse),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 422, description = "Invalid request body", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []))
+)]
+async fn create_notification_channel(
+  MemberUser(user): MemberUser,
+  State(state): State<AppState>,
+  Json(payload): Json<CreateNotificationChannel>,
+) -> Result<CustomResponse<PublicNotificationChannel>, Error> {
+  let notifier = match state.notifiers.get(payload.kind) {
+    Some(notifier) => notifier,
+    None => {
+      debug!(
+        "No {:?} notifier configured, returning 400 status code",
+        payload.kind
+      );
+      return Err(Error::bad_request());
+    }
+  };
+
+  let target = payload.target.trim();
+  notifier.validate_target(target)?;
+
+  let channel = NotificationChannel::new(user.id, payload.kind, target.to_owned());
+  let channel = NotificationChannel::create(channel).await?;
+  let res = PublicNotificationChannel::from(channel);
+
+  let res = CustomResponseBuilder::new()
+    .body(res)
+    .status_code(StatusCode::CREATED)
+    .build();
+
+  Ok(res)
+}
+
+#[utoipa::path(
+  get,
+  path = "/v1/notifications/channels",
+  responses(
+    (status = 200, description = "Notification channels of the user", body = [PublicNotificationChannel]),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []))
+)]
+async fn query_notification_channels(
+  user: TokenUser,
+) -> Result<Json<Vec<PublicNotificationChannel>>, Error> {
+  let options = FindOptions::builder()
+    .sort(doc! { "created_at": 1_i32 })
+    .build();
+  let channels = NotificationChannel::find(doc! { "user": &user.id }, options)
+    .await?
+    .into_iter()
+    .map(Into::into)
+    .collect::<Vec<PublicNotificationChannel>>();
+
+  debug!("Returning notification channels");
+  Ok(Json(channels))
+}
+
+/// Mutes or unmutes a channel.
+#[utoipa::path(
+  put,
+  path = "/v1/notifications/channels/{id}",
+  params(("id" = String, Path, description = "Notification channel id")),
+  request_body = UpdateNotificationChannel,
+  responses(
+    (status = 200, description = "Updated notification channel", body = PublicNotificationChannel),
+    (status = 400, description = "Invalid notification channel id", body = ErrorResponse),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 404, description = "Notification channel not found", body = ErrorResponse),
+    (status = 422, description = "Invalid request body", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []))
+)]
+async fn update_notification_channel_by_id(
+  MemberUser(user): MemberUser,
+  Path(id): Path<String>,
+  Json(payload): Json<UpdateNotificationChannel>,
+) -> Result<Json<PublicNotificationChannel>, Error> {
+  let channel_id = to_object_id(id)?;
+
+  let channel = NotificationChannel::find_one_and_update(
+    doc! { "_id": &channel_id, "user": &user.id },
+    doc! { "$set": { "enabled": payload.enabled, "updated_at": date::now() } },
+  )
+  .await?
+  .map(PublicNotificationChannel::from);
+
+  let channel = match channel {
+    Some(channel) => channel,
+    None => {
+      debug!("Notification channel not found, returning 404 status code");
+      return Err(Error::not_found());
+    }
+  };
+
+  debug!("Returning notification channel");
+  Ok(Json(channel))
+}
+
+#[utoipa::path(
+  delete,
+  path = "/v1/notifications/channels/{id}",
+  params(("id" = String, Path, description = "Notification channel id")),
+  responses(
+    (status = 204, description = "Notification channel removed"),
+    (status = 400, description = "Invalid notification channel id", body = ErrorResponse),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 404, description = "Notification channel not found", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []))
+)]
+async fn remove_notification_channel_by_id(
+  MemberUser(user): MemberUser,
+  Path(id): Path<String>,
+) -> Result<CustomResponse<()>, Error> {
+  let channel_id = to_object_id(id)?;
+  let delete_result =
+    NotificationChannel::delete_one(doc! { "_id": channel_id, "user": &user.id }).await?;
+
+  if delete_result.deleted_count == 0 {
+    debug!("Notification channel not found, returning 404 status code");
+    return Err(Error::not_found());
+  }
+
+  let res = CustomResponseBuilder::new()
+    .status_code(StatusCode::NO_CONTENT)
+    .build();
+
+  Ok(res)
+}
+
+#[utoipa::path(
+  get,
+  path = "/v1/notifications/email",
+  responses(
+    (status = 200, description = "Email preferences of the user", body = EmailPreferences),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []))
+)]
+async fn get_email_preferences(user: TokenUser) -> Result<Json<EmailPreferences>, Error> {
+  let user = match User::find_by_id(&user.id).await? {
+    Some(user) => user,
+    None => {
+      debug!("User not found, returning 404 status code");
+      return Err(Error::not_found());
+    }
+  };
+
+  debug!("Returning email preferences");
+  Ok(Json(user.email_preferences))
+}
+
+/// Replaces the email preferences of the user. Emails are only sent when the
+/// server has SMTP configured.
+#[utoipa::path(
+  put,
+  path = "/v1/notifications/email",
+  request_body = EmailPreferences,
+  responses(
+    (status = 200, description = "Updated email preferences", body = EmailPreferences),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 422, description = "Invalid request body", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []))
+)]
+async fn update_email_preferences(
+  user: TokenUser,
+  Json(payload): Json<EmailPreferences>,
+) -> Result<Json<EmailPreferences>, Error> {
+  let user = User::find_one_and_update(
+    doc! { "_id": &user.id },
+    doc! {
+      "$set": {
+        "email_preferences.alerts": payload.alerts,
+        "email_preferences.weekly_digest": payload.weekly_digest,
+        "updated_at": date::now()
+      }
+    },
+  )
+  .await?;
+
+  let user = match user {
+    Some(user) => user,
+    None => {
+      debug!("User not found, returning 404 status code");
+      return Err(Error::not_found());
+    }
+  };
+
+  debug!("Returning email preferences");
+  Ok(Json(user.email_preferences))
+}
+
+#[derive(Deserialize, ToSchema)]
+struct CreateNotificationChannel {
+  kind: ChannelKind,
+  // E.g. a Telegram chat id.
+  target: String,
+}
+
+#[derive(Deserialize, ToSchema)]
+struct UpdateNotificationChannel {
+  enabled: bool,
+}
@@ -0,0 +1,423 @@
+use axum::extract::Path;
+use axum::http::StatusCode;
+use bson::doc;
+use bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tracing::debug;
+use utoipa::{OpenApi, ToSchema};
+use wither::mongodb::options::FindOptions;
+
+use crate::errors::{AuthenticateError, Error, ErrorResponse};
+use crate::models::membership::{Membership, PublicMembership};
+use crate::models::organization::{Organization, PublicOrganization};
+use crate::models::organization_invite::{OrganizationInvite, PublicOrganizationInvite};
+use crate::models::user::Role;
+use crate::services::ownership::Ownership;
+use crate::state::AppState;
+use crate::utils::custom_response::{CustomResponse, CustomResponseBuilder};
+use crate::utils::date;
+use crate::utils::json::Json;
+use crate::utils::models::ModelExt;
+use crate::utils::route_table::RouteTable;
+use crate::utils::secret;
+use crate::utils::to_object_id::to_object_id;
+use crate::utils::token::{MemberUser, TokenUser};
+
+#[derive(OpenApi)]
+#[openapi(
+  paths(
+    create_organization,
+    query_organizations,
+    query_members,
+    update_member_role,
+    remove_member,
+    create_invite,
+    accept_invite
+  ),
+  components(schemas(
+    PublicOrganization,
+    PublicMembership,
+    PublicOrganizationInvite,
+    CreatedInvite,
+    CreateOrganization,
+    CreateInvite,
+    UpdateMemberRole,
+    AcceptInvite
+  ))
+)]
+pub struct ApiDoc;
+
+const INVITE_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+pub fn create_route() -> RouteTable<AppState> {
+  RouteTable::new()
+    .post("/organizations", create_organization)
+    .get("/organizations", query_organizations)
+    .get("/organizations/:id/members", query_members)
+    .put("/organizations/:id/members/:user", update_member_role)
+    .delete("/organizations/:id/members/:user", remove_member)
+    .post("/organizations/:id/invites", create_invite)
+    .post("/invites/accept", accept_invite)
+}
+
+/// Creates an organization, the user creating it becomes its admin.
+#[utoipa::path(
+  post,
+  path = "/v1/organizations",
+  request_body = CreateOrganization,
+  responses(
+    (status = 201, description = "Organization created", body = PublicOrganization),
+    (status = 400, description = "Empty organization name", body = ErrorResponse),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 422, description = "Invalid request body", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []))
+)]
+async fn create_organization(
+  MemberUser(user): MemberUser,
+  Json(payload): Json<CreateOrganization>,
+) -> Result<CustomResponse<PublicOrganization>, Error> {
+  let name = payload.name.trim();
+  if name.is_empty() {
+    debug!("Empty organization name, returning 400 status code");
+    return Err(Error::bad_request());
+  }
+
+  let organization = Organization::create(Organization::new(name.to_owned(), user.id)).await?;
+  let membership = Membership::new(organization.id.unwrap(), user.id, Role::Admin);
+  Membership::create(membership).await?;
+  let res = PublicOrganization::from(organization);
+
+  let res = CustomResponseBuilder::new()
+    .body(res)
+    .status_code(StatusCode::CREATED)
+    .build();
+
+  Ok(res)
+}
+
+#[utoipa::path(
+  get,
+  path = "/v1/organizations",
+  responses(
+    (status = 200, description = "Organizations of the user", body = [PublicOrganization]),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []))
+)]
+async fn query_organizations(user: TokenUser) -> Result<Json<Vec<PublicOrganization>>, Error> {
+  let ownership = Ownership::load(user.id).await?;
+  let options = FindOptions::builder()
+    .sort(doc! { "created_at": 1_i32 })
+    .build();
+  let organizations = Organization::find(
+    doc! { "_id": { "$in": ownership.organizations(Role::ReadOnly) } },
+    options,
+  )
+  .await?
+  .into_iter()
+  .map(Into::into)
+  .collect::<Vec<PublicOrganization>>();
+
+  debug!("Returning organizations");
+  Ok(Json(organizations))
+}
+
+#[utoipa::path(
+  get,
+  path = "/v1/organizations/{id}/members",
+  params(("id" = String, Path, description = "Organization id")),
+  responses(
+    (status = 200, description = "Members of the organization", body = [PublicMembership]),
+    (status = 400, description = "Invalid organization id", body = ErrorResponse),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 404, description = "Organization not found", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []))
+)]
+async fn query_members(
+  user: TokenUser,
+  Path(id): Path<String>,
+) -> Result<Json<Vec<PublicMembership>>, Error> {
+  let organization_id = to_object_id(id)?;
+  Ownership::load(user.id)
+    .await?
+    .check(Some(organization_id), Role::ReadOnly)?;
+
+  let options = FindOptions::builder()
+    .sort(doc! { "created_at": 1_i32 })
+    .build();
+  let members = Membership::find(doc! { "organization": &organization_id }, options)
+    .await?
+    .into_iter()
+    .map(Into::into)
+    .collect::<Vec<PublicMembership>>();
+
+  debug!("Returning organization members");
+  Ok(Json(members))
+}
+
+#[utoipa::path(
+  put,
+  path = "/v1/organizations/{id}/members/{user}",
+  params(
+    ("id" = String, Path, description = "Organization id"),
+    ("user" = String, Path, description = "User id of the member")
+  ),
+  request_body = UpdateMemberRole,
+  responses(
+    (status = 200, description = "Updated member", body = PublicMembership),
+    (status = 400, description = "Invalid id or last admin demoted", body = ErrorResponse),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 403, description = "User is not an admin of the organization", body = ErrorResponse),
+    (status = 404, description = "Organization or member not found", body = ErrorResponse),
+    (status = 422, description = "Invalid request body", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []))
+)]
+async fn update_member_role(
+  MemberUser(user): MemberUser,
+  Path((id, member_id)): Path<(String, String)>,
+  Json(payload): Json<UpdateMemberRole>,
+) -> Result<Json<PublicMembership>, Error> {
+  let organization_id = to_object_id(id)?;
+  let member_id = to_object_id(member_id)?;
+  Ownership::load(user.id)
+    .await?
+    .check(Some(organization_id), Role::Admin)?;
+
+  if payload.role != Role::Admin {
+    check_other_admins(organization_id, member_id).await?;
+  }
+
+  let membership = Membership::find_one_and_update(
+    doc! { "organization": &organization_id, "user": &member_id },
+    doc! { "$set": { "role": payload.role.as_str(), "updated_at": date::now() } },
+  )
+  .await?;
+
+  let membership = match membership {
+    Some(membership) => membership,
+    None => {
+      debug!("Member not found, returning 404 status code");
+      return Err(Error::not_found());
+    }
+  };
+
+  debug!("Returning updated member");
+  Ok(Json(PublicMembership::from(membership)))
+}
+
+/// Removes a member from the organization. Admins remove any member, other
+/// members can only leave. The shared resources they created are kept.
+#[utoipa::path(
+  delete,
+  path = "/v1/organizations/{id}/members/{user}",
+  params(
+    ("id" = String, Path, description = "Organization id"),
+    ("user" = String, Path, description = "User id of the member")
+  ),
+  responses(
+    (status = 204, description = "Member removed"),
+    (status = 400, description = "Invalid id or last admin removed", body = ErrorResponse),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 403, description = "User is not an admin of the organization", body = ErrorResponse),
+    (status = 404, description = "Organization or member not found", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []))
+)]
+async fn remove_member(
+  MemberUser(user): MemberUser,
+  Path((id, member_id)): Path<(String, String)>,
+) -> Result<CustomResponse<()>, Error> {
+  let organization_id = to_object_id(id)?;
+  let member_id = to_object_id(member_id)?;
+  let required_role = if member_id == user.id {
+    Role::ReadOnly
+  } else {
+    Role::Admin
+  };
+  Ownership::load(user.id)
+    .await?
+    .check(Some(organization_id), required_role)?;
+
+  check_other_admins(organization_id, member_id).await?;
+
+  let delete_result =
+    Membership::delete_one(doc! { "organization": &organization_id, "user": &member_id }).await?;
+  if delete_result.deleted_count == 0 {
+    debug!("Member not found, returning 404 status code");
+    return Err(Error::not_found());
+  }
+
+  let res = CustomResponseBuilder::new()
+    .status_code(StatusCode::NO_CONTENT)
+    .build();
+
+  Ok(res)
+}
+
+/// Rejects demoting or removing the last admin of an organization, nobody
+/// could manage its members anymore.
+async fn check_other_admins(organization: ObjectId, member: ObjectId) -> Result<(), Error> {
+  let admins = Membership::count(doc! {
+    "organization": &organization,
+    "user": { "$ne": &member },
+    "role": Role::Admin.as_str()
+  })
+  .await?;
+
+  let is_admin = Membership::exists(doc! {
+    "organization": &organization,
+    "user": &member,
+    "role": Role::Admin.as_str()
+  })
+  .await?;
+
+  if is_admin && admins == 0 {
+    debug!("Last organization admin, returning 400 status code");
+    return Err(Error::bad_request());
+  }
+
+  Ok(())
+}
+
+/// Invites a user by email. The token is only returned by this request, and
+/// is sent to the invited user to accept the invite.
+#[utoipa::path(
+  post,
+  path = "/v1/organizations/{id}/invites",
+  params(("id" = String, Path, description = "Organization id")),
+  request_body = CreateInvite,
+  responses(
+    (status = 201, description = "Invite created", body = CreatedInvite),
+    (status = 400, description = "Invalid organization id or email", body = ErrorResponse),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 403, description = "User is not an admin of the organization", body = ErrorResponse),
+    (status = 404, description = "Organization not found", body = ErrorResponse),
+    (status = 422, description = "Invalid request body", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []))
+)]
+async fn create_invite(
+  MemberUser(user): MemberUser,
+  Path(id): Path<String>,
+  Json(payload): Json<CreateInvite>,
+) -> Result<CustomResponse<CreatedInvite>, Error> {
+  let organization_id = to_object_id(id)?;
+  Ownership::load(user.id)
+    .await?
+    .check(Some(organization_id), Role::Admin)?;
+
+  // Invalid emails are rejected by the model validation.
+  let (invite, token) = OrganizationInvite::generate(
+    organization_id,
+    payload.email.trim().to_lowercase(),
+    payload.role.unwrap_or(Role::Member),
+    user.id,
+    date::after(INVITE_TTL),
+  );
+  let invite = OrganizationInvite::create(invite).await?;
+  let res = CreatedInvite {
+    invite: PublicOrganizationInvite::from(invite),
+    token,
+  };
+
+  let res = CustomResponseBuilder::new()
+    .body(res)
+    .status_code(StatusCode::CREATED)
+    .build();
+
+  Ok(res)
+}
+
+/// Joins the organization of an invite sent to the email of the user.
+#[utoipa::path(
+  post,
+  path = "/v1/invites/accept",
+  request_body = AcceptInvite,
+  responses(
+    (status = 201, description = "Invite accepted", body = PublicMembership),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 403, description = "Invite sent to another email", body = ErrorResponse),
+    (status = 404, description = "Unknown or expired invite", body = ErrorResponse),
+    (status = 409, description = "User is already a member", body = ErrorResponse),
+    (status = 422, description = "Invalid request body", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []))
+)]
+async fn accept_invite(
+  MemberUser(user): MemberUser,
+  Json(payload): Json<AcceptInvite>,
+) -> Result<CustomResponse<PublicMembership>, Error> {
+  let invite = OrganizationInvite::find_one(
+    doc! {
+      "hash": secret::hash(&payload.token),
+      "expires_at": { "$gt": date::now() }
+    },
+    None,
+  )
+  .await?;
+
+  let invite = match invite {
+    Some(invite) => invite,
+    None => {
+      debug!("Invite not found, returning 404 status code");
+      return Err(Error::not_found());
+    }
+  };
+
+  if invite.email != user.email.to_lowercase() {
+    debug!("Invite sent to another email, returning 403 status code");
+    return Err(Error::Authenticate(AuthenticateError::Forbidden));
+  }
+
+  let query = doc! { "organization": &invite.organization, "user": &user.id };
+  if Membership::exists(query).await? {
+    debug!("User already a member, returning 409 status code");
+    return Err(Error::conflict());
+  }
+
+  let membership = Membership::new(invite.organization, user.id, invite.role);
+  let membership = Membership::create(membership).await?;
+  OrganizationInvite::delete_one(doc! { "_id": invite.id.unwrap() }).await?;
+  let res = PublicMembership::from(membership);
+
+  let res = CustomResponseBuilder::new()
+    .body(res)
+    .status_code(StatusCode::CREATED)
+    .build();
+
+  Ok(res)
+}
+
+#[derive(Deserialize, ToSchema)]
+struct CreateOrganization {
+  name: String,
+}
+
+#[derive(Deserialize, ToSchema)]
+struct UpdateMemberRole {
+  role: Role,
+}
+
+#[derive(Deserialize, ToSchema)]
+struct CreateInvite {
+  email: String,
+  // Defaults to member.
+  role: Option<Role>,
+}
+
+#[derive(Deserialize, ToSchema)]
+struct AcceptInvite {
+  token: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CreatedInvite {
+  #[serde(flatten)]
+  pub invite: PublicOrganizationInvite,
+  // Sent to the invited user. It can't be retrieved later.
+  pub token: String,
+}
@@ -0,0 +1,42 @@
+use axum::extract::State;
+use tracing::debug;
+use utoipa::OpenApi;
+
+use crate::errors::{Error, ErrorResponse};
+use crate::services::portfolio::{Portfolio, Position, PositionKind, ProtocolPositions};
+use crate::state::AppState;
+use crate::utils::address::EvmAddress;
+use crate::utils::json::Json;
+use crate::utils::route_table::RouteTable;
+
+#[derive(OpenApi)]
+#[openapi(
+  paths(query_portfolio),
+  components(schemas(Portfolio, ProtocolPositions, Position, PositionKind))
+)]
+pub struct ApiDoc;
+
+pub fn create_route() -> RouteTable<AppState> {
+  RouteTable::new().get("/portfolio/:address", query_portfolio)
+}
+
+#[utoipa::path(
+  get,
+  path = "/v1/portfolio/{address}",
+  params(("address" = String, Path, description = "EVM address")),
+  responses(
+    (status = 200, description = "DeFi positions of the address by protocol and chain", body = Portfolio),
+    (status = 400, description = "Invalid address", body = ErrorResponse),
+    (status = 503, description = "Zerion request failed", body = ErrorResponse),
+    (status = 502, description = "Zerion returned an invalid response", body = ErrorResponse)
+  )
+)]
+async fn query_portfolio(
+  State(state): State<AppState>,
+  EvmAddress(address): EvmAddress,
+) -> Result<Json<Portfolio>, Error> {
+  let portfolio = state.portfolio.fetch_portfolio(&address).await?;
+
+  debug!("Returning portfolio");
+  Ok(Json(portfolio))
+}
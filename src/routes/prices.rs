@@ -0,0 +1,106 @@
+use axum::extract::{Path, State};
+use serde::Deserialize;
+use std::collections::HashMap;
+use tracing::debug;
+use utoipa::{IntoParams, OpenApi};
+
+use crate::errors::{Error, ErrorResponse};
+use crate::services::prices::TokenPrice;
+use crate::state::AppState;
+use crate::utils::json::Json;
+use crate::utils::query::Query;
+use crate::utils::route_table::RouteTable;
+
+#[derive(OpenApi)]
+#[openapi(paths(get_prices, get_price), components(schemas(TokenPrice)))]
+pub struct ApiDoc;
+
+pub fn create_route() -> RouteTable<AppState> {
+  RouteTable::new()
+    .get("/prices", get_prices)
+    .get("/prices/:token", get_price)
+}
+
+#[utoipa::path(
+  get,
+  path = "/v1/prices",
+  params(PricesQuery),
+  responses(
+    (status = 200, description = "USD prices by token id, unknown tokens are left out", body = HashMap<String, TokenPrice>),
+    (status = 400, description = "Missing, invalid or too many token ids", body = ErrorResponse),
+    (status = 503, description = "CoinGecko request failed", body = ErrorResponse),
+    (status = 502, description = "CoinGecko returned an invalid response", body = ErrorResponse)
+  )
+)]
+async fn get_prices(
+  State(state): State<AppState>,
+  Query(query): Query<PricesQuery>,
+) -> Result<Json<HashMap<String, TokenPrice>>, Error> {
+  let mut ids = query
+    .ids
+    .split(',')
+    .map(parse_token_id)
+    .collect::<Result<Vec<String>, Error>>()?;
+  ids.sort();
+  ids.dedup();
+
+  if ids.len() > state.settings.prices.max_ids {
+    debug!("Too many token ids, returning 400 status code");
+    return Err(Error::bad_request());
+  }
+
+  let prices = state.prices.prices(&ids).await?;
+  Ok(Json(prices))
+}
+
+#[utoipa::path(
+  get,
+  path = "/v1/prices/{token}",
+  params(("token" = String, Path, description = "CoinGecko token id, e.g. `ethereum`")),
+  responses(
+    (status = 200, description = "USD price of the token", body = TokenPrice),
+    (status = 400, description = "Invalid token id", body = ErrorResponse),
+    (status = 404, description = "Token unknown to CoinGecko", body = ErrorResponse),
+    (status = 503, description = "CoinGecko request failed", body = ErrorResponse),
+    (status = 502, description = "CoinGecko returned an invalid response", body = ErrorResponse)
+  )
+)]
+async fn get_price(
+  State(state): State<AppState>,
+  Path(token): Path<String>,
+) -> Result<Json<TokenPrice>, Error> {
+  let id = parse_token_id(&token)?;
+  let mut prices = state.prices.prices(&[id.clone()]).await?;
+
+  match prices.remove(&id) {
+    Some(price) => Ok(Json(price)),
+    None => {
+      debug!("Token {} not found, returning 404 status code", id);
+      Err(Error::not_found())
+    }
+  }
+}
+
+/// Normalizes a CoinGecko token id, made of lowercase letters, digits and
+/// dashes, e.g. `wrapped-bitcoin`.
+pub fn parse_token_id(id: &str) -> Result<String, Error> {
+  let id = id.trim().to_lowercase();
+  let is_valid = !id.is_empty()
+    && id
+      .chars()
+      .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+
+  if !is_valid {
+    debug!("Invalid token id, returning 400 status code");
+    return Err(Error::bad_request());
+  }
+
+  Ok(id)
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct PricesQuery {
+  /// Comma separated CoinGecko token ids, e.g. `bitcoin,ethereum`.
+  ids: String,
+}
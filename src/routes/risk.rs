@@ -0,0 +1,65 @@
+use axum::extract::State;
+use serde::Deserialize;
+use tracing::debug;
+use utoipa::{IntoParams, OpenApi};
+
+use crate::errors::{Error, ErrorResponse};
+use crate::services::feature_flags::{RequireFeature, RiskScoreFeature};
+use crate::services::risk::{RiskFactor, RiskFactorKind, RiskLevel, RiskReport};
+use crate::state::AppState;
+use crate::utils::address::EvmAddress;
+use crate::utils::json::Json;
+use crate::utils::query::Query;
+use crate::utils::route_table::RouteTable;
+
+// Chain whose approvals are scanned when none is requested.
+const DEFAULT_CHAIN: &str = "ethereum";
+
+#[derive(OpenApi)]
+#[openapi(
+  paths(query_risk),
+  components(schemas(RiskReport, RiskFactor, RiskFactorKind, RiskLevel))
+)]
+pub struct ApiDoc;
+
+pub fn create_route() -> RouteTable<AppState> {
+  RouteTable::new().get("/risk/:address", query_risk)
+}
+
+#[utoipa::path(
+  get,
+  path = "/v1/risk/{address}",
+  params(("address" = String, Path, description = "EVM address"), RiskQuery),
+  responses(
+    (status = 200, description = "Risk score of the address, from 0 to 100, with its contributing factors", body = RiskReport),
+    (status = 400, description = "Unsupported chain or invalid address", body = ErrorResponse),
+    (status = 404, description = "Risk scores disabled", body = ErrorResponse)
+  )
+)]
+async fn query_risk(
+  _feature: RequireFeature<RiskScoreFeature>,
+  State(state): State<AppState>,
+  EvmAddress(address): EvmAddress,
+  Query(query): Query<RiskQuery>,
+) -> Result<Json<RiskReport>, Error> {
+  let chain = query.chain.unwrap_or_else(|| DEFAULT_CHAIN.to_owned());
+  if !state.rpc.supports_chain(&chain) {
+    debug!(
+      "No RPC provider for chain {}, returning 400 status code",
+      chain
+    );
+    return Err(Error::bad_request());
+  }
+
+  let report = state.risk.assess(&chain, &address).await?;
+
+  debug!("Returning risk score");
+  Ok(Json(report))
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct RiskQuery {
+  /// Chain whose approvals are scanned, `ethereum` by default.
+  chain: Option<String>,
+}
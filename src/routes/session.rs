@@ -0,0 +1,119 @@
+use axum::extract::Path;
+use axum::http::StatusCode;
+use bson::doc;
+use tracing::debug;
+use utoipa::OpenApi;
+use wither::mongodb::options::FindOptions;
+
+use crate::errors::{Error, ErrorResponse};
+use crate::models::refresh_token::RefreshToken;
+use crate::models::session::{PublicSession, Session};
+use crate::routes::auth;
+use crate::state::AppState;
+use crate::utils::custom_response::{CustomResponse, CustomResponseBuilder};
+use crate::utils::date;
+use crate::utils::json::Json;
+use crate::utils::models::ModelExt;
+use crate::utils::route_table::RouteTable;
+use crate::utils::to_object_id::to_object_id;
+use crate::utils::token::TokenUser;
+
+#[derive(OpenApi)]
+#[openapi(
+  paths(query_sessions, remove_session_by_id, remove_sessions),
+  components(schemas(PublicSession))
+)]
+pub struct ApiDoc;
+
+pub fn create_route() -> RouteTable<AppState> {
+  RouteTable::new()
+    .get("/me/sessions", query_sessions)
+    .delete("/me/sessions", remove_sessions)
+    .delete("/me/sessions/:id", remove_session_by_id)
+}
+
+/// Active sessions of the user, most recently used first. Each sign in
+/// starts a session, kept alive by refreshing its tokens.
+#[utoipa::path(
+  get,
+  path = "/v1/me/sessions",
+  responses(
+    (status = 200, description = "Active sessions of the user", body = [PublicSession]),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []), ("apiKey" = []))
+)]
+async fn query_sessions(user: TokenUser) -> Result<Json<Vec<PublicSession>>, Error> {
+  let options = FindOptions::builder()
+    .sort(doc! { "last_used_at": -1_i32 })
+    .build();
+  let sessions = Session::find(
+    doc! { "user": &user.id, "expires_at": { "$gt": date::now() } },
+    options,
+  )
+  .await?
+  .into_iter()
+  .map(|session| PublicSession {
+    current: session.id == user.session,
+    ..PublicSession::from(session)
+  })
+  .collect::<Vec<PublicSession>>();
+
+  debug!("Returning sessions");
+  Ok(Json(sessions))
+}
+
+/// Revokes a session, its refresh and access tokens are rejected right away.
+#[utoipa::path(
+  delete,
+  path = "/v1/me/sessions/{id}",
+  params(("id" = String, Path, description = "Session id")),
+  responses(
+    (status = 204, description = "Session revoked"),
+    (status = 400, description = "Invalid session id", body = ErrorResponse),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 404, description = "Session not found", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []), ("apiKey" = []))
+)]
+async fn remove_session_by_id(
+  user: TokenUser,
+  Path(id): Path<String>,
+) -> Result<CustomResponse<()>, Error> {
+  let session_id = to_object_id(id)?;
+  if !Session::exists(doc! { "_id": session_id, "user": &user.id }).await? {
+    debug!("Session not found, returning 404 status code");
+    return Err(Error::not_found());
+  }
+
+  auth::revoke_session(session_id).await?;
+
+  let res = CustomResponseBuilder::new()
+    .status_code(StatusCode::NO_CONTENT)
+    .build();
+
+  Ok(res)
+}
+
+/// Revokes every session of the user, including the one of this request.
+/// API keys are not affected, see `DELETE /api-keys/:id`.
+#[utoipa::path(
+  delete,
+  path = "/v1/me/sessions",
+  responses(
+    (status = 204, description = "Sessions revoked"),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []), ("apiKey" = []))
+)]
+async fn remove_sessions(user: TokenUser) -> Result<CustomResponse<()>, Error> {
+  // Refresh tokens first, so no session is started again from them.
+  RefreshToken::delete_many(doc! { "user": &user.id }).await?;
+  Session::delete_many(doc! { "user": &user.id }).await?;
+
+  let res = CustomResponseBuilder::new()
+    .status_code(StatusCode::NO_CONTENT)
+    .build();
+
+  Ok(res)
+}
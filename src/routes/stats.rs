@@ -0,0 +1,76 @@
+use serde::Deserialize;
+use tracing::debug;
+use utoipa::{IntoParams, OpenApi};
+
+use crate::errors::{Error, ErrorResponse};
+use crate::services::stats::{
+  self, AddressCount, DayCount, PeriodCredits, SourceCount, Stats, WatchlistSize,
+};
+use crate::state::AppState;
+use crate::utils::json::Json;
+use crate::utils::query::Query;
+use crate::utils::route_table::RouteTable;
+use crate::utils::token::TokenUser;
+
+#[derive(OpenApi)]
+#[openapi(
+  paths(query_stats),
+  components(schemas(
+    Stats,
+    DayCount,
+    SourceCount,
+    WatchlistSize,
+    AddressCount,
+    PeriodCredits
+  ))
+)]
+pub struct ApiDoc;
+
+pub fn create_route() -> RouteTable<AppState> {
+  RouteTable::new().get("/me/stats", query_stats)
+}
+
+/// Statistics of the authenticated user over the last days: cats created
+/// per day, visible labels per source, largest watchlists, most queried
+/// addresses and upstream credits spent per month.
+#[utoipa::path(
+  get,
+  path = "/v1/me/stats",
+  params(StatsQuery),
+  responses(
+    (status = 200, description = "Statistics of the user", body = Stats),
+    (status = 400, description = "Invalid number of days", body = ErrorResponse),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []), ("apiKey" = []))
+)]
+async fn query_stats(
+  user: TokenUser,
+  Query(query): Query<StatsQuery>,
+) -> Result<Json<Stats>, Error> {
+  let stats = stats::compute(Some(user.id), query.days()?).await?;
+
+  debug!("Returning user stats");
+  Ok(Json(stats))
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct StatsQuery {
+  /// Days covered by the statistics, today included, 30 by default and at
+  /// most 365.
+  days: Option<u32>,
+}
+
+impl StatsQuery {
+  pub fn days(&self) -> Result<u32, Error> {
+    match self.days {
+      None => Ok(stats::DEFAULT_DAYS),
+      Some(days) if (1..=stats::MAX_DAYS).contains(&days) => Ok(days),
+      Some(_) => {
+        debug!("Invalid number of days, returning 400 status code");
+        Err(Error::bad_request())
+      }
+    }
+  }
+}
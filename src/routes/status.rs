@@ -1,14 +1,40 @@
-use axum::{routing::get, Json, Router};
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::Json;
 use bson::doc;
 use serde::{Deserialize, Serialize};
-use tracing::debug;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::time::{Duration, Instant};
+use tokio::time::timeout;
+use tracing::{debug, warn};
+use utoipa::{OpenApi, ToSchema};
 
+use crate::database;
 use crate::errors::Error;
+use crate::settings::IntelligenceBackend;
+use crate::state::AppState;
+use crate::utils::route_table::RouteTable;
 
-pub fn create_route() -> Router {
-  Router::new().route("/status", get(get_status))
+#[derive(OpenApi)]
+#[openapi(
+  paths(get_status, get_health, get_ready),
+  components(schemas(Status, Readiness, DependencyCheck))
+)]
+pub struct ApiDoc;
+
+pub fn create_route() -> RouteTable<AppState> {
+  RouteTable::new()
+    .get("/status", get_status)
+    .get("/health", get_health)
+    .get("/ready", get_ready)
 }
 
+#[utoipa::path(
+  get,
+  path = "/status",
+  responses((status = 200, description = "API is up", body = Status))
+)]
 async fn get_status() -> Result<Json<Status>, Error> {
   debug!("Returning status");
   Ok(Json(Status {
@@ -16,7 +42,108 @@ async fn get_status() -> Result<Json<Status>, Error> {
   }))
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// Liveness probe. It checks no dependency, so a database outage doesn't get
+/// the API restarted.
+#[utoipa::path(
+  get,
+  path = "/health",
+  responses((status = 200, description = "API is alive", body = Status))
+)]
+async fn get_health() -> Json<Status> {
+  Json(Status {
+    status: "ok".to_owned(),
+  })
+}
+
+/// Readiness probe. Pings MongoDB, and the Arkham API when enabled, at the
+/// same time. Arkham is not pinged when the fixture provider stands in for
+/// it.
+#[utoipa::path(
+  get,
+  path = "/ready",
+  responses(
+    (status = 200, description = "API is ready", body = Readiness),
+    (status = 503, description = "A dependency is failing", body = Readiness)
+  )
+)]
+async fn get_ready(State(state): State<AppState>) -> (StatusCode, Json<Readiness>) {
+  let settings = &state.settings.health;
+  let limit = Duration::from_millis(settings.timeout_ms);
+
+  let arkham = async {
+    if settings.check_arkham && state.settings.arkham.provider == IntelligenceBackend::Arkham {
+      Some(check("arkham", limit, state.arkham.check()).await)
+    } else {
+      None
+    }
+  };
+  let (mongodb, arkham) = tokio::join!(check("mongodb", limit, database::check()), arkham);
+
+  let mut checks = BTreeMap::new();
+  checks.insert("mongodb".to_owned(), mongodb);
+  if let Some(arkham) = arkham {
+    checks.insert("arkham".to_owned(), arkham);
+  }
+
+  let ready = checks.values().all(|check| check.status == "ok");
+  let (status_code, status) = if ready {
+    (StatusCode::OK, "ok")
+  } else {
+    (StatusCode::SERVICE_UNAVAILABLE, "unavailable")
+  };
+
+  let readiness = Readiness {
+    status: status.to_owned(),
+    checks,
+  };
+
+  (status_code, Json(readiness))
+}
+
+/// Runs a dependency check, failing it when it takes longer than the limit.
+/// Errors are logged rather than returned, probes are unauthenticated.
+async fn check<F>(name: &str, limit: Duration, future: F) -> DependencyCheck
+where
+  F: Future<Output = Result<(), Error>>,
+{
+  let started_at = Instant::now();
+  let result = timeout(limit, future).await;
+  let latency_ms = started_at.elapsed().as_millis() as u64;
+
+  let status = match result {
+    Ok(Ok(())) => "ok",
+    Ok(Err(err)) => {
+      warn!("Readiness check of {} failed: {}", name, err);
+      "error"
+    }
+    Err(_) => {
+      warn!("Readiness check of {} timed out after {:?}", name, limit);
+      "timeout"
+    }
+  };
+
+  DependencyCheck {
+    status: status.to_owned(),
+    latency_ms,
+  }
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
 struct Status {
   status: String,
 }
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+struct Readiness {
+  // `ok` or `unavailable`.
+  status: String,
+  // Checks by dependency name, e.g. `mongodb`.
+  checks: BTreeMap<String, DependencyCheck>,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+struct DependencyCheck {
+  // `ok`, `error` or `timeout`.
+  status: String,
+  latency_ms: u64,
+}
@@ -0,0 +1,105 @@
+use axum::extract::{Path, State};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tracing::debug;
+use utoipa::{IntoParams, OpenApi, ToSchema};
+
+use crate::errors::{Error, ErrorResponse};
+use crate::services::explorer::Transaction;
+use crate::state::AppState;
+use crate::utils::address::EvmAddress;
+use crate::utils::json::Json;
+use crate::utils::query::Query;
+use crate::utils::route_table::RouteTable;
+use crate::utils::serde_helpers::serialize_checksum_address;
+
+// Explorers only return the first 10000 transactions of an address.
+const MAX_TRANSACTIONS: u64 = 10_000;
+
+// Page size when no limit is requested.
+const DEFAULT_LIMIT: u64 = 25;
+
+#[derive(OpenApi)]
+#[openapi(
+  paths(query_transactions),
+  components(schemas(TransactionPage, Transaction))
+)]
+pub struct ApiDoc;
+
+pub fn create_route() -> RouteTable<AppState> {
+  RouteTable::new().get("/tx/:chain/:address", query_transactions)
+}
+
+#[utoipa::path(
+  get,
+  path = "/v1/tx/{chain}/{address}",
+  params(
+    ("chain" = String, Path, description = "Chain name, e.g. `ethereum` or `arbitrum_one`"),
+    ("address" = String, Path, description = "EVM address"),
+    TransactionsQuery
+  ),
+  responses(
+    (status = 200, description = "Page of the transactions of the address, newest first", body = TransactionPage),
+    (status = 400, description = "Unsupported chain, invalid address or page", body = ErrorResponse),
+    (status = 503, description = "Block explorer request failed", body = ErrorResponse),
+    (status = 502, description = "Block explorer returned an invalid response", body = ErrorResponse),
+    (status = 504, description = "Block explorer request timed out", body = ErrorResponse)
+  )
+)]
+async fn query_transactions(
+  State(state): State<AppState>,
+  Path(params): Path<HashMap<String, String>>,
+  EvmAddress(address): EvmAddress,
+  Query(query): Query<TransactionsQuery>,
+) -> Result<Json<TransactionPage>, Error> {
+  let chain = params.get("chain").cloned().unwrap_or_default();
+  let page = query.page.unwrap_or(1);
+  let limit = query
+    .limit
+    .unwrap_or(DEFAULT_LIMIT)
+    .clamp(1, state.settings.pagination.max_limit);
+
+  if page == 0 || page.saturating_mul(limit) > MAX_TRANSACTIONS {
+    debug!("Transactions page out of bounds, returning 400 status code");
+    return Err(Error::bad_request());
+  }
+
+  let transactions = state
+    .explorer
+    .fetch_transactions(&chain, &address, page, limit)
+    .await?;
+
+  // Explorers don't count the transactions, a full page may be the last.
+  let has_next = transactions.len() as u64 == limit && page * limit < MAX_TRANSACTIONS;
+
+  debug!("Returning transactions");
+  Ok(Json(TransactionPage {
+    chain,
+    address,
+    page,
+    limit,
+    has_next,
+    transactions,
+  }))
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct TransactionsQuery {
+  /// Page number, starting at 1.
+  page: Option<u64>,
+  /// Number of transactions per page.
+  limit: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct TransactionPage {
+  pub chain: String,
+  #[serde(serialize_with = "serialize_checksum_address")]
+  pub address: String,
+  pub page: u64,
+  pub limit: u64,
+  // Whether the next page may hold transactions.
+  pub has_next: bool,
+  pub transactions: Vec<Transaction>,
+}
@@ -0,0 +1,82 @@
+use axum::extract::State;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+use utoipa::{OpenApi, ToSchema};
+
+use crate::errors::{Error, ErrorResponse};
+use crate::models::user::Plan;
+use crate::state::AppState;
+use crate::utils::json::Json;
+use crate::utils::route_table::RouteTable;
+use crate::utils::token::TokenUser;
+use crate::utils::usage;
+
+#[derive(OpenApi)]
+#[openapi(paths(query_usage), components(schemas(UsageReport, QuotaUsage, Plan)))]
+pub struct ApiDoc;
+
+pub fn create_route() -> RouteTable<AppState> {
+  RouteTable::new().get("/me/usage", query_usage)
+}
+
+/// Usage of the authenticated user in the current month, including this
+/// request, against the quotas of their plan.
+#[utoipa::path(
+  get,
+  path = "/v1/me/usage",
+  responses(
+    (status = 200, description = "Usage in the current month", body = UsageReport),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 429, description = "Monthly request quota exceeded", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []), ("apiKey" = []))
+)]
+async fn query_usage(
+  user: TokenUser,
+  State(state): State<AppState>,
+) -> Result<Json<UsageReport>, Error> {
+  let (requests, upstream_credits) = usage::current_usage(&user.id).await?;
+  let quota = usage::quota(&state.settings.usage, user.plan);
+  let now = Utc::now();
+
+  let res = UsageReport {
+    plan: user.plan,
+    period: usage::period(now),
+    // This request is recorded once it is handled.
+    requests: QuotaUsage::new(requests + 1, quota.requests),
+    upstream_credits: QuotaUsage::new(upstream_credits, quota.upstream_credits),
+    resets_at: usage::next_period_start(now).to_rfc3339(),
+  };
+
+  debug!("Returning usage");
+  Ok(Json(res))
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct UsageReport {
+  pub plan: Plan,
+  // Month of the usage in UTC, e.g. `2024-05`.
+  pub period: String,
+  pub requests: QuotaUsage,
+  pub upstream_credits: QuotaUsage,
+  // RFC 3339 date of the start of the next period.
+  pub resets_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct QuotaUsage {
+  pub used: u64,
+  pub limit: u64,
+  pub remaining: u64,
+}
+
+impl QuotaUsage {
+  fn new(used: u64, limit: u64) -> Self {
+    Self {
+      used,
+      limit,
+      remaining: limit.saturating_sub(used),
+    }
+  }
+}
@@ -1,27 +1,91 @@
-use axum::http::StatusCode;
-use axum::{routing::post, Json, Router};
-use bson::doc;
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use bson::{doc, Bson, Document};
 use serde::{Deserialize, Serialize};
-use tracing::debug;
+use tracing::{debug, error, info};
+use utoipa::{OpenApi, ToSchema};
+use validator::Validate;
 
-use crate::errors::{AuthenticateError, Error};
+use crate::errors::{AuthenticateError, Error, ErrorResponse};
+use crate::models::job::{JobKind, PublicJob};
+use crate::models::refresh_token::RefreshToken;
+use crate::models::session::Session;
 use crate::models::user;
 use crate::models::user::{PublicUser, User};
-use crate::settings::SETTINGS;
+use crate::routes::auth;
+use crate::routes::jobs::{accepted, respond_async};
+use crate::services::account::{self, DeletionConfirmation};
+use crate::services::{jobs, mfa};
+use crate::state::AppState;
+use crate::utils::authenticate_request::require_token;
+use crate::utils::client::ClientInfo;
 use crate::utils::custom_response::{CustomResponse, CustomResponseBuilder};
+use crate::utils::date;
+use crate::utils::json::{Json, ValidJson};
 use crate::utils::models::ModelExt;
-use crate::utils::token;
+use crate::utils::route_table::RouteTable;
+use crate::utils::token::TokenUser;
+use crate::utils::validation::not_blank;
 
-pub fn create_route() -> Router {
-  Router::new()
-    .route("/users", post(create_user))
-    .route("/users/authenticate", post(authenticate_user))
+#[derive(OpenApi)]
+#[openapi(
+  paths(
+    create_user,
+    authenticate_user,
+    get_profile,
+    update_profile,
+    request_account_deletion,
+    remove_account
+  ),
+  components(schemas(
+    PublicUser,
+    CreateBody,
+    AuthorizeBody,
+    AuthenticateResponse,
+    UpdateProfile,
+    DeletionConfirmation,
+    DeleteAccount
+  ))
+)]
+pub struct ApiDoc;
+
+pub fn create_route() -> RouteTable<AppState> {
+  RouteTable::new()
+    .post("/users", create_user)
+    .post("/users/authenticate", authenticate_user)
+    .get("/me", get_profile)
+    .put("/me", update_profile)
+    .delete("/me", remove_account)
+    .post("/me/deletion", request_account_deletion)
 }
 
-async fn create_user(Json(body): Json<CreateBody>) -> Result<CustomResponse<PublicUser>, Error> {
+#[utoipa::path(
+  post,
+  path = "/v1/users",
+  request_body = CreateBody,
+  responses(
+    (status = 201, description = "User created, a verification link is emailed", body = PublicUser),
+    (status = 422, description = "Invalid request body, or email of a wallet user", body = ErrorResponse)
+  )
+)]
+async fn create_user(
+  State(state): State<AppState>,
+  Json(body): Json<CreateBody>,
+) -> Result<CustomResponse<PublicUser>, Error> {
+  if user::is_wallet_email(&body.email) {
+    debug!("Email of a wallet user, returning 422 status code");
+    return Err(Error::InvalidPayload(
+      "Emails of wallet users are reserved".to_owned(),
+    ));
+  }
+
   let password_hash = user::hash_password(body.password).await?;
   let user = User::new(body.name, body.email, password_hash);
   let user = User::create(user).await?;
+
+  spawn_email_verification(state, user.clone());
+
   let res = PublicUser::from(user);
 
   let res = CustomResponseBuilder::new()
@@ -32,7 +96,20 @@ async fn create_user(Json(body): Json<CreateBody>) -> Result<CustomResponse<Publ
   Ok(res)
 }
 
+#[utoipa::path(
+  post,
+  path = "/v1/users/authenticate",
+  request_body = AuthorizeBody,
+  responses(
+    (status = 200, description = "User authenticated", body = AuthenticateResponse),
+    (status = 400, description = "Missing email or password", body = ErrorResponse),
+    (status = 401, description = "Wrong credentials or two-factor authentication code, or missing code", body = ErrorResponse),
+    (status = 404, description = "User not found", body = ErrorResponse),
+    (status = 423, description = "User is locked", body = ErrorResponse)
+  )
+)]
 async fn authenticate_user(
+  client: ClientInfo,
   Json(body): Json<AuthorizeBody>,
 ) -> Result<Json<AuthenticateResponse>, Error> {
   let email = &body.email;
@@ -64,38 +141,295 @@ async fn authenticate_user(
   }
 
   if user.locked_at.is_some() {
-    debug!("User is locked, returning 401");
+    debug!("User is locked, returning 423 status code");
     return Err(Error::Authenticate(AuthenticateError::Locked));
   }
 
-  let secret = SETTINGS.auth.secret.as_str();
-  let token = token::create(user.clone(), secret)
-    .map_err(|_| Error::Authenticate(AuthenticateError::TokenCreation))?;
+  mfa::verify_sign_in(&user, body.mfa_code.as_deref()).await?;
 
-  let res = AuthenticateResponse {
-    access_token: token,
-    user: PublicUser::from(user),
-  };
+  let res = auth::sign_in(user, &client).await?;
 
   Ok(Json(res))
 }
 
+/// Profile of the authenticated user.
+#[utoipa::path(
+  get,
+  path = "/v1/me",
+  responses(
+    (status = 200, description = "Profile of the user", body = PublicUser),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []), ("apiKey" = []))
+)]
+async fn get_profile(user: TokenUser) -> Result<Json<PublicUser>, Error> {
+  let user = match User::find_by_id(&user.id).await? {
+    Some(user) => user,
+    None => {
+      debug!("User not found, returning 404 status code");
+      return Err(Error::not_found());
+    }
+  };
+
+  debug!("Returning profile");
+  Ok(Json(PublicUser::from(user)))
+}
+
+/// Changes the name or email of the user. Changing the email takes the
+/// current password and an access token, signs the other sessions out and
+/// tells the previous email. A new email has to be verified again, a
+/// verification link is emailed to it.
+#[utoipa::path(
+  put,
+  path = "/v1/me",
+  request_body = UpdateProfile,
+  responses(
+    (status = 200, description = "Updated profile", body = PublicUser),
+    (status = 400, description = "Missing current password to change the email", body = ErrorResponse),
+    (status = 401, description = "Invalid authentication token, or wrong current password", body = ErrorResponse),
+    (status = 403, description = "Email changed with an API key", body = ErrorResponse),
+    (status = 409, description = "Email used by another user", body = ErrorResponse),
+    (status = 422, description = "Invalid request body, or email of a wallet user", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []), ("apiKey" = []))
+)]
+async fn update_profile(
+  State(state): State<AppState>,
+  user: TokenUser,
+  headers: HeaderMap,
+  ValidJson(payload): ValidJson<UpdateProfile>,
+) -> Result<Json<PublicUser>, Error> {
+  let current = match User::find_by_id(&user.id).await? {
+    Some(current) => current,
+    None => {
+      debug!("User not found, returning 404 status code");
+      return Err(Error::not_found());
+    }
+  };
+
+  let mut update = Document::new();
+  if let Some(name) = &payload.name {
+    update.insert("name", name.trim());
+  }
+  let email = payload
+    .email
+    .as_deref()
+    .map(str::trim)
+    .filter(|email| *email != current.email);
+  if let Some(email) = email {
+    if current.wallet.is_some() {
+      return Err(Error::InvalidPayload(
+        "Wallet users can't change their email".to_owned(),
+      ));
+    }
+    if user::is_wallet_email(email) {
+      debug!("Email of a wallet user, returning 422 status code");
+      return Err(Error::InvalidPayload(
+        "Emails of wallet users are reserved".to_owned(),
+      ));
+    }
+    require_token(&headers)?;
+    let password = match payload.current_password.as_deref() {
+      Some(password) if !password.is_empty() => password,
+      _ => {
+        debug!("Missing current password, returning 400 status code");
+        return Err(Error::bad_request());
+      }
+    };
+    if !current.is_password_match(password) {
+      debug!("Current password is incorrect, returning 401 status code");
+      return Err(Error::Authenticate(AuthenticateError::WrongCredentials));
+    }
+    if User::exists(doc! { "email": email, "_id": { "$ne": &user.id } }).await? {
+      debug!("Email used by another user, returning 409 status code");
+      return Err(Error::conflict());
+    }
+    update.insert("email", email);
+    update.insert("email_verified_at", Bson::Null);
+  }
+  update.insert("updated_at", date::now());
+
+  let updated =
+    User::find_one_and_update(doc! { "_id": &user.id }, doc! { "$set": update }).await?;
+  let updated = match updated {
+    Some(updated) => updated,
+    None => {
+      debug!("User not found, returning 404 status code");
+      return Err(Error::not_found());
+    }
+  };
+
+  if email.is_some() {
+    auth::revoke_other_sessions(user.id, user.session).await?;
+    spawn_email_change_notice(state.clone(), current, updated.email.clone());
+    spawn_email_verification(state, updated.clone());
+  }
+
+  debug!("Returning updated profile");
+  Ok(Json(PublicUser::from(updated)))
+}
+
+/// First step of deleting the account of the user, handing out the token
+/// confirming the deletion with `DELETE /me`.
+#[utoipa::path(
+  post,
+  path = "/v1/me/deletion",
+  responses(
+    (status = 201, description = "Deletion confirmation token", body = DeletionConfirmation),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 403, description = "Deletion requested with an API key", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []))
+)]
+async fn request_account_deletion(
+  State(state): State<AppState>,
+  user: TokenUser,
+  headers: HeaderMap,
+) -> Result<CustomResponse<DeletionConfirmation>, Error> {
+  require_token(&headers)?;
+  let confirmation = account::request_deletion(user.id, &state.settings.account).await?;
+
+  let res = CustomResponseBuilder::new()
+    .body(confirmation)
+    .status_code(StatusCode::CREATED)
+    .build();
+
+  Ok(res)
+}
+
+/// Deletes the account of the user along everything they own: cats,
+/// watchlists, address notes, alerts, webhooks, API keys and sessions.
+/// Clients sending `Prefer: respond-async` get a job instead, the account
+/// being locked until the job deletes it.
+#[utoipa::path(
+  delete,
+  path = "/v1/me",
+  request_body = DeleteAccount,
+  responses(
+    (status = 202, description = "Deletion queued", body = PublicJob),
+    (status = 204, description = "Account deleted"),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 403, description = "Deletion with an API key", body = ErrorResponse),
+    (status = 422, description = "Invalid or expired confirmation token", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []))
+)]
+async fn remove_account(
+  user: TokenUser,
+  headers: HeaderMap,
+  Json(payload): Json<DeleteAccount>,
+) -> Result<Response, Error> {
+  require_token(&headers)?;
+  if !account::confirm_deletion(user.id, &payload.confirmation_token).await? {
+    debug!("Invalid deletion confirmation token, returning 422 status code");
+    return Err(Error::InvalidPayload(
+      "Invalid or expired confirmation token".to_owned(),
+    ));
+  }
+
+  if respond_async(&headers) {
+    User::update_one(
+      doc! { "_id": &user.id },
+      doc! { "$set": { "locked_at": date::now(), "updated_at": date::now() } },
+      None,
+    )
+    .await?;
+    RefreshToken::delete_many(doc! { "user": &user.id }).await?;
+    Session::delete_many(doc! { "user": &user.id }).await?;
+    let job = jobs::enqueue(user.id, JobKind::AccountDeletion, &doc! {}).await?;
+
+    info!("Queued the deletion of user {}", user.id);
+    return Ok(accepted(job));
+  }
+
+  if !account::delete_user(user.id).await? {
+    debug!("User not found, returning 404 status code");
+    return Err(Error::not_found());
+  }
+  info!("User {} deleted their account", user.id);
+
+  Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+/// Emails a verification link to a new email in the background. Failing to
+/// send it doesn't fail the request, users can ask for another one.
+fn spawn_email_verification(state: AppState, user: User) {
+  let notifier = match state.notifiers.email() {
+    Some(notifier) => notifier.clone(),
+    None => {
+      debug!("No SMTP server configured, skipping email verification");
+      return;
+    }
+  };
+
+  tokio::spawn(async move {
+    let result = account::send_email_verification(&user, &notifier, &state.settings.account).await;
+    if let Err(err) = result {
+      error!("Failed to send email verification email: {}", err);
+    }
+  });
+}
+
+/// Tells the previous email of a user it was changed in the background,
+/// like `spawn_email_verification`.
+fn spawn_email_change_notice(state: AppState, user: User, email: String) {
+  let notifier = match state.notifiers.email() {
+    Some(notifier) => notifier.clone(),
+    None => {
+      debug!("No SMTP server configured, skipping email change notice");
+      return;
+    }
+  };
+
+  tokio::spawn(async move {
+    let result =
+      account::send_email_change_notice(&user, &email, &notifier, &state.settings.account).await;
+    if let Err(err) = result {
+      error!("Failed to send email change notice: {}", err);
+    }
+  });
+}
+
 // TODO: Validate password length
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
+#[schema(as = CreateUser)]
 struct CreateBody {
   name: String,
   email: String,
   password: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
+#[schema(as = AuthenticateUser)]
 struct AuthorizeBody {
   email: String,
   password: String,
+  // TOTP or recovery code, required when two-factor authentication is
+  // enabled.
+  #[serde(default)]
+  mfa_code: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct AuthenticateResponse {
   pub access_token: String,
+  // Exchanged for new tokens at `/v1/auth/refresh`.
+  pub refresh_token: String,
   pub user: PublicUser,
 }
+
+#[derive(Debug, Deserialize, ToSchema, Validate)]
+struct UpdateProfile {
+  #[validate(custom(function = "not_blank"))]
+  name: Option<String>,
+  #[validate(email)]
+  email: Option<String>,
+  // Required to change the email.
+  current_password: Option<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+struct DeleteAccount {
+  // Token of `POST /me/deletion`.
+  confirmation_token: String,
+}
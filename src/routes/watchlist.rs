@@ -0,0 +1,531 @@
+use axum::extract::{OriginalUri, Path, State};
+use axum::http::{HeaderMap, StatusCode};
+use bson::doc;
+use bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+use utoipa::{OpenApi, ToSchema};
+use validator::Validate;
+use wither::mongodb::options::FindOptions;
+
+use crate::errors::{Error, ErrorResponse};
+use crate::models::address_snapshot::{AddressSnapshot, PublicAddressSnapshot};
+use crate::models::share::{PublicShare, ShareAccess, SharedResource};
+use crate::models::user::Role;
+use crate::models::watched_address::{PublicWatchedAddress, WatchedAddress};
+use crate::models::watchlist::{PublicWatchlist, Watchlist};
+use crate::services::live::LiveEvent;
+use crate::services::ownership::Ownership;
+use crate::services::sharing::{self, CreateShare};
+use crate::state::AppState;
+use crate::utils::address::normalize_evm_address;
+use crate::utils::custom_response::{CustomResponse, CustomResponseBuilder};
+use crate::utils::date;
+use crate::utils::json::{Json, ValidJson};
+use crate::utils::links::Link;
+use crate::utils::models::ModelExt;
+use crate::utils::pagination::{Cursor, Pagination};
+use crate::utils::query::Query;
+use crate::utils::request_query::RequestQuery;
+use crate::utils::route_table::RouteTable;
+use crate::utils::to_object_id::to_object_id;
+use crate::utils::token::{MemberUser, TokenUser};
+use crate::utils::validation::{evm_address, not_blank};
+use crate::utils::version::expected_version;
+
+#[derive(OpenApi)]
+#[openapi(
+  paths(
+    create_watchlist,
+    add_watched_address,
+    get_watchlist_by_id,
+    update_watchlist_by_id,
+    query_watchlist_history,
+    create_watchlist_share,
+    query_watchlist_shares,
+    remove_watchlist_share
+  ),
+  components(schemas(
+    PublicWatchlist,
+    Link,
+    PublicWatchedAddress,
+    PublicAddressSnapshot,
+    WatchlistDetail,
+    CreateWatchlist,
+    UpdateWatchlist,
+    AddWatchedAddress,
+    PublicShare,
+    CreateShare,
+    SharedResource,
+    ShareAccess
+  ))
+)]
+pub struct ApiDoc;
+
+// Chain of the addresses added without one.
+const DEFAULT_CHAIN: &str = "ethereum";
+
+pub fn create_route() -> RouteTable<AppState> {
+  RouteTable::new()
+    .post("/watchlists", create_watchlist)
+    .get("/watchlists/:id", get_watchlist_by_id)
+    .put("/watchlists/:id", update_watchlist_by_id)
+    .post("/watchlists/:id/addresses", add_watched_address)
+    .get("/watchlists/:id/history", query_watchlist_history)
+    .post("/watchlists/:id/shares", create_watchlist_share)
+    .get("/watchlists/:id/shares", query_watchlist_shares)
+    .delete("/watchlists/:id/shares/:user", remove_watchlist_share)
+}
+
+#[utoipa::path(
+  post,
+  path = "/v1/watchlists",
+  request_body = CreateWatchlist,
+  responses(
+    (status = 201, description = "Watchlist created", body = PublicWatchlist),
+    (status = 400, description = "Empty watchlist name or invalid organization id", body = ErrorResponse),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 403, description = "User can't change the organization resources", body = ErrorResponse),
+    (status = 404, description = "Organization not found", body = ErrorResponse),
+    (status = 422, description = "Invalid request body", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []))
+)]
+async fn create_watchlist(
+  MemberUser(user): MemberUser,
+  Json(payload): Json<CreateWatchlist>,
+) -> Result<CustomResponse<PublicWatchlist>, Error> {
+  let organization = payload.organization.map(to_object_id).transpose()?;
+  let watchlist = insert_watchlist(&user, &payload.name, organization).await?;
+  let res = PublicWatchlist::from(watchlist);
+
+  let res = CustomResponseBuilder::new()
+    .body(res)
+    .status_code(StatusCode::CREATED)
+    .build();
+
+  Ok(res)
+}
+
+/// Creates a watchlist of the user, shared with the members of the
+/// organization when set. Also used by the gRPC API.
+pub async fn insert_watchlist(
+  user: &TokenUser,
+  name: &str,
+  organization: Option<ObjectId>,
+) -> Result<Watchlist, Error> {
+  let name = name.trim();
+  if name.is_empty() {
+    debug!("Empty watchlist name, returning 400 status code");
+    return Err(Error::bad_request());
+  }
+
+  Ownership::load(user.id)
+    .await?
+    .check(organization, Role::Member)?;
+
+  let mut watchlist = Watchlist::new(user.id, name.to_owned());
+  watchlist.organization = organization;
+  Watchlist::create(watchlist).await
+}
+
+#[utoipa::path(
+  post,
+  path = "/v1/watchlists/{id}/addresses",
+  params(("id" = String, Path, description = "Watchlist id")),
+  request_body = AddWatchedAddress,
+  responses(
+    (status = 201, description = "Address added to the watchlist", body = PublicWatchedAddress),
+    (status = 400, description = "Invalid watchlist id or chain", body = ErrorResponse),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 404, description = "Watchlist not found", body = ErrorResponse),
+    (status = 409, description = "Address already in the watchlist", body = ErrorResponse),
+    (status = 422, description = "Invalid request body or address", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []))
+)]
+async fn add_watched_address(
+  MemberUser(user): MemberUser,
+  State(state): State<AppState>,
+  Path(id): Path<String>,
+  ValidJson(payload): ValidJson<AddWatchedAddress>,
+) -> Result<CustomResponse<PublicWatchedAddress>, Error> {
+  let watchlist_id = to_object_id(id)?;
+  let watched = watch_address(&state, &user, watchlist_id, payload).await?;
+  let res = PublicWatchedAddress::from(watched);
+
+  let res = CustomResponseBuilder::new()
+    .body(res)
+    .status_code(StatusCode::CREATED)
+    .build();
+
+  Ok(res)
+}
+
+/// Adds an address to a watchlist the user can change, on the default chain
+/// unless the payload names one. Also used by the gRPC API.
+pub async fn watch_address(
+  state: &AppState,
+  user: &TokenUser,
+  watchlist_id: ObjectId,
+  payload: AddWatchedAddress,
+) -> Result<WatchedAddress, Error> {
+  let address = normalize_evm_address(&payload.address)?;
+  let chain = match payload.chain.as_deref().map(str::trim) {
+    None => DEFAULT_CHAIN.to_owned(),
+    Some(chain) if !chain.is_empty() => chain.to_lowercase(),
+    Some(_) => {
+      debug!("Empty chain, returning 400 status code");
+      return Err(Error::bad_request());
+    }
+  };
+  let nickname = payload
+    .nickname
+    .map(|nickname| nickname.trim().to_owned())
+    .filter(|nickname| !nickname.is_empty());
+
+  let mut query_filter = Ownership::load(user.id).await?.writable();
+  query_filter.insert("_id", watchlist_id);
+  if !Watchlist::exists(query_filter).await? {
+    debug!("Watchlist not found, returning 404 status code");
+    return Err(Error::not_found());
+  }
+
+  let is_watched = WatchedAddress::exists(doc! {
+    "watchlist": &watchlist_id,
+    "chain": &chain,
+    "address": &address
+  })
+  .await?;
+  if is_watched {
+    debug!("Address already watched, returning 409 status code");
+    return Err(Error::conflict());
+  }
+
+  let watched = WatchedAddress::new(watchlist_id, user.id, address, chain, nickname);
+  let watched = WatchedAddress::create(watched).await?;
+  // Sending only fails when nobody is connected.
+  let _ = state
+    .live_events
+    .send(LiveEvent::AddressWatched(watched.clone()));
+
+  Ok(watched)
+}
+
+#[utoipa::path(
+  get,
+  path = "/v1/watchlists/{id}",
+  params(("id" = String, Path, description = "Watchlist id")),
+  responses(
+    (status = 200, description = "Watchlist and its addresses", body = WatchlistDetail),
+    (status = 400, description = "Invalid watchlist id", body = ErrorResponse),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 404, description = "Watchlist not found", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []))
+)]
+async fn get_watchlist_by_id(
+  user: TokenUser,
+  Path(id): Path<String>,
+) -> Result<Json<WatchlistDetail>, Error> {
+  let watchlist_id = to_object_id(id)?;
+  let mut query_filter = Ownership::load(user.id).await?.readable();
+  query_filter.insert("_id", watchlist_id);
+  let watchlist = Watchlist::find_one(query_filter, None)
+    .await?
+    .map(PublicWatchlist::from);
+
+  let watchlist = match watchlist {
+    Some(watchlist) => watchlist,
+    None => {
+      debug!("Watchlist not found, returning 404 status code");
+      return Err(Error::not_found());
+    }
+  };
+
+  let options = FindOptions::builder()
+    .sort(doc! { "created_at": 1_i32 })
+    .build();
+  let addresses = WatchedAddress::find(doc! { "watchlist": &watchlist_id }, options)
+    .await?
+    .into_iter()
+    .map(Into::into)
+    .collect::<Vec<PublicWatchedAddress>>();
+
+  debug!("Returning watchlist");
+  Ok(Json(WatchlistDetail {
+    watchlist,
+    addresses,
+  }))
+}
+
+#[utoipa::path(
+  put,
+  path = "/v1/watchlists/{id}",
+  params(
+    ("id" = String, Path, description = "Watchlist id"),
+    ("If-Match" = String, Header, description = "Version of the watchlist being updated")
+  ),
+  request_body = UpdateWatchlist,
+  responses(
+    (status = 200, description = "Updated watchlist", body = PublicWatchlist),
+    (status = 400, description = "Invalid watchlist id or version", body = ErrorResponse),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 404, description = "Watchlist not found", body = ErrorResponse),
+    (status = 409, description = "Watchlist was modified by another request", body = ErrorResponse),
+    (status = 422, description = "Invalid request body", body = ErrorResponse),
+    (status = 428, description = "Missing If-Match header", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []))
+)]
+async fn update_watchlist_by_id(
+  MemberUser(user): MemberUser,
+  Path(id): Path<String>,
+  headers: HeaderMap,
+  ValidJson(payload): ValidJson<UpdateWatchlist>,
+) -> Result<Json<PublicWatchlist>, Error> {
+  let watchlist_id = to_object_id(id)?;
+  let version = expected_version(&headers)?;
+  let mut query_filter = Ownership::load(user.id).await?.writable();
+  query_filter.insert("_id", watchlist_id);
+
+  let update = doc! { "$set": {
+    "name": payload.name.trim(),
+    "updated_at": date::now()
+  } };
+  let watchlist = Watchlist::update_versioned(query_filter, version, update)
+    .await?
+    .map(PublicWatchlist::from);
+
+  let watchlist = match watchlist {
+    Some(watchlist) => watchlist,
+    None => {
+      debug!("Watchlist not found, returning 404 status code");
+      return Err(Error::not_found());
+    }
+  };
+
+  debug!("Returning watchlist");
+  Ok(Json(watchlist))
+}
+
+/// Snapshots of the addresses of the watchlist, newest first. The watcher
+/// records one every time the Arkham data of an address changes, see
+/// `GET /addresses/:address/diff` for what changed.
+#[utoipa::path(
+  get,
+  path = "/v1/watchlists/{id}/history",
+  params(("id" = String, Path, description = "Watchlist id"), RequestQuery),
+  responses(
+    (
+      status = 200,
+      description = "Paginated snapshots of the watchlist addresses",
+      body = [PublicAddressSnapshot],
+      headers(
+        ("x-pagination-count" = u64, description = "Total number of snapshots"),
+        ("x-pagination-offset" = u64, description = "Offset of the returned page"),
+        ("x-pagination-limit" = u64, description = "Size of the returned page"),
+        ("x-pagination-total-pages" = u64, description = "Total number of pages"),
+        ("x-pagination-has-next" = bool, description = "Whether there are snapshots after the returned page"),
+        ("x-pagination-next-cursor" = String, description = "Cursor of the next page, only sent for full pages")
+      )
+    ),
+    (status = 400, description = "Invalid watchlist id or query parameters", body = ErrorResponse),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 404, description = "Watchlist not found", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []))
+)]
+async fn query_watchlist_history(
+  user: TokenUser,
+  Path(id): Path<String>,
+  Query(query): Query<RequestQuery>,
+  OriginalUri(uri): OriginalUri,
+) -> Result<CustomResponse<Vec<PublicAddressSnapshot>>, Error> {
+  let watchlist_id = to_object_id(id)?;
+  let sort = query.sort(&["created_at"])?;
+  let mut query_filter = Ownership::load(user.id).await?.readable();
+  query_filter.insert("_id", watchlist_id);
+  if !Watchlist::exists(query_filter).await? {
+    debug!("Watchlist not found, returning 404 status code");
+    return Err(Error::not_found());
+  }
+
+  let addresses = WatchedAddress::find(doc! { "watchlist": &watchlist_id }, None)
+    .await?
+    .into_iter()
+    .map(|watched| watched.address)
+    .collect::<Vec<String>>();
+  let mut snapshot_filter = doc! { "address": { "$in": addresses } };
+  query.filter_created(&mut snapshot_filter);
+  let pagination = Pagination::build_from_request_query(query).uri(uri);
+
+  let options = FindOptions::builder()
+    .sort(sort)
+    .skip(pagination.offset)
+    .limit(pagination.limit as i64)
+    .build();
+
+  let (snapshots, count) =
+    AddressSnapshot::find_page_and_count(snapshot_filter, pagination.after_cursor(), options)
+      .await?;
+  let pagination = pagination.next_cursor(&snapshots, |snapshot| {
+    Cursor::new(snapshot.created_at, snapshot.id.unwrap())
+  });
+  let snapshots = snapshots
+    .into_iter()
+    .map(Into::into)
+    .collect::<Vec<PublicAddressSnapshot>>();
+
+  let res = CustomResponseBuilder::new()
+    .body(snapshots)
+    .pagination(pagination.count(count).build())
+    .build();
+
+  debug!("Returning watchlist history");
+  Ok(res)
+}
+
+/// Shares the watchlist with another user, or changes the access they were
+/// granted. Only the owners of the watchlist can share it, not the users it
+/// is shared with.
+#[utoipa::path(
+  post,
+  path = "/v1/watchlists/{id}/shares",
+  params(("id" = String, Path, description = "Watchlist id")),
+  request_body = CreateShare,
+  responses(
+    (status = 200, description = "Access of the user changed", body = PublicShare),
+    (status = 201, description = "Watchlist shared", body = PublicShare),
+    (status = 400, description = "Invalid watchlist id, or watchlist shared with its owner", body = ErrorResponse),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 404, description = "Watchlist or user not found", body = ErrorResponse),
+    (status = 422, description = "Invalid request body", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []))
+)]
+async fn create_watchlist_share(
+  MemberUser(user): MemberUser,
+  Path(id): Path<String>,
+  ValidJson(payload): ValidJson<CreateShare>,
+) -> Result<CustomResponse<PublicShare>, Error> {
+  let watchlist_id = to_object_id(id)?;
+  check_watchlist_owner(user.id, watchlist_id).await?;
+
+  let (share, created) =
+    sharing::grant(SharedResource::Watchlist, watchlist_id, user.id, payload).await?;
+  let status_code = if created {
+    StatusCode::CREATED
+  } else {
+    StatusCode::OK
+  };
+
+  let res = CustomResponseBuilder::new()
+    .body(share)
+    .status_code(status_code)
+    .build();
+
+  Ok(res)
+}
+
+#[utoipa::path(
+  get,
+  path = "/v1/watchlists/{id}/shares",
+  params(("id" = String, Path, description = "Watchlist id")),
+  responses(
+    (status = 200, description = "Users the watchlist is shared with", body = [PublicShare]),
+    (status = 400, description = "Invalid watchlist id", body = ErrorResponse),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 404, description = "Watchlist not found", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []))
+)]
+async fn query_watchlist_shares(
+  user: TokenUser,
+  Path(id): Path<String>,
+) -> Result<Json<Vec<PublicShare>>, Error> {
+  let watchlist_id = to_object_id(id)?;
+  check_watchlist_owner(user.id, watchlist_id).await?;
+
+  let shares = sharing::list(watchlist_id).await?;
+
+  debug!("Returning watchlist shares");
+  Ok(Json(shares))
+}
+
+#[utoipa::path(
+  delete,
+  path = "/v1/watchlists/{id}/shares/{user}",
+  params(
+    ("id" = String, Path, description = "Watchlist id"),
+    ("user" = String, Path, description = "Id of the user the watchlist is shared with")
+  ),
+  responses(
+    (status = 204, description = "Share revoked"),
+    (status = 400, description = "Invalid watchlist or user id", body = ErrorResponse),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 404, description = "Watchlist or share not found", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []))
+)]
+async fn remove_watchlist_share(
+  MemberUser(user): MemberUser,
+  Path((id, shared_with)): Path<(String, String)>,
+) -> Result<CustomResponse<()>, Error> {
+  let watchlist_id = to_object_id(id)?;
+  let shared_with = to_object_id(shared_with)?;
+  check_watchlist_owner(user.id, watchlist_id).await?;
+
+  if !sharing::revoke(watchlist_id, shared_with).await? {
+    debug!("Share not found, returning 404 status code");
+    return Err(Error::not_found());
+  }
+
+  let res = CustomResponseBuilder::new()
+    .status_code(StatusCode::NO_CONTENT)
+    .build();
+
+  Ok(res)
+}
+
+/// Fails with a 404 unless the user owns the watchlist, see
+/// `Ownership::owned`.
+async fn check_watchlist_owner(user: ObjectId, watchlist_id: ObjectId) -> Result<(), Error> {
+  let mut query_filter = Ownership::load(user).await?.owned();
+  query_filter.insert("_id", watchlist_id);
+  if !Watchlist::exists(query_filter).await? {
+    debug!("Watchlist not found, returning 404 status code");
+    return Err(Error::not_found());
+  }
+
+  Ok(())
+}
+
+#[derive(Deserialize, ToSchema)]
+struct CreateWatchlist {
+  name: String,
+  // Shares the watchlist with the members of the organization.
+  organization: Option<String>,
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+struct UpdateWatchlist {
+  #[validate(custom(function = "not_blank"))]
+  name: String,
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct AddWatchedAddress {
+  #[validate(custom(function = "evm_address"))]
+  pub address: String,
+  // Defaults to `ethereum`.
+  pub chain: Option<String>,
+  pub nickname: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct WatchlistDetail {
+  #[serde(flatten)]
+  pub watchlist: PublicWatchlist,
+  pub addresses: Vec<PublicWatchedAddress>,
+}
@@ -0,0 +1,450 @@
+use axum::extract::{OriginalUri, Path, State};
+use axum::http::StatusCode;
+use bson::doc;
+use bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+use utoipa::{OpenApi, ToSchema};
+use wither::mongodb::options::FindOptions;
+
+use crate::errors::{Error, ErrorResponse};
+use crate::models::alert_rule::AlertCondition;
+use crate::models::webhook_dead_letter::{PublicWebhookDeadLetter, WebhookDeadLetter};
+use crate::models::webhook_delivery::{
+  DeliveryStatus, PublicWebhookDelivery, WebhookDelivery, WEBHOOK_DELIVERY_FILTER_FIELDS,
+  WEBHOOK_DELIVERY_SORT_FIELDS,
+};
+use crate::models::webhook_endpoint::{PublicWebhookEndpoint, WebhookEndpoint};
+use crate::services::webhooks;
+use crate::state::AppState;
+use crate::utils::custom_response::{CustomResponse, CustomResponseBuilder};
+use crate::utils::date;
+use crate::utils::json::Json;
+use crate::utils::models::ModelExt;
+use crate::utils::pagination::{Cursor, Pagination};
+use crate::utils::query::Query;
+use crate::utils::query_fields::Filters;
+use crate::utils::request_query::RequestQuery;
+use crate::utils::route_table::RouteTable;
+use crate::utils::to_object_id::to_object_id;
+use crate::utils::token::{MemberUser, TokenUser};
+
+#[derive(OpenApi)]
+#[openapi(
+  paths(
+    create_webhook,
+    query_webhooks,
+    get_webhook_by_id,
+    remove_webhook_by_id,
+    rotate_webhook_secret_by_id,
+    test_webhook_by_id,
+    query_webhook_deliveries,
+    query_webhook_dead_letters
+  ),
+  components(schemas(
+    PublicWebhookEndpoint,
+    CreatedWebhook,
+    WebhookTest,
+    PublicWebhookDelivery,
+    PublicWebhookDeadLetter,
+    DeliveryStatus,
+    CreateWebhook
+  ))
+)]
+pub struct ApiDoc;
+
+pub fn create_route() -> RouteTable<AppState> {
+  RouteTable::new()
+    .post("/webhooks", create_webhook)
+    .get("/webhooks", query_webhooks)
+    .get("/webhooks/:id", get_webhook_by_id)
+    .delete("/webhooks/:id", remove_webhook_by_id)
+    .post("/webhooks/:id/secret", rotate_webhook_secret_by_id)
+    .post("/webhooks/:id/test", test_webhook_by_id)
+    .get("/webhooks/:id/deliveries", query_webhook_deliveries)
+    .get("/webhooks/:id/dead-letters", query_webhook_dead_letters)
+}
+
+/// Creates a webhook endpoint. The secret signing its payloads is only
+/// returned by this request.
+#[utoipa::path(
+  post,
+  path = "/v1/webhooks",
+  request_body = CreateWebhook,
+  responses(
+    (status = 201, description = "Webhook endpoint created", body = CreatedWebhook),
+    (status = 400, description = "Invalid webhook URL", body = ErrorResponse),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 422, description = "Invalid request body", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []))
+)]
+async fn create_webhook(
+  MemberUser(user): MemberUser,
+  Json(payload): Json<CreateWebhook>,
+) -> Result<CustomResponse<CreatedWebhook>, Error> {
+  let url = payload.url.trim();
+  let is_http = reqwest::Url::parse(url)
+    .map(|url| matches!(url.scheme(), "http" | "https"))
+    .unwrap_or(false);
+  if !is_http {
+    debug!("Invalid webhook URL, returning 400 status code");
+    return Err(Error::bad_request());
+  }
+
+  let mut events = Vec::new();
+  for event in payload.events.unwrap_or_default() {
+    if !events.contains(&event) {
+      events.push(event);
+    }
+  }
+
+  let endpoint = WebhookEndpoint::new(user.id, url.to_owned(), events);
+  let endpoint = WebhookEndpoint::create(endpoint).await?;
+  let res = CreatedWebhook {
+    secret: endpoint.secret.clone().unwrap_or_default(),
+    webhook: PublicWebhookEndpoint::from(endpoint),
+  };
+
+  let res = CustomResponseBuilder::new()
+    .body(res)
+    .status_code(StatusCode::CREATED)
+    .build();
+
+  Ok(res)
+}
+
+#[utoipa::path(
+  get,
+  path = "/v1/webhooks",
+  responses(
+    (status = 200, description = "Webhook endpoints of the user", body = [PublicWebhookEndpoint]),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []))
+)]
+async fn query_webhooks(user: TokenUser) -> Result<Json<Vec<PublicWebhookEndpoint>>, Error> {
+  let options = FindOptions::builder()
+    .sort(doc! { "created_at": 1_i32 })
+    .build();
+  let endpoints = WebhookEndpoint::find(doc! { "user": &user.id }, options)
+    .await?
+    .into_iter()
+    .map(Into::into)
+    .collect::<Vec<PublicWebhookEndpoint>>();
+
+  debug!("Returning webhook endpoints");
+  Ok(Json(endpoints))
+}
+
+#[utoipa::path(
+  get,
+  path = "/v1/webhooks/{id}",
+  params(("id" = String, Path, description = "Webhook endpoint id")),
+  responses(
+    (status = 200, description = "Webhook endpoint", body = PublicWebhookEndpoint),
+    (status = 400, description = "Invalid webhook endpoint id", body = ErrorResponse),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 404, description = "Webhook endpoint not found", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []))
+)]
+async fn get_webhook_by_id(
+  user: TokenUser,
+  Path(id): Path<String>,
+) -> Result<Json<PublicWebhookEndpoint>, Error> {
+  let endpoint = find_endpoint(&user, id).await?;
+
+  debug!("Returning webhook endpoint");
+  Ok(Json(PublicWebhookEndpoint::from(endpoint)))
+}
+
+/// Pending deliveries of a removed endpoint are moved to the dead letters by
+/// the delivery worker.
+#[utoipa::path(
+  delete,
+  path = "/v1/webhooks/{id}",
+  params(("id" = String, Path, description = "Webhook endpoint id")),
+  responses(
+    (status = 204, description = "Webhook endpoint removed"),
+    (status = 400, description = "Invalid webhook endpoint id", body = ErrorResponse),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 404, description = "Webhook endpoint not found", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []))
+)]
+async fn remove_webhook_by_id(
+  MemberUser(user): MemberUser,
+  Path(id): Path<String>,
+) -> Result<CustomResponse<()>, Error> {
+  let endpoint_id = to_object_id(id)?;
+  let delete_result =
+    WebhookEndpoint::delete_one(doc! { "_id": endpoint_id, "user": &user.id }).await?;
+
+  if delete_result.deleted_count == 0 {
+    debug!("Webhook endpoint not found, returning 404 status code");
+    return Err(Error::not_found());
+  }
+
+  let res = CustomResponseBuilder::new()
+    .status_code(StatusCode::NO_CONTENT)
+    .build();
+
+  Ok(res)
+}
+
+/// Replaces the secret signing the payloads of the endpoint, returning the
+/// new one. Payloads are signed with it from then on, including the pending
+/// deliveries. Endpoints created before signatures existed get their first
+/// secret this way.
+#[utoipa::path(
+  post,
+  path = "/v1/webhooks/{id}/secret",
+  params(("id" = String, Path, description = "Webhook endpoint id")),
+  responses(
+    (status = 200, description = "Webhook endpoint with its new secret", body = CreatedWebhook),
+    (status = 400, description = "Invalid webhook endpoint id", body = ErrorResponse),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 404, description = "Webhook endpoint not found", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []))
+)]
+async fn rotate_webhook_secret_by_id(
+  MemberUser(user): MemberUser,
+  Path(id): Path<String>,
+) -> Result<Json<CreatedWebhook>, Error> {
+  let endpoint_id = to_object_id(id)?;
+  let secret = WebhookEndpoint::generate_secret();
+
+  let endpoint = WebhookEndpoint::find_one_and_update(
+    doc! { "_id": &endpoint_id, "user": &user.id },
+    doc! { "$set": { "secret": &secret, "updated_at": date::now() } },
+  )
+  .await?;
+
+  let endpoint = match endpoint {
+    Some(endpoint) => endpoint,
+    None => {
+      debug!("Webhook endpoint not found, returning 404 status code");
+      return Err(Error::not_found());
+    }
+  };
+
+  debug!("Returning webhook endpoint with its new secret");
+  Ok(Json(CreatedWebhook {
+    webhook: PublicWebhookEndpoint::from(endpoint),
+    secret,
+  }))
+}
+
+/// Posts a sample alert event to the endpoint, signed like the real ones.
+/// The attempt is not retried nor recorded in the deliveries.
+#[utoipa::path(
+  post,
+  path = "/v1/webhooks/{id}/test",
+  params(("id" = String, Path, description = "Webhook endpoint id")),
+  responses(
+    (status = 200, description = "Result of the attempt", body = WebhookTest),
+    (status = 400, description = "Invalid webhook endpoint id", body = ErrorResponse),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 404, description = "Webhook endpoint not found", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []))
+)]
+async fn test_webhook_by_id(
+  MemberUser(user): MemberUser,
+  State(state): State<AppState>,
+  Path(id): Path<String>,
+) -> Result<Json<WebhookTest>, Error> {
+  let endpoint = find_endpoint(&user, id).await?;
+
+  let client = webhooks::http_client(&state.settings.webhooks);
+  let res = match webhooks::send_test(&client, &endpoint).await {
+    Ok(()) => WebhookTest {
+      delivered: true,
+      error: None,
+    },
+    Err(error) => WebhookTest {
+      delivered: false,
+      error: Some(error),
+    },
+  };
+
+  debug!("Returning webhook test result");
+  Ok(Json(res))
+}
+
+#[utoipa::path(
+  get,
+  path = "/v1/webhooks/{id}/deliveries",
+  params(("id" = String, Path, description = "Webhook endpoint id"), RequestQuery),
+  responses(
+    (
+      status = 200,
+      description = "Paginated pending and delivered deliveries, newest first",
+      body = [PublicWebhookDelivery],
+      headers(
+        ("x-pagination-count" = u64, description = "Total number of deliveries"),
+        ("x-pagination-offset" = u64, description = "Offset of the returned page"),
+        ("x-pagination-limit" = u64, description = "Size of the returned page"),
+        ("x-pagination-total-pages" = u64, description = "Total number of pages"),
+        ("x-pagination-has-next" = bool, description = "Whether there are deliveries after the returned page"),
+        ("x-pagination-next-cursor" = String, description = "Cursor of the next page, only sent for full pages")
+      )
+    ),
+    (status = 400, description = "Invalid webhook endpoint id or query parameters", body = ErrorResponse),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 404, description = "Webhook endpoint not found", body = ErrorResponse),
+    (status = 422, description = "Unsupported sort or filter field", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []))
+)]
+async fn query_webhook_deliveries(
+  user: TokenUser,
+  Path(id): Path<String>,
+  Query(query): Query<RequestQuery>,
+  OriginalUri(uri): OriginalUri,
+  filters: Filters,
+) -> Result<CustomResponse<Vec<PublicWebhookDelivery>>, Error> {
+  let endpoint_id = find_endpoint_id(&user, id).await?;
+  let sort = query.sort(WEBHOOK_DELIVERY_SORT_FIELDS)?;
+  let mut query_filter = doc! { "endpoint": endpoint_id };
+  query.filter_created(&mut query_filter);
+  filters.apply(WEBHOOK_DELIVERY_FILTER_FIELDS, &mut query_filter)?;
+  let pagination = Pagination::build_from_request_query(query).uri(uri);
+
+  let options = FindOptions::builder()
+    .sort(sort)
+    .skip(pagination.offset)
+    .limit(pagination.limit as i64)
+    .build();
+
+  let (deliveries, count) =
+    WebhookDelivery::find_page_and_count(query_filter, pagination.after_cursor(), options).await?;
+  let pagination = pagination.next_cursor(&deliveries, |delivery| {
+    Cursor::new(delivery.created_at, delivery.id.unwrap())
+  });
+  let deliveries = deliveries
+    .into_iter()
+    .map(Into::into)
+    .collect::<Vec<PublicWebhookDelivery>>();
+
+  let res = CustomResponseBuilder::new()
+    .body(deliveries)
+    .pagination(pagination.count(count).build())
+    .build();
+
+  debug!("Returning webhook deliveries");
+  Ok(res)
+}
+
+#[utoipa::path(
+  get,
+  path = "/v1/webhooks/{id}/dead-letters",
+  params(("id" = String, Path, description = "Webhook endpoint id"), RequestQuery),
+  responses(
+    (
+      status = 200,
+      description = "Paginated permanently failed deliveries, newest first",
+      body = [PublicWebhookDeadLetter],
+      headers(
+        ("x-pagination-count" = u64, description = "Total number of dead letters"),
+        ("x-pagination-offset" = u64, description = "Offset of the returned page"),
+        ("x-pagination-limit" = u64, description = "Size of the returned page"),
+        ("x-pagination-total-pages" = u64, description = "Total number of pages"),
+        ("x-pagination-has-next" = bool, description = "Whether there are dead letters after the returned page"),
+        ("x-pagination-next-cursor" = String, description = "Cursor of the next page, only sent for full pages")
+      )
+    ),
+    (status = 400, description = "Invalid webhook endpoint id or query parameters", body = ErrorResponse),
+    (status = 401, description = "Invalid authentication token", body = ErrorResponse),
+    (status = 404, description = "Webhook endpoint not found", body = ErrorResponse)
+  ),
+  security(("bearerAuth" = []))
+)]
+async fn query_webhook_dead_letters(
+  user: TokenUser,
+  Path(id): Path<String>,
+  Query(query): Query<RequestQuery>,
+  OriginalUri(uri): OriginalUri,
+) -> Result<CustomResponse<Vec<PublicWebhookDeadLetter>>, Error> {
+  let endpoint_id = find_endpoint_id(&user, id).await?;
+  let sort = query.sort(&["created_at"])?;
+  let mut query_filter = doc! { "endpoint": endpoint_id };
+  query.filter_created(&mut query_filter);
+  let pagination = Pagination::build_from_request_query(query).uri(uri);
+
+  let options = FindOptions::builder()
+    .sort(sort)
+    .skip(pagination.offset)
+    .limit(pagination.limit as i64)
+    .build();
+
+  let (dead_letters, count) =
+    WebhookDeadLetter::find_page_and_count(query_filter, pagination.after_cursor(), options)
+      .await?;
+  let pagination = pagination.next_cursor(&dead_letters, |dead_letter| {
+    Cursor::new(dead_letter.created_at, dead_letter.id.unwrap())
+  });
+  let dead_letters = dead_letters
+    .into_iter()
+    .map(Into::into)
+    .collect::<Vec<PublicWebhookDeadLetter>>();
+
+  let res = CustomResponseBuilder::new()
+    .body(dead_letters)
+    .pagination(pagination.count(count).build())
+    .build();
+
+  debug!("Returning webhook dead letters");
+  Ok(res)
+}
+
+/// Finds an endpoint owned by the user.
+async fn find_endpoint(user: &TokenUser, id: String) -> Result<WebhookEndpoint, Error> {
+  let endpoint_id = to_object_id(id)?;
+
+  match WebhookEndpoint::find_one(doc! { "_id": &endpoint_id, "user": &user.id }, None).await? {
+    Some(endpoint) => Ok(endpoint),
+    None => {
+      debug!("Webhook endpoint not found, returning 404 status code");
+      Err(Error::not_found())
+    }
+  }
+}
+
+/// Parses the id of an endpoint owned by the user.
+async fn find_endpoint_id(user: &TokenUser, id: String) -> Result<ObjectId, Error> {
+  let endpoint_id = to_object_id(id)?;
+
+  if !WebhookEndpoint::exists(doc! { "_id": &endpoint_id, "user": &user.id }).await? {
+    debug!("Webhook endpoint not found, returning 404 status code");
+    return Err(Error::not_found());
+  }
+
+  Ok(endpoint_id)
+}
+
+#[derive(Deserialize, ToSchema)]
+struct CreateWebhook {
+  url: String,
+  // Conditions of the alert events to post, every event when omitted or
+  // empty.
+  events: Option<Vec<AlertCondition>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CreatedWebhook {
+  #[serde(flatten)]
+  pub webhook: PublicWebhookEndpoint,
+  // Key of the HMAC-SHA256 signature sent in the `x-degen-signature` header
+  // of the payloads, see `services::webhooks::verify`.
+  pub secret: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct WebhookTest {
+  pub delivered: bool,
+  // Why the endpoint didn't accept the sample event.
+  pub error: Option<String>,
+}
@@ -0,0 +1,272 @@
+use bson::serde_helpers::bson_datetime_as_rfc3339_string;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tracing::debug;
+use utoipa::ToSchema;
+use wither::bson::{doc, oid::ObjectId};
+
+use crate::errors::Error;
+use crate::models::account_token::{AccountToken, TokenPurpose};
+use crate::models::address_query::AddressQuery;
+use crate::models::alert_event::AlertEvent;
+use crate::models::alert_rule::AlertRule;
+use crate::models::api_key::ApiKey;
+use crate::models::cat::Cat;
+use crate::models::job::{Job, JobKind};
+use crate::models::membership::Membership;
+use crate::models::notification_channel::NotificationChannel;
+use crate::models::refresh_token::RefreshToken;
+use crate::models::session::Session;
+use crate::models::share::Share;
+use crate::models::totp_factor::TotpFactor;
+use crate::models::usage::Usage;
+use crate::models::user::{self, User};
+use crate::models::user_address_note::UserAddressNote;
+use crate::models::watched_address::WatchedAddress;
+use crate::models::watchlist::Watchlist;
+use crate::models::webhook_dead_letter::WebhookDeadLetter;
+use crate::models::webhook_delivery::WebhookDelivery;
+use crate::models::webhook_endpoint::WebhookEndpoint;
+use crate::notifications::email::EmailNotifier;
+use crate::routes::cat::cat_attachment_files;
+use crate::services::{data_export, gridfs};
+use crate::settings;
+use crate::utils::date;
+use crate::utils::date::Date;
+use crate::utils::models::{transaction, ModelExt};
+use crate::utils::secret;
+
+/// Token confirming the deletion of an account, see `delete_user`.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct DeletionConfirmation {
+  pub confirmation_token: String,
+  #[serde(with = "bson_datetime_as_rfc3339_string")]
+  #[schema(value_type = String, format = DateTime)]
+  pub expires_at: Date,
+}
+
+/// Emails a password reset link to the user with this email. Unknown emails
+/// and wallet users are ignored, callers must not tell them apart so emails
+/// can't be enumerated.
+pub async fn request_password_reset(
+  email: &str,
+  notifier: &EmailNotifier,
+  settings: &settings::Account,
+) -> Result<(), Error> {
+  let user = match User::find_one(doc! { "email": email.trim() }, None).await? {
+    Some(user) if user.wallet.is_none() => user,
+    _ => {
+      debug!("No user to reset the password of, skipping email");
+      return Ok(());
+    }
+  };
+
+  let ttl = Duration::from_secs(settings.password_reset_ttl_secs);
+  let token = issue_token(user.id.unwrap(), TokenPurpose::PasswordReset, ttl).await?;
+  let link = link(&settings.app_url, "reset-password", &token);
+
+  notifier.send_password_reset(&user, &link).await
+}
+
+/// Sets a new password with a password reset token, and revokes the sessions
+/// of the user since their password may have leaked. Returns whether the
+/// token was valid.
+pub async fn reset_password(token: &str, password: String) -> Result<bool, Error> {
+  let user = match use_token(token, TokenPurpose::PasswordReset).await? {
+    Some(user) => user,
+    None => return Ok(false),
+  };
+
+  let password_hash = user::hash_password(password).await?;
+  User::update_one(
+    doc! { "_id": &user },
+    doc! { "$set": { "password": password_hash, "updated_at": date::now() } },
+    None,
+  )
+  .await?;
+
+  AccountToken::delete_many(doc! {
+    "user": &user,
+    "purpose": TokenPurpose::PasswordReset.as_str()
+  })
+  .await?;
+  RefreshToken::delete_many(doc! { "user": &user }).await?;
+  Session::delete_many(doc! { "user": &user }).await?;
+
+  Ok(true)
+}
+
+/// Emails an email verification link to a user, replacing the previous one.
+/// Wallet users have no email to verify.
+pub async fn send_email_verification(
+  user: &User,
+  notifier: &EmailNotifier,
+  settings: &settings::Account,
+) -> Result<(), Error> {
+  if user.wallet.is_some() {
+    return Ok(());
+  }
+
+  let ttl = Duration::from_secs(settings.email_verification_ttl_secs);
+  let token = issue_token(user.id.unwrap(), TokenPurpose::EmailVerification, ttl).await?;
+  let link = link(&settings.app_url, "verify-email", &token);
+
+  notifier.send_email_verification(user, &link).await
+}
+
+/// Tells the previous email of a user it was changed, with a password reset
+/// link to take the account back if it wasn't them. `user` is the user
+/// before the change.
+pub async fn send_email_change_notice(
+  user: &User,
+  email: &str,
+  notifier: &EmailNotifier,
+  settings: &settings::Account,
+) -> Result<(), Error> {
+  let ttl = Duration::from_secs(settings.password_reset_ttl_secs);
+  let token = issue_token(user.id.unwrap(), TokenPurpose::PasswordReset, ttl).await?;
+  let link = link(&settings.app_url, "reset-password", &token);
+
+  notifier.send_email_change(user, email, &link).await
+}
+
+/// Marks the email of a user as verified with an email verification token.
+/// Returns whether the token was valid.
+pub async fn verify_email(token: &str) -> Result<bool, Error> {
+  let user = match use_token(token, TokenPurpose::EmailVerification).await? {
+    Some(user) => user,
+    None => return Ok(false),
+  };
+
+  User::update_one(
+    doc! { "_id": &user, "email_verified_at": null },
+    doc! { "$set": { "email_verified_at": date::now(), "updated_at": date::now() } },
+    None,
+  )
+  .await?;
+
+  Ok(true)
+}
+
+/// First step of deleting an account, the token is then sent along the
+/// deletion request so it can't be deleted by mistake.
+pub async fn request_deletion(
+  user: ObjectId,
+  settings: &settings::Account,
+) -> Result<DeletionConfirmation, Error> {
+  let ttl = Duration::from_secs(settings.deletion_confirmation_ttl_secs);
+  let expires_at = date::after(ttl);
+  let confirmation_token = issue_token(user, TokenPurpose::AccountDeletion, ttl).await?;
+
+  Ok(DeletionConfirmation {
+    confirmation_token,
+    expires_at,
+  })
+}
+
+/// Checks the token of `request_deletion`, using it. Returns whether it was
+/// issued to this user.
+pub async fn confirm_deletion(user: ObjectId, token: &str) -> Result<bool, Error> {
+  let owner = use_token(token, TokenPurpose::AccountDeletion).await?;
+
+  Ok(owner == Some(user))
+}
+
+/// Deletes a user along everything they own, their attached files included.
+/// Returns whether the user existed.
+pub async fn delete_user(user: ObjectId) -> Result<bool, Error> {
+  // Files can't be deleted in the transaction, they are once it commits.
+  let mut files = cat_attachment_files(doc! { "user": user }).await?;
+  files.extend(data_export::archive_files(user).await?);
+  // The user and their data are removed together.
+  let removed = transaction(move || async move {
+    let delete_result = User::delete_one(doc! { "_id": &user }).await?;
+    if delete_result.deleted_count == 0 {
+      return Ok(false);
+    }
+
+    remove_user_data(&user).await?;
+    Ok(true)
+  })
+  .await?;
+
+  if removed {
+    gridfs::delete_many(files).await?;
+  }
+
+  Ok(removed)
+}
+
+async fn remove_user_data(user: &ObjectId) -> Result<(), Error> {
+  let query = doc! { "user": user };
+
+  Cat::delete_many(query.clone()).await?;
+  ApiKey::delete_many(query.clone()).await?;
+  RefreshToken::delete_many(query.clone()).await?;
+  Session::delete_many(query.clone()).await?;
+  TotpFactor::delete_many(query.clone()).await?;
+  AccountToken::delete_many(query.clone()).await?;
+  Membership::delete_many(query.clone()).await?;
+  Watchlist::delete_many(query.clone()).await?;
+  WatchedAddress::delete_many(query.clone()).await?;
+  AlertRule::delete_many(query.clone()).await?;
+  AlertEvent::delete_many(query.clone()).await?;
+  NotificationChannel::delete_many(query.clone()).await?;
+  WebhookEndpoint::delete_many(query.clone()).await?;
+  WebhookDelivery::delete_many(query.clone()).await?;
+  WebhookDeadLetter::delete_many(query.clone()).await?;
+  UserAddressNote::delete_many(query.clone()).await?;
+  Usage::delete_many(query.clone()).await?;
+  AddressQuery::delete_many(query.clone()).await?;
+  // Grants to the user, and the ones they gave on their resources.
+  Share::delete_many(doc! { "$or": [{ "user": user }, { "granted_by": user }] }).await?;
+  // The deletion job itself is kept, its status stays readable.
+  Job::delete_many(doc! {
+    "user": user,
+    "kind": { "$ne": JobKind::AccountDeletion.as_str() }
+  })
+  .await?;
+
+  Ok(())
+}
+
+/// Generates a token, deleting the previous ones of the user with the same
+/// purpose so only the last email works.
+async fn issue_token(
+  user: ObjectId,
+  purpose: TokenPurpose,
+  ttl: Duration,
+) -> Result<String, Error> {
+  AccountToken::delete_many(doc! { "user": &user, "purpose": purpose.as_str() }).await?;
+  let (model, token) = AccountToken::generate(user, purpose, date::after(ttl));
+  AccountToken::create(model).await?;
+
+  Ok(token)
+}
+
+/// Deletes a token atomically, so it can only be used once. Returns its
+/// user, `None` when the token is unknown, expired or for another purpose.
+async fn use_token(token: &str, purpose: TokenPurpose) -> Result<Option<ObjectId>, Error> {
+  let token = AccountToken::find_one_and_delete(doc! {
+    "hash": secret::hash(token.trim()),
+    "purpose": purpose.as_str(),
+    "expires_at": { "$gt": date::now() }
+  })
+  .await?;
+
+  Ok(token.map(|token| token.user))
+}
+
+/// Link to a page of the web app, handing it the token.
+fn link(app_url: &str, page: &str, token: &str) -> String {
+  let mut url = Url::parse(app_url).expect("account.app_url is validated on startup");
+  url
+    .path_segments_mut()
+    .expect("HTTP URLs have a path")
+    .pop_if_empty()
+    .push(page);
+  url.query_pairs_mut().append_pair("token", token);
+
+  url.to_string()
+}
@@ -0,0 +1,311 @@
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::sync::Arc;
+use tracing::{debug, warn};
+
+use crate::errors::Error;
+use crate::routes::arkham::{
+  ArkhamEntityDetail, ArkhamPortfolio, ArkhamResponse, ArkhamTransfers, TransfersQuery,
+};
+use crate::services::arkham::ArkhamClient;
+use crate::utils::cache::TtlCache;
+
+/// Source of intelligence data on EVM addresses and the entities behind
+/// them. Implementations are composed with `ChainedProvider` so lookups keep
+/// working when one of them is unavailable.
+#[async_trait]
+pub trait IntelligenceProvider: Send + Sync {
+  async fn lookup_address(&self, address: &str) -> Result<ArkhamResponse, Error>;
+  async fn lookup_entity(&self, entity_id: &str) -> Result<ArkhamEntityDetail, Error>;
+  async fn transfers(
+    &self,
+    address: &str,
+    query: &TransfersQuery,
+  ) -> Result<ArkhamTransfers, Error>;
+  async fn portfolio(&self, address: &str) -> Result<ArkhamPortfolio, Error>;
+}
+
+/// Fetches data from the Arkham API and records the addresses in the given
+/// cache, so a `CachedProvider` reading the same cache can serve them later.
+pub struct ArkhamProvider {
+  client: ArkhamClient,
+  cache: Arc<TtlCache<ArkhamResponse>>,
+}
+
+impl ArkhamProvider {
+  pub fn new(client: ArkhamClient, cache: Arc<TtlCache<ArkhamResponse>>) -> Self {
+    Self { client, cache }
+  }
+}
+
+#[async_trait]
+impl IntelligenceProvider for ArkhamProvider {
+  async fn lookup_address(&self, address: &str) -> Result<ArkhamResponse, Error> {
+    let arkham_data = self.client.fetch_address(address).await?;
+    self.cache.insert(address, arkham_data.clone()).await;
+
+    Ok(arkham_data)
+  }
+
+  async fn lookup_entity(&self, entity_id: &str) -> Result<ArkhamEntityDetail, Error> {
+    self.client.fetch_entity(entity_id).await
+  }
+
+  async fn transfers(
+    &self,
+    address: &str,
+    query: &TransfersQuery,
+  ) -> Result<ArkhamTransfers, Error> {
+    self.client.fetch_transfers(address, query).await
+  }
+
+  async fn portfolio(&self, address: &str) -> Result<ArkhamPortfolio, Error> {
+    self.client.fetch_portfolio(address).await
+  }
+}
+
+/// Serves the last known data for an address, even if it has expired.
+/// Entities, transfers and portfolios are not cached here, so they are never
+/// found.
+pub struct CachedProvider {
+  cache: Arc<TtlCache<ArkhamResponse>>,
+}
+
+impl CachedProvider {
+  pub fn new(cache: Arc<TtlCache<ArkhamResponse>>) -> Self {
+    Self { cache }
+  }
+}
+
+#[async_trait]
+impl IntelligenceProvider for CachedProvider {
+  async fn lookup_address(&self, address: &str) -> Result<ArkhamResponse, Error> {
+    match self.cache.get_stale(address).await {
+      Some(entry) => {
+        debug!("Serving cached Arkham data for address: {}", address);
+        Ok(entry.value)
+      }
+      None => Err(Error::not_found()),
+    }
+  }
+
+  async fn lookup_entity(&self, _entity_id: &str) -> Result<ArkhamEntityDetail, Error> {
+    Err(Error::not_found())
+  }
+
+  async fn transfers(
+    &self,
+    _address: &str,
+    _query: &TransfersQuery,
+  ) -> Result<ArkhamTransfers, Error> {
+    Err(Error::not_found())
+  }
+
+  async fn portfolio(&self, _address: &str) -> Result<ArkhamPortfolio, Error> {
+    Err(Error::not_found())
+  }
+}
+
+/// Serves fixed data instead of calling an upstream, to run the app and its
+/// tests without an Arkham API key. Unknown addresses and entities are not
+/// found, and addresses without fixture transfers or portfolio have none.
+/// Transfers are returned as is, ignoring the pagination and time range of
+/// the query.
+#[derive(Debug, Deserialize)]
+pub struct FixtureProvider {
+  // Keyed by lowercased address.
+  #[serde(default)]
+  addresses: HashMap<String, ArkhamResponse>,
+  #[serde(default)]
+  entities: HashMap<String, ArkhamEntityDetail>,
+  // Keyed by lowercased address.
+  #[serde(default)]
+  transfers: HashMap<String, ArkhamTransfers>,
+  // Keyed by lowercased address.
+  #[serde(default)]
+  portfolios: HashMap<String, ArkhamPortfolio>,
+}
+
+impl FixtureProvider {
+  /// Reads the fixtures from a JSON file, see `from_json`.
+  pub fn load(path: &str) -> io::Result<Self> {
+    let reader = BufReader::new(File::open(path)?);
+    let fixtures = serde_json::from_reader(reader)?;
+
+    Ok(Self::from_json(fixtures)?)
+  }
+
+  /// Fixtures from an object with `addresses`, `entities`, `transfers` and
+  /// `portfolios` objects, keyed by address or entity id. Addresses are
+  /// matched regardless of their case.
+  pub fn from_json(fixtures: Value) -> serde_json::Result<Self> {
+    let fixtures: Self = serde_json::from_value(fixtures)?;
+
+    Ok(Self {
+      addresses: lowercase_keys(fixtures.addresses),
+      entities: fixtures.entities,
+      transfers: lowercase_keys(fixtures.transfers),
+      portfolios: lowercase_keys(fixtures.portfolios),
+    })
+  }
+}
+
+fn lowercase_keys<V>(map: HashMap<String, V>) -> HashMap<String, V> {
+  map
+    .into_iter()
+    .map(|(key, value)| (key.to_lowercase(), value))
+    .collect()
+}
+
+#[async_trait]
+impl IntelligenceProvider for FixtureProvider {
+  async fn lookup_address(&self, address: &str) -> Result<ArkhamResponse, Error> {
+    match self.addresses.get(&address.to_lowercase()) {
+      Some(data) => Ok(data.clone()),
+      None => Err(Error::not_found()),
+    }
+  }
+
+  async fn lookup_entity(&self, entity_id: &str) -> Result<ArkhamEntityDetail, Error> {
+    match self.entities.get(entity_id) {
+      Some(entity) => Ok(entity.clone()),
+      None => Err(Error::not_found()),
+    }
+  }
+
+  async fn transfers(
+    &self,
+    address: &str,
+    _query: &TransfersQuery,
+  ) -> Result<ArkhamTransfers, Error> {
+    let transfers = self
+      .transfers
+      .get(&address.to_lowercase())
+      .cloned()
+      .unwrap_or(ArkhamTransfers {
+        transfers: Vec::new(),
+        count: Some(0),
+      });
+
+    Ok(transfers)
+  }
+
+  async fn portfolio(&self, address: &str) -> Result<ArkhamPortfolio, Error> {
+    let portfolio = self
+      .portfolios
+      .get(&address.to_lowercase())
+      .cloned()
+      .unwrap_or_default();
+
+    Ok(portfolio)
+  }
+}
+
+/// Tries a primary provider and then each fallback in order, returning the
+/// first successful lookup. When every provider fails, the primary error is
+/// returned since it is the most relevant one.
+pub struct ChainedProvider {
+  primary: Box<dyn IntelligenceProvider>,
+  fallbacks: Vec<Box<dyn IntelligenceProvider>>,
+}
+
+impl ChainedProvider {
+  pub fn new<P: IntelligenceProvider + 'static>(primary: P) -> Self {
+    Self {
+      primary: Box::new(primary),
+      fallbacks: Vec::new(),
+    }
+  }
+
+  pub fn fallback<P: IntelligenceProvider + 'static>(mut self, provider: P) -> Self {
+    self.fallbacks.push(Box::new(provider));
+    self
+  }
+}
+
+#[async_trait]
+impl IntelligenceProvider for ChainedProvider {
+  async fn lookup_address(&self, address: &str) -> Result<ArkhamResponse, Error> {
+    let primary_err = match self.primary.lookup_address(address).await {
+      Ok(data) => return Ok(data),
+      Err(err) => err,
+    };
+
+    warn!(
+      "Primary address intelligence lookup failed: {}. Trying fallbacks",
+      primary_err
+    );
+    for fallback in &self.fallbacks {
+      if let Ok(data) = fallback.lookup_address(address).await {
+        return Ok(data);
+      }
+    }
+
+    Err(primary_err)
+  }
+
+  async fn lookup_entity(&self, entity_id: &str) -> Result<ArkhamEntityDetail, Error> {
+    let primary_err = match self.primary.lookup_entity(entity_id).await {
+      Ok(entity) => return Ok(entity),
+      Err(err) => err,
+    };
+
+    warn!(
+      "Primary entity intelligence lookup failed: {}. Trying fallbacks",
+      primary_err
+    );
+    for fallback in &self.fallbacks {
+      if let Ok(entity) = fallback.lookup_entity(entity_id).await {
+        return Ok(entity);
+      }
+    }
+
+    Err(primary_err)
+  }
+
+  async fn transfers(
+    &self,
+    address: &str,
+    query: &TransfersQuery,
+  ) -> Result<ArkhamTransfers, Error> {
+    let primary_err = match self.primary.transfers(address, query).await {
+      Ok(transfers) => return Ok(transfers),
+      Err(err) => err,
+    };
+
+    warn!(
+      "Primary transfers lookup failed: {}. Trying fallbacks",
+      primary_err
+    );
+    for fallback in &self.fallbacks {
+      if let Ok(transfers) = fallback.transfers(address, query).await {
+        return Ok(transfers);
+      }
+    }
+
+    Err(primary_err)
+  }
+
+  async fn portfolio(&self, address: &str) -> Result<ArkhamPortfolio, Error> {
+    let primary_err = match self.primary.portfolio(address).await {
+      Ok(portfolio) => return Ok(portfolio),
+      Err(err) => err,
+    };
+
+    warn!(
+      "Primary portfolio lookup failed: {}. Trying fallbacks",
+      primary_err
+    );
+    for fallback in &self.fallbacks {
+      if let Ok(portfolio) = fallback.portfolio(address).await {
+        return Ok(portfolio);
+      }
+    }
+
+    Err(primary_err)
+  }
+}
@@ -0,0 +1,524 @@
+use chrono::DateTime;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::time::Duration;
+use tracing::debug;
+use wither::bson::{doc, oid::ObjectId};
+use wither::mongodb::options::FindOneOptions;
+
+use crate::errors::Error;
+use crate::models::alert_event::AlertEvent;
+use crate::models::alert_rule::{AlertCondition, AlertRule};
+use crate::models::price_sample::PriceSample;
+use crate::models::watched_address::WatchedAddress;
+use crate::routes::arkham::{
+  ArkhamChainData, ArkhamResponse, ArkhamTransfer, ArkhamTransferAddress, TransfersQuery,
+};
+use crate::services::address_intelligence::IntelligenceProvider;
+use crate::services::live::LiveEvent;
+use crate::services::prices::PriceProvider;
+use crate::services::watcher::AddressChange;
+use crate::services::webhooks;
+use crate::settings;
+use crate::state::AppState;
+use crate::utils::date;
+use crate::utils::date::Date;
+use crate::utils::models::ModelExt;
+
+/// A condition matching the change of an address on a chain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trigger {
+  pub chain: String,
+  pub message: String,
+  // Arkham entity of the address on the chain, if any.
+  pub entity: Option<String>,
+}
+
+/// Evaluates a condition against two snapshots of an address, returning a
+/// trigger for every chain the condition matches on.
+pub fn evaluate(
+  condition: AlertCondition,
+  previous: &ArkhamResponse,
+  current: &ArkhamResponse,
+) -> Vec<Trigger> {
+  current
+    .chains()
+    .into_iter()
+    .filter_map(|(chain, data)| {
+      let message = evaluate_chain(condition, previous.chain(chain), data)?;
+      Some(Trigger {
+        chain: chain.to_owned(),
+        message,
+        entity: data.entity_name().map(str::to_owned),
+      })
+    })
+    .collect()
+}
+
+fn evaluate_chain(
+  condition: AlertCondition,
+  previous: Option<&ArkhamChainData>,
+  current: &ArkhamChainData,
+) -> Option<String> {
+  match condition {
+    AlertCondition::EntityLabelChanged => {
+      // An address showing up on a new chain is not a label change.
+      let previous = display_name(previous?);
+      let current = display_name(current);
+      (previous != current).then(|| {
+        format!(
+          "Entity label changed from {} to {}",
+          previous.unwrap_or("none"),
+          current.unwrap_or("none")
+        )
+      })
+    }
+    AlertCondition::FlaggedAsContract => {
+      let was_contract = previous.is_some_and(ArkhamChainData::is_contract);
+      (current.is_contract() && !was_contract).then(|| String::from("Address flagged as contract"))
+    }
+    AlertCondition::EntityAttached => {
+      let entity_id = current.entity_id()?;
+      let previous_id = previous.and_then(ArkhamChainData::entity_id);
+      (previous_id != Some(entity_id)).then(|| {
+        format!(
+          "Arkham entity {} attached",
+          current.entity_name().unwrap_or(entity_id)
+        )
+      })
+    }
+    // Raised from the transfers of the address, see `process_transfers`.
+    AlertCondition::WhaleTransfer => None,
+    // Raised from token prices, see `process_prices`.
+    AlertCondition::PriceAbove | AlertCondition::PriceBelow | AlertCondition::PriceChange => None,
+  }
+}
+
+fn display_name(data: &ArkhamChainData) -> Option<&str> {
+  data.label_name().or_else(|| data.entity_name())
+}
+
+/// Evaluates the rules of every user watching the changed address, storing
+/// an alert event for each match on a chain the user watches the address on,
+/// and queues their webhook deliveries. Returns the stored events.
+pub async fn process_change(change: &AddressChange) -> Result<Vec<AlertEvent>, Error> {
+  let chains_by_user = watching_users(&change.address).await?;
+  if chains_by_user.is_empty() {
+    return Ok(Vec::new());
+  }
+
+  let users = chains_by_user.keys().cloned().collect::<Vec<ObjectId>>();
+  let rules = AlertRule::find(
+    doc! {
+      "user": { "$in": users },
+      "address": { "$in": [null, &change.address] }
+    },
+    None,
+  )
+  .await?;
+
+  let mut events = Vec::new();
+  for rule in rules {
+    let chains = &chains_by_user[&rule.user];
+    let triggers = evaluate(rule.condition, &change.previous, &change.current);
+
+    for trigger in triggers {
+      if !chains.contains(&trigger.chain) {
+        continue;
+      }
+
+      let mut event = AlertEvent::new(
+        rule.user,
+        rule.id.unwrap(),
+        rule.condition,
+        change.address.clone(),
+        trigger.chain,
+        trigger.message,
+      );
+      // Set upfront, deliveries reference the events inserted below.
+      event.id = Some(ObjectId::new());
+      event.entity = trigger.entity;
+      events.push(event);
+    }
+  }
+
+  debug!(
+    "Raising {} alerts for address {}",
+    events.len(),
+    change.address
+  );
+  AlertEvent::insert_many(events.clone()).await?;
+  webhooks::enqueue(&events).await?;
+
+  Ok(events)
+}
+
+/// Raises an alert for every transfer of a watched address worth at least
+/// the `min_usd` of the whale transfer rules of the users watching it, on a
+/// chain they watch it on, and queues their webhook deliveries. Rules only
+/// alert on the transfers made after their creation, once per transfer.
+/// Returns the stored events.
+pub async fn process_transfers(
+  provider: &dyn IntelligenceProvider,
+  address: &str,
+) -> Result<Vec<AlertEvent>, Error> {
+  let chains_by_user = watching_users(address).await?;
+  if chains_by_user.is_empty() {
+    return Ok(Vec::new());
+  }
+
+  let users = chains_by_user.keys().cloned().collect::<Vec<ObjectId>>();
+  let rules = AlertRule::find(
+    doc! {
+      "user": { "$in": users },
+      "condition": "whale_transfer",
+      "address": { "$in": [null, address] }
+    },
+    None,
+  )
+  .await?;
+
+  // Transfers are only fetched for the addresses someone is alerted on.
+  let since = match rules.iter().map(|rule| rule.created_at).min() {
+    Some(since) => since,
+    None => return Ok(Vec::new()),
+  };
+  let query = TransfersQuery {
+    limit: None,
+    offset: None,
+    time_gte: Some(since.timestamp_millis()),
+    time_lte: None,
+  };
+  let transfers = provider.transfers(address, &query).await?.transfers;
+
+  let rule_ids = rules
+    .iter()
+    .map(|rule| rule.id.unwrap())
+    .collect::<Vec<ObjectId>>();
+  let hashes = transfers
+    .iter()
+    .filter_map(|transfer| transfer.transaction_hash.clone())
+    .collect::<Vec<String>>();
+  let raised = AlertEvent::find(
+    doc! { "rule": { "$in": rule_ids }, "transaction_hash": { "$in": hashes } },
+    None,
+  )
+  .await?
+  .into_iter()
+  .filter_map(|event| Some((event.rule, event.transaction_hash?)))
+  .collect::<HashSet<(ObjectId, String)>>();
+
+  let mut events = Vec::new();
+  for rule in rules {
+    let rule_id = rule.id.unwrap();
+    let chains = &chains_by_user[&rule.user];
+    let min_usd = rule.min_usd.unwrap_or_default();
+
+    for transfer in &transfers {
+      let (hash, chain, usd_value) = match (
+        &transfer.transaction_hash,
+        transfer.chain(),
+        transfer.usd_value(),
+      ) {
+        (Some(hash), Some(chain), Some(usd_value)) => (hash, chain, usd_value),
+        _ => continue,
+      };
+
+      // Providers may not filter the transfers by time.
+      let is_before_rule =
+        made_at(transfer).is_some_and(|made_at| made_at < rule.created_at.timestamp_millis());
+      if usd_value < min_usd
+        || is_before_rule
+        || !chains.contains(chain)
+        || raised.contains(&(rule_id, hash.clone()))
+      {
+        continue;
+      }
+
+      let mut event = AlertEvent::new(
+        rule.user,
+        rule_id,
+        rule.condition,
+        address.to_owned(),
+        chain.to_owned(),
+        transfer_message(transfer, address, usd_value),
+      );
+      // Set upfront, deliveries reference the events inserted below.
+      event.id = Some(ObjectId::new());
+      event.entity = transfer
+        .side(address)
+        .and_then(ArkhamTransferAddress::entity_name)
+        .map(str::to_owned);
+      event.transaction_hash = Some(hash.clone());
+      event.counterparty = transfer.counterparty(address).and_then(counterparty_name);
+      events.push(event);
+    }
+  }
+
+  debug!(
+    "Raising {} whale transfer alerts for address {}",
+    events.len(),
+    address
+  );
+  AlertEvent::insert_many(events.clone()).await?;
+  webhooks::enqueue(&events).await?;
+
+  Ok(events)
+}
+
+/// Evaluates the price rules on the current price of their token, raising an
+/// alert for every rule matching outside of its cooldown, and queues their
+/// webhook deliveries. The prices are then recorded for the next run, so
+/// threshold rules only alert when the price crosses their threshold, and
+/// the first price of a token is a baseline. Returns the stored events.
+pub async fn process_prices(
+  provider: &dyn PriceProvider,
+  settings: &settings::PriceAlerts,
+) -> Result<Vec<AlertEvent>, Error> {
+  let rules = AlertRule::find(
+    doc! { "condition": { "$in": ["price_above", "price_below", "price_change"] } },
+    None,
+  )
+  .await?;
+
+  // Prices are only fetched for the tokens someone is alerted on.
+  let tokens = rules
+    .iter()
+    .filter_map(|rule| rule.token.clone())
+    .collect::<BTreeSet<String>>()
+    .into_iter()
+    .collect::<Vec<String>>();
+  if tokens.is_empty() {
+    return Ok(Vec::new());
+  }
+  let prices = provider.prices(&tokens).await?;
+
+  let mut previous_prices = HashMap::new();
+  for token in prices.keys() {
+    if let Some(sample) = latest_sample(token, None).await? {
+      previous_prices.insert(token.clone(), sample.usd);
+    }
+  }
+
+  let mut events = Vec::new();
+  for rule in rules {
+    let (token, usd) = match rule
+      .token
+      .as_ref()
+      .and_then(|token| Some((token, prices.get(token)?.usd)))
+    {
+      Some(price) => price,
+      None => continue,
+    };
+
+    let message = match rule.condition {
+      AlertCondition::PriceAbove | AlertCondition::PriceBelow => {
+        let previous = previous_prices.get(token).copied();
+        crossing_message(&rule, token, previous, usd)
+      }
+      AlertCondition::PriceChange => {
+        let window = Duration::from_secs(rule.window_secs.unwrap_or_default());
+        match latest_sample(token, Some(date::before(window))).await? {
+          Some(sample) => change_message(&rule, token, sample.usd, usd),
+          None => None,
+        }
+      }
+      _ => None,
+    };
+    let message = match message {
+      Some(message) => message,
+      None => continue,
+    };
+
+    // Claimed with a single update, so concurrent runs raise the alert once.
+    let cooldown = Duration::from_secs(rule.cooldown_secs.unwrap_or(settings.cooldown_secs));
+    let claimed = AlertRule::find_one_and_update(
+      doc! {
+        "_id": rule.id.unwrap(),
+        "$or": [
+          { "last_triggered_at": null },
+          { "last_triggered_at": { "$lte": date::before(cooldown) } }
+        ]
+      },
+      doc! { "$set": { "last_triggered_at": date::now() } },
+    )
+    .await?;
+    if claimed.is_none() {
+      debug!("Price rule {} is cooling down", rule.id.unwrap());
+      continue;
+    }
+
+    let mut event = AlertEvent::price(
+      rule.user,
+      rule.id.unwrap(),
+      rule.condition,
+      token.clone(),
+      message,
+    );
+    // Set upfront, deliveries reference the events inserted below.
+    event.id = Some(ObjectId::new());
+    events.push(event);
+  }
+
+  // Kept long enough to always know the price at the start of the longest
+  // window.
+  let expires_at = date::after(Duration::from_secs(settings.max_window_secs * 2));
+  let samples = prices
+    .into_values()
+    .map(|price| PriceSample::new(price.id, price.usd, expires_at))
+    .collect::<Vec<PriceSample>>();
+  PriceSample::insert_many(samples).await?;
+
+  debug!("Raising {} price alerts", events.len());
+  AlertEvent::insert_many(events.clone()).await?;
+  webhooks::enqueue(&events).await?;
+
+  Ok(events)
+}
+
+/// Sends raised alerts to the live connections and notification channels of
+/// their users.
+pub async fn publish(state: &AppState, events: &[AlertEvent]) -> Result<(), Error> {
+  for event in events {
+    // Sending only fails when nobody is connected.
+    let _ = state.live_events.send(LiveEvent::Alert(event.clone()));
+  }
+
+  state.notifiers.dispatch(events).await
+}
+
+/// Latest recorded price of a token, the latest one recorded before `before`
+/// when set.
+async fn latest_sample(token: &str, before: Option<Date>) -> Result<Option<PriceSample>, Error> {
+  let mut filter = doc! { "token": token };
+  if let Some(before) = before {
+    filter.insert("created_at", doc! { "$lte": before });
+  }
+  let options = FindOneOptions::builder()
+    .sort(doc! { "created_at": -1_i32 })
+    .build();
+
+  PriceSample::find_one(filter, options).await
+}
+
+fn crossing_message(
+  rule: &AlertRule,
+  token: &str,
+  previous: Option<f64>,
+  usd: f64,
+) -> Option<String> {
+  let threshold = rule.price_usd?;
+  let previous = previous?;
+  let crossed = match rule.condition {
+    AlertCondition::PriceAbove => previous <= threshold && usd > threshold,
+    _ => previous >= threshold && usd < threshold,
+  };
+  if !crossed {
+    return None;
+  }
+
+  let direction = if usd > threshold {
+    "rose above"
+  } else {
+    "fell below"
+  };
+  Some(format!(
+    "{} {} {}, now at {}",
+    token,
+    direction,
+    format_usd(threshold),
+    format_usd(usd)
+  ))
+}
+
+fn change_message(rule: &AlertRule, token: &str, start: f64, usd: f64) -> Option<String> {
+  let expected = rule.change_percent?;
+  if start <= 0.0 {
+    return None;
+  }
+
+  let change = (usd - start) / start * 100.0;
+  let matches = if expected < 0.0 {
+    change <= expected
+  } else {
+    change >= expected
+  };
+  if !matches {
+    return None;
+  }
+
+  let direction = if change < 0.0 { "fell" } else { "rose" };
+  Some(format!(
+    "{} {} {:.2}% in {}, now at {}",
+    token,
+    direction,
+    change.abs(),
+    format_window(rule.window_secs.unwrap_or_default()),
+    format_usd(usd)
+  ))
+}
+
+// Cents are not enough for the price of most memecoins.
+fn format_usd(value: f64) -> String {
+  if value.abs() >= 1.0 {
+    format!("${:.2}", value)
+  } else {
+    format!("${:.6}", value)
+  }
+}
+
+fn format_window(secs: u64) -> String {
+  if secs % 86400 == 0 {
+    format!("{}d", secs / 86400)
+  } else if secs % 3600 == 0 {
+    format!("{}h", secs / 3600)
+  } else if secs % 60 == 0 {
+    format!("{}m", secs / 60)
+  } else {
+    format!("{}s", secs)
+  }
+}
+
+/// Chains each user watches an address on, by user.
+async fn watching_users(address: &str) -> Result<HashMap<ObjectId, HashSet<String>>, Error> {
+  let watched = WatchedAddress::find(doc! { "address": address }, None).await?;
+  let mut chains_by_user: HashMap<ObjectId, HashSet<String>> = HashMap::new();
+  for watched in watched {
+    chains_by_user
+      .entry(watched.user)
+      .or_default()
+      .insert(watched.chain);
+  }
+
+  Ok(chains_by_user)
+}
+
+/// Unix time in milliseconds of the block of a transfer.
+fn made_at(transfer: &ArkhamTransfer) -> Option<i64> {
+  let made_at = DateTime::parse_from_rfc3339(transfer.block_timestamp()?).ok()?;
+  Some(made_at.timestamp_millis())
+}
+
+fn transfer_message(transfer: &ArkhamTransfer, address: &str, usd_value: f64) -> String {
+  let token = transfer.token_symbol().unwrap_or("tokens");
+  let counterparty = transfer
+    .counterparty(address)
+    .and_then(counterparty_name)
+    .unwrap_or_else(|| String::from("an unknown address"));
+
+  if transfer.is_sent_by(address) {
+    format!("Sent ${:.2} of {} to {}", usd_value, token, counterparty)
+  } else {
+    format!(
+      "Received ${:.2} of {} from {}",
+      usd_value, token, counterparty
+    )
+  }
+}
+
+// Counterparties without a label are shown by address.
+fn counterparty_name(counterparty: &ArkhamTransferAddress) -> Option<String> {
+  counterparty
+    .display_name()
+    .or_else(|| counterparty.address())
+    .map(str::to_owned)
+}
@@ -0,0 +1,361 @@
+use futures::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tracing::{info, warn};
+use utoipa::ToSchema;
+
+use crate::errors::Error;
+use crate::services::address_intelligence::IntelligenceProvider;
+use crate::services::rpc::{encode_address, uint_to_decimal, RpcClient};
+use crate::settings;
+use crate::utils::serde_helpers::serialize_checksum_address;
+
+// Topics of `Approval(address,address,uint256)`, shared by ERC-20 and ERC-721,
+// and of `ApprovalForAll(address,address,bool)`.
+const APPROVAL_TOPIC: &str = "0x8c5be1e5ebec7d5bd14f71427d1e84f3dd0314c0f7b2291e5b200ac8c7c3b925";
+const APPROVAL_FOR_ALL_TOPIC: &str =
+  "0x17307eab39ab6107e8899845ad3d59bd9653f200f220920489ca2b5937696c31";
+
+// Selectors of `allowance(address,address)`, `isApprovedForAll(address,address)`
+// and `getApproved(uint256)`.
+const ALLOWANCE_SELECTOR: &str = "dd62ed3e";
+const IS_APPROVED_FOR_ALL_SELECTOR: &str = "e985e9c5";
+const GET_APPROVED_SELECTOR: &str = "081812fc";
+
+// Number of approvals checked on chain at the same time.
+const CHECK_CONCURRENCY: usize = 8;
+
+/// Finds the outstanding token approvals of an address from its `Approval`
+/// and `ApprovalForAll` logs, then checks on chain which ones are still in
+/// effect, since allowances are spent and NFTs change hands without logs
+/// from the owner.
+pub struct ApprovalScanner {
+  rpc: Arc<RpcClient>,
+  address_intelligence: Arc<dyn IntelligenceProvider>,
+  risky_entity_types: HashSet<String>,
+  max_approvals: usize,
+}
+
+impl ApprovalScanner {
+  pub fn new(
+    rpc: Arc<RpcClient>,
+    address_intelligence: Arc<dyn IntelligenceProvider>,
+    settings: &settings::Approvals,
+  ) -> Self {
+    Self {
+      rpc,
+      address_intelligence,
+      risky_entity_types: settings.risky_entity_types.iter().cloned().collect(),
+      max_approvals: settings.max_approvals,
+    }
+  }
+
+  /// Outstanding approvals of an address on a chain, most severe first.
+  /// Approvals whose state can't be read, e.g. of burned NFTs, are counted
+  /// as unchecked rather than failing the scan.
+  pub async fn fetch_approvals(&self, chain: &str, owner: &str) -> Result<ApprovalScan, Error> {
+    info!("Querying {} approvals of address: {}", chain, owner);
+    let filter = json!([{
+      "fromBlock": "earliest",
+      "toBlock": "latest",
+      "topics": [[APPROVAL_TOPIC, APPROVAL_FOR_ALL_TOPIC], format!("0x{}", encode_address(owner))]
+    }]);
+    let logs = self
+      .rpc
+      .request::<Vec<ApprovalLog>>(chain, "eth_getLogs", filter)
+      .await?;
+
+    // Logs are oldest first, so later approvals replace the earlier ones of
+    // the same token and spender, or of the same NFT.
+    let mut candidates = HashMap::new();
+    for log in logs {
+      if let Some(candidate) = Candidate::from_log(log) {
+        candidates.insert(candidate.key(), candidate);
+      }
+    }
+    let mut candidates = candidates.into_values().collect::<Vec<Candidate>>();
+    candidates.sort_by(|a, b| a.key().cmp(&b.key()));
+    if candidates.len() > self.max_approvals {
+      warn!(
+        "Address {} has {} approvals on {}, only checking {}",
+        owner,
+        candidates.len(),
+        chain,
+        self.max_approvals
+      );
+      candidates.truncate(self.max_approvals);
+    }
+
+    let checks = candidates
+      .into_iter()
+      .map(|candidate| self.check(chain, owner, candidate));
+    let results = stream::iter(checks)
+      .buffered(CHECK_CONCURRENCY)
+      .collect::<Vec<Result<Option<Approval>, Error>>>()
+      .await;
+
+    let mut approvals = Vec::new();
+    let mut unchecked = 0;
+    for result in results {
+      match result {
+        Ok(Some(approval)) => approvals.push(approval),
+        Ok(None) => {}
+        Err(err) => {
+          warn!("Failed to check an approval of {}: {}", owner, err);
+          unchecked += 1;
+        }
+      }
+    }
+
+    self.assess_spenders(chain, &mut approvals).await;
+    approvals.sort_by(|a, b| {
+      b.severity
+        .cmp(&a.severity)
+        .then_with(|| a.token.cmp(&b.token))
+        .then_with(|| a.spender.cmp(&b.spender))
+    });
+
+    Ok(ApprovalScan {
+      approvals,
+      unchecked,
+    })
+  }
+
+  /// Reads the current state of an approval, `None` when it was spent,
+  /// revoked or cleared by a transfer.
+  async fn check(
+    &self,
+    chain: &str,
+    owner: &str,
+    candidate: Candidate,
+  ) -> Result<Option<Approval>, Error> {
+    let (standard, token_id, allowance, unlimited) = match &candidate.kind {
+      CandidateKind::Allowance => {
+        let data = format!(
+          "0x{}{}{}",
+          ALLOWANCE_SELECTOR,
+          encode_address(owner),
+          encode_address(&candidate.spender)
+        );
+        let result = self.rpc.call(chain, &candidate.token, &data).await?;
+        let allowance = word(&result, 0)?;
+        if allowance.iter().all(|byte| *byte == 0) {
+          return Ok(None);
+        }
+        let unlimited = is_unlimited(allowance);
+        let allowance = uint_to_decimal(allowance);
+        (TokenStandard::Erc20, None, Some(allowance), unlimited)
+      }
+      CandidateKind::Operator => {
+        let data = format!(
+          "0x{}{}{}",
+          IS_APPROVED_FOR_ALL_SELECTOR,
+          encode_address(owner),
+          encode_address(&candidate.spender)
+        );
+        let result = self.rpc.call(chain, &candidate.token, &data).await?;
+        if word(&result, 0)?.iter().all(|byte| *byte == 0) {
+          return Ok(None);
+        }
+        // Operators may transfer every token of the collection.
+        (TokenStandard::Erc721, None, None, true)
+      }
+      CandidateKind::Token(token_id) => {
+        let data = format!("0x{}{}", GET_APPROVED_SELECTOR, token_id);
+        let result = self.rpc.call(chain, &candidate.token, &data).await?;
+        let approved = encode_address(&hex::encode(&word(&result, 0)?[12..]));
+        if approved != encode_address(&candidate.spender) {
+          return Ok(None);
+        }
+        let token_id = uint_to_decimal(&hex::decode(token_id).unwrap_or_default());
+        (TokenStandard::Erc721, Some(token_id), None, false)
+      }
+    };
+
+    Ok(Some(Approval {
+      token: candidate.token,
+      standard,
+      token_id,
+      spender: candidate.spender,
+      spender_name: None,
+      allowance,
+      unlimited,
+      risky: false,
+      severity: if unlimited {
+        Severity::Medium
+      } else {
+        Severity::Low
+      },
+    }))
+  }
+
+  /// Names the spenders known to Arkham and raises the approvals to risky
+  /// spenders to high severity. Spenders that can't be looked up are left
+  /// as they are.
+  async fn assess_spenders(&self, chain: &str, approvals: &mut [Approval]) {
+    let spenders = approvals
+      .iter()
+      .map(|approval| approval.spender.clone())
+      .collect::<HashSet<String>>();
+    let lookups = spenders.into_iter().map(|spender| async move {
+      let data = self.address_intelligence.lookup_address(&spender).await;
+      (spender, data)
+    });
+
+    let mut spenders = HashMap::new();
+    for (spender, data) in stream::iter(lookups)
+      .buffered(CHECK_CONCURRENCY)
+      .collect::<Vec<_>>()
+      .await
+    {
+      match data {
+        Ok(data) => {
+          spenders.insert(spender, data);
+        }
+        Err(err) => warn!("Failed to look up spender {}: {}", spender, err),
+      }
+    }
+
+    for approval in approvals {
+      let data = match spenders
+        .get(&approval.spender)
+        .and_then(|data| data.chain(chain))
+      {
+        Some(data) => data,
+        None => continue,
+      };
+      approval.spender_name = data
+        .entity_name()
+        .or_else(|| data.label_name())
+        .map(str::to_owned);
+      approval.risky = data.entity_type().map_or(false, |entity_type| {
+        self.risky_entity_types.contains(entity_type)
+      });
+      if approval.risky {
+        approval.severity = Severity::High;
+      }
+    }
+  }
+}
+
+#[derive(Debug)]
+pub struct ApprovalScan {
+  pub approvals: Vec<Approval>,
+  // Number of approvals found in the logs whose state couldn't be read.
+  pub unchecked: usize,
+}
+
+/// Approval still in effect.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Approval {
+  #[serde(serialize_with = "serialize_checksum_address")]
+  pub token: String,
+  pub standard: TokenStandard,
+  // Set for the approval of a single NFT.
+  pub token_id: Option<String>,
+  #[serde(serialize_with = "serialize_checksum_address")]
+  pub spender: String,
+  // Arkham entity or label of the spender.
+  pub spender_name: Option<String>,
+  // Remaining ERC-20 allowance, in the smallest unit of the token.
+  pub allowance: Option<String>,
+  // Whether the spender may take every token, ERC-20 allowances of at least
+  // `type(uint96).max` or NFT operators.
+  pub unlimited: bool,
+  // Whether Arkham labels the spender with a risky entity type.
+  pub risky: bool,
+  pub severity: Severity,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub enum TokenStandard {
+  #[serde(rename = "ERC20")]
+  Erc20,
+  #[serde(rename = "ERC721")]
+  Erc721,
+}
+
+/// High for risky spenders, medium for unlimited approvals, low otherwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+  Low,
+  Medium,
+  High,
+}
+
+#[derive(Deserialize)]
+struct ApprovalLog {
+  address: String,
+  topics: Vec<String>,
+}
+
+/// Approval found in the logs, which may no longer be in effect.
+struct Candidate {
+  token: String,
+  spender: String,
+  kind: CandidateKind,
+}
+
+enum CandidateKind {
+  // ERC-20 allowance.
+  Allowance,
+  // ERC-721 operator of every token.
+  Operator,
+  // ERC-721 approval of a single token, with its ID as a hex word.
+  Token(String),
+}
+
+impl Candidate {
+  fn from_log(log: ApprovalLog) -> Option<Self> {
+    let topic_address = |topic: &String| {
+      let topic = topic.trim_start_matches("0x");
+      topic
+        .get(24..)
+        .filter(|_| topic.len() == 64)
+        .map(|address| format!("0x{}", address.to_lowercase()))
+    };
+
+    let spender = topic_address(log.topics.get(2)?)?;
+    let kind = match (log.topics[0].as_str(), log.topics.len()) {
+      (APPROVAL_TOPIC, 3) => CandidateKind::Allowance,
+      // ERC-721 indexes the token ID, ERC-20 logs the amount as data.
+      (APPROVAL_TOPIC, 4) => {
+        CandidateKind::Token(log.topics[3].trim_start_matches("0x").to_lowercase())
+      }
+      (APPROVAL_FOR_ALL_TOPIC, 3) => CandidateKind::Operator,
+      _ => return None,
+    };
+
+    Some(Self {
+      token: log.address.to_lowercase(),
+      spender,
+      kind,
+    })
+  }
+
+  /// Approvals of the same key replace each other.
+  fn key(&self) -> (String, String) {
+    match &self.kind {
+      CandidateKind::Allowance => (self.token.clone(), format!("allowance:{}", self.spender)),
+      CandidateKind::Operator => (self.token.clone(), format!("operator:{}", self.spender)),
+      CandidateKind::Token(token_id) => (self.token.clone(), format!("token:{}", token_id)),
+    }
+  }
+}
+
+/// 32 bytes word of an ABI encoded result.
+fn word(data: &[u8], index: usize) -> Result<&[u8], Error> {
+  data
+    .get(index * 32..(index + 1) * 32)
+    .ok_or_else(|| Error::UpstreamInvalidResponse("expected an ABI encoded word".to_owned()))
+}
+
+/// Whether an allowance is at least `type(uint96).max`, the largest some
+/// tokens store. Wallets approve the maximum of the type for unlimited
+/// approvals.
+fn is_unlimited(allowance: &[u8]) -> bool {
+  allowance[..20].iter().any(|byte| *byte != 0) || allowance[20..].iter().all(|byte| *byte == 0xff)
+}
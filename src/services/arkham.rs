@@ -0,0 +1,389 @@
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::time::sleep;
+use tracing::{debug, debug_span, error, field, info, warn, Instrument};
+
+use crate::errors::Error;
+use crate::routes::arkham::{
+  parse_arkham_portfolio, parse_arkham_response, ArkhamEntityDetail, ArkhamPortfolio,
+  ArkhamResponse, ArkhamTransfers, TransfersQuery,
+};
+use crate::settings;
+use crate::utils::circuit_breaker::CircuitBreaker;
+use crate::utils::metrics;
+use crate::utils::retry::{retry, RetryPolicy};
+use crate::utils::telemetry;
+use crate::utils::usage;
+
+// Number of characters of an invalid upstream body included in the logs.
+const BODY_SNIPPET_LENGTH: usize = 200;
+
+/// Client for the Arkham API. It is cheap to clone, clones share the same
+/// connection pool.
+#[derive(Clone)]
+pub struct ArkhamClient {
+  http_client: reqwest::Client,
+  url: String,
+  api_key: String,
+  retry_policy: RetryPolicy,
+  governor: Arc<Governor>,
+  circuit_breaker: Arc<CircuitBreaker>,
+}
+
+impl ArkhamClient {
+  pub fn new(http_client: reqwest::Client, settings: &settings::Arkham) -> Self {
+    Self {
+      http_client,
+      url: settings.url.clone(),
+      api_key: settings.api_key.clone(),
+      retry_policy: RetryPolicy {
+        max_attempts: settings.retry_attempts,
+        base_delay: Duration::from_millis(settings.retry_base_delay_ms),
+        max_delay: Duration::from_millis(settings.retry_max_delay_ms),
+      },
+      governor: Arc::new(Governor::new(
+        settings.requests_per_sec,
+        settings.requests_burst,
+        Duration::from_millis(settings.max_queue_wait_ms),
+      )),
+      circuit_breaker: Arc::new(CircuitBreaker::new(
+        "Arkham",
+        settings.circuit_failure_threshold,
+        Duration::from_secs(settings.circuit_reset_secs),
+      )),
+    }
+  }
+
+  pub async fn fetch_address(&self, address: &str) -> Result<ArkhamResponse, Error> {
+    info!("Querying arkham with address: {}", address);
+    let path = format!("/intelligence/address/{}/all", address);
+    let res = self.send_request("address", self.get(&path)).await?;
+
+    let arkham_data = parse_arkham_response(read_json::<Value>(res).await?)?;
+    info!("Successfully retrieved Arkham data");
+    Ok(arkham_data)
+  }
+
+  pub async fn fetch_entity(&self, entity_id: &str) -> Result<ArkhamEntityDetail, Error> {
+    info!("Querying arkham with entity: {}", entity_id);
+    let path = format!("/intelligence/entity/{}", entity_id);
+    let res = self.send_request("entity", self.get(&path)).await?;
+
+    let entity = read_json::<ArkhamEntityDetail>(res).await?;
+    info!("Successfully retrieved Arkham entity");
+    Ok(entity)
+  }
+
+  /// Fetches the tokens held by an address on every chain, with their USD
+  /// value.
+  pub async fn fetch_portfolio(&self, address: &str) -> Result<ArkhamPortfolio, Error> {
+    info!("Querying arkham portfolio with address: {}", address);
+    let path = format!("/balances/address/{}", address);
+    let res = self.send_request("portfolio", self.get(&path)).await?;
+
+    let portfolio = parse_arkham_portfolio(read_json::<Value>(res).await?)?;
+    info!("Successfully retrieved Arkham portfolio");
+    Ok(portfolio)
+  }
+
+  /// Fetches the transfers sent or received by an address. Pagination and
+  /// time range parameters are passed through to Arkham.
+  pub async fn fetch_transfers(
+    &self,
+    address: &str,
+    query: &TransfersQuery,
+  ) -> Result<ArkhamTransfers, Error> {
+    info!("Querying arkham transfers with address: {}", address);
+    let request = self.transfers_request(address, query);
+    let res = self.send_request("transfers", request).await?;
+
+    let transfers = read_json::<ArkhamTransfers>(res).await?;
+    info!("Successfully retrieved Arkham transfers");
+    Ok(transfers)
+  }
+
+  /// Same request as `fetch_transfers`, returning the response unread so its
+  /// body can be streamed to the client, see `utils::proxy`.
+  pub async fn stream_transfers(
+    &self,
+    address: &str,
+    query: &TransfersQuery,
+  ) -> Result<reqwest::Response, Error> {
+    info!("Streaming arkham transfers with address: {}", address);
+    let request = self.transfers_request(address, query);
+    let res = self.send_request("transfers", request).await?;
+
+    // The body isn't read, only the content type can be checked, see
+    // `read_json`.
+    let content_type = res
+      .headers()
+      .get(reqwest::header::CONTENT_TYPE)
+      .and_then(|value| value.to_str().ok())
+      .unwrap_or_default();
+    if !content_type.contains("json") {
+      error!("Received a non JSON Arkham response ({})", content_type);
+      return Err(Error::UpstreamInvalidResponse(format!(
+        "expected JSON, received {:?}",
+        content_type
+      )));
+    }
+
+    Ok(res)
+  }
+
+  fn transfers_request(&self, address: &str, query: &TransfersQuery) -> reqwest::RequestBuilder {
+    self
+      .get("/transfers")
+      .query(&[("base", address)])
+      .query(query)
+  }
+
+  /// Checks the Arkham API is reachable, sending a single request.
+  pub async fn check(&self) -> Result<(), Error> {
+    let res = self.execute("health", self.get("/health").build()?).await?;
+
+    let status = res.status();
+    if !status.is_success() {
+      return Err(Error::upstream_unavailable("Arkham", Some(status.as_u16())));
+    }
+
+    Ok(())
+  }
+
+  /// Sends a request once in its own span, recording it in the metrics under
+  /// the endpoint.
+  async fn execute(
+    &self,
+    endpoint: &'static str,
+    mut request: reqwest::Request,
+  ) -> Result<reqwest::Response, Error> {
+    self.governor.acquire().await?;
+
+    // Upstream spans are children of the request one, see `telemetry`.
+    let span = debug_span!("arkham_request", endpoint, status = field::Empty);
+    let headers = span.in_scope(telemetry::trace_headers);
+    request.headers_mut().extend(headers);
+
+    let started_at = Instant::now();
+    let result = self
+      .http_client
+      .execute(request)
+      .instrument(span.clone())
+      .await;
+    let status = result.as_ref().ok().map(|res| res.status().as_u16());
+    if let Some(status) = status {
+      span.record("status", status);
+    }
+    metrics::record_arkham_request(endpoint, status, started_at.elapsed());
+
+    result.map_err(|err| Error::upstream_request("Arkham", err))
+  }
+
+  /// Builds an authenticated GET request to the Arkham API.
+  fn get(&self, path: &str) -> reqwest::RequestBuilder {
+    self
+      .http_client
+      .get(format!("{}{}", self.url, path))
+      .header("API-Key", &self.api_key)
+  }
+
+  /// Sends a request to the Arkham API, turning unsuccessful responses into
+  /// errors. GET requests are retried on rate limiting, server errors and
+  /// connection failures, other methods are sent once since they may not be
+  /// idempotent. Requests that still fail count towards opening the circuit,
+  /// which then rejects requests without sending them.
+  async fn send_request(
+    &self,
+    endpoint: &'static str,
+    request: reqwest::RequestBuilder,
+  ) -> Result<reqwest::Response, Error> {
+    let request = request.build()?;
+    if !self.circuit_breaker.allow() {
+      debug!("Arkham circuit is open, returning 503 status code");
+      return Err(Error::upstream_unavailable("Arkham", None));
+    }
+
+    // Charged once per call, retries are free.
+    if let Err(err) = usage::charge_upstream_credit() {
+      self.circuit_breaker.record_cancelled();
+      return Err(err);
+    }
+
+    let result = if request.method() == reqwest::Method::GET {
+      retry(
+        "arkham_request",
+        &self.retry_policy,
+        // GET requests have no body, so cloning them never fails.
+        || self.execute(endpoint, request.try_clone().unwrap()),
+        is_retryable,
+      )
+      .await
+    } else {
+      self.execute(endpoint, request).await
+    };
+
+    let res = match result {
+      Ok(res) => {
+        let status = res.status();
+        if status == reqwest::StatusCode::TOO_MANY_REQUESTS || status.is_server_error() {
+          self.circuit_breaker.record_failure();
+        } else {
+          self.circuit_breaker.record_success();
+        }
+        res
+      }
+      Err(err @ (Error::ReqwestError(_) | Error::UpstreamTimeout { .. })) => {
+        self.circuit_breaker.record_failure();
+        return Err(err);
+      }
+      // Shed by the governor before reaching Arkham.
+      Err(err) => {
+        self.circuit_breaker.record_cancelled();
+        return Err(err);
+      }
+    };
+
+    let status = res.status();
+    debug!("Received response with status: {}", status);
+
+    if status.is_success() {
+      return Ok(res);
+    }
+
+    if status == reqwest::StatusCode::NOT_FOUND {
+      debug!("Arkham resource not found, returning 404 status code");
+      return Err(Error::not_found());
+    }
+
+    let body = res
+      .text()
+      .await
+      .unwrap_or_else(|_| String::from("Could not retrieve response body"));
+    // The body may be anything (e.g. an HTML error page), so it is only
+    // logged.
+    error!("Received a {} error: {}", status, body);
+    Err(Error::upstream_unavailable("Arkham", Some(status.as_u16())))
+  }
+}
+
+fn is_retryable(result: &Result<reqwest::Response, Error>) -> bool {
+  match result {
+    Ok(res) => {
+      let status = res.status();
+      status == reqwest::StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+    }
+    Err(Error::ReqwestError(err)) => err.is_connect(),
+    Err(Error::UpstreamTimeout { .. }) => true,
+    // Requests shed by the governor would be shed again.
+    Err(_) => false,
+  }
+}
+
+/// Throttles the requests made with the Arkham API key so they stay under
+/// the rate limit of our plan, whichever route they come from. Requests over
+/// the rate are queued, and shed when they would wait longer than
+/// `max_wait`.
+pub struct Governor {
+  requests_per_sec: f64,
+  burst: f64,
+  max_wait: Duration,
+  state: Mutex<GovernorState>,
+}
+
+struct GovernorState {
+  // Goes below zero while requests are queued.
+  tokens: f64,
+  updated_at: Instant,
+}
+
+impl Governor {
+  pub fn new(requests_per_sec: f64, burst: u32, max_wait: Duration) -> Self {
+    let burst = f64::from(burst.max(1));
+    Self {
+      requests_per_sec,
+      burst,
+      max_wait,
+      state: Mutex::new(GovernorState {
+        tokens: burst,
+        updated_at: Instant::now(),
+      }),
+    }
+  }
+
+  /// Reserves a request slot, returning how long the request has to wait
+  /// for it, or an error when the wait would be too long.
+  pub fn reserve(&self) -> Result<Duration, Error> {
+    let mut state = self.state.lock().unwrap();
+    let now = Instant::now();
+
+    let elapsed = now.duration_since(state.updated_at).as_secs_f64();
+    state.tokens = (state.tokens + elapsed * self.requests_per_sec).min(self.burst);
+    state.updated_at = now;
+
+    let tokens = state.tokens - 1.0;
+    let wait = if tokens >= 0.0 {
+      Duration::ZERO
+    } else {
+      Duration::try_from_secs_f64(-tokens / self.requests_per_sec).unwrap_or(Duration::MAX)
+    };
+
+    if wait > self.max_wait {
+      warn!("Arkham request queue is full, shedding request");
+      return Err(Error::too_many_requests());
+    }
+
+    state.tokens = tokens;
+    Ok(wait)
+  }
+
+  /// Waits until a request can be sent.
+  pub async fn acquire(&self) -> Result<(), Error> {
+    let wait = self.reserve()?;
+    if !wait.is_zero() {
+      debug!("Queueing Arkham request for {:?}", wait);
+      sleep(wait).await;
+    }
+
+    Ok(())
+  }
+}
+
+/// Reads a successful Arkham response as JSON. Proxies in front of Arkham
+/// sometimes answer with an HTML page and a 2xx status, so the content type
+/// and body are checked instead of trusting the status code.
+async fn read_json<T: DeserializeOwned>(res: reqwest::Response) -> Result<T, Error> {
+  let content_type = res
+    .headers()
+    .get(reqwest::header::CONTENT_TYPE)
+    .and_then(|value| value.to_str().ok())
+    .unwrap_or_default()
+    .to_owned();
+  let body = res.text().await?;
+
+  if !content_type.contains("json") {
+    error!(
+      "Received a non JSON Arkham response ({}): {}",
+      content_type,
+      body_snippet(&body)
+    );
+    return Err(Error::UpstreamInvalidResponse(format!(
+      "expected JSON, received {:?}",
+      content_type
+    )));
+  }
+
+  serde_json::from_str::<T>(&body).map_err(|err| {
+    error!(
+      "Failed to parse Arkham response: {}. Body: {}",
+      err,
+      body_snippet(&body)
+    );
+    Error::UpstreamInvalidResponse(err.to_string())
+  })
+}
+
+fn body_snippet(body: &str) -> String {
+  body.chars().take(BODY_SNIPPET_LENGTH).collect()
+}
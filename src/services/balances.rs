@@ -0,0 +1,405 @@
+use futures::future;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tracing::{error, info, warn};
+use utoipa::ToSchema;
+
+use crate::errors::{Error, ErrorResponse};
+use crate::services::bitcoin::BitcoinClient;
+use crate::settings;
+use crate::utils::address::AddressChain;
+use crate::utils::serde_helpers::{
+  serialize_checksum_address, serialize_optional_checksum_address,
+};
+use crate::utils::telemetry;
+
+// EVM chains, named like the Arkham chains, with their Covalent chain name.
+const CHAINS: [(&str, &str); 6] = [
+  ("ethereum", "eth-mainnet"),
+  ("bsc", "bsc-mainnet"),
+  ("polygon", "matic-mainnet"),
+  ("arbitrum_one", "arbitrum-mainnet"),
+  ("avalanche", "avalanche-mainnet"),
+  ("optimism", "optimism-mainnet"),
+];
+
+// Balances of SOL are in lamports.
+const SOL_DECIMALS: u32 = 9;
+
+// Balances of BTC are in satoshis.
+const BTC_DECIMALS: u32 = 8;
+
+// Program owning the SPL token accounts of an address.
+const SPL_TOKEN_PROGRAM: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+
+/// Client for the Covalent balances API, which returns the native and ERC-20
+/// balances of an address on a chain along with their USD value. Solana
+/// balances come from the Helius JSON-RPC API instead, without USD values,
+/// and Bitcoin balances from Blockchair.
+pub struct BalanceClient {
+  http_client: reqwest::Client,
+  url: String,
+  api_key: String,
+  solana_url: String,
+  solana_api_key: String,
+  bitcoin: Arc<BitcoinClient>,
+}
+
+impl BalanceClient {
+  pub fn new(
+    http_client: reqwest::Client,
+    settings: &settings::Balances,
+    bitcoin: Arc<BitcoinClient>,
+  ) -> Self {
+    Self {
+      http_client,
+      url: settings.url.clone(),
+      api_key: settings.api_key.clone(),
+      solana_url: settings.solana_url.clone(),
+      solana_api_key: settings.solana_api_key.clone(),
+      bitcoin,
+    }
+  }
+
+  /// Fetches the balances of an address on every chain of its kind at the
+  /// same time. Chains that fail are reported with their error and left out
+  /// of the total, so one unavailable chain doesn't hide the others.
+  pub async fn fetch_balances(&self, address: &str, chain: AddressChain) -> AddressBalances {
+    let chains = match chain {
+      AddressChain::Evm => self.fetch_evm_balances(address).await,
+      AddressChain::Solana => vec![self.fetch_solana_balances(address).await],
+      AddressChain::Bitcoin => vec![self.fetch_bitcoin_balances(address).await],
+    };
+
+    AddressBalances {
+      address: address.to_owned(),
+      usd_value: chains.iter().map(|chain| chain.usd_value).sum(),
+      chains,
+    }
+  }
+
+  async fn fetch_evm_balances(&self, address: &str) -> Vec<ChainBalances> {
+    let lookups = CHAINS.iter().map(|(chain, covalent_chain)| async move {
+      match self.fetch_chain(covalent_chain, address).await {
+        Ok(tokens) => ChainBalances::new(chain, tokens),
+        Err(err) => {
+          warn!(
+            "Failed to fetch the {} balances of {}: {}",
+            chain, address, err
+          );
+          ChainBalances::failed(chain, &err)
+        }
+      }
+    });
+    future::join_all(lookups).await
+  }
+
+  async fn fetch_solana_balances(&self, address: &str) -> ChainBalances {
+    let tokens = future::try_join(
+      self.solana_rpc::<SolanaBalance>("getBalance", json!([address])),
+      self.solana_rpc::<SolanaTokenAccounts>(
+        "getTokenAccountsByOwner",
+        json!([
+          address,
+          { "programId": SPL_TOKEN_PROGRAM },
+          { "encoding": "jsonParsed" }
+        ]),
+      ),
+    )
+    .await
+    .map(|(balance, accounts)| {
+      let native = TokenBalance {
+        symbol: Some("SOL".to_owned()),
+        name: Some("Solana".to_owned()),
+        contract_address: None,
+        decimals: Some(SOL_DECIMALS),
+        balance: balance.value.to_string(),
+        usd_value: None,
+      };
+      let tokens = accounts
+        .value
+        .into_iter()
+        .map(|account| TokenBalance::from(account.account.data.parsed.info));
+
+      std::iter::once(native).chain(tokens).collect::<Vec<_>>()
+    });
+
+    match tokens {
+      Ok(tokens) => ChainBalances::new("solana", tokens),
+      Err(err) => {
+        warn!(
+          "Failed to fetch the solana balances of {}: {}",
+          address, err
+        );
+        ChainBalances::failed("solana", &err)
+      }
+    }
+  }
+
+  /// Balance of a Bitcoin address, or of every address of an extended
+  /// public key.
+  async fn fetch_bitcoin_balances(&self, address: &str) -> ChainBalances {
+    match self.bitcoin.fetch_wallet(address, 0, 0).await {
+      Ok(wallet) => ChainBalances::new(
+        "bitcoin",
+        vec![TokenBalance {
+          symbol: Some("BTC".to_owned()),
+          name: Some("Bitcoin".to_owned()),
+          contract_address: None,
+          decimals: Some(BTC_DECIMALS),
+          balance: wallet.balance.to_string(),
+          usd_value: wallet.usd_value,
+        }],
+      ),
+      Err(err) => {
+        warn!(
+          "Failed to fetch the bitcoin balances of {}: {}",
+          address, err
+        );
+        ChainBalances::failed("bitcoin", &err)
+      }
+    }
+  }
+
+  async fn solana_rpc<T: DeserializeOwned>(&self, method: &str, params: Value) -> Result<T, Error> {
+    info!("Calling Helius {}", method);
+    let res = self
+      .http_client
+      .post(self.solana_url.as_str())
+      .query(&[("api-key", &self.solana_api_key)])
+      .headers(telemetry::trace_headers())
+      .json(&json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params }))
+      .send()
+      .await
+      .map_err(|err| Error::upstream_request("Helius", err))?;
+
+    let status = res.status();
+    if !status.is_success() {
+      error!("Received a {} error from Helius", status);
+      return Err(Error::upstream_unavailable("Helius", Some(status.as_u16())));
+    }
+
+    let res = res.json::<SolanaRpcResponse<T>>().await.map_err(|err| {
+      error!("Failed to parse Helius response: {}", err);
+      Error::UpstreamInvalidResponse(err.to_string())
+    })?;
+
+    match (res.result, res.error) {
+      (Some(result), None) => Ok(result),
+      (_, error) => {
+        error!("Helius {} request failed: {:?}", method, error);
+        Err(Error::upstream_unavailable("Helius", None))
+      }
+    }
+  }
+
+  async fn fetch_chain(
+    &self,
+    covalent_chain: &str,
+    address: &str,
+  ) -> Result<Vec<TokenBalance>, Error> {
+    info!(
+      "Querying {} balances of address: {}",
+      covalent_chain, address
+    );
+    let res = self
+      .http_client
+      .get(format!(
+        "{}/{}/address/{}/balances_v2/",
+        self.url, covalent_chain, address
+      ))
+      .headers(telemetry::trace_headers())
+      .bearer_auth(&self.api_key)
+      .send()
+      .await
+      .map_err(|err| Error::upstream_request("Covalent", err))?;
+
+    let status = res.status();
+    if !status.is_success() {
+      error!("Received a {} error from Covalent", status);
+      return Err(Error::upstream_unavailable(
+        "Covalent",
+        Some(status.as_u16()),
+      ));
+    }
+
+    let res = res.json::<CovalentResponse>().await.map_err(|err| {
+      error!("Failed to parse Covalent response: {}", err);
+      Error::UpstreamInvalidResponse(err.to_string())
+    })?;
+
+    let items = match res.data {
+      Some(data) if !res.error => data.items,
+      _ => {
+        error!("Covalent request failed: {:?}", res.error_message);
+        return Err(Error::upstream_unavailable("Covalent", None));
+      }
+    };
+
+    Ok(items.into_iter().map(TokenBalance::from).collect())
+  }
+}
+
+/// Balances of an address across chains.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct AddressBalances {
+  #[serde(serialize_with = "serialize_checksum_address")]
+  pub address: String,
+  // Total USD value of the tokens with a known price, on every chain.
+  pub usd_value: f64,
+  pub chains: Vec<ChainBalances>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ChainBalances {
+  pub chain: String,
+  pub usd_value: f64,
+  pub tokens: Vec<TokenBalance>,
+  // Set when the balances of the chain couldn't be fetched.
+  pub error: Option<ErrorResponse>,
+}
+
+impl ChainBalances {
+  fn new(chain: &str, tokens: Vec<TokenBalance>) -> Self {
+    Self {
+      chain: chain.to_owned(),
+      usd_value: tokens.iter().filter_map(|token| token.usd_value).sum(),
+      tokens,
+      error: None,
+    }
+  }
+
+  fn failed(chain: &str, err: &Error) -> Self {
+    Self {
+      chain: chain.to_owned(),
+      usd_value: 0.0,
+      tokens: Vec::new(),
+      error: Some(ErrorResponse::from(err)),
+    }
+  }
+}
+
+/// Native, ERC-20 or SPL token balance. Balances are in the smallest unit of
+/// the token, as decimal strings since they overflow 64 bits.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct TokenBalance {
+  pub symbol: Option<String>,
+  pub name: Option<String>,
+  // `None` for the native token of the chain, the mint of SPL tokens.
+  #[serde(serialize_with = "serialize_optional_checksum_address")]
+  pub contract_address: Option<String>,
+  pub decimals: Option<u32>,
+  pub balance: String,
+  // `None` when the token has no known price.
+  pub usd_value: Option<f64>,
+}
+
+impl From<CovalentItem> for TokenBalance {
+  fn from(item: CovalentItem) -> Self {
+    let contract_address = if item.native_token {
+      None
+    } else {
+      item.contract_address.map(|address| address.to_lowercase())
+    };
+
+    Self {
+      symbol: item.contract_ticker_symbol,
+      name: item.contract_name,
+      contract_address,
+      decimals: item.contract_decimals,
+      balance: item.balance.unwrap_or_else(|| "0".to_owned()),
+      usd_value: item.quote,
+    }
+  }
+}
+
+#[derive(Deserialize)]
+struct CovalentResponse {
+  data: Option<CovalentData>,
+  #[serde(default)]
+  error: bool,
+  error_message: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct CovalentData {
+  #[serde(default)]
+  items: Vec<CovalentItem>,
+}
+
+#[derive(Deserialize)]
+struct CovalentItem {
+  contract_ticker_symbol: Option<String>,
+  contract_name: Option<String>,
+  contract_address: Option<String>,
+  contract_decimals: Option<u32>,
+  #[serde(default)]
+  native_token: bool,
+  balance: Option<String>,
+  quote: Option<f64>,
+}
+
+impl From<SolanaTokenInfo> for TokenBalance {
+  fn from(info: SolanaTokenInfo) -> Self {
+    // Token accounts carry no token metadata.
+    Self {
+      symbol: None,
+      name: None,
+      contract_address: Some(info.mint),
+      decimals: Some(info.token_amount.decimals),
+      balance: info.token_amount.amount,
+      usd_value: None,
+    }
+  }
+}
+
+#[derive(Deserialize)]
+struct SolanaRpcResponse<T> {
+  result: Option<T>,
+  error: Option<Value>,
+}
+
+#[derive(Deserialize)]
+struct SolanaBalance {
+  value: u64,
+}
+
+#[derive(Deserialize)]
+struct SolanaTokenAccounts {
+  value: Vec<SolanaTokenAccount>,
+}
+
+#[derive(Deserialize)]
+struct SolanaTokenAccount {
+  account: SolanaAccount,
+}
+
+#[derive(Deserialize)]
+struct SolanaAccount {
+  data: SolanaAccountData,
+}
+
+#[derive(Deserialize)]
+struct SolanaAccountData {
+  parsed: SolanaParsedAccount,
+}
+
+#[derive(Deserialize)]
+struct SolanaParsedAccount {
+  info: SolanaTokenInfo,
+}
+
+#[derive(Deserialize)]
+struct SolanaTokenInfo {
+  mint: String,
+  #[serde(rename = "tokenAmount")]
+  token_amount: SolanaTokenAmount,
+}
+
+#[derive(Deserialize)]
+struct SolanaTokenAmount {
+  amount: String,
+  decimals: u32,
+}
@@ -0,0 +1,167 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tracing::{error, info};
+use utoipa::ToSchema;
+
+use crate::errors::Error;
+use crate::settings;
+use crate::utils::address::is_bitcoin_xpub;
+use crate::utils::telemetry;
+
+/// Client for the Blockchair Bitcoin API, which serves the balance and the
+/// transactions of an address, or of every address derived from an
+/// extended public key.
+pub struct BitcoinClient {
+  http_client: reqwest::Client,
+  url: String,
+  api_key: String,
+}
+
+impl BitcoinClient {
+  pub fn new(http_client: reqwest::Client, settings: &settings::Bitcoin) -> Self {
+    Self {
+      http_client,
+      url: settings.url.clone(),
+      api_key: settings.api_key.clone(),
+    }
+  }
+
+  /// Fetches the balance of an address or extended public key, along with
+  /// `limit` of its transactions after the first `offset`, newest first.
+  pub async fn fetch_wallet(
+    &self,
+    address: &str,
+    limit: u64,
+    offset: u64,
+  ) -> Result<BitcoinWallet, Error> {
+    let dashboard = if is_bitcoin_xpub(address) {
+      "xpub"
+    } else {
+      "address"
+    };
+
+    info!("Querying Bitcoin {}: {}", dashboard, address);
+    let mut query = vec![
+      ("transaction_details", "true".to_owned()),
+      // Transactions then unspent outputs, which are never needed.
+      ("limit", format!("{},0", limit)),
+      ("offset", format!("{},0", offset)),
+    ];
+    if !self.api_key.is_empty() {
+      query.push(("key", self.api_key.clone()));
+    }
+
+    let res = self
+      .http_client
+      .get(format!("{}/dashboards/{}/{}", self.url, dashboard, address))
+      .headers(telemetry::trace_headers())
+      .query(&query)
+      .send()
+      .await
+      .map_err(|err| Error::upstream_request("Blockchair", err))?;
+
+    let status = res.status();
+    if !status.is_success() {
+      error!("Received a {} error from Blockchair", status);
+      return Err(Error::upstream_unavailable(
+        "Blockchair",
+        Some(status.as_u16()),
+      ));
+    }
+
+    let res = res.json::<BlockchairResponse>().await.map_err(|err| {
+      error!("Failed to parse Blockchair response: {}", err);
+      Error::UpstreamInvalidResponse(err.to_string())
+    })?;
+
+    // Keyed by the address as requested, which is the only one.
+    let dashboard = res.data.into_values().next().ok_or_else(|| {
+      error!("Blockchair response has no data for {}", address);
+      Error::UpstreamInvalidResponse("missing address data".to_owned())
+    })?;
+
+    BitcoinWallet::try_from(dashboard)
+  }
+}
+
+/// Balance and transactions of a Bitcoin address or extended public key.
+/// Amounts are in satoshis.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BitcoinWallet {
+  pub balance: u64,
+  // `None` when Blockchair has no price.
+  pub usd_value: Option<f64>,
+  pub transaction_count: u64,
+  pub transactions: Vec<BitcoinTransaction>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BitcoinTransaction {
+  pub hash: String,
+  // `None` while the transaction is unconfirmed.
+  pub block_number: Option<u64>,
+  // RFC 3339 date of the block, or when the transaction was first seen.
+  pub timestamp: String,
+  // Negative when the wallet spent more than it received.
+  pub balance_change: i64,
+}
+
+#[derive(Deserialize)]
+struct BlockchairResponse {
+  data: HashMap<String, BlockchairDashboard>,
+}
+
+#[derive(Deserialize)]
+struct BlockchairDashboard {
+  #[serde(alias = "xpub")]
+  address: BlockchairSummary,
+  #[serde(default)]
+  transactions: Vec<BlockchairTransaction>,
+}
+
+#[derive(Deserialize)]
+struct BlockchairSummary {
+  balance: u64,
+  balance_usd: Option<f64>,
+  transaction_count: u64,
+}
+
+#[derive(Deserialize)]
+struct BlockchairTransaction {
+  // `-1` for unconfirmed transactions.
+  block_id: i64,
+  hash: String,
+  // UTC, e.g. `2009-01-03 18:15:05`.
+  time: String,
+  balance_change: i64,
+}
+
+impl TryFrom<BlockchairDashboard> for BitcoinWallet {
+  type Error = Error;
+
+  fn try_from(dashboard: BlockchairDashboard) -> Result<Self, Error> {
+    let transactions = dashboard
+      .transactions
+      .into_iter()
+      .map(|transaction| {
+        let timestamp = NaiveDateTime::parse_from_str(&transaction.time, "%Y-%m-%d %H:%M:%S")
+          .map_err(|_| Error::UpstreamInvalidResponse("invalid time".to_owned()))?;
+
+        Ok(BitcoinTransaction {
+          hash: transaction.hash,
+          block_number: u64::try_from(transaction.block_id).ok(),
+          timestamp: timestamp.and_utc().to_rfc3339(),
+          balance_change: transaction.balance_change,
+        })
+      })
+      .collect::<Result<Vec<_>, Error>>()?;
+
+    Ok(Self {
+      balance: dashboard.address.balance,
+      usd_value: dashboard.address.balance_usd,
+      transaction_count: dashboard.address.transaction_count,
+      transactions,
+    })
+  }
+}
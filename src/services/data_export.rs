@@ -0,0 +1,189 @@
+use bytes::Bytes;
+use futures::{future, stream};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::info;
+use wither::bson::{self, doc, oid::ObjectId, Bson, Document};
+use wither::Model as WitherModel;
+
+use crate::errors::Error;
+use crate::models::alert_event::AlertEvent;
+use crate::models::alert_rule::AlertRule;
+use crate::models::cat::Cat;
+use crate::models::job::{Job, JobKind};
+use crate::models::usage::Usage;
+use crate::models::user::{PublicUser, User};
+use crate::models::user_address_note::UserAddressNote;
+use crate::models::watched_address::WatchedAddress;
+use crate::models::watchlist::Watchlist;
+use crate::services::export::{Command, Format};
+use crate::services::gridfs;
+use crate::utils::models::ModelExt;
+use crate::utils::ndjson::NDJSON_CONTENT_TYPE;
+
+/// Payload of a `data_export` job.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DataExport {
+  pub format: Format,
+}
+
+/// Archive stored by a `data_export` job, kept as its result.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DataArchive {
+  // GridFS file holding the archive.
+  pub file: ObjectId,
+  pub format: Format,
+  pub size: u64,
+}
+
+impl DataArchive {
+  /// Archive of a succeeded `data_export` job.
+  pub fn of(job: &Job) -> Option<Self> {
+    if job.kind != JobKind::DataExport {
+      return None;
+    }
+
+    bson::from_document(job.result.clone()?).ok()
+  }
+
+  pub fn content_type(&self) -> &'static str {
+    content_type(self.format)
+  }
+
+  pub fn filename(&self) -> &'static str {
+    filename(self.format)
+  }
+}
+
+/// Assembles the data of a user into an archive stored in GridFS: their
+/// profile, cats, watchlists, address notes, alerts and usage. Documents are
+/// written as relaxed extended JSON, like the `export` command does.
+///
+/// JSON archives are an object holding the profile and an array per
+/// collection. NDJSON archives have a line per document, tagged with its
+/// collection.
+pub async fn create_archive(user: ObjectId, format: Format) -> Result<DataArchive, Error> {
+  let profile = match User::find_by_id(&user).await? {
+    Some(profile) => PublicUser::from(profile),
+    None => return Err(Error::not_found()),
+  };
+  let profile = serde_json::to_value(profile).map_err(serialize_error)?;
+
+  let mut archive = Vec::new();
+  match format {
+    Format::Json => {
+      archive.extend_from_slice(b"{\n\"profile\": ");
+      serde_json::to_writer(&mut archive, &profile).map_err(serialize_error)?;
+      for (collection, filter) in collections(user) {
+        archive.extend_from_slice(format!(",\n\"{}\": ", collection).as_bytes());
+        command(collection, format, filter)
+          .write(&mut archive)
+          .await?;
+      }
+      archive.extend_from_slice(b"}\n");
+    }
+    Format::Ndjson => {
+      write_line(&mut archive, "profile", profile)?;
+      for (collection, filter) in collections(user) {
+        let mut documents = Vec::new();
+        command(collection, format, filter)
+          .write(&mut documents)
+          .await?;
+
+        for line in documents.split(|byte| *byte == b'\n') {
+          if line.is_empty() {
+            continue;
+          }
+          let document = serde_json::from_slice::<Value>(line).map_err(serialize_error)?;
+          write_line(&mut archive, collection, document)?;
+        }
+      }
+    }
+  }
+
+  let body = stream::once(future::ready(Ok(Bytes::from(archive))));
+  let file = gridfs::upload(filename(format), content_type(format), body, usize::MAX).await?;
+  info!("Exported the data of user {} ({} bytes)", user, file.length);
+
+  Ok(DataArchive {
+    file: file.id,
+    format,
+    size: file.length,
+  })
+}
+
+/// Files of the archives exported by a user, removed along their account.
+pub async fn archive_files(user: ObjectId) -> Result<Vec<ObjectId>, Error> {
+  let jobs = Job::find(
+    doc! {
+      "user": user,
+      "kind": JobKind::DataExport.as_str(),
+      "status": "succeeded"
+    },
+    None,
+  )
+  .await?;
+
+  let files = jobs
+    .iter()
+    .filter_map(DataArchive::of)
+    .map(|archive| archive.file)
+    .collect();
+
+  Ok(files)
+}
+
+/// Collections holding data of the user, with the query of their documents.
+/// Removed cats are left out.
+fn collections(user: ObjectId) -> Vec<(&'static str, Document)> {
+  let query = doc! { "user": user };
+  vec![
+    (
+      Cat::COLLECTION_NAME,
+      doc! { "user": user, "deleted_at": Bson::Null },
+    ),
+    (Watchlist::COLLECTION_NAME, query.clone()),
+    (WatchedAddress::COLLECTION_NAME, query.clone()),
+    (UserAddressNote::COLLECTION_NAME, query.clone()),
+    (AlertRule::COLLECTION_NAME, query.clone()),
+    (AlertEvent::COLLECTION_NAME, query.clone()),
+    (Usage::COLLECTION_NAME, query),
+  ]
+}
+
+fn content_type(format: Format) -> &'static str {
+  match format {
+    Format::Ndjson => NDJSON_CONTENT_TYPE,
+    Format::Json => "application/json",
+  }
+}
+
+fn filename(format: Format) -> &'static str {
+  match format {
+    Format::Ndjson => "data-export.ndjson",
+    Format::Json => "data-export.json",
+  }
+}
+
+fn command(collection: &str, format: Format, filter: Document) -> Command {
+  Command {
+    collection: collection.to_owned(),
+    format,
+    filter,
+    // Every field, ids included.
+    fields: Vec::new(),
+    output: None,
+  }
+}
+
+fn write_line(archive: &mut Vec<u8>, collection: &str, document: Value) -> Result<(), Error> {
+  let line = json!({ "collection": collection, "document": document });
+  serde_json::to_writer(&mut *archive, &line).map_err(serialize_error)?;
+  archive.push(b'\n');
+
+  Ok(())
+}
+
+fn serialize_error(err: serde_json::Error) -> Error {
+  Error::General(format!("Failed to serialize exported data: {}", err))
+}
@@ -0,0 +1,338 @@
+use chrono::{DateTime, Duration, Utc};
+use futures::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tracing::{info, warn};
+use utoipa::ToSchema;
+
+use crate::errors::Error;
+use crate::routes::arkham::ArkhamResponse;
+use crate::services::address_intelligence::IntelligenceProvider;
+use crate::services::approvals::{Approval, ApprovalScanner};
+use crate::services::balances::{AddressBalances, BalanceClient};
+use crate::services::explorer::{ExplorerClient, Transaction};
+use crate::settings;
+use crate::utils::address::AddressChain;
+use crate::utils::serde_helpers::serialize_checksum_address;
+
+const MAX_SCORE: u32 = 100;
+
+// Lowest scores of the ape, degen and full degen tiers.
+const APE_SCORE: u32 = 25;
+const DEGEN_SCORE: u32 = 50;
+const FULL_DEGEN_SCORE: u32 = 75;
+
+// Transactions read to count the ones in the trade window, the frequency
+// being capped there.
+const MAX_TRANSACTIONS: u64 = 100;
+
+// Number of spenders checked on the explorer at the same time.
+const CHECK_CONCURRENCY: usize = 4;
+
+/// Scores how degen an address is from its on-chain behavior: how often it
+/// trades, how much of its balance is in memecoins, how many approvals it
+/// gave to unverified contracts and the type of its Arkham entity. Each
+/// factor adds its points to the score.
+pub struct DegenScorer {
+  address_intelligence: Arc<dyn IntelligenceProvider>,
+  approvals: Arc<ApprovalScanner>,
+  balances: Arc<BalanceClient>,
+  explorer: Arc<ExplorerClient>,
+  // Uppercased, token symbols are uppercased before matching.
+  memecoin_symbols: HashSet<String>,
+  settings: settings::DegenScore,
+}
+
+impl DegenScorer {
+  pub fn new(
+    address_intelligence: Arc<dyn IntelligenceProvider>,
+    approvals: Arc<ApprovalScanner>,
+    balances: Arc<BalanceClient>,
+    explorer: Arc<ExplorerClient>,
+    settings: &settings::DegenScore,
+  ) -> Self {
+    Self {
+      address_intelligence,
+      approvals,
+      balances,
+      explorer,
+      memecoin_symbols: settings
+        .memecoin_symbols
+        .iter()
+        .map(|symbol| symbol.to_uppercase())
+        .collect(),
+      settings: settings.clone(),
+    }
+  }
+
+  /// Degen score of an address, with its transactions and approvals on
+  /// `chain` and its balances on every chain. Sources being unavailable
+  /// doesn't fail the score, the ones that couldn't be read are reported
+  /// instead.
+  pub async fn score(&self, chain: &str, address: &str) -> DegenScoreReport {
+    info!("Scoring the degen behavior of address: {}", address);
+    let (transactions, balances, approvals, arkham) = tokio::join!(
+      self
+        .explorer
+        .fetch_transactions(chain, address, 1, MAX_TRANSACTIONS),
+      self.balances.fetch_balances(address, AddressChain::Evm),
+      self.approvals.fetch_approvals(chain, address),
+      self.address_intelligence.lookup_address(address),
+    );
+
+    let mut factors = Vec::new();
+    let mut unavailable_sources = Vec::new();
+    match transactions {
+      Ok(transactions) => factors.push(self.trade_factor(chain, &transactions)),
+      Err(err) => {
+        warn!("Failed to fetch the transactions of {}: {}", address, err);
+        unavailable_sources.push("explorer".to_owned());
+      }
+    }
+    match self.memecoin_factor(&balances) {
+      Some(factor) => factors.push(factor),
+      None => unavailable_sources.push("balances".to_owned()),
+    }
+    match approvals {
+      Ok(scan) => factors.push(self.approval_factor(chain, &scan.approvals).await),
+      Err(err) => {
+        warn!("Failed to scan the approvals of {}: {}", address, err);
+        unavailable_sources.push("approvals".to_owned());
+      }
+    }
+    match arkham {
+      Ok(arkham) => factors.push(self.entity_factor(Some(&arkham))),
+      // Addresses unknown to Arkham have no entity.
+      Err(Error::NotFound(_)) => factors.push(self.entity_factor(None)),
+      Err(err) => {
+        warn!("Failed to look up {} on Arkham: {}", address, err);
+        unavailable_sources.push("arkham".to_owned());
+      }
+    }
+
+    let score = factors
+      .iter()
+      .map(|factor| factor.points)
+      .sum::<u32>()
+      .min(MAX_SCORE);
+
+    DegenScoreReport {
+      address: address.to_owned(),
+      chain: chain.to_owned(),
+      score,
+      tier: DegenTier::from_score(score),
+      factors,
+      unavailable_sources,
+    }
+  }
+
+  fn trade_factor(&self, chain: &str, transactions: &[Transaction]) -> DegenFactor {
+    let window_days = self.settings.trade_window_days;
+    let since = Utc::now() - Duration::days(window_days as i64);
+    let trades = transactions
+      .iter()
+      .filter(|transaction| {
+        DateTime::parse_from_rfc3339(&transaction.timestamp)
+          .map_or(false, |timestamp| timestamp >= since)
+      })
+      .count();
+
+    DegenFactor {
+      kind: DegenFactorKind::TradeFrequency,
+      points: (trades as u32)
+        .saturating_mul(self.settings.trade_points)
+        .min(self.settings.max_trade_points),
+      max_points: self.settings.max_trade_points,
+      detail: format!(
+        "{} transactions on {} in the last {} days",
+        trades, chain, window_days
+      ),
+    }
+  }
+
+  /// `None` when the balances of every chain failed.
+  fn memecoin_factor(&self, balances: &AddressBalances) -> Option<DegenFactor> {
+    if balances.chains.iter().all(|chain| chain.error.is_some()) {
+      warn!("Failed to fetch the balances of {}", balances.address);
+      return None;
+    }
+
+    let memecoin_usd = balances
+      .chains
+      .iter()
+      .flat_map(|chain| &chain.tokens)
+      .filter(|token| {
+        token
+          .symbol
+          .as_ref()
+          .is_some_and(|symbol| self.memecoin_symbols.contains(&symbol.to_uppercase()))
+      })
+      .filter_map(|token| token.usd_value)
+      .sum::<f64>();
+    let share = if balances.usd_value > 0.0 {
+      (memecoin_usd / balances.usd_value).clamp(0.0, 1.0)
+    } else {
+      0.0
+    };
+
+    Some(DegenFactor {
+      kind: DegenFactorKind::MemecoinExposure,
+      points: (share * self.settings.memecoin_points as f64).round() as u32,
+      max_points: self.settings.memecoin_points,
+      detail: format!(
+        "{:.0}% of the balance is in memecoins, worth ${:.2}",
+        share * 100.0,
+        memecoin_usd
+      ),
+    })
+  }
+
+  /// Spenders whose verification can't be read are not counted.
+  async fn approval_factor(&self, chain: &str, approvals: &[Approval]) -> DegenFactor {
+    let spenders = approvals
+      .iter()
+      .map(|approval| approval.spender.clone())
+      .collect::<HashSet<String>>();
+    let checks = spenders.into_iter().map(|spender| async move {
+      let verified = self.explorer.is_verified(chain, &spender).await;
+      (spender, verified)
+    });
+
+    let mut verified_by_spender = HashMap::new();
+    for (spender, verified) in stream::iter(checks)
+      .buffered(CHECK_CONCURRENCY)
+      .collect::<Vec<_>>()
+      .await
+    {
+      match verified {
+        Ok(verified) => {
+          verified_by_spender.insert(spender, verified);
+        }
+        Err(err) => warn!("Failed to check the source code of {}: {}", spender, err),
+      }
+    }
+
+    let unverified = approvals
+      .iter()
+      .filter(|approval| verified_by_spender.get(&approval.spender) == Some(&false))
+      .count();
+
+    DegenFactor {
+      kind: DegenFactorKind::UnverifiedApprovals,
+      points: (unverified as u32)
+        .saturating_mul(self.settings.unverified_approval_points)
+        .min(self.settings.max_unverified_approval_points),
+      max_points: self.settings.max_unverified_approval_points,
+      detail: format!(
+        "{} approvals on {} to unverified contracts",
+        unverified, chain
+      ),
+    }
+  }
+
+  fn entity_factor(&self, arkham: Option<&ArkhamResponse>) -> DegenFactor {
+    let max_points = self
+      .settings
+      .entity_type_points
+      .values()
+      .copied()
+      .chain([self.settings.anonymous_points])
+      .max()
+      .unwrap_or_default();
+
+    // Entities are usually the same on every chain, the first one is used.
+    let entity = arkham.and_then(|arkham| {
+      arkham
+        .chains()
+        .into_iter()
+        .find_map(|(_, data)| Some((data.entity_name(), data.entity_type()?)))
+    });
+    let (points, detail) = match entity {
+      Some((name, entity_type)) => (
+        self
+          .settings
+          .entity_type_points
+          .get(entity_type)
+          .copied()
+          .unwrap_or_default(),
+        format!(
+          "Arkham entity {} is of type {}",
+          name.unwrap_or("without name"),
+          entity_type
+        ),
+      ),
+      None => (
+        self.settings.anonymous_points,
+        String::from("No Arkham entity, the address is anonymous"),
+      ),
+    };
+
+    DegenFactor {
+      kind: DegenFactorKind::EntityType,
+      points,
+      max_points,
+      detail,
+    }
+  }
+}
+
+/// Degen score of an address, from 0 to 100, with the factors it adds up.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct DegenScoreReport {
+  #[serde(serialize_with = "serialize_checksum_address")]
+  pub address: String,
+  // Chain whose transactions and approvals were read.
+  pub chain: String,
+  pub score: u32,
+  pub tier: DegenTier,
+  pub factors: Vec<DegenFactor>,
+  // Sources that couldn't be read, `explorer`, `balances`, `approvals` or
+  // `arkham`, whose factors are missing from the score.
+  pub unavailable_sources: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct DegenFactor {
+  pub kind: DegenFactorKind,
+  // Points added to the score, out of `max_points`.
+  pub points: u32,
+  pub max_points: u32,
+  pub detail: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DegenFactorKind {
+  // Transactions in the trade window.
+  TradeFrequency,
+  // Share of the balance in memecoins.
+  MemecoinExposure,
+  UnverifiedApprovals,
+  // Type of the Arkham entity of the address, e.g. `mev-bot`.
+  EntityType,
+}
+
+/// Normie under 25, ape under 50, degen under 75, full degen otherwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DegenTier {
+  Normie,
+  Ape,
+  Degen,
+  FullDegen,
+}
+
+impl DegenTier {
+  fn from_score(score: u32) -> Self {
+    if score >= FULL_DEGEN_SCORE {
+      Self::FullDegen
+    } else if score >= DEGEN_SCORE {
+      Self::Degen
+    } else if score >= APE_SCORE {
+      Self::Ape
+    } else {
+      Self::Normie
+    }
+  }
+}
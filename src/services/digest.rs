@@ -0,0 +1,102 @@
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio::time::{interval, MissedTickBehavior};
+use tracing::{debug, error, info, warn};
+use wither::bson::doc;
+use wither::mongodb::options::FindOptions;
+
+use crate::errors::Error;
+use crate::models::alert_event::AlertEvent;
+use crate::models::user::User;
+use crate::notifications::email::EmailNotifier;
+use crate::settings;
+use crate::utils::date;
+use crate::utils::models::ModelExt;
+use crate::utils::shutdown::ShutdownSignal;
+
+// Digests are emailed once a week.
+const DIGEST_PERIOD: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+// Maximum number of alerts listed in a digest, the others are only counted.
+const DIGEST_MAX_EVENTS: i64 = 50;
+
+/// Starts the worker emailing the weekly digests, checking for due digests
+/// every `digest.poll_interval_secs`.
+pub fn spawn(
+  settings: settings::Digest,
+  notifier: Arc<EmailNotifier>,
+  mut shutdown: ShutdownSignal,
+) -> JoinHandle<()> {
+  let period = Duration::from_secs(settings.poll_interval_secs);
+  info!("Starting digest worker, polling every {:?}", period);
+
+  tokio::spawn(async move {
+    let mut ticks = interval(period);
+    ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+      tokio::select! {
+        _ = ticks.tick() => {}
+        _ = shutdown.requested() => break,
+      }
+      if let Err(err) = send_due(&notifier).await {
+        error!("Failed to send digests: {}", err);
+      }
+    }
+
+    info!("Digest worker stopped");
+  })
+}
+
+/// Emails a digest to every user subscribed to them whose last digest is a
+/// week old. Returns the number of sent digests.
+pub async fn send_due(notifier: &EmailNotifier) -> Result<u64, Error> {
+  let period_start = date::before(DIGEST_PERIOD);
+  let users = User::find(
+    doc! {
+      "email_preferences.weekly_digest": true,
+      "$or": [
+        { "digest_sent_at": null },
+        { "digest_sent_at": { "$lte": period_start } }
+      ]
+    },
+    None,
+  )
+  .await?;
+  debug!("Sending digests to {} users", users.len());
+
+  let mut sent = 0;
+  for user in users {
+    let user_id = user.id.unwrap();
+    // Covers every alert since the last digest, even when the worker was
+    // down for longer than a week.
+    let since = user.digest_sent_at.unwrap_or(period_start);
+    let query = doc! { "user": &user_id, "created_at": { "$gt": since } };
+
+    let options = FindOptions::builder()
+      .sort(doc! { "created_at": -1_i32 })
+      .limit(DIGEST_MAX_EVENTS)
+      .build();
+    let (events, total) = AlertEvent::find_and_count(query, options).await?;
+
+    // Users without alerts get no email, their next digest is due in a week.
+    if !events.is_empty() {
+      if let Err(err) = notifier.send_digest(&user, &events, total).await {
+        // Retried on the next poll.
+        warn!("Failed to email digest to user {}: {}", user_id, err);
+        continue;
+      }
+      sent += 1;
+    }
+
+    User::update_one(
+      doc! { "_id": &user_id },
+      doc! { "$set": { "digest_sent_at": date::now() } },
+      None,
+    )
+    .await?;
+  }
+
+  Ok(sent)
+}
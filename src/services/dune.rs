@@ -0,0 +1,230 @@
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use tracing::{debug, error, info};
+use utoipa::ToSchema;
+
+use crate::errors::Error;
+use crate::settings;
+use crate::utils::telemetry;
+
+/// Client for the Dune Analytics API. Queries are executed asynchronously
+/// by Dune: an execution is started, then polled until its results are
+/// ready.
+pub struct DuneClient {
+  http_client: reqwest::Client,
+  url: String,
+  api_key: String,
+}
+
+impl DuneClient {
+  pub fn new(http_client: reqwest::Client, settings: &settings::Dune) -> Self {
+    Self {
+      http_client,
+      url: settings.url.clone(),
+      api_key: settings.api_key.clone(),
+    }
+  }
+
+  /// Starts an execution of a saved query with the given parameters.
+  pub async fn execute_query(
+    &self,
+    query_id: u64,
+    parameters: &HashMap<String, Value>,
+  ) -> Result<DuneExecution, Error> {
+    info!("Executing Dune query: {}", query_id);
+    let res = self
+      .http_client
+      .post(format!("{}/query/{}/execute", self.url, query_id))
+      .headers(telemetry::trace_headers())
+      .header("X-Dune-API-Key", &self.api_key)
+      .json(&json!({ "query_parameters": parameters }))
+      .send()
+      .await
+      .map_err(|err| Error::upstream_request("Dune", err))?;
+
+    let execution = read_json::<DuneResponse>(res).await?;
+    Ok(DuneExecution::from(execution))
+  }
+
+  /// State of an execution, with its result rows once it has completed.
+  pub async fn fetch_execution(&self, execution_id: &str) -> Result<DuneExecution, Error> {
+    info!("Querying Dune execution: {}", execution_id);
+    let res = self
+      .execution_request(execution_id)
+      .send()
+      .await
+      .map_err(|err| Error::upstream_request("Dune", err))?;
+
+    let execution = read_json::<DuneResponse>(res).await?;
+    Ok(DuneExecution::from(execution))
+  }
+
+  /// Same request as `fetch_execution`, returning the response unread so its
+  /// body, in Dune's format, can be streamed to the client, see
+  /// `utils::proxy`.
+  pub async fn stream_execution(&self, execution_id: &str) -> Result<reqwest::Response, Error> {
+    info!("Streaming Dune execution: {}", execution_id);
+    let res = self
+      .execution_request(execution_id)
+      .send()
+      .await
+      .map_err(|err| Error::upstream_request("Dune", err))?;
+
+    check_status(res)
+  }
+
+  fn execution_request(&self, execution_id: &str) -> reqwest::RequestBuilder {
+    self
+      .http_client
+      .get(format!("{}/execution/{}/results", self.url, execution_id))
+      .headers(telemetry::trace_headers())
+      .header("X-Dune-API-Key", &self.api_key)
+  }
+}
+
+async fn read_json<T: DeserializeOwned>(res: reqwest::Response) -> Result<T, Error> {
+  let res = check_status(res)?;
+  res.json::<T>().await.map_err(|err| {
+    error!("Failed to parse Dune response: {}", err);
+    Error::UpstreamInvalidResponse(err.to_string())
+  })
+}
+
+fn check_status(res: reqwest::Response) -> Result<reqwest::Response, Error> {
+  let status = res.status();
+  if status == reqwest::StatusCode::NOT_FOUND {
+    debug!("Dune query or execution not found, returning 404 status code");
+    return Err(Error::not_found());
+  }
+
+  // Dune rejects invalid query parameters with a 400.
+  if status == reqwest::StatusCode::BAD_REQUEST {
+    debug!("Dune rejected the request, returning 400 status code");
+    return Err(Error::bad_request());
+  }
+
+  if !status.is_success() {
+    error!("Received a {} error from Dune", status);
+    return Err(Error::upstream_unavailable("Dune", Some(status.as_u16())));
+  }
+
+  Ok(res)
+}
+
+/// Execution of a Dune query. Columns and rows are empty until it has
+/// completed.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct DuneExecution {
+  pub execution_id: String,
+  pub query_id: Option<u64>,
+  pub state: ExecutionState,
+  pub submitted_at: Option<String>,
+  pub ended_at: Option<String>,
+  pub columns: Vec<DuneColumn>,
+  // Rows as objects keyed by column name.
+  #[schema(value_type = Vec<Object>)]
+  pub rows: Vec<Map<String, Value>>,
+  // Set when the execution failed.
+  pub error: Option<String>,
+}
+
+impl From<DuneResponse> for DuneExecution {
+  fn from(res: DuneResponse) -> Self {
+    let (columns, rows) = match res.result {
+      Some(result) => {
+        let metadata = result.metadata.unwrap_or_default();
+        let columns = metadata
+          .column_names
+          .into_iter()
+          .enumerate()
+          .map(|(index, name)| DuneColumn {
+            name,
+            column_type: metadata.column_types.get(index).cloned(),
+          })
+          .collect();
+        (columns, result.rows)
+      }
+      None => (Vec::new(), Vec::new()),
+    };
+
+    Self {
+      execution_id: res.execution_id,
+      query_id: res.query_id,
+      state: ExecutionState::from(res.state.as_str()),
+      submitted_at: res.submitted_at,
+      ended_at: res.execution_ended_at,
+      columns,
+      rows,
+      error: res.error.and_then(|error| error.message),
+    }
+  }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct DuneColumn {
+  pub name: String,
+  // Dune SQL type, e.g. `varchar` or `double`.
+  #[serde(rename = "type")]
+  pub column_type: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecutionState {
+  Pending,
+  Executing,
+  Completed,
+  // Completed with truncated results, over the size limit of Dune.
+  CompletedPartial,
+  Failed,
+  Cancelled,
+  Expired,
+}
+
+impl From<&str> for ExecutionState {
+  fn from(state: &str) -> Self {
+    match state {
+      "QUERY_STATE_PENDING" => Self::Pending,
+      "QUERY_STATE_EXECUTING" => Self::Executing,
+      "QUERY_STATE_COMPLETED" => Self::Completed,
+      "QUERY_STATE_COMPLETED_PARTIAL" => Self::CompletedPartial,
+      "QUERY_STATE_CANCELLED" => Self::Cancelled,
+      "QUERY_STATE_EXPIRED" => Self::Expired,
+      // `QUERY_STATE_FAILED`, and states added by Dune since.
+      _ => Self::Failed,
+    }
+  }
+}
+
+#[derive(Deserialize)]
+struct DuneResponse {
+  execution_id: String,
+  query_id: Option<u64>,
+  state: String,
+  submitted_at: Option<String>,
+  execution_ended_at: Option<String>,
+  result: Option<DuneResult>,
+  error: Option<DuneError>,
+}
+
+#[derive(Deserialize)]
+struct DuneResult {
+  #[serde(default)]
+  rows: Vec<Map<String, Value>>,
+  metadata: Option<DuneMetadata>,
+}
+
+#[derive(Default, Deserialize)]
+struct DuneMetadata {
+  #[serde(default)]
+  column_names: Vec<String>,
+  #[serde(default)]
+  column_types: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct DuneError {
+  message: Option<String>,
+}
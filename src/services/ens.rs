@@ -0,0 +1,261 @@
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sha3::{Digest, Keccak256};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, error, info};
+
+use crate::errors::Error;
+use crate::settings;
+use crate::utils::address::normalize_evm_address;
+use crate::utils::cache::{self, TtlCache};
+use crate::utils::telemetry;
+
+// Selectors of `resolver(bytes32)` on the registry, and of `addr(bytes32)`
+// and `name(bytes32)` on resolvers.
+const RESOLVER_SELECTOR: &str = "0178b8bf";
+const ADDR_SELECTOR: &str = "3b3b57de";
+const NAME_SELECTOR: &str = "691f3431";
+
+const ZERO_ADDRESS: &str = "0x0000000000000000000000000000000000000000";
+
+/// Resolves ENS names through an Ethereum JSON-RPC provider, reading the ENS
+/// registry and resolver contracts with `eth_call`. Results are cached,
+/// names missing on chain included. It is cheap to clone, clones share the
+/// caches.
+#[derive(Clone)]
+pub struct EnsClient {
+  http_client: reqwest::Client,
+  rpc_url: String,
+  registry: String,
+  // Address of each name, `None` when the name doesn't resolve.
+  addresses: Arc<TtlCache<Option<String>>>,
+  // Primary name of each address, `None` when it has none.
+  names: Arc<TtlCache<Option<String>>>,
+}
+
+impl EnsClient {
+  pub fn new(
+    http_client: reqwest::Client,
+    settings: &settings::Ens,
+    caches: &settings::Cache,
+  ) -> Self {
+    let ttl = Duration::from_secs(settings.cache_ttl_secs);
+    Self {
+      http_client,
+      rpc_url: settings.rpc_url.clone(),
+      registry: settings.registry.to_lowercase(),
+      addresses: Arc::new(TtlCache::new(cache::backend(caches, "ens:addresses"), ttl)),
+      names: Arc::new(TtlCache::new(cache::backend(caches, "ens:names"), ttl)),
+    }
+  }
+
+  /// Resolves a normalized name (see `normalize_name`) to its address.
+  /// Names without a resolver or an address are not found.
+  pub async fn resolve(&self, name: &str) -> Result<String, Error> {
+    let address = match self.addresses.get(name).await {
+      Some(entry) => entry.value,
+      None => {
+        let address = self.fetch_address(name).await?;
+        self.addresses.insert(name, address.clone()).await;
+        address
+      }
+    };
+
+    match address {
+      Some(address) => Ok(address),
+      None => {
+        debug!(
+          "ENS name {} does not resolve, returning 404 status code",
+          name
+        );
+        Err(Error::not_found())
+      }
+    }
+  }
+
+  /// Looks up the primary name of an address. Like ENS clients do, the name
+  /// is only returned when it resolves back to the address, since anyone
+  /// can claim any name in their reverse record.
+  pub async fn lookup_name(&self, address: &str) -> Result<Option<String>, Error> {
+    if let Some(entry) = self.names.get(address).await {
+      return Ok(entry.value);
+    }
+
+    let name = match self.fetch_name(address).await? {
+      Some(name) if self.resolve(&name).await.ok().as_deref() == Some(address) => Some(name),
+      _ => None,
+    };
+    self.names.insert(address, name.clone()).await;
+
+    Ok(name)
+  }
+
+  async fn fetch_address(&self, name: &str) -> Result<Option<String>, Error> {
+    info!("Resolving ENS name: {}", name);
+    let node = namehash(name);
+    let resolver = match self.resolver(&node).await? {
+      Some(resolver) => resolver,
+      None => return Ok(None),
+    };
+
+    let result = self.call(&resolver, ADDR_SELECTOR, &node).await?;
+    Ok(decode_address(&result)?.filter(|address| address != ZERO_ADDRESS))
+  }
+
+  async fn fetch_name(&self, address: &str) -> Result<Option<String>, Error> {
+    info!("Looking up the ENS name of address: {}", address);
+    let reverse_name = format!("{}.addr.reverse", address.trim_start_matches("0x"));
+    let node = namehash(&reverse_name);
+    let resolver = match self.resolver(&node).await? {
+      Some(resolver) => resolver,
+      None => return Ok(None),
+    };
+
+    let result = self.call(&resolver, NAME_SELECTOR, &node).await?;
+    // Reverse records are free text, so they are normalized like the names
+    // received from clients.
+    let name = decode_string(&result)?.and_then(|name| normalize_name(&name).ok());
+    Ok(name)
+  }
+
+  /// Resolver contract of a node, `None` when the name has no resolver.
+  async fn resolver(&self, node: &[u8; 32]) -> Result<Option<String>, Error> {
+    let result = self.call(&self.registry, RESOLVER_SELECTOR, node).await?;
+    Ok(decode_address(&result)?.filter(|address| address != ZERO_ADDRESS))
+  }
+
+  /// Calls a contract function taking a single `bytes32` argument, returning
+  /// the ABI encoded result.
+  async fn call(&self, to: &str, selector: &str, node: &[u8; 32]) -> Result<Vec<u8>, Error> {
+    let data = format!("0x{}{}", selector, hex::encode(node));
+    let payload = json!({
+      "jsonrpc": "2.0",
+      "id": 1,
+      "method": "eth_call",
+      "params": [{ "to": to, "data": data }, "latest"]
+    });
+
+    let res = self
+      .http_client
+      .post(&self.rpc_url)
+      .headers(telemetry::trace_headers())
+      .json(&payload)
+      .send()
+      .await
+      .map_err(|err| Error::upstream_request("ENS", err))?;
+
+    let status = res.status();
+    if !status.is_success() {
+      error!("Received a {} error from the Ethereum RPC provider", status);
+      return Err(Error::upstream_unavailable("ENS", Some(status.as_u16())));
+    }
+
+    let res = res.json::<RpcResponse>().await.map_err(|err| {
+      error!("Failed to parse Ethereum RPC response: {}", err);
+      Error::UpstreamInvalidResponse(err.to_string())
+    })?;
+
+    match (res.result, res.error) {
+      (Some(result), _) => hex::decode(result.trim_start_matches("0x"))
+        .map_err(|err| Error::UpstreamInvalidResponse(err.to_string())),
+      (None, error) => {
+        error!("Ethereum RPC call failed: {:?}", error);
+        Err(Error::upstream_unavailable("ENS", None))
+      }
+    }
+  }
+}
+
+#[derive(Deserialize)]
+struct RpcResponse {
+  result: Option<String>,
+  error: Option<Value>,
+}
+
+/// Whether a lookup input is an ENS name rather than an address. Addresses
+/// never contain dots, while every ENS name does.
+pub fn is_name(input: &str) -> bool {
+  input.contains('.')
+}
+
+/// Normalizes an ENS name to lowercase. Full UTS-46 normalization is not
+/// implemented, so only ASCII letters, digits, `-` and `_` are accepted in
+/// labels, which covers the vast majority of names.
+pub fn normalize_name<S: AsRef<str>>(name: S) -> Result<String, Error> {
+  let name = name.as_ref().trim();
+  let is_valid = name.contains('.')
+    && name.split('.').all(|label| {
+      !label.is_empty()
+        && label
+          .chars()
+          .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    });
+
+  if !is_valid {
+    return Err(Error::InvalidAddress(name.to_string()));
+  }
+
+  Ok(name.to_ascii_lowercase())
+}
+
+/// ENS node of a name, as defined by EIP-137.
+pub fn namehash(name: &str) -> [u8; 32] {
+  let mut node = [0_u8; 32];
+  if name.is_empty() {
+    return node;
+  }
+
+  for label in name.rsplit('.') {
+    let label_hash = Keccak256::digest(label.as_bytes());
+    let mut hasher = Keccak256::new();
+    hasher.update(node);
+    hasher.update(label_hash);
+    node = hasher.finalize().into();
+  }
+
+  node
+}
+
+/// Decodes an ABI encoded `address`, the last 20 bytes of a 32 bytes word.
+/// An empty result means the called contract doesn't exist.
+fn decode_address(data: &[u8]) -> Result<Option<String>, Error> {
+  if data.is_empty() {
+    return Ok(None);
+  }
+
+  if data.len() < 32 {
+    return Err(Error::UpstreamInvalidResponse(
+      "expected an ABI encoded address".to_owned(),
+    ));
+  }
+
+  normalize_evm_address(format!("0x{}", hex::encode(&data[12..32]))).map(Some)
+}
+
+/// Decodes an ABI encoded `string`: an offset, then the length and the
+/// bytes. Empty strings are returned as `None`.
+fn decode_string(data: &[u8]) -> Result<Option<String>, Error> {
+  if data.is_empty() {
+    return Ok(None);
+  }
+
+  let invalid = || Error::UpstreamInvalidResponse("expected an ABI encoded string".to_owned());
+  let slice = |start: usize, length: usize| -> Result<&[u8], Error> {
+    let end = start.checked_add(length).ok_or_else(invalid)?;
+    data.get(start..end).ok_or_else(invalid)
+  };
+  let read_usize = |at: usize| -> Result<usize, Error> {
+    let word = slice(at, 32)?;
+    // Lengths and offsets fitting in a response fit in 8 bytes.
+    let bytes: [u8; 8] = word[24..].try_into().unwrap();
+    usize::try_from(u64::from_be_bytes(bytes)).map_err(|_| invalid())
+  };
+
+  let offset = read_usize(0)?;
+  let length = read_usize(offset)?;
+  let bytes = slice(offset.checked_add(32).ok_or_else(invalid)?, length)?;
+  let string = String::from_utf8(bytes.to_vec()).map_err(|_| invalid())?;
+
+  Ok(Some(string).filter(|string| !string.is_empty()))
+}
@@ -0,0 +1,234 @@
+use chrono::DateTime;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use tracing::{debug, error, info};
+use utoipa::ToSchema;
+
+use crate::errors::Error;
+use crate::settings;
+use crate::utils::serde_helpers::{
+  serialize_checksum_address, serialize_optional_checksum_address,
+};
+use crate::utils::telemetry;
+
+/// Client for the Etherscan family of block explorer APIs (Etherscan,
+/// BscScan, Arbiscan...), which share the same interface. Each chain has its
+/// own explorer and API key.
+pub struct ExplorerClient {
+  http_client: reqwest::Client,
+  explorers: HashMap<String, settings::Explorer>,
+}
+
+impl ExplorerClient {
+  pub fn new(http_client: reqwest::Client, settings: &settings::Explorers) -> Self {
+    Self {
+      http_client,
+      explorers: settings.chains.clone(),
+    }
+  }
+
+  pub fn supports_chain(&self, chain: &str) -> bool {
+    self.explorers.contains_key(chain)
+  }
+
+  /// Fetches a page of the normal transactions of an address, newest first.
+  /// `page` starts at 1.
+  pub async fn fetch_transactions(
+    &self,
+    chain: &str,
+    address: &str,
+    page: u64,
+    limit: u64,
+  ) -> Result<Vec<Transaction>, Error> {
+    info!("Querying {} transactions of address: {}", chain, address);
+    let transactions = self
+      .request(
+        chain,
+        &[
+          ("module", "account"),
+          ("action", "txlist"),
+          ("address", address),
+          ("sort", "desc"),
+          ("page", page.to_string().as_str()),
+          ("offset", limit.to_string().as_str()),
+        ],
+      )
+      .await?;
+
+    transactions
+      .into_iter()
+      .map(|transaction| {
+        let transaction = serde_json::from_value::<ExplorerTransaction>(transaction)
+          .map_err(|err| Error::UpstreamInvalidResponse(err.to_string()))?;
+        Transaction::try_from(transaction)
+      })
+      .collect()
+  }
+
+  /// Whether the source code of a contract is verified on the explorer of
+  /// the chain. Addresses without code are not verified either.
+  pub async fn is_verified(&self, chain: &str, address: &str) -> Result<bool, Error> {
+    info!(
+      "Querying the {} source code of contract: {}",
+      chain, address
+    );
+    let contracts = self
+      .request(
+        chain,
+        &[
+          ("module", "contract"),
+          ("action", "getsourcecode"),
+          ("address", address),
+        ],
+      )
+      .await?;
+
+    let contract = contracts
+      .into_iter()
+      .next()
+      .map(serde_json::from_value::<ExplorerContract>)
+      .transpose()
+      .map_err(|err| Error::UpstreamInvalidResponse(err.to_string()))?;
+
+    Ok(contract.is_some_and(|contract| !contract.source_code.is_empty()))
+  }
+
+  /// Sends a request to the explorer of the chain, returning its list of
+  /// results.
+  async fn request(&self, chain: &str, query: &[(&str, &str)]) -> Result<Vec<Value>, Error> {
+    let explorer = match self.explorers.get(chain) {
+      Some(explorer) => explorer,
+      None => {
+        debug!("No explorer for chain {}, returning 400 status code", chain);
+        return Err(Error::bad_request());
+      }
+    };
+
+    let res = self
+      .http_client
+      .get(&explorer.url)
+      .headers(telemetry::trace_headers())
+      .query(query)
+      .query(&[("apikey", explorer.api_key.as_str())])
+      .send()
+      .await
+      .map_err(|err| Error::upstream_request("Block explorer", err))?;
+
+    let status = res.status();
+    if !status.is_success() {
+      error!("Received a {} error from the {} explorer", status, chain);
+      return Err(Error::upstream_unavailable(
+        "Block explorer",
+        Some(status.as_u16()),
+      ));
+    }
+
+    let res = res.json::<ExplorerResponse>().await.map_err(|err| {
+      error!("Failed to parse {} explorer response: {}", chain, err);
+      Error::UpstreamInvalidResponse(err.to_string())
+    })?;
+
+    // Failures are reported with a `0` status and a message as result, but
+    // so are addresses without transactions, with an empty list.
+    match res.result {
+      Value::Array(results) => Ok(results),
+      result => {
+        error!(
+          "The {} explorer request failed: {} ({})",
+          chain, res.message, result
+        );
+        Err(Error::upstream_unavailable("Block explorer", None))
+      }
+    }
+  }
+}
+
+/// Transaction of an address, normalized across explorers. Amounts are in
+/// wei, as decimal strings since they overflow 64 bits.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Transaction {
+  pub hash: String,
+  pub block_number: u64,
+  // RFC 3339 date of the block.
+  pub timestamp: String,
+  #[serde(serialize_with = "serialize_checksum_address")]
+  pub from: String,
+  // `None` for contract creations, see `contract_address`.
+  #[serde(serialize_with = "serialize_optional_checksum_address")]
+  pub to: Option<String>,
+  #[serde(serialize_with = "serialize_optional_checksum_address")]
+  pub contract_address: Option<String>,
+  pub value: String,
+  pub gas_used: u64,
+  pub gas_price: String,
+  pub is_error: bool,
+  pub function_name: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ExplorerResponse {
+  #[serde(default)]
+  message: String,
+  result: Value,
+}
+
+/// Transaction as returned by the explorers, every field being a string.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExplorerTransaction {
+  hash: String,
+  block_number: String,
+  time_stamp: String,
+  from: String,
+  #[serde(default)]
+  to: String,
+  #[serde(default)]
+  contract_address: String,
+  value: String,
+  gas_used: String,
+  gas_price: String,
+  #[serde(default)]
+  is_error: String,
+  #[serde(default)]
+  function_name: String,
+}
+
+/// Contract as returned by `getsourcecode`, with an empty source code when
+/// it is not verified.
+#[derive(Deserialize)]
+struct ExplorerContract {
+  #[serde(rename = "SourceCode", default)]
+  source_code: String,
+}
+
+impl TryFrom<ExplorerTransaction> for Transaction {
+  type Error = Error;
+
+  fn try_from(transaction: ExplorerTransaction) -> Result<Self, Error> {
+    let invalid = |field: &str| Error::UpstreamInvalidResponse(format!("invalid {}", field));
+    let number = |value: &str, field: &str| value.parse::<u64>().map_err(|_| invalid(field));
+    let non_empty = |value: String| Some(value.to_lowercase()).filter(|value| !value.is_empty());
+
+    let timestamp = transaction
+      .time_stamp
+      .parse::<i64>()
+      .ok()
+      .and_then(|secs| DateTime::from_timestamp(secs, 0))
+      .ok_or_else(|| invalid("timeStamp"))?;
+
+    Ok(Self {
+      hash: transaction.hash,
+      block_number: number(&transaction.block_number, "blockNumber")?,
+      timestamp: timestamp.to_rfc3339(),
+      from: transaction.from.to_lowercase(),
+      to: non_empty(transaction.to),
+      contract_address: non_empty(transaction.contract_address),
+      value: transaction.value,
+      gas_used: number(&transaction.gas_used, "gasUsed")?,
+      gas_price: transaction.gas_price,
+      is_error: transaction.is_error == "1",
+      function_name: Some(transaction.function_name).filter(|name| !name.is_empty()),
+    })
+  }
+}
@@ -0,0 +1,186 @@
+use futures::TryStreamExt;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncWrite, AsyncWriteExt, BufWriter};
+use utoipa::ToSchema;
+use wither::bson::{doc, Bson, Document};
+use wither::mongodb::options::FindOptions;
+
+use crate::database::CONNECTION;
+use crate::errors::Error;
+
+pub const USAGE: &str = "Usage: rustapi export <collection> [--format ndjson | json] \
+                         [--filter <extended JSON>] [--fields <field,...>] [--output <path>]";
+
+/// Short names of the collections most often exported.
+const COLLECTION_ALIASES: [(&str, &str); 2] = [
+  ("labels", "address_labels"),
+  ("snapshots", "address_snapshots"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+  // One document per line, the format of `POST /labels/import`.
+  Ndjson,
+  // A single array of documents.
+  Json,
+}
+
+/// Dumps a collection to JSON, for backups and to move datasets between
+/// environments. Documents are written as relaxed extended JSON, so ids and
+/// dates keep their type when imported back with `mongoimport`.
+#[derive(Debug, PartialEq)]
+pub struct Command {
+  pub collection: String,
+  pub format: Format,
+  pub filter: Document,
+  // Every field when empty. `_id` is only exported when listed.
+  pub fields: Vec<String>,
+  // Standard output when unset.
+  pub output: Option<String>,
+}
+
+impl Command {
+  /// Parses the arguments following `export`, `None` when they are invalid.
+  pub fn parse(args: &[String]) -> Option<Self> {
+    let (collection, options) = args.split_first()?;
+    if collection.starts_with("--") {
+      return None;
+    }
+
+    let mut command = Self {
+      collection: collection_name(collection),
+      format: Format::Ndjson,
+      filter: Document::new(),
+      fields: Vec::new(),
+      output: None,
+    };
+    for option in options.chunks(2) {
+      match option {
+        [name, value] if name == "--format" => {
+          command.format = match value.as_str() {
+            "ndjson" => Format::Ndjson,
+            "json" => Format::Json,
+            _ => return None,
+          }
+        }
+        [name, value] if name == "--filter" => command.filter = parse_filter(value)?,
+        [name, value] if name == "--fields" => {
+          command.fields = value
+            .split(',')
+            .map(str::trim)
+            .filter(|field| !field.is_empty())
+            .map(ToOwned::to_owned)
+            .collect();
+        }
+        [name, value] if name == "--output" => command.output = Some(value.to_owned()),
+        _ => return None,
+      }
+    }
+
+    Some(command)
+  }
+
+  pub async fn execute(self) -> Result<(), Error> {
+    let exported = match self.output.as_deref() {
+      Some(path) => {
+        let file = tokio::fs::File::create(path)
+          .await
+          .map_err(|err| Error::General(format!("Failed to create {}: {}", path, err)))?;
+        self.write(file).await?
+      }
+      None => self.write(tokio::io::stdout()).await?,
+    };
+
+    // Printed on stderr, stdout may hold the export.
+    eprintln!("Exported {} documents from {}", exported, self.collection);
+    Ok(())
+  }
+
+  /// Streams the matching documents to `writer`, oldest first, returning
+  /// their number. Documents are written as they are read, so collections
+  /// larger than the memory can be exported.
+  pub async fn write<W>(&self, writer: W) -> Result<u64, Error>
+  where
+    W: AsyncWrite + Unpin,
+  {
+    let mut projection = Document::new();
+    if !self.fields.is_empty() {
+      projection.insert("_id", 0_i32);
+      for field in &self.fields {
+        projection.insert(field.as_str(), 1_i32);
+      }
+    }
+    let options = FindOptions::builder()
+      .projection((!projection.is_empty()).then_some(projection))
+      .sort(doc! { "_id": 1_i32 })
+      .build();
+
+    let database = CONNECTION.get().await;
+    let mut cursor = database
+      .collection::<Document>(&self.collection)
+      .find(self.filter.clone(), options)
+      .await?;
+
+    let mut writer = BufWriter::new(writer);
+    let mut exported = 0_u64;
+    if self.format == Format::Json {
+      write_all(&mut writer, b"[").await?;
+    }
+    while let Some(document) = cursor.try_next().await? {
+      let json = Bson::Document(document).into_relaxed_extjson();
+      let line = serde_json::to_vec(&json)
+        .map_err(|err| Error::General(format!("Failed to serialize document: {}", err)))?;
+
+      match self.format {
+        Format::Ndjson => {
+          write_all(&mut writer, &line).await?;
+          write_all(&mut writer, b"\n").await?;
+        }
+        Format::Json => {
+          let separator: &[u8] = if exported == 0 { b"\n" } else { b",\n" };
+          write_all(&mut writer, separator).await?;
+          write_all(&mut writer, &line).await?;
+        }
+      }
+      exported += 1;
+    }
+    if self.format == Format::Json {
+      write_all(&mut writer, b"\n]\n").await?;
+    }
+    writer
+      .flush()
+      .await
+      .map_err(|err| Error::General(format!("Failed to write export: {}", err)))?;
+
+    Ok(exported)
+  }
+}
+
+fn collection_name(name: &str) -> String {
+  COLLECTION_ALIASES
+    .iter()
+    .find(|(alias, _)| *alias == name)
+    .map_or(name, |(_, collection)| collection)
+    .to_owned()
+}
+
+/// Filters are MongoDB queries in extended JSON, e.g.
+/// `{"source": "arkham"}` or `{"_id": {"$gt": {"$oid": "..."}}}`.
+fn parse_filter(filter: &str) -> Option<Document> {
+  let value = serde_json::from_str::<serde_json::Value>(filter).ok()?;
+  match Bson::try_from(value).ok()? {
+    Bson::Document(filter) => Some(filter),
+    _ => None,
+  }
+}
+
+async fn write_all<W>(writer: &mut W, bytes: &[u8]) -> Result<(), Error>
+where
+  W: AsyncWrite + Unpin,
+{
+  writer
+    .write_all(bytes)
+    .await
+    .map_err(|err| Error::General(format!("Failed to write export: {}", err)))
+}
@@ -0,0 +1,193 @@
+use axum::{async_trait, extract::FromRequestParts, http::request::Parts};
+use bson::doc;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::marker::PhantomData;
+use std::time::Duration;
+use tracing::debug;
+use wither::bson::oid::ObjectId;
+
+use crate::errors::Error;
+use crate::models::feature_flag::{FeatureFlag, PublicFeatureFlag};
+use crate::settings::{self, FeatureFlagDefault};
+use crate::state::AppState;
+use crate::utils::cache::{self, TtlCache};
+use crate::utils::models::ModelExt;
+use crate::utils::token::TokenUser;
+
+/// Feature gated by a flag, see `RequireFeature`.
+pub trait Feature: Send + Sync + 'static {
+  const NAME: &'static str;
+}
+
+/// `GET /risk/:address`.
+pub struct RiskScoreFeature;
+
+impl Feature for RiskScoreFeature {
+  const NAME: &'static str = "risk_score";
+}
+
+/// `GET /arkham/:address/graph`.
+pub struct AddressGraphFeature;
+
+impl Feature for AddressGraphFeature {
+  const NAME: &'static str = "address_graph";
+}
+
+/// State of a flag, from Mongo or its configured default.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FlagState {
+  pub enabled: bool,
+  pub rollout_percent: u8,
+  #[serde(default)]
+  pub users: Vec<ObjectId>,
+}
+
+impl FlagState {
+  /// Whether the feature is enabled for the user. Partial rollouts only
+  /// include authenticated users.
+  pub fn is_enabled_for(&self, name: &str, user: Option<&ObjectId>) -> bool {
+    if !self.enabled {
+      return false;
+    }
+    if self.rollout_percent >= 100 {
+      return true;
+    }
+
+    match user {
+      Some(user) => self.users.contains(user) || rollout_bucket(name, user) < self.rollout_percent,
+      None => false,
+    }
+  }
+}
+
+impl From<&FeatureFlagDefault> for FlagState {
+  fn from(flag: &FeatureFlagDefault) -> Self {
+    Self {
+      enabled: flag.enabled,
+      rollout_percent: flag.rollout_percent,
+      users: vec![],
+    }
+  }
+}
+
+impl From<FeatureFlag> for FlagState {
+  fn from(flag: FeatureFlag) -> Self {
+    Self {
+      enabled: flag.enabled,
+      rollout_percent: flag.rollout_percent,
+      users: flag.users,
+    }
+  }
+}
+
+/// Bucket of a user in the rollouts of a flag, between 0 and 99. Users keep
+/// their bucket as a rollout grows, and hashing the flag name along keeps the
+/// rollouts of different flags independent.
+pub fn rollout_bucket(name: &str, user: &ObjectId) -> u8 {
+  let digest = Sha256::digest(format!("{}:{}", name, user.to_hex()));
+  (u16::from_be_bytes([digest[0], digest[1]]) % 100) as u8
+}
+
+/// Flags of the features enabled gradually, stored in Mongo by admins and
+/// defaulting to the configuration. Flags are cached, and dropped from the
+/// cache when changed so a feature can be turned off at once.
+pub struct FeatureFlags {
+  defaults: HashMap<String, FeatureFlagDefault>,
+  cache: TtlCache<FlagState>,
+}
+
+impl FeatureFlags {
+  pub fn new(settings: &settings::FeatureFlags, cache_settings: &settings::Cache) -> Self {
+    Self {
+      defaults: settings.defaults.clone(),
+      cache: TtlCache::new(
+        cache::backend(cache_settings, "feature_flags"),
+        Duration::from_secs(settings.cache_ttl_secs),
+      ),
+    }
+  }
+
+  /// State of a flag. Unknown flags are disabled.
+  pub async fn get(&self, name: &str) -> Result<FlagState, Error> {
+    if let Some(entry) = self.cache.get(name).await {
+      return Ok(entry.value);
+    }
+
+    let state = match FeatureFlag::find_one(doc! { "name": name }, None).await? {
+      Some(flag) => FlagState::from(flag),
+      None => self
+        .defaults
+        .get(name)
+        .map(FlagState::from)
+        .unwrap_or(FlagState {
+          enabled: false,
+          rollout_percent: 0,
+          users: vec![],
+        }),
+    };
+
+    Ok(self.cache.insert(name, state).await.value)
+  }
+
+  pub async fn is_enabled(&self, name: &str, user: Option<&ObjectId>) -> Result<bool, Error> {
+    let state = self.get(name).await?;
+    Ok(state.is_enabled_for(name, user))
+  }
+
+  /// Configured and stored flags, by name.
+  pub async fn list(&self) -> Result<Vec<PublicFeatureFlag>, Error> {
+    let mut flags: BTreeMap<String, PublicFeatureFlag> = self
+      .defaults
+      .iter()
+      .map(|(name, flag)| {
+        let flag = PublicFeatureFlag {
+          name: name.clone(),
+          enabled: flag.enabled,
+          rollout_percent: flag.rollout_percent,
+          users: vec![],
+        };
+        (name.clone(), flag)
+      })
+      .collect();
+
+    for flag in FeatureFlag::find(doc! {}, None).await? {
+      flags.insert(flag.name.clone(), PublicFeatureFlag::from(flag));
+    }
+
+    Ok(flags.into_values().collect())
+  }
+
+  /// Drops a changed flag from the cache.
+  pub async fn invalidate(&self, name: &str) {
+    self.cache.invalidate(name).await;
+  }
+}
+
+/// Rejects the requests to a feature disabled for the user with a 404, as if
+/// the route didn't exist. The user is the one authenticated by a previous
+/// `TokenUser` extractor, if any.
+pub struct RequireFeature<F>(PhantomData<F>);
+
+#[async_trait]
+impl<F> FromRequestParts<AppState> for RequireFeature<F>
+where
+  F: Feature,
+{
+  type Rejection = Error;
+
+  async fn from_request_parts(
+    parts: &mut Parts,
+    state: &AppState,
+  ) -> Result<Self, Self::Rejection> {
+    let user = parts.extensions.get::<TokenUser>().map(|user| user.id);
+    let flags = &state.feature_flags;
+    if !flags.is_enabled(F::NAME, user.as_ref()).await? {
+      debug!("Feature {} disabled, returning 404 status code", F::NAME);
+      return Err(Error::not_found());
+    }
+
+    Ok(Self(PhantomData))
+  }
+}
@@ -0,0 +1,157 @@
+use futures::future;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, warn};
+use utoipa::ToSchema;
+
+use crate::errors::{Error, ErrorResponse};
+use crate::services::rpc::RpcClient;
+use crate::utils::cache::{Cache, TtlCache};
+
+// Number of recent blocks the priority fee suggestions are based on.
+const FEE_HISTORY_BLOCKS: u64 = 20;
+
+// Percentiles of the priority fees paid in each block, for slow, standard
+// and fast inclusion.
+const REWARD_PERCENTILES: [u8; 3] = [25, 50, 75];
+
+const WEI_PER_GWEI: f64 = 1_000_000_000.0;
+
+/// Suggests gas fees for every chain with an RPC provider, from the fee
+/// history of their recent blocks. Suggestions are cached for a short time
+/// since they change with every block.
+pub struct GasOracle {
+  rpc: Arc<RpcClient>,
+  cache: TtlCache<ChainGas>,
+}
+
+impl GasOracle {
+  pub fn new(rpc: Arc<RpcClient>, cache: Arc<dyn Cache>, ttl: Duration) -> Self {
+    Self {
+      rpc,
+      cache: TtlCache::new(cache, ttl),
+    }
+  }
+
+  /// Fee suggestions of every chain, fetched at the same time. Chains that
+  /// fail are reported with their error, so one unavailable provider doesn't
+  /// hide the others.
+  pub async fn fetch_gas(&self) -> Vec<ChainGas> {
+    let lookups = self.rpc.chains().into_iter().map(|chain| async move {
+      if let Some(entry) = self.cache.get(&chain).await {
+        debug!("Returning cached {} gas fees", chain);
+        return entry.value;
+      }
+
+      match self.fetch_chain(&chain).await {
+        Ok(gas) => {
+          self.cache.insert(chain.as_str(), gas.clone()).await;
+          gas
+        }
+        Err(err) => {
+          warn!("Failed to fetch the {} gas fees: {}", chain, err);
+          ChainGas::failed(&chain, &err)
+        }
+      }
+    });
+
+    future::join_all(lookups).await
+  }
+
+  async fn fetch_chain(&self, chain: &str) -> Result<ChainGas, Error> {
+    let params = json!([
+      format!("0x{:x}", FEE_HISTORY_BLOCKS),
+      "latest",
+      REWARD_PERCENTILES
+    ]);
+    let history = self
+      .rpc
+      .request::<FeeHistory>(chain, "eth_feeHistory", params)
+      .await?;
+
+    // The last base fee is the one of the next block.
+    let base_fee = match history.base_fee_per_gas.last() {
+      Some(base_fee) => parse_quantity(base_fee)?,
+      None => {
+        return Err(Error::UpstreamInvalidResponse(
+          "empty fee history".to_owned(),
+        ))
+      }
+    };
+
+    let mut priority_fees = [0_f64; REWARD_PERCENTILES.len()];
+    for (index, priority_fee) in priority_fees.iter_mut().enumerate() {
+      let mut rewards = history
+        .reward
+        .iter()
+        .filter_map(|rewards| rewards.get(index))
+        .map(|reward| parse_quantity(reward))
+        .collect::<Result<Vec<f64>, Error>>()?;
+      rewards.sort_by(f64::total_cmp);
+      // Median over the blocks, so a single busy block doesn't skew it.
+      *priority_fee = rewards.get(rewards.len() / 2).copied().unwrap_or(0.0);
+    }
+    let [slow, standard, fast] = priority_fees;
+
+    Ok(ChainGas {
+      chain: chain.to_owned(),
+      base_fee_gwei: Some(base_fee),
+      priority_fee_gwei: Some(PriorityFees {
+        slow,
+        standard,
+        fast,
+      }),
+      error: None,
+    })
+  }
+}
+
+/// Gas fee suggestions of a chain, in gwei.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ChainGas {
+  pub chain: String,
+  // Base fee of the next block.
+  pub base_fee_gwei: Option<f64>,
+  pub priority_fee_gwei: Option<PriorityFees>,
+  // Set when the fees of the chain couldn't be fetched.
+  pub error: Option<ErrorResponse>,
+}
+
+impl ChainGas {
+  fn failed(chain: &str, err: &Error) -> Self {
+    Self {
+      chain: chain.to_owned(),
+      base_fee_gwei: None,
+      priority_fee_gwei: None,
+      error: Some(ErrorResponse::from(err)),
+    }
+  }
+}
+
+/// Priority fees paid by the 25th, 50th and 75th percentiles of the recent
+/// transactions.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PriorityFees {
+  pub slow: f64,
+  pub standard: f64,
+  pub fast: f64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FeeHistory {
+  #[serde(default)]
+  base_fee_per_gas: Vec<String>,
+  // Chains without priority fees omit the rewards.
+  #[serde(default)]
+  reward: Vec<Vec<String>>,
+}
+
+/// Parses a hex quantity in wei, to gwei.
+fn parse_quantity(quantity: &str) -> Result<f64, Error> {
+  u128::from_str_radix(quantity.trim_start_matches("0x"), 16)
+    .map(|wei| wei as f64 / WEI_PER_GWEI)
+    .map_err(|_| Error::UpstreamInvalidResponse(format!("invalid quantity {}", quantity)))
+}
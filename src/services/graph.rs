@@ -0,0 +1,362 @@
+use futures::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use tracing::{info, warn};
+use utoipa::ToSchema;
+use wither::bson::{doc, Bson};
+use wither::mongodb::options::FindOptions;
+
+use crate::errors::Error;
+use crate::models::address_label::AddressLabel;
+use crate::routes::arkham::ArkhamEntityDetail;
+use crate::services::label_dataset;
+use crate::state::AppState;
+use crate::utils::address::to_checksum_address;
+use crate::utils::models::ModelExt;
+
+// Number of addresses or entities looked up at the same time.
+const LOOKUP_CONCURRENCY: usize = 5;
+
+/// Expands an address into the graph of its related addresses: the other
+/// addresses of its Arkham entity, and the addresses sharing one of its
+/// public labels. Each level of `depth` expands the addresses found by the
+/// previous one, until the graph has `arkham.graph_max_nodes` nodes.
+pub async fn build_graph(
+  state: &AppState,
+  address: &str,
+  depth: usize,
+) -> Result<AddressGraph, Error> {
+  info!("Building the graph of address: {}", address);
+  let max_nodes = state.settings.arkham.graph_max_nodes;
+  let mut graph = GraphBuilder::new(max_nodes);
+  graph.add_address(address, 0);
+
+  let mut frontier = vec![address.to_owned()];
+  let mut expanded_entities = HashSet::new();
+  for level in 1..=depth {
+    if frontier.is_empty() {
+      break;
+    }
+
+    let expansions = frontier
+      .iter()
+      .map(|address| expand_address(state, address, max_nodes));
+    let expansions = stream::iter(expansions)
+      .buffered(LOOKUP_CONCURRENCY)
+      .collect::<Vec<Result<Expansion, Error>>>()
+      .await;
+
+    let mut next = Vec::new();
+    let mut entities = Vec::new();
+    for (address, expansion) in frontier.iter().zip(expansions) {
+      let expansion = match expansion {
+        Ok(expansion) => expansion,
+        // Without the root there is no graph, related addresses are only
+        // left unexpanded.
+        Err(err) if level == 1 => return Err(err),
+        Err(err) => {
+          warn!("Failed to expand address {}: {}", address, err);
+          continue;
+        }
+      };
+
+      graph.set_label(address, expansion.label);
+      if let Some((entity_id, entity_name)) = expansion.entity {
+        if graph.add_entity(&entity_id, entity_name, level) {
+          graph.add_edge(
+            &address_id(address),
+            &entity_node_id(&entity_id),
+            Relation::Entity,
+            None,
+          );
+        }
+        if expanded_entities.insert(entity_id.clone()) {
+          entities.push(entity_id);
+        }
+      }
+
+      for label in expansion.co_labeled {
+        let is_new = !graph.has_address(&label.eth_address);
+        if graph.add_address(&label.eth_address, level) {
+          graph.add_edge(
+            &address_id(address),
+            &address_id(&label.eth_address),
+            Relation::Label,
+            Some(label.name),
+          );
+          if is_new {
+            next.push(label.eth_address);
+          }
+        }
+      }
+    }
+
+    let details = entities
+      .iter()
+      .map(|entity_id| fetch_entity(state, entity_id));
+    let details = stream::iter(details)
+      .buffered(LOOKUP_CONCURRENCY)
+      .collect::<Vec<Result<ArkhamEntityDetail, Error>>>()
+      .await;
+    for (entity_id, detail) in entities.iter().zip(details) {
+      let detail = match detail {
+        Ok(detail) => detail,
+        Err(err) => {
+          warn!("Failed to fetch Arkham entity {}: {}", entity_id, err);
+          continue;
+        }
+      };
+
+      for member in detail.addresses() {
+        let member = member.to_lowercase();
+        let is_new = !graph.has_address(&member);
+        if graph.add_address(&member, level) {
+          graph.add_edge(
+            &entity_node_id(entity_id),
+            &address_id(&member),
+            Relation::Entity,
+            None,
+          );
+          if is_new {
+            next.push(member);
+          }
+        }
+      }
+    }
+
+    frontier = next;
+  }
+
+  Ok(graph.build(address, depth))
+}
+
+/// Entity and labels of an address, with the addresses sharing its labels.
+struct Expansion {
+  // Name of the address, from its labels.
+  label: Option<String>,
+  // Arkham entity id and name.
+  entity: Option<(String, Option<String>)>,
+  co_labeled: Vec<AddressLabel>,
+}
+
+async fn expand_address(
+  state: &AppState,
+  address: &str,
+  max_nodes: usize,
+) -> Result<Expansion, Error> {
+  let arkham = match state.address_intelligence.lookup_address(address).await {
+    Ok(arkham) => Some(arkham),
+    // Addresses unknown to Arkham have no entity.
+    Err(Error::NotFound(_)) => None,
+    Err(err) => return Err(err),
+  };
+  let chains = arkham
+    .as_ref()
+    .map(|arkham| arkham.chains())
+    .unwrap_or_default();
+  let entity = chains.iter().find_map(|(_, data)| {
+    let entity_id = data.entity_id()?;
+    Some((entity_id.to_owned(), data.entity_name().map(str::to_owned)))
+  });
+  let arkham_label = chains.iter().find_map(|(_, data)| data.label_name());
+
+  let datasets = label_dataset::visible_datasets().await?;
+  let query = doc! {
+    "eth_address": address,
+    "organization": Bson::Null,
+    "dataset": datasets.clone(),
+  };
+  let labels = AddressLabel::find(query, None).await?;
+  let names = labels
+    .iter()
+    .map(|label| label.name.clone())
+    .collect::<Vec<String>>();
+  let co_labeled = if names.is_empty() {
+    Vec::new()
+  } else {
+    let query = doc! {
+      "name": { "$in": names.clone() },
+      "organization": Bson::Null,
+      "dataset": datasets,
+      "eth_address": { "$ne": address },
+    };
+    let options = FindOptions::builder()
+      .sort(doc! { "created_at": 1 })
+      .limit(max_nodes as i64)
+      .build();
+    AddressLabel::find(query, options).await?
+  };
+
+  Ok(Expansion {
+    label: names
+      .into_iter()
+      .next()
+      .or_else(|| arkham_label.map(str::to_owned)),
+    entity,
+    co_labeled,
+  })
+}
+
+async fn fetch_entity(state: &AppState, entity_id: &str) -> Result<ArkhamEntityDetail, Error> {
+  if let Some(entry) = state.entity_cache.get(entity_id).await {
+    return Ok(entry.value);
+  }
+
+  let entity = state.address_intelligence.lookup_entity(entity_id).await?;
+  Ok(state.entity_cache.insert(entity_id, entity).await.value)
+}
+
+fn address_id(address: &str) -> String {
+  to_checksum_address(address)
+}
+
+fn entity_node_id(entity_id: &str) -> String {
+  format!("entity:{}", entity_id)
+}
+
+/// Collects the nodes and edges of a graph, ignoring duplicates and the
+/// nodes over the limit.
+struct GraphBuilder {
+  max_nodes: usize,
+  nodes: Vec<GraphNode>,
+  // Index of the nodes by id.
+  indexes: HashMap<String, usize>,
+  edges: Vec<GraphEdge>,
+  // Edges are undirected, keyed by their sorted node ids.
+  edge_keys: HashSet<(String, String, Relation)>,
+  truncated: bool,
+}
+
+impl GraphBuilder {
+  fn new(max_nodes: usize) -> Self {
+    Self {
+      max_nodes,
+      nodes: Vec::new(),
+      indexes: HashMap::new(),
+      edges: Vec::new(),
+      edge_keys: HashSet::new(),
+      truncated: false,
+    }
+  }
+
+  fn has_address(&self, address: &str) -> bool {
+    self.indexes.contains_key(&address_id(address))
+  }
+
+  /// Whether the node is in the graph, `false` when it is over the limit.
+  fn add_address(&mut self, address: &str, depth: usize) -> bool {
+    self.add_node(GraphNode {
+      id: address_id(address),
+      kind: NodeKind::Address,
+      label: None,
+      depth,
+    })
+  }
+
+  fn add_entity(&mut self, entity_id: &str, name: Option<String>, depth: usize) -> bool {
+    self.add_node(GraphNode {
+      id: entity_node_id(entity_id),
+      kind: NodeKind::Entity,
+      label: name,
+      depth,
+    })
+  }
+
+  fn add_node(&mut self, node: GraphNode) -> bool {
+    if self.indexes.contains_key(&node.id) {
+      return true;
+    }
+    if self.nodes.len() >= self.max_nodes {
+      self.truncated = true;
+      return false;
+    }
+
+    self.indexes.insert(node.id.clone(), self.nodes.len());
+    self.nodes.push(node);
+    true
+  }
+
+  fn set_label(&mut self, address: &str, label: Option<String>) {
+    if let Some(index) = self.indexes.get(&address_id(address)) {
+      if label.is_some() {
+        self.nodes[*index].label = label;
+      }
+    }
+  }
+
+  fn add_edge(&mut self, source: &str, target: &str, relation: Relation, label: Option<String>) {
+    let key = if source < target {
+      (source.to_owned(), target.to_owned(), relation)
+    } else {
+      (target.to_owned(), source.to_owned(), relation)
+    };
+    if !self.edge_keys.insert(key) {
+      return;
+    }
+
+    self.edges.push(GraphEdge {
+      source: source.to_owned(),
+      target: target.to_owned(),
+      relation,
+      label,
+    });
+  }
+
+  fn build(self, address: &str, depth: usize) -> AddressGraph {
+    AddressGraph {
+      address: address_id(address),
+      depth,
+      nodes: self.nodes,
+      edges: self.edges,
+      truncated: self.truncated,
+    }
+  }
+}
+
+/// Graph of the addresses related to an address, with its entities.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct AddressGraph {
+  pub address: String,
+  pub depth: usize,
+  // Nodes in the order they were found, the address first.
+  pub nodes: Vec<GraphNode>,
+  pub edges: Vec<GraphEdge>,
+  // Whether nodes were left out to stay under the node limit.
+  pub truncated: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct GraphNode {
+  // Checksummed address, or `entity:` followed by the Arkham entity id.
+  pub id: String,
+  pub kind: NodeKind,
+  // Label of an address, or name of an entity.
+  pub label: Option<String>,
+  // Number of expansions between the address and the node.
+  pub depth: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum NodeKind {
+  Address,
+  Entity,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct GraphEdge {
+  pub source: String,
+  pub target: String,
+  pub relation: Relation,
+  // Shared label name of `label` edges.
+  pub label: Option<String>,
+}
+
+/// `entity` links an address to its Arkham entity, `label` two addresses
+/// sharing a public label.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Relation {
+  Entity,
+  Label,
+}
@@ -0,0 +1,184 @@
+use bytes::{Bytes, BytesMut};
+use futures::stream::{BoxStream, Stream, StreamExt, TryStreamExt};
+use wither::bson::spec::BinarySubtype;
+use wither::bson::{doc, oid::ObjectId, Binary, Document};
+use wither::mongodb::options::{FindOptions, IndexOptions};
+use wither::mongodb::{Collection, IndexModel};
+
+use crate::database::CONNECTION;
+use crate::errors::Error;
+use crate::utils::date;
+
+// Files are stored in the `attachments.files` and `attachments.chunks`
+// collections, following the GridFS specification so they can also be read
+// with the MongoDB tools, e.g. `mongofiles --prefix attachments`.
+const BUCKET: &str = "attachments";
+
+// Size of the chunks files are split into, the GridFS default.
+const CHUNK_SIZE: usize = 255 * 1024;
+
+/// File stored with `upload`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredFile {
+  pub id: ObjectId,
+  pub length: u64,
+}
+
+/// Creates the indexes the GridFS specification requires, so chunks are
+/// read in order without sorting.
+pub async fn create_indexes() -> Result<(), Error> {
+  let chunks_index = IndexModel::builder()
+    .keys(doc! { "files_id": 1_i32, "n": 1_i32 })
+    .options(IndexOptions::builder().unique(true).build())
+    .build();
+  chunks().await.create_index(chunks_index, None).await?;
+
+  let files_index = IndexModel::builder()
+    .keys(doc! { "filename": 1_i32, "uploadDate": 1_i32 })
+    .build();
+  files().await.create_index(files_index, None).await?;
+
+  Ok(())
+}
+
+/// Stores the content of `body` as it is read. Uploads going over
+/// `max_bytes` are removed and rejected with a 413.
+pub async fn upload<S>(
+  filename: &str,
+  content_type: &str,
+  body: S,
+  max_bytes: usize,
+) -> Result<StoredFile, Error>
+where
+  S: Stream<Item = Result<Bytes, Error>>,
+{
+  let id = ObjectId::new();
+  let mut body = Box::pin(body);
+  let result = async {
+    let mut buffer = BytesMut::with_capacity(CHUNK_SIZE);
+    let mut length = 0_usize;
+    let mut n = 0_i32;
+
+    while let Some(bytes) = body.try_next().await? {
+      length += bytes.len();
+      if length > max_bytes {
+        return Err(Error::PayloadTooLarge(max_bytes));
+      }
+
+      buffer.extend_from_slice(&bytes);
+      while buffer.len() >= CHUNK_SIZE {
+        let chunk = buffer.split_to(CHUNK_SIZE);
+        insert_chunk(id, n, &chunk).await?;
+        n += 1;
+      }
+    }
+    if !buffer.is_empty() {
+      insert_chunk(id, n, &buffer).await?;
+    }
+
+    files()
+      .await
+      .insert_one(
+        doc! {
+          "_id": id,
+          "length": length as i64,
+          "chunkSize": CHUNK_SIZE as i32,
+          "uploadDate": date::now(),
+          "filename": filename,
+          "metadata": { "contentType": content_type },
+        },
+        None,
+      )
+      .await?;
+
+    Ok(length as u64)
+  }
+  .await;
+
+  match result {
+    Ok(length) => Ok(StoredFile { id, length }),
+    Err(err) => {
+      // Chunks of a failed upload are never read, don't keep them around.
+      chunks()
+        .await
+        .delete_many(doc! { "files_id": id }, None)
+        .await?;
+      Err(err)
+    }
+  }
+}
+
+/// Content of a stored file, read chunk by chunk. `None` when there is no
+/// such file.
+pub async fn download(
+  id: ObjectId,
+) -> Result<Option<BoxStream<'static, Result<Bytes, Error>>>, Error> {
+  let file = files().await.find_one(doc! { "_id": id }, None).await?;
+  if file.is_none() {
+    return Ok(None);
+  }
+
+  let options = FindOptions::builder().sort(doc! { "n": 1_i32 }).build();
+  let cursor = chunks()
+    .await
+    .find(doc! { "files_id": id }, options)
+    .await?;
+
+  let content = cursor
+    .map(|chunk| {
+      let chunk = chunk?;
+      let data = chunk
+        .get_binary_generic("data")
+        .map_err(|_| Error::General("Invalid GridFS chunk".to_owned()))?;
+      Ok(Bytes::copy_from_slice(data))
+    })
+    .boxed();
+
+  Ok(Some(content))
+}
+
+/// Removes a stored file. Removing a missing file does nothing.
+pub async fn delete(id: ObjectId) -> Result<(), Error> {
+  delete_many(vec![id]).await
+}
+
+/// Removes several stored files, e.g. the attachments of deleted resources.
+pub async fn delete_many(ids: Vec<ObjectId>) -> Result<(), Error> {
+  if ids.is_empty() {
+    return Ok(());
+  }
+
+  files()
+    .await
+    .delete_many(doc! { "_id": { "$in": &ids } }, None)
+    .await?;
+  chunks()
+    .await
+    .delete_many(doc! { "files_id": { "$in": &ids } }, None)
+    .await?;
+
+  Ok(())
+}
+
+async fn insert_chunk(id: ObjectId, n: i32, data: &[u8]) -> Result<(), Error> {
+  let data = Binary {
+    subtype: BinarySubtype::Generic,
+    bytes: data.to_vec(),
+  };
+  chunks()
+    .await
+    .insert_one(doc! { "files_id": id, "n": n, "data": data }, None)
+    .await?;
+
+  Ok(())
+}
+
+async fn files() -> Collection<Document> {
+  let database = CONNECTION.get().await;
+  database.collection(&format!("{}.files", BUCKET))
+}
+
+async fn chunks() -> Collection<Document> {
+  let database = CONNECTION.get().await;
+  database.collection(&format!("{}.chunks", BUCKET))
+}
@@ -0,0 +1,85 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeSet;
+use utoipa::ToSchema;
+use wither::bson::doc;
+use wither::mongodb::options::FindOneOptions;
+
+use crate::errors::Error;
+use crate::models::address_snapshot::AddressSnapshot;
+use crate::routes::arkham::ArkhamResponse;
+use crate::utils::date::Date;
+use crate::utils::models::ModelExt;
+
+/// Field of the Arkham data of an address that differs between two
+/// snapshots. Missing fields are `null`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct FieldChange {
+  // Dot separated path of the field, e.g. `ethereum.balance`.
+  pub path: String,
+  #[schema(value_type = Object)]
+  pub before: Value,
+  #[schema(value_type = Object)]
+  pub after: Value,
+}
+
+/// Latest snapshot of the address recorded at or before the date, which is
+/// what the watcher knew of it back then.
+pub async fn snapshot_at(address: &str, date: Date) -> Result<Option<AddressSnapshot>, Error> {
+  let options = FindOneOptions::builder()
+    .sort(doc! { "created_at": -1_i32, "_id": -1_i32 })
+    .build();
+  AddressSnapshot::find_one(
+    doc! { "address": address, "created_at": { "$lte": date } },
+    options,
+  )
+  .await
+}
+
+/// Fields that changed from one Arkham response to the other, in field name
+/// order. Nested objects are compared field by field, arrays as a whole.
+pub fn diff(before: Option<&ArkhamResponse>, after: &ArkhamResponse) -> Vec<FieldChange> {
+  let before = before.map_or(Value::Null, to_value);
+  let mut changes = Vec::new();
+  diff_values("", &before, &to_value(after), &mut changes);
+  changes
+}
+
+fn diff_values(path: &str, before: &Value, after: &Value, changes: &mut Vec<FieldChange>) {
+  if before == after {
+    return;
+  }
+
+  let empty = Map::new();
+  let (before_fields, after_fields) = match (before, after) {
+    (Value::Object(before), Value::Object(after)) => (before, after),
+    (Value::Object(before), Value::Null) => (before, &empty),
+    (Value::Null, Value::Object(after)) => (&empty, after),
+    _ => {
+      changes.push(FieldChange {
+        path: path.to_owned(),
+        before: before.clone(),
+        after: after.clone(),
+      });
+      return;
+    }
+  };
+
+  let names = before_fields.keys().chain(after_fields.keys());
+  for name in names.collect::<BTreeSet<&String>>() {
+    let field_path = match path {
+      "" => name.clone(),
+      path => format!("{}.{}", path, name),
+    };
+    diff_values(
+      &field_path,
+      before_fields.get(name).unwrap_or(&Value::Null),
+      after_fields.get(name).unwrap_or(&Value::Null),
+      changes,
+    );
+  }
+}
+
+fn to_value(data: &ArkhamResponse) -> Value {
+  serde_json::to_value(data).unwrap_or(Value::Null)
+}
@@ -0,0 +1,225 @@
+use futures::future;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio::time::sleep;
+use tracing::{debug, error, info, warn};
+use wither::bson::{self, doc, oid::ObjectId, Document};
+
+use crate::errors::Error;
+use crate::models::job::{Job, JobKind};
+use crate::routes::label;
+use crate::services::data_export::{self, DataExport};
+use crate::services::{account, graph};
+use crate::settings;
+use crate::state::AppState;
+use crate::utils::date;
+use crate::utils::models::ModelExt;
+use crate::utils::retry::RetryPolicy;
+use crate::utils::shutdown::ShutdownSignal;
+
+/// Payload of a `label_import` job.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LabelImport {
+  // Newline-delimited JSON labels, in the format of `POST /labels/import`.
+  pub body: String,
+}
+
+/// Payload of a `graph_expansion` job.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GraphExpansion {
+  pub address: String,
+  pub depth: usize,
+}
+
+/// Queues a job for the workers, due right away.
+pub async fn enqueue<P: Serialize>(
+  user: ObjectId,
+  kind: JobKind,
+  payload: &P,
+) -> Result<Job, Error> {
+  let payload = bson::to_document(payload).map_err(|err| Error::General(err.to_string()))?;
+  let job = Job::create(Job::new(user, kind, payload)).await?;
+
+  debug!("Queued {:?} job {}", kind, job.id.unwrap());
+  Ok(job)
+}
+
+/// Starts `job_queue.concurrency` workers, each running the due jobs one at
+/// a time and polling every `job_queue.poll_interval_ms` once none are left.
+/// On shutdown, the jobs in progress are finished before stopping.
+pub fn spawn(state: AppState, shutdown: ShutdownSignal) -> JoinHandle<()> {
+  let settings = &state.settings.job_queue;
+  info!(
+    "Starting {} job workers, polling every {}ms",
+    settings.concurrency, settings.poll_interval_ms
+  );
+
+  tokio::spawn(async move {
+    let workers = (0..state.settings.job_queue.concurrency).map(|_| work(&state, shutdown.clone()));
+    future::join_all(workers).await;
+
+    info!("Job workers stopped");
+  })
+}
+
+async fn work(state: &AppState, mut shutdown: ShutdownSignal) {
+  let period = Duration::from_millis(state.settings.job_queue.poll_interval_ms);
+
+  loop {
+    // Keeps going without waiting while jobs are due.
+    let delay = match run_next(state).await {
+      Ok(true) => Duration::ZERO,
+      Ok(false) => period,
+      Err(err) => {
+        error!("Failed to run queued job: {}", err);
+        period
+      }
+    };
+
+    tokio::select! {
+      biased;
+      _ = shutdown.requested() => break,
+      _ = sleep(delay) => {}
+    }
+  }
+}
+
+/// Claims a due job and runs it once. Returns whether a job was due.
+pub async fn run_next(state: &AppState) -> Result<bool, Error> {
+  let job = match claim(&state.settings.job_queue).await? {
+    Some(job) => job,
+    None => return Ok(false),
+  };
+
+  let id = job.id.unwrap();
+  if let Err(err) = attempt(state, job).await {
+    error!("Failed to record job {}: {}", id, err);
+  }
+
+  Ok(true)
+}
+
+/// Leases a due job by pushing back its `run_at`, so other workers don't run
+/// it too. Running jobs whose lease expired, because their server stopped,
+/// are claimed again.
+async fn claim(settings: &settings::JobQueue) -> Result<Option<Job>, Error> {
+  let lease = Duration::from_millis(settings.lease_ms);
+  let now = date::now();
+  Job::find_one_and_update(
+    doc! { "status": { "$in": ["queued", "running"] }, "run_at": { "$lte": now } },
+    doc! {
+      "$set": {
+        "status": "running",
+        "run_at": date::after(lease),
+        "started_at": now,
+        "updated_at": now
+      },
+      "$inc": { "attempts": 1 }
+    },
+  )
+  .await
+}
+
+async fn attempt(state: &AppState, job: Job) -> Result<(), Error> {
+  let id = job.id.unwrap();
+  let settings = &state.settings.job_queue;
+  let result = match job.kind {
+    JobKind::LabelImport => import_labels(&job.payload).await,
+    JobKind::GraphExpansion => expand_graph(state, &job.payload).await,
+    JobKind::AccountDeletion => delete_account(job.user).await,
+    JobKind::DataExport => export_data(job.user, &job.payload).await,
+  };
+
+  let now = date::now();
+  match result {
+    Ok(result) => {
+      debug!("Job {} succeeded", id);
+      Job::update_one(
+        doc! { "_id": id },
+        doc! { "$set": {
+          "status": "succeeded",
+          "result": result,
+          "finished_at": now,
+          "updated_at": now
+        } },
+        None,
+      )
+      .await?;
+    }
+    // Client errors, e.g. an invalid payload, would fail again.
+    Err(err) if job.attempts >= settings.max_attempts || !err.status_code().is_server_error() => {
+      warn!("Job {} failed permanently: {}", id, err);
+      Job::update_one(
+        doc! { "_id": id },
+        doc! { "$set": {
+          "status": "failed",
+          "last_error": err.to_string(),
+          "finished_at": now,
+          "updated_at": now
+        } },
+        None,
+      )
+      .await?;
+    }
+    Err(err) => {
+      let policy = RetryPolicy {
+        max_attempts: settings.max_attempts,
+        base_delay: Duration::from_millis(settings.retry_base_delay_ms),
+        max_delay: Duration::from_millis(settings.retry_max_delay_ms),
+      };
+      let delay = policy.delay(job.attempts);
+      debug!("Job {} failed, retrying in {:?}: {}", id, delay, err);
+
+      Job::update_one(
+        doc! { "_id": id },
+        doc! { "$set": {
+          "status": "queued",
+          "run_at": date::after(delay),
+          "last_error": err.to_string(),
+          "updated_at": now
+        } },
+        None,
+      )
+      .await?;
+    }
+  }
+
+  Ok(())
+}
+
+async fn import_labels(payload: &Document) -> Result<Document, Error> {
+  let payload = parse_payload::<LabelImport>(payload)?;
+  let summary = label::import_ndjson(payload.body.as_bytes(), None).await?;
+
+  to_result(&summary)
+}
+
+async fn expand_graph(state: &AppState, payload: &Document) -> Result<Document, Error> {
+  let payload = parse_payload::<GraphExpansion>(payload)?;
+  let graph = graph::build_graph(state, &payload.address, payload.depth).await?;
+
+  to_result(&graph)
+}
+
+async fn delete_account(user: ObjectId) -> Result<Document, Error> {
+  let deleted = account::delete_user(user).await?;
+
+  Ok(doc! { "deleted": deleted })
+}
+
+async fn export_data(user: ObjectId, payload: &Document) -> Result<Document, Error> {
+  let payload = parse_payload::<DataExport>(payload)?;
+  let archive = data_export::create_archive(user, payload.format).await?;
+
+  to_result(&archive)
+}
+
+fn parse_payload<P: DeserializeOwned>(payload: &Document) -> Result<P, Error> {
+  bson::from_document(payload.clone()).map_err(|err| Error::InvalidPayload(err.to_string()))
+}
+
+fn to_result<R: Serialize>(result: &R) -> Result<Document, Error> {
+  bson::to_document(result).map_err(|err| Error::General(err.to_string()))
+}
@@ -0,0 +1,163 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::{debug, error, info};
+use wither::bson::{doc, oid::ObjectId, Bson, Document};
+use wither::mongodb::options::FindOneOptions;
+
+use crate::errors::Error;
+use crate::models::address_label::AddressLabel;
+use crate::models::label_dataset::LabelDataset;
+use crate::routes::label::{self, ImportSummary};
+use crate::services::label_history;
+use crate::utils::date;
+use crate::utils::models::ModelExt;
+
+/// Outcome of a reload, with the id of the loaded version.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DatasetReload {
+  pub dataset: String,
+  #[serde(flatten)]
+  pub summary: ImportSummary,
+}
+
+/// Replaces the labels of the dataset with the ones read from its source, a
+/// local path or an HTTP(S) or S3 URL.
+///
+/// The labels are loaded as a new version, hidden from queries until it's
+/// fully stored. Activating it is then a single write, so queries see either
+/// the previous labels or the new ones, never a mix of both. The labels of
+/// the versions it replaces are removed afterwards. Labels created through
+/// the API or imported before the first reload are not part of any version
+/// and are kept.
+pub async fn reload(source: &str) -> Result<DatasetReload, Error> {
+  let body = to_ndjson(read_source(source).await?)?;
+
+  let dataset = LabelDataset::create(LabelDataset {
+    id: None,
+    source: source.to_owned(),
+    loaded_at: None,
+    created_at: date::now(),
+  })
+  .await?;
+  let dataset_id = dataset.id.unwrap();
+
+  let summary = match label::import_ndjson(&body, Some(dataset_id)).await {
+    Ok(summary) if summary.inserted > 0 => summary,
+    // An empty dataset is more likely a broken source than the wish to
+    // remove every label.
+    Ok(_) => {
+      discard(dataset_id).await?;
+      return Err(Error::InvalidPayload(
+        "Label dataset has no valid label".to_owned(),
+      ));
+    }
+    Err(err) => {
+      discard(dataset_id).await?;
+      return Err(err);
+    }
+  };
+
+  let previous = active().await?.and_then(|dataset| dataset.id);
+  let loaded_at = date::now();
+  LabelDataset::update_one(
+    doc! { "_id": dataset_id },
+    doc! { "$set": { "loaded_at": loaded_at } },
+    None,
+  )
+  .await?;
+
+  // Recorded before the replaced versions and their labels are removed.
+  // Like the other label changes, a failure doesn't fail the reload.
+  if let Err(err) = label_history::record_dataset_changes(previous, dataset_id, loaded_at).await {
+    error!("Failed to record label dataset changes: {}", err);
+  }
+
+  // Only the versions loaded before this one, a concurrent reload finishing
+  // later replaces this one in turn.
+  let replaced = LabelDataset::find(doc! { "loaded_at": { "$lt": loaded_at } }, None)
+    .await?
+    .into_iter()
+    .filter_map(|dataset| dataset.id)
+    .collect::<Vec<ObjectId>>();
+  for replaced_id in replaced {
+    discard(replaced_id).await?;
+  }
+
+  info!(
+    "Reloaded label dataset {}: {} inserted, {} updated, {} skipped, {} failed",
+    dataset_id, summary.inserted, summary.updated, summary.skipped, summary.failed
+  );
+  Ok(DatasetReload {
+    dataset: dataset_id.to_hex(),
+    summary,
+  })
+}
+
+/// Version whose labels are visible, the last one loaded.
+pub async fn active() -> Result<Option<LabelDataset>, Error> {
+  let options = FindOneOptions::builder()
+    .sort(doc! { "loaded_at": -1 })
+    .build();
+
+  LabelDataset::find_one(doc! { "loaded_at": { "$ne": Bson::Null } }, options).await
+}
+
+/// Filter on the `dataset` of the visible labels: the labels of the active
+/// version and the ones outside of any version.
+pub async fn visible_datasets() -> Result<Document, Error> {
+  let mut datasets = vec![Bson::Null];
+  if let Some(id) = active().await?.and_then(|dataset| dataset.id) {
+    datasets.push(Bson::ObjectId(id));
+  }
+
+  Ok(doc! { "$in": datasets })
+}
+
+/// URL of a remote source, `None` for local paths. S3 objects are fetched
+/// over HTTPS, so private buckets need a presigned URL instead.
+pub fn source_url(source: &str) -> Option<String> {
+  if source.starts_with("http://") || source.starts_with("https://") {
+    return Some(source.to_owned());
+  }
+
+  let (bucket, key) = source.strip_prefix("s3://")?.split_once('/')?;
+  Some(format!("https://{}.s3.amazonaws.com/{}", bucket, key))
+}
+
+async fn read_source(source: &str) -> Result<Vec<u8>, Error> {
+  if let Some(url) = source_url(source) {
+    let body = reqwest::get(url).await?.error_for_status()?.bytes().await?;
+    return Ok(body.to_vec());
+  }
+
+  let path = source.strip_prefix("file://").unwrap_or(source);
+  tokio::fs::read(path)
+    .await
+    .map_err(|err| Error::General(format!("Failed to read label dataset {}: {}", path, err)))
+}
+
+/// Datasets are newline-delimited JSON, in the format of
+/// `POST /labels/import`, or a JSON array of the same labels.
+pub fn to_ndjson(body: Vec<u8>) -> Result<Vec<u8>, Error> {
+  if body.trim_ascii_start().first() != Some(&b'[') {
+    return Ok(body);
+  }
+
+  let labels = serde_json::from_slice::<Vec<Value>>(&body)
+    .map_err(|err| Error::InvalidPayload(format!("Invalid label dataset: {}", err)))?;
+  let lines = labels.iter().map(Value::to_string).collect::<Vec<String>>();
+
+  Ok(lines.join("\n").into_bytes())
+}
+
+/// Removes a version and its labels.
+async fn discard(dataset_id: ObjectId) -> Result<(), Error> {
+  let deleted_count = AddressLabel::delete_many(doc! { "dataset": dataset_id }).await?;
+  LabelDataset::delete_one(doc! { "_id": dataset_id }).await?;
+
+  debug!(
+    "Removed label dataset {} and its {} labels",
+    dataset_id, deleted_count
+  );
+  Ok(())
+}
@@ -0,0 +1,129 @@
+use tracing::error;
+use wither::bson::{doc, oid::ObjectId, Bson, Document};
+use wither::mongodb::options::FindOptions;
+
+use crate::errors::Error;
+use crate::models::address_label::AddressLabel;
+use crate::models::label_change::LabelChange;
+use crate::utils::date::Date;
+use crate::utils::models::ModelExt;
+
+/// Records label changes. Failures are logged without failing the request,
+/// the labels are already changed.
+pub async fn record(changes: Vec<LabelChange>) {
+  if changes.is_empty() {
+    return;
+  }
+
+  if let Err(err) = LabelChange::insert_many(changes).await {
+    error!("Failed to record label changes: {}", err);
+  }
+}
+
+/// Changes of the labels of an address matching the query, e.g. the labels
+/// of the organizations of the user, newest first.
+pub async fn changes(address: &str, mut query: Document) -> Result<Vec<LabelChange>, Error> {
+  query.insert("eth_address", address);
+  let options = FindOptions::builder()
+    .sort(doc! { "changed_at": -1_i32, "_id": -1_i32 })
+    .build();
+
+  LabelChange::find(query, options).await
+}
+
+/// Labels an address had at a time, as the last change of each of its
+/// labels made until then, leaving out the removed ones.
+pub async fn labels_as_of(
+  address: &str,
+  mut query: Document,
+  as_of: Date,
+) -> Result<Vec<LabelChange>, Error> {
+  query.insert("eth_address", address);
+  query.insert("changed_at", doc! { "$lte": as_of });
+  let pipeline = vec![
+    doc! { "$match": query },
+    doc! { "$sort": { "changed_at": -1_i32, "_id": -1_i32 } },
+    doc! { "$group": {
+      "_id": { "source": "$source", "organization": "$organization" },
+      "change": { "$first": "$$ROOT" }
+    } },
+    doc! { "$replaceRoot": { "newRoot": "$change" } },
+    doc! { "$match": { "new_name": { "$ne": Bson::Null } } },
+    doc! { "$sort": { "changed_at": -1_i32, "_id": -1_i32 } },
+  ];
+
+  LabelChange::aggregate::<LabelChange>(pipeline).await
+}
+
+/// Records what a new version of the label dataset changed compared to the
+/// one it replaced: the labels added, renamed and removed, as changed when
+/// the version was activated. Computed by MongoDB, datasets can hold
+/// millions of labels.
+pub async fn record_dataset_changes(
+  previous: Option<ObjectId>,
+  current: ObjectId,
+  changed_at: Date,
+) -> Result<(), Error> {
+  let labels = <AddressLabel as wither::Model>::COLLECTION_NAME;
+  let changes = <LabelChange as wither::Model>::COLLECTION_NAME;
+  // Dataset labels are public, and a new id is given to each change.
+  let change = |old_name: Bson, new_name: Bson| {
+    doc! { "$project": {
+      "_id": 0_i32,
+      "organization": { "$literal": Bson::Null },
+      "eth_address": 1_i32,
+      "source": 1_i32,
+      "old_name": old_name,
+      "new_name": new_name,
+      "changed_at": { "$literal": changed_at },
+    } }
+  };
+  // Label of the other version with the same address and source.
+  let same_label = |dataset: ObjectId, name: &str| {
+    doc! { "$lookup": {
+      "from": labels,
+      "let": { "eth_address": "$eth_address", "source": "$source" },
+      "pipeline": [
+        { "$match": { "$expr": { "$and": [
+          { "$eq": ["$eth_address", "$$eth_address"] },
+          { "$eq": ["$source", "$$source"] },
+          { "$eq": ["$dataset", dataset] }
+        ] } } },
+        { "$project": { "name": 1_i32 } }
+      ],
+      "as": name,
+    } }
+  };
+  let merge = doc! { "$merge": { "into": changes } };
+
+  let mut added = vec![doc! { "$match": { "dataset": current } }];
+  match previous {
+    Some(previous) => added.extend([
+      same_label(previous, "previous"),
+      change(
+        doc! { "$ifNull": [{ "$arrayElemAt": ["$previous.name", 0_i32] }, Bson::Null] }.into(),
+        "$name".into(),
+      ),
+      doc! { "$match": { "$expr": { "$ne": ["$old_name", "$new_name"] } } },
+    ]),
+    None => added.push(change(
+      doc! { "$literal": Bson::Null }.into(),
+      "$name".into(),
+    )),
+  }
+  added.push(merge.clone());
+  AddressLabel::aggregate::<Document>(added).await?;
+
+  if let Some(previous) = previous {
+    let removed = vec![
+      doc! { "$match": { "dataset": previous } },
+      same_label(current, "current"),
+      doc! { "$match": { "current": { "$size": 0_i32 } } },
+      change("$name".into(), doc! { "$literal": Bson::Null }.into()),
+      merge,
+    ];
+    AddressLabel::aggregate::<Document>(removed).await?;
+  }
+
+  Ok(())
+}
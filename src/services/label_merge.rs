@@ -0,0 +1,142 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::models::address_label::AddressLabel;
+use crate::models::user_address_note::UserAddressNote;
+use crate::routes::arkham::{ArkhamChainData, ArkhamResponse};
+use crate::utils::serde_helpers::serialize_checksum_address;
+
+pub const USER_SOURCE: &str = "user";
+pub const LABELS_SOURCE: &str = "labels";
+pub const ARKHAM_SOURCE: &str = "arkham";
+
+/// Sources of the merged labels, see `settings::LabelMerge`.
+pub const LABEL_SOURCES: [&str; 3] = [USER_SOURCE, LABELS_SOURCE, ARKHAM_SOURCE];
+
+/// What is known of an address across the sources, each field taken from
+/// the most trusted source that has it.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct MergedLabels {
+  #[serde(serialize_with = "serialize_checksum_address")]
+  pub address: String,
+  pub name: Option<SourcedValue>,
+  pub entity: Option<SourcedValue>,
+  pub entity_type: Option<SourcedValue>,
+  pub note: Option<SourcedValue>,
+  // Every name of the address, most trusted first.
+  pub names: Vec<SourcedValue>,
+  // Sources that couldn't be read, e.g. `arkham` when it's down.
+  pub unavailable_sources: Vec<String>,
+}
+
+/// Value of a field with its provenance.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct SourcedValue {
+  pub value: String,
+  // One of `user`, `labels` or `arkham`.
+  pub source: String,
+  // Source of a stored label, e.g. `etherscan`.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub label_source: Option<String>,
+}
+
+impl SourcedValue {
+  fn new(value: &str, source: &str) -> Self {
+    Self {
+      value: value.to_owned(),
+      source: source.to_owned(),
+      label_source: None,
+    }
+  }
+}
+
+/// Values found for each field of an address, in no particular order.
+#[derive(Debug, Default)]
+pub struct Candidates {
+  names: Vec<SourcedValue>,
+  entities: Vec<SourcedValue>,
+  entity_types: Vec<SourcedValue>,
+  notes: Vec<SourcedValue>,
+}
+
+impl Candidates {
+  /// Adds the label and entity of the address, from the first chain that
+  /// has them.
+  pub fn add_arkham(&mut self, arkham: &ArkhamResponse) {
+    let chains = arkham.chains();
+
+    self
+      .names
+      .extend(arkham_value(&chains, ArkhamChainData::label_name));
+    self
+      .entities
+      .extend(arkham_value(&chains, ArkhamChainData::entity_name));
+    self
+      .entity_types
+      .extend(arkham_value(&chains, ArkhamChainData::entity_type));
+  }
+
+  /// Adds the names of the stored labels, in their order.
+  pub fn add_labels(&mut self, labels: Vec<AddressLabel>) {
+    self
+      .names
+      .extend(labels.into_iter().map(|label| SourcedValue {
+        value: label.name,
+        source: LABELS_SOURCE.to_owned(),
+        label_source: Some(label.source),
+      }));
+  }
+
+  /// Adds the custom name and note the user gave to the address.
+  pub fn add_note(&mut self, note: &UserAddressNote) {
+    let value = |value: &Option<String>| {
+      value
+        .as_deref()
+        .map(|value| SourcedValue::new(value, USER_SOURCE))
+    };
+
+    self.names.extend(value(&note.name));
+    self.notes.extend(value(&note.note));
+  }
+}
+
+fn arkham_value(
+  chains: &[(&str, &ArkhamChainData)],
+  field: fn(&ArkhamChainData) -> Option<&str>,
+) -> Option<SourcedValue> {
+  chains
+    .iter()
+    .find_map(|(_, data)| field(data))
+    .map(|value| SourcedValue::new(value, ARKHAM_SOURCE))
+}
+
+/// Consolidates the candidates by the priority of their sources, sources
+/// with the same priority keeping the order they were added in.
+pub fn merge(
+  priority: &[String],
+  address: String,
+  candidates: Candidates,
+  unavailable_sources: Vec<String>,
+) -> MergedLabels {
+  let by_priority = |mut values: Vec<SourcedValue>| {
+    values.sort_by_key(|value| {
+      priority
+        .iter()
+        .position(|source| *source == value.source)
+        .unwrap_or(priority.len())
+    });
+    values
+  };
+  let first = |values: Vec<SourcedValue>| by_priority(values).into_iter().next();
+
+  let names = by_priority(candidates.names);
+  MergedLabels {
+    address,
+    name: names.first().cloned(),
+    entity: first(candidates.entities),
+    entity_type: first(candidates.entity_types),
+    note: first(candidates.notes),
+    names,
+    unavailable_sources,
+  }
+}
@@ -0,0 +1,201 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use wither::bson::{doc, oid::ObjectId};
+
+use crate::errors::Error;
+use crate::models::alert_event::{AlertEvent, PublicAlertEvent};
+use crate::models::watched_address::{PublicWatchedAddress, WatchedAddress};
+use crate::models::watchlist::Watchlist;
+use crate::services::ownership::Ownership;
+use crate::utils::models::ModelExt;
+use crate::utils::to_object_id::to_object_id;
+
+/// Event streamed to the connected users, see `routes::live`.
+#[derive(Debug, Clone)]
+pub enum LiveEvent {
+  Alert(AlertEvent),
+  AddressWatched(WatchedAddress),
+}
+
+impl LiveEvent {
+  pub fn user(&self) -> ObjectId {
+    match self {
+      LiveEvent::Alert(event) => event.user,
+      LiveEvent::AddressWatched(watched) => watched.user,
+    }
+  }
+
+  // Price alerts are not about a chain.
+  fn chain(&self) -> Option<&str> {
+    match self {
+      LiveEvent::Alert(event) => event.chain.as_deref(),
+      LiveEvent::AddressWatched(watched) => Some(&watched.chain),
+    }
+  }
+
+  /// Message sent to the clients, tagged with the event type.
+  pub fn to_message(&self) -> LiveMessage {
+    match self {
+      LiveEvent::Alert(event) => LiveMessage::Alert {
+        data: PublicAlertEvent::from(event.clone()),
+      },
+      LiveEvent::AddressWatched(watched) => LiveMessage::AddressWatched {
+        data: PublicWatchedAddress::from(watched.clone()),
+      },
+    }
+  }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LiveMessage {
+  Alert { data: PublicAlertEvent },
+  AddressWatched { data: PublicWatchedAddress },
+  // Events were dropped because the client was reading too slowly.
+  Lagged { skipped: u64 },
+  Error { message: String },
+}
+
+/// Filters sent by the clients, as query parameters when connecting or as a
+/// message to replace them. Missing filters match every event. Price alerts
+/// are not about an address, so they only match without filters.
+#[derive(Debug, Default, Deserialize)]
+pub struct SubscriptionFilter {
+  #[serde(default)]
+  pub watchlists: Option<Vec<String>>,
+  #[serde(default)]
+  pub chains: Option<Vec<String>>,
+}
+
+impl SubscriptionFilter {
+  /// Reads filters from comma separated query parameters, e.g.
+  /// `?chains=ethereum,bsc`.
+  pub fn from_query(watchlists: Option<&str>, chains: Option<&str>) -> Self {
+    let split = |list: &str| {
+      list
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_owned)
+        .collect::<Vec<String>>()
+    };
+
+    Self {
+      watchlists: watchlists.map(split),
+      chains: chains.map(split),
+    }
+  }
+
+  pub fn from_message(message: &str) -> Result<Self, Error> {
+    serde_json::from_str::<Self>(message).map_err(|err| Error::InvalidPayload(err.to_string()))
+  }
+}
+
+/// Events a connection is subscribed to.
+#[derive(Debug)]
+pub struct Subscription {
+  user: ObjectId,
+  chains: Option<HashSet<String>>,
+  watchlists: Option<HashSet<ObjectId>>,
+  // Chain and address pairs of the filtered watchlists, alerts don't carry
+  // the watchlist they were raised for.
+  watched: HashSet<(String, String)>,
+}
+
+impl Subscription {
+  /// Subscribes a user to the events matching the filter. Watchlist filters
+  /// only accept watchlists the user can look up.
+  pub async fn new(user: ObjectId, filter: SubscriptionFilter) -> Result<Self, Error> {
+    let chains = filter.chains.map(|chains| {
+      chains
+        .into_iter()
+        .map(|chain| chain.trim().to_lowercase())
+        .collect::<HashSet<String>>()
+    });
+
+    let watchlists = match filter.watchlists {
+      Some(ids) => {
+        let ids = ids
+          .into_iter()
+          .map(to_object_id)
+          .collect::<Result<HashSet<ObjectId>, Error>>()?;
+        let mut query = Ownership::load(user).await?.readable();
+        query.insert("_id", doc! { "$in": to_vec(&ids) });
+        if Watchlist::count(query).await? != ids.len() as u64 {
+          return Err(Error::not_found());
+        }
+        Some(ids)
+      }
+      None => None,
+    };
+
+    let watched = match &watchlists {
+      Some(ids) => WatchedAddress::find(doc! { "watchlist": { "$in": to_vec(ids) } }, None)
+        .await?
+        .into_iter()
+        .map(|watched| (watched.chain, watched.address))
+        .collect(),
+      None => HashSet::new(),
+    };
+
+    Ok(Self::with_watched(user, chains, watchlists, watched))
+  }
+
+  pub fn with_watched(
+    user: ObjectId,
+    chains: Option<HashSet<String>>,
+    watchlists: Option<HashSet<ObjectId>>,
+    watched: HashSet<(String, String)>,
+  ) -> Self {
+    Self {
+      user,
+      chains,
+      watchlists,
+      watched,
+    }
+  }
+
+  pub fn user(&self) -> ObjectId {
+    self.user
+  }
+
+  /// Whether the event is sent to the connection. Addresses added to the
+  /// filtered watchlists are tracked, so their later alerts match too.
+  pub fn accepts(&mut self, event: &LiveEvent) -> bool {
+    if event.user() != self.user {
+      return false;
+    }
+
+    if let Some(chains) = &self.chains {
+      if !event.chain().is_some_and(|chain| chains.contains(chain)) {
+        return false;
+      }
+    }
+
+    let watchlists = match &self.watchlists {
+      Some(watchlists) => watchlists,
+      None => return true,
+    };
+
+    match event {
+      LiveEvent::Alert(alert) => match (&alert.chain, &alert.address) {
+        (Some(chain), Some(address)) => self.watched.contains(&(chain.clone(), address.clone())),
+        // Price alerts are not about a watched address.
+        _ => false,
+      },
+      LiveEvent::AddressWatched(watched) => {
+        if !watchlists.contains(&watched.watchlist) {
+          return false;
+        }
+        self
+          .watched
+          .insert((watched.chain.clone(), watched.address.clone()));
+        true
+      }
+    }
+  }
+}
+
+fn to_vec(ids: &HashSet<ObjectId>) -> Vec<ObjectId> {
+  ids.iter().copied().collect()
+}
@@ -0,0 +1,233 @@
+use bson::Bson;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+use utoipa::ToSchema;
+use wither::bson::{doc, oid::ObjectId};
+
+use crate::errors::{AuthenticateError, Error};
+use crate::models::totp_factor::TotpFactor;
+use crate::models::user::User;
+use crate::settings;
+use crate::utils::date;
+use crate::utils::models::ModelExt;
+use crate::utils::secret;
+use crate::utils::totp;
+
+const RECOVERY_CODE_LENGTH: usize = 10;
+
+/// Secret of a TOTP factor being enrolled, only returned when it is
+/// created.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct TotpEnrollment {
+  // Base32 encoded, for users typing it in their authenticator app.
+  pub secret: String,
+  // Payload of the QR code scanned by authenticator apps.
+  pub otpauth_uri: String,
+}
+
+/// Recovery codes, each one usable once instead of a TOTP code. They are
+/// only returned when generated.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RecoveryCodes {
+  pub recovery_codes: Vec<String>,
+}
+
+/// Starts the enrollment of a TOTP factor, replacing any pending one. Users
+/// with two-factor authentication enabled must disable it first.
+pub async fn enroll(user: ObjectId, settings: &settings::Mfa) -> Result<TotpEnrollment, Error> {
+  let account = match User::find_by_id(&user).await? {
+    Some(account) if account.mfa_enabled => {
+      debug!("Two-factor authentication already enabled, returning 409 status code");
+      return Err(Error::conflict());
+    }
+    Some(account) => account,
+    None => return Err(Error::not_found()),
+  };
+
+  TotpFactor::delete_many(doc! { "user": &user }).await?;
+  let secret = totp::generate_secret();
+  TotpFactor::create(TotpFactor::new(user, secret.clone())).await?;
+
+  Ok(TotpEnrollment {
+    otpauth_uri: totp::otpauth_uri(&settings.issuer, &account.email, &secret),
+    secret,
+  })
+}
+
+/// Confirms the pending factor of the user with a first code, enabling
+/// two-factor authentication. `None` when there is no pending factor.
+pub async fn confirm(
+  user: ObjectId,
+  code: &str,
+  settings: &settings::Mfa,
+) -> Result<Option<RecoveryCodes>, Error> {
+  let factor =
+    TotpFactor::find_one(doc! { "user": &user, "confirmed_at": Bson::Null }, None).await?;
+  let factor = match factor {
+    Some(factor) => factor,
+    None => return Ok(None),
+  };
+
+  if !claim_code(&factor, code).await? {
+    return Err(invalid_code());
+  }
+
+  let (recovery_codes, hashes) = generate_recovery_codes(settings.recovery_codes);
+  TotpFactor::update_one(
+    doc! { "_id": factor.id.unwrap() },
+    doc! {
+      "$set": {
+        "recovery_codes": hashes,
+        "confirmed_at": date::now(),
+        "updated_at": date::now(),
+      }
+    },
+    None,
+  )
+  .await?;
+  User::update_one(
+    doc! { "_id": &user },
+    doc! { "$set": { "mfa_enabled": true, "updated_at": date::now() } },
+    None,
+  )
+  .await?;
+
+  Ok(Some(RecoveryCodes { recovery_codes }))
+}
+
+/// Disables two-factor authentication once a TOTP or recovery code proves
+/// the user still has their factor. Returns whether it was enabled.
+pub async fn disable(user: ObjectId, code: &str) -> Result<bool, Error> {
+  let factor = match confirmed_factor(user).await? {
+    Some(factor) => factor,
+    None => return Ok(false),
+  };
+  if !verify_code(&factor, code).await? {
+    return Err(invalid_code());
+  }
+
+  TotpFactor::delete_one(doc! { "_id": factor.id.unwrap() }).await?;
+  User::update_one(
+    doc! { "_id": &user },
+    doc! { "$set": { "mfa_enabled": false, "updated_at": date::now() } },
+    None,
+  )
+  .await?;
+
+  Ok(true)
+}
+
+/// Replaces the recovery codes of the user, the previous ones can't be used
+/// anymore. `None` when two-factor authentication is not enabled.
+pub async fn regenerate_recovery_codes(
+  user: ObjectId,
+  code: &str,
+  settings: &settings::Mfa,
+) -> Result<Option<RecoveryCodes>, Error> {
+  let factor = match confirmed_factor(user).await? {
+    Some(factor) => factor,
+    None => return Ok(None),
+  };
+  if !verify_code(&factor, code).await? {
+    return Err(invalid_code());
+  }
+
+  let (recovery_codes, hashes) = generate_recovery_codes(settings.recovery_codes);
+  TotpFactor::update_one(
+    doc! { "_id": factor.id.unwrap() },
+    doc! { "$set": { "recovery_codes": hashes, "updated_at": date::now() } },
+    None,
+  )
+  .await?;
+
+  Ok(Some(RecoveryCodes { recovery_codes }))
+}
+
+/// Second step of signing in, before any token is issued. Users with
+/// two-factor authentication enabled must send a TOTP or recovery code.
+pub async fn verify_sign_in(user: &User, code: Option<&str>) -> Result<(), Error> {
+  if !user.mfa_enabled {
+    return Ok(());
+  }
+
+  let code = match code {
+    Some(code) => code,
+    None => {
+      debug!("Missing two-factor authentication code, returning 401 status code");
+      return Err(Error::Authenticate(AuthenticateError::MfaRequired));
+    }
+  };
+
+  let verified = match confirmed_factor(user.id.unwrap()).await? {
+    Some(factor) => verify_code(&factor, code).await?,
+    None => false,
+  };
+  if !verified {
+    debug!("Invalid two-factor authentication code, returning 401 status code");
+    return Err(Error::Authenticate(AuthenticateError::WrongCredentials));
+  }
+
+  Ok(())
+}
+
+async fn confirmed_factor(user: ObjectId) -> Result<Option<TotpFactor>, Error> {
+  TotpFactor::find_one(
+    doc! { "user": &user, "confirmed_at": { "$ne": Bson::Null } },
+    None,
+  )
+  .await
+}
+
+/// Checks a TOTP code, or else a recovery code, consuming it.
+async fn verify_code(factor: &TotpFactor, code: &str) -> Result<bool, Error> {
+  if totp::is_code(code) {
+    return claim_code(factor, code).await;
+  }
+
+  let hash = secret::hash(&code.trim().to_lowercase());
+  let result = TotpFactor::update_one(
+    doc! { "_id": factor.id.unwrap(), "recovery_codes": &hash },
+    doc! { "$pull": { "recovery_codes": &hash }, "$set": { "updated_at": date::now() } },
+    None,
+  )
+  .await?;
+
+  Ok(result.modified_count > 0)
+}
+
+/// Checks a TOTP code, claiming its time step atomically so the same code
+/// can't be used twice.
+async fn claim_code(factor: &TotpFactor, code: &str) -> Result<bool, Error> {
+  let timestamp = Utc::now().timestamp() as u64;
+  let step = match totp::verify(&factor.secret, code, timestamp) {
+    Some(step) => step as i64,
+    None => return Ok(false),
+  };
+
+  let claimed = TotpFactor::find_one_and_update(
+    doc! {
+      "_id": factor.id.unwrap(),
+      "$or": [{ "last_step": Bson::Null }, { "last_step": { "$lt": step } }]
+    },
+    doc! { "$set": { "last_step": step } },
+  )
+  .await?;
+
+  Ok(claimed.is_some())
+}
+
+/// Recovery codes with their hashes, the codes being lowercased so they are
+/// easier to type.
+fn generate_recovery_codes(count: usize) -> (Vec<String>, Vec<String>) {
+  let codes = (0..count)
+    .map(|_| secret::generate(RECOVERY_CODE_LENGTH).to_lowercase())
+    .collect::<Vec<String>>();
+  let hashes = codes.iter().map(|code| secret::hash(code)).collect();
+
+  (codes, hashes)
+}
+
+fn invalid_code() -> Error {
+  Error::InvalidPayload("Invalid two-factor authentication code".to_owned())
+}
@@ -0,0 +1,37 @@
+pub mod account;
+pub mod address_intelligence;
+pub mod alerts;
+pub mod approvals;
+pub mod arkham;
+pub mod balances;
+pub mod bitcoin;
+pub mod data_export;
+pub mod degen_score;
+pub mod digest;
+pub mod dune;
+pub mod ens;
+pub mod explorer;
+pub mod export;
+pub mod feature_flags;
+pub mod gas;
+pub mod graph;
+pub mod gridfs;
+pub mod history;
+pub mod jobs;
+pub mod label_dataset;
+pub mod label_history;
+pub mod label_merge;
+pub mod live;
+pub mod mfa;
+pub mod nfts;
+pub mod ownership;
+pub mod portfolio;
+pub mod prices;
+pub mod retention;
+pub mod risk;
+pub mod rpc;
+pub mod scheduler;
+pub mod sharing;
+pub mod stats;
+pub mod watcher;
+pub mod webhooks;
@@ -0,0 +1,240 @@
+use futures::future;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use tracing::{error, info, warn};
+use utoipa::ToSchema;
+
+use crate::errors::Error;
+use crate::settings;
+use crate::utils::serde_helpers::serialize_checksum_address;
+use crate::utils::telemetry;
+
+// Largest page size of the Alchemy NFT API.
+const PAGE_SIZE: u32 = 100;
+
+/// Client for the Alchemy NFT API, which has one endpoint per chain.
+pub struct NftClient {
+  http_client: reqwest::Client,
+  api_key: String,
+  chains: HashMap<String, String>,
+  max_pages_per_chain: u32,
+}
+
+impl NftClient {
+  pub fn new(http_client: reqwest::Client, settings: &settings::Nfts) -> Self {
+    Self {
+      http_client,
+      api_key: settings.api_key.clone(),
+      chains: settings.chains.clone(),
+      max_pages_per_chain: settings.max_pages_per_chain,
+    }
+  }
+
+  pub fn supports_chain(&self, chain: &str) -> bool {
+    self.chains.contains_key(chain)
+  }
+
+  /// Fetches the NFTs of an address on the given chains, or on every chain,
+  /// at the same time, grouped by collection. Chains that fail are reported
+  /// by name so one unavailable chain doesn't hide the others.
+  pub async fn fetch_holdings(&self, address: &str, chain: Option<&str>) -> NftHoldings {
+    let chains = self
+      .chains
+      .iter()
+      .filter(|(name, _)| chain.map_or(true, |chain| chain == name.as_str()));
+    let lookups = chains.map(|(chain, url)| async move {
+      let result = self.fetch_chain(chain, url, address).await;
+      if let Err(err) = &result {
+        warn!("Failed to fetch the {} NFTs of {}: {}", chain, address, err);
+      }
+      (chain.clone(), result)
+    });
+
+    let mut holdings = NftHoldings::default();
+    let mut collections = BTreeMap::new();
+    for (chain, result) in future::join_all(lookups).await {
+      let owned = match result {
+        Ok(owned) => owned,
+        Err(_) => {
+          holdings.unavailable_chains.push(chain);
+          continue;
+        }
+      };
+      holdings.truncated |= owned.truncated;
+
+      for nft in owned.nfts {
+        let contract = nft.contract.address.to_lowercase();
+        let collection = collections
+          .entry((chain.clone(), contract.clone()))
+          .or_insert_with(|| NftCollection::new(&chain, contract, &nft.contract));
+        collection.tokens.push(Nft::from(nft));
+      }
+    }
+
+    holdings.unavailable_chains.sort();
+    holdings.collections = collections.into_values().collect();
+    holdings
+  }
+
+  async fn fetch_chain(&self, chain: &str, url: &str, address: &str) -> Result<OwnedNfts, Error> {
+    info!("Querying {} NFTs of address: {}", chain, address);
+    let mut owned = OwnedNfts::default();
+    let mut page_key = None;
+
+    for _ in 0..self.max_pages_per_chain {
+      let mut query = vec![
+        ("owner", address.to_owned()),
+        ("withMetadata", "true".to_owned()),
+        ("pageSize", PAGE_SIZE.to_string()),
+      ];
+      if let Some(page_key) = page_key.take() {
+        query.push(("pageKey", page_key));
+      }
+
+      let res = self
+        .http_client
+        .get(format!("{}/{}/getNFTsForOwner", url, self.api_key))
+        .headers(telemetry::trace_headers())
+        .query(&query)
+        .send()
+        .await
+        .map_err(|err| Error::upstream_request("NFT API", err))?;
+
+      let status = res.status();
+      if !status.is_success() {
+        error!("Received a {} error from the {} NFT API", status, chain);
+        return Err(Error::upstream_unavailable(
+          "NFT API",
+          Some(status.as_u16()),
+        ));
+      }
+
+      let res = res.json::<AlchemyResponse>().await.map_err(|err| {
+        error!("Failed to parse {} NFT API response: {}", chain, err);
+        Error::UpstreamInvalidResponse(err.to_string())
+      })?;
+
+      owned.nfts.extend(res.owned_nfts);
+      page_key = res.page_key;
+      if page_key.is_none() {
+        return Ok(owned);
+      }
+    }
+
+    // Holders of thousands of NFTs are cut at `max_pages_per_chain` pages.
+    owned.truncated = true;
+    Ok(owned)
+  }
+}
+
+#[derive(Default)]
+struct OwnedNfts {
+  nfts: Vec<AlchemyNft>,
+  truncated: bool,
+}
+
+/// NFTs of an address, grouped by collection.
+#[derive(Debug, Default)]
+pub struct NftHoldings {
+  // Collections sorted by chain and contract address.
+  pub collections: Vec<NftCollection>,
+  // Chains whose NFTs couldn't be fetched.
+  pub unavailable_chains: Vec<String>,
+  // Whether some chain holds more NFTs than fetched.
+  pub truncated: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct NftCollection {
+  pub chain: String,
+  #[serde(serialize_with = "serialize_checksum_address")]
+  pub contract_address: String,
+  pub name: Option<String>,
+  // `ERC721` or `ERC1155`.
+  pub token_type: Option<String>,
+  // OpenSea floor price, in the native token of the chain.
+  pub floor_price: Option<f64>,
+  pub tokens: Vec<Nft>,
+}
+
+impl NftCollection {
+  fn new(chain: &str, contract_address: String, contract: &AlchemyContract) -> Self {
+    let opensea = contract.open_sea_metadata.as_ref();
+    Self {
+      chain: chain.to_owned(),
+      contract_address,
+      name: contract
+        .name
+        .clone()
+        .or_else(|| opensea.and_then(|opensea| opensea.collection_name.clone())),
+      token_type: contract.token_type.clone(),
+      floor_price: opensea.and_then(|opensea| opensea.floor_price),
+      tokens: Vec::new(),
+    }
+  }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct Nft {
+  pub token_id: String,
+  pub name: Option<String>,
+  pub image_url: Option<String>,
+  // Number of tokens held, above 1 for some ERC-1155 tokens.
+  pub balance: String,
+}
+
+impl From<AlchemyNft> for Nft {
+  fn from(nft: AlchemyNft) -> Self {
+    let image_url = nft
+      .image
+      .and_then(|image| image.cached_url.or(image.original_url));
+
+    Self {
+      token_id: nft.token_id,
+      name: nft.name,
+      image_url,
+      balance: nft.balance.unwrap_or_else(|| "1".to_owned()),
+    }
+  }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AlchemyResponse {
+  #[serde(default)]
+  owned_nfts: Vec<AlchemyNft>,
+  page_key: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AlchemyNft {
+  contract: AlchemyContract,
+  token_id: String,
+  name: Option<String>,
+  image: Option<AlchemyImage>,
+  balance: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AlchemyContract {
+  address: String,
+  name: Option<String>,
+  token_type: Option<String>,
+  open_sea_metadata: Option<AlchemyOpenSeaMetadata>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AlchemyOpenSeaMetadata {
+  floor_price: Option<f64>,
+  collection_name: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AlchemyImage {
+  cached_url: Option<String>,
+  original_url: Option<String>,
+}
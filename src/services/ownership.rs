@@ -0,0 +1,126 @@
+use wither::bson::{doc, oid::ObjectId, Bson, Document};
+
+use crate::errors::{AuthenticateError, Error};
+use crate::models::membership::Membership;
+use crate::models::share::{Share, ShareAccess};
+use crate::models::user::Role;
+use crate::utils::models::ModelExt;
+
+/// Resources a user has access to: the personal ones they own, the ones of
+/// the organizations they are a member of and the ones shared with them.
+/// Organization resources keep the user who created them in `user` and their
+/// organization in `organization`, personal ones have no organization.
+#[derive(Debug)]
+pub struct Ownership {
+  user: ObjectId,
+  memberships: Vec<(ObjectId, Role)>,
+  // Resources shared with the user, see `Share`.
+  shares: Vec<(ObjectId, ShareAccess)>,
+}
+
+impl Ownership {
+  pub async fn load(user: ObjectId) -> Result<Self, Error> {
+    let memberships = Membership::find(doc! { "user": &user }, None)
+      .await?
+      .into_iter()
+      .map(|membership| (membership.organization, membership.role))
+      .collect();
+    let shares = Share::find(doc! { "user": &user }, None)
+      .await?
+      .into_iter()
+      .map(|share| (share.resource, share.access))
+      .collect();
+
+    Ok(Self::new(user, memberships, shares))
+  }
+
+  pub fn new(
+    user: ObjectId,
+    memberships: Vec<(ObjectId, Role)>,
+    shares: Vec<(ObjectId, ShareAccess)>,
+  ) -> Self {
+    Self {
+      user,
+      memberships,
+      shares,
+    }
+  }
+
+  pub fn user(&self) -> ObjectId {
+    self.user
+  }
+
+  pub fn role_in(&self, organization: ObjectId) -> Option<Role> {
+    self
+      .memberships
+      .iter()
+      .find(|(id, _)| *id == organization)
+      .map(|(_, role)| *role)
+  }
+
+  /// Organizations in which the user has at least the role.
+  pub fn organizations(&self, role: Role) -> Vec<ObjectId> {
+    self
+      .memberships
+      .iter()
+      .filter(|(_, member_role)| member_role.grants(role))
+      .map(|(id, _)| *id)
+      .collect()
+  }
+
+  /// Resources shared with the user with at least the permissions of the
+  /// role.
+  pub fn shared(&self, role: Role) -> Vec<ObjectId> {
+    self
+      .shares
+      .iter()
+      .filter(|(_, access)| access.role().grants(role))
+      .map(|(id, _)| *id)
+      .collect()
+  }
+
+  /// Filter matching the resources the user can look up.
+  pub fn readable(&self) -> Document {
+    self.filter(Role::ReadOnly, true)
+  }
+
+  /// Filter matching the resources the user can change.
+  pub fn writable(&self) -> Document {
+    self.filter(Role::Member, true)
+  }
+
+  /// Filter matching the resources the user can remove and share, which
+  /// excludes the ones shared with them.
+  pub fn owned(&self) -> Document {
+    self.filter(Role::Member, false)
+  }
+
+  fn filter(&self, role: Role, with_shared: bool) -> Document {
+    let mut filters = vec![
+      doc! { "user": &self.user, "organization": Bson::Null },
+      doc! { "organization": { "$in": self.organizations(role) } },
+    ];
+    if with_shared {
+      filters.push(doc! { "_id": { "$in": self.shared(role) } });
+    }
+
+    doc! { "$or": filters }
+  }
+
+  /// Checks the user has the role in the organization, e.g. before creating
+  /// a resource owned by it. Personal resources have no organization and
+  /// always pass.
+  pub fn check(&self, organization: Option<ObjectId>, role: Role) -> Result<(), Error> {
+    let organization = match organization {
+      Some(organization) => organization,
+      None => return Ok(()),
+    };
+
+    match self.role_in(organization) {
+      Some(member_role) if member_role.grants(role) => Ok(()),
+      Some(_) => Err(Error::Authenticate(AuthenticateError::Forbidden)),
+      // Organizations of other users are not disclosed.
+      None => Err(Error::not_found()),
+    }
+  }
+}
@@ -0,0 +1,356 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Duration;
+use tracing::{debug, error, info, warn};
+use utoipa::ToSchema;
+
+use crate::errors::Error;
+use crate::settings;
+use crate::utils::cache::{self, TtlCache};
+use crate::utils::circuit_breaker::CircuitBreaker;
+use crate::utils::retry::{retry, RetryPolicy};
+use crate::utils::serde_helpers::serialize_checksum_address;
+use crate::utils::telemetry;
+
+// Maximum number of position pages fetched per address.
+const MAX_PAGES: usize = 5;
+
+// Zerion chain ids, with the Arkham chain name. Other chains keep the Zerion
+// id.
+const CHAINS: [(&str, &str); 6] = [
+  ("ethereum", "ethereum"),
+  ("binance-smart-chain", "bsc"),
+  ("polygon", "polygon"),
+  ("arbitrum", "arbitrum_one"),
+  ("avalanche", "avalanche"),
+  ("optimism", "optimism"),
+];
+
+/// Client for the Zerion portfolio API, returning the DeFi positions of an
+/// address (lending, liquidity pools, staking...) across protocols and
+/// chains. Portfolios are cached, and the last known one is served while
+/// Zerion is unavailable.
+pub struct PortfolioClient {
+  http_client: reqwest::Client,
+  url: String,
+  api_key: String,
+  retry_policy: RetryPolicy,
+  circuit_breaker: CircuitBreaker,
+  cache: TtlCache<Portfolio>,
+}
+
+impl PortfolioClient {
+  pub fn new(
+    http_client: reqwest::Client,
+    settings: &settings::Portfolio,
+    caches: &settings::Cache,
+  ) -> Self {
+    Self {
+      http_client,
+      url: settings.url.clone(),
+      api_key: settings.api_key.clone(),
+      retry_policy: RetryPolicy {
+        max_attempts: settings.retry_attempts,
+        base_delay: Duration::from_millis(settings.retry_base_delay_ms),
+        max_delay: Duration::from_millis(settings.retry_max_delay_ms),
+      },
+      circuit_breaker: CircuitBreaker::new(
+        "Zerion",
+        settings.circuit_failure_threshold,
+        Duration::from_secs(settings.circuit_reset_secs),
+      ),
+      cache: TtlCache::new(
+        cache::backend(caches, "portfolio"),
+        Duration::from_secs(settings.cache_ttl_secs),
+      ),
+    }
+  }
+
+  pub async fn fetch_portfolio(&self, address: &str) -> Result<Portfolio, Error> {
+    if let Some(entry) = self.cache.get(address).await {
+      debug!("Returning cached portfolio");
+      return Ok(entry.value);
+    }
+
+    match self.fetch_positions(address).await {
+      Ok(positions) => {
+        let portfolio = Portfolio::new(address, positions);
+        self.cache.insert(address, portfolio.clone()).await;
+        Ok(portfolio)
+      }
+      Err(err) => match self.cache.get_stale(address).await {
+        Some(entry) => {
+          warn!("Serving the stale portfolio of {}: {}", address, err);
+          Ok(entry.value)
+        }
+        None => Err(err),
+      },
+    }
+  }
+
+  async fn fetch_positions(&self, address: &str) -> Result<Vec<ZerionPosition>, Error> {
+    info!("Querying Zerion positions of address: {}", address);
+    let mut positions = Vec::new();
+    let mut url = Some(format!(
+      "{}/wallets/{}/positions/?filter[positions]=only_complex&filter[trash]=only_non_trash&currency=usd",
+      self.url, address
+    ));
+
+    for _ in 0..MAX_PAGES {
+      let page_url = match url.take() {
+        Some(page_url) => page_url,
+        None => break,
+      };
+      let page = self.fetch_page(&page_url).await?;
+      positions.extend(page.data);
+      url = page.links.and_then(|links| links.next);
+    }
+
+    if url.is_some() {
+      warn!("Address {} has over {} position pages", address, MAX_PAGES);
+    }
+    Ok(positions)
+  }
+
+  /// Fetches a page of positions, retrying rate limited and failed requests.
+  /// Failures count towards opening the circuit, which then rejects requests
+  /// without sending them.
+  async fn fetch_page(&self, url: &str) -> Result<ZerionPage, Error> {
+    if !self.circuit_breaker.allow() {
+      debug!("Zerion circuit is open, returning 503 status code");
+      return Err(Error::upstream_unavailable("Zerion", None));
+    }
+
+    let send = || {
+      self
+        .http_client
+        .get(url)
+        .headers(telemetry::trace_headers())
+        .basic_auth(&self.api_key, Some(""))
+        .send()
+    };
+    let result = retry("zerion_request", &self.retry_policy, send, is_retryable).await;
+
+    let res = match result {
+      Ok(res) if res.status().is_success() => res,
+      Ok(res) => {
+        let status = res.status();
+        error!("Received a {} error from Zerion", status);
+        if status == reqwest::StatusCode::TOO_MANY_REQUESTS || status.is_server_error() {
+          self.circuit_breaker.record_failure();
+        } else {
+          self.circuit_breaker.record_success();
+        }
+        return Err(Error::upstream_unavailable("Zerion", Some(status.as_u16())));
+      }
+      Err(err) => {
+        self.circuit_breaker.record_failure();
+        return Err(Error::upstream_request("Zerion", err));
+      }
+    };
+    self.circuit_breaker.record_success();
+
+    res.json::<ZerionPage>().await.map_err(|err| {
+      error!("Failed to parse Zerion response: {}", err);
+      Error::UpstreamInvalidResponse(err.to_string())
+    })
+  }
+}
+
+fn is_retryable(result: &Result<reqwest::Response, reqwest::Error>) -> bool {
+  match result {
+    Ok(res) => {
+      let status = res.status();
+      status == reqwest::StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+    }
+    Err(err) => err.is_connect() || err.is_timeout(),
+  }
+}
+
+/// DeFi positions of an address, grouped by protocol and chain.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Portfolio {
+  #[serde(serialize_with = "serialize_checksum_address")]
+  pub address: String,
+  // Net USD value of the positions, loans being subtracted.
+  pub usd_value: f64,
+  // Protocols sorted by decreasing value.
+  pub protocols: Vec<ProtocolPositions>,
+}
+
+impl Portfolio {
+  fn new(address: &str, positions: Vec<ZerionPosition>) -> Self {
+    let mut protocols = BTreeMap::new();
+    for position in positions {
+      let attributes = position.attributes;
+      let chain = position
+        .relationships
+        .and_then(|relationships| relationships.chain)
+        .map(|chain| chain.data.id)
+        .unwrap_or_default();
+      let chain = match CHAINS
+        .iter()
+        .find(|(zerion_chain, _)| *zerion_chain == chain)
+      {
+        Some((_, name)) => (*name).to_owned(),
+        None => chain,
+      };
+      let protocol = attributes
+        .protocol
+        .or_else(|| attributes.application_metadata.and_then(|app| app.name))
+        .unwrap_or_else(|| "Unknown".to_owned());
+
+      let position = Position {
+        kind: PositionKind::from(attributes.position_type.as_str()),
+        name: attributes.name,
+        symbol: attributes
+          .fungible_info
+          .and_then(|fungible| fungible.symbol),
+        quantity: attributes.quantity.numeric,
+        usd_value: attributes.value,
+      };
+
+      protocols
+        .entry((protocol.clone(), chain.clone()))
+        .or_insert_with(|| ProtocolPositions {
+          protocol,
+          chain,
+          usd_value: 0.0,
+          positions: Vec::new(),
+        })
+        .positions
+        .push(position);
+    }
+
+    let mut protocols = protocols
+      .into_values()
+      .map(|mut protocol| {
+        protocol.usd_value = protocol.positions.iter().map(Position::net_value).sum();
+        protocol
+      })
+      .collect::<Vec<ProtocolPositions>>();
+    protocols.sort_by(|a, b| b.usd_value.total_cmp(&a.usd_value));
+
+    Self {
+      address: address.to_owned(),
+      usd_value: protocols.iter().map(|protocol| protocol.usd_value).sum(),
+      protocols,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ProtocolPositions {
+  pub protocol: String,
+  pub chain: String,
+  // Net USD value, loans being subtracted.
+  pub usd_value: f64,
+  pub positions: Vec<Position>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Position {
+  pub kind: PositionKind,
+  pub name: String,
+  pub symbol: Option<String>,
+  // Token amount, as a decimal string.
+  pub quantity: String,
+  // `None` when the token has no known price.
+  pub usd_value: Option<f64>,
+}
+
+impl Position {
+  fn net_value(&self) -> f64 {
+    let value = self.usd_value.unwrap_or_default();
+    match self.kind {
+      PositionKind::Loan => -value,
+      _ => value,
+    }
+  }
+}
+
+/// Lending deposits and LP shares are deposits, borrowed tokens are loans.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum PositionKind {
+  Deposit,
+  Loan,
+  Locked,
+  Staked,
+  Reward,
+  Other,
+}
+
+impl From<&str> for PositionKind {
+  fn from(position_type: &str) -> Self {
+    match position_type {
+      "deposit" => Self::Deposit,
+      "loan" => Self::Loan,
+      "locked" => Self::Locked,
+      "staked" => Self::Staked,
+      "reward" => Self::Reward,
+      _ => Self::Other,
+    }
+  }
+}
+
+#[derive(Deserialize)]
+struct ZerionPage {
+  #[serde(default)]
+  data: Vec<ZerionPosition>,
+  links: Option<ZerionLinks>,
+}
+
+#[derive(Deserialize)]
+struct ZerionLinks {
+  next: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ZerionPosition {
+  attributes: ZerionAttributes,
+  relationships: Option<ZerionRelationships>,
+}
+
+#[derive(Deserialize)]
+struct ZerionAttributes {
+  #[serde(default)]
+  name: String,
+  #[serde(default)]
+  position_type: String,
+  protocol: Option<String>,
+  application_metadata: Option<ZerionApplication>,
+  quantity: ZerionQuantity,
+  value: Option<f64>,
+  fungible_info: Option<ZerionFungible>,
+}
+
+#[derive(Deserialize)]
+struct ZerionApplication {
+  name: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ZerionQuantity {
+  numeric: String,
+}
+
+#[derive(Deserialize)]
+struct ZerionFungible {
+  symbol: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ZerionRelationships {
+  chain: Option<ZerionRelationship>,
+}
+
+#[derive(Deserialize)]
+struct ZerionRelationship {
+  data: ZerionResource,
+}
+
+#[derive(Deserialize)]
+struct ZerionResource {
+  id: String,
+}
@@ -0,0 +1,185 @@
+use async_trait::async_trait;
+use chrono::DateTime;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, error, info};
+use utoipa::ToSchema;
+
+use crate::errors::Error;
+use crate::settings;
+use crate::utils::cache::{Cache, TtlCache};
+use crate::utils::circuit_breaker::CircuitBreaker;
+use crate::utils::telemetry;
+
+/// USD price of a token, by CoinGecko id (e.g. `ethereum`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct TokenPrice {
+  pub id: String,
+  pub usd: f64,
+  pub usd_24h_change: Option<f64>,
+  // When the provider last updated the price, as an RFC 3339 date.
+  pub updated_at: Option<String>,
+}
+
+/// Source of token prices. Ids unknown to the provider are left out of the
+/// result instead of failing the whole lookup.
+#[async_trait]
+pub trait PriceProvider: Send + Sync {
+  async fn prices(&self, ids: &[String]) -> Result<HashMap<String, TokenPrice>, Error>;
+}
+
+/// Fetches prices from the CoinGecko simple price endpoint, all ids in a
+/// single request. Failures count towards opening the circuit, which then
+/// rejects requests without sending them.
+pub struct CoinGeckoProvider {
+  http_client: reqwest::Client,
+  url: String,
+  api_key: String,
+  circuit_breaker: CircuitBreaker,
+}
+
+impl CoinGeckoProvider {
+  pub fn new(http_client: reqwest::Client, settings: &settings::Prices) -> Self {
+    Self {
+      http_client,
+      url: settings.url.clone(),
+      api_key: settings.api_key.clone(),
+      circuit_breaker: CircuitBreaker::new(
+        "CoinGecko",
+        settings.circuit_failure_threshold,
+        Duration::from_secs(settings.circuit_reset_secs),
+      ),
+    }
+  }
+}
+
+#[async_trait]
+impl PriceProvider for CoinGeckoProvider {
+  async fn prices(&self, ids: &[String]) -> Result<HashMap<String, TokenPrice>, Error> {
+    if !self.circuit_breaker.allow() {
+      debug!("CoinGecko circuit is open, returning 503 status code");
+      return Err(Error::upstream_unavailable("CoinGecko", None));
+    }
+
+    info!("Querying CoinGecko prices of {} tokens", ids.len());
+    let mut request = self
+      .http_client
+      .get(format!("{}/simple/price", self.url))
+      .headers(telemetry::trace_headers())
+      .query(&[
+        ("ids", ids.join(",").as_str()),
+        ("vs_currencies", "usd"),
+        ("include_24hr_change", "true"),
+        ("include_last_updated_at", "true"),
+      ]);
+    // The public API works without a key, with a lower rate limit.
+    if !self.api_key.is_empty() {
+      request = request.header("x-cg-demo-api-key", &self.api_key);
+    }
+
+    let res = match request.send().await {
+      Ok(res) => res,
+      Err(err) => {
+        self.circuit_breaker.record_failure();
+        return Err(Error::upstream_request("CoinGecko", err));
+      }
+    };
+    let status = res.status();
+    if status == reqwest::StatusCode::TOO_MANY_REQUESTS || status.is_server_error() {
+      self.circuit_breaker.record_failure();
+    } else {
+      self.circuit_breaker.record_success();
+    }
+    if !status.is_success() {
+      error!("Received a {} error from CoinGecko", status);
+      return Err(Error::upstream_unavailable(
+        "CoinGecko",
+        Some(status.as_u16()),
+      ));
+    }
+
+    let quotes = res
+      .json::<HashMap<String, CoinGeckoQuote>>()
+      .await
+      .map_err(|err| {
+        error!("Failed to parse CoinGecko response: {}", err);
+        Error::UpstreamInvalidResponse(err.to_string())
+      })?;
+
+    let prices = quotes
+      .into_iter()
+      // Tokens without a USD price are treated as unknown.
+      .filter_map(|(id, quote)| {
+        let price = TokenPrice {
+          id: id.clone(),
+          usd: quote.usd?,
+          usd_24h_change: quote.usd_24h_change,
+          updated_at: quote
+            .last_updated_at
+            .and_then(|secs| DateTime::from_timestamp(secs, 0))
+            .map(|date| date.to_rfc3339()),
+        };
+        Some((id, price))
+      })
+      .collect();
+
+    Ok(prices)
+  }
+}
+
+#[derive(Deserialize)]
+struct CoinGeckoQuote {
+  usd: Option<f64>,
+  usd_24h_change: Option<f64>,
+  last_updated_at: Option<i64>,
+}
+
+/// Serves the prices cached by previous lookups and fetches the others from
+/// the wrapped provider, in a single lookup.
+pub struct CachedPriceProvider {
+  provider: Box<dyn PriceProvider>,
+  cache: TtlCache<TokenPrice>,
+}
+
+impl CachedPriceProvider {
+  pub fn new<P: PriceProvider + 'static>(
+    provider: P,
+    cache: Arc<dyn Cache>,
+    ttl: Duration,
+  ) -> Self {
+    Self {
+      provider: Box::new(provider),
+      cache: TtlCache::new(cache, ttl),
+    }
+  }
+}
+
+#[async_trait]
+impl PriceProvider for CachedPriceProvider {
+  async fn prices(&self, ids: &[String]) -> Result<HashMap<String, TokenPrice>, Error> {
+    let mut prices = HashMap::new();
+    let mut missing = Vec::new();
+    for id in ids {
+      match self.cache.get(id).await {
+        Some(entry) => {
+          prices.insert(id.clone(), entry.value);
+        }
+        None => missing.push(id.clone()),
+      }
+    }
+
+    if missing.is_empty() {
+      debug!("Returning cached prices");
+      return Ok(prices);
+    }
+
+    for (id, price) in self.provider.prices(&missing).await? {
+      self.cache.insert(id.as_str(), price.clone()).await;
+      prices.insert(id, price);
+    }
+
+    Ok(prices)
+  }
+}
@@ -0,0 +1,109 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tracing::info;
+use wither::bson::{doc, Document};
+use wither::Model as WitherModel;
+
+use crate::errors::Error;
+use crate::models::address_snapshot::AddressSnapshot;
+use crate::models::alert_event::AlertEvent;
+use crate::models::audit_log::AuditLog;
+use crate::models::webhook_dead_letter::WebhookDeadLetter;
+use crate::models::webhook_delivery::WebhookDelivery;
+use crate::settings;
+use crate::utils::date;
+use crate::utils::models::ModelExt;
+
+const SECS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Retention of a collection, as listed by `GET /admin/retention`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+  pub collection: String,
+  // `None` when the documents are kept forever.
+  pub retention_days: Option<u64>,
+  // Documents past their retention, removed by the next cleanup.
+  pub expired: u64,
+}
+
+/// Documents removed from a collection by a cleanup.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Cleanup {
+  pub collection: String,
+  pub deleted: u64,
+}
+
+/// Retention of every collection with one, with the number of documents
+/// past it.
+pub async fn policies(settings: &settings::Retention) -> Result<Vec<RetentionPolicy>, Error> {
+  Ok(vec![
+    policy::<AddressSnapshot>(settings.address_snapshots_days, doc! {}).await?,
+    policy::<AlertEvent>(settings.alert_events_days, doc! {}).await?,
+    policy::<AuditLog>(settings.audit_logs_days, doc! {}).await?,
+    policy::<WebhookDelivery>(settings.webhook_deliveries_days, delivered()).await?,
+    policy::<WebhookDeadLetter>(settings.webhook_dead_letters_days, doc! {}).await?,
+  ])
+}
+
+/// Removes the documents past their retention, run by the
+/// `retention_cleanup` job. Collections kept forever are left out.
+pub async fn clean_up(settings: &settings::Retention) -> Result<Vec<Cleanup>, Error> {
+  let cleanups = vec![
+    clean_up_collection::<AddressSnapshot>(settings.address_snapshots_days, doc! {}).await?,
+    clean_up_collection::<AlertEvent>(settings.alert_events_days, doc! {}).await?,
+    clean_up_collection::<AuditLog>(settings.audit_logs_days, doc! {}).await?,
+    clean_up_collection::<WebhookDelivery>(settings.webhook_deliveries_days, delivered()).await?,
+    clean_up_collection::<WebhookDeadLetter>(settings.webhook_dead_letters_days, doc! {}).await?,
+  ];
+
+  Ok(cleanups.into_iter().flatten().collect())
+}
+
+async fn policy<M: ModelExt>(days: Option<u64>, query: Document) -> Result<RetentionPolicy, Error> {
+  let expired = match expired(days, query) {
+    Some(query) => M::count(query).await?,
+    None => 0,
+  };
+
+  Ok(RetentionPolicy {
+    collection: M::T::COLLECTION_NAME.to_owned(),
+    retention_days: days,
+    expired,
+  })
+}
+
+async fn clean_up_collection<M: ModelExt>(
+  days: Option<u64>,
+  query: Document,
+) -> Result<Option<Cleanup>, Error> {
+  let query = match expired(days, query) {
+    Some(query) => query,
+    None => return Ok(None),
+  };
+
+  let deleted = M::delete_many(query).await?;
+  info!(
+    "Removed {} documents past their retention from {}",
+    deleted,
+    M::T::COLLECTION_NAME
+  );
+
+  Ok(Some(Cleanup {
+    collection: M::T::COLLECTION_NAME.to_owned(),
+    deleted,
+  }))
+}
+
+/// Narrows the query to the documents created more than `days` ago, `None`
+/// when the documents are kept forever.
+fn expired(days: Option<u64>, mut query: Document) -> Option<Document> {
+  let retention = Duration::from_secs(days?.saturating_mul(SECS_PER_DAY));
+  query.insert("created_at", doc! { "$lt": date::before(retention) });
+
+  Some(query)
+}
+
+/// Pending deliveries are kept, they may still be delivered.
+fn delivered() -> Document {
+  doc! { "status": "delivered" }
+}
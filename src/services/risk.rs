@@ -0,0 +1,256 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::Arc;
+use tracing::{info, warn};
+use utoipa::ToSchema;
+use wither::bson::{doc, Bson};
+
+use crate::errors::Error;
+use crate::models::address_label::AddressLabel;
+use crate::routes::arkham::ArkhamResponse;
+use crate::services::address_intelligence::IntelligenceProvider;
+use crate::services::approvals::{Approval, ApprovalScanner};
+use crate::services::label_dataset;
+use crate::settings;
+use crate::utils::models::ModelExt;
+use crate::utils::serde_helpers::serialize_checksum_address;
+
+const MAX_SCORE: u32 = 100;
+
+// Lowest scores of the medium and high risk levels.
+const MEDIUM_RISK_SCORE: u32 = 30;
+const HIGH_RISK_SCORE: u32 = 70;
+
+/// Scores the risk of interacting with an address from its Arkham entity
+/// and label, its public labels and its outstanding approvals. Each
+/// heuristic that matches adds its points to the score.
+pub struct RiskScorer {
+  address_intelligence: Arc<dyn IntelligenceProvider>,
+  approvals: Arc<ApprovalScanner>,
+  risky_entity_types: HashSet<String>,
+  // Lowercased, label names are lowercased before matching.
+  risky_label_keywords: Vec<String>,
+  settings: settings::Risk,
+}
+
+impl RiskScorer {
+  pub fn new(
+    address_intelligence: Arc<dyn IntelligenceProvider>,
+    approvals: Arc<ApprovalScanner>,
+    settings: &settings::Risk,
+    approvals_settings: &settings::Approvals,
+  ) -> Self {
+    Self {
+      address_intelligence,
+      approvals,
+      risky_entity_types: approvals_settings
+        .risky_entity_types
+        .iter()
+        .cloned()
+        .collect(),
+      risky_label_keywords: settings
+        .risky_label_keywords
+        .iter()
+        .map(|keyword| keyword.to_lowercase())
+        .collect(),
+      settings: settings.clone(),
+    }
+  }
+
+  /// Risk of an address, with its approvals on `chain`. Arkham and the RPC
+  /// provider being unavailable doesn't fail the assessment, the sources
+  /// that couldn't be read are reported instead.
+  pub async fn assess(&self, chain: &str, address: &str) -> Result<RiskReport, Error> {
+    info!("Assessing the risk of address: {}", address);
+    let labels_query = doc! {
+      "eth_address": address,
+      "organization": Bson::Null,
+      "dataset": label_dataset::visible_datasets().await?,
+    };
+    let (arkham, labels, approvals) = tokio::join!(
+      self.address_intelligence.lookup_address(address),
+      AddressLabel::find(labels_query, None),
+      self.approvals.fetch_approvals(chain, address),
+    );
+
+    let mut factors = Vec::new();
+    let mut unavailable_sources = Vec::new();
+    match arkham {
+      Ok(arkham) => factors.extend(self.arkham_factors(&arkham)),
+      // Addresses unknown to Arkham have no entity or label.
+      Err(Error::NotFound(_)) => {}
+      Err(err) => {
+        warn!("Failed to look up {} on Arkham: {}", address, err);
+        unavailable_sources.push("arkham".to_owned());
+      }
+    }
+    factors.extend(self.label_factor(&labels?));
+    match approvals {
+      Ok(scan) => factors.extend(self.approval_factors(chain, &scan.approvals)),
+      Err(err) => {
+        warn!("Failed to scan the approvals of {}: {}", address, err);
+        unavailable_sources.push("approvals".to_owned());
+      }
+    }
+
+    let score = factors
+      .iter()
+      .map(|factor| factor.points)
+      .sum::<u32>()
+      .min(MAX_SCORE);
+
+    Ok(RiskReport {
+      address: address.to_owned(),
+      chain: chain.to_owned(),
+      score,
+      level: RiskLevel::from_score(score),
+      factors,
+      unavailable_sources,
+    })
+  }
+
+  fn arkham_factors(&self, arkham: &ArkhamResponse) -> Vec<RiskFactor> {
+    let mut factors = Vec::new();
+    let chains = arkham.chains();
+
+    // Entities and labels are usually the same on every chain, they are
+    // only counted once.
+    let risky_entity = chains.iter().find_map(|(_, data)| {
+      data
+        .entity_type()
+        .filter(|entity_type| self.risky_entity_types.contains(*entity_type))
+        .map(|entity_type| (data.entity_name(), entity_type))
+    });
+    if let Some((name, entity_type)) = risky_entity {
+      factors.push(RiskFactor {
+        kind: RiskFactorKind::RiskyEntity,
+        points: self.settings.risky_entity_points,
+        detail: format!(
+          "Arkham entity {} is of type {}",
+          name.unwrap_or("without name"),
+          entity_type
+        ),
+      });
+    }
+
+    let risky_label = chains
+      .iter()
+      .filter_map(|(_, data)| data.label_name())
+      .find(|name| self.is_risky_label(name));
+    if let Some(name) = risky_label {
+      factors.push(RiskFactor {
+        kind: RiskFactorKind::RiskyArkhamLabel,
+        points: self.settings.risky_label_points,
+        detail: format!("Arkham labels the address \"{}\"", name),
+      });
+    }
+
+    factors
+  }
+
+  fn label_factor(&self, labels: &[AddressLabel]) -> Option<RiskFactor> {
+    let label = labels
+      .iter()
+      .find(|label| self.is_risky_label(&label.name))?;
+
+    Some(RiskFactor {
+      kind: RiskFactorKind::RiskyLabel,
+      points: self.settings.risky_label_points,
+      detail: format!("Labelled \"{}\" by {}", label.name, label.source),
+    })
+  }
+
+  fn approval_factors(&self, chain: &str, approvals: &[Approval]) -> Vec<RiskFactor> {
+    let mut factors = Vec::new();
+
+    let risky = approvals.iter().filter(|approval| approval.risky).count();
+    if risky > 0 {
+      factors.push(RiskFactor {
+        kind: RiskFactorKind::RiskyApproval,
+        points: self.settings.risky_approval_points,
+        detail: format!("{} approvals on {} to risky spenders", risky, chain),
+      });
+    }
+
+    let unlimited = approvals
+      .iter()
+      .filter(|approval| approval.unlimited)
+      .count();
+    if unlimited > 0 {
+      let points = (unlimited as u32)
+        .saturating_mul(self.settings.unlimited_approval_points)
+        .min(self.settings.max_unlimited_approval_points);
+      factors.push(RiskFactor {
+        kind: RiskFactorKind::UnlimitedApprovals,
+        points,
+        detail: format!("{} unlimited approvals on {}", unlimited, chain),
+      });
+    }
+
+    factors
+  }
+
+  fn is_risky_label(&self, name: &str) -> bool {
+    let name = name.to_lowercase();
+    self
+      .risky_label_keywords
+      .iter()
+      .any(|keyword| name.contains(keyword.as_str()))
+  }
+}
+
+/// Risk score of an address, from 0 to 100, with the factors it adds up.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RiskReport {
+  #[serde(serialize_with = "serialize_checksum_address")]
+  pub address: String,
+  // Chain whose approvals were scanned.
+  pub chain: String,
+  pub score: u32,
+  pub level: RiskLevel,
+  pub factors: Vec<RiskFactor>,
+  // Sources that couldn't be read, `arkham` or `approvals`, whose factors
+  // are missing from the score.
+  pub unavailable_sources: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RiskFactor {
+  pub kind: RiskFactorKind,
+  // Points added to the score.
+  pub points: u32,
+  pub detail: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RiskFactorKind {
+  // Arkham entity of a risky type, e.g. `hacker`.
+  RiskyEntity,
+  RiskyArkhamLabel,
+  // Public label of the address, from the labels collection.
+  RiskyLabel,
+  RiskyApproval,
+  UnlimitedApprovals,
+}
+
+/// Low under 30, medium under 70, high otherwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum RiskLevel {
+  Low,
+  Medium,
+  High,
+}
+
+impl RiskLevel {
+  fn from_score(score: u32) -> Self {
+    if score >= HIGH_RISK_SCORE {
+      Self::High
+    } else if score >= MEDIUM_RISK_SCORE {
+      Self::Medium
+    } else {
+      Self::Low
+    }
+  }
+}
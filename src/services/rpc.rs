@@ -0,0 +1,170 @@
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::time::Duration;
+use tracing::{debug, error};
+
+use crate::errors::Error;
+use crate::settings;
+use crate::utils::circuit_breaker::CircuitBreaker;
+use crate::utils::telemetry;
+
+/// JSON-RPC client for the EVM chains, with one provider per chain. Each
+/// provider has its own circuit, so one being down doesn't affect the
+/// others.
+pub struct RpcClient {
+  http_client: reqwest::Client,
+  chains: HashMap<String, String>,
+  circuit_breakers: HashMap<String, CircuitBreaker>,
+}
+
+impl RpcClient {
+  pub fn new(http_client: reqwest::Client, settings: &settings::Rpc) -> Self {
+    let circuit_breakers = settings
+      .chains
+      .keys()
+      .map(|chain| {
+        let circuit_breaker = CircuitBreaker::new(
+          format!("{} RPC", chain),
+          settings.circuit_failure_threshold,
+          Duration::from_secs(settings.circuit_reset_secs),
+        );
+        (chain.clone(), circuit_breaker)
+      })
+      .collect();
+
+    Self {
+      http_client,
+      chains: settings.chains.clone(),
+      circuit_breakers,
+    }
+  }
+
+  pub fn supports_chain(&self, chain: &str) -> bool {
+    self.chains.contains_key(chain)
+  }
+
+  /// Chains with a provider, sorted by name.
+  pub fn chains(&self) -> Vec<String> {
+    let mut chains = self.chains.keys().cloned().collect::<Vec<String>>();
+    chains.sort();
+    chains
+  }
+
+  /// Sends a JSON-RPC request to the provider of a chain, returning its
+  /// result. Unknown chains are rejected as bad requests.
+  pub async fn request<T: DeserializeOwned>(
+    &self,
+    chain: &str,
+    method: &str,
+    params: Value,
+  ) -> Result<T, Error> {
+    let url = match self.chains.get(chain) {
+      Some(url) => url,
+      None => {
+        debug!(
+          "No RPC provider for chain {}, returning 400 status code",
+          chain
+        );
+        return Err(Error::bad_request());
+      }
+    };
+
+    // Every chain with a provider has a circuit.
+    let circuit_breaker = &self.circuit_breakers[chain];
+    if !circuit_breaker.allow() {
+      debug!("{} RPC circuit is open, returning 503 status code", chain);
+      return Err(Error::upstream_unavailable("RPC provider", None));
+    }
+
+    let payload = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+    let result = self
+      .http_client
+      .post(url)
+      .headers(telemetry::trace_headers())
+      .json(&payload)
+      .send()
+      .await;
+
+    let res = match result {
+      Ok(res) => res,
+      Err(err) => {
+        circuit_breaker.record_failure();
+        return Err(Error::upstream_request("RPC provider", err));
+      }
+    };
+    let status = res.status();
+    if status == reqwest::StatusCode::TOO_MANY_REQUESTS || status.is_server_error() {
+      circuit_breaker.record_failure();
+    } else {
+      circuit_breaker.record_success();
+    }
+    if !status.is_success() {
+      error!(
+        "Received a {} error from the {} RPC provider",
+        status, chain
+      );
+      return Err(Error::upstream_unavailable(
+        "RPC provider",
+        Some(status.as_u16()),
+      ));
+    }
+
+    let res = res.json::<RpcResponse>().await.map_err(|err| {
+      error!("Failed to parse {} RPC response: {}", chain, err);
+      Error::UpstreamInvalidResponse(err.to_string())
+    })?;
+
+    match (res.result, res.error) {
+      (Some(result), _) => serde_json::from_value(result)
+        .map_err(|err| Error::UpstreamInvalidResponse(err.to_string())),
+      (None, error) => {
+        error!("{} RPC {} failed: {:?}", chain, method, error);
+        Err(Error::upstream_unavailable("RPC provider", None))
+      }
+    }
+  }
+
+  /// Calls a contract at the latest block with ABI encoded `data`, returning
+  /// the ABI encoded result.
+  pub async fn call(&self, chain: &str, to: &str, data: &str) -> Result<Vec<u8>, Error> {
+    let params = json!([{ "to": to, "data": data }, "latest"]);
+    let result = self.request::<String>(chain, "eth_call", params).await?;
+    hex::decode(result.trim_start_matches("0x"))
+      .map_err(|err| Error::UpstreamInvalidResponse(err.to_string()))
+  }
+}
+
+#[derive(Deserialize)]
+struct RpcResponse {
+  result: Option<Value>,
+  error: Option<Value>,
+}
+
+/// ABI encodes an address as a 32 bytes word, in hex without prefix.
+pub fn encode_address(address: &str) -> String {
+  format!("{:0>64}", address.trim_start_matches("0x").to_lowercase())
+}
+
+/// Decimal representation of a big-endian unsigned integer, such as an ABI
+/// encoded `uint256`.
+pub fn uint_to_decimal(bytes: &[u8]) -> String {
+  let mut digits = Vec::new();
+  let mut number = bytes.to_vec();
+  while number.iter().any(|byte| *byte != 0) {
+    // Long division by 10, the remainder being the next digit.
+    let mut remainder = 0_u32;
+    for byte in number.iter_mut() {
+      let value = (remainder << 8) | u32::from(*byte);
+      *byte = (value / 10) as u8;
+      remainder = value % 10;
+    }
+    digits.push(char::from(b'0' + remainder as u8));
+  }
+
+  if digits.is_empty() {
+    return "0".to_owned();
+  }
+  digits.iter().rev().collect()
+}
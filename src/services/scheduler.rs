@@ -0,0 +1,331 @@
+use chrono::{DateTime, Utc};
+use cron::Schedule;
+use futures::future::{self, BoxFuture};
+use futures::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::Instant;
+use tokio::task::JoinHandle;
+use tokio::time::sleep;
+use tracing::{debug, error, info, warn};
+use wither::bson::doc;
+
+use crate::errors::Error;
+use crate::models::usage::Usage;
+use crate::routes::arkham;
+use crate::services::alerts;
+use crate::services::label_dataset;
+use crate::services::retention;
+use crate::services::watcher;
+use crate::settings;
+use crate::state::AppState;
+use crate::utils::metrics;
+use crate::utils::models::ModelExt;
+use crate::utils::shutdown::ShutdownSignal;
+use crate::utils::usage;
+
+type JobFn = fn(AppState) -> BoxFuture<'static, Result<(), Error>>;
+
+/// Recurring work run by the scheduler, on the cron schedule set for its
+/// name in `scheduler.jobs`.
+#[derive(Clone, Copy)]
+pub struct Job {
+  pub name: &'static str,
+  pub description: &'static str,
+  run: JobFn,
+}
+
+/// Every job the scheduler can run. Jobs are added here and scheduled in the
+/// configuration.
+pub fn jobs() -> Vec<Job> {
+  vec![
+    Job {
+      name: "watchlist_refresh",
+      description: "Re-queries the watched addresses and raises their alerts",
+      run: refresh_watchlists,
+    },
+    Job {
+      name: "cache_warmup",
+      description: "Fetches the watched addresses missing from the address cache",
+      run: warm_up_cache,
+    },
+    Job {
+      name: "label_dataset_sync",
+      description: "Imports the new labels of the label dataset",
+      run: sync_label_dataset,
+    },
+    Job {
+      name: "price_alerts",
+      description: "Raises the alerts on the prices of the tokens users are alerted on",
+      run: raise_price_alerts,
+    },
+    Job {
+      name: "usage_rollup",
+      description: "Records the usage of the current month in the metrics",
+      run: roll_up_usage,
+    },
+    Job {
+      name: "retention_cleanup",
+      description: "Removes the documents past the retention of their collection",
+      run: clean_up_expired,
+    },
+  ]
+}
+
+/// Runs the jobs on their schedule and keeps the outcome of their last run.
+/// Every server instance runs the scheduled jobs, so they must be safe to
+/// run concurrently.
+pub struct Scheduler {
+  jobs: Vec<ScheduledJob>,
+  states: Mutex<HashMap<&'static str, JobState>>,
+}
+
+struct ScheduledJob {
+  job: Job,
+  // Expression from the settings, and the schedule parsed from it.
+  expression: Option<String>,
+  schedule: Option<Schedule>,
+}
+
+#[derive(Default)]
+struct JobState {
+  running: bool,
+  next_run_at: Option<DateTime<Utc>>,
+  last_run: Option<JobRun>,
+}
+
+/// State of a job, as listed by `GET /admin/jobs`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobStatus {
+  pub name: String,
+  pub description: String,
+  // Cron expression of the job, `None` when it is not scheduled.
+  pub schedule: Option<String>,
+  pub running: bool,
+  // Unset when the scheduler is disabled.
+  pub next_run_at: Option<DateTime<Utc>>,
+  pub last_run: Option<JobRun>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobRun {
+  pub started_at: DateTime<Utc>,
+  pub duration_ms: u64,
+  pub succeeded: bool,
+  pub error: Option<String>,
+}
+
+impl Scheduler {
+  pub fn new(settings: &settings::Scheduler) -> Self {
+    let jobs = jobs();
+    for name in settings.jobs.keys() {
+      if !jobs.iter().any(|job| job.name == name) {
+        warn!("Ignoring the schedule of unknown job {}", name);
+      }
+    }
+
+    let jobs = jobs
+      .into_iter()
+      .map(|job| {
+        let expression = settings.jobs.get(job.name).cloned();
+        // Expressions are validated with the settings.
+        let schedule = expression
+          .as_deref()
+          .and_then(|expression| Schedule::from_str(expression).ok());
+
+        ScheduledJob {
+          job,
+          expression,
+          schedule,
+        }
+      })
+      .collect();
+
+    Self {
+      jobs,
+      states: Mutex::new(HashMap::new()),
+    }
+  }
+
+  /// Status of every job, scheduled or not.
+  pub fn statuses(&self) -> Vec<JobStatus> {
+    let states = self.states.lock().unwrap();
+    self
+      .jobs
+      .iter()
+      .map(|scheduled| {
+        let state = states.get(scheduled.job.name);
+        JobStatus {
+          name: scheduled.job.name.to_owned(),
+          description: scheduled.job.description.to_owned(),
+          schedule: scheduled.expression.clone(),
+          running: state.map(|state| state.running).unwrap_or(false),
+          next_run_at: state.and_then(|state| state.next_run_at),
+          last_run: state.and_then(|state| state.last_run.clone()),
+        }
+      })
+      .collect()
+  }
+
+  /// Runs a job once, recording its outcome. Failures are logged, the job
+  /// runs again on its next schedule.
+  pub async fn run(&self, state: &AppState, job: Job) {
+    self.update(job.name, |state| state.running = true);
+    info!("Running scheduled job {}", job.name);
+
+    let started_at = Utc::now();
+    let started = Instant::now();
+    let error = match (job.run)(state.clone()).await {
+      Ok(()) => None,
+      Err(err) => {
+        error!("Scheduled job {} failed: {}", job.name, err);
+        Some(err.to_string())
+      }
+    };
+    let elapsed = started.elapsed();
+    metrics::record_job_run(job.name, error.is_none(), elapsed);
+
+    self.update(job.name, |state| {
+      state.running = false;
+      state.last_run = Some(JobRun {
+        started_at,
+        duration_ms: elapsed.as_millis() as u64,
+        succeeded: error.is_none(),
+        error,
+      });
+    });
+  }
+
+  fn update<F: FnOnce(&mut JobState)>(&self, job: &'static str, update: F) {
+    update(self.states.lock().unwrap().entry(job).or_default());
+  }
+}
+
+/// Starts the scheduled jobs. A run that takes longer than its schedule
+/// skips the runs it overlaps instead of running concurrently. On shutdown,
+/// the runs in progress are finished before stopping.
+pub fn spawn(state: AppState, shutdown: ShutdownSignal) -> JoinHandle<()> {
+  tokio::spawn(async move {
+    let schedules = state
+      .scheduler
+      .jobs
+      .iter()
+      .filter_map(|scheduled| Some((scheduled.job, scheduled.schedule.clone()?)))
+      .collect::<Vec<(Job, Schedule)>>();
+    info!("Starting scheduler with {} jobs", schedules.len());
+
+    let runs = schedules
+      .into_iter()
+      .map(|(job, schedule)| run_on_schedule(&state, job, schedule, shutdown.clone()));
+    future::join_all(runs).await;
+
+    info!("Scheduler stopped");
+  })
+}
+
+async fn run_on_schedule(
+  state: &AppState,
+  job: Job,
+  schedule: Schedule,
+  mut shutdown: ShutdownSignal,
+) {
+  // Schedules only end when their expression restricts the years.
+  while let Some(next_run_at) = schedule.upcoming(Utc).next() {
+    state
+      .scheduler
+      .update(job.name, |state| state.next_run_at = Some(next_run_at));
+    let delay = (next_run_at - Utc::now()).to_std().unwrap_or_default();
+
+    tokio::select! {
+      _ = sleep(delay) => {}
+      _ = shutdown.requested() => break,
+    }
+    state.scheduler.run(state, job).await;
+  }
+}
+
+fn refresh_watchlists(state: AppState) -> BoxFuture<'static, Result<(), Error>> {
+  Box::pin(async move { watcher::poll(&state).await })
+}
+
+/// Addresses still cached are skipped, so the job only fetches the ones
+/// that expired since the last run.
+fn warm_up_cache(state: AppState) -> BoxFuture<'static, Result<(), Error>> {
+  Box::pin(async move {
+    let addresses = watcher::watched_addresses().await?;
+    debug!("Warming up the cache with {} addresses", addresses.len());
+
+    let concurrency = state.settings.arkham.batch_concurrency;
+    stream::iter(addresses)
+      .for_each_concurrent(concurrency, |address| {
+        let state = &state;
+        async move {
+          if let Err(err) = arkham::lookup_address(state, &address, false).await {
+            warn!("Failed to warm up the cache with {}: {}", address, err);
+          }
+        }
+      })
+      .await;
+
+    Ok(())
+  })
+}
+
+/// Replaces the dataset labels with the current dataset, see
+/// `label_dataset::reload`.
+fn sync_label_dataset(state: AppState) -> BoxFuture<'static, Result<(), Error>> {
+  Box::pin(async move {
+    label_dataset::reload(&state.settings.label_dataset.url).await?;
+
+    Ok(())
+  })
+}
+
+fn raise_price_alerts(state: AppState) -> BoxFuture<'static, Result<(), Error>> {
+  Box::pin(async move {
+    let settings = &state.settings.price_alerts;
+    let events = alerts::process_prices(state.prices.as_ref(), settings).await?;
+    alerts::publish(&state, &events).await
+  })
+}
+
+fn roll_up_usage(_state: AppState) -> BoxFuture<'static, Result<(), Error>> {
+  Box::pin(async move {
+    let pipeline = vec![
+      doc! { "$match": { "period": usage::period(Utc::now()) } },
+      doc! { "$group": {
+        "_id": null,
+        "users": { "$sum": 1 },
+        "requests": { "$sum": "$requests" },
+        "upstream_credits": { "$sum": "$upstream_credits" }
+      } },
+    ];
+    let totals = Usage::aggregate::<UsageTotals>(pipeline)
+      .await?
+      .into_iter()
+      .next()
+      .unwrap_or_default();
+
+    metrics::record_usage_totals(totals.users, totals.requests, totals.upstream_credits);
+    Ok(())
+  })
+}
+
+/// Removes the old snapshots, alert events, audit logs and webhook
+/// deliveries, see `retention::clean_up`.
+fn clean_up_expired(state: AppState) -> BoxFuture<'static, Result<(), Error>> {
+  Box::pin(async move {
+    retention::clean_up(&state.settings.retention).await?;
+
+    Ok(())
+  })
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct UsageTotals {
+  users: u64,
+  requests: u64,
+  upstream_credits: u64,
+}
@@ -0,0 +1,76 @@
+use serde::Deserialize;
+use tracing::debug;
+use utoipa::ToSchema;
+use validator::Validate;
+use wither::bson::{doc, oid::ObjectId};
+use wither::mongodb::options::FindOptions;
+
+use crate::errors::Error;
+use crate::models::share::{PublicShare, Share, ShareAccess, SharedResource};
+use crate::models::user::User;
+use crate::utils::date;
+use crate::utils::models::ModelExt;
+
+/// Body of `POST /cats/:id/shares` and `POST /watchlists/:id/shares`.
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct CreateShare {
+  // Email of the user the resource is shared with.
+  #[validate(email)]
+  pub email: String,
+  pub access: ShareAccess,
+}
+
+/// Shares a resource owned by `granted_by` with the user registered with
+/// the email, or changes the access of their existing grant. Returns the
+/// grant and whether it was created.
+pub async fn grant(
+  resource_type: SharedResource,
+  resource: ObjectId,
+  granted_by: ObjectId,
+  payload: CreateShare,
+) -> Result<(PublicShare, bool), Error> {
+  let user = match User::find_one(doc! { "email": payload.email.trim() }, None).await? {
+    Some(user) => user.id.unwrap(),
+    None => {
+      debug!("User not found, returning 404 status code");
+      return Err(Error::not_found());
+    }
+  };
+  if user == granted_by {
+    debug!("Resource shared with its owner, returning 400 status code");
+    return Err(Error::bad_request());
+  }
+
+  let share = Share::find_one_and_update(
+    doc! { "resource": &resource, "user": &user },
+    doc! { "$set": { "access": payload.access.as_str(), "updated_at": date::now() } },
+  )
+  .await?;
+  if let Some(share) = share {
+    return Ok((PublicShare::from(share), false));
+  }
+
+  let share = Share::new(resource_type, resource, user, granted_by, payload.access);
+  let share = Share::create(share).await?;
+  Ok((PublicShare::from(share), true))
+}
+
+/// Grants of a resource, oldest first.
+pub async fn list(resource: ObjectId) -> Result<Vec<PublicShare>, Error> {
+  let options = FindOptions::builder()
+    .sort(doc! { "created_at": 1_i32 })
+    .build();
+  let shares = Share::find(doc! { "resource": &resource }, options)
+    .await?
+    .into_iter()
+    .map(PublicShare::from)
+    .collect();
+
+  Ok(shares)
+}
+
+/// Revokes the grant of a user, returning whether there was one.
+pub async fn revoke(resource: ObjectId, user: ObjectId) -> Result<bool, Error> {
+  let result = Share::delete_one(doc! { "resource": &resource, "user": &user }).await?;
+  Ok(result.deleted_count > 0)
+}
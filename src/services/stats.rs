@@ -0,0 +1,219 @@
+use bson::{Bson, Document};
+use chrono::{Duration, NaiveDate, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+use utoipa::ToSchema;
+use wither::bson::{doc, oid::ObjectId};
+use wither::mongodb::options::UpdateOptions;
+
+use crate::errors::Error;
+use crate::models::address_label::AddressLabel;
+use crate::models::address_query::AddressQuery;
+use crate::models::cat::Cat;
+use crate::models::usage::Usage;
+use crate::models::watched_address::WatchedAddress;
+use crate::models::watchlist::Watchlist;
+use crate::routes::label::organizations_visible_to;
+use crate::services::ownership::Ownership;
+use crate::utils::date::{self, Date};
+use crate::utils::models::ModelExt;
+use crate::utils::usage;
+
+pub const DEFAULT_DAYS: u32 = 30;
+pub const MAX_DAYS: u32 = 365;
+// Entries of the rankings, the largest watchlists and the most queried
+// addresses.
+const TOP_LIMIT: i64 = 10;
+
+/// Statistics of a user, or of every user, over the last days.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct Stats {
+  pub days: u32,
+  pub cats_per_day: Vec<DayCount>,
+  pub labels_per_source: Vec<SourceCount>,
+  pub largest_watchlists: Vec<WatchlistSize>,
+  pub top_queried_addresses: Vec<AddressCount>,
+  pub upstream_credits: Vec<PeriodCredits>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct DayCount {
+  // Day in UTC, e.g. `2024-05-21`.
+  pub day: String,
+  pub count: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SourceCount {
+  pub source: String,
+  pub count: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct WatchlistSize {
+  #[schema(value_type = String)]
+  pub id: ObjectId,
+  pub name: String,
+  pub addresses: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct AddressCount {
+  pub address: String,
+  pub count: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct PeriodCredits {
+  // Month in UTC, e.g. `2024-05`.
+  pub period: String,
+  pub requests: u64,
+  pub upstream_credits: u64,
+}
+
+/// Statistics over the last `days`, of a user or of every user when `None`.
+/// Labels are counted among the ones visible to the user.
+pub async fn compute(user: Option<ObjectId>, days: u32) -> Result<Stats, Error> {
+  let first_day = Utc::now().date_naive() - Duration::days(i64::from(days) - 1);
+  let since = Utc.from_utc_datetime(&first_day.and_hms_opt(0, 0, 0).unwrap());
+
+  let scope = match user {
+    Some(user) => doc! { "user": user },
+    None => doc! {},
+  };
+  let labels_scope = match user {
+    Some(user) => {
+      let ownership = Ownership::load(user).await?;
+      doc! { "organization": organizations_visible_to(Some(&ownership)) }
+    }
+    None => doc! {},
+  };
+
+  Ok(Stats {
+    days,
+    cats_per_day: cats_per_day(scope.clone(), since.into()).await?,
+    labels_per_source: labels_per_source(labels_scope).await?,
+    largest_watchlists: largest_watchlists(scope.clone()).await?,
+    top_queried_addresses: top_queried_addresses(scope.clone(), &day(first_day)).await?,
+    upstream_credits: upstream_credits(scope, &usage::period(since)).await?,
+  })
+}
+
+/// Counts a lookup of an address. Failures are logged without failing the
+/// request, the statistics are best effort.
+pub async fn record_address_query(user: Option<ObjectId>, address: String) {
+  let now = date::now();
+  let day = day(Utc::now().date_naive());
+  let options = UpdateOptions::builder().upsert(true).build();
+
+  let result = AddressQuery::update_one(
+    doc! { "user": user, "address": &address, "day": day },
+    doc! {
+      "$inc": { "count": 1_i64 },
+      "$set": { "updated_at": now },
+      "$setOnInsert": { "created_at": now },
+    },
+    options,
+  )
+  .await;
+
+  if let Err(err) = result {
+    error!("Failed to record the query of address {}: {}", address, err);
+  }
+}
+
+/// Day of a date, as stored by `AddressQuery` and returned by `DayCount`.
+fn day(date: NaiveDate) -> String {
+  date.format("%Y-%m-%d").to_string()
+}
+
+async fn cats_per_day(mut query: Document, since: Date) -> Result<Vec<DayCount>, Error> {
+  // Aggregations don't exclude the removed cats.
+  query.insert("deleted_at", Bson::Null);
+  query.insert("created_at", doc! { "$gte": since });
+  let pipeline = vec![
+    doc! { "$match": query },
+    doc! { "$group": {
+      "_id": { "$dateToString": { "format": "%Y-%m-%d", "date": "$created_at" } },
+      "count": { "$sum": 1_i32 }
+    } },
+    doc! { "$sort": { "_id": 1_i32 } },
+    doc! { "$project": { "_id": 0_i32, "day": "$_id", "count": 1_i32 } },
+  ];
+
+  Cat::aggregate::<DayCount>(pipeline).await
+}
+
+async fn labels_per_source(query: Document) -> Result<Vec<SourceCount>, Error> {
+  let pipeline = vec![
+    doc! { "$match": query },
+    doc! { "$group": { "_id": "$source", "count": { "$sum": 1_i32 } } },
+    doc! { "$sort": { "count": -1_i32, "_id": 1_i32 } },
+    doc! { "$project": { "_id": 0_i32, "source": "$_id", "count": 1_i32 } },
+  ];
+
+  AddressLabel::aggregate::<SourceCount>(pipeline).await
+}
+
+async fn largest_watchlists(query: Document) -> Result<Vec<WatchlistSize>, Error> {
+  let watched_addresses = <WatchedAddress as wither::Model>::COLLECTION_NAME;
+  let pipeline = vec![
+    doc! { "$match": query },
+    doc! { "$lookup": {
+      "from": watched_addresses,
+      "localField": "_id",
+      "foreignField": "watchlist",
+      "as": "addresses",
+    } },
+    doc! { "$project": {
+      "_id": 0_i32,
+      "id": "$_id",
+      "name": 1_i32,
+      "addresses": { "$size": "$addresses" }
+    } },
+    doc! { "$sort": { "addresses": -1_i32, "id": 1_i32 } },
+    doc! { "$limit": TOP_LIMIT },
+  ];
+
+  Watchlist::aggregate::<WatchlistSize>(pipeline).await
+}
+
+async fn top_queried_addresses(
+  mut query: Document,
+  first_day: &str,
+) -> Result<Vec<AddressCount>, Error> {
+  query.insert("day", doc! { "$gte": first_day });
+  let pipeline = vec![
+    doc! { "$match": query },
+    doc! { "$group": { "_id": "$address", "count": { "$sum": "$count" } } },
+    doc! { "$sort": { "count": -1_i32, "_id": 1_i32 } },
+    doc! { "$limit": TOP_LIMIT },
+    doc! { "$project": { "_id": 0_i32, "address": "$_id", "count": 1_i32 } },
+  ];
+
+  AddressQuery::aggregate::<AddressCount>(pipeline).await
+}
+
+async fn upstream_credits(
+  mut query: Document,
+  first_period: &str,
+) -> Result<Vec<PeriodCredits>, Error> {
+  query.insert("period", doc! { "$gte": first_period });
+  let pipeline = vec![
+    doc! { "$match": query },
+    doc! { "$group": {
+      "_id": "$period",
+      "requests": { "$sum": "$requests" },
+      "upstream_credits": { "$sum": "$upstream_credits" }
+    } },
+    doc! { "$sort": { "_id": 1_i32 } },
+    doc! { "$project": {
+      "_id": 0_i32,
+      "period": "$_id",
+      "requests": 1_i32,
+      "upstream_credits": 1_i32
+    } },
+  ];
+
+  Usage::aggregate::<PeriodCredits>(pipeline).await
+}
@@ -0,0 +1,142 @@
+use futures::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio::time::{interval, MissedTickBehavior};
+use tracing::{debug, error, info, warn};
+use wither::bson::doc;
+use wither::mongodb::options::FindOneOptions;
+
+use crate::errors::Error;
+use crate::models::address_snapshot::AddressSnapshot;
+use crate::models::watched_address::WatchedAddress;
+use crate::routes::arkham::ArkhamResponse;
+use crate::services::alerts;
+use crate::state::AppState;
+use crate::utils::models::ModelExt;
+use crate::utils::shutdown::ShutdownSignal;
+
+/// Emitted by the watcher when the Arkham data of a watched address differs
+/// from its previous snapshot.
+#[derive(Debug, Clone)]
+pub struct AddressChange {
+  pub address: String,
+  pub previous: ArkhamResponse,
+  pub current: ArkhamResponse,
+}
+
+/// Starts the worker re-querying Arkham for the watched addresses every
+/// `watcher.poll_interval_secs`. A poll that takes longer than the interval
+/// delays the next one instead of overlapping with it. On shutdown, the poll
+/// in progress is finished before stopping.
+pub fn spawn(state: AppState, mut shutdown: ShutdownSignal) -> JoinHandle<()> {
+  let period = Duration::from_secs(state.settings.watcher.poll_interval_secs);
+  info!("Starting address watcher, polling every {:?}", period);
+
+  tokio::spawn(async move {
+    let mut ticks = interval(period);
+    ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+      tokio::select! {
+        _ = ticks.tick() => {}
+        _ = shutdown.requested() => break,
+      }
+      if let Err(err) = poll(&state).await {
+        error!("Failed to poll watched addresses: {}", err);
+      }
+    }
+
+    info!("Address watcher stopped");
+  })
+}
+
+/// Polls every watched address once, along with the transfers of the ones
+/// with whale transfer alerts. Arkham returns the data of all chains for an
+/// address, so addresses watched on several chains or by several users are
+/// only fetched once.
+pub async fn poll(state: &AppState) -> Result<(), Error> {
+  let addresses = watched_addresses().await?;
+  debug!("Polling {} watched addresses", addresses.len());
+
+  stream::iter(addresses)
+    .for_each_concurrent(state.settings.watcher.concurrency, |address| async move {
+      if let Err(err) = poll_address(state, address.as_str()).await {
+        warn!("Failed to poll watched address {}: {}", address, err);
+      }
+      if let Err(err) = poll_transfers(state, address.as_str()).await {
+        warn!(
+          "Failed to poll transfers of watched address {}: {}",
+          address, err
+        );
+      }
+    })
+    .await;
+
+  Ok(())
+}
+
+/// Addresses watched by at least one user, once each.
+pub async fn watched_addresses() -> Result<Vec<String>, Error> {
+  let addresses =
+    WatchedAddress::aggregate::<DistinctAddress>(vec![doc! { "$group": { "_id": "$address" } }])
+      .await?
+      .into_iter()
+      .map(|watched| watched.address)
+      .collect();
+
+  Ok(addresses)
+}
+
+/// Fetches an address and records a snapshot when its data changed since the
+/// last one.
+async fn poll_address(state: &AppState, address: &str) -> Result<(), Error> {
+  let current = state.address_intelligence.lookup_address(address).await?;
+
+  let options = FindOneOptions::builder()
+    .sort(doc! { "created_at": -1_i32, "_id": -1_i32 })
+    .build();
+  let previous = AddressSnapshot::find_one(doc! { "address": address }, options).await?;
+
+  if let Some(previous) = &previous {
+    if previous.data == current {
+      debug!("Watched address {} is unchanged", address);
+      return Ok(());
+    }
+  }
+
+  AddressSnapshot::create(AddressSnapshot::new(address.to_owned(), current.clone())).await?;
+
+  // The first snapshot of an address is a baseline, not a change.
+  if let Some(previous) = previous {
+    info!("Watched address {} changed", address);
+    let change = AddressChange {
+      address: address.to_owned(),
+      previous: previous.data,
+      current,
+    };
+
+    match alerts::process_change(&change).await {
+      Ok(events) => alerts::publish(state, &events).await?,
+      Err(err) => error!("Failed to evaluate alert rules of {}: {}", address, err),
+    }
+
+    // Sending only fails when nobody is subscribed, which is fine.
+    let _ = state.address_changes.send(change);
+  }
+
+  Ok(())
+}
+
+/// Raises the whale transfer alerts of an address, see
+/// `alerts::process_transfers`.
+async fn poll_transfers(state: &AppState, address: &str) -> Result<(), Error> {
+  let events = alerts::process_transfers(state.address_intelligence.as_ref(), address).await?;
+  alerts::publish(state, &events).await
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct DistinctAddress {
+  #[serde(rename = "_id")]
+  address: String,
+}
@@ -0,0 +1,394 @@
+use chrono::Utc;
+use futures::stream::{self, StreamExt};
+use hmac::{Hmac, Mac};
+use reqwest::header;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio::time::{interval, MissedTickBehavior};
+use tracing::{debug, error, info, warn};
+use wither::bson::{doc, oid::ObjectId};
+
+use crate::errors::Error;
+use crate::models::alert_event::{AlertEvent, PublicAlertEvent};
+use crate::models::alert_rule::AlertCondition;
+use crate::models::webhook_dead_letter::WebhookDeadLetter;
+use crate::models::webhook_delivery::WebhookDelivery;
+use crate::models::webhook_endpoint::WebhookEndpoint;
+use crate::settings;
+use crate::utils::date;
+use crate::utils::models::ModelExt;
+use crate::utils::retry::RetryPolicy;
+use crate::utils::shutdown::ShutdownSignal;
+use crate::utils::telemetry;
+
+// Header carrying the delivery id, so receivers can drop duplicates.
+const DELIVERY_HEADER: &str = "x-webhook-delivery";
+// Header carrying the signature of the payload along with the unix time it
+// was signed at, so receivers can reject replayed payloads, see
+// `signature_header`.
+pub const SIGNATURE_HEADER: &str = "x-degen-signature";
+
+// Address of the sample events sent by `send_test`.
+const SAMPLE_ADDRESS: &str = "0x0000000000000000000000000000000000000000";
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Body posted to webhook endpoints.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WebhookPayload {
+  pub delivery: String,
+  // Whether the event is a sample sent with `send_test`.
+  #[serde(default)]
+  pub test: bool,
+  pub event: PublicAlertEvent,
+}
+
+/// Queues the delivery of alert events to every webhook endpoint of their
+/// users accepting them. Returns the number of queued deliveries.
+pub async fn enqueue(events: &[AlertEvent]) -> Result<u64, Error> {
+  if events.is_empty() {
+    return Ok(0);
+  }
+
+  let users = events
+    .iter()
+    .map(|event| event.user)
+    .collect::<Vec<ObjectId>>();
+  let endpoints = WebhookEndpoint::find(doc! { "user": { "$in": users } }, None).await?;
+
+  let mut endpoints_by_user: HashMap<ObjectId, Vec<WebhookEndpoint>> = HashMap::new();
+  for endpoint in endpoints {
+    endpoints_by_user
+      .entry(endpoint.user)
+      .or_default()
+      .push(endpoint);
+  }
+
+  let deliveries = events
+    .iter()
+    .flat_map(|event| {
+      let endpoints = endpoints_by_user
+        .get(&event.user)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+      endpoints
+        .iter()
+        .filter(|endpoint| endpoint.accepts(event.condition))
+        .map(|endpoint| WebhookDelivery::new(event.user, endpoint.id.unwrap(), event.id.unwrap()))
+    })
+    .collect::<Vec<WebhookDelivery>>();
+
+  WebhookDelivery::insert_many(deliveries).await
+}
+
+/// Starts the worker posting the queued deliveries every
+/// `webhooks.poll_interval_ms`.
+pub fn spawn(settings: settings::Webhooks, mut shutdown: ShutdownSignal) -> JoinHandle<()> {
+  let period = Duration::from_millis(settings.poll_interval_ms);
+  info!(
+    "Starting webhook delivery worker, polling every {:?}",
+    period
+  );
+
+  tokio::spawn(async move {
+    let client = http_client(&settings);
+    let mut ticks = interval(period);
+    ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+      tokio::select! {
+        _ = ticks.tick() => {}
+        _ = shutdown.requested() => break,
+      }
+      if let Err(err) = deliver_due(&client, &settings).await {
+        error!("Failed to deliver webhooks: {}", err);
+      }
+    }
+
+    info!("Webhook delivery worker stopped");
+  })
+}
+
+pub fn http_client(settings: &settings::Webhooks) -> reqwest::Client {
+  reqwest::Client::builder()
+    .timeout(Duration::from_millis(settings.timeout_ms))
+    .build()
+    .expect("Failed to build webhook HTTP client")
+}
+
+/// Claims the deliveries due and attempts them once. Returns the number of
+/// attempted deliveries.
+pub async fn deliver_due(
+  client: &reqwest::Client,
+  settings: &settings::Webhooks,
+) -> Result<u64, Error> {
+  let mut deliveries = Vec::new();
+  while deliveries.len() < settings.batch_size as usize {
+    match claim(settings).await? {
+      Some(delivery) => deliveries.push(delivery),
+      None => break,
+    }
+  }
+
+  let count = deliveries.len() as u64;
+  stream::iter(deliveries)
+    .for_each_concurrent(settings.concurrency, |delivery| async move {
+      let id = delivery.id.unwrap();
+      if let Err(err) = attempt(client, settings, delivery).await {
+        error!("Failed to record webhook delivery {}: {}", id, err);
+      }
+    })
+    .await;
+
+  Ok(count)
+}
+
+/// Leases a due delivery by pushing back its next attempt, so other server
+/// instances polling at the same time don't send it too.
+async fn claim(settings: &settings::Webhooks) -> Result<Option<WebhookDelivery>, Error> {
+  let lease = Duration::from_millis(settings.timeout_ms).saturating_mul(2);
+  WebhookDelivery::find_one_and_update(
+    doc! { "status": "pending", "next_attempt_at": { "$lte": date::now() } },
+    doc! { "$set": { "next_attempt_at": date::after(lease) } },
+  )
+  .await
+}
+
+async fn attempt(
+  client: &reqwest::Client,
+  settings: &settings::Webhooks,
+  delivery: WebhookDelivery,
+) -> Result<(), Error> {
+  let id = delivery.id.unwrap();
+  let event = AlertEvent::find_by_id(&delivery.event).await?;
+  let endpoint = WebhookEndpoint::find_by_id(&delivery.endpoint).await?;
+
+  let result = match (event, endpoint) {
+    (Some(event), Some(endpoint)) => {
+      let payload = WebhookPayload {
+        delivery: id.to_hex(),
+        test: false,
+        event: event.into(),
+      };
+      post(client, &endpoint, &payload).await
+    }
+    // Nothing left to deliver, retrying would not help.
+    _ => {
+      let error = String::from("Alert event or webhook endpoint was removed");
+      return dead_letter(delivery, error).await;
+    }
+  };
+
+  let attempts = delivery.attempts + 1;
+  let now = date::now();
+  match result {
+    Ok(()) => {
+      debug!("Delivered webhook {}", id);
+      WebhookDelivery::update_one(
+        doc! { "_id": id },
+        doc! { "$set": {
+          "status": "delivered",
+          "attempts": attempts,
+          "delivered_at": now,
+          "updated_at": now
+        } },
+        None,
+      )
+      .await?;
+    }
+    Err(error) if attempts >= settings.max_attempts => {
+      warn!("Webhook delivery {} failed permanently: {}", id, error);
+      return dead_letter(
+        WebhookDelivery {
+          attempts,
+          ..delivery
+        },
+        error,
+      )
+      .await;
+    }
+    Err(error) => {
+      let policy = RetryPolicy {
+        max_attempts: settings.max_attempts,
+        base_delay: Duration::from_millis(settings.retry_base_delay_ms),
+        max_delay: Duration::from_millis(settings.retry_max_delay_ms),
+      };
+      let delay = policy.delay(attempts);
+      debug!(
+        "Webhook delivery {} failed, retrying in {:?}: {}",
+        id, delay, error
+      );
+
+      WebhookDelivery::update_one(
+        doc! { "_id": id },
+        doc! { "$set": {
+          "attempts": attempts,
+          "next_attempt_at": date::after(delay),
+          "last_error": error,
+          "updated_at": now
+        } },
+        None,
+      )
+      .await?;
+    }
+  }
+
+  Ok(())
+}
+
+/// Posts a sample alert event to an endpoint once, without queuing a
+/// delivery, so users can check their receiver. Returns the error message of
+/// a failed attempt.
+pub async fn send_test(client: &reqwest::Client, endpoint: &WebhookEndpoint) -> Result<(), String> {
+  let condition = endpoint
+    .events
+    .first()
+    .copied()
+    .unwrap_or(AlertCondition::EntityAttached);
+  let event = AlertEvent {
+    id: Some(ObjectId::new()),
+    ..AlertEvent::new(
+      endpoint.user,
+      ObjectId::new(),
+      condition,
+      SAMPLE_ADDRESS.to_owned(),
+      "ethereum".to_owned(),
+      "Test event sent to check the webhook endpoint".to_owned(),
+    )
+  };
+
+  let payload = WebhookPayload {
+    delivery: ObjectId::new().to_hex(),
+    test: true,
+    event: event.into(),
+  };
+  post(client, endpoint, &payload).await
+}
+
+/// Hex HMAC-SHA256 of the payload signed at the unix `timestamp`, keyed by
+/// the secret of the endpoint. Receivers compute it over the timestamp, a
+/// `.` and the raw body, and compare it with the `v1` signature, see
+/// `verify`.
+pub fn sign(secret: &str, timestamp: &str, body: &[u8]) -> String {
+  hex::encode(mac(secret, timestamp, body).finalize().into_bytes())
+}
+
+/// Value of the signature header of a payload signed at the unix
+/// `timestamp`, e.g. `t=1700000000,v1=5257a869...`.
+pub fn signature_header(secret: &str, timestamp: i64, body: &[u8]) -> String {
+  let timestamp = timestamp.to_string();
+  format!("t={},v1={}", timestamp, sign(secret, &timestamp, body))
+}
+
+/// Why a received payload failed `verify`.
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum SignatureError {
+  #[error("Malformed signature header")]
+  Malformed,
+  #[error("Payload was signed too long ago")]
+  Expired,
+  #[error("Signature does not match the payload")]
+  Mismatch,
+}
+
+/// Verifies the signature header of a payload received by a webhook
+/// endpoint, for receivers written in Rust. `body` is the raw request body,
+/// the signature covers its exact bytes. Payloads signed more than
+/// `tolerance` away from `now`, a unix timestamp, are rejected so captured
+/// requests can't be replayed later. Unknown signature schemes are ignored.
+// Not called by the API itself, it only sends webhooks.
+#[allow(dead_code)]
+pub fn verify(
+  secret: &str,
+  header: &str,
+  body: &[u8],
+  now: i64,
+  tolerance: Duration,
+) -> Result<(), SignatureError> {
+  let mut timestamp = None;
+  let mut signatures = Vec::new();
+  for part in header.split(',') {
+    match part.trim().split_once('=') {
+      Some(("t", value)) => timestamp = Some(value),
+      Some(("v1", value)) => signatures.push(value),
+      _ => {}
+    }
+  }
+
+  let timestamp = timestamp.ok_or(SignatureError::Malformed)?;
+  let signed_at = timestamp
+    .parse::<i64>()
+    .map_err(|_| SignatureError::Malformed)?;
+  if signatures.is_empty() {
+    return Err(SignatureError::Malformed);
+  }
+
+  if now.abs_diff(signed_at) > tolerance.as_secs() {
+    return Err(SignatureError::Expired);
+  }
+
+  // Compared in constant time by the MAC.
+  let matches = signatures
+    .into_iter()
+    .filter_map(|signature| hex::decode(signature).ok())
+    .any(|signature| {
+      mac(secret, timestamp, body)
+        .verify_slice(&signature)
+        .is_ok()
+    });
+  if !matches {
+    return Err(SignatureError::Mismatch);
+  }
+
+  Ok(())
+}
+
+fn mac(secret: &str, timestamp: &str, body: &[u8]) -> HmacSha256 {
+  let mut mac =
+    HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+  mac.update(timestamp.as_bytes());
+  mac.update(b".");
+  mac.update(body);
+  mac
+}
+
+/// Posts the payload, signed when the endpoint has a secret, turning
+/// unsuccessful responses into an error message.
+async fn post(
+  client: &reqwest::Client,
+  endpoint: &WebhookEndpoint,
+  payload: &WebhookPayload,
+) -> Result<(), String> {
+  let body = serde_json::to_vec(payload).map_err(|err| err.to_string())?;
+
+  let mut req = client
+    .post(&endpoint.url)
+    .header(header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+    .header(DELIVERY_HEADER, &payload.delivery)
+    .headers(telemetry::trace_headers());
+  if let Some(secret) = &endpoint.secret {
+    let signature = signature_header(secret, Utc::now().timestamp(), &body);
+    req = req.header(SIGNATURE_HEADER, signature);
+  }
+
+  let res = req.body(body).send().await.map_err(|err| err.to_string())?;
+
+  let status = res.status();
+  if !status.is_success() {
+    return Err(format!("Received a {} status code", status));
+  }
+
+  Ok(())
+}
+
+/// Moves a delivery to the dead letters.
+async fn dead_letter(delivery: WebhookDelivery, error: String) -> Result<(), Error> {
+  let id = delivery.id.unwrap();
+  WebhookDeadLetter::create(WebhookDeadLetter::new(delivery, error)).await?;
+  WebhookDelivery::delete_one(doc! { "_id": id }).await?;
+
+  Ok(())
+}
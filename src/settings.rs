@@ -1,64 +1,798 @@
+use chrono::{DateTime, Utc};
 use config::{Config, ConfigError, Environment, File};
 use lazy_static::lazy_static;
+use lettre::message::Mailbox;
 use serde::Deserialize;
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+use std::time::Duration;
 use std::{env, fmt};
 
+use crate::services::label_merge::LABEL_SOURCES;
+use crate::utils::address::normalize_evm_address;
+use crate::utils::api_version::VERSIONS;
+
 lazy_static! {
   pub static ref SETTINGS: Settings = Settings::new().expect("Failed to setup settings");
 }
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct Server {
+  pub host: String,
+  pub port: u16,
+  // On SIGTERM or SIGINT, in-flight requests are given this long to
+  // complete, then the background workers the same to stop.
+  pub shutdown_timeout_secs: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Grpc {
+  // Serves `proto/degen.proto` next to the HTTP API, on the same host.
+  pub enabled: bool,
   pub port: u16,
 }
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct Logger {
   pub level: String,
+  // `json` writes one JSON object per line, for log collectors like Loki or
+  // Datadog, see `logger::JsonFormat`.
+  pub format: LogFormat,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+  Text,
+  Json,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Redaction {
+  // Scrubs the secrets from the logs and the error responses, see
+  // `utils::redact`.
+  pub enabled: bool,
+  // Secrets to scrub on top of the configured API keys and tokens, e.g. the
+  // keys embedded in the `rpc` provider URLs.
+  #[serde(default)]
+  pub secrets: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Telemetry {
+  // Exports spans to an OTLP collector over gRPC, e.g.
+  // `http://localhost:4317`.
+  pub enabled: bool,
+  pub otlp_endpoint: String,
+  pub service_name: String,
+  // Share of the traces started by the API that are exported, between 0 and
+  // 1. Traces started by callers follow their sampling decision.
+  pub sample_ratio: f64,
 }
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct Database {
   pub uri: String,
   pub name: String,
+  // Client options, the ones left unset keep the value of the URI or the
+  // default of the driver.
+  pub min_pool_size: Option<u32>,
+  pub max_pool_size: Option<u32>,
+  pub server_selection_timeout_ms: Option<u64>,
+  pub connect_timeout_ms: Option<u64>,
+  // Members of the replica set reads are sent to, e.g. `secondary_preferred`
+  // to move the reads off the primary.
+  pub read_preference: Option<ReadPreferenceMode>,
+  pub retry_writes: Option<bool>,
+  pub connect_attempts: u32,
+  pub connect_retry_delay_ms: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReadPreferenceMode {
+  Primary,
+  PrimaryPreferred,
+  Secondary,
+  SecondaryPreferred,
+  Nearest,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CacheBackend {
+  Memory,
+  Redis,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Cache {
+  // `memory` keeps the caches in each instance, `redis` shares them between
+  // the instances of a deployment.
+  pub backend: CacheBackend,
+  // Redis server of the `redis` backend, e.g. `redis://localhost:6379`.
+  pub redis_url: String,
+  // Prefix of the Redis keys, so several deployments can share a server.
+  pub key_prefix: String,
 }
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct Auth {
   pub secret: String,
+  // Access tokens are short lived, clients get new ones with their refresh
+  // token until it expires.
+  pub access_token_ttl_secs: u64,
+  pub refresh_token_ttl_secs: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Siwe {
+  // Domain Sign-In With Ethereum messages must be issued for, e.g.
+  // `app.example.com`.
+  pub domain: String,
+  pub nonce_ttl_secs: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Mfa {
+  // Shown by authenticator apps next to the account, see `utils::totp`.
+  pub issuer: String,
+  // Recovery codes handed out when two-factor authentication is enabled.
+  pub recovery_codes: usize,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Account {
+  // Web app the links of the account emails point to, e.g.
+  // `https://app.example.com`.
+  pub app_url: String,
+  pub password_reset_ttl_secs: u64,
+  pub email_verification_ttl_secs: u64,
+  // Time users have to confirm the deletion of their account.
+  pub deletion_confirmation_ttl_secs: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Pagination {
+  pub max_limit: u64,
+  // Counts of the cats listed with a cursor are cached per filter for this
+  // long, zero counts them on every page, see `CountStrategy`.
+  pub count_cache_ttl_secs: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IntelligenceBackend {
+  Arkham,
+  Fixture,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Arkham {
+  // `arkham` calls the Arkham API, `fixture` serves the data of
+  // `fixtures_path` instead, to run without an API key.
+  pub provider: IntelligenceBackend,
+  // JSON file of the `fixture` provider, see
+  // `services::address_intelligence::FixtureProvider`.
+  pub fixtures_path: String,
+  pub url: String,
+  pub api_key: String,
+  pub cache_ttl_secs: u64,
+  // Maximum number of addresses accepted by a single batch request.
+  pub batch_max_addresses: usize,
+  // Maximum number of concurrent upstream requests made by batch lookups,
+  // shared by every batch request.
+  pub batch_concurrency: usize,
+  // Retries of failed upstream requests, with an exponential backoff.
+  pub retry_attempts: u32,
+  pub retry_base_delay_ms: u64,
+  pub retry_max_delay_ms: u64,
+  // Rate limit of the Arkham plan, shared by every request made with the
+  // API key. Requests over it wait up to `max_queue_wait_ms` before being
+  // rejected.
+  pub requests_per_sec: f64,
+  pub requests_burst: u32,
+  pub max_queue_wait_ms: u64,
+  // Consecutive failed requests after which Arkham requests are rejected
+  // without being sent, until `circuit_reset_secs` have passed.
+  pub circuit_failure_threshold: u32,
+  pub circuit_reset_secs: u64,
+  // Address streams look the address up every `stream_refresh_secs`, and
+  // send a keep-alive comment every `stream_keep_alive_secs`.
+  pub stream_refresh_secs: u64,
+  pub stream_keep_alive_secs: u64,
+  // Limits of the related address graphs, see `services::graph`.
+  pub graph_max_depth: usize,
+  pub graph_max_nodes: usize,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Ens {
+  // Ethereum JSON-RPC endpoint ENS names are resolved with.
+  pub rpc_url: String,
+  // Address of the ENS registry contract.
+  pub registry: String,
+  // How long resolved names and addresses are cached.
+  pub cache_ttl_secs: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Prices {
+  // CoinGecko API, e.g. `https://api.coingecko.com/api/v3`. The key is
+  // optional, requests without one get a lower rate limit.
+  pub url: String,
+  pub api_key: String,
+  pub cache_ttl_secs: u64,
+  // Maximum number of tokens accepted by a single request.
+  pub max_ids: usize,
+  // Consecutive failures after which CoinGecko requests are rejected
+  // without being sent, until `circuit_reset_secs` have passed.
+  pub circuit_failure_threshold: u32,
+  pub circuit_reset_secs: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Balances {
+  // Covalent API, e.g. `https://api.covalenthq.com/v1`.
+  pub url: String,
+  pub api_key: String,
+  // Helius JSON-RPC API, e.g. `https://mainnet.helius-rpc.com`, queried for
+  // the balances of Solana addresses, which Covalent doesn't serve.
+  pub solana_url: String,
+  pub solana_api_key: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Bitcoin {
+  // Blockchair Bitcoin API, e.g. `https://api.blockchair.com/bitcoin`. The
+  // API key is optional, requests without one get a lower rate limit.
+  pub url: String,
+  pub api_key: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Portfolio {
+  // Zerion API, e.g. `https://api.zerion.io/v1`.
+  pub url: String,
+  pub api_key: String,
+  pub cache_ttl_secs: u64,
+  pub retry_attempts: u32,
+  pub retry_base_delay_ms: u64,
+  pub retry_max_delay_ms: u64,
+  // Consecutive failures after which Zerion requests are rejected without
+  // being sent, until `circuit_reset_secs` have passed.
+  pub circuit_failure_threshold: u32,
+  pub circuit_reset_secs: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Dune {
+  // Dune API, e.g. `https://api.dune.com/api/v1`.
+  pub url: String,
+  pub api_key: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Nfts {
+  pub api_key: String,
+  // Alchemy NFT API by chain name, named like the Arkham chains, e.g.
+  // `ethereum` for `https://eth-mainnet.g.alchemy.com/nft/v3`.
+  #[serde(default)]
+  pub chains: HashMap<String, String>,
+  // Maximum number of 100 NFT pages fetched per chain and lookup.
+  pub max_pages_per_chain: u32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Rpc {
+  // JSON-RPC providers by chain name, named like the Arkham chains.
+  #[serde(default)]
+  pub chains: HashMap<String, String>,
+  // Consecutive failures after which the requests to a provider are
+  // rejected without being sent, until `circuit_reset_secs` have passed.
+  pub circuit_failure_threshold: u32,
+  pub circuit_reset_secs: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Gas {
+  // Fee suggestions change with every block, so they are cached briefly.
+  pub cache_ttl_secs: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Approvals {
+  // Arkham entity types whose addresses are risky spenders, e.g. `hacker`.
+  pub risky_entity_types: Vec<String>,
+  // Maximum number of approvals checked on chain per lookup.
+  pub max_approvals: usize,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Risk {
+  // Words of label names marking a risky address, matched case
+  // insensitively, e.g. `exploit`.
+  pub risky_label_keywords: Vec<String>,
+  // Points added to the score by each factor, the score being capped to 100.
+  // Entity types are the `approvals.risky_entity_types`.
+  pub risky_entity_points: u32,
+  pub risky_label_points: u32,
+  pub risky_approval_points: u32,
+  // Points by unlimited approval, up to `max_unlimited_approval_points`.
+  pub unlimited_approval_points: u32,
+  pub max_unlimited_approval_points: u32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DegenScore {
+  // Symbols of the memecoins, matched case insensitively, e.g. `PEPE`.
+  pub memecoin_symbols: Vec<String>,
+  // Days the transactions of the address are counted over.
+  pub trade_window_days: u64,
+  // Points added to the score by each factor, the score being capped to 100.
+  // Points by transaction in the window, up to `max_trade_points`.
+  pub trade_points: u32,
+  pub max_trade_points: u32,
+  // Points when the whole balance is in memecoins, scaled down to their
+  // share of it.
+  pub memecoin_points: u32,
+  // Points by approval to an unverified contract, up to
+  // `max_unverified_approval_points`.
+  pub unverified_approval_points: u32,
+  pub max_unverified_approval_points: u32,
+  // Points by Arkham entity type, types not listed adding none.
+  pub entity_type_points: HashMap<String, u32>,
+  // Points of the addresses without an Arkham entity.
+  pub anonymous_points: u32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Explorer {
+  // Etherscan compatible API, e.g. `https://api.etherscan.io/api`.
+  pub url: String,
+  pub api_key: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Explorers {
+  // Block explorers by chain name, named like the Arkham chains, e.g.
+  // `arbitrum_one`.
+  #[serde(default)]
+  pub chains: HashMap<String, Explorer>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Watcher {
+  // Whether the worker polling the watched addresses is started.
+  pub enabled: bool,
+  pub poll_interval_secs: u64,
+  // Maximum number of addresses polled at the same time. Polls also go
+  // through the Arkham rate limit, so they never exceed the plan quota.
+  pub concurrency: usize,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Webhooks {
+  // Whether the worker delivering alerts to webhooks is started.
+  pub enabled: bool,
+  pub poll_interval_ms: u64,
+  // Maximum number of deliveries claimed by every poll.
+  pub batch_size: u32,
+  pub concurrency: usize,
+  pub timeout_ms: u64,
+  // Deliveries failing this many times are moved to the dead letters.
+  pub max_attempts: u32,
+  pub retry_base_delay_ms: u64,
+  pub retry_max_delay_ms: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Maintenance {
+  // Starts the server in maintenance mode, also toggled at runtime by admins,
+  // see `utils::maintenance`.
+  pub enabled: bool,
+  // Sent in `Retry-After` while in maintenance.
+  pub retry_after_secs: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Retention {
+  // Days the documents of each collection are kept, removed afterwards by
+  // the `retention_cleanup` job. Unset keeps them forever, see
+  // `services::retention`.
+  #[serde(default)]
+  pub address_snapshots_days: Option<u64>,
+  #[serde(default)]
+  pub alert_events_days: Option<u64>,
+  #[serde(default)]
+  pub audit_logs_days: Option<u64>,
+  // Delivered webhooks only, pending ones are kept until delivered or dead.
+  #[serde(default)]
+  pub webhook_deliveries_days: Option<u64>,
+  #[serde(default)]
+  pub webhook_dead_letters_days: Option<u64>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FeatureFlags {
+  // Flags are read from Mongo at most this often by each server instance,
+  // so changes made on another instance apply within this delay.
+  pub cache_ttl_secs: u64,
+  // Flags missing from Mongo by name, e.g. `risk_score`. Unknown flags are
+  // disabled.
+  #[serde(default)]
+  pub defaults: HashMap<String, FeatureFlagDefault>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FeatureFlagDefault {
+  pub enabled: bool,
+  // Share of the users the feature is enabled for, between 0 and 100.
+  pub rollout_percent: u8,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Telegram {
+  pub api_url: String,
+  // Alerts are not pushed to Telegram when no bot is configured.
+  pub bot_token: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Discord {
+  // Hosts Discord webhook URLs may point to.
+  pub webhook_hosts: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Smtp {
+  // Alerts are not emailed when no SMTP host is configured.
+  pub host: String,
+  pub port: u16,
+  pub username: String,
+  pub password: String,
+  // Sender of the emails, e.g. `Degen <alerts@example.com>`.
+  pub from: String,
+  // Upgrades the connection with STARTTLS. Only disable it for local relays.
+  pub starttls: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Digest {
+  // Whether the worker emailing the weekly digests is started. Digests are
+  // only sent when SMTP is configured.
+  pub enabled: bool,
+  pub poll_interval_secs: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PriceAlerts {
+  // Longest window of the price change rules, prices are kept for twice as
+  // long so the start of the window is always known.
+  pub max_window_secs: u64,
+  // Time after raising an alert before the rule can raise another one,
+  // unless the rule sets its own.
+  pub cooldown_secs: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Scheduler {
+  // Whether the scheduled jobs are started.
+  pub enabled: bool,
+  // Cron expressions of the jobs by name, in UTC with a leading seconds
+  // field, e.g. `0 */10 * * * *` for every ten minutes. Jobs without an
+  // expression don't run, see `services::scheduler::jobs`.
+  #[serde(default)]
+  pub jobs: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct JobQueue {
+  // Whether the workers running the queued jobs are started.
+  pub enabled: bool,
+  pub poll_interval_ms: u64,
+  // Number of jobs run at the same time by each server instance.
+  pub concurrency: usize,
+  // Jobs still running after this long, e.g. because their server stopped,
+  // are claimed again by another worker.
+  pub lease_ms: u64,
+  // Jobs failing this many times are marked as failed.
+  pub max_attempts: u32,
+  pub retry_base_delay_ms: u64,
+  pub retry_max_delay_ms: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct LabelDataset {
+  // Labels loaded by the `label_dataset_sync` job and
+  // `POST /admin/labels/reload`, as newline-delimited JSON in the format of
+  // `POST /labels/import` or a JSON array. A local path, or an HTTP(S) or S3
+  // URL, see `services::label_dataset`.
+  pub url: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct LabelMerge {
+  // Sources of the merged labels of an address, most trusted first:
+  // `user` for the notes of the user, `labels` for the stored labels and
+  // `arkham` for the address intelligence. Unlisted sources come last.
+  pub priority: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct LabelSuggest {
+  // Suggestions for a prefix are cached for this long, zero looks them up
+  // on every request.
+  pub cache_ttl_secs: u64,
+  pub default_limit: u64,
+  pub max_limit: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RouteRateLimit {
+  // Number of requests a user can burst.
+  pub capacity: u32,
+  // Number of requests regained every second.
+  pub refill_per_sec: f64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RateLimit {
+  pub default: RouteRateLimit,
+  // Limits by route path, e.g. `/v1/arkham/:address`.
+  #[serde(default)]
+  pub routes: HashMap<String, RouteRateLimit>,
+  pub anonymous: AnonymousRateLimit,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AnonymousRateLimit {
+  // Limits of the public tier, keyed by client IP instead of user. Meant to
+  // be stricter than the ones of authenticated users.
+  pub default: RouteRateLimit,
+  #[serde(default)]
+  pub routes: HashMap<String, RouteRateLimit>,
+  // Read the client IP from the last `X-Forwarded-For` address, set by the
+  // reverse proxy in front of the API. Clients can spoof the header when
+  // there is no such proxy.
+  #[serde(default)]
+  pub trust_forwarded_for: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BodyLimit {
+  // Largest request body accepted, in bytes, see `utils::body_limit`.
+  // Overridden by route path, e.g. `/v1/labels/import`.
+  pub default_bytes: usize,
+  #[serde(default)]
+  pub routes: HashMap<String, usize>,
+}
+
+// Route files are attached to cats with, see `Attachments`.
+const ATTACHMENTS_ROUTE: &str = "/v1/cats/:id/attachments";
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Attachments {
+  // Largest file attached to a resource, in bytes. The body limit of the
+  // upload routes must leave room for the multipart envelope.
+  pub max_size_bytes: usize,
+  // Media types of the files that can be attached, e.g. `image/png`.
+  pub content_types: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Proxy {
+  // Size of the chunks upstream responses are streamed in with `?raw=true`,
+  // see `utils::proxy`. 0 forwards them as received.
+  pub chunk_bytes: usize,
+}
+
+// Names of the upstream services with their own timeouts, like their
+// settings.
+pub const UPSTREAMS: [&str; 11] = [
+  "arkham",
+  "balances",
+  "bitcoin",
+  "dune",
+  "ens",
+  "explorers",
+  "nfts",
+  "notifications",
+  "portfolio",
+  "prices",
+  "rpc",
+];
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Timeouts {
+  // Time routes have to respond before a 504 is returned, see
+  // `utils::timeout`. Overridden by route path, e.g. `/v1/arkham/batch`.
+  pub request_ms: u64,
+  #[serde(default)]
+  pub routes: HashMap<String, u64>,
+  // Timeouts of the upstream requests, overridden by upstream name, one of
+  // `UPSTREAMS`.
+  pub upstream: UpstreamTimeout,
+  #[serde(default)]
+  pub upstreams: HashMap<String, UpstreamTimeout>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct UpstreamTimeout {
+  // Time to establish the connection, then to complete the whole request,
+  // connection included.
+  pub connect_ms: u64,
+  pub total_ms: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Cors {
+  // Allows any origin, method and header, for local development. The other
+  // settings are then ignored.
+  pub permissive: bool,
+  // Origins browsers may call the API from, e.g. `https://app.example.com`.
+  #[serde(default)]
+  pub allowed_origins: Vec<String>,
+  pub allowed_methods: Vec<String>,
+  pub allowed_headers: Vec<String>,
+  // Whether browsers may send cookies along with cross-origin requests.
+  pub allow_credentials: bool,
+  // How long browsers may cache preflight responses.
+  pub max_age_secs: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Compression {
+  pub enabled: bool,
+  pub gzip: bool,
+  pub br: bool,
+  // Smaller responses are sent as is, compressing them isn't worth it.
+  pub min_size_bytes: u16,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Versions {
+  // Deprecated API versions by name, e.g. `v1`. They are still served, with
+  // the headers announcing their removal, see `utils::api_version`.
+  #[serde(default)]
+  pub deprecated: HashMap<String, Deprecation>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Deprecation {
+  // RFC 3339 dates, e.g. `2025-01-01T00:00:00Z`.
+  pub deprecated_at: DateTime<Utc>,
+  // When the version stops being served, if already planned.
+  pub sunset_at: Option<DateTime<Utc>>,
+  // Migration guide linked from the responses.
+  pub link: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Usage {
+  // Whether requests over the monthly quotas are rejected. Requests are
+  // metered either way.
+  pub enforce_quotas: bool,
+  // Monthly quotas by plan.
+  pub free: Quota,
+  pub pro: Quota,
+  pub enterprise: Quota,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Quota {
+  pub requests: u64,
+  // Requests made to the paid upstream APIs, e.g. Arkham, on behalf of the
+  // user.
+  pub upstream_credits: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Idempotency {
+  // How long responses are replayed for retries with the same
+  // `Idempotency-Key`.
+  pub ttl_secs: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Health {
+  // Whether `/ready` also checks the Arkham API. Each probe then costs an
+  // upstream request.
+  pub check_arkham: bool,
+  // Dependencies slower to answer are reported as failing.
+  pub timeout_ms: u64,
 }
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct Settings {
   pub environment: String,
   pub server: Server,
+  pub grpc: Grpc,
   pub logger: Logger,
+  pub redaction: Redaction,
+  pub telemetry: Telemetry,
   pub database: Database,
+  pub cache: Cache,
   pub auth: Auth,
+  pub siwe: Siwe,
+  pub mfa: Mfa,
+  pub account: Account,
+  pub pagination: Pagination,
+  pub arkham: Arkham,
+  pub ens: Ens,
+  pub prices: Prices,
+  pub explorers: Explorers,
+  pub balances: Balances,
+  pub bitcoin: Bitcoin,
+  pub nfts: Nfts,
+  pub portfolio: Portfolio,
+  pub dune: Dune,
+  pub rpc: Rpc,
+  pub gas: Gas,
+  pub approvals: Approvals,
+  pub risk: Risk,
+  pub degen_score: DegenScore,
+  pub rate_limit: RateLimit,
+  pub timeouts: Timeouts,
+  pub body_limit: BodyLimit,
+  pub attachments: Attachments,
+  pub proxy: Proxy,
+  pub usage: Usage,
+  pub idempotency: Idempotency,
+  pub cors: Cors,
+  pub compression: Compression,
+  pub versions: Versions,
+  pub health: Health,
+  pub watcher: Watcher,
+  pub webhooks: Webhooks,
+  pub telegram: Telegram,
+  pub discord: Discord,
+  pub smtp: Smtp,
+  pub digest: Digest,
+  pub price_alerts: PriceAlerts,
+  pub scheduler: Scheduler,
+  pub label_dataset: LabelDataset,
+  pub label_merge: LabelMerge,
+  pub label_suggest: LabelSuggest,
+  pub job_queue: JobQueue,
+  pub feature_flags: FeatureFlags,
+  pub maintenance: Maintenance,
+  pub retention: Retention,
 }
 
 impl Settings {
   pub fn new() -> Result<Self, ConfigError> {
-    dotenv::dotenv().ok();
-    let run_mode = env::var("RUN_MODE").unwrap_or_else(|_| "development".into());
+    // Tests may touch the settings before the test server is set up, so
+    // default to the test configuration when running them.
+    #[cfg(not(test))]
+    let default_run_mode = "development";
+    #[cfg(test)]
+    let default_run_mode = "test";
+    let run_mode = env::var("RUN_MODE").unwrap_or_else(|_| default_run_mode.into());
+
+    Self::load(profile(&run_mode))
+  }
 
+  /// Settings of a profile, e.g. `staging`: the defaults, overridden by the
+  /// profile file (e.g. its database, log level, cache TTLs and upstream
+  /// URLs), then by `config/local` and the environment variables, e.g.
+  /// `ARKHAM__URL` for `arkham.url`.
+  pub fn load(profile: &str) -> Result<Self, ConfigError> {
     let mut builder = Config::builder()
       .add_source(File::with_name("config/default"))
-      .add_source(File::with_name(&format!("config/{run_mode}")).required(false))
+      .add_source(File::with_name(&format!("config/{profile}")).required(false))
       .add_source(File::with_name("config/local").required(false))
       .add_source(Environment::default().separator("__"));
 
     // Some cloud services like Heroku exposes a randomly assigned port in
     // the PORT env var and there is no way to change the env var name.
     if let Ok(port) = env::var("PORT") {
-      match port.parse::<u16>() {
-        Ok(port) => {
-          builder = builder.set_override("server.port", port)?;
-        }
-        Err(_) => {
-          return Err(ConfigError::Message("Invalid PORT value".to_string()));
-        }
-      }
+      builder = builder.set_override("server.port", parse_port(&port)?)?;
+    }
+
+    if let Ok(host) = env::var("HOST") {
+      builder = builder.set_override("server.host", host)?;
     }
 
     // Read the MONGODB_URI environment variable.
@@ -66,10 +800,646 @@ impl Settings {
       builder = builder.set_override("database.uri", uri)?;
     }
 
-    builder
+    if let Ok(url) = env::var("REDIS_URL") {
+      builder = builder.set_override("cache.redis_url", url)?;
+    }
+
+    if let Ok(api_key) = env::var("ARKHAM_API_KEY") {
+      builder = builder.set_override("arkham.api_key", api_key)?;
+    }
+
+    if let Ok(api_key) = env::var("COINGECKO_API_KEY") {
+      builder = builder.set_override("prices.api_key", api_key)?;
+    }
+
+    if let Ok(api_key) = env::var("COVALENT_API_KEY") {
+      builder = builder.set_override("balances.api_key", api_key)?;
+    }
+
+    if let Ok(api_key) = env::var("HELIUS_API_KEY") {
+      builder = builder.set_override("balances.solana_api_key", api_key)?;
+    }
+
+    if let Ok(api_key) = env::var("BLOCKCHAIR_API_KEY") {
+      builder = builder.set_override("bitcoin.api_key", api_key)?;
+    }
+
+    if let Ok(api_key) = env::var("ALCHEMY_API_KEY") {
+      builder = builder.set_override("nfts.api_key", api_key)?;
+    }
+
+    if let Ok(api_key) = env::var("ZERION_API_KEY") {
+      builder = builder.set_override("portfolio.api_key", api_key)?;
+    }
+
+    if let Ok(api_key) = env::var("DUNE_API_KEY") {
+      builder = builder.set_override("dune.api_key", api_key)?;
+    }
+
+    if let Ok(bot_token) = env::var("TELEGRAM_BOT_TOKEN") {
+      builder = builder.set_override("telegram.bot_token", bot_token)?;
+    }
+
+    if let Ok(password) = env::var("SMTP_PASSWORD") {
+      builder = builder.set_override("smtp.password", password)?;
+    }
+
+    // Comma separated, e.g. `https://app.example.com,https://example.com`.
+    if let Ok(origins) = env::var("CORS_ALLOWED_ORIGINS") {
+      let origins = origins
+        .split(',')
+        .map(|origin| origin.trim().to_owned())
+        .filter(|origin| !origin.is_empty())
+        .collect::<Vec<String>>();
+      builder = builder.set_override("cors.allowed_origins", origins)?;
+    }
+
+    let settings: Settings = builder
       .build()?
       // Deserialize (and thus freeze) the entire configuration.
-      .try_deserialize()
+      .try_deserialize()?;
+
+    settings.validate()?;
+    Ok(settings)
+  }
+
+  /// Checks the values that deserialize fine but would break the app at
+  /// runtime, so a bad configuration is reported at startup instead of in
+  /// the middle of a request.
+  pub fn validate(&self) -> Result<(), ConfigError> {
+    let mut errors = Vec::new();
+    let mut check = |valid: bool, message: &str| {
+      if !valid {
+        errors.push(message.to_owned());
+      }
+    };
+
+    check(
+      self.server.address().is_ok(),
+      "server.host must be an IP address",
+    );
+    check(
+      !self.grpc.enabled || self.grpc.port != self.server.port,
+      "grpc.port must differ from server.port",
+    );
+    check(
+      !self.telemetry.enabled || !self.telemetry.otlp_endpoint.is_empty(),
+      "telemetry.otlp_endpoint must be set when telemetry is enabled",
+    );
+    check(
+      (0.0..=1.0).contains(&self.telemetry.sample_ratio),
+      "telemetry.sample_ratio must be between 0 and 1",
+    );
+    check(!self.database.uri.is_empty(), "database.uri must be set");
+    check(!self.database.name.is_empty(), "database.name must be set");
+    check(
+      self.database.connect_attempts >= 1,
+      "database.connect_attempts must be at least 1",
+    );
+    check(
+      self.database.max_pool_size != Some(0),
+      "database.max_pool_size must be at least 1",
+    );
+    check(
+      match (self.database.min_pool_size, self.database.max_pool_size) {
+        (Some(min), Some(max)) => min <= max,
+        _ => true,
+      },
+      "database.min_pool_size must not exceed database.max_pool_size",
+    );
+    check(
+      self.cache.backend != CacheBackend::Redis
+        || self.cache.redis_url.starts_with("redis://")
+        || self.cache.redis_url.starts_with("rediss://"),
+      "cache.redis_url must be a Redis URL when cache.backend is redis",
+    );
+    check(!self.auth.secret.is_empty(), "auth.secret must be set");
+    check(
+      self.auth.access_token_ttl_secs >= 1,
+      "auth.access_token_ttl_secs must be at least 1",
+    );
+    check(
+      self.auth.refresh_token_ttl_secs >= self.auth.access_token_ttl_secs,
+      "auth.refresh_token_ttl_secs must not be shorter than auth.access_token_ttl_secs",
+    );
+    check(!self.siwe.domain.is_empty(), "siwe.domain must be set");
+    check(
+      self.siwe.nonce_ttl_secs >= 1,
+      "siwe.nonce_ttl_secs must be at least 1",
+    );
+    check(!self.mfa.issuer.is_empty(), "mfa.issuer must be set");
+    check(
+      self.mfa.recovery_codes >= 1,
+      "mfa.recovery_codes must be at least 1",
+    );
+    check(
+      (self.account.app_url.starts_with("http://") || self.account.app_url.starts_with("https://"))
+        && self.account.app_url.parse::<reqwest::Url>().is_ok(),
+      "account.app_url must be an HTTP URL",
+    );
+    check(
+      self.account.password_reset_ttl_secs >= 1,
+      "account.password_reset_ttl_secs must be at least 1",
+    );
+    check(
+      self.account.email_verification_ttl_secs >= 1,
+      "account.email_verification_ttl_secs must be at least 1",
+    );
+    check(
+      self.account.deletion_confirmation_ttl_secs >= 1,
+      "account.deletion_confirmation_ttl_secs must be at least 1",
+    );
+    check(
+      self.pagination.max_limit >= 1,
+      "pagination.max_limit must be at least 1",
+    );
+    check(
+      self.arkham.url.starts_with("http://") || self.arkham.url.starts_with("https://"),
+      "arkham.url must be an HTTP URL",
+    );
+    check(
+      self.arkham.provider == IntelligenceBackend::Fixture || !self.arkham.api_key.is_empty(),
+      "arkham.api_key must be set",
+    );
+    check(
+      self.arkham.provider != IntelligenceBackend::Fixture || !self.arkham.fixtures_path.is_empty(),
+      "arkham.fixtures_path must be set when arkham.provider is fixture",
+    );
+    check(
+      self.arkham.batch_max_addresses >= 1,
+      "arkham.batch_max_addresses must be at least 1",
+    );
+    check(
+      self.arkham.batch_concurrency >= 1,
+      "arkham.batch_concurrency must be at least 1",
+    );
+    check(
+      self.arkham.retry_attempts >= 1,
+      "arkham.retry_attempts must be at least 1",
+    );
+    check(
+      self.arkham.requests_per_sec > 0.0,
+      "arkham.requests_per_sec must be positive",
+    );
+    check(
+      self.arkham.requests_burst >= 1,
+      "arkham.requests_burst must be at least 1",
+    );
+    check(
+      self.arkham.circuit_failure_threshold >= 1,
+      "arkham.circuit_failure_threshold must be at least 1",
+    );
+    check(
+      self.arkham.stream_refresh_secs >= 1,
+      "arkham.stream_refresh_secs must be at least 1",
+    );
+    check(
+      self.arkham.stream_keep_alive_secs >= 1,
+      "arkham.stream_keep_alive_secs must be at least 1",
+    );
+    check(
+      self.arkham.graph_max_depth >= 1,
+      "arkham.graph_max_depth must be at least 1",
+    );
+    check(
+      self.arkham.graph_max_nodes >= 1,
+      "arkham.graph_max_nodes must be at least 1",
+    );
+    check(
+      self.ens.rpc_url.starts_with("http://") || self.ens.rpc_url.starts_with("https://"),
+      "ens.rpc_url must be an HTTP URL",
+    );
+    check(
+      normalize_evm_address(&self.ens.registry).is_ok(),
+      "ens.registry must be an EVM address",
+    );
+    check(
+      self.prices.url.starts_with("http://") || self.prices.url.starts_with("https://"),
+      "prices.url must be an HTTP URL",
+    );
+    check(
+      self.prices.max_ids >= 1,
+      "prices.max_ids must be at least 1",
+    );
+    check(
+      self.prices.circuit_failure_threshold >= 1,
+      "prices.circuit_failure_threshold must be at least 1",
+    );
+    check(
+      self.balances.url.starts_with("http://") || self.balances.url.starts_with("https://"),
+      "balances.url must be an HTTP URL",
+    );
+    check(
+      self.balances.solana_url.starts_with("http://")
+        || self.balances.solana_url.starts_with("https://"),
+      "balances.solana_url must be an HTTP URL",
+    );
+    check(
+      self.bitcoin.url.starts_with("http://") || self.bitcoin.url.starts_with("https://"),
+      "bitcoin.url must be an HTTP URL",
+    );
+    for (chain, url) in &self.nfts.chains {
+      check(
+        url.starts_with("http://") || url.starts_with("https://"),
+        &format!("nfts.chains.{chain}.url must be an HTTP URL"),
+      );
+    }
+    check(
+      self.nfts.max_pages_per_chain >= 1,
+      "nfts.max_pages_per_chain must be at least 1",
+    );
+    check(
+      self.portfolio.url.starts_with("http://") || self.portfolio.url.starts_with("https://"),
+      "portfolio.url must be an HTTP URL",
+    );
+    check(
+      self.portfolio.retry_attempts >= 1,
+      "portfolio.retry_attempts must be at least 1",
+    );
+    check(
+      self.portfolio.circuit_failure_threshold >= 1,
+      "portfolio.circuit_failure_threshold must be at least 1",
+    );
+    check(
+      self.dune.url.starts_with("http://") || self.dune.url.starts_with("https://"),
+      "dune.url must be an HTTP URL",
+    );
+    for (chain, url) in &self.rpc.chains {
+      check(
+        url.starts_with("http://") || url.starts_with("https://"),
+        &format!("rpc.chains.{chain} must be an HTTP URL"),
+      );
+    }
+    check(
+      self.rpc.circuit_failure_threshold >= 1,
+      "rpc.circuit_failure_threshold must be at least 1",
+    );
+    check(
+      self.approvals.max_approvals >= 1,
+      "approvals.max_approvals must be at least 1",
+    );
+    check(
+      self.degen_score.trade_window_days >= 1,
+      "degen_score.trade_window_days must be at least 1",
+    );
+    for (chain, explorer) in &self.explorers.chains {
+      check(
+        explorer.url.starts_with("http://") || explorer.url.starts_with("https://"),
+        &format!("explorers.chains.{chain}.url must be an HTTP URL"),
+      );
+    }
+
+    for (version, deprecation) in &self.versions.deprecated {
+      check(
+        VERSIONS.contains(&version.as_str()),
+        &format!("versions.deprecated.{version} must be an API version"),
+      );
+      check(
+        deprecation
+          .sunset_at
+          .iter()
+          .all(|sunset_at| *sunset_at >= deprecation.deprecated_at),
+        &format!("versions.deprecated.{version}.sunset_at must not be before deprecated_at"),
+      );
+      check(
+        deprecation
+          .link
+          .iter()
+          .all(|link| link.starts_with("http://") || link.starts_with("https://")),
+        &format!("versions.deprecated.{version}.link must be an HTTP URL"),
+      );
+    }
+
+    check(
+      self.health.timeout_ms >= 1,
+      "health.timeout_ms must be at least 1",
+    );
+    check(
+      self.watcher.poll_interval_secs >= 1,
+      "watcher.poll_interval_secs must be at least 1",
+    );
+    check(
+      self.watcher.concurrency >= 1,
+      "watcher.concurrency must be at least 1",
+    );
+
+    check(
+      self.webhooks.poll_interval_ms >= 1,
+      "webhooks.poll_interval_ms must be at least 1",
+    );
+    check(
+      self.webhooks.batch_size >= 1,
+      "webhooks.batch_size must be at least 1",
+    );
+    check(
+      self.webhooks.concurrency >= 1,
+      "webhooks.concurrency must be at least 1",
+    );
+    check(
+      self.webhooks.timeout_ms >= 1,
+      "webhooks.timeout_ms must be at least 1",
+    );
+    check(
+      self.webhooks.max_attempts >= 1,
+      "webhooks.max_attempts must be at least 1",
+    );
+
+    check(
+      self.telegram.api_url.starts_with("http://") || self.telegram.api_url.starts_with("https://"),
+      "telegram.api_url must be an HTTP URL",
+    );
+
+    check(
+      self.smtp.host.is_empty() || self.smtp.from.parse::<Mailbox>().is_ok(),
+      "smtp.from must be an email address",
+    );
+    check(
+      self.digest.poll_interval_secs >= 1,
+      "digest.poll_interval_secs must be at least 1",
+    );
+    check(
+      self.price_alerts.max_window_secs >= 60,
+      "price_alerts.max_window_secs must be at least 60",
+    );
+
+    for (job, schedule) in &self.scheduler.jobs {
+      check(
+        cron::Schedule::from_str(schedule).is_ok(),
+        &format!("scheduler.jobs.{job} must be a cron expression"),
+      );
+    }
+    check(
+      !self.scheduler.jobs.contains_key("label_dataset_sync") || !self.label_dataset.url.is_empty(),
+      "label_dataset.url is required to sync the label dataset",
+    );
+    for (index, source) in self.label_merge.priority.iter().enumerate() {
+      check(
+        LABEL_SOURCES.contains(&source.as_str()),
+        &format!("label_merge.priority.{source} is not a label source"),
+      );
+      check(
+        !self.label_merge.priority[..index].contains(source),
+        &format!("label_merge.priority.{source} is listed twice"),
+      );
+    }
+    check(
+      (1..=self.label_suggest.max_limit).contains(&self.label_suggest.default_limit),
+      "label_suggest.default_limit must be between 1 and label_suggest.max_limit",
+    );
+
+    check(
+      self.job_queue.poll_interval_ms >= 1,
+      "job_queue.poll_interval_ms must be at least 1",
+    );
+    check(
+      self.job_queue.concurrency >= 1,
+      "job_queue.concurrency must be at least 1",
+    );
+    check(
+      self.job_queue.lease_ms >= 1,
+      "job_queue.lease_ms must be at least 1",
+    );
+    check(
+      self.job_queue.max_attempts >= 1,
+      "job_queue.max_attempts must be at least 1",
+    );
+    check(
+      self.maintenance.retry_after_secs >= 1,
+      "maintenance.retry_after_secs must be at least 1",
+    );
+    let retention = [
+      (
+        "address_snapshots_days",
+        self.retention.address_snapshots_days,
+      ),
+      ("alert_events_days", self.retention.alert_events_days),
+      ("audit_logs_days", self.retention.audit_logs_days),
+      (
+        "webhook_deliveries_days",
+        self.retention.webhook_deliveries_days,
+      ),
+      (
+        "webhook_dead_letters_days",
+        self.retention.webhook_dead_letters_days,
+      ),
+    ];
+    for (name, days) in retention {
+      check(
+        days != Some(0),
+        &format!("retention.{name} must be at least 1"),
+      );
+    }
+    for (name, flag) in &self.feature_flags.defaults {
+      check(
+        flag.rollout_percent <= 100,
+        &format!("feature_flags.defaults.{name}.rollout_percent must be at most 100"),
+      );
+    }
+
+    let rate_limits = std::iter::once(("default", &self.rate_limit.default)).chain(
+      self
+        .rate_limit
+        .routes
+        .iter()
+        .map(|(route, limit)| (route.as_str(), limit)),
+    );
+    for (route, limit) in rate_limits {
+      check(
+        limit.capacity >= 1 && limit.refill_per_sec > 0.0,
+        &format!("rate_limit {route} must have a capacity and a positive refill rate"),
+      );
+    }
+
+    check(
+      self.timeouts.request_ms >= 1,
+      "timeouts.request_ms must be at least 1",
+    );
+    for (route, timeout_ms) in &self.timeouts.routes {
+      check(
+        *timeout_ms >= 1,
+        &format!("timeouts.routes.{route} must be at least 1"),
+      );
+    }
+    let upstream_timeouts = std::iter::once(("upstream", &self.timeouts.upstream)).chain(
+      self
+        .timeouts
+        .upstreams
+        .iter()
+        .map(|(upstream, timeout)| (upstream.as_str(), timeout)),
+    );
+    for (upstream, timeout) in upstream_timeouts {
+      check(
+        timeout.connect_ms >= 1 && timeout.total_ms >= timeout.connect_ms,
+        &format!(
+          "timeouts {upstream} must have a connect_ms of at least 1 and no shorter total_ms"
+        ),
+      );
+    }
+    for upstream in self.timeouts.upstreams.keys() {
+      check(
+        UPSTREAMS.contains(&upstream.as_str()),
+        &format!(
+          "timeouts.upstreams.{upstream} must be one of {}",
+          UPSTREAMS.join(", ")
+        ),
+      );
+    }
+
+    let body_limits = std::iter::once(("default", &self.body_limit.default_bytes)).chain(
+      self
+        .body_limit
+        .routes
+        .iter()
+        .map(|(route, limit)| (route.as_str(), limit)),
+    );
+    for (route, limit) in body_limits {
+      check(
+        *limit >= 1,
+        &format!("body_limit {route} must be at least 1 byte"),
+      );
+    }
+
+    check(
+      self.attachments.max_size_bytes >= 1,
+      "attachments.max_size_bytes must be at least 1",
+    );
+    check(
+      self.body_limit.limit(Some(ATTACHMENTS_ROUTE)) > self.attachments.max_size_bytes,
+      &format!("body_limit {ATTACHMENTS_ROUTE} must be larger than attachments.max_size_bytes"),
+    );
+    for content_type in &self.attachments.content_types {
+      check(
+        content_type.parse::<mime::Mime>().is_ok(),
+        &format!("attachments.content_types {content_type} must be a media type"),
+      );
+    }
+
+    for origin in &self.cors.allowed_origins {
+      check(
+        is_origin(origin),
+        &format!("cors.allowed_origins {origin} must be an HTTP origin, e.g. https://example.com"),
+      );
+    }
+    for method in &self.cors.allowed_methods {
+      check(
+        http::Method::from_bytes(method.as_bytes()).is_ok(),
+        &format!("cors.allowed_methods {method} must be an HTTP method"),
+      );
+    }
+    for header in &self.cors.allowed_headers {
+      check(
+        http::HeaderName::from_bytes(header.as_bytes()).is_ok(),
+        &format!("cors.allowed_headers {header} must be a header name"),
+      );
+    }
+
+    check(
+      self.idempotency.ttl_secs >= 1,
+      "idempotency.ttl_secs must be at least 1",
+    );
+
+    if errors.is_empty() {
+      return Ok(());
+    }
+
+    Err(ConfigError::Message(format!(
+      "Invalid configuration: {}",
+      errors.join(", ")
+    )))
+  }
+
+  /// Configured secrets, scrubbed from the logs and the error responses,
+  /// see `utils::redact`.
+  pub fn secrets(&self) -> Vec<String> {
+    let mut secrets = vec![
+      self.auth.secret.clone(),
+      self.arkham.api_key.clone(),
+      self.prices.api_key.clone(),
+      self.balances.api_key.clone(),
+      self.balances.solana_api_key.clone(),
+      self.bitcoin.api_key.clone(),
+      self.nfts.api_key.clone(),
+      self.portfolio.api_key.clone(),
+      self.dune.api_key.clone(),
+      self.telegram.bot_token.clone(),
+      self.smtp.password.clone(),
+    ];
+    let explorers = self.explorers.chains.values();
+    secrets.extend(explorers.map(|explorer| explorer.api_key.clone()));
+    secrets.extend(self.redaction.secrets.iter().cloned());
+
+    secrets
+  }
+}
+
+/// Whether the value is a scheme and host, with an optional port, like the
+/// `Origin` header sent by browsers.
+fn is_origin(value: &str) -> bool {
+  let host = match value
+    .strip_prefix("https://")
+    .or_else(|| value.strip_prefix("http://"))
+  {
+    Some(host) => host,
+    None => return false,
+  };
+
+  !host.is_empty() && !host.contains(['/', '?', '#', ' '])
+}
+
+/// Profile of a `RUN_MODE`, accepting the short names of the profiles, e.g.
+/// `prod` for `production`.
+pub fn profile(run_mode: &str) -> &str {
+  match run_mode {
+    "dev" => "development",
+    "stage" => "staging",
+    "prod" => "production",
+    run_mode => run_mode,
+  }
+}
+
+pub fn parse_port(port: &str) -> Result<u16, ConfigError> {
+  port.trim().parse::<u16>().map_err(|_| {
+    ConfigError::Message(format!(
+      "Invalid PORT value {port:?}, expected a number between 0 and 65535"
+    ))
+  })
+}
+
+impl Server {
+  /// Socket address the server listens on.
+  pub fn address(&self) -> Result<SocketAddr, ConfigError> {
+    let ip = self.host.parse::<IpAddr>().map_err(|_| {
+      ConfigError::Message(format!(
+        "Invalid HOST value {:?}, expected an IP address",
+        self.host
+      ))
+    })?;
+
+    Ok(SocketAddr::new(ip, self.port))
+  }
+}
+
+impl Timeouts {
+  /// Timeout of a route, by its path.
+  pub fn request(&self, route: Option<&str>) -> Duration {
+    let timeout_ms = route
+      .and_then(|route| self.routes.get(route))
+      .unwrap_or(&self.request_ms);
+    Duration::from_millis(*timeout_ms)
+  }
+
+  /// Timeouts of the requests to an upstream service, by name.
+  pub fn upstream(&self, name: &str) -> &UpstreamTimeout {
+    self.upstreams.get(name).unwrap_or(&self.upstream)
+  }
+}
+
+impl BodyLimit {
+  /// Body size limit of a route, by its path.
+  pub fn limit(&self, route: Option<&str>) -> usize {
+    *route
+      .and_then(|route| self.routes.get(route))
+      .unwrap_or(&self.default_bytes)
   }
 }
 
@@ -0,0 +1,213 @@
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast, Semaphore};
+
+use crate::graphql::{self, GraphqlSchema};
+use crate::models::address_label::LabelSuggestion;
+use crate::notifications::Notifiers;
+use crate::routes::arkham::{ArkhamEntityDetail, ArkhamPortfolio, ArkhamResponse};
+use crate::services::address_intelligence::{
+  ArkhamProvider, CachedProvider, ChainedProvider, FixtureProvider, IntelligenceProvider,
+};
+use crate::services::approvals::ApprovalScanner;
+use crate::services::arkham::ArkhamClient;
+use crate::services::balances::BalanceClient;
+use crate::services::bitcoin::BitcoinClient;
+use crate::services::degen_score::DegenScorer;
+use crate::services::dune::DuneClient;
+use crate::services::ens::EnsClient;
+use crate::services::explorer::ExplorerClient;
+use crate::services::feature_flags::FeatureFlags;
+use crate::services::gas::GasOracle;
+use crate::services::live::LiveEvent;
+use crate::services::nfts::NftClient;
+use crate::services::portfolio::PortfolioClient;
+use crate::services::prices::{CachedPriceProvider, CoinGeckoProvider, PriceProvider};
+use crate::services::risk::RiskScorer;
+use crate::services::rpc::RpcClient;
+use crate::services::scheduler::Scheduler;
+use crate::services::watcher::AddressChange;
+use crate::settings::{IntelligenceBackend, Settings, UpstreamTimeout};
+use crate::utils::cache::{self, TtlCache};
+use crate::utils::idempotency::IdempotencyStore;
+use crate::utils::maintenance::Maintenance;
+use crate::utils::rate_limit::RateLimiter;
+
+// Number of address changes kept for subscribers lagging behind.
+const ADDRESS_CHANGES_CAPACITY: usize = 256;
+
+// Number of live events kept for WebSocket clients lagging behind.
+const LIVE_EVENTS_CAPACITY: usize = 1024;
+
+/// State shared by every request handler, created once at startup.
+#[derive(Clone)]
+pub struct AppState {
+  pub settings: Arc<Settings>,
+  pub arkham: ArkhamClient,
+  pub ens: EnsClient,
+  pub explorer: Arc<ExplorerClient>,
+  pub balances: Arc<BalanceClient>,
+  pub bitcoin: Arc<BitcoinClient>,
+  pub nfts: Arc<NftClient>,
+  pub portfolio: Arc<PortfolioClient>,
+  pub dune: Arc<DuneClient>,
+  pub rpc: Arc<RpcClient>,
+  pub approvals: Arc<ApprovalScanner>,
+  pub gas: Arc<GasOracle>,
+  pub risk: Arc<RiskScorer>,
+  pub degen_score: Arc<DegenScorer>,
+  pub address_cache: Arc<TtlCache<ArkhamResponse>>,
+  pub entity_cache: Arc<TtlCache<ArkhamEntityDetail>>,
+  pub portfolio_cache: Arc<TtlCache<ArkhamPortfolio>>,
+  // Suggestions by prefix, see `GET /labels/suggest`.
+  pub label_suggestions: Arc<TtlCache<Vec<LabelSuggestion>>>,
+  pub address_intelligence: Arc<dyn IntelligenceProvider>,
+  pub prices: Arc<dyn PriceProvider>,
+  // Bounds the upstream requests of batch lookups across all requests.
+  pub batch_permits: Arc<Semaphore>,
+  pub rate_limiter: Arc<RateLimiter>,
+  // Responses replayed for retried requests, see `utils::idempotency`.
+  pub idempotency: Arc<IdempotencyStore>,
+  // Changes found by the watcher, see `services::watcher`.
+  pub address_changes: broadcast::Sender<AddressChange>,
+  // Events streamed to the connected users, see `routes::live`.
+  pub live_events: broadcast::Sender<LiveEvent>,
+  pub notifiers: Arc<Notifiers>,
+  // Recurring jobs and their last runs, see `services::scheduler`.
+  pub scheduler: Arc<Scheduler>,
+  // Flags of the features enabled gradually, see `services::feature_flags`.
+  pub feature_flags: Arc<FeatureFlags>,
+  pub maintenance: Arc<Maintenance>,
+  pub graphql: GraphqlSchema,
+}
+
+impl AppState {
+  pub fn new(settings: Arc<Settings>) -> Self {
+    // An HTTP client per upstream service with its timeouts, so connections
+    // and TLS sessions are reused across requests.
+    let http_client = |upstream: &str| build_http_client(settings.timeouts.upstream(upstream));
+    let arkham = ArkhamClient::new(http_client("arkham"), &settings.arkham);
+    let ens = EnsClient::new(http_client("ens"), &settings.ens, &settings.cache);
+    let explorer = Arc::new(ExplorerClient::new(
+      http_client("explorers"),
+      &settings.explorers,
+    ));
+    let bitcoin = Arc::new(BitcoinClient::new(
+      http_client("bitcoin"),
+      &settings.bitcoin,
+    ));
+    let balances = Arc::new(BalanceClient::new(
+      http_client("balances"),
+      &settings.balances,
+      bitcoin.clone(),
+    ));
+    let nfts = NftClient::new(http_client("nfts"), &settings.nfts);
+    let portfolio = PortfolioClient::new(
+      http_client("portfolio"),
+      &settings.portfolio,
+      &settings.cache,
+    );
+    let dune = DuneClient::new(http_client("dune"), &settings.dune);
+    let rpc = Arc::new(RpcClient::new(http_client("rpc"), &settings.rpc));
+    let gas = GasOracle::new(
+      rpc.clone(),
+      cache::backend(&settings.cache, "gas"),
+      Duration::from_secs(settings.gas.cache_ttl_secs),
+    );
+    let prices = CachedPriceProvider::new(
+      CoinGeckoProvider::new(http_client("prices"), &settings.prices),
+      cache::backend(&settings.cache, "prices"),
+      Duration::from_secs(settings.prices.cache_ttl_secs),
+    );
+    let notifiers = Notifiers::new(http_client("notifications"), &settings);
+
+    let cache_ttl = Duration::from_secs(settings.arkham.cache_ttl_secs);
+    let address_cache = Arc::new(TtlCache::new(
+      cache::backend(&settings.cache, "arkham:address"),
+      cache_ttl,
+    ));
+    let entity_cache = Arc::new(TtlCache::new(
+      cache::backend(&settings.cache, "arkham:entity"),
+      cache_ttl,
+    ));
+    let portfolio_cache = Arc::new(TtlCache::new(
+      cache::backend(&settings.cache, "arkham:portfolio"),
+      cache_ttl,
+    ));
+
+    let address_intelligence: Arc<dyn IntelligenceProvider> = match settings.arkham.provider {
+      // Falls back to the last known data when Arkham is down or over quota.
+      IntelligenceBackend::Arkham => Arc::new(
+        ChainedProvider::new(ArkhamProvider::new(arkham.clone(), address_cache.clone()))
+          .fallback(CachedProvider::new(address_cache.clone())),
+      ),
+      IntelligenceBackend::Fixture => Arc::new(
+        FixtureProvider::load(&settings.arkham.fixtures_path)
+          .expect("Failed to load the intelligence fixtures"),
+      ),
+    };
+    let approvals = Arc::new(ApprovalScanner::new(
+      rpc.clone(),
+      address_intelligence.clone(),
+      &settings.approvals,
+    ));
+    let risk = RiskScorer::new(
+      address_intelligence.clone(),
+      approvals.clone(),
+      &settings.risk,
+      &settings.approvals,
+    );
+    let degen_score = DegenScorer::new(
+      address_intelligence.clone(),
+      approvals.clone(),
+      balances.clone(),
+      explorer.clone(),
+      &settings.degen_score,
+    );
+
+    Self {
+      batch_permits: Arc::new(Semaphore::new(settings.arkham.batch_concurrency)),
+      rate_limiter: Arc::new(RateLimiter::new(&settings.rate_limit)),
+      idempotency: Arc::new(IdempotencyStore::new(&settings.idempotency)),
+      address_changes: broadcast::channel(ADDRESS_CHANGES_CAPACITY).0,
+      live_events: broadcast::channel(LIVE_EVENTS_CAPACITY).0,
+      prices: Arc::new(prices),
+      notifiers: Arc::new(notifiers),
+      scheduler: Arc::new(Scheduler::new(&settings.scheduler)),
+      feature_flags: Arc::new(FeatureFlags::new(&settings.feature_flags, &settings.cache)),
+      maintenance: Arc::new(Maintenance::new(&settings.maintenance)),
+      label_suggestions: Arc::new(TtlCache::new(
+        cache::backend(&settings.cache, "label_suggestions"),
+        Duration::from_secs(settings.label_suggest.cache_ttl_secs),
+      )),
+      settings,
+      arkham,
+      ens,
+      explorer,
+      balances,
+      bitcoin,
+      nfts: Arc::new(nfts),
+      portfolio: Arc::new(portfolio),
+      dune: Arc::new(dune),
+      rpc,
+      approvals,
+      gas: Arc::new(gas),
+      risk: Arc::new(risk),
+      degen_score: Arc::new(degen_score),
+      address_cache,
+      entity_cache,
+      portfolio_cache,
+      graphql: graphql::build_schema(address_intelligence.clone()),
+      address_intelligence,
+    }
+  }
+}
+
+/// HTTP client giving up on requests after the timeouts.
+fn build_http_client(timeout: &UpstreamTimeout) -> reqwest::Client {
+  reqwest::Client::builder()
+    .connect_timeout(Duration::from_millis(timeout.connect_ms))
+    .timeout(Duration::from_millis(timeout.total_ms))
+    .build()
+    .expect("Failed to build the HTTP client")
+}
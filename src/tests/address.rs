@@ -0,0 +1,157 @@
+use crate::utils::address::{
+  normalize_address, normalize_bitcoin_address, normalize_evm_address, normalize_solana_address,
+  normalize_wallet_address, to_checksum_address, AddressChain,
+};
+
+#[test]
+fn to_checksum_address_follows_eip_55() {
+  let addresses = [
+    "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+    "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+    "0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB",
+    "0xD1220A0cf47c7B9Be7A2E6BA89F429762e7b9aDb",
+  ];
+
+  for address in addresses {
+    let normalized = normalize_evm_address(address).unwrap();
+    assert_eq!(to_checksum_address(&normalized), address);
+  }
+}
+
+#[test]
+fn to_checksum_address_roundtrips_through_normalization() {
+  let address = "0x52908400098527886e0f7030069857d2e4169ee7";
+  let checksummed = to_checksum_address(address);
+
+  assert_ne!(checksummed, address, "Letters should be mixed-case");
+  assert_eq!(normalize_evm_address(&checksummed).unwrap(), address);
+}
+
+#[test]
+fn normalize_solana_address_accepts_base58_public_keys() {
+  let addresses = [
+    "11111111111111111111111111111111",
+    "So11111111111111111111111111111111111111112",
+    "9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM",
+  ];
+
+  for address in addresses {
+    assert_eq!(normalize_solana_address(address).unwrap(), address);
+  }
+  assert_eq!(
+    normalize_solana_address(" So11111111111111111111111111111111111111112 ").unwrap(),
+    "So11111111111111111111111111111111111111112"
+  );
+}
+
+#[test]
+fn normalize_solana_address_rejects_invalid_addresses() {
+  let addresses = [
+    // Too short.
+    "1111111111111111111111111111111",
+    // Too long.
+    "9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWMM",
+    // `0`, `O`, `I` and `l` are not base58 digits.
+    "0WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM",
+    "OWzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM",
+    "IWzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM",
+    "lWzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM",
+    "0x52908400098527886e0f7030069857d2e4169ee7",
+  ];
+
+  for address in addresses {
+    assert!(
+      normalize_solana_address(address).is_err(),
+      "{} should be rejected",
+      address
+    );
+  }
+}
+
+#[test]
+fn normalize_address_detects_chain() {
+  let solana = "So11111111111111111111111111111111111111112";
+  let evm = "0x52908400098527886E0F7030069857D2E4169EE7";
+
+  assert_eq!(AddressChain::detect(solana), Some(AddressChain::Solana));
+  assert_eq!(AddressChain::detect(evm), Some(AddressChain::Evm));
+  assert_eq!(AddressChain::detect("degen"), None);
+
+  assert_eq!(normalize_address(solana, None).unwrap(), solana);
+  assert_eq!(
+    normalize_address(evm, None).unwrap(),
+    "0x52908400098527886e0f7030069857d2e4169ee7"
+  );
+  assert!(normalize_address(evm, Some(AddressChain::Solana)).is_err());
+  assert!(normalize_address(solana, Some(AddressChain::Evm)).is_err());
+}
+
+#[test]
+fn to_checksum_address_keeps_solana_addresses() {
+  let address = "9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM";
+
+  assert_eq!(to_checksum_address(address), address);
+}
+
+#[test]
+fn normalize_bitcoin_address_accepts_mainnet_addresses() {
+  let addresses = [
+    // P2PKH
+    "1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa",
+    // P2SH
+    "3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy",
+    // P2WPKH, bech32
+    "bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq",
+    // P2TR, bech32m
+    "bc1p5d7rjq7g6rdk2yhzks9smlaqtedr4dekq08ge8ztwac72sfr9rusxg3297",
+  ];
+
+  for address in addresses {
+    assert_eq!(normalize_bitcoin_address(address).unwrap(), address);
+    assert_eq!(AddressChain::detect(address), Some(AddressChain::Bitcoin));
+  }
+  assert_eq!(
+    normalize_bitcoin_address("BC1QAR0SRRR7XFKVY5L643LYDNW9RE59GTZZWF5MDQ").unwrap(),
+    "bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq",
+    "Bech32 addresses should be lowercased"
+  );
+}
+
+#[test]
+fn normalize_bitcoin_address_rejects_invalid_addresses() {
+  let addresses = [
+    // Wrong base58check checksum.
+    "1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNb",
+    // Wrong bech32 checksum.
+    "bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdp",
+    // Mixed case.
+    "bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5MDQ",
+    // Testnet.
+    "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx",
+    "9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM",
+  ];
+
+  for address in addresses {
+    assert!(
+      normalize_bitcoin_address(address).is_err(),
+      "{} should be rejected",
+      address
+    );
+  }
+}
+
+#[test]
+fn normalize_wallet_address_accepts_xpubs() {
+  let xpub = "xpub661MyMwAqRbcFtXgS5sYJABqqG9YLmC4Q1Rdap9gSE8NqtwybGhePY2gZ29ESFjqJoCu1Rupje8YtGqsefD265TMg7usUDFdp6W1EGMcet8";
+
+  assert_eq!(normalize_wallet_address(xpub, None).unwrap(), xpub);
+  assert_eq!(
+    normalize_wallet_address(xpub, Some(AddressChain::Bitcoin)).unwrap(),
+    xpub
+  );
+  assert!(normalize_wallet_address(xpub, Some(AddressChain::Solana)).is_err());
+  assert!(
+    normalize_address(xpub, None).is_err(),
+    "Extended public keys are not addresses"
+  );
+}
@@ -0,0 +1,228 @@
+use async_trait::async_trait;
+use serde_json::json;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::runtime::Runtime;
+
+use crate::errors::Error;
+use crate::routes::arkham::{
+  parse_arkham_portfolio, parse_arkham_response, ArkhamEntityDetail, ArkhamPortfolio,
+  ArkhamResponse, ArkhamTransfers, TransfersQuery,
+};
+use crate::services::address_intelligence::{
+  CachedProvider, ChainedProvider, FixtureProvider, IntelligenceProvider,
+};
+use crate::utils::cache::{MemoryCache, TtlCache};
+
+const ADDRESS: &str = "0x00000000000000000000000000000000000000aa";
+
+/// Provider standing in for an unavailable upstream.
+struct FailingProvider;
+
+#[async_trait]
+impl IntelligenceProvider for FailingProvider {
+  async fn lookup_address(&self, _address: &str) -> Result<ArkhamResponse, Error> {
+    Err(Error::upstream_unavailable("Arkham", Some(503)))
+  }
+
+  async fn lookup_entity(&self, _entity_id: &str) -> Result<ArkhamEntityDetail, Error> {
+    Err(Error::upstream_unavailable("Arkham", Some(503)))
+  }
+
+  async fn transfers(
+    &self,
+    _address: &str,
+    _query: &TransfersQuery,
+  ) -> Result<ArkhamTransfers, Error> {
+    Err(Error::upstream_unavailable("Arkham", Some(503)))
+  }
+
+  async fn portfolio(&self, _address: &str) -> Result<ArkhamPortfolio, Error> {
+    Err(Error::upstream_unavailable("Arkham", Some(503)))
+  }
+}
+
+fn arkham_response() -> ArkhamResponse {
+  parse_arkham_response(json!({
+    "ethereum": { "address": ADDRESS, "chain": "ethereum", "balance": 1.5 }
+  }))
+  .unwrap()
+}
+
+fn expired_cache() -> Arc<TtlCache<ArkhamResponse>> {
+  // Entries expire immediately, so only the stale fallback can read them.
+  Arc::new(TtlCache::new(Arc::new(MemoryCache::new()), Duration::ZERO))
+}
+
+#[test]
+fn chained_provider_falls_back_to_cached_response() {
+  let runtime = Runtime::new().unwrap();
+  let cache = expired_cache();
+  runtime.block_on(cache.insert(ADDRESS, arkham_response()));
+  let provider = ChainedProvider::new(FailingProvider).fallback(CachedProvider::new(cache));
+
+  let data = runtime.block_on(provider.lookup_address(ADDRESS)).unwrap();
+
+  let actual = serde_json::to_value(data).unwrap();
+  let expected = serde_json::to_value(arkham_response()).unwrap();
+  assert_eq!(actual, expected);
+}
+
+#[test]
+fn chained_provider_returns_primary_error_when_all_fail() {
+  let provider =
+    ChainedProvider::new(FailingProvider).fallback(CachedProvider::new(expired_cache()));
+
+  let runtime = Runtime::new().unwrap();
+  let result = runtime.block_on(provider.lookup_address(ADDRESS));
+
+  match result {
+    Err(Error::UpstreamUnavailable { service, status }) => {
+      assert_eq!(service, "Arkham");
+      assert_eq!(status, Some(503));
+    }
+    other => panic!("Expected the primary error, got {:?}", other.map(|_| ())),
+  }
+}
+
+fn fixture_provider() -> FixtureProvider {
+  FixtureProvider::from_json(json!({
+    "addresses": {
+      "0x00000000000000000000000000000000000000AA": {
+        "ethereum": { "address": ADDRESS, "chain": "ethereum", "balance": 1.5 }
+      }
+    },
+    "entities": {
+      "degen": { "id": "degen", "name": "Degen", "addresses": [ADDRESS] }
+    },
+    "transfers": {
+      "0x00000000000000000000000000000000000000aa": { "transfers": [{ "id": "transfer-1" }], "count": 1 }
+    },
+    "portfolios": {
+      "0x00000000000000000000000000000000000000AA": {
+        "total_usd": 3000.0,
+        "chains": {
+          "ethereum": {
+            "total_usd": 3000.0,
+            "holdings": [{ "id": "ethereum", "symbol": "ETH", "balance": 1.0, "price": 3000.0, "usd_value": 3000.0 }]
+          }
+        }
+      }
+    }
+  }))
+  .unwrap()
+}
+
+#[test]
+fn fixture_provider_serves_fixtures() {
+  let provider = fixture_provider();
+  let runtime = Runtime::new().unwrap();
+
+  // Addresses are matched regardless of their case.
+  let data = runtime.block_on(provider.lookup_address(ADDRESS)).unwrap();
+  let actual = serde_json::to_value(data).unwrap();
+  let expected = serde_json::to_value(arkham_response()).unwrap();
+  assert_eq!(actual, expected);
+
+  let entity = runtime.block_on(provider.lookup_entity("degen")).unwrap();
+  assert_eq!(entity.name.as_deref(), Some("Degen"));
+  assert_eq!(entity.addresses(), [ADDRESS]);
+
+  let query = TransfersQuery {
+    limit: None,
+    offset: None,
+    time_gte: None,
+    time_lte: None,
+  };
+  let transfers = runtime
+    .block_on(provider.transfers(ADDRESS, &query))
+    .unwrap();
+  assert_eq!(transfers.count, Some(1));
+  assert_eq!(transfers.transfers.len(), 1);
+
+  let portfolio = runtime.block_on(provider.portfolio(ADDRESS)).unwrap();
+  assert_eq!(portfolio.total_usd, 3000.0);
+  assert_eq!(portfolio.chains["ethereum"].holdings.len(), 1);
+}
+
+#[test]
+fn fixture_provider_does_not_find_unknown_data() {
+  let provider = fixture_provider();
+  let runtime = Runtime::new().unwrap();
+  let unknown = "0x00000000000000000000000000000000000000bb";
+
+  let result = runtime.block_on(provider.lookup_address(unknown));
+  assert!(matches!(result, Err(Error::NotFound(_))));
+
+  let result = runtime.block_on(provider.lookup_entity("unknown"));
+  assert!(matches!(result, Err(Error::NotFound(_))));
+
+  let query = TransfersQuery {
+    limit: None,
+    offset: None,
+    time_gte: None,
+    time_lte: None,
+  };
+  let transfers = runtime
+    .block_on(provider.transfers(unknown, &query))
+    .unwrap();
+  assert_eq!(transfers.count, Some(0));
+  assert!(transfers.transfers.is_empty());
+
+  let portfolio = runtime.block_on(provider.portfolio(unknown)).unwrap();
+  assert_eq!(portfolio, ArkhamPortfolio::default());
+}
+
+#[test]
+fn chained_provider_falls_back_for_entities() {
+  let provider = ChainedProvider::new(FailingProvider).fallback(fixture_provider());
+
+  let runtime = Runtime::new().unwrap();
+  let entity = runtime.block_on(provider.lookup_entity("degen")).unwrap();
+
+  assert_eq!(entity.id.as_deref(), Some("degen"));
+}
+
+#[test]
+fn parse_arkham_portfolio_sorts_and_sums_holdings() {
+  let portfolio = parse_arkham_portfolio(json!({
+    "balances": {
+      "ethereum": [
+        { "id": "usd-coin", "symbol": "USDC", "ethereumAddress": "0xa0b8", "balance": "1000", "price": 1, "usd": 1000 },
+        { "id": "ethereum", "symbol": "ETH", "balance": 1.5, "price": 3000, "usd": 4500 }
+      ],
+      "polygon": [{ "id": "matic-network", "symbol": "MATIC", "balance": 10, "price": 0.5, "usd": 5 }],
+      "bsc": null,
+      "arbitrum_one": [],
+      "base": "not holdings"
+    },
+    "totalBalance": { "ethereum": 5500, "polygon": 5 }
+  }))
+  .unwrap();
+
+  assert_eq!(portfolio.total_usd, 5505.0);
+  // Chains without holdings and malformed chains are left out.
+  let chains: Vec<&str> = portfolio.chains.keys().map(String::as_str).collect();
+  assert_eq!(chains, ["ethereum", "polygon"]);
+
+  let ethereum = &portfolio.chains["ethereum"];
+  assert_eq!(ethereum.total_usd, 5500.0);
+  let symbols: Vec<_> = ethereum
+    .holdings
+    .iter()
+    .map(|holding| holding.symbol.as_deref().unwrap())
+    .collect();
+  assert_eq!(symbols, ["ETH", "USDC"]);
+  assert_eq!(ethereum.holdings[1].balance, Some(1000.0));
+  assert_eq!(
+    ethereum.holdings[1].token_address.as_deref(),
+    Some("0xa0b8")
+  );
+}
+
+#[test]
+fn parse_arkham_portfolio_rejects_payload_without_balances() {
+  let result = parse_arkham_portfolio(json!({ "message": "unexpected" }));
+
+  assert!(matches!(result, Err(Error::UpstreamInvalidResponse(_))));
+}
@@ -0,0 +1,328 @@
+use bson::doc;
+use bson::oid::ObjectId;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::models::alert_event::AlertEvent;
+use crate::models::alert_rule::{AlertCondition, AlertRule};
+use crate::models::price_sample::PriceSample;
+use crate::models::watched_address::WatchedAddress;
+use crate::routes::arkham::{parse_arkham_response, ArkhamResponse};
+use crate::services::alerts::{
+  evaluate, process_change, process_prices, process_transfers, Trigger,
+};
+use crate::services::watcher::AddressChange;
+use crate::settings::SETTINGS;
+use crate::state::AppState;
+use crate::tests::setup::use_app;
+use crate::utils::date;
+use crate::utils::models::ModelExt;
+
+const ADDRESS: &str = "0x00000000000000000000000000000000000000a1";
+
+fn response(ethereum: Value) -> ArkhamResponse {
+  parse_arkham_response(json!({ "ethereum": ethereum })).unwrap()
+}
+
+fn labelled(label: &str) -> ArkhamResponse {
+  response(json!({ "address": ADDRESS, "arkhamLabel": { "name": label } }))
+}
+
+#[test]
+fn evaluate_entity_label_changed() {
+  let triggers = evaluate(
+    AlertCondition::EntityLabelChanged,
+    &labelled("Binance 14"),
+    &labelled("Binance Hot Wallet"),
+  );
+
+  assert_eq!(
+    triggers,
+    vec![Trigger {
+      chain: "ethereum".to_owned(),
+      message: "Entity label changed from Binance 14 to Binance Hot Wallet".to_owned(),
+      entity: None,
+    }]
+  );
+
+  let triggers = evaluate(
+    AlertCondition::EntityLabelChanged,
+    &labelled("Binance 14"),
+    &labelled("Binance 14"),
+  );
+  assert!(triggers.is_empty(), "Unchanged label should not match");
+}
+
+#[test]
+fn evaluate_flagged_as_contract() {
+  let previous = response(json!({ "address": ADDRESS, "contract": false }));
+  let current = response(json!({ "address": ADDRESS, "contract": true }));
+
+  let triggers = evaluate(AlertCondition::FlaggedAsContract, &previous, &current);
+  assert_eq!(triggers.len(), 1);
+
+  let triggers = evaluate(AlertCondition::FlaggedAsContract, &current, &current);
+  assert!(triggers.is_empty(), "Known contracts should not match");
+}
+
+#[test]
+fn evaluate_entity_attached() {
+  let previous = response(json!({ "address": ADDRESS }));
+  let current = response(json!({
+    "address": ADDRESS,
+    "arkhamEntity": { "id": "binance", "name": "Binance" }
+  }));
+
+  let triggers = evaluate(AlertCondition::EntityAttached, &previous, &current);
+  assert_eq!(
+    triggers,
+    vec![Trigger {
+      chain: "ethereum".to_owned(),
+      message: "Arkham entity Binance attached".to_owned(),
+      entity: Some("Binance".to_owned()),
+    }]
+  );
+
+  let triggers = evaluate(AlertCondition::EntityAttached, &current, &previous);
+  assert!(triggers.is_empty(), "Removed entities should not match");
+}
+
+#[test]
+fn process_change_raises_alerts_of_watching_users() {
+  use_app(async move {
+    let watcher = ObjectId::new();
+    let other_user = ObjectId::new();
+    let watched = WatchedAddress::new(
+      ObjectId::new(),
+      watcher,
+      ADDRESS.to_owned(),
+      "ethereum".to_owned(),
+      None,
+    );
+    WatchedAddress::create(watched).await.unwrap();
+
+    let rules = [
+      AlertRule::new(watcher, AlertCondition::EntityLabelChanged, None),
+      AlertRule::new(
+        watcher,
+        AlertCondition::EntityLabelChanged,
+        Some("0x00000000000000000000000000000000000000a2".to_owned()),
+      ),
+      AlertRule::new(watcher, AlertCondition::FlaggedAsContract, None),
+      AlertRule::new(other_user, AlertCondition::EntityLabelChanged, None),
+    ];
+    for rule in rules {
+      AlertRule::create(rule).await.unwrap();
+    }
+
+    let change = AddressChange {
+      address: ADDRESS.to_owned(),
+      previous: labelled("Binance 14"),
+      current: labelled("Binance Hot Wallet"),
+    };
+    let events = process_change(&change).await.unwrap();
+    assert_eq!(
+      events.len(),
+      1,
+      "Only the matching rule of the watching user"
+    );
+
+    let events = AlertEvent::find(doc! {}, None).await.unwrap();
+    let event = events.first().unwrap();
+    assert_eq!(event.user, watcher);
+    assert_eq!(event.condition, AlertCondition::EntityLabelChanged);
+    assert_eq!(event.address.as_deref(), Some(ADDRESS));
+    assert_eq!(event.chain.as_deref(), Some("ethereum"));
+  });
+}
+
+fn whale_rule(user: ObjectId, min_usd: f64) -> AlertRule {
+  let mut rule = AlertRule::new(user, AlertCondition::WhaleTransfer, None);
+  rule.min_usd = Some(min_usd);
+  // The mock transfers were made at the start of 1970.
+  rule.created_at = bson::DateTime::from_millis(0);
+  rule
+}
+
+#[test]
+fn process_transfers_raises_whale_transfer_alerts_once() {
+  use_app(async move {
+    let watcher = ObjectId::new();
+    let other_user = ObjectId::new();
+    let watched = WatchedAddress::new(
+      ObjectId::new(),
+      watcher,
+      ADDRESS.to_owned(),
+      "ethereum".to_owned(),
+      None,
+    );
+    WatchedAddress::create(watched).await.unwrap();
+
+    // Every mock transfer is worth $4200.25.
+    let rule = AlertRule::create(whale_rule(watcher, 4000.0))
+      .await
+      .unwrap();
+    AlertRule::create(whale_rule(watcher, 5000.0))
+      .await
+      .unwrap();
+    AlertRule::create(whale_rule(other_user, 1.0))
+      .await
+      .unwrap();
+
+    let state = AppState::new(Arc::new(SETTINGS.clone()));
+    let provider = state.address_intelligence.as_ref();
+    let events = process_transfers(provider, ADDRESS).await.unwrap();
+    assert_eq!(events.len(), 3, "Every transfer above the threshold");
+
+    let event = events.first().unwrap();
+    assert_eq!(event.user, watcher);
+    assert_eq!(event.rule, rule.id.unwrap());
+    assert_eq!(event.condition, AlertCondition::WhaleTransfer);
+    assert_eq!(event.chain.as_deref(), Some("ethereum"));
+    assert_eq!(event.message, "Sent $4200.25 of ETH to Degen Treasury");
+    assert_eq!(event.counterparty.as_deref(), Some("Degen Treasury"));
+    assert_eq!(event.transaction_hash, Some(format!("0x{:064x}", 1)));
+
+    let events = process_transfers(provider, ADDRESS).await.unwrap();
+    assert!(events.is_empty(), "Transfers should only alert once");
+    let count = AlertEvent::count(doc! {}).await.unwrap();
+    assert_eq!(count, 3);
+  });
+}
+
+#[test]
+fn process_transfers_ignores_transfers_before_the_rule() {
+  use_app(async move {
+    let watcher = ObjectId::new();
+    let watched = WatchedAddress::new(
+      ObjectId::new(),
+      watcher,
+      ADDRESS.to_owned(),
+      "ethereum".to_owned(),
+      None,
+    );
+    WatchedAddress::create(watched).await.unwrap();
+    let mut rule = whale_rule(watcher, 1.0);
+    rule.created_at = bson::DateTime::from_millis(2500);
+    AlertRule::create(rule).await.unwrap();
+
+    let state = AppState::new(Arc::new(SETTINGS.clone()));
+    let events = process_transfers(state.address_intelligence.as_ref(), ADDRESS)
+      .await
+      .unwrap();
+
+    assert_eq!(events.len(), 1, "Only the transfer made at 3000 ms");
+  });
+}
+
+fn price_rule(condition: AlertCondition, token: &str) -> AlertRule {
+  let mut rule = AlertRule::new(ObjectId::new(), condition, None);
+  rule.token = Some(token.to_owned());
+  rule
+}
+
+fn sample(token: &str, usd: f64, age: Duration) -> PriceSample {
+  let mut sample = PriceSample::new(
+    token.to_owned(),
+    usd,
+    date::after(Duration::from_secs(3600)),
+  );
+  sample.created_at = date::before(age);
+  sample
+}
+
+#[test]
+fn process_prices_raises_alerts_when_the_price_crosses_the_threshold() {
+  use_app(async move {
+    // The mock prices are $3000.25 for ethereum and $60000.50 for bitcoin.
+    PriceSample::create(sample("ethereum", 2900.0, Duration::from_secs(60)))
+      .await
+      .unwrap();
+    let mut rule = price_rule(AlertCondition::PriceAbove, "ethereum");
+    rule.price_usd = Some(3000.0);
+    let rule = AlertRule::create(rule).await.unwrap();
+    let mut above = price_rule(AlertCondition::PriceAbove, "ethereum");
+    above.price_usd = Some(4000.0);
+    AlertRule::create(above).await.unwrap();
+    // Bitcoin has no recorded price yet, the current one is a baseline.
+    let mut baseline = price_rule(AlertCondition::PriceBelow, "bitcoin");
+    baseline.price_usd = Some(70000.0);
+    AlertRule::create(baseline).await.unwrap();
+
+    let state = AppState::new(Arc::new(SETTINGS.clone()));
+    let settings = &SETTINGS.price_alerts;
+    let events = process_prices(state.prices.as_ref(), settings)
+      .await
+      .unwrap();
+    assert_eq!(events.len(), 1, "Only the crossed threshold");
+
+    let event = events.first().unwrap();
+    assert_eq!(event.user, rule.user);
+    assert_eq!(event.rule, rule.id.unwrap());
+    assert_eq!(event.condition, AlertCondition::PriceAbove);
+    assert_eq!(event.token.as_deref(), Some("ethereum"));
+    assert_eq!(event.address, None);
+    assert_eq!(
+      event.message,
+      "ethereum rose above $3000.00, now at $3000.25"
+    );
+
+    let samples = PriceSample::count(doc! {}).await.unwrap();
+    assert_eq!(samples, 3, "The current prices should be recorded");
+
+    let events = process_prices(state.prices.as_ref(), settings)
+      .await
+      .unwrap();
+    assert!(
+      events.is_empty(),
+      "The price should cross the threshold again"
+    );
+  });
+}
+
+#[test]
+fn process_prices_raises_price_change_alerts_after_the_cooldown() {
+  use_app(async move {
+    PriceSample::create(sample("ethereum", 2000.0, Duration::from_secs(7200)))
+      .await
+      .unwrap();
+    let mut rule = price_rule(AlertCondition::PriceChange, "ethereum");
+    rule.change_percent = Some(10.0);
+    rule.window_secs = Some(3600);
+    let rule = AlertRule::create(rule).await.unwrap();
+    let mut drop = price_rule(AlertCondition::PriceChange, "ethereum");
+    drop.change_percent = Some(-10.0);
+    drop.window_secs = Some(3600);
+    AlertRule::create(drop).await.unwrap();
+
+    let state = AppState::new(Arc::new(SETTINGS.clone()));
+    let settings = &SETTINGS.price_alerts;
+    let events = process_prices(state.prices.as_ref(), settings)
+      .await
+      .unwrap();
+    assert_eq!(events.len(), 1, "Only the rule on a rise");
+    assert_eq!(
+      events.first().unwrap().message,
+      "ethereum rose 50.01% in 1h, now at $3000.25"
+    );
+
+    let events = process_prices(state.prices.as_ref(), settings)
+      .await
+      .unwrap();
+    assert!(events.is_empty(), "The rule should be cooling down");
+
+    let triggered_at = date::before(Duration::from_secs(settings.cooldown_secs + 60));
+    AlertRule::update_one(
+      doc! { "_id": rule.id.unwrap() },
+      doc! { "$set": { "last_triggered_at": triggered_at } },
+      None,
+    )
+    .await
+    .unwrap();
+    let events = process_prices(state.prices.as_ref(), settings)
+      .await
+      .unwrap();
+    assert_eq!(events.len(), 1, "The cooldown should be over");
+  });
+}
@@ -0,0 +1,98 @@
+use chrono::{TimeZone, Utc};
+
+use crate::settings::{Deprecation, Settings};
+use crate::utils::api_version::{deprecation_headers, version};
+
+fn deprecation() -> Deprecation {
+  Deprecation {
+    deprecated_at: Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap(),
+    sunset_at: Some(Utc.with_ymd_and_hms(2025, 7, 1, 0, 0, 0).unwrap()),
+    link: Some("https://docs.example.com/migrate-to-v2".to_owned()),
+  }
+}
+
+#[test]
+fn version_of_versioned_paths() {
+  assert_eq!(version("/v1/cats"), Some("v1"));
+  assert_eq!(version("/v1"), Some("v1"));
+  assert_eq!(
+    version("/v1/arkham/0x00000000000000000000000000000000000000a1"),
+    Some("v1")
+  );
+}
+
+#[test]
+fn version_of_unversioned_paths() {
+  assert_eq!(version("/status"), None);
+  assert_eq!(version("/"), None);
+  // Only the first segment names the version.
+  assert_eq!(version("/graphql/v1"), None);
+  assert_eq!(version("/v9/cats"), None);
+}
+
+#[test]
+fn deprecation_headers_of_deprecated_version() {
+  let headers = deprecation_headers(&deprecation())
+    .into_iter()
+    .map(|(name, value)| (name.to_string(), value.to_str().unwrap().to_owned()))
+    .collect::<Vec<(String, String)>>();
+
+  assert_eq!(
+    headers,
+    vec![
+      ("deprecation".to_owned(), "@1735689600".to_owned()),
+      (
+        "sunset".to_owned(),
+        "Tue, 01 Jul 2025 00:00:00 GMT".to_owned()
+      ),
+      (
+        "link".to_owned(),
+        "<https://docs.example.com/migrate-to-v2>; rel=\"deprecation\"; type=\"text/html\""
+          .to_owned()
+      ),
+    ]
+  );
+}
+
+#[test]
+fn deprecation_headers_without_sunset() {
+  let deprecation = Deprecation {
+    sunset_at: None,
+    link: None,
+    ..deprecation()
+  };
+
+  let headers = deprecation_headers(&deprecation);
+
+  assert_eq!(headers.len(), 1);
+  assert_eq!(headers[0].0, "deprecation");
+}
+
+#[test]
+fn settings_validate_deprecated_versions() {
+  let mut settings = Settings::new().unwrap();
+  settings
+    .versions
+    .deprecated
+    .insert("v1".to_owned(), deprecation());
+  assert!(settings.validate().is_ok());
+
+  let invalid = Deprecation {
+    sunset_at: Some(Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap()),
+    link: Some("docs.example.com".to_owned()),
+    ..deprecation()
+  };
+  settings
+    .versions
+    .deprecated
+    .insert("v1".to_owned(), invalid);
+  settings
+    .versions
+    .deprecated
+    .insert("v0".to_owned(), deprecation());
+
+  let err = settings.validate().unwrap_err().to_string();
+  assert!(err.contains("versions.deprecated.v0 must be an API version"));
+  assert!(err.contains("versions.deprecated.v1.sunset_at"));
+  assert!(err.contains("versions.deprecated.v1.link"));
+}
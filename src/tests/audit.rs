@@ -0,0 +1,42 @@
+use bson::{doc, oid::ObjectId};
+
+use crate::models::audit_log::{AuditAction, AuditLog};
+
+#[test]
+fn audit_log_diff_keeps_changed_fields() {
+  let id = ObjectId::new();
+  let before = doc! { "_id": id, "name": "Tigrin", "tags": ["orange"], "updated_at": 1 };
+  let after = doc! { "_id": id, "name": "Michi", "tags": ["orange"], "updated_at": 2, "age": 3 };
+
+  let (before, after) = AuditLog::diff(&before, &after).unwrap();
+  assert_eq!(before, doc! { "name": "Tigrin", "age": null, "_id": id });
+  assert_eq!(after, doc! { "name": "Michi", "age": 3, "_id": id });
+}
+
+#[test]
+fn audit_log_diff_skips_bookkeeping_changes() {
+  let id = ObjectId::new();
+  let before = doc! { "_id": id, "name": "Tigrin", "last_used_at": 1 };
+  let after = doc! { "_id": id, "name": "Tigrin", "last_used_at": 2 };
+
+  assert!(AuditLog::diff(&before, &after).is_none());
+}
+
+#[test]
+fn audit_log_redacts_secrets() {
+  let id = ObjectId::new();
+  let user = doc! { "_id": id, "email": "nico@test.com", "password": "hash" };
+
+  let log = AuditLog::new(
+    None,
+    "POST /users".to_owned(),
+    "users",
+    AuditAction::Create,
+    None,
+    Some(user),
+  );
+  assert_eq!(log.document, Some(id));
+  let after = log.after.unwrap();
+  assert_eq!(after.get_str("email").unwrap(), "nico@test.com");
+  assert_eq!(after.get_str("password").unwrap(), "[redacted]");
+}
@@ -0,0 +1,114 @@
+use axum::http::{header, HeaderMap, HeaderValue};
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+
+use crate::errors::ErrorResponse;
+use crate::models::watchlist::PublicWatchlist;
+use crate::tests::setup::use_app;
+use crate::tests::utils::{create_user, create_user_token};
+use crate::utils::binary_format::BinaryFormat;
+
+fn accept(value: &'static str) -> HeaderMap {
+  let mut headers = HeaderMap::new();
+  headers.insert(header::ACCEPT, HeaderValue::from_static(value));
+  headers
+}
+
+#[test]
+fn binary_format_from_accept() {
+  let format = BinaryFormat::from_accept(&accept("application/msgpack"));
+  assert_eq!(format, Some(BinaryFormat::MessagePack));
+
+  let format = BinaryFormat::from_accept(&accept("text/html, application/cbor;q=0.9"));
+  assert_eq!(format, Some(BinaryFormat::Cbor));
+
+  // The first of JSON and the binary formats wins.
+  let format = BinaryFormat::from_accept(&accept("application/json, application/msgpack"));
+  assert_eq!(format, None);
+
+  let format = BinaryFormat::from_accept(&accept("*/*"));
+  assert_eq!(format, None);
+  assert_eq!(BinaryFormat::from_accept(&HeaderMap::new()), None);
+}
+
+#[test]
+fn binary_format_round_trip() {
+  let value = json!({ "name": "Exchanges", "count": 2, "tags": ["cex", null] });
+
+  for format in [BinaryFormat::MessagePack, BinaryFormat::Cbor] {
+    let bytes = format.encode(&value).unwrap();
+    let decoded = format.decode::<Value>(&bytes).unwrap();
+    assert_eq!(decoded, value);
+  }
+}
+
+#[test]
+fn post_msgpack_watchlist() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user.clone()).await.unwrap();
+
+    let body = BinaryFormat::MessagePack
+      .encode(&json!({ "name": "Exchanges" }))
+      .unwrap();
+    let client = reqwest::Client::new();
+    let res = client
+      .post("http://localhost:8088/v1/watchlists")
+      .header("Authorization", format!("Bearer {}", token))
+      .header("Content-Type", "application/msgpack")
+      .header("Accept", "application/msgpack")
+      .body(body)
+      .send()
+      .await
+      .unwrap();
+
+    assert_eq!(res.status(), StatusCode::CREATED);
+    assert_eq!(res.headers()["content-type"], "application/msgpack");
+
+    let bytes = res.bytes().await.unwrap();
+    let body = rmp_serde::from_slice::<PublicWatchlist>(&bytes).unwrap();
+    assert_eq!(body.name, "Exchanges");
+    assert_eq!(body.user, user.id.unwrap());
+  });
+}
+
+#[test]
+fn post_invalid_cbor_watchlist() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user).await.unwrap();
+    let client = reqwest::Client::new();
+
+    // The body doesn't match the payload.
+    let body = BinaryFormat::Cbor.encode(&json!({ "name": 42 })).unwrap();
+    let res = client
+      .post("http://localhost:8088/v1/watchlists")
+      .header("Authorization", format!("Bearer {}", token))
+      .header("Content-Type", "application/cbor")
+      .header("Accept", "application/cbor")
+      .body(body)
+      .send()
+      .await
+      .unwrap();
+
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(res.headers()["content-type"], "application/cbor");
+    let bytes = res.bytes().await.unwrap();
+    let body = ciborium::from_reader::<ErrorResponse, _>(&bytes[..]).unwrap();
+    assert_eq!(body.code, "validation_failed");
+
+    // The body isn't CBOR.
+    let res = client
+      .post("http://localhost:8088/v1/watchlists")
+      .header("Authorization", format!("Bearer {}", token))
+      .header("Content-Type", "application/cbor")
+      .body(vec![0xff, 0x00])
+      .send()
+      .await
+      .unwrap();
+
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    let body = res.json::<ErrorResponse>().await.unwrap();
+    assert_eq!(body.code, "malformed_payload");
+  });
+}
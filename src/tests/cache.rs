@@ -0,0 +1,80 @@
+use std::sync::Arc;
+use std::thread::sleep;
+use std::time::Duration;
+use tokio::runtime::Runtime;
+
+use crate::utils::cache::{Cache, MemoryCache, TtlCache};
+
+#[test]
+fn memory_cache_expires_entries_after_their_ttl() {
+  let runtime = Runtime::new().unwrap();
+  let cache = MemoryCache::new();
+
+  runtime.block_on(async {
+    cache
+      .set_with_ttl("short", b"1".to_vec(), Duration::from_millis(50))
+      .await;
+    cache
+      .set_with_ttl("long", b"2".to_vec(), Duration::from_secs(60))
+      .await;
+    assert_eq!(cache.get("short").await, Some(b"1".to_vec()));
+  });
+
+  sleep(Duration::from_millis(100));
+
+  runtime.block_on(async {
+    assert_eq!(cache.get("short").await, None);
+    assert_eq!(cache.get("long").await, Some(b"2".to_vec()));
+  });
+}
+
+#[test]
+fn memory_cache_invalidates_entries() {
+  let runtime = Runtime::new().unwrap();
+  let cache = MemoryCache::new();
+
+  runtime.block_on(async {
+    cache
+      .set_with_ttl("key", b"value".to_vec(), Duration::from_secs(60))
+      .await;
+    cache.invalidate("key").await;
+    assert_eq!(cache.get("key").await, None);
+  });
+}
+
+#[test]
+fn ttl_cache_serves_expired_entries_as_stale() {
+  let runtime = Runtime::new().unwrap();
+  let cache = TtlCache::new(Arc::new(MemoryCache::new()), Duration::ZERO);
+
+  runtime.block_on(async {
+    cache.insert("key", vec!["value".to_owned()]).await;
+
+    assert!(cache.get("key").await.is_none(), "Entry should be expired");
+    let entry = cache.get_stale("key").await.unwrap();
+    assert_eq!(entry.value, vec!["value".to_owned()]);
+    assert!(entry.is_expired());
+  });
+
+  let stats = cache.stats();
+  assert_eq!(stats.hits, 0);
+  assert_eq!(stats.misses, 1);
+}
+
+#[test]
+fn ttl_cache_counts_hits_and_misses() {
+  let runtime = Runtime::new().unwrap();
+  let cache = TtlCache::new(Arc::new(MemoryCache::new()), Duration::from_secs(60));
+
+  runtime.block_on(async {
+    assert!(cache.get("key").await.is_none());
+    cache.insert("key", 42_u64).await;
+    let entry = cache.get("key").await.unwrap();
+    assert_eq!(entry.value, 42);
+    assert!(entry.remaining_ttl() > Duration::from_secs(55));
+  });
+
+  let stats = cache.stats();
+  assert_eq!(stats.hits, 1);
+  assert_eq!(stats.misses, 1);
+}
@@ -0,0 +1,46 @@
+use bson::oid::ObjectId;
+use serde_json::Value as Json;
+
+use crate::models::cat::{Cat, PublicCat};
+use crate::utils::casing::{convert_keys, Casing};
+
+fn public_cat() -> Json {
+  let mut cat = Cat::new(ObjectId::new(), "Tigrin".to_owned());
+  cat.id = Some(ObjectId::new());
+
+  serde_json::to_value(PublicCat::from(cat)).unwrap()
+}
+
+#[test]
+fn convert_keys_to_camel_case() {
+  let cat = convert_keys(public_cat(), Casing::Camel);
+
+  assert!(cat["createdAt"].is_string());
+  assert!(cat["updatedAt"].is_string());
+  assert!(cat.get("created_at").is_none());
+  assert_eq!(cat["name"], "Tigrin");
+}
+
+#[test]
+fn convert_keys_to_snake_case() {
+  let cat = convert_keys(public_cat(), Casing::Camel);
+  let cat = convert_keys(cat, Casing::Snake);
+
+  assert!(cat["created_at"].is_string());
+  assert!(cat["updated_at"].is_string());
+  assert!(cat.get("createdAt").is_none());
+}
+
+#[test]
+fn convert_keys_keeps_non_identifier_keys() {
+  let address = "0x52908400098527886E0F7030069857D2E4169EE7";
+  let value = serde_json::json!({ address: { "usd_value": 1, "arkhamEntity": null } });
+
+  let value = convert_keys(value, Casing::Snake);
+  assert!(value[address]["usd_value"].is_number());
+  assert!(value[address].get("arkham_entity").is_some());
+
+  let value = convert_keys(value, Casing::Camel);
+  assert!(value[address]["usdValue"].is_number());
+  assert!(value[address].get("arkhamEntity").is_some());
+}
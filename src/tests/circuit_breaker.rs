@@ -0,0 +1,61 @@
+use std::thread::sleep;
+use std::time::Duration;
+
+use crate::utils::circuit_breaker::CircuitBreaker;
+
+#[test]
+fn circuit_opens_after_consecutive_failures() {
+  let breaker = CircuitBreaker::new("test", 3, Duration::from_secs(60));
+
+  breaker.record_failure();
+  breaker.record_failure();
+  assert!(breaker.allow());
+  assert!(!breaker.is_open());
+
+  breaker.record_failure();
+  assert!(breaker.is_open());
+  assert!(!breaker.allow());
+}
+
+#[test]
+fn circuit_success_resets_the_failures() {
+  let breaker = CircuitBreaker::new("test", 2, Duration::from_secs(60));
+
+  breaker.record_failure();
+  breaker.record_success();
+  breaker.record_failure();
+  assert!(!breaker.is_open());
+}
+
+#[test]
+fn circuit_lets_a_single_trial_through_after_the_timeout() {
+  let breaker = CircuitBreaker::new("test", 1, Duration::from_millis(10));
+  breaker.record_failure();
+  assert!(!breaker.allow());
+
+  sleep(Duration::from_millis(20));
+  assert!(breaker.allow());
+  assert!(!breaker.allow(), "Only one trial request at a time");
+
+  // A failed trial opens the circuit again.
+  breaker.record_failure();
+  assert!(!breaker.allow());
+
+  sleep(Duration::from_millis(20));
+  assert!(breaker.allow());
+  breaker.record_success();
+  assert!(!breaker.is_open());
+  assert!(breaker.allow());
+}
+
+#[test]
+fn circuit_cancelled_trial_lets_another_through() {
+  let breaker = CircuitBreaker::new("test", 1, Duration::from_millis(10));
+  breaker.record_failure();
+
+  sleep(Duration::from_millis(20));
+  assert!(breaker.allow());
+  breaker.record_cancelled();
+  assert!(breaker.is_open());
+  assert!(breaker.allow());
+}
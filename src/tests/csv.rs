@@ -0,0 +1,27 @@
+use serde_json::json;
+
+use crate::utils::csv::to_csv;
+
+#[test]
+fn to_csv_writes_header_and_rows() {
+  let items = json!([
+    { "id": "1", "name": "Tigrin, the cat", "tags": ["orange"] },
+    { "id": "2", "name": "Cielito", "nickname": null }
+  ]);
+
+  let actual = String::from_utf8(to_csv(&items).unwrap()).unwrap();
+  let expected = concat!(
+    "id,name,tags,nickname\n",
+    "1,\"Tigrin, the cat\",\"[\"\"orange\"\"]\",\n",
+    "2,Cielito,,\n",
+  );
+  assert_eq!(actual, expected);
+}
+
+#[test]
+fn to_csv_writes_nothing_for_empty_list() {
+  let items: Vec<serde_json::Value> = Vec::new();
+
+  let actual = to_csv(&items).unwrap();
+  assert!(actual.is_empty());
+}
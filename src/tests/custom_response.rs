@@ -0,0 +1,73 @@
+use axum::response::IntoResponse;
+use serde::Serialize;
+use serde_json::{json, Value as Json};
+use tokio::runtime::Runtime;
+
+use crate::utils::custom_response::{CustomResponse, CustomResponseBuilder, ResponseMeta};
+
+fn to_json<T: Serialize>(res: CustomResponse<T>) -> Json {
+  let runtime = Runtime::new().unwrap();
+  let body = runtime
+    .block_on(hyper::body::to_bytes(res.into_response().into_body()))
+    .unwrap();
+
+  serde_json::from_slice(&body).unwrap()
+}
+
+fn meta() -> ResponseMeta {
+  ResponseMeta {
+    cache: Some("MISS".to_owned()),
+    upstream_latency_ms: Some(42),
+    ..Default::default()
+  }
+}
+
+#[test]
+fn custom_response_without_meta_is_sent_as_is() {
+  let res = CustomResponseBuilder::new()
+    .body(json!({ "name": "Tigrin" }))
+    .build();
+
+  assert_eq!(to_json(res), json!({ "name": "Tigrin" }));
+}
+
+#[test]
+fn custom_response_adds_meta_and_warnings_to_objects() {
+  let res = CustomResponseBuilder::new()
+    .body(json!({ "name": "Tigrin" }))
+    .meta(meta())
+    .warning("avalanche data unavailable, served stale")
+    .build();
+
+  assert_eq!(
+    to_json(res),
+    json!({
+      "name": "Tigrin",
+      "meta": { "cache": "MISS", "upstream_latency_ms": 42 },
+      "warnings": ["avalanche data unavailable, served stale"]
+    })
+  );
+}
+
+#[test]
+fn custom_response_sends_warnings_along_with_meta() {
+  let res = CustomResponseBuilder::new()
+    .body(json!({ "name": "Tigrin" }))
+    .meta(meta())
+    .build();
+
+  assert_eq!(to_json(res)["warnings"], json!([]));
+}
+
+#[test]
+fn custom_response_wraps_other_bodies_in_data() {
+  let res = CustomResponseBuilder::new()
+    .body(json!(["Tigrin", "Misifu"]))
+    .warning("partial results")
+    .build();
+
+  assert_eq!(
+    to_json(res),
+    json!({ "data": ["Tigrin", "Misifu"], "warnings": ["partial results"] })
+  );
+}
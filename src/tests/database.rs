@@ -0,0 +1,80 @@
+use std::time::{Duration, Instant};
+use tokio::runtime::Runtime;
+
+use wither::mongodb::options::{ReadPreference, SelectionCriteria};
+
+use crate::database::{client_options, connect};
+use crate::settings::{Database, ReadPreferenceMode};
+
+#[test]
+fn connect_retries_and_gives_up_on_refused_connection() {
+  let settings = Database {
+    // Nothing listens on this port, so every attempt is refused.
+    uri: "mongodb://localhost:1".to_owned(),
+    name: "rustapi-test".to_owned(),
+    min_pool_size: None,
+    max_pool_size: Some(1),
+    server_selection_timeout_ms: Some(100),
+    connect_timeout_ms: None,
+    read_preference: None,
+    retry_writes: None,
+    connect_attempts: 3,
+    connect_retry_delay_ms: 50,
+  };
+
+  let runtime = Runtime::new().unwrap();
+  let started_at = Instant::now();
+  let result = runtime.block_on(connect(&settings));
+
+  assert!(result.is_err(), "Connection should fail after all attempts");
+  // Three attempts sleep twice between them: 50ms and then 100ms.
+  assert!(
+    started_at.elapsed() >= Duration::from_millis(150),
+    "Connection should back off between attempts"
+  );
+}
+
+#[test]
+fn client_options_override_the_uri() {
+  let mut settings = Database {
+    uri: "mongodb://localhost:27017/?maxPoolSize=20&connectTimeoutMS=2000&retryWrites=false"
+      .to_owned(),
+    name: "rustapi-test".to_owned(),
+    min_pool_size: None,
+    max_pool_size: None,
+    server_selection_timeout_ms: None,
+    connect_timeout_ms: None,
+    read_preference: None,
+    retry_writes: None,
+    connect_attempts: 1,
+    connect_retry_delay_ms: 0,
+  };
+
+  let runtime = Runtime::new().unwrap();
+  let options = runtime.block_on(client_options(&settings)).unwrap();
+  assert_eq!(
+    options.max_pool_size,
+    Some(20),
+    "URI options should be kept"
+  );
+  assert_eq!(options.connect_timeout, Some(Duration::from_millis(2000)));
+  assert_eq!(options.retry_writes, Some(false));
+  assert!(options.selection_criteria.is_none());
+
+  settings.min_pool_size = Some(5);
+  settings.max_pool_size = Some(200);
+  settings.connect_timeout_ms = Some(500);
+  settings.read_preference = Some(ReadPreferenceMode::SecondaryPreferred);
+  settings.retry_writes = Some(true);
+  let options = runtime.block_on(client_options(&settings)).unwrap();
+  assert_eq!(options.min_pool_size, Some(5));
+  assert_eq!(options.max_pool_size, Some(200));
+  assert_eq!(options.connect_timeout, Some(Duration::from_millis(500)));
+  assert_eq!(options.retry_writes, Some(true));
+  assert!(matches!(
+    options.selection_criteria,
+    Some(SelectionCriteria::ReadPreference(
+      ReadPreference::SecondaryPreferred { .. }
+    ))
+  ));
+}
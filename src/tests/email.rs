@@ -0,0 +1,239 @@
+use bson::doc;
+use bson::oid::ObjectId;
+use lettre::transport::stub::AsyncStubTransport;
+
+use crate::models::account_token::{AccountToken, TokenPurpose};
+use crate::models::alert_event::AlertEvent;
+use crate::models::alert_rule::AlertCondition;
+use crate::models::user::User;
+use crate::notifications::email::{
+  alert_email, digest_email, email_change_email, password_reset_email, EmailNotifier,
+};
+use crate::notifications::Notifiers;
+use crate::services::{account, digest};
+use crate::settings::SETTINGS;
+use crate::tests::setup::use_app;
+use crate::tests::utils::create_user;
+use crate::utils::models::ModelExt;
+
+fn alert_event(user: ObjectId, message: &str) -> AlertEvent {
+  AlertEvent::new(
+    user,
+    ObjectId::new(),
+    AlertCondition::FlaggedAsContract,
+    "0x00000000000000000000000000000000000000a1".to_owned(),
+    "ethereum".to_owned(),
+    message.to_owned(),
+  )
+}
+
+fn email_notifier(transport: &AsyncStubTransport) -> EmailNotifier {
+  EmailNotifier::new(
+    transport.clone(),
+    "Degen <alerts@test.com>".parse().unwrap(),
+  )
+}
+
+// Recipients of the emails sent through the stub transport.
+async fn recipients(transport: &AsyncStubTransport) -> Vec<String> {
+  transport
+    .messages()
+    .await
+    .iter()
+    .flat_map(|(envelope, _)| envelope.to().iter().map(ToString::to_string))
+    .collect()
+}
+
+#[test]
+fn alert_email_has_text_and_html_bodies() {
+  let event = alert_event(ObjectId::new(), "Entity label changed from <none> to A&B");
+
+  let email = alert_email(&[event]);
+  assert_eq!(
+    email.subject,
+    "Degen alert: Entity label changed from <none> to A&B"
+  );
+  assert!(email.text.contains(
+    "Entity label changed from <none> to A&B\nAddress: 0x00000000000000000000000000000000000000a1"
+  ));
+  assert!(email
+    .html
+    .contains("<strong>Entity label changed from &lt;none&gt; to A&amp;B</strong>"));
+  assert!(email
+    .html
+    .contains("https://etherscan.io/address/0x00000000000000000000000000000000000000a1"));
+}
+
+#[test]
+fn digest_email_counts_unlisted_alerts() {
+  let events = vec![alert_event(ObjectId::new(), "Address flagged as contract")];
+
+  let email = digest_email(&events, 3);
+  assert_eq!(email.subject, "Your weekly Degen digest: 3 alerts");
+  assert!(email.text.ends_with("And 2 more alerts.\n"));
+  assert!(email.html.contains("<p>And 2 more alerts.</p>"));
+}
+
+#[test]
+fn dispatch_emails_users_who_opted_in() {
+  use_app(async move {
+    let subscriber = create_user("subscriber@test.com").await.unwrap();
+    let subscriber_id = subscriber.id.unwrap();
+    User::update_one(
+      doc! { "_id": &subscriber_id },
+      doc! { "$set": { "email_preferences.alerts": true } },
+      None,
+    )
+    .await
+    .unwrap();
+    let other = create_user("other@test.com").await.unwrap();
+
+    let transport = AsyncStubTransport::new_ok();
+    let notifiers = Notifiers::default().with_email(email_notifier(&transport));
+    let events = [
+      alert_event(subscriber_id, "Address flagged as contract"),
+      alert_event(subscriber_id, "Arkham entity Binance attached"),
+      alert_event(other.id.unwrap(), "Address flagged as contract"),
+    ];
+    notifiers.dispatch(&events).await.unwrap();
+
+    assert_eq!(
+      recipients(&transport).await,
+      vec!["subscriber@test.com"],
+      "A single email, only to the user who opted in"
+    );
+    let messages = transport.messages().await;
+    assert!(messages[0].1.contains("Subject: 2 Degen alerts"));
+  });
+}
+
+#[test]
+fn digest_is_sent_once_a_week() {
+  use_app(async move {
+    let user = create_user("digest@test.com").await.unwrap();
+    let user_id = user.id.unwrap();
+    User::update_one(
+      doc! { "_id": &user_id },
+      doc! { "$set": { "email_preferences.weekly_digest": true } },
+      None,
+    )
+    .await
+    .unwrap();
+    AlertEvent::create(alert_event(user_id, "Address flagged as contract"))
+      .await
+      .unwrap();
+
+    let transport = AsyncStubTransport::new_ok();
+    let notifier = email_notifier(&transport);
+
+    let sent = digest::send_due(&notifier).await.unwrap();
+    assert_eq!(sent, 1);
+    assert_eq!(recipients(&transport).await, vec!["digest@test.com"]);
+
+    let user = User::find_by_id(&user_id).await.unwrap().unwrap();
+    assert!(user.digest_sent_at.is_some());
+
+    let sent = digest::send_due(&notifier).await.unwrap();
+    assert_eq!(sent, 0, "The next digest is due in a week");
+  });
+}
+
+#[test]
+fn password_reset_email_links_to_the_app() {
+  let email = password_reset_email("http://localhost:8080/reset-password?token=a&b");
+
+  assert_eq!(email.subject, "Reset your Degen password");
+  assert!(email
+    .text
+    .ends_with("\nhttp://localhost:8080/reset-password?token=a&b\n"));
+  assert!(email
+    .html
+    .contains("<a href=\"http://localhost:8080/reset-password?token=a&amp;b\">"));
+}
+
+#[test]
+fn password_reset_is_emailed_to_registered_users() {
+  use_app(async move {
+    let user = create_user("reset@test.com").await.unwrap();
+    let transport = AsyncStubTransport::new_ok();
+    let notifier = email_notifier(&transport);
+
+    for email in ["reset@test.com", "unknown@test.com"] {
+      account::request_password_reset(email, &notifier, &SETTINGS.account)
+        .await
+        .unwrap();
+    }
+
+    assert_eq!(recipients(&transport).await, vec!["reset@test.com"]);
+    let messages = transport.messages().await;
+    assert!(messages[0].1.contains("Subject: Reset your Degen password"));
+
+    let tokens = AccountToken::count(doc! {
+      "user": user.id.unwrap(),
+      "purpose": TokenPurpose::PasswordReset.as_str()
+    })
+    .await
+    .unwrap();
+    assert_eq!(tokens, 1);
+  });
+}
+
+#[test]
+fn email_verification_replaces_the_previous_token() {
+  use_app(async move {
+    let user = create_user("verify@test.com").await.unwrap();
+    let transport = AsyncStubTransport::new_ok();
+    let notifier = email_notifier(&transport);
+
+    for _ in 0..2 {
+      account::send_email_verification(&user, &notifier, &SETTINGS.account)
+        .await
+        .unwrap();
+    }
+
+    assert_eq!(
+      recipients(&transport).await,
+      vec!["verify@test.com", "verify@test.com"]
+    );
+    let tokens = AccountToken::count(doc! { "user": user.id.unwrap() })
+      .await
+      .unwrap();
+    assert_eq!(tokens, 1, "Only the last link works");
+  });
+}
+
+#[test]
+fn email_change_email_names_the_new_email() {
+  let email = email_change_email(
+    "nico@test.com",
+    "http://localhost:8080/reset-password?token=a",
+  );
+
+  assert_eq!(email.subject, "Your Degen email was changed");
+  assert!(email.text.contains("changed to nico@test.com."));
+  assert!(email
+    .text
+    .ends_with("\nhttp://localhost:8080/reset-password?token=a\n"));
+}
+
+#[test]
+fn email_change_notice_is_emailed_to_the_previous_email() {
+  use_app(async move {
+    let user = create_user("previous@test.com").await.unwrap();
+    let transport = AsyncStubTransport::new_ok();
+    let notifier = email_notifier(&transport);
+
+    account::send_email_change_notice(&user, "next@test.com", &notifier, &SETTINGS.account)
+      .await
+      .unwrap();
+
+    assert_eq!(recipients(&transport).await, vec!["previous@test.com"]);
+    let tokens = AccountToken::count(doc! {
+      "user": user.id.unwrap(),
+      "purpose": TokenPurpose::PasswordReset.as_str()
+    })
+    .await
+    .unwrap();
+    assert_eq!(tokens, 1, "The link resets the password");
+  });
+}
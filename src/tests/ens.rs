@@ -0,0 +1,43 @@
+use crate::services::ens::{is_name, namehash, normalize_name};
+
+#[test]
+fn namehash_of_empty_name() {
+  assert_eq!(namehash(""), [0_u8; 32]);
+}
+
+#[test]
+fn namehash_follows_eip_137() {
+  assert_eq!(
+    hex::encode(namehash("eth")),
+    "93cdeb708b7545dc668eb9280176169d1c33cfd8ed6f04690a0bcc88a93fc4ae"
+  );
+  assert_eq!(
+    hex::encode(namehash("foo.eth")),
+    "de9b09fd7c5f901e23a3f19fecc54828e9c848539801e86591bd9801b019f84f"
+  );
+}
+
+#[test]
+fn is_name_distinguishes_names_from_addresses() {
+  assert!(is_name("vitalik.eth"));
+  assert!(!is_name("0x00000000000000000000000000000000000000a1"));
+}
+
+#[test]
+fn normalize_name_lowercases() {
+  assert_eq!(normalize_name(" Vitalik.ETH ").unwrap(), "vitalik.eth");
+  assert_eq!(normalize_name("pay.my-dao.eth").unwrap(), "pay.my-dao.eth");
+}
+
+#[test]
+fn normalize_name_rejects_invalid_names() {
+  for name in [
+    "eth",
+    "vitalik..eth",
+    ".eth",
+    "vitalik.eth.",
+    "vi talik.eth",
+  ] {
+    assert!(normalize_name(name).is_err(), "{} should be rejected", name);
+  }
+}
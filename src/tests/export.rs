@@ -0,0 +1,107 @@
+use bson::doc;
+use serde_json::{json, Value};
+
+use crate::models::address_label::AddressLabel;
+use crate::services::export::{Command, Format};
+use crate::tests::setup::use_app;
+use crate::utils::models::ModelExt;
+
+fn args(args: &[&str]) -> Vec<String> {
+  args.iter().map(|arg| arg.to_string()).collect()
+}
+
+#[test]
+fn parse_export_command() {
+  let command = Command::parse(&args(&["labels"])).unwrap();
+  assert_eq!(command.collection, "address_labels");
+  assert_eq!(command.format, Format::Ndjson);
+  assert!(command.filter.is_empty());
+  assert!(command.fields.is_empty());
+  assert_eq!(command.output, None);
+
+  let command = Command::parse(&args(&[
+    "cats",
+    "--format",
+    "json",
+    "--filter",
+    r#"{"name": "Tigrin"}"#,
+    "--fields",
+    "name, _id",
+    "--output",
+    "cats.json",
+  ]))
+  .unwrap();
+  assert_eq!(command.collection, "cats");
+  assert_eq!(command.format, Format::Json);
+  assert_eq!(command.filter, doc! { "name": "Tigrin" });
+  assert_eq!(command.fields, vec!["name", "_id"]);
+  assert_eq!(command.output.as_deref(), Some("cats.json"));
+}
+
+#[test]
+fn parse_invalid_export_command() {
+  assert_eq!(Command::parse(&args(&[])), None);
+  assert_eq!(Command::parse(&args(&["--format", "json"])), None);
+  assert_eq!(Command::parse(&args(&["labels", "--format", "csv"])), None);
+  assert_eq!(Command::parse(&args(&["labels", "--filter", "[]"])), None);
+  assert_eq!(Command::parse(&args(&["labels", "--output"])), None);
+  assert_eq!(Command::parse(&args(&["labels", "--limit", "10"])), None);
+}
+
+#[test]
+fn export_writes_the_matching_documents() {
+  use_app(async move {
+    for (name, source) in [
+      ("Binance", "arkham"),
+      ("Jump", "arkham"),
+      ("Binance 14", "etherscan"),
+    ] {
+      let label = AddressLabel::new(
+        "0x00000000000000000000000000000000000000a1".to_owned(),
+        name.to_owned(),
+        source.to_owned(),
+      );
+      AddressLabel::create(label).await.unwrap();
+    }
+
+    let command = Command::parse(&args(&[
+      "labels",
+      "--filter",
+      r#"{"source": "arkham"}"#,
+      "--fields",
+      "name,source",
+    ]))
+    .unwrap();
+    let mut output = Vec::new();
+    let exported = command.write(&mut output).await.unwrap();
+    assert_eq!(exported, 2);
+    let lines = String::from_utf8(output)
+      .unwrap()
+      .lines()
+      .map(|line| serde_json::from_str::<Value>(line).unwrap())
+      .collect::<Vec<Value>>();
+    assert_eq!(
+      lines,
+      vec![
+        json!({ "name": "Binance", "source": "arkham" }),
+        json!({ "name": "Jump", "source": "arkham" }),
+      ],
+      "Only the selected fields should be exported, oldest first"
+    );
+
+    let command = Command::parse(&args(&["labels", "--format", "json"])).unwrap();
+    let mut output = Vec::new();
+    assert_eq!(command.write(&mut output).await.unwrap(), 3);
+    let labels = serde_json::from_slice::<Vec<Value>>(&output).unwrap();
+    assert_eq!(labels.len(), 3);
+    assert!(
+      labels[0]["_id"]["$oid"].is_string(),
+      "Ids should be exported as extended JSON"
+    );
+    assert_eq!(
+      AddressLabel::count(doc! {}).await.unwrap(),
+      3,
+      "Exports should not change the collection"
+    );
+  });
+}
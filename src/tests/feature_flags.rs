@@ -0,0 +1,62 @@
+use wither::bson::oid::ObjectId;
+
+use crate::services::feature_flags::{rollout_bucket, FlagState};
+
+fn flag(enabled: bool, rollout_percent: u8) -> FlagState {
+  FlagState {
+    enabled,
+    rollout_percent,
+    users: vec![],
+  }
+}
+
+#[test]
+fn disabled_flags_are_off_for_everyone() {
+  let user = ObjectId::new();
+  let mut state = flag(false, 100);
+  state.users.push(user);
+
+  assert!(!state.is_enabled_for("risk_score", Some(&user)));
+  assert!(!state.is_enabled_for("risk_score", None));
+}
+
+#[test]
+fn fully_rolled_out_flags_are_on_for_everyone() {
+  let state = flag(true, 100);
+
+  assert!(state.is_enabled_for("risk_score", Some(&ObjectId::new())));
+  assert!(state.is_enabled_for("risk_score", None));
+}
+
+#[test]
+fn partial_rollouts_include_listed_users_only_when_authenticated() {
+  let user = ObjectId::new();
+  let mut state = flag(true, 0);
+  state.users.push(user);
+
+  assert!(state.is_enabled_for("risk_score", Some(&user)));
+  assert!(!state.is_enabled_for("risk_score", Some(&ObjectId::new())));
+  assert!(!state.is_enabled_for("risk_score", None));
+}
+
+#[test]
+fn partial_rollouts_follow_the_user_bucket() {
+  let users: Vec<ObjectId> = (0..1000).map(|_| ObjectId::new()).collect();
+  let state = flag(true, 30);
+
+  let enabled = users
+    .iter()
+    .filter(|user| state.is_enabled_for("address_graph", Some(user)))
+    .count();
+  assert!((200..400).contains(&enabled), "{} users enabled", enabled);
+
+  for user in &users {
+    let bucket = rollout_bucket("address_graph", user);
+    assert!(bucket < 100);
+    assert_eq!(bucket, rollout_bucket("address_graph", user));
+    assert_eq!(
+      state.is_enabled_for("address_graph", Some(user)),
+      bucket < 30
+    );
+  }
+}
@@ -0,0 +1,39 @@
+use bson::{doc, oid::ObjectId};
+use serde_json::Value as Json;
+
+use crate::models::cat::{Cat, PublicCat, PUBLIC_CAT_FIELDS};
+use crate::utils::fields::Fields;
+
+#[test]
+fn fields_parse_ignores_blank_and_repeated_fields() {
+  let fields = Fields::parse("name, ,name,created_at", PUBLIC_CAT_FIELDS).unwrap();
+  assert_eq!(
+    fields.projection(),
+    doc! { "_id": 1, "created_at": 1, "name": 1 }
+  );
+}
+
+#[test]
+fn fields_parse_rejects_unknown_fields() {
+  assert!(Fields::parse("name,password", PUBLIC_CAT_FIELDS).is_err());
+  assert!(Fields::parse(",", PUBLIC_CAT_FIELDS).is_err());
+}
+
+#[test]
+fn fields_select_matches_public_model() {
+  let mut cat = Cat::new(ObjectId::new(), "Tigrin".to_owned());
+  cat.id = Some(ObjectId::new());
+  let document = bson::to_document(&cat).unwrap();
+  let public = serde_json::to_value(PublicCat::from(cat)).unwrap();
+
+  let fields = Fields::parse("user,name,organization,created_at", PUBLIC_CAT_FIELDS).unwrap();
+  let selected = Json::Object(fields.select(document));
+
+  assert_eq!(selected["id"], public["id"]);
+  assert_eq!(selected["user"], public["user"]);
+  assert_eq!(selected["name"], public["name"]);
+  assert_eq!(selected["organization"], Json::Null);
+  assert_eq!(selected["created_at"], public["created_at"]);
+  assert!(selected.get("tags").is_none());
+  assert!(selected.get("updated_at").is_none());
+}
@@ -0,0 +1,34 @@
+use std::time::Duration;
+
+use crate::errors::Error;
+use crate::services::arkham::Governor;
+
+#[test]
+fn governor_allows_bursts_then_queues_and_sheds() {
+  let governor = Governor::new(10.0, 2, Duration::from_millis(150));
+
+  // The burst goes out right away.
+  assert_eq!(governor.reserve().unwrap(), Duration::ZERO);
+  assert_eq!(governor.reserve().unwrap(), Duration::ZERO);
+
+  // The next request waits for a token, 100ms at 10 requests per second.
+  let wait = governor.reserve().unwrap();
+  assert!(wait > Duration::from_millis(90) && wait <= Duration::from_millis(100));
+
+  // Waiting 200ms is over the limit, so the request is shed.
+  match governor.reserve() {
+    Err(Error::TooManyRequests(_)) => {}
+    other => panic!("Expected the request to be shed, got {:?}", other),
+  }
+}
+
+#[test]
+fn governor_refills_over_time() {
+  let governor = Governor::new(10.0, 1, Duration::ZERO);
+
+  assert_eq!(governor.reserve().unwrap(), Duration::ZERO);
+  assert!(governor.reserve().is_err(), "Bucket should be empty");
+
+  std::thread::sleep(Duration::from_millis(110));
+  assert_eq!(governor.reserve().unwrap(), Duration::ZERO);
+}
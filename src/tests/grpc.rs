@@ -0,0 +1,160 @@
+use futures::TryStreamExt;
+use tonic::metadata::MetadataValue;
+use tonic::{Code, Request};
+
+use crate::grpc::proto;
+use crate::grpc::proto::labels_client::LabelsClient;
+use crate::grpc::proto::watchlists_client::WatchlistsClient;
+use crate::models::address_label::AddressLabel;
+use crate::tests::setup::use_app;
+use crate::tests::utils::{create_readonly_user, create_user, create_user_token};
+use crate::utils::models::ModelExt;
+
+const GRPC_URL: &str = "http://127.0.0.1:8091";
+const ADDRESS: &str = "0x00000000000000000000000000000000000000a1";
+
+fn with_token<T>(message: T, token: &str) -> Request<T> {
+  let mut request = Request::new(message);
+  let authorization = MetadataValue::try_from(format!("Bearer {}", token)).unwrap();
+  request
+    .metadata_mut()
+    .insert("authorization", authorization);
+  request
+}
+
+#[test]
+fn grpc_watchlists_require_authentication() {
+  use_app(async move {
+    let mut client = WatchlistsClient::connect(GRPC_URL).await.unwrap();
+    let status = client
+      .list_watchlists(proto::ListWatchlistsRequest {})
+      .await
+      .unwrap_err();
+
+    assert_eq!(status.code(), Code::Unauthenticated);
+    assert_eq!(
+      status.metadata().get("x-error-code").unwrap(),
+      "invalid_token"
+    );
+  });
+}
+
+#[test]
+fn grpc_watchlist_addresses() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user.clone()).await.unwrap();
+    let mut client = WatchlistsClient::connect(GRPC_URL).await.unwrap();
+
+    let request = proto::CreateWatchlistRequest {
+      name: " Exchanges ".to_owned(),
+      organization: None,
+    };
+    let watchlist = client
+      .create_watchlist(with_token(request, &token))
+      .await
+      .unwrap()
+      .into_inner();
+    assert_eq!(watchlist.name, "Exchanges");
+    assert_eq!(watchlist.user, user.id.unwrap().to_hex());
+
+    let request = proto::AddWatchedAddressRequest {
+      watchlist: watchlist.id.clone(),
+      address: ADDRESS.to_uppercase().replace("0X", "0x"),
+      chain: None,
+      nickname: Some("Hot wallet".to_owned()),
+    };
+    let watched = client
+      .add_watched_address(with_token(request.clone(), &token))
+      .await
+      .unwrap()
+      .into_inner();
+    assert_eq!(watched.address, ADDRESS);
+    assert_eq!(watched.chain, "ethereum");
+
+    // Same errors as the REST API.
+    let status = client
+      .add_watched_address(with_token(request, &token))
+      .await
+      .unwrap_err();
+    assert_eq!(status.code(), Code::AlreadyExists);
+
+    let request = proto::ListWatchedAddressesRequest {
+      watchlist: watchlist.id.clone(),
+    };
+    let addresses = client
+      .list_watched_addresses(with_token(request, &token))
+      .await
+      .unwrap()
+      .into_inner()
+      .try_collect::<Vec<_>>()
+      .await
+      .unwrap();
+    assert_eq!(addresses, vec![watched]);
+
+    let watchlists = client
+      .list_watchlists(with_token(proto::ListWatchlistsRequest {}, &token))
+      .await
+      .unwrap()
+      .into_inner()
+      .watchlists;
+    assert_eq!(watchlists.len(), 1);
+    assert_eq!(watchlists[0].id, watchlist.id);
+  });
+}
+
+#[test]
+fn grpc_readonly_users_cant_create_watchlists() {
+  use_app(async move {
+    let user = create_readonly_user("reader@test.com").await.unwrap();
+    let token = create_user_token(user).await.unwrap();
+    let mut client = WatchlistsClient::connect(GRPC_URL).await.unwrap();
+
+    let request = proto::CreateWatchlistRequest {
+      name: "Exchanges".to_owned(),
+      organization: None,
+    };
+    let status = client
+      .create_watchlist(with_token(request, &token))
+      .await
+      .unwrap_err();
+
+    assert_eq!(status.code(), Code::PermissionDenied);
+  });
+}
+
+#[test]
+fn grpc_list_labels() {
+  use_app(async move {
+    let label = AddressLabel::new(
+      ADDRESS.to_owned(),
+      "Binance Hot Wallet".to_owned(),
+      "arkham".to_owned(),
+    );
+    AddressLabel::create(label).await.unwrap();
+
+    // Public labels don't need credentials.
+    let mut client = LabelsClient::connect(GRPC_URL).await.unwrap();
+    let labels = client
+      .list_labels(proto::ListLabelsRequest {
+        address: ADDRESS.to_owned(),
+      })
+      .await
+      .unwrap()
+      .into_inner()
+      .labels;
+
+    assert_eq!(labels.len(), 1);
+    assert_eq!(labels[0].name, "Binance Hot Wallet");
+    assert_eq!(labels[0].eth_address, ADDRESS);
+    assert_eq!(labels[0].organization, None);
+
+    let status = client
+      .list_labels(proto::ListLabelsRequest {
+        address: "0x1234".to_owned(),
+      })
+      .await
+      .unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+  });
+}
@@ -0,0 +1,55 @@
+use serde_json::json;
+
+use crate::routes::arkham::parse_arkham_response;
+use crate::services::history::{diff, FieldChange};
+use crate::tests::mock_arkham::address_payload;
+
+const ADDRESS: &str = "0x00000000000000000000000000000000000000a1";
+
+#[test]
+fn diff_reports_changed_fields() {
+  let before = parse_arkham_response(address_payload(ADDRESS)).unwrap();
+  let mut payload = address_payload(ADDRESS);
+  payload["ethereum"]["balance"] = json!(12.5);
+  payload["ethereum"]["arkhamEntity"]["name"] = json!("Degen Fund");
+  let after = parse_arkham_response(payload).unwrap();
+
+  let changes = diff(Some(&before), &after);
+  let expected = vec![
+    FieldChange {
+      path: "ethereum.arkhamEntity.name".to_owned(),
+      before: json!("Degen"),
+      after: json!("Degen Fund"),
+    },
+    FieldChange {
+      path: "ethereum.balance".to_owned(),
+      before: json!(null),
+      after: json!(12.5),
+    },
+  ];
+  assert_eq!(changes, expected);
+}
+
+#[test]
+fn diff_of_identical_data_is_empty() {
+  let data = parse_arkham_response(address_payload(ADDRESS)).unwrap();
+
+  assert!(diff(Some(&data), &data).is_empty());
+}
+
+#[test]
+fn diff_without_previous_data_reports_every_field() {
+  let data = parse_arkham_response(address_payload(ADDRESS)).unwrap();
+
+  let changes = diff(None, &data);
+  let change = changes
+    .iter()
+    .find(|change| change.path == "ethereum.arkhamLabel.name")
+    .expect("Fields should be reported as added");
+  assert_eq!(change.before, json!(null));
+  assert_eq!(change.after, json!("Degen Wallet"));
+  assert!(
+    changes.iter().all(|change| !change.after.is_null()),
+    "Missing fields should not be reported"
+  );
+}
@@ -0,0 +1,79 @@
+use axum::body::Bytes;
+use axum::http::{HeaderMap, StatusCode};
+
+use crate::settings::Idempotency;
+use crate::utils::idempotency::{fingerprint, Claim, IdempotencyStore, StoredResponse};
+
+fn store() -> IdempotencyStore {
+  IdempotencyStore::new(&Idempotency { ttl_secs: 60 })
+}
+
+fn response() -> StoredResponse {
+  StoredResponse {
+    status: StatusCode::CREATED,
+    headers: HeaderMap::new(),
+    body: Bytes::from_static(b"{\"name\":\"Tigrin\"}"),
+  }
+}
+
+#[test]
+fn idempotency_store_replays_completed_requests() {
+  let store = store();
+  let request = fingerprint("POST", "/v1/cats", b"{\"name\":\"Tigrin\"}");
+
+  assert!(matches!(
+    store.claim("nico", "key", &request),
+    Claim::Acquired
+  ));
+  assert!(matches!(
+    store.claim("nico", "key", &request),
+    Claim::InFlight
+  ));
+
+  store.complete("nico", "key", &request, response());
+  match store.claim("nico", "key", &request) {
+    Claim::Replay(response) => {
+      assert_eq!(response.status, StatusCode::CREATED);
+      assert_eq!(response.body, "{\"name\":\"Tigrin\"}");
+    }
+    claim => panic!("Expected a replay, got {:?}", claim),
+  }
+
+  // Keys are scoped by user.
+  assert!(matches!(
+    store.claim("nahuel", "key", &request),
+    Claim::Acquired
+  ));
+}
+
+#[test]
+fn idempotency_store_rejects_keys_reused_for_other_requests() {
+  let store = store();
+  let request = fingerprint("POST", "/v1/cats", b"{\"name\":\"Tigrin\"}");
+  let other = fingerprint("POST", "/v1/cats", b"{\"name\":\"Michi\"}");
+
+  assert!(matches!(
+    store.claim("nico", "key", &request),
+    Claim::Acquired
+  ));
+  assert!(matches!(
+    store.claim("nico", "key", &other),
+    Claim::Mismatch
+  ));
+}
+
+#[test]
+fn idempotency_store_releases_keys_of_failed_requests() {
+  let store = store();
+  let request = fingerprint("POST", "/v1/cats", b"{}");
+
+  assert!(matches!(
+    store.claim("nico", "key", &request),
+    Claim::Acquired
+  ));
+  store.release("nico", "key");
+  assert!(matches!(
+    store.claim("nico", "key", &request),
+    Claim::Acquired
+  ));
+}
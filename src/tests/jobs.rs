@@ -0,0 +1,113 @@
+use bson::doc;
+use bson::oid::ObjectId;
+use serde_json::json;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::models::address_label::AddressLabel;
+use crate::models::job::{Job, JobKind, JobStatus};
+use crate::services::jobs::{self, GraphExpansion, LabelImport};
+use crate::settings::SETTINGS;
+use crate::state::AppState;
+use crate::tests::setup::use_app;
+use crate::utils::date;
+use crate::utils::models::ModelExt;
+
+const ADDRESS: &str = "0x00000000000000000000000000000000000000a1";
+
+#[test]
+fn run_next_runs_queued_label_import() {
+  use_app(async move {
+    let state = AppState::new(Arc::new(SETTINGS.clone()));
+    let lines = [
+      json!({ "eth_address": ADDRESS, "name": "Binance", "source": "arkham" }).to_string(),
+      String::from("not json"),
+    ];
+    let payload = LabelImport {
+      body: lines.join("\n"),
+    };
+    let job = jobs::enqueue(ObjectId::new(), JobKind::LabelImport, &payload)
+      .await
+      .unwrap();
+    assert_eq!(job.status, JobStatus::Queued);
+
+    assert!(jobs::run_next(&state).await.unwrap());
+    assert!(!jobs::run_next(&state).await.unwrap(), "No job left");
+
+    let job = Job::find_by_id(&job.id.unwrap()).await.unwrap().unwrap();
+    assert_eq!(job.status, JobStatus::Succeeded);
+    assert_eq!(job.attempts, 1);
+    assert!(job.finished_at.is_some());
+    let result = job.result.unwrap();
+    assert_eq!(result.get_i64("inserted").unwrap(), 1);
+    assert_eq!(result.get_i64("failed").unwrap(), 1);
+
+    let count = AddressLabel::count(doc! {}).await.unwrap();
+    assert_eq!(count, 1);
+  });
+}
+
+#[test]
+fn run_next_fails_job_with_invalid_payload() {
+  use_app(async move {
+    let state = AppState::new(Arc::new(SETTINGS.clone()));
+    let payload = GraphExpansion {
+      address: ADDRESS.to_owned(),
+      depth: 1,
+    };
+    let job = jobs::enqueue(ObjectId::new(), JobKind::LabelImport, &payload)
+      .await
+      .unwrap();
+
+    jobs::run_next(&state).await.unwrap();
+
+    // Not retried, the payload would be invalid again.
+    let job = Job::find_by_id(&job.id.unwrap()).await.unwrap().unwrap();
+    assert_eq!(job.status, JobStatus::Failed);
+    assert_eq!(job.attempts, 1);
+    assert!(job.last_error.is_some());
+    assert!(job.result.is_none());
+  });
+}
+
+#[test]
+fn run_next_claims_job_with_expired_lease() {
+  use_app(async move {
+    let state = AppState::new(Arc::new(SETTINGS.clone()));
+    let payload = bson::to_document(&LabelImport {
+      body: String::new(),
+    })
+    .unwrap();
+
+    // Left running by a server which stopped before finishing it.
+    let mut job = Job::new(ObjectId::new(), JobKind::LabelImport, payload);
+    job.status = JobStatus::Running;
+    job.attempts = 1;
+    job.run_at = date::before(Duration::from_secs(1));
+    let job = Job::create(job).await.unwrap();
+
+    assert!(jobs::run_next(&state).await.unwrap());
+
+    let job = Job::find_by_id(&job.id.unwrap()).await.unwrap().unwrap();
+    assert_eq!(job.status, JobStatus::Succeeded);
+    assert_eq!(job.attempts, 2);
+  });
+}
+
+#[test]
+fn run_next_skips_job_with_active_lease() {
+  use_app(async move {
+    let state = AppState::new(Arc::new(SETTINGS.clone()));
+    let payload = bson::to_document(&LabelImport {
+      body: String::new(),
+    })
+    .unwrap();
+
+    let mut job = Job::new(ObjectId::new(), JobKind::LabelImport, payload);
+    job.status = JobStatus::Running;
+    job.run_at = date::after(Duration::from_secs(60));
+    Job::create(job).await.unwrap();
+
+    assert!(!jobs::run_next(&state).await.unwrap());
+  });
+}
@@ -0,0 +1,148 @@
+use bson::doc;
+use std::env;
+
+use crate::errors::Error;
+use crate::models::address_label::AddressLabel;
+use crate::models::label_change::LabelChange;
+use crate::models::label_dataset::LabelDataset;
+use crate::services::label_dataset::{self, source_url, to_ndjson};
+use crate::tests::setup::use_app;
+use crate::utils::models::ModelExt;
+
+const NDJSON_DATASET: &str = "src/tests/fixtures/labels.ndjson";
+const JSON_DATASET: &str = "src/tests/fixtures/labels.json";
+
+/// Names of the labels visible to anonymous users.
+async fn visible_label_names() -> Vec<String> {
+  let query = doc! {
+    "organization": null,
+    "dataset": label_dataset::visible_datasets().await.unwrap(),
+  };
+  let mut names = AddressLabel::find(query, None)
+    .await
+    .unwrap()
+    .into_iter()
+    .map(|label| label.name)
+    .collect::<Vec<String>>();
+  names.sort();
+  names
+}
+
+#[test]
+fn source_url_of_remote_sources() {
+  assert_eq!(
+    source_url("https://example.com/labels.ndjson").as_deref(),
+    Some("https://example.com/labels.ndjson")
+  );
+  assert_eq!(
+    source_url("s3://datasets/labels/latest.ndjson").as_deref(),
+    Some("https://datasets.s3.amazonaws.com/labels/latest.ndjson")
+  );
+  assert_eq!(source_url("/var/lib/labels.ndjson"), None);
+  assert_eq!(source_url("file:///var/lib/labels.ndjson"), None);
+}
+
+#[test]
+fn to_ndjson_converts_json_arrays() {
+  let ndjson = b"{\"name\":\"a\"}\n{\"name\":\"b\"}\n".to_vec();
+  assert_eq!(to_ndjson(ndjson.clone()).unwrap(), ndjson);
+
+  let array = b" [{\"name\":\"a\"}, {\"name\":\"b\"}]".to_vec();
+  assert_eq!(
+    to_ndjson(array).unwrap(),
+    b"{\"name\":\"a\"}\n{\"name\":\"b\"}".to_vec()
+  );
+
+  assert!(matches!(
+    to_ndjson(b"[{\"name\":".to_vec()),
+    Err(Error::InvalidPayload(_))
+  ));
+}
+
+#[test]
+fn reload_swaps_the_dataset_labels() {
+  use_app(async move {
+    let created = AddressLabel::new(
+      "0x28c6c06298d514db089934071355e5743bf21d60".to_owned(),
+      "Exchange".to_owned(),
+      "api".to_owned(),
+    );
+    AddressLabel::create(created).await.unwrap();
+
+    let first = label_dataset::reload(NDJSON_DATASET).await.unwrap();
+    assert_eq!(first.summary.inserted, 2);
+    assert_eq!(
+      visible_label_names().await,
+      vec!["Binance 14", "Binance 15", "Exchange"]
+    );
+
+    let second = label_dataset::reload(&format!("file://{}", JSON_DATASET))
+      .await
+      .unwrap();
+    assert_eq!(second.summary.inserted, 1);
+    assert_ne!(second.dataset, first.dataset);
+
+    // The labels of the first version are gone, the ones created through the
+    // API are kept.
+    assert_eq!(
+      visible_label_names().await,
+      vec!["Binance Hot Wallet", "Exchange"]
+    );
+    assert_eq!(AddressLabel::count(doc! {}).await.unwrap(), 2);
+    let active = label_dataset::active().await.unwrap().unwrap();
+    assert_eq!(active.id.unwrap().to_hex(), second.dataset);
+    assert_eq!(LabelDataset::count(doc! {}).await.unwrap(), 1);
+  });
+}
+
+#[test]
+fn reload_keeps_the_labels_of_a_failed_reload() {
+  use_app(async move {
+    label_dataset::reload(NDJSON_DATASET).await.unwrap();
+
+    let empty = env::temp_dir().join("rustapi-empty-labels.ndjson");
+    std::fs::write(&empty, "\n{\"name\":\"no address\"}\n").unwrap();
+    let err = label_dataset::reload(empty.to_str().unwrap())
+      .await
+      .unwrap_err();
+    assert!(matches!(err, Error::InvalidPayload(_)));
+
+    let err = label_dataset::reload("src/tests/fixtures/missing.ndjson")
+      .await
+      .unwrap_err();
+    assert!(matches!(err, Error::General(_)));
+
+    assert_eq!(
+      visible_label_names().await,
+      vec!["Binance 14", "Binance 15"]
+    );
+    assert_eq!(LabelDataset::count(doc! {}).await.unwrap(), 1);
+  });
+}
+
+#[test]
+fn reload_records_the_label_changes() {
+  use_app(async move {
+    label_dataset::reload(NDJSON_DATASET).await.unwrap();
+    label_dataset::reload(JSON_DATASET).await.unwrap();
+
+    let mut changes = LabelChange::find(doc! {}, None)
+      .await
+      .unwrap()
+      .into_iter()
+      .map(|change| (change.old_name, change.new_name))
+      .collect::<Vec<(Option<String>, Option<String>)>>();
+    changes.sort();
+
+    let name = |name: &str| Some(name.to_owned());
+    assert_eq!(
+      changes,
+      vec![
+        (None, name("Binance 14")),
+        (None, name("Binance 15")),
+        (name("Binance 14"), name("Binance Hot Wallet")),
+        (name("Binance 15"), None),
+      ]
+    );
+  });
+}
@@ -0,0 +1,80 @@
+use wither::bson::oid::ObjectId;
+
+use crate::models::address_label::AddressLabel;
+use crate::models::user_address_note::UserAddressNote;
+use crate::services::label_merge::{merge, Candidates, SourcedValue};
+
+const ADDRESS: &str = "0x00000000000000000000000000000000000000a1";
+
+fn candidates() -> Candidates {
+  let mut candidates = Candidates::default();
+  candidates.add_labels(vec![
+    AddressLabel::new(
+      ADDRESS.to_owned(),
+      "Binance 14".to_owned(),
+      "etherscan".to_owned(),
+    ),
+    AddressLabel::new(ADDRESS.to_owned(), "Binance".to_owned(), "dune".to_owned()),
+  ]);
+
+  let mut note = UserAddressNote::new(ObjectId::new(), ADDRESS.to_owned());
+  note.name = Some("My exchange".to_owned());
+  note.note = Some("Deposit address".to_owned());
+  candidates.add_note(&note);
+
+  candidates
+}
+
+fn priority(sources: &[&str]) -> Vec<String> {
+  sources.iter().map(|source| source.to_string()).collect()
+}
+
+#[test]
+fn merge_takes_each_field_from_the_first_source() {
+  let merged = merge(
+    &priority(&["user", "labels", "arkham"]),
+    ADDRESS.to_owned(),
+    candidates(),
+    vec![],
+  );
+
+  assert_eq!(merged.name.unwrap().value, "My exchange");
+  assert_eq!(
+    merged.note,
+    Some(SourcedValue {
+      value: "Deposit address".to_owned(),
+      source: "user".to_owned(),
+      label_source: None,
+    })
+  );
+  assert!(merged.entity.is_none());
+
+  let names = merged
+    .names
+    .iter()
+    .map(|name| (name.value.as_str(), name.label_source.as_deref()))
+    .collect::<Vec<_>>();
+  assert_eq!(
+    names,
+    vec![
+      ("My exchange", None),
+      ("Binance 14", Some("etherscan")),
+      ("Binance", Some("dune"))
+    ]
+  );
+}
+
+#[test]
+fn merge_ranks_unlisted_sources_last() {
+  let merged = merge(
+    &priority(&["labels"]),
+    ADDRESS.to_owned(),
+    candidates(),
+    vec![],
+  );
+
+  let name = merged.name.unwrap();
+  assert_eq!(name.value, "Binance 14");
+  assert_eq!(name.source, "labels");
+  assert_eq!(merged.names.last().unwrap().source, "user");
+}
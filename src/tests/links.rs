@@ -0,0 +1,34 @@
+use axum::http::Method;
+
+use crate::utils::links::{Link, LinkBuilder};
+
+fn link(href: &str, method: &str) -> Link {
+  Link {
+    href: href.to_owned(),
+    method: method.to_owned(),
+  }
+}
+
+#[test]
+fn link_builder_prefixes_the_paths() {
+  let links = LinkBuilder::new("/cats/1")
+    .link("self", Method::GET)
+    .sub("tags", Method::POST, "/tags")
+    .related("owner", "/users/2")
+    .build();
+
+  assert_eq!(links.len(), 3);
+  assert_eq!(links["self"], link("/v1/cats/1", "GET"));
+  assert_eq!(links["tags"], link("/v1/cats/1/tags", "POST"));
+  assert_eq!(links["owner"], link("/v1/users/2", "GET"));
+}
+
+#[test]
+fn link_builder_keeps_the_last_link_of_a_relation() {
+  let links = LinkBuilder::new("/cats/1")
+    .link("update", Method::PUT)
+    .link("update", Method::PATCH)
+    .build();
+
+  assert_eq!(links["update"], link("/v1/cats/1", "PATCH"));
+}
@@ -0,0 +1,65 @@
+use bson::oid::ObjectId;
+use std::collections::HashSet;
+
+use crate::models::alert_event::AlertEvent;
+use crate::models::alert_rule::AlertCondition;
+use crate::models::watched_address::WatchedAddress;
+use crate::services::live::{LiveEvent, Subscription, SubscriptionFilter};
+
+const ADDRESS: &str = "0x00000000000000000000000000000000000000a1";
+
+fn alert(user: ObjectId, chain: &str) -> LiveEvent {
+  LiveEvent::Alert(AlertEvent::new(
+    user,
+    ObjectId::new(),
+    AlertCondition::FlaggedAsContract,
+    ADDRESS.to_owned(),
+    chain.to_owned(),
+    "Address flagged as contract".to_owned(),
+  ))
+}
+
+#[test]
+fn subscription_filters_by_user_and_chain() {
+  let user = ObjectId::new();
+  let chains = HashSet::from(["ethereum".to_owned()]);
+  let mut subscription = Subscription::with_watched(user, Some(chains), None, HashSet::new());
+
+  assert!(subscription.accepts(&alert(user, "ethereum")));
+  assert!(!subscription.accepts(&alert(user, "bsc")));
+  assert!(
+    !subscription.accepts(&alert(ObjectId::new(), "ethereum")),
+    "Events of other users are never sent"
+  );
+}
+
+#[test]
+fn subscription_tracks_addresses_added_to_watchlists() {
+  let user = ObjectId::new();
+  let watchlist = ObjectId::new();
+  let mut subscription =
+    Subscription::with_watched(user, None, Some(HashSet::from([watchlist])), HashSet::new());
+  assert!(!subscription.accepts(&alert(user, "ethereum")));
+
+  let watched = WatchedAddress::new(
+    watchlist,
+    user,
+    ADDRESS.to_owned(),
+    "ethereum".to_owned(),
+    None,
+  );
+  assert!(subscription.accepts(&LiveEvent::AddressWatched(watched)));
+  assert!(subscription.accepts(&alert(user, "ethereum")));
+  assert!(!subscription.accepts(&alert(user, "bsc")));
+}
+
+#[test]
+fn subscription_filter_reads_comma_separated_lists() {
+  let filter = SubscriptionFilter::from_query(None, Some("ethereum, bsc,"));
+
+  assert_eq!(filter.watchlists, None);
+  assert_eq!(
+    filter.chains,
+    Some(vec!["ethereum".to_owned(), "bsc".to_owned()])
+  );
+}
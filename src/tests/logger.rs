@@ -0,0 +1,99 @@
+use axum::http::Request;
+use serde_json::{json, Value as Json};
+use std::io;
+use std::sync::{Arc, Mutex};
+use tracing::{info, info_span};
+use tracing_subscriber::fmt::format::JsonFields;
+use tracing_subscriber::layer::SubscriberExt;
+use wither::bson::oid::ObjectId;
+
+use crate::logger::JsonFormat;
+use crate::utils::request_id::REQUEST_ID_HEADER;
+use crate::utils::telemetry;
+
+#[derive(Clone, Default)]
+struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl io::Write for Buffer {
+  fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+    self.0.lock().unwrap().extend_from_slice(buf);
+    Ok(buf.len())
+  }
+
+  fn flush(&mut self) -> io::Result<()> {
+    Ok(())
+  }
+}
+
+fn log_lines(log: impl FnOnce()) -> Vec<Json> {
+  let buffer = Buffer::default();
+  let writer = buffer.clone();
+  let layer = tracing_subscriber::fmt::layer()
+    .fmt_fields(JsonFields::new())
+    .event_format(JsonFormat)
+    .with_writer(move || writer.clone());
+  let subscriber = tracing_subscriber::registry().with(layer);
+
+  tracing::subscriber::with_default(subscriber, log);
+
+  let output = buffer.0.lock().unwrap().clone();
+  String::from_utf8(output)
+    .unwrap()
+    .lines()
+    .map(|line| serde_json::from_str(line).unwrap())
+    .collect()
+}
+
+#[test]
+fn json_format_writes_one_object_per_event() {
+  let lines = log_lines(|| {
+    info!(status = 200, latency_ms = 12, "finished processing request");
+  });
+
+  assert_eq!(lines.len(), 1);
+  let line = &lines[0];
+  assert!(line["timestamp"].is_string());
+  assert_eq!(line["level"], "INFO");
+  assert_eq!(line["message"], "finished processing request");
+  assert_eq!(line["status"], 200);
+  assert_eq!(line["latency_ms"], 12);
+}
+
+#[test]
+fn json_format_flattens_span_fields() {
+  let lines = log_lines(|| {
+    let request = info_span!("request", request_id = "7f1c", method = "GET");
+    let _request = request.enter();
+    let query = info_span!("query", method = "find", user.id = "6523f0c3");
+    let _query = query.enter();
+    info!("Returning cats");
+  });
+
+  let line = &lines[0];
+  assert_eq!(line["request_id"], "7f1c");
+  assert_eq!(line["user_id"], "6523f0c3");
+  // Fields of the inner spans take precedence.
+  assert_eq!(line["method"], "find");
+  assert_eq!(line.get("span"), None);
+  assert_eq!(line["message"], json!("Returning cats"));
+}
+
+#[test]
+fn request_span_carries_the_request_context() {
+  let lines = log_lines(|| {
+    let req = Request::builder()
+      .uri("/v1/cats")
+      .header(REQUEST_ID_HEADER, "7f1c")
+      .body(())
+      .unwrap();
+    let span = telemetry::make_span(&req);
+    let _span = span.enter();
+    telemetry::record_user(ObjectId::parse_str("6523f0c3e8a0b6a2d1e4c5f7").unwrap());
+    info!("Returning cats");
+  });
+
+  let line = &lines[0];
+  assert_eq!(line["request_id"], "7f1c");
+  assert_eq!(line["method"], "GET");
+  assert_eq!(line["user_id"], "6523f0c3e8a0b6a2d1e4c5f7");
+}
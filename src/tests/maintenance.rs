@@ -0,0 +1,35 @@
+use crate::settings;
+use crate::utils::maintenance::{is_allowed, Maintenance, MaintenanceStatus};
+
+#[test]
+fn maintenance_allows_health_and_admin_routes() {
+  assert!(is_allowed("/health"));
+  assert!(is_allowed("/ready"));
+  assert!(is_allowed("/v1/admin/maintenance"));
+  assert!(is_allowed("/v1/admin/users/:id/role"));
+
+  assert!(!is_allowed("/v1/cats"));
+  assert!(!is_allowed("/v1/administrators"));
+}
+
+#[test]
+fn maintenance_starts_as_configured() {
+  let maintenance = Maintenance::new(&settings::Maintenance {
+    enabled: true,
+    retry_after_secs: 60,
+  });
+  assert_eq!(
+    maintenance.status(),
+    MaintenanceStatus {
+      enabled: true,
+      retry_after_secs: 60
+    }
+  );
+
+  let status = MaintenanceStatus {
+    enabled: false,
+    retry_after_secs: 30,
+  };
+  maintenance.set(status);
+  assert_eq!(maintenance.status(), status);
+}
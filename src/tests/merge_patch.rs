@@ -0,0 +1,70 @@
+use serde_json::json;
+
+use crate::utils::merge_patch::merge;
+
+// Examples from the appendix of RFC 7396.
+#[test]
+fn merge_rfc_examples() {
+  let examples = [
+    (
+      json!({ "a": "b" }),
+      json!({ "a": "c" }),
+      json!({ "a": "c" }),
+    ),
+    (
+      json!({ "a": "b" }),
+      json!({ "b": "c" }),
+      json!({ "a": "b", "b": "c" }),
+    ),
+    (json!({ "a": "b" }), json!({ "a": null }), json!({})),
+    (
+      json!({ "a": "b", "b": "c" }),
+      json!({ "a": null }),
+      json!({ "b": "c" }),
+    ),
+    (
+      json!({ "a": ["b"] }),
+      json!({ "a": "c" }),
+      json!({ "a": "c" }),
+    ),
+    (
+      json!({ "a": "c" }),
+      json!({ "a": ["b"] }),
+      json!({ "a": ["b"] }),
+    ),
+    (
+      json!({ "a": { "b": "c" } }),
+      json!({ "a": { "b": "d", "c": null } }),
+      json!({ "a": { "b": "d" } }),
+    ),
+    (
+      json!({ "a": [{ "b": "c" }] }),
+      json!({ "a": [1] }),
+      json!({ "a": [1] }),
+    ),
+    (json!(["a", "b"]), json!(["c", "d"]), json!(["c", "d"])),
+    (json!({ "a": "b" }), json!(["c"]), json!(["c"])),
+    (json!({ "a": "foo" }), json!(null), json!(null)),
+    (json!({ "a": "foo" }), json!("bar"), json!("bar")),
+    (
+      json!({ "e": null }),
+      json!({ "a": 1 }),
+      json!({ "e": null, "a": 1 }),
+    ),
+    (
+      json!([1, 2]),
+      json!({ "a": "b", "c": null }),
+      json!({ "a": "b" }),
+    ),
+    (
+      json!({}),
+      json!({ "a": { "bb": { "ccc": null } } }),
+      json!({ "a": { "bb": {} } }),
+    ),
+  ];
+
+  for (mut target, patch, expected) in examples {
+    merge(&mut target, patch.clone());
+    assert_eq!(target, expected, "Patch: {}", patch);
+  }
+}
@@ -0,0 +1,48 @@
+use bson::doc;
+
+use crate::migrations::{self, Command};
+use crate::models::applied_migration::AppliedMigration;
+use crate::tests::setup::use_app;
+use crate::utils::models::ModelExt;
+
+fn args(args: &[&str]) -> Vec<String> {
+  args.iter().map(|arg| arg.to_string()).collect()
+}
+
+#[test]
+fn parse_migrate_command() {
+  assert_eq!(Command::parse(&args(&[])), Some(Command::Run));
+  assert_eq!(Command::parse(&args(&["run"])), Some(Command::Run));
+  assert_eq!(
+    Command::parse(&args(&["rollback"])),
+    Some(Command::Rollback(1))
+  );
+  assert_eq!(
+    Command::parse(&args(&["rollback", "3"])),
+    Some(Command::Rollback(3))
+  );
+  assert_eq!(Command::parse(&args(&["status"])), Some(Command::Status));
+}
+
+#[test]
+fn parse_invalid_migrate_command() {
+  assert_eq!(Command::parse(&args(&["rollback", "all"])), None);
+  assert_eq!(Command::parse(&args(&["status", "now"])), None);
+  assert_eq!(Command::parse(&args(&["up"])), None);
+}
+
+#[test]
+fn app_startup_applies_migrations() {
+  use_app(async move {
+    let applied = AppliedMigration::exists(doc! { "name": "0001_backfill_cat_deleted_at" })
+      .await
+      .unwrap();
+    assert!(applied, "Migrations should be applied at startup");
+
+    let names = migrations::run().await.unwrap();
+    assert!(names.is_empty(), "Applied migrations should not run again");
+
+    let status = migrations::status().await.unwrap();
+    assert!(status.iter().all(|(_, applied)| *applied));
+  });
+}
@@ -0,0 +1,849 @@
+use axum::{
+  extract::{Path, Query},
+  http::{header, HeaderMap, StatusCode},
+  response::{IntoResponse, Response},
+  routing::{get, post},
+  Json, Router,
+};
+use serde_json::json;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::services::ens::namehash;
+use crate::settings::SETTINGS;
+use crate::utils::address::AddressChain;
+
+// The mock Arkham API listens on the port configured in config/test.json.
+const PORT: u16 = 8089;
+
+// Lookups for this address fail upstream with a 500 status code.
+pub const FAILING_ADDRESS: &str = "0x00000000000000000000000000000000000000f0";
+
+// Lookups for this address get an HTML page with a 200 status code, like the
+// error pages of a proxy in front of Arkham.
+pub const HTML_ADDRESS: &str = "0x00000000000000000000000000000000000000f1";
+
+// Lookups for this address fail with a 503 status code every other request,
+// starting with the first one.
+pub const FLAKY_ADDRESS: &str = "0x00000000000000000000000000000000000000f2";
+
+// Lookups for this address answer after the Arkham timeout of the tests.
+pub const SLOW_ADDRESS: &str = "0x00000000000000000000000000000000000000f3";
+
+// This address also holds PEPE on Ethereum, worth as much as its other
+// tokens.
+pub const MEMECOIN_HOLDER: &str = "0x00000000000000000000000000000000000000a9";
+
+// Solana address known by the mock Arkham and Helius APIs, like every EVM
+// address.
+pub const SOLANA_ADDRESS: &str = "9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM";
+
+// Bitcoin address and extended public key known by the mock Arkham and
+// Blockchair APIs, like every EVM address.
+pub const BITCOIN_ADDRESS: &str = "bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq";
+pub const BITCOIN_XPUB: &str = "xpub661MyMwAqRbcFtXgS5sYJABqqG9YLmC4Q1Rdap9gSE8NqtwybGhePY2gZ29ESFjqJoCu1Rupje8YtGqsefD265TMg7usUDFdp6W1EGMcet8";
+
+static FLAKY_REQUESTS: AtomicU32 = AtomicU32::new(0);
+
+// Messages received by the mock Telegram bot API, see `send_telegram_message`.
+pub static TELEGRAM_MESSAGES: Mutex<Vec<Value>> = Mutex::new(Vec::new());
+
+// Messages received by the mock Discord webhooks, with the webhook id.
+pub static DISCORD_MESSAGES: Mutex<Vec<(String, Value)>> = Mutex::new(Vec::new());
+
+// The only entity known by the mock Arkham API.
+pub const ENTITY_ID: &str = "degen";
+
+// The only ENS name known by the mock Ethereum RPC provider, and the address
+// it resolves to. The address has the name as reverse record.
+pub const ENS_NAME: &str = "degen.eth";
+pub const ENS_ADDRESS: &str = "0x00000000000000000000000000000000000000e1";
+
+// This address claims `ENS_NAME` in its reverse record, without the name
+// resolving to it.
+pub const SPOOFED_ENS_ADDRESS: &str = "0x00000000000000000000000000000000000000e2";
+
+// Arkham labels this address as a hacker. Every address has approved it to
+// spend some of a token.
+pub const RISKY_SPENDER: &str = "0x00000000000000000000000000000000000000c3";
+
+// The only saved query known by the mock Dune API, its executions start
+// pending. The first execution has completed, the second is still running.
+pub const DUNE_QUERY_ID: u64 = 1234;
+pub const DUNE_COMPLETED_EXECUTION: &str = "01HDUNECOMPLETED";
+pub const DUNE_PENDING_EXECUTION: &str = "01HDUNEPENDING";
+
+// Token ids requested from the mock CoinGecko API, one entry per request.
+pub static COINGECKO_REQUESTS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+const ENS_RESOLVER: &str = "0x00000000000000000000000000000000000000e0";
+
+// Tokens and spenders of the approvals of every address, see
+// `approval_logs`.
+const APPROVED_TOKEN: &str = "0x00000000000000000000000000000000000000b1";
+const REVOKED_TOKEN: &str = "0x00000000000000000000000000000000000000b2";
+const APPROVED_NFT: &str = "0x00000000000000000000000000000000000000d1";
+const SPENDER: &str = "0x00000000000000000000000000000000000000c1";
+const OPERATOR: &str = "0x00000000000000000000000000000000000000c2";
+
+pub fn create_app() -> Router {
+  Router::new()
+    .route("/health", get(|| async { "ok" }))
+    .route("/intelligence/address/:address/all", get(get_address))
+    .route("/intelligence/entity/:id", get(get_entity))
+    .route("/transfers", get(get_transfers))
+    .route("/balances/address/:address", get(get_balances))
+    // Webhook receivers used by the webhook delivery tests.
+    .route("/webhooks/ok", post(|| async { StatusCode::OK }))
+    .route(
+      "/webhooks/failing",
+      post(|| async { StatusCode::INTERNAL_SERVER_ERROR }),
+    )
+    // Telegram bot API of the `test` bot token.
+    .route("/bottest/sendMessage", post(send_telegram_message))
+    .route("/api/webhooks/:id/:token", post(send_discord_message))
+    // Ethereum JSON-RPC provider used to resolve ENS names.
+    .route("/rpc", post(rpc_call))
+    .route(
+      "/rpc/unavailable",
+      post(|| async { StatusCode::BAD_GATEWAY }),
+    )
+    .route("/coingecko/simple/price", get(get_coingecko_prices))
+    .route("/etherscan/api", get(get_etherscan_transactions))
+    .route(
+      "/covalent/:chain/address/:address/balances_v2/",
+      get(get_covalent_balances),
+    )
+    .route("/helius", post(helius_rpc_call))
+    .route(
+      "/blockchair/dashboards/:kind/:address",
+      get(get_blockchair_dashboard),
+    )
+    .route("/dune/query/:query_id/execute", post(execute_dune_query))
+    .route("/dune/execution/:id/results", get(get_dune_results))
+    .route(
+      "/zerion/wallets/:address/positions/",
+      get(get_zerion_positions),
+    )
+    .route(
+      "/alchemy/:network/:api_key/getNFTsForOwner",
+      get(get_alchemy_nfts),
+    )
+}
+
+pub async fn serve() {
+  let address = SocketAddr::from(([127, 0, 0, 1], PORT));
+  axum::Server::bind(&address)
+    .serve(create_app().into_make_service())
+    .await
+    .expect("Failed to start mock Arkham server");
+}
+
+// Requests must carry the configured API key, like the real Arkham API.
+fn authorize(headers: &HeaderMap) -> Result<(), StatusCode> {
+  match headers.get("API-Key") {
+    Some(api_key) if api_key == SETTINGS.arkham.api_key.as_str() => Ok(()),
+    _ => Err(StatusCode::UNAUTHORIZED),
+  }
+}
+
+async fn get_address(
+  headers: HeaderMap,
+  Path(address): Path<String>,
+) -> Result<Response, StatusCode> {
+  authorize(&headers)?;
+
+  if address == FAILING_ADDRESS {
+    return Err(StatusCode::INTERNAL_SERVER_ERROR);
+  }
+
+  if address == FLAKY_ADDRESS && FLAKY_REQUESTS.fetch_add(1, Ordering::SeqCst) % 2 == 0 {
+    return Err(StatusCode::SERVICE_UNAVAILABLE);
+  }
+
+  if address == SLOW_ADDRESS {
+    tokio::time::sleep(Duration::from_millis(1500)).await;
+  }
+
+  if address == HTML_ADDRESS {
+    let html = "<html><body><h1>Service temporarily unavailable</h1></body></html>";
+    return Ok(([(header::CONTENT_TYPE, "text/html")], html).into_response());
+  }
+
+  Ok(Json(address_payload(&address)).into_response())
+}
+
+async fn get_entity(headers: HeaderMap, Path(id): Path<String>) -> Result<Json<Value>, StatusCode> {
+  authorize(&headers)?;
+
+  if id != ENTITY_ID {
+    return Err(StatusCode::NOT_FOUND);
+  }
+
+  Ok(Json(json!({
+    "id": ENTITY_ID,
+    "name": "Degen",
+    "type": "individual",
+    "addresses": ["0x00000000000000000000000000000000000000a1"],
+    "twitter": "https://twitter.com/degen",
+    "populatedTags": [{ "id": "whale", "label": "Whale" }]
+  })))
+}
+
+// Every address holds the same tokens, out of USD value order, with a chain
+// it has nothing on.
+async fn get_balances(
+  headers: HeaderMap,
+  Path(address): Path<String>,
+) -> Result<Json<Value>, StatusCode> {
+  authorize(&headers)?;
+
+  if address == FAILING_ADDRESS {
+    return Err(StatusCode::INTERNAL_SERVER_ERROR);
+  }
+
+  Ok(Json(json!({
+    "balances": {
+      "ethereum": [
+        {
+          "id": "usd-coin",
+          "name": "USD Coin",
+          "symbol": "USDC",
+          "ethereumAddress": APPROVED_TOKEN,
+          "balance": "1000",
+          "price": 1,
+          "usd": 1000
+        },
+        { "id": "ethereum", "name": "Ether", "symbol": "ETH", "balance": 1.5, "price": 3000, "usd": 4500 }
+      ],
+      "polygon": [
+        { "id": "matic-network", "name": "Polygon", "symbol": "MATIC", "balance": 10, "price": 0.5, "usd": 5 }
+      ],
+      "bsc": null
+    },
+    "totalBalance": { "ethereum": 5500, "polygon": 5 }
+  })))
+}
+
+// Every address has the same three transfers, at 1000, 2000 and 3000 ms.
+async fn get_transfers(
+  headers: HeaderMap,
+  Query(query): Query<HashMap<String, String>>,
+) -> Result<Json<Value>, StatusCode> {
+  authorize(&headers)?;
+
+  let param = |name: &str| -> Result<Option<i64>, StatusCode> {
+    query
+      .get(name)
+      .map(|value| value.parse::<i64>().map_err(|_| StatusCode::BAD_REQUEST))
+      .transpose()
+  };
+
+  let base = query.get("base").ok_or(StatusCode::BAD_REQUEST)?;
+  let time_gte = param("timeGte")?.unwrap_or(i64::MIN);
+  let time_lte = param("timeLte")?.unwrap_or(i64::MAX);
+  let offset = param("offset")?.unwrap_or(0) as usize;
+  let limit = param("limit")?.unwrap_or(100) as usize;
+
+  let transfers = (1..=3)
+    .map(|index| (index, index * 1000))
+    .filter(|(_, time)| *time >= time_gte && *time <= time_lte)
+    .map(|(index, time)| {
+      json!({
+        "id": format!("transfer-{}", index),
+        "transactionHash": format!("0x{:064x}", index),
+        "fromAddress": { "address": base, "chain": "ethereum" },
+        "toAddress": {
+          "address": "0x00000000000000000000000000000000000000b1",
+          "chain": "ethereum",
+          "arkhamLabel": { "name": "Degen Treasury" }
+        },
+        "tokenSymbol": "ETH",
+        "blockTimestamp": chrono::DateTime::from_timestamp_millis(time).unwrap().to_rfc3339(),
+        "blockNumber": index,
+        "chain": "ethereum",
+        "unitValue": "1.5",
+        "historicalUSD": 4200.25
+      })
+    })
+    .collect::<Vec<Value>>();
+
+  let count = transfers.len();
+  let transfers = transfers
+    .into_iter()
+    .skip(offset)
+    .take(limit)
+    .collect::<Vec<Value>>();
+
+  Ok(Json(json!({ "transfers": transfers, "count": count })))
+}
+
+async fn send_telegram_message(Json(message): Json<Value>) -> Json<Value> {
+  TELEGRAM_MESSAGES.lock().unwrap().push(message);
+  Json(json!({ "ok": true }))
+}
+
+async fn send_discord_message(
+  Path((id, _token)): Path<(String, String)>,
+  Json(message): Json<Value>,
+) -> StatusCode {
+  DISCORD_MESSAGES.lock().unwrap().push((id, message));
+  StatusCode::NO_CONTENT
+}
+
+// Only `bitcoin`, `ethereum` and `tether` have a price, other ids are left
+// out like CoinGecko does.
+async fn get_coingecko_prices(Query(query): Query<HashMap<String, String>>) -> Json<Value> {
+  let ids = query.get("ids").cloned().unwrap_or_default();
+  COINGECKO_REQUESTS.lock().unwrap().push(ids.clone());
+
+  let prices = ids
+    .split(',')
+    .filter_map(|id| {
+      let usd = match id {
+        "bitcoin" => 60000.5,
+        "ethereum" => 3000.25,
+        "tether" => 1.0,
+        _ => return None,
+      };
+      let quote = json!({ "usd": usd, "usd_24h_change": -1.5, "last_updated_at": 1700000000 });
+      Some((id.to_owned(), quote))
+    })
+    .collect::<serde_json::Map<String, Value>>();
+
+  Json(Value::Object(prices))
+}
+
+// Every address has the same three transactions, in blocks 3, 2 and 1, the
+// last one creating a contract. Lookups of `FAILING_ADDRESS` fail like when
+// the API key is over its rate limit. Only the source code of the `SPENDER`
+// contract is verified.
+async fn get_etherscan_transactions(Query(query): Query<HashMap<String, String>>) -> Json<Value> {
+  let param = |name: &str| query.get(name).map(String::as_str).unwrap_or_default();
+  let address = param("address");
+
+  if param("apikey") != "test" || address == FAILING_ADDRESS {
+    return Json(json!({ "status": "0", "message": "NOTOK", "result": "Max rate limit reached" }));
+  }
+
+  if param("action") == "getsourcecode" {
+    let source_code = if address == SPENDER {
+      "pragma solidity ^0.8.0;"
+    } else {
+      ""
+    };
+    let contract = json!({ "SourceCode": source_code, "ContractName": "" });
+    return Json(json!({ "status": "1", "message": "OK", "result": [contract] }));
+  }
+
+  let page = param("page").parse::<usize>().unwrap_or(1).max(1);
+  let offset = param("offset").parse::<usize>().unwrap_or(10);
+  let transactions = (1..=3)
+    .rev()
+    .map(|block| {
+      let creation = block == 1;
+      json!({
+        "blockNumber": block.to_string(),
+        "timeStamp": (1700000000 + block).to_string(),
+        "hash": format!("0x{:064x}", block),
+        "from": address,
+        "to": if creation { "" } else { "0x00000000000000000000000000000000000000B1" },
+        "contractAddress": if creation { "0x00000000000000000000000000000000000000c1" } else { "" },
+        "value": "1500000000000000000",
+        "gasUsed": "21000",
+        "gasPrice": "30000000000",
+        "isError": "0",
+        "functionName": ""
+      })
+    })
+    .skip((page - 1) * offset)
+    .take(offset)
+    .collect::<Vec<Value>>();
+
+  if transactions.is_empty() {
+    return Json(json!({ "status": "0", "message": "No transactions found", "result": [] }));
+  }
+
+  Json(json!({ "status": "1", "message": "OK", "result": transactions }))
+}
+
+// Every address holds 1.5 of the native token, worth $3000, on every chain
+// and 100 USDC on Ethereum, and `MEMECOIN_HOLDER` as much PEPE as the rest.
+// BSC balances of `FAILING_ADDRESS` fail.
+async fn get_covalent_balances(
+  headers: HeaderMap,
+  Path((chain, address)): Path<(String, String)>,
+) -> Result<Json<Value>, StatusCode> {
+  match headers.get(header::AUTHORIZATION) {
+    Some(value) if value == "Bearer test" => {}
+    _ => return Err(StatusCode::UNAUTHORIZED),
+  }
+
+  if chain == "bsc-mainnet" && address == FAILING_ADDRESS {
+    return Err(StatusCode::INTERNAL_SERVER_ERROR);
+  }
+
+  let mut items = vec![json!({
+    "contract_ticker_symbol": "NATIVE",
+    "contract_name": "Native token",
+    "contract_address": "0xeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeee",
+    "contract_decimals": 18,
+    "native_token": true,
+    "balance": "1500000000000000000",
+    "quote": 3000.0
+  })];
+  if chain == "eth-mainnet" {
+    items.push(json!({
+      "contract_ticker_symbol": "USDC",
+      "contract_name": "USD Coin",
+      "contract_address": "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48",
+      "contract_decimals": 6,
+      "native_token": false,
+      "balance": "100000000",
+      "quote": 100.0
+    }));
+  }
+  if chain == "eth-mainnet" && address == MEMECOIN_HOLDER {
+    items.push(json!({
+      "contract_ticker_symbol": "PEPE",
+      "contract_name": "Pepe",
+      "contract_address": "0x6982508145454ce325ddbe47a25d4ec3d2311933",
+      "contract_decimals": 18,
+      "native_token": false,
+      "balance": "1000000000000000000000000000",
+      "quote": 18100.0
+    }));
+  }
+
+  Ok(Json(json!({
+    "data": { "address": address, "chain_name": chain, "items": items },
+    "error": false,
+    "error_message": null
+  })))
+}
+
+// Every Solana address holds 2.5 SOL and 100 USDC.
+async fn helius_rpc_call(
+  Query(query): Query<HashMap<String, String>>,
+  Json(request): Json<Value>,
+) -> Result<Json<Value>, StatusCode> {
+  if query.get("api-key").map(String::as_str) != Some("test") {
+    return Err(StatusCode::UNAUTHORIZED);
+  }
+
+  let result = match request["method"].as_str() {
+    Some("getBalance") => json!({ "context": { "slot": 1 }, "value": 2500000000_u64 }),
+    Some("getTokenAccountsByOwner") => json!({
+      "context": { "slot": 1 },
+      "value": [{
+        "pubkey": "7UX2i7SucgLMQcfZ75s3VXmZZY4YRUyJN9X1RgfMoDUi",
+        "account": {
+          "data": {
+            "parsed": {
+              "info": {
+                "mint": "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v",
+                "owner": request["params"][0],
+                "tokenAmount": { "amount": "100000000", "decimals": 6 }
+              },
+              "type": "account"
+            },
+            "program": "spl-token"
+          }
+        }
+      }]
+    }),
+    _ => {
+      let error = json!({ "code": -32601, "message": "Method not found" });
+      return Ok(Json(
+        json!({ "jsonrpc": "2.0", "id": request["id"], "error": error }),
+      ));
+    }
+  };
+
+  Ok(Json(
+    json!({ "jsonrpc": "2.0", "id": request["id"], "result": result }),
+  ))
+}
+
+// Every Bitcoin address and extended public key holds 1.5 BTC, worth
+// $90000, and has three transactions, one per day from January 1, 2024.
+async fn get_blockchair_dashboard(
+  Path((kind, address)): Path<(String, String)>,
+  Query(query): Query<HashMap<String, String>>,
+) -> Result<Json<Value>, StatusCode> {
+  if query.get("key").map(String::as_str) != Some("test") {
+    return Err(StatusCode::UNAUTHORIZED);
+  }
+
+  // Only the transactions limit and offset matter, see `fetch_wallet`.
+  let first = |name: &str| -> usize {
+    query
+      .get(name)
+      .and_then(|value| value.split(',').next())
+      .and_then(|value| value.parse().ok())
+      .unwrap_or(0)
+  };
+  let transactions = [(3, 50000000), (2, -50000000), (1, 150000000)]
+    .into_iter()
+    .map(|(day, balance_change)| {
+      json!({
+        "block_id": 820000 + day,
+        "hash": format!("{:064x}", day),
+        "time": format!("2024-01-0{} 00:00:00", day),
+        "balance_change": balance_change
+      })
+    })
+    .skip(first("offset"))
+    .take(first("limit"))
+    .collect::<Vec<Value>>();
+
+  let summary = json!({
+    "balance": 150000000,
+    "balance_usd": 90000.0,
+    "transaction_count": 3
+  });
+  Ok(Json(json!({
+    "data": { address: { kind: summary, "transactions": transactions } },
+    "context": { "code": 200 }
+  })))
+}
+
+// Every address holds two Ethereum collections, the first with a floor
+// price, spread over two pages, and a Polygon collection. Polygon lookups of
+// `FAILING_ADDRESS` fail, other networks have no NFTs.
+async fn get_alchemy_nfts(
+  Path((network, api_key)): Path<(String, String)>,
+  Query(query): Query<HashMap<String, String>>,
+) -> Result<Json<Value>, StatusCode> {
+  if api_key != "test" {
+    return Err(StatusCode::UNAUTHORIZED);
+  }
+
+  let owner = query.get("owner").map(String::as_str).unwrap_or_default();
+  let apes = json!({
+    "address": "0x00000000000000000000000000000000000000d1",
+    "name": "Degen Apes",
+    "tokenType": "ERC721",
+    "openSeaMetadata": { "floorPrice": 1.25, "collectionName": "Degen Apes" }
+  });
+  let nft = |contract: &Value, token_id: &str, image: Value, balance: &str| {
+    json!({
+      "contract": contract,
+      "tokenId": token_id,
+      "name": format!("#{}", token_id),
+      "image": image,
+      "balance": balance
+    })
+  };
+
+  let (nfts, page_key) = match network.as_str() {
+    "eth-mainnet" if query.get("pageKey").is_none() => {
+      let items = json!({
+        "address": "0x00000000000000000000000000000000000000d2",
+        "name": null,
+        "tokenType": "ERC1155",
+        "openSeaMetadata": { "floorPrice": null, "collectionName": null }
+      });
+      let image = json!({ "cachedUrl": "https://nft.test/apes/1.png" });
+      let nfts = vec![
+        nft(&apes, "1", image, "1"),
+        nft(&items, "7", json!({}), "3"),
+      ];
+      (nfts, Some("2"))
+    }
+    "eth-mainnet" => {
+      let image = json!({ "cachedUrl": null, "originalUrl": "ipfs://apes/2.png" });
+      (vec![nft(&apes, "2", image, "1")], None)
+    }
+    "polygon-mainnet" if owner == FAILING_ADDRESS => {
+      return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+    "polygon-mainnet" => {
+      let lands = json!({
+        "address": "0x00000000000000000000000000000000000000d3",
+        "name": "Degen Lands",
+        "tokenType": "ERC721"
+      });
+      (vec![nft(&lands, "42", json!({}), "1")], None)
+    }
+    _ => (Vec::new(), None),
+  };
+
+  Ok(Json(json!({ "ownedNfts": nfts, "pageKey": page_key })))
+}
+
+// Every address has a lending position on Aave, an LP position on Uniswap
+// and staked ETH on Lido, over two pages. Lookups of `FAILING_ADDRESS` fail.
+async fn get_zerion_positions(
+  headers: HeaderMap,
+  Path(address): Path<String>,
+  Query(query): Query<HashMap<String, String>>,
+) -> Result<Json<Value>, StatusCode> {
+  // Basic authentication with the API key as username, `test:` in base64.
+  match headers.get(header::AUTHORIZATION) {
+    Some(value) if value == "Basic dGVzdDo=" => {}
+    _ => return Err(StatusCode::UNAUTHORIZED),
+  }
+
+  if address == FAILING_ADDRESS {
+    return Err(StatusCode::INTERNAL_SERVER_ERROR);
+  }
+
+  let position = |protocol: Option<&str>, chain: &str, kind: &str, symbol: &str, value: f64| {
+    json!({
+      "type": "positions",
+      "attributes": {
+        "name": symbol,
+        "position_type": kind,
+        "protocol": protocol,
+        "application_metadata": { "name": "Lido" },
+        "quantity": { "numeric": "1.5" },
+        "value": value,
+        "fungible_info": { "symbol": symbol }
+      },
+      "relationships": { "chain": { "data": { "type": "chains", "id": chain } } }
+    })
+  };
+
+  if query.get("page[after]").is_none() {
+    let next = format!(
+      "http://localhost:{}/zerion/wallets/{}/positions/?page[after]=2",
+      PORT, address
+    );
+    return Ok(Json(json!({
+      "links": { "next": next },
+      "data": [
+        position(Some("Aave V3"), "ethereum", "deposit", "USDC", 1000.0),
+        position(Some("Aave V3"), "ethereum", "loan", "DAI", 400.0)
+      ]
+    })));
+  }
+
+  Ok(Json(json!({
+    "links": {},
+    "data": [
+      position(Some("Uniswap V3"), "arbitrum", "deposit", "WETH", 500.0),
+      position(Some("Uniswap V3"), "arbitrum", "deposit", "USDC", 500.0),
+      position(None, "ethereum", "staked", "stETH", 2000.0)
+    ]
+  })))
+}
+
+async fn execute_dune_query(
+  headers: HeaderMap,
+  Path(query_id): Path<u64>,
+) -> Result<Json<Value>, StatusCode> {
+  if headers.get("X-Dune-API-Key").map(|key| key == "test") != Some(true) {
+    return Err(StatusCode::UNAUTHORIZED);
+  }
+
+  if query_id != DUNE_QUERY_ID {
+    return Err(StatusCode::NOT_FOUND);
+  }
+
+  Ok(Json(json!({
+    "execution_id": DUNE_PENDING_EXECUTION,
+    "state": "QUERY_STATE_PENDING"
+  })))
+}
+
+async fn get_dune_results(
+  headers: HeaderMap,
+  Path(id): Path<String>,
+) -> Result<Json<Value>, StatusCode> {
+  if headers.get("X-Dune-API-Key").map(|key| key == "test") != Some(true) {
+    return Err(StatusCode::UNAUTHORIZED);
+  }
+
+  let execution = match id.as_str() {
+    DUNE_COMPLETED_EXECUTION => json!({
+      "execution_id": id,
+      "query_id": DUNE_QUERY_ID,
+      "state": "QUERY_STATE_COMPLETED",
+      "is_execution_finished": true,
+      "submitted_at": "2024-01-01T00:00:00Z",
+      "execution_ended_at": "2024-01-01T00:00:05Z",
+      "result": {
+        "rows": [
+          { "project": "uniswap", "volume": 1500.5 },
+          { "project": "curve", "volume": 700.25 }
+        ],
+        "metadata": {
+          "column_names": ["project", "volume"],
+          "column_types": ["varchar", "double"],
+          "row_count": 2
+        }
+      }
+    }),
+    DUNE_PENDING_EXECUTION => json!({
+      "execution_id": id,
+      "query_id": DUNE_QUERY_ID,
+      "state": "QUERY_STATE_EXECUTING",
+      "is_execution_finished": false,
+      "submitted_at": "2024-01-01T00:00:00Z"
+    }),
+    _ => return Err(StatusCode::NOT_FOUND),
+  };
+
+  Ok(Json(execution))
+}
+
+// Answers the `eth_call`s made by the ENS client, by function selector. The
+// registry and resolver addresses are ignored, unknown nodes have neither a
+// resolver nor records.
+async fn rpc_call(Json(request): Json<Value>) -> Json<Value> {
+  if request["method"] == "eth_getLogs" {
+    let owner = request["params"][0]["topics"][1]
+      .as_str()
+      .unwrap_or_default();
+    if owner.ends_with(&FAILING_ADDRESS[2..]) {
+      let error = json!({ "code": -32005, "message": "query returned more than 10000 results" });
+      return Json(json!({ "jsonrpc": "2.0", "id": request["id"], "error": error }));
+    }
+    return Json(json!({ "jsonrpc": "2.0", "id": request["id"], "result": approval_logs(owner) }));
+  }
+
+  // 1 gwei then 2 gwei base fees, and the same priority fees in every block.
+  if request["method"] == "eth_feeHistory" {
+    let history = json!({
+      "oldestBlock": "0x1",
+      "baseFeePerGas": ["0x3b9aca00", "0x77359400"],
+      "gasUsedRatio": [0.5],
+      "reward": [["0x5f5e100", "0x3b9aca00", "0x77359400"]]
+    });
+    return Json(json!({ "jsonrpc": "2.0", "id": request["id"], "result": history }));
+  }
+
+  let to = request["params"][0]["to"].as_str().unwrap_or_default();
+  let data = request["params"][0]["data"].as_str().unwrap_or_default();
+  let (selector, node) = data.trim_start_matches("0x").split_at(8);
+  let is_node = |name: &str| node == hex::encode(namehash(name));
+  let reverse_name = |address: &str| format!("{}.addr.reverse", &address[2..]);
+  let reverse_names = [reverse_name(ENS_ADDRESS), reverse_name(SPOOFED_ENS_ADDRESS)];
+  let has_reverse_record = reverse_names.iter().any(|name| is_node(name));
+
+  let result = match selector {
+    // `resolver(bytes32)`
+    "0178b8bf" if is_node(ENS_NAME) || has_reverse_record => abi_address(ENS_RESOLVER),
+    // `addr(bytes32)`
+    "3b3b57de" if is_node(ENS_NAME) => abi_address(ENS_ADDRESS),
+    // `name(bytes32)`
+    "691f3431" if has_reverse_record => abi_string(ENS_NAME),
+    // `allowance(address,address)`, by spender.
+    "dd62ed3e" if to == APPROVED_TOKEN && node.ends_with(&SPENDER[2..]) => "f".repeat(64),
+    "dd62ed3e" if to == APPROVED_TOKEN && node.ends_with(&RISKY_SPENDER[2..]) => {
+      format!("{:064x}", 1000)
+    }
+    // `isApprovedForAll(address,address)`
+    "e985e9c5" if to == APPROVED_NFT && node.ends_with(&OPERATOR[2..]) => format!("{:064x}", 1),
+    // `getApproved(uint256)`, token 8 was transferred since its approval.
+    "081812fc" if to == APPROVED_NFT && node == format!("{:064x}", 7) => abi_address(SPENDER),
+    _ => abi_address("0x0"),
+  };
+
+  Json(json!({ "jsonrpc": "2.0", "id": request["id"], "result": format!("0x{}", result) }))
+}
+
+// Approval logs of an owner, oldest first: an unlimited and a limited
+// allowance of a token, an allowance revoked later, an NFT operator and two
+// single NFT approvals.
+fn approval_logs(owner: &str) -> Vec<Value> {
+  let approval = "0x8c5be1e5ebec7d5bd14f71427d1e84f3dd0314c0f7b2291e5b200ac8c7c3b925";
+  let approval_for_all = "0x17307eab39ab6107e8899845ad3d59bd9653f200f220920489ca2b5937696c31";
+  let topic = |address: &str| format!("0x{}", abi_address(address));
+  let word = |value: u64| format!("0x{:064x}", value);
+  let log = |token: &str, event: &str, args: Vec<String>, data: String| {
+    let topics = [vec![event.to_owned(), owner.to_owned()], args].concat();
+    json!({ "address": token, "topics": topics, "data": data })
+  };
+
+  vec![
+    log(
+      APPROVED_TOKEN,
+      approval,
+      vec![topic(SPENDER)],
+      format!("0x{}", "f".repeat(64)),
+    ),
+    log(
+      APPROVED_TOKEN,
+      approval,
+      vec![topic(RISKY_SPENDER)],
+      word(1000),
+    ),
+    log(REVOKED_TOKEN, approval, vec![topic(SPENDER)], word(500)),
+    log(REVOKED_TOKEN, approval, vec![topic(SPENDER)], word(0)),
+    log(
+      APPROVED_NFT,
+      approval_for_all,
+      vec![topic(OPERATOR)],
+      word(1),
+    ),
+    log(
+      APPROVED_NFT,
+      approval,
+      vec![topic(SPENDER), word(7)],
+      "0x".to_owned(),
+    ),
+    log(
+      APPROVED_NFT,
+      approval,
+      vec![topic(OPERATOR), word(8)],
+      "0x".to_owned(),
+    ),
+  ]
+}
+
+fn abi_address(address: &str) -> String {
+  format!("{:0>64}", &address[2..])
+}
+
+fn abi_string(string: &str) -> String {
+  let data = hex::encode(string);
+  let padded_length = (data.len() + 63) / 64 * 64;
+  format!("{:064x}{:064x}{:0<padded_length$}", 32, string.len(), data)
+}
+
+/// Arkham data of an address on every EVM chain, or only on its own chain
+/// for Solana and Bitcoin addresses.
+pub fn address_payload(address: &str) -> Value {
+  let chain_type = match AddressChain::detect(address) {
+    Some(AddressChain::Solana) => "solana",
+    Some(AddressChain::Bitcoin) => "bitcoin",
+    _ => "evm",
+  };
+  let chain = |chain: &str| {
+    json!({
+      "address": address,
+      "chain": chain,
+      "arkhamEntity": {
+        "name": "Degen",
+        "id": "degen",
+        "type": if address == RISKY_SPENDER { "hacker" } else { "individual" }
+      },
+      "arkhamLabel": {
+        "name": "Degen Wallet",
+        "address": address,
+        "chainType": chain_type
+      },
+      "isUserAddress": false,
+      "contract": false
+    })
+  };
+
+  if chain_type != "evm" {
+    return json!({ chain_type: chain(chain_type) });
+  }
+
+  json!({
+    "bsc": chain("bsc"),
+    "ethereum": chain("ethereum"),
+    "polygon": chain("polygon"),
+    "arbitrum_one": chain("arbitrum_one"),
+    "avalanche": chain("avalanche"),
+    "optimism": chain("optimism")
+  })
+}
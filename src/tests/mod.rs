@@ -1,3 +1,49 @@
+mod address;
+mod address_intelligence;
+mod alerts;
+mod api_version;
+mod audit;
+mod binary_format;
+mod cache;
+mod casing;
+mod circuit_breaker;
+mod csv;
+mod custom_response;
+mod database;
+mod email;
+mod ens;
+mod export;
+mod feature_flags;
+mod fields;
+mod governor;
+mod grpc;
+mod history;
+mod idempotency;
+mod jobs;
+mod label_dataset;
+mod label_merge;
+mod links;
+mod live;
+mod logger;
+mod maintenance;
+mod merge_patch;
+mod migrations;
+mod mock_arkham;
+mod models;
+mod notifications;
+mod pagination;
+mod proxy;
+mod rate_limit;
+mod redact;
+mod retry;
+mod route_table;
 mod routes;
+mod rpc;
+mod scheduler;
+mod settings;
 mod setup;
+mod shutdown;
+mod totp;
 mod utils;
+mod watcher;
+mod webhooks;
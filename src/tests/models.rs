@@ -0,0 +1,285 @@
+use axum::http::StatusCode;
+use bson::doc;
+use bson::oid::ObjectId;
+use bson::Document;
+use futures::TryStreamExt;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::Subscriber;
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::prelude::*;
+use wither::mongodb::options::FindOptions;
+use wither::Model as WitherModel;
+
+use crate::database::CONNECTION;
+use crate::errors::Error;
+use crate::models::address_label::AddressLabel;
+use crate::models::cat::Cat;
+use crate::models::user::User;
+use crate::tests::setup::use_app;
+use crate::tests::utils::create_user;
+use crate::utils::models::{transaction, CountStrategy, ModelExt};
+use crate::utils::pagination::Cursor;
+
+type Fields = HashMap<String, String>;
+
+// Captures the fields of every `mongo` span.
+#[derive(Clone, Default)]
+struct SpanCapture {
+  spans: Arc<Mutex<HashMap<u64, Fields>>>,
+}
+
+struct FieldVisitor<'a>(&'a mut Fields);
+
+impl Visit for FieldVisitor<'_> {
+  fn record_str(&mut self, field: &Field, value: &str) {
+    self.0.insert(field.name().to_owned(), value.to_owned());
+  }
+
+  fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+    self
+      .0
+      .insert(field.name().to_owned(), format!("{:?}", value));
+  }
+}
+
+impl<S: Subscriber> Layer<S> for SpanCapture {
+  fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, _ctx: Context<'_, S>) {
+    if attrs.metadata().name() != "mongo" {
+      return;
+    }
+
+    let mut fields = Fields::new();
+    attrs.record(&mut FieldVisitor(&mut fields));
+    self.spans.lock().unwrap().insert(id.into_u64(), fields);
+  }
+
+  fn on_record(&self, id: &Id, values: &Record<'_>, _ctx: Context<'_, S>) {
+    if let Some(fields) = self.spans.lock().unwrap().get_mut(&id.into_u64()) {
+      values.record(&mut FieldVisitor(fields));
+    }
+  }
+}
+
+#[test]
+fn find_one_emits_span() {
+  let capture = SpanCapture::default();
+  let subscriber = tracing_subscriber::registry().with(capture.clone());
+
+  use_app(async move {
+    let _guard = tracing::subscriber::set_default(subscriber);
+    Cat::find_one(doc! {}, None).await.unwrap();
+  });
+
+  let spans = capture.spans.lock().unwrap();
+  let span = spans
+    .values()
+    .find(|fields| fields.get("operation").map(String::as_str) == Some("find_one"))
+    .expect("find_one should emit a mongo span");
+
+  assert_eq!(span.get("collection").unwrap(), "cats");
+  assert!(
+    span.contains_key("elapsed_ms"),
+    "Span should record elapsed time"
+  );
+}
+
+#[test]
+fn app_startup_creates_model_indexes() {
+  use_app(async move {
+    let connection = CONNECTION.get().await;
+
+    let cat_indexes = Cat::collection(connection)
+      .list_index_names()
+      .await
+      .unwrap();
+    assert!(cat_indexes.contains(&"user_1_created_at_1".to_owned()));
+
+    let label_indexes = AddressLabel::collection(connection)
+      .list_index_names()
+      .await
+      .unwrap();
+    assert!(label_indexes.contains(&"eth_address_1_created_at_1".to_owned()));
+    assert!(label_indexes.contains(&"name_text_source_text".to_owned()));
+  });
+}
+
+#[test]
+fn versioned_model_updates_increment_version() {
+  use_app(async move {
+    let user = ObjectId::new();
+    let cat = Cat::create(Cat::new(user, "Tigrin".to_owned()))
+      .await
+      .unwrap();
+    let id = cat.id.unwrap();
+
+    Cat::update_one(
+      doc! { "_id": id },
+      doc! { "$set": { "name": "Tigris" } },
+      None,
+    )
+    .await
+    .unwrap();
+    let cat = Cat::update_versioned(doc! { "_id": id }, 2, doc! { "$set": { "name": "Tiger" } })
+      .await
+      .unwrap()
+      .unwrap();
+    assert_eq!(cat.version, 3);
+
+    let stale =
+      Cat::update_versioned(doc! { "_id": id }, 2, doc! { "$set": { "name": "Leo" } }).await;
+    assert_eq!(stale.unwrap_err().status_code(), StatusCode::CONFLICT);
+
+    let missing = Cat::update_versioned(doc! { "_id": ObjectId::new() }, 1, doc! {})
+      .await
+      .unwrap();
+    assert!(missing.is_none());
+  });
+}
+
+#[test]
+fn transaction_returns_the_result_of_the_operation() {
+  use_app(async move {
+    let user = ObjectId::new();
+    let count = transaction(move || async move {
+      Cat::create(Cat::new(user, "Tigrin".to_owned())).await?;
+      // Nested transactions join the outer one.
+      transaction(move || async move {
+        Cat::create(Cat::new(user, "Tigris".to_owned())).await?;
+        Ok(())
+      })
+      .await?;
+      // Reads in the transaction see its writes.
+      Cat::count(doc! { "user": user }).await
+    })
+    .await
+    .unwrap();
+    assert_eq!(count, 2);
+    assert_eq!(Cat::count(doc! { "user": user }).await.unwrap(), 2);
+
+    let err = transaction(|| async { Err::<(), Error>(Error::conflict()) })
+      .await
+      .unwrap_err();
+    assert_eq!(
+      err.status_code(),
+      StatusCode::CONFLICT,
+      "Errors of the operation should be returned"
+    );
+  });
+}
+
+#[test]
+fn transaction_reads_see_its_writes() {
+  use_app(async move {
+    let user = ObjectId::new();
+    let strategy = CountStrategy::Cached(Duration::from_secs(60));
+    let (found, counted, aggregated, streamed) = transaction(move || async move {
+      Cat::create(Cat::new(user, "Tigrin".to_owned())).await?;
+
+      let found = Cat::find(doc! { "user": user }, None).await?.len();
+      let (_, counted) = Cat::find_and_count_with(doc! { "user": user }, None, strategy).await?;
+      let pipeline = vec![doc! { "$match": { "user": user } }];
+      let aggregated = Cat::aggregate::<Document>(pipeline).await?.len();
+      let streamed = Cat::cursor(doc! { "user": user }, None)
+        .await?
+        .try_collect::<Vec<Cat>>()
+        .await?
+        .len();
+
+      Ok((found, counted, aggregated, streamed))
+    })
+    .await
+    .unwrap();
+
+    assert_eq!(found, 1);
+    assert_eq!(counted, 1);
+    assert_eq!(aggregated, 1);
+    assert_eq!(streamed, 1);
+  });
+}
+
+#[test]
+fn find_and_count_with_cached_count() {
+  use_app(async move {
+    let user = ObjectId::new();
+    let strategy = CountStrategy::Cached(Duration::from_secs(60));
+    Cat::create(Cat::new(user, "Tigrin".to_owned()))
+      .await
+      .unwrap();
+
+    let (_, count) = Cat::find_and_count_with(doc! { "user": user }, None, strategy)
+      .await
+      .unwrap();
+    assert_eq!(count, 1);
+
+    Cat::create(Cat::new(user, "Tigris".to_owned()))
+      .await
+      .unwrap();
+    let (cats, count) = Cat::find_and_count_with(doc! { "user": user }, None, strategy)
+      .await
+      .unwrap();
+    assert_eq!(cats.len(), 2);
+    assert_eq!(count, 1, "The count should be cached until it expires");
+
+    let (_, count) = Cat::find_and_count(doc! { "user": user }, None)
+      .await
+      .unwrap();
+    assert_eq!(count, 2);
+  });
+}
+
+#[test]
+fn find_page_and_count_with_cached_count() {
+  use_app(async move {
+    let user = ObjectId::new();
+    let strategy = CountStrategy::Cached(Duration::from_secs(60));
+    for name in ["Tigrin", "Cielito", "Mimi"] {
+      Cat::create(Cat::new(user, name.to_owned())).await.unwrap();
+    }
+    let options = FindOptions::builder().sort(Cursor::sort()).limit(2).build();
+
+    let (cats, count) =
+      Cat::find_page_and_count_with(doc! { "user": user }, None, options.clone(), strategy)
+        .await
+        .unwrap();
+    assert_eq!(cats.len(), 2);
+    assert_eq!(count, 3);
+
+    // Created after the first page was counted, only a new count sees it.
+    Cat::create(Cat::new(user, "Tigris".to_owned()))
+      .await
+      .unwrap();
+    let last = cats.last().unwrap();
+    let after = Cursor::new(last.created_at, last.id.unwrap()).filter();
+    let (cats, count) =
+      Cat::find_page_and_count_with(doc! { "user": user }, Some(after), options, strategy)
+        .await
+        .unwrap();
+    assert_eq!(cats.len(), 1);
+    assert_eq!(count, 3, "Pages of the same results should be counted once");
+  });
+}
+
+#[test]
+fn find_and_count_with_estimated_count() {
+  use_app(async move {
+    create_user("nahuel@gmail.com").await.unwrap();
+    create_user("nico@gmail.com").await.unwrap();
+
+    let (_, count) = User::find_and_count_with(doc! {}, None, CountStrategy::Estimated)
+      .await
+      .unwrap();
+    assert_eq!(count, 2);
+
+    // Filtered queries are counted exactly.
+    let query = doc! { "email": "nico@gmail.com" };
+    let (_, count) = User::find_and_count_with(query, None, CountStrategy::Estimated)
+      .await
+      .unwrap();
+    assert_eq!(count, 1);
+  });
+}
@@ -0,0 +1,170 @@
+use bson::oid::ObjectId;
+use std::sync::Arc;
+
+use crate::models::alert_event::AlertEvent;
+use crate::models::alert_rule::AlertCondition;
+use crate::models::notification_channel::{ChannelKind, NotificationChannel};
+use crate::notifications::discord::{embed, DiscordNotifier};
+use crate::notifications::telegram::{format_message, TelegramNotifier};
+use crate::notifications::Notifier;
+use crate::settings::SETTINGS;
+use crate::state::AppState;
+use crate::tests::mock_arkham::{DISCORD_MESSAGES, TELEGRAM_MESSAGES};
+use crate::tests::setup::use_app;
+use crate::utils::models::ModelExt;
+
+fn alert_event(user: ObjectId, message: &str) -> AlertEvent {
+  AlertEvent::new(
+    user,
+    ObjectId::new(),
+    AlertCondition::EntityLabelChanged,
+    "0x00000000000000000000000000000000000000a1".to_owned(),
+    "ethereum".to_owned(),
+    message.to_owned(),
+  )
+}
+
+#[test]
+fn telegram_message_is_escaped() {
+  let event = alert_event(ObjectId::new(), "Entity label changed from <none> to A&B");
+
+  assert_eq!(
+    format_message(&event),
+    "<b>Degen alert</b>\nEntity label changed from &lt;none&gt; to A&amp;B\n\n\
+     Address: <code>0x00000000000000000000000000000000000000a1</code>\nChain: ethereum"
+  );
+}
+
+#[test]
+fn telegram_message_shows_the_transfer() {
+  let mut event = alert_event(ObjectId::new(), "Sent $4200.25 of ETH to Degen Treasury");
+  event.counterparty = Some("Degen Treasury".to_owned());
+  event.transaction_hash = Some("0xabc".to_owned());
+
+  assert_eq!(
+    format_message(&event),
+    "<b>Degen alert</b>\nSent $4200.25 of ETH to Degen Treasury\n\n\
+     Address: <code>0x00000000000000000000000000000000000000a1</code>\nChain: ethereum\n\
+     Counterparty: Degen Treasury\nTransaction: <code>0xabc</code>"
+  );
+}
+
+#[test]
+fn telegram_message_shows_the_token_of_price_alerts() {
+  let event = AlertEvent::price(
+    ObjectId::new(),
+    ObjectId::new(),
+    AlertCondition::PriceAbove,
+    "ethereum".to_owned(),
+    "ethereum rose above $3000.00, now at $3000.25".to_owned(),
+  );
+
+  assert_eq!(
+    format_message(&event),
+    "<b>Degen alert</b>\nethereum rose above $3000.00, now at $3000.25\n\nToken: ethereum"
+  );
+
+  let embed = embed(&event);
+  assert!(embed.get("url").is_none(), "Price alerts have no address");
+  assert_eq!(embed["fields"][0]["name"], "Token");
+}
+
+#[test]
+fn telegram_validates_chat_ids() {
+  let notifier = TelegramNotifier::new(reqwest::Client::new(), &SETTINGS.telegram);
+
+  assert!(notifier.validate_target("123456").is_ok());
+  assert!(notifier.validate_target("-1001234567890").is_ok());
+  assert!(notifier.validate_target("@degen_alerts").is_ok());
+  assert!(notifier.validate_target("@").is_err());
+  assert!(notifier.validate_target("degen alerts").is_err());
+}
+
+#[test]
+fn dispatch_sends_events_to_the_channels_of_their_users() {
+  use_app(async move {
+    let user = ObjectId::new();
+    let channel = NotificationChannel::new(user, ChannelKind::Telegram, "518001".to_owned());
+    NotificationChannel::create(channel).await.unwrap();
+    let channel =
+      NotificationChannel::new(ObjectId::new(), ChannelKind::Telegram, "518002".to_owned());
+    NotificationChannel::create(channel).await.unwrap();
+
+    let state = AppState::new(Arc::new(SETTINGS.clone()));
+    let events = [alert_event(user, "Arkham entity Binance attached")];
+    state.notifiers.dispatch(&events).await.unwrap();
+
+    let chats = TELEGRAM_MESSAGES
+      .lock()
+      .unwrap()
+      .iter()
+      .filter_map(|message| message["chat_id"].as_str().map(str::to_owned))
+      .filter(|chat_id| chat_id.starts_with("518"))
+      .collect::<Vec<String>>();
+    assert_eq!(chats, vec!["518001"], "Only the channel of the user");
+  });
+}
+
+#[test]
+fn discord_validates_webhook_urls() {
+  let notifier = DiscordNotifier::new(reqwest::Client::new(), &SETTINGS.discord);
+
+  assert!(notifier
+    .validate_target("http://localhost:8089/api/webhooks/1/token")
+    .is_ok());
+  assert!(
+    notifier
+      .validate_target("https://example.com/api/webhooks/1/token")
+      .is_err(),
+    "Only Discord hosts are allowed"
+  );
+  assert!(notifier
+    .validate_target("http://localhost:8089/webhooks/ok")
+    .is_err());
+}
+
+#[test]
+fn discord_embed_links_the_address() {
+  let mut event = alert_event(ObjectId::new(), "Arkham entity Binance attached");
+  event.entity = Some("Binance".to_owned());
+
+  let embed = embed(&event);
+  assert_eq!(embed["description"], "Arkham entity Binance attached");
+  assert_eq!(
+    embed["url"],
+    "https://etherscan.io/address/0x00000000000000000000000000000000000000a1"
+  );
+  let fields = embed["fields"]
+    .as_array()
+    .unwrap()
+    .iter()
+    .map(|field| field["name"].as_str().unwrap())
+    .collect::<Vec<&str>>();
+  assert_eq!(fields, vec!["Address", "Entity", "Chain"]);
+}
+
+#[test]
+fn dispatch_skips_muted_channels() {
+  use_app(async move {
+    let user = ObjectId::new();
+    let url = |id: &str| format!("http://localhost:8089/api/webhooks/{}/token", id);
+    let channel = NotificationChannel::new(user, ChannelKind::Discord, url("519001"));
+    NotificationChannel::create(channel).await.unwrap();
+    let mut channel = NotificationChannel::new(user, ChannelKind::Discord, url("519002"));
+    channel.enabled = false;
+    NotificationChannel::create(channel).await.unwrap();
+
+    let state = AppState::new(Arc::new(SETTINGS.clone()));
+    let events = [alert_event(user, "Address flagged as contract")];
+    state.notifiers.dispatch(&events).await.unwrap();
+
+    let webhooks = DISCORD_MESSAGES
+      .lock()
+      .unwrap()
+      .iter()
+      .map(|(id, _)| id.clone())
+      .filter(|id| id.starts_with("519"))
+      .collect::<Vec<String>>();
+    assert_eq!(webhooks, vec!["519001"], "Muted channels should be skipped");
+  });
+}
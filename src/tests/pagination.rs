@@ -0,0 +1,281 @@
+use wither::bson::{doc, oid::ObjectId};
+
+use crate::utils::date;
+use crate::utils::pagination::{Cursor, Pagination};
+use crate::utils::request_query::{escape_regex, RequestQuery, SortOrder};
+
+fn build(offset: Option<i64>, limit: Option<i64>) -> Pagination {
+  let query = RequestQuery {
+    from: None,
+    offset,
+    limit,
+    ..Default::default()
+  };
+
+  Pagination::build_from_request_query(query).count(0).build()
+}
+
+#[test]
+fn pagination_defaults() {
+  let pagination = build(None, None);
+  assert_eq!(pagination.offset, 0);
+  assert_eq!(pagination.limit, 100);
+}
+
+#[test]
+fn pagination_clamps_oversized_limit() {
+  let pagination = build(None, Some(1_000_000));
+  assert_eq!(pagination.limit, 100);
+}
+
+#[test]
+fn pagination_clamps_zero_and_negative_limit() {
+  let pagination = build(None, Some(0));
+  assert_eq!(pagination.limit, 1);
+
+  let pagination = build(None, Some(-10));
+  assert_eq!(pagination.limit, 1);
+}
+
+#[test]
+fn pagination_floors_negative_offset() {
+  let pagination = build(Some(-5), Some(10));
+  assert_eq!(pagination.offset, 0);
+  assert_eq!(pagination.limit, 10);
+}
+
+fn build_with_count(offset: i64, limit: i64, count: u64) -> Pagination {
+  let query = RequestQuery {
+    from: None,
+    offset: Some(offset),
+    limit: Some(limit),
+    ..Default::default()
+  };
+
+  Pagination::build_from_request_query(query)
+    .count(count)
+    .build()
+}
+
+#[test]
+fn pagination_metadata_for_empty_collection() {
+  let pagination = build_with_count(0, 10, 0);
+  assert_eq!(pagination.total_pages(), 0);
+  assert!(!pagination.has_next());
+}
+
+#[test]
+fn pagination_metadata_rounds_pages_up() {
+  let pagination = build_with_count(0, 10, 21);
+  assert_eq!(pagination.total_pages(), 3);
+  assert!(pagination.has_next());
+
+  let pagination = build_with_count(20, 10, 21);
+  assert!(
+    !pagination.has_next(),
+    "Last page should not have a next one"
+  );
+}
+
+#[test]
+fn pagination_metadata_for_offset_past_the_end() {
+  let pagination = build_with_count(50, 10, 21);
+  assert_eq!(pagination.total_pages(), 3);
+  assert!(!pagination.has_next());
+}
+
+#[test]
+fn pagination_links_to_other_pages() {
+  let query = RequestQuery {
+    offset: Some(10),
+    limit: Some(10),
+    ..Default::default()
+  };
+  let uri = "/v1/cats?name=Tom&offset=10&limit=10".parse().unwrap();
+  let pagination = Pagination::build_from_request_query(query)
+    .uri(uri)
+    .count(35)
+    .build();
+
+  assert_eq!(
+    pagination.links(),
+    vec![
+      ("first", "/v1/cats?name=Tom&limit=10&offset=0".to_owned()),
+      ("prev", "/v1/cats?name=Tom&limit=10&offset=0".to_owned()),
+      ("next", "/v1/cats?name=Tom&limit=10&offset=20".to_owned()),
+      ("last", "/v1/cats?name=Tom&limit=10&offset=30".to_owned()),
+    ]
+  );
+}
+
+#[test]
+fn pagination_links_without_uri() {
+  let pagination = build_with_count(0, 10, 35);
+  assert!(pagination.links().is_empty());
+}
+
+#[test]
+fn pagination_links_with_cursor() {
+  let now = date::now();
+  let id = ObjectId::new();
+  let query = RequestQuery {
+    limit: Some(1),
+    cursor: Some(Cursor::new(now, ObjectId::new())),
+    ..Default::default()
+  };
+  let uri = "/v1/cats?limit=1&cursor=abc".parse().unwrap();
+  let pagination = Pagination::build_from_request_query(query)
+    .uri(uri)
+    .next_cursor(&[id], |id| Cursor::new(now, *id))
+    .count(3)
+    .build();
+
+  let next = format!("/v1/cats?limit=1&cursor={}", Cursor::new(now, id).encode());
+  assert_eq!(
+    pagination.links(),
+    vec![
+      ("first", "/v1/cats?limit=1&offset=0".to_owned()),
+      ("next", next),
+    ]
+  );
+}
+
+#[test]
+fn cursor_roundtrip() {
+  let cursor = Cursor::new(date::now(), ObjectId::new());
+  assert_eq!(Cursor::decode(&cursor.encode()), Some(cursor));
+}
+
+#[test]
+fn cursor_rejects_invalid_values() {
+  assert_eq!(Cursor::decode(""), None);
+  assert_eq!(Cursor::decode("not a cursor"), None);
+  // Hex of "123:abc", the id is not an object id.
+  assert_eq!(Cursor::decode(&hex::encode("123:abc")), None);
+}
+
+#[test]
+fn cursor_replaces_offset() {
+  let query = RequestQuery {
+    from: None,
+    offset: Some(20),
+    limit: Some(10),
+    cursor: Some(Cursor::new(date::now(), ObjectId::new())),
+    ..Default::default()
+  };
+
+  let now = date::now();
+  let ids = (0..10).map(|_| ObjectId::new()).collect::<Vec<_>>();
+  let pagination = Pagination::build_from_request_query(query)
+    .count(11)
+    .next_cursor(&ids, |id| Cursor::new(now, *id))
+    .build();
+  assert_eq!(pagination.offset, 0);
+  assert!(pagination.has_next());
+}
+
+#[test]
+fn cursor_pages_without_next_cursor_are_last() {
+  let query = RequestQuery {
+    limit: Some(10),
+    cursor: Some(Cursor::new(date::now(), ObjectId::new())),
+    ..Default::default()
+  };
+
+  // Counts cover every page, so they don't tell where the cursor is.
+  let pagination = Pagination::build_from_request_query(query)
+    .count(25)
+    .build();
+  assert!(!pagination.has_next());
+}
+
+#[test]
+fn cursor_only_set_for_full_pages() {
+  let ids = vec![ObjectId::new(), ObjectId::new()];
+  let now = date::now();
+
+  let pagination = build(None, Some(2));
+  assert!(pagination.next_cursor.is_none());
+
+  let query = RequestQuery {
+    limit: Some(2),
+    ..Default::default()
+  };
+  let pagination = Pagination::build_from_request_query(query)
+    .next_cursor(&ids, |id| Cursor::new(now, *id))
+    .count(5)
+    .build();
+  assert_eq!(pagination.next_cursor, Some(Cursor::new(now, ids[1])));
+
+  let query = RequestQuery {
+    limit: Some(3),
+    ..Default::default()
+  };
+  let pagination = Pagination::build_from_request_query(query)
+    .next_cursor(&ids, |id| Cursor::new(now, *id))
+    .count(2)
+    .build();
+  assert!(pagination.next_cursor.is_none());
+}
+
+#[test]
+fn request_query_default_sort() {
+  let query = RequestQuery::default();
+  assert!(query.has_default_sort());
+  assert_eq!(query.sort(&["created_at"]).unwrap(), Cursor::sort());
+}
+
+#[test]
+fn request_query_sort_by_allowed_field() {
+  let query = RequestQuery {
+    sort: Some("name".to_owned()),
+    order: Some(SortOrder::Asc),
+    ..Default::default()
+  };
+  assert!(!query.has_default_sort());
+  assert_eq!(
+    query.sort(&["created_at", "name"]).unwrap(),
+    doc! { "name": 1, "_id": 1 }
+  );
+}
+
+#[test]
+fn request_query_rejects_unknown_sort_field() {
+  let query = RequestQuery {
+    sort: Some("password".to_owned()),
+    ..Default::default()
+  };
+  assert!(query.sort(&["created_at", "name"]).is_err());
+}
+
+#[test]
+fn request_query_rejects_cursor_with_custom_sort() {
+  let query = RequestQuery {
+    order: Some(SortOrder::Asc),
+    cursor: Some(Cursor::new(date::now(), ObjectId::new())),
+    ..Default::default()
+  };
+  assert!(query.sort(&["created_at"]).is_err());
+}
+
+#[test]
+fn request_query_filters_creation_date() {
+  let after = date::now();
+  let query = RequestQuery {
+    created_after: Some(after),
+    ..Default::default()
+  };
+
+  let mut filter = doc! { "user": "nico" };
+  query.filter_created(&mut filter);
+  assert_eq!(
+    filter,
+    doc! { "user": "nico", "created_at": { "$gt": after } }
+  );
+}
+
+#[test]
+fn escape_regex_matches_literally() {
+  assert_eq!(escape_regex("Mr. (Cat)*"), r"Mr\. \(Cat\)\*");
+  assert_eq!(escape_regex("Tigrin"), "Tigrin");
+}
@@ -0,0 +1,49 @@
+use bytes::Bytes;
+use futures::stream::{self, StreamExt};
+use tokio::runtime::Runtime;
+
+use crate::utils::proxy::rechunk;
+
+fn rechunked(chunks: Vec<Result<&'static str, ()>>, chunk_bytes: usize) -> Vec<Result<Bytes, ()>> {
+  let chunks = stream::iter(chunks).map(|chunk| chunk.map(Bytes::from));
+
+  let runtime = Runtime::new().unwrap();
+  runtime.block_on(rechunk(chunks, chunk_bytes).collect())
+}
+
+#[test]
+fn rechunk_regroups_chunks_by_size() {
+  let chunks = rechunked(vec![Ok("ab"), Ok("cde"), Ok("f"), Ok("ghij")], 3);
+
+  assert_eq!(
+    chunks,
+    [
+      Ok(Bytes::from("abc")),
+      Ok(Bytes::from("def")),
+      Ok(Bytes::from("ghi")),
+      Ok(Bytes::from("j"))
+    ]
+  );
+}
+
+#[test]
+fn rechunk_splits_large_chunks() {
+  let chunks = rechunked(vec![Ok("abcdefg")], 2);
+
+  assert_eq!(
+    chunks,
+    [
+      Ok(Bytes::from("ab")),
+      Ok(Bytes::from("cd")),
+      Ok(Bytes::from("ef")),
+      Ok(Bytes::from("g"))
+    ]
+  );
+}
+
+#[test]
+fn rechunk_ends_after_an_error() {
+  let chunks = rechunked(vec![Ok("abcd"), Ok("e"), Err(()), Ok("fgh")], 3);
+
+  assert_eq!(chunks, [Ok(Bytes::from("abc")), Err(())]);
+}
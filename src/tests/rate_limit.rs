@@ -0,0 +1,117 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::time::Duration;
+
+use crate::settings::{AnonymousRateLimit, RateLimit, RouteRateLimit};
+use crate::utils::rate_limit::RateLimiter;
+
+fn limiter() -> RateLimiter {
+  let mut routes = HashMap::new();
+  routes.insert(
+    "/v1/cats".to_owned(),
+    RouteRateLimit {
+      capacity: 2,
+      refill_per_sec: 1.0,
+    },
+  );
+
+  RateLimiter::new(&RateLimit {
+    default: RouteRateLimit {
+      capacity: 5,
+      refill_per_sec: 1.0,
+    },
+    routes,
+    anonymous: AnonymousRateLimit {
+      default: RouteRateLimit {
+        capacity: 1,
+        refill_per_sec: 1.0,
+      },
+      routes: HashMap::new(),
+      trust_forwarded_for: false,
+    },
+  })
+}
+
+#[test]
+fn rate_limiter_rejects_requests_over_capacity() {
+  let limiter = limiter();
+
+  assert!(limiter.acquire("/v1/cats", "tigrin").is_ok());
+  assert!(limiter.acquire("/v1/cats", "tigrin").is_ok());
+
+  let rejected = limiter.acquire("/v1/cats", "tigrin").unwrap_err();
+  let retry_after = rejected.retry_after;
+  assert!(retry_after > Duration::ZERO && retry_after <= Duration::from_secs(1));
+}
+
+#[test]
+fn rate_limiter_reports_the_bucket_status() {
+  let limiter = limiter();
+
+  let status = limiter.acquire("/v1/cats", "tigrin").unwrap();
+  assert_eq!(status.limit, 2);
+  assert_eq!(status.remaining, 1);
+  assert!(status.reset > Duration::ZERO && status.reset <= Duration::from_secs(1));
+
+  let status = limiter.acquire("/v1/cats", "tigrin").unwrap();
+  assert_eq!(status.remaining, 0);
+  assert!(status.reset > Duration::from_secs(1) && status.reset <= Duration::from_secs(2));
+
+  let rejected = limiter.acquire("/v1/cats", "tigrin").unwrap_err();
+  assert_eq!(rejected.status.limit, 2);
+  assert_eq!(rejected.status.remaining, 0);
+}
+
+#[test]
+fn rate_limiter_keeps_a_bucket_per_user_and_route() {
+  let limiter = limiter();
+
+  assert!(limiter.acquire("/v1/cats", "tigrin").is_ok());
+  assert!(limiter.acquire("/v1/cats", "tigrin").is_ok());
+  assert!(limiter.acquire("/v1/cats", "tigrin").is_err());
+
+  assert!(
+    limiter.acquire("/v1/cats", "cielito").is_ok(),
+    "Other users should have their own bucket"
+  );
+  assert!(
+    limiter.acquire("/v1/cats/:id", "tigrin").is_ok(),
+    "Other routes should use their own bucket"
+  );
+}
+
+#[test]
+fn rate_limiter_refills_over_time() {
+  let limiter = limiter();
+
+  assert!(limiter.acquire("/v1/cats", "tigrin").is_ok());
+  assert!(limiter.acquire("/v1/cats", "tigrin").is_ok());
+  assert!(limiter.acquire("/v1/cats", "tigrin").is_err());
+
+  std::thread::sleep(Duration::from_millis(1100));
+  assert!(limiter.acquire("/v1/cats", "tigrin").is_ok());
+}
+
+#[test]
+fn rate_limiter_limits_anonymous_requests_by_ip() {
+  let limiter = limiter();
+  let ip = "203.0.113.7".parse::<IpAddr>().unwrap();
+
+  let status = limiter.acquire_anonymous("/v1/cats", ip).unwrap();
+  assert_eq!(
+    status.limit, 1,
+    "Anonymous requests should use the public tier"
+  );
+  assert!(limiter.acquire_anonymous("/v1/cats", ip).is_err());
+
+  assert!(
+    limiter
+      .acquire_anonymous("/v1/cats", "203.0.113.8".parse().unwrap())
+      .is_ok(),
+    "Other IPs should have their own bucket"
+  );
+  assert!(
+    limiter.acquire("/v1/cats", &ip.to_string()).is_ok(),
+    "Users should not share the bucket of an IP"
+  );
+}
@@ -0,0 +1,76 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+use tokio::runtime::Runtime;
+
+use crate::utils::retry::{retry, RetryPolicy};
+
+fn policy(max_attempts: u32) -> RetryPolicy {
+  RetryPolicy {
+    max_attempts,
+    base_delay: Duration::from_millis(1),
+    max_delay: Duration::from_millis(5),
+  }
+}
+
+#[test]
+fn retry_until_success() {
+  let attempts = AtomicU32::new(0);
+  let operation = || async {
+    // Fails the first two attempts.
+    attempts.fetch_add(1, Ordering::SeqCst) >= 2
+  };
+
+  let runtime = Runtime::new().unwrap();
+  let result = runtime.block_on(retry("test", &policy(5), operation, |ok| !ok));
+
+  assert!(result);
+  assert_eq!(attempts.load(Ordering::SeqCst), 3);
+}
+
+#[test]
+fn retry_gives_up_after_max_attempts() {
+  let attempts = AtomicU32::new(0);
+  let operation = || async {
+    attempts.fetch_add(1, Ordering::SeqCst);
+    false
+  };
+
+  let runtime = Runtime::new().unwrap();
+  let result = runtime.block_on(retry("test", &policy(3), operation, |ok| !ok));
+
+  assert!(!result);
+  assert_eq!(attempts.load(Ordering::SeqCst), 3);
+}
+
+#[test]
+fn retry_skips_results_that_are_not_retryable() {
+  let attempts = AtomicU32::new(0);
+  let operation = || async {
+    attempts.fetch_add(1, Ordering::SeqCst);
+    false
+  };
+
+  let runtime = Runtime::new().unwrap();
+  runtime.block_on(retry("test", &policy(3), operation, |_| false));
+
+  assert_eq!(attempts.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn retry_delay_grows_exponentially_up_to_the_maximum() {
+  let policy = RetryPolicy {
+    max_attempts: 10,
+    base_delay: Duration::from_millis(100),
+    max_delay: Duration::from_millis(1000),
+  };
+
+  // Half of every delay is jitter.
+  let delay = policy.delay(1);
+  assert!(delay >= Duration::from_millis(50) && delay <= Duration::from_millis(100));
+
+  let delay = policy.delay(3);
+  assert!(delay >= Duration::from_millis(200) && delay <= Duration::from_millis(400));
+
+  let delay = policy.delay(10);
+  assert!(delay >= Duration::from_millis(500) && delay <= Duration::from_millis(1000));
+}
@@ -0,0 +1,40 @@
+use axum::http::Method;
+
+use crate::utils::route_table::{RouteInfo, RouteTable};
+
+async fn handler() -> &'static str {
+  "ok"
+}
+
+#[test]
+fn route_table_reports_duplicate_registrations() {
+  let arkham: RouteTable = RouteTable::new().get("/arkham/:address", handler);
+  let cats: RouteTable = RouteTable::new()
+    .get("/cats", handler)
+    .get("/arkham/:address", handler);
+
+  let routes: RouteTable =
+    RouteTable::new().nest("/v1", RouteTable::new().merge(arkham).merge(cats));
+
+  let actual = routes.duplicates();
+  let expected = [RouteInfo {
+    method: Method::GET,
+    path: "/v1/arkham/:address".to_owned(),
+  }];
+  assert_eq!(actual, expected);
+
+  // The duplicate is dropped instead of making axum panic on overlapping
+  // routes.
+  routes.into_router();
+}
+
+#[test]
+fn route_table_allows_different_methods_on_the_same_path() {
+  let routes: RouteTable = RouteTable::new()
+    .get("/cats/:id", handler)
+    .put("/cats/:id", handler)
+    .delete("/cats/:id", handler);
+
+  assert!(routes.duplicates().is_empty());
+  routes.into_router();
+}
@@ -0,0 +1,128 @@
+use reqwest;
+use reqwest::StatusCode;
+use serde_json::json;
+use serde_json::Value as Json;
+
+use crate::models::address_snapshot::AddressSnapshot;
+use crate::models::watched_address::WatchedAddress;
+use crate::models::watchlist::Watchlist;
+use crate::routes::arkham::parse_arkham_response;
+use crate::tests::mock_arkham::address_payload;
+use crate::tests::setup::use_app;
+use crate::tests::utils::create_user;
+use crate::tests::utils::create_user_token;
+use crate::utils::date::Date;
+use crate::utils::models::ModelExt;
+
+const ADDRESS: &str = "0x00000000000000000000000000000000000000a1";
+
+// 2024-01-01T00:00:00Z, 2024-01-02T00:00:00Z and 2024-01-03T00:00:00Z.
+const DAY_1: i64 = 1_704_067_200_000;
+const DAY_2: i64 = 1_704_153_600_000;
+const DAY_3: i64 = 1_704_240_000_000;
+
+async fn snapshot(balance: f64, created_at: i64) {
+  let mut payload = address_payload(ADDRESS);
+  payload["ethereum"]["balance"] = json!(balance);
+  let data = parse_arkham_response(payload).unwrap();
+
+  let mut snapshot = AddressSnapshot::new(ADDRESS.to_owned(), data);
+  snapshot.created_at = Date::from_millis(created_at);
+  AddressSnapshot::create(snapshot).await.unwrap();
+}
+
+#[test]
+fn get_address_diff_route() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user.clone()).await.unwrap();
+    let watchlist = Watchlist::new(user.id.unwrap(), "Exchanges".to_owned());
+    let watchlist = Watchlist::create(watchlist).await.unwrap();
+    let watched = WatchedAddress::new(
+      watchlist.id.unwrap(),
+      user.id.unwrap(),
+      ADDRESS.to_owned(),
+      "ethereum".to_owned(),
+      None,
+    );
+    WatchedAddress::create(watched).await.unwrap();
+    snapshot(1.0, DAY_1).await;
+    snapshot(2.0, DAY_2).await;
+    snapshot(3.0, DAY_3).await;
+
+    let client = reqwest::Client::new();
+    let res = client
+      .get(format!(
+        "http://localhost:8088/v1/addresses/{}/diff?from=2024-01-01T12:00:00Z&to=2024-01-03T00:00:00Z",
+        ADDRESS
+      ))
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<Json>().await.unwrap();
+    assert_eq!(body["from_snapshot_at"], "2024-01-01T00:00:00Z");
+    assert_eq!(body["to_snapshot_at"], "2024-01-03T00:00:00Z");
+    let expected = json!([{ "path": "ethereum.balance", "before": 1.0, "after": 3.0 }]);
+    assert_eq!(body["changes"], expected);
+  });
+}
+
+#[test]
+fn get_address_diff_route_with_unwatched_address() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user.clone()).await.unwrap();
+    snapshot(1.0, DAY_1).await;
+
+    let client = reqwest::Client::new();
+    let res = client
+      .get(format!(
+        "http://localhost:8088/v1/addresses/{}/diff?from=2024-01-01T00:00:00Z&to=2024-01-02T00:00:00Z",
+        ADDRESS
+      ))
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::NOT_FOUND;
+    assert_eq!(actual, expected);
+  });
+}
+
+#[test]
+fn get_address_diff_route_with_reversed_dates() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user.clone()).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .get(format!(
+        "http://localhost:8088/v1/addresses/{}/diff?from=2024-01-02T00:00:00Z&to=2024-01-01T00:00:00Z",
+        ADDRESS
+      ))
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::BAD_REQUEST;
+    assert_eq!(actual, expected);
+  });
+}
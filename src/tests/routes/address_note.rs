@@ -0,0 +1,163 @@
+use reqwest;
+use reqwest::StatusCode;
+use serde_json::json;
+
+use crate::models::user_address_note::PublicUserAddressNote;
+use crate::tests::setup::use_app;
+use crate::tests::utils::create_user;
+use crate::tests::utils::create_user_token;
+use crate::utils::address::to_checksum_address;
+
+const ADDRESS: &str = "0x00000000000000000000000000000000000000a1";
+
+#[test]
+fn put_address_note_route() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user.clone()).await.unwrap();
+    let url = format!(
+      "http://localhost:8088/v1/address-notes/{}",
+      to_checksum_address(ADDRESS)
+    );
+
+    let client = reqwest::Client::new();
+    let res = client
+      .put(&url)
+      .header("Authorization", format!("Bearer {}", token))
+      .json(&json!({ "name": " Cold wallet ", "note": "Hardware wallet" }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<PublicUserAddressNote>().await.unwrap();
+    assert_eq!(body.address, to_checksum_address(ADDRESS));
+    assert_eq!(body.name.as_deref(), Some("Cold wallet"));
+    assert_eq!(body.note.as_deref(), Some("Hardware wallet"));
+
+    // Saving again replaces the note.
+    let res = client
+      .put(&url)
+      .header("Authorization", format!("Bearer {}", token))
+      .json(&json!({ "name": "Savings" }))
+      .send()
+      .await
+      .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = client
+      .get("http://localhost:8088/v1/address-notes")
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+    let body = res.json::<Vec<PublicUserAddressNote>>().await.unwrap();
+    assert_eq!(body.len(), 1);
+    assert_eq!(body[0].name.as_deref(), Some("Savings"));
+    assert_eq!(body[0].note, None);
+  });
+}
+
+#[test]
+fn put_address_note_route_with_empty_note() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user.clone()).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .put(format!(
+        "http://localhost:8088/v1/address-notes/{}",
+        ADDRESS
+      ))
+      .header("Authorization", format!("Bearer {}", token))
+      .json(&json!({ "name": " ", "note": null }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::BAD_REQUEST;
+    assert_eq!(actual, expected);
+  });
+}
+
+#[test]
+fn get_address_note_route_of_other_user() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user.clone()).await.unwrap();
+    let other = create_user("lucas@test.com").await.unwrap();
+    let other_token = create_user_token(other.clone()).await.unwrap();
+    let url = format!("http://localhost:8088/v1/address-notes/{}", ADDRESS);
+
+    let client = reqwest::Client::new();
+    client
+      .put(&url)
+      .header("Authorization", format!("Bearer {}", token))
+      .json(&json!({ "note": "Private" }))
+      .send()
+      .await
+      .unwrap();
+
+    let res = client
+      .get(&url)
+      .header("Authorization", format!("Bearer {}", other_token))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::NOT_FOUND;
+    assert_eq!(actual, expected);
+  });
+}
+
+#[test]
+fn remove_address_note_route() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user.clone()).await.unwrap();
+    let url = format!("http://localhost:8088/v1/address-notes/{}", ADDRESS);
+
+    let client = reqwest::Client::new();
+    client
+      .put(&url)
+      .header("Authorization", format!("Bearer {}", token))
+      .json(&json!({ "note": "Private" }))
+      .send()
+      .await
+      .unwrap();
+
+    let res = client
+      .delete(&url)
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::NO_CONTENT;
+    assert_eq!(actual, expected);
+
+    let res = client
+      .get(&url)
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+  });
+}
@@ -0,0 +1,947 @@
+use bson::doc;
+use bson::oid::ObjectId;
+use reqwest;
+use reqwest::StatusCode;
+use serde_json::json;
+use std::time::Duration;
+
+use crate::models::audit_log::{AuditAction, PublicAuditLog};
+use crate::models::cat::Cat;
+use crate::models::cat::PublicCat;
+use crate::models::feature_flag::PublicFeatureFlag;
+use crate::models::user::{PublicUser, Role, User};
+use crate::models::watchlist::Watchlist;
+use crate::models::webhook_delivery::{DeliveryStatus, WebhookDelivery};
+use crate::routes::admin::{RemoveCatsResponse, UserUsage};
+use crate::services::feature_flags::FeatureFlags;
+use crate::services::retention::{Cleanup, RetentionPolicy};
+use crate::services::scheduler::JobStatus;
+use crate::services::stats::Stats;
+use crate::settings::SETTINGS;
+use crate::tests::setup::use_app;
+use crate::tests::utils::create_admin_user;
+use crate::tests::utils::create_user;
+use crate::tests::utils::create_user_token;
+use crate::utils::date;
+use crate::utils::models::ModelExt;
+
+async fn create_webhook_delivery(status: DeliveryStatus, age_days: u64) {
+  let mut delivery = WebhookDelivery::new(ObjectId::new(), ObjectId::new(), ObjectId::new());
+  delivery.status = status;
+  delivery.created_at = date::before(Duration::from_secs(age_days * 24 * 60 * 60));
+  WebhookDelivery::create(delivery).await.unwrap();
+}
+
+#[test]
+fn query_audit_logs_route() {
+  use_app(async move {
+    let admin = create_admin_user("admin@test.com").await.unwrap();
+    let admin_token = create_user_token(admin).await.unwrap();
+    let nico = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(nico.clone()).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .post("http://localhost:8088/v1/cats")
+      .header("Authorization", format!("Bearer {}", token))
+      .json(&json!({ "name": "Tigrin" }))
+      .send()
+      .await
+      .unwrap();
+    let cat = res.json::<PublicCat>().await.unwrap();
+
+    let res = client
+      .delete(format!("http://localhost:8088/v1/cats/{}", cat.id))
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+    let res = client
+      .get(format!(
+        "http://localhost:8088/v1/admin/audit?resource=cats&user={}",
+        nico.id.unwrap()
+      ))
+      .header("Authorization", format!("Bearer {}", admin_token))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<Vec<PublicAuditLog>>().await.unwrap();
+    assert_eq!(body.len(), 2);
+
+    // Newest first, removing a cat sets its deletion date.
+    let removed = &body[0];
+    assert_eq!(removed.action, AuditAction::Update);
+    assert_eq!(removed.route, "DELETE /v1/cats/:id");
+    assert_eq!(removed.document, Some(cat.id));
+    assert_eq!(removed.before.as_ref().unwrap()["deleted_at"], json!(null));
+    assert!(removed.after.as_ref().unwrap()["deleted_at"].is_string());
+
+    let created = &body[1];
+    assert_eq!(created.action, AuditAction::Create);
+    assert_eq!(created.actor, nico.id);
+    assert_eq!(created.route, "POST /v1/cats");
+    assert_eq!(created.before, None);
+    assert_eq!(created.after.as_ref().unwrap()["name"], "Tigrin");
+
+    // Non admin:
+    let res = client
+      .get("http://localhost:8088/v1/admin/audit")
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+  });
+}
+
+#[test]
+fn remove_cats_by_user_route() {
+  use_app(async move {
+    let admin = create_admin_user("admin@test.com").await.unwrap();
+    let token = create_user_token(admin).await.unwrap();
+
+    let nico = create_user("nico@test.com").await.unwrap();
+    let nahuel = create_user("nahuel@test.com").await.unwrap();
+
+    Cat::create(Cat::new(nico.id.unwrap(), "Tigrin".to_owned()))
+      .await
+      .unwrap();
+    Cat::create(Cat::new(nico.id.unwrap(), "Cielito".to_owned()))
+      .await
+      .unwrap();
+    Cat::create(Cat::new(nahuel.id.unwrap(), "Cholin".to_owned()))
+      .await
+      .unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .delete(format!(
+        "http://localhost:8088/v1/admin/cats?user={}",
+        nico.id.unwrap()
+      ))
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<RemoveCatsResponse>().await.unwrap();
+    assert_eq!(body.deleted_count, 2);
+
+    // Cats from the database:
+    let count = Cat::count(doc! { "user": nico.id.unwrap() }).await.unwrap();
+    assert_eq!(count, 0, "User cats should be removed");
+    let count = Cat::count(doc! { "user": nahuel.id.unwrap() })
+      .await
+      .unwrap();
+    assert_eq!(count, 1, "Other users cats should be kept");
+  });
+}
+
+#[test]
+fn remove_cats_without_user_route() {
+  use_app(async move {
+    let admin = create_admin_user("admin@test.com").await.unwrap();
+    let token = create_user_token(admin.clone()).await.unwrap();
+
+    Cat::create(Cat::new(admin.id.unwrap(), "Tigrin".to_owned()))
+      .await
+      .unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .delete("http://localhost:8088/v1/admin/cats")
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::BAD_REQUEST;
+    assert_eq!(actual, expected);
+
+    // Cats from the database:
+    let count = Cat::count(doc! {}).await.unwrap();
+    assert_eq!(count, 1, "No cats should be removed");
+  });
+}
+
+#[test]
+fn remove_cats_as_non_admin_route() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user.clone()).await.unwrap();
+
+    Cat::create(Cat::new(user.id.unwrap(), "Tigrin".to_owned()))
+      .await
+      .unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .delete(format!(
+        "http://localhost:8088/v1/admin/cats?user={}",
+        user.id.unwrap()
+      ))
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::FORBIDDEN;
+    assert_eq!(actual, expected);
+
+    // Cats from the database:
+    let count = Cat::count(doc! {}).await.unwrap();
+    assert_eq!(count, 1, "No cats should be removed");
+  });
+}
+
+#[test]
+fn update_cat_owner_route() {
+  use_app(async move {
+    let admin = create_admin_user("admin@test.com").await.unwrap();
+    let token = create_user_token(admin).await.unwrap();
+
+    let nico = create_user("nico@test.com").await.unwrap();
+    let nahuel = create_user("nahuel@test.com").await.unwrap();
+
+    let tigrin = Cat::new(nico.id.unwrap(), "Tigrin".to_owned());
+    let tigrin = Cat::create(tigrin).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .put(format!(
+        "http://localhost:8088/v1/admin/cats/{}/owner",
+        tigrin.id.unwrap()
+      ))
+      .header("Authorization", format!("Bearer {}", token))
+      .json(&json!({ "user": nahuel.id.unwrap().to_hex() }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<PublicCat>().await.unwrap();
+    assert_eq!(
+      body.user,
+      nahuel.id.unwrap(),
+      "Cat should belong to new owner"
+    );
+
+    // Previous owner cats:
+    let nico_token = create_user_token(nico).await.unwrap();
+    let res = client
+      .get("http://localhost:8088/v1/cats")
+      .header("Authorization", format!("Bearer {}", nico_token))
+      .send()
+      .await
+      .unwrap();
+    let body = res.json::<Vec<PublicCat>>().await.unwrap();
+    assert!(body.is_empty(), "Previous owner should not list the cat");
+
+    // New owner cats:
+    let nahuel_token = create_user_token(nahuel).await.unwrap();
+    let res = client
+      .get("http://localhost:8088/v1/cats")
+      .header("Authorization", format!("Bearer {}", nahuel_token))
+      .send()
+      .await
+      .unwrap();
+    let body = res.json::<Vec<PublicCat>>().await.unwrap();
+    assert_eq!(body.len(), 1, "New owner should list the cat");
+    assert_eq!(body.first().unwrap().name, "Tigrin");
+  });
+}
+
+#[test]
+fn remove_cat_by_id_route() {
+  use_app(async move {
+    let admin = create_admin_user("admin@test.com").await.unwrap();
+    let token = create_user_token(admin).await.unwrap();
+    let nico = create_user("nico@test.com").await.unwrap();
+
+    let tigrin = Cat::new(nico.id.unwrap(), "Tigrin".to_owned());
+    let tigrin = Cat::create(tigrin).await.unwrap();
+    Cat::soft_delete(doc! { "_id": tigrin.id.unwrap() })
+      .await
+      .unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .delete(format!(
+        "http://localhost:8088/v1/admin/cats/{}",
+        tigrin.id.unwrap()
+      ))
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::NO_CONTENT;
+    assert_eq!(actual, expected);
+
+    // Removed cats:
+    let query = doc! { "deleted_at": { "$ne": null } };
+    let removed_count = Cat::count(query).await.unwrap();
+    assert_eq!(removed_count, 0, "Removed cat should be deleted for good");
+
+    // Unknown cat:
+    let res = client
+      .delete(format!(
+        "http://localhost:8088/v1/admin/cats/{}",
+        tigrin.id.unwrap()
+      ))
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+  });
+}
+
+#[test]
+fn update_cat_owner_as_non_admin_route() {
+  use_app(async move {
+    let nico = create_user("nico@test.com").await.unwrap();
+    let nahuel = create_user("nahuel@test.com").await.unwrap();
+    let token = create_user_token(nahuel.clone()).await.unwrap();
+
+    let tigrin = Cat::new(nico.id.unwrap(), "Tigrin".to_owned());
+    let tigrin = Cat::create(tigrin).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .put(format!(
+        "http://localhost:8088/v1/admin/cats/{}/owner",
+        tigrin.id.unwrap()
+      ))
+      .header("Authorization", format!("Bearer {}", token))
+      .json(&json!({ "user": nahuel.id.unwrap().to_hex() }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::FORBIDDEN;
+    assert_eq!(actual, expected);
+
+    // Cat from the database:
+    let cat = Cat::find_by_id(&tigrin.id.unwrap()).await.unwrap().unwrap();
+    assert_eq!(cat.user, nico.id.unwrap(), "Cat owner should not change");
+  });
+}
+
+#[test]
+fn query_users_route() {
+  use_app(async move {
+    let admin = create_admin_user("admin@test.com").await.unwrap();
+    let token = create_user_token(admin).await.unwrap();
+    create_user("nico@test.com").await.unwrap();
+    create_user("nahuel@test.com").await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .get("http://localhost:8088/v1/admin/users?role=member")
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Headers:
+    assert_eq!(res.headers().get("x-pagination-count").unwrap(), "2");
+
+    // Body:
+    let body = res.json::<Vec<PublicUser>>().await.unwrap();
+    assert_eq!(body.len(), 2);
+    assert!(body.iter().all(|user| user.role == Role::Member));
+  });
+}
+
+#[test]
+fn query_users_route_links_pages() {
+  use_app(async move {
+    let admin = create_admin_user("admin@test.com").await.unwrap();
+    let token = create_user_token(admin).await.unwrap();
+    create_user("nico@test.com").await.unwrap();
+    create_user("nahuel@test.com").await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .get("http://localhost:8088/v1/admin/users?limit=1&offset=1")
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Headers:
+    let base = "/v1/admin/users?limit=1";
+    let expected = format!(
+      "<{0}&offset=0>; rel=\"first\", <{0}&offset=0>; rel=\"prev\", \
+       <{0}&offset=2>; rel=\"next\", <{0}&offset=2>; rel=\"last\"",
+      base
+    );
+    assert_eq!(res.headers()["link"], expected.as_str());
+  });
+}
+
+#[test]
+fn query_users_as_non_admin_route() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .get("http://localhost:8088/v1/admin/users")
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::FORBIDDEN;
+    assert_eq!(actual, expected);
+  });
+}
+
+#[test]
+fn update_user_disabled_route() {
+  use_app(async move {
+    let admin = create_admin_user("admin@test.com").await.unwrap();
+    let token = create_user_token(admin).await.unwrap();
+    let nico = create_user("nico@test.com").await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .put(format!(
+        "http://localhost:8088/v1/admin/users/{}/disabled",
+        nico.id.unwrap()
+      ))
+      .header("Authorization", format!("Bearer {}", token))
+      .json(&json!({ "disabled": true }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<PublicUser>().await.unwrap();
+    assert!(body.locked_at.is_some());
+
+    let res = client
+      .post("http://localhost:8088/v1/users/authenticate")
+      .json(&json!({ "email": "nico@test.com", "password": "Password1" }))
+      .send()
+      .await
+      .unwrap();
+    assert_eq!(
+      res.status(),
+      StatusCode::LOCKED,
+      "Disabled users can't authenticate"
+    );
+  });
+}
+
+#[test]
+fn update_user_role_route() {
+  use_app(async move {
+    let admin = create_admin_user("admin@test.com").await.unwrap();
+    let token = create_user_token(admin.clone()).await.unwrap();
+    let nico = create_user("nico@test.com").await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .put(format!(
+        "http://localhost:8088/v1/admin/users/{}/role",
+        nico.id.unwrap()
+      ))
+      .header("Authorization", format!("Bearer {}", token))
+      .json(&json!({ "role": "readonly" }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<PublicUser>().await.unwrap();
+    assert_eq!(body.role, Role::ReadOnly);
+
+    let res = client
+      .put(format!(
+        "http://localhost:8088/v1/admin/users/{}/role",
+        admin.id.unwrap()
+      ))
+      .header("Authorization", format!("Bearer {}", token))
+      .json(&json!({ "role": "member" }))
+      .send()
+      .await
+      .unwrap();
+    assert_eq!(
+      res.status(),
+      StatusCode::BAD_REQUEST,
+      "Admins can't demote themselves"
+    );
+  });
+}
+
+#[test]
+fn remove_user_by_id_route() {
+  use_app(async move {
+    let admin = create_admin_user("admin@test.com").await.unwrap();
+    let token = create_user_token(admin).await.unwrap();
+    let nico = create_user("nico@test.com").await.unwrap();
+    let nahuel = create_user("nahuel@test.com").await.unwrap();
+
+    Cat::create(Cat::new(nico.id.unwrap(), "Tigrin".to_owned()))
+      .await
+      .unwrap();
+    Cat::create(Cat::new(nahuel.id.unwrap(), "Cholin".to_owned()))
+      .await
+      .unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .delete(format!(
+        "http://localhost:8088/v1/admin/users/{}",
+        nico.id.unwrap()
+      ))
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::NO_CONTENT;
+    assert_eq!(actual, expected);
+
+    // Users and cats from the database:
+    assert!(User::find_by_id(&nico.id.unwrap()).await.unwrap().is_none());
+    let count = Cat::count(doc! { "user": nico.id.unwrap() }).await.unwrap();
+    assert_eq!(count, 0, "User data should be removed");
+    let count = Cat::count(doc! { "user": nahuel.id.unwrap() })
+      .await
+      .unwrap();
+    assert_eq!(count, 1, "Other users data should be kept");
+  });
+}
+
+#[test]
+fn get_user_usage_route() {
+  use_app(async move {
+    let admin = create_admin_user("admin@test.com").await.unwrap();
+    let token = create_user_token(admin).await.unwrap();
+    let nico = create_user("nico@test.com").await.unwrap();
+
+    Cat::create(Cat::new(nico.id.unwrap(), "Tigrin".to_owned()))
+      .await
+      .unwrap();
+    Watchlist::create(Watchlist::new(nico.id.unwrap(), "Exchanges".to_owned()))
+      .await
+      .unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .get(format!(
+        "http://localhost:8088/v1/admin/users/{}/usage",
+        nico.id.unwrap()
+      ))
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<UserUsage>().await.unwrap();
+    assert_eq!(body.cats, 1);
+    assert_eq!(body.watchlists, 1);
+    assert_eq!(body.api_keys, 0);
+    assert!(body.api_key_last_used_at.is_none());
+  });
+}
+
+#[test]
+fn query_jobs_route() {
+  use_app(async move {
+    let admin = create_admin_user("admin@test.com").await.unwrap();
+    let token = create_user_token(admin).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .get("http://localhost:8088/v1/admin/jobs")
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<Vec<JobStatus>>().await.unwrap();
+    let names = body.iter().map(|job| job.name.as_str()).collect::<Vec<_>>();
+    assert_eq!(
+      names,
+      vec![
+        "watchlist_refresh",
+        "cache_warmup",
+        "label_dataset_sync",
+        "price_alerts",
+        "usage_rollup",
+        "retention_cleanup"
+      ]
+    );
+    let warmup = &body[1];
+    assert_eq!(warmup.schedule.as_deref(), Some("0 */10 * * * *"));
+    // The scheduler is disabled in the tests.
+    assert!(warmup.next_run_at.is_none());
+    assert!(warmup.last_run.is_none());
+
+    // Non admin:
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user).await.unwrap();
+    let res = client
+      .get("http://localhost:8088/v1/admin/jobs")
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+  });
+}
+
+#[test]
+fn update_feature_flag_route() {
+  use_app(async move {
+    let admin = create_admin_user("admin@test.com").await.unwrap();
+    let token = create_user_token(admin).await.unwrap();
+    let nico = create_user("nico@test.com").await.unwrap();
+    let tester = create_user("tester@test.com").await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .put("http://localhost:8088/v1/admin/feature-flags/beta_search")
+      .header("Authorization", format!("Bearer {}", token))
+      .json(&json!({
+        "enabled": true,
+        "rollout_percent": 0,
+        "users": [tester.id.unwrap().to_hex()]
+      }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<PublicFeatureFlag>().await.unwrap();
+    assert_eq!(body.name, "beta_search");
+    assert!(body.enabled);
+    assert_eq!(body.rollout_percent, 0);
+    assert_eq!(body.users, vec![tester.id.unwrap().to_hex()]);
+
+    // Only enabled for the listed users:
+    let flags = FeatureFlags::new(&SETTINGS.feature_flags, &SETTINGS.cache);
+    let enabled = flags
+      .is_enabled("beta_search", tester.id.as_ref())
+      .await
+      .unwrap();
+    assert!(enabled);
+    let enabled = flags
+      .is_enabled("beta_search", nico.id.as_ref())
+      .await
+      .unwrap();
+    assert!(!enabled);
+
+    // Listed along the configured defaults:
+    let res = client
+      .get("http://localhost:8088/v1/admin/feature-flags")
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = res.json::<Vec<PublicFeatureFlag>>().await.unwrap();
+    let names: Vec<&str> = body.iter().map(|flag| flag.name.as_str()).collect();
+    assert_eq!(names, vec!["address_graph", "beta_search", "risk_score"]);
+
+    // Invalid rollout:
+    let res = client
+      .put("http://localhost:8088/v1/admin/feature-flags/beta_search")
+      .header("Authorization", format!("Bearer {}", token))
+      .json(&json!({ "enabled": true, "rollout_percent": 101 }))
+      .send()
+      .await
+      .unwrap();
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+  });
+}
+
+#[test]
+fn update_maintenance_route() {
+  use_app(async move {
+    let admin = create_admin_user("admin@test.com").await.unwrap();
+    let token = create_user_token(admin).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let set_maintenance = |enabled: bool| {
+      client
+        .put("http://localhost:8088/v1/admin/maintenance")
+        .header("Authorization", format!("Bearer {}", token))
+        .json(&json!({ "enabled": enabled, "retry_after_secs": 120 }))
+        .send()
+    };
+
+    let res = set_maintenance(true).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    // Responses are read before leaving the maintenance, so a failure doesn't
+    // leave the API down for the next tests.
+    let cats = client
+      .get("http://localhost:8088/v1/cats")
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+    let status = client
+      .get("http://localhost:8088/status")
+      .send()
+      .await
+      .unwrap();
+    let maintenance = client
+      .get("http://localhost:8088/v1/admin/maintenance")
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    let res = set_maintenance(false).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    // Status code:
+    let status_code = cats.status();
+    let actual = status_code;
+    let expected = StatusCode::SERVICE_UNAVAILABLE;
+    assert_eq!(actual, expected);
+
+    // Body:
+    assert_eq!(cats.headers()["retry-after"], "120");
+    let body = cats.json::<serde_json::Value>().await.unwrap();
+    assert_eq!(body["code"], "maintenance");
+    assert_eq!(body["details"], json!({ "retry_after_secs": 120 }));
+
+    // Health and admin routes are still served:
+    assert_eq!(status.status(), StatusCode::OK);
+    let body = maintenance.json::<serde_json::Value>().await.unwrap();
+    assert_eq!(body, json!({ "enabled": true, "retry_after_secs": 120 }));
+
+    let res = client
+      .get("http://localhost:8088/v1/cats")
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+  });
+}
+
+#[test]
+fn reload_labels_without_dataset_route() {
+  use_app(async move {
+    let admin = create_admin_user("admin@test.com").await.unwrap();
+    let token = create_user_token(admin).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .post("http://localhost:8088/v1/admin/labels/reload")
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::NOT_FOUND;
+    assert_eq!(actual, expected);
+  });
+}
+
+#[test]
+fn query_stats_route() {
+  use_app(async move {
+    let admin = create_admin_user("admin@test.com").await.unwrap();
+    let token = create_user_token(admin).await.unwrap();
+    let nico = create_user("nico@test.com").await.unwrap();
+    let john = create_user("john@test.com").await.unwrap();
+    Cat::create(Cat::new(nico.id.unwrap(), "Tigrin".to_owned()))
+      .await
+      .unwrap();
+    Cat::create(Cat::new(john.id.unwrap(), "Cholin".to_owned()))
+      .await
+      .unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .get("http://localhost:8088/v1/admin/stats")
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<Stats>().await.unwrap();
+    assert_eq!(body.days, 30);
+    assert_eq!(body.cats_per_day.len(), 1);
+    assert_eq!(body.cats_per_day[0].count, 2);
+  });
+}
+
+#[test]
+fn query_retention_route() {
+  use_app(async move {
+    let admin = create_admin_user("admin@test.com").await.unwrap();
+    let token = create_user_token(admin).await.unwrap();
+    create_webhook_delivery(DeliveryStatus::Delivered, 60).await;
+    create_webhook_delivery(DeliveryStatus::Pending, 60).await;
+    create_webhook_delivery(DeliveryStatus::Delivered, 1).await;
+
+    let client = reqwest::Client::new();
+    let res = client
+      .get("http://localhost:8088/v1/admin/retention")
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<Vec<RetentionPolicy>>().await.unwrap();
+    let deliveries = body
+      .iter()
+      .find(|policy| policy.collection == "webhook_deliveries")
+      .unwrap();
+    assert_eq!(
+      deliveries.retention_days,
+      SETTINGS.retention.webhook_deliveries_days
+    );
+    assert_eq!(deliveries.expired, 1, "Pending deliveries are kept");
+  });
+}
+
+#[test]
+fn clean_up_retention_route() {
+  use_app(async move {
+    let admin = create_admin_user("admin@test.com").await.unwrap();
+    let token = create_user_token(admin).await.unwrap();
+    create_webhook_delivery(DeliveryStatus::Delivered, 60).await;
+    create_webhook_delivery(DeliveryStatus::Pending, 60).await;
+    create_webhook_delivery(DeliveryStatus::Delivered, 1).await;
+
+    let client = reqwest::Client::new();
+    let res = client
+      .post("http://localhost:8088/v1/admin/retention/cleanup")
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<Vec<Cleanup>>().await.unwrap();
+    let deliveries = body
+      .iter()
+      .find(|cleanup| cleanup.collection == "webhook_deliveries")
+      .unwrap();
+    assert_eq!(deliveries.deleted, 1);
+
+    let remaining = WebhookDelivery::count(doc! {}).await.unwrap();
+    assert_eq!(remaining, 2);
+  });
+}
@@ -0,0 +1,248 @@
+use bson::oid::ObjectId;
+use reqwest;
+use reqwest::StatusCode;
+use serde_json::json;
+
+use crate::models::alert_event::{AlertEvent, PublicAlertEvent};
+use crate::models::alert_rule::{AlertCondition, PublicAlertRule};
+use crate::tests::setup::use_app;
+use crate::tests::utils::create_user;
+use crate::tests::utils::create_user_token;
+use crate::utils::address::to_checksum_address;
+use crate::utils::models::ModelExt;
+
+const ADDRESS: &str = "0x00000000000000000000000000000000000000a1";
+
+#[test]
+fn post_alert_rule_route() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .post("http://localhost:8088/v1/alerts/rules")
+      .header("Authorization", format!("Bearer {}", token))
+      .json(&json!({
+        "condition": "flagged_as_contract",
+        "address": ADDRESS.replace('a', "A")
+      }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::CREATED;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<PublicAlertRule>().await.unwrap();
+    assert_eq!(body.condition, AlertCondition::FlaggedAsContract);
+    assert_eq!(body.address, Some(to_checksum_address(ADDRESS)));
+
+    // Rules of the user:
+    let res = client
+      .get("http://localhost:8088/v1/alerts/rules")
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+    let rules = res.json::<Vec<PublicAlertRule>>().await.unwrap();
+    assert_eq!(rules.len(), 1);
+  });
+}
+
+#[test]
+fn get_alerts_route() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let user_id = user.id.unwrap();
+    let token = create_user_token(user).await.unwrap();
+
+    for user in [user_id, ObjectId::new()] {
+      let event = AlertEvent::new(
+        user,
+        ObjectId::new(),
+        AlertCondition::EntityAttached,
+        ADDRESS.to_owned(),
+        "ethereum".to_owned(),
+        "Arkham entity Binance attached".to_owned(),
+      );
+      AlertEvent::create(event).await.unwrap();
+    }
+
+    let client = reqwest::Client::new();
+    let res = client
+      .get("http://localhost:8088/v1/alerts")
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Response pagination headers:
+    let headers = res.headers();
+    assert_eq!(headers.get("X-Pagination-Count").unwrap(), "1");
+
+    // Body:
+    let body = res.json::<Vec<PublicAlertEvent>>().await.unwrap();
+    assert_eq!(body.len(), 1, "Only the alerts of the user");
+    assert_eq!(
+      body.first().unwrap().address,
+      Some(to_checksum_address(ADDRESS))
+    );
+  });
+}
+
+#[test]
+fn post_whale_transfer_alert_rule_route() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .post("http://localhost:8088/v1/alerts/rules")
+      .header("Authorization", format!("Bearer {}", token))
+      .json(&json!({ "condition": "whale_transfer", "min_usd": 1000000 }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::CREATED;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<PublicAlertRule>().await.unwrap();
+    assert_eq!(body.condition, AlertCondition::WhaleTransfer);
+    assert_eq!(body.min_usd, Some(1000000.0));
+  });
+}
+
+#[test]
+fn post_alert_rule_route_with_invalid_threshold() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let payloads = [
+      json!({ "condition": "whale_transfer" }),
+      json!({ "condition": "whale_transfer", "min_usd": -5 }),
+      json!({ "condition": "flagged_as_contract", "min_usd": 1000 }),
+    ];
+    for payload in payloads {
+      let res = client
+        .post("http://localhost:8088/v1/alerts/rules")
+        .header("Authorization", format!("Bearer {}", token))
+        .json(&payload)
+        .send()
+        .await
+        .unwrap();
+
+      // Status code:
+      let status_code = res.status();
+      let actual = status_code;
+      let expected = StatusCode::UNPROCESSABLE_ENTITY;
+      assert_eq!(actual, expected, "{}", payload);
+    }
+  });
+}
+
+#[test]
+fn post_price_alert_rule_route() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .post("http://localhost:8088/v1/alerts/rules")
+      .header("Authorization", format!("Bearer {}", token))
+      .json(&json!({
+        "condition": "price_change",
+        "token": "Ethereum",
+        "change_percent": -10,
+        "window_secs": 3600
+      }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::CREATED;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<PublicAlertRule>().await.unwrap();
+    assert_eq!(body.condition, AlertCondition::PriceChange);
+    assert_eq!(body.token.as_deref(), Some("ethereum"));
+    assert_eq!(body.change_percent, Some(-10.0));
+    assert_eq!(body.window_secs, Some(3600));
+    assert_eq!(body.address, None);
+    assert_eq!(body.last_triggered_at, None);
+  });
+}
+
+#[test]
+fn post_price_alert_rule_route_with_invalid_fields() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let payloads = [
+      json!({ "condition": "price_above", "price_usd": 4000 }),
+      json!({ "condition": "price_above", "token": "ethereum" }),
+      json!({ "condition": "price_below", "token": "ethereum", "price_usd": 0 }),
+      json!({ "condition": "price_above", "token": "ethereum", "price_usd": 4000, "address": ADDRESS }),
+      json!({ "condition": "price_above", "token": "ethereum", "price_usd": 4000, "window_secs": 3600 }),
+      json!({ "condition": "price_change", "token": "ethereum", "window_secs": 3600 }),
+      json!({ "condition": "price_change", "token": "ethereum", "change_percent": -100, "window_secs": 3600 }),
+      json!({ "condition": "price_change", "token": "ethereum", "change_percent": 5, "window_secs": 10 }),
+      json!({ "condition": "price_change", "token": "ethereum", "change_percent": 5, "window_secs": 172800 }),
+      json!({ "condition": "flagged_as_contract", "token": "ethereum" }),
+    ];
+    for payload in payloads {
+      let res = client
+        .post("http://localhost:8088/v1/alerts/rules")
+        .header("Authorization", format!("Bearer {}", token))
+        .json(&payload)
+        .send()
+        .await
+        .unwrap();
+
+      // Status code:
+      let status_code = res.status();
+      let actual = status_code;
+      let expected = StatusCode::UNPROCESSABLE_ENTITY;
+      assert_eq!(actual, expected, "{}", payload);
+    }
+
+    let res = client
+      .post("http://localhost:8088/v1/alerts/rules")
+      .header("Authorization", format!("Bearer {}", token))
+      .json(&json!({ "condition": "price_above", "token": "not a token", "price_usd": 1 }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::BAD_REQUEST;
+    assert_eq!(actual, expected);
+  });
+}
@@ -0,0 +1,142 @@
+use reqwest;
+use reqwest::StatusCode;
+use serde_json::json;
+
+use crate::models::api_key::{ApiKey, ApiKeyScope, PublicApiKey};
+use crate::models::user::User;
+use crate::routes::api_key::CreatedApiKey;
+use crate::tests::setup::use_app;
+use crate::tests::utils::create_user;
+use crate::tests::utils::create_user_token;
+use crate::utils::models::ModelExt;
+
+async fn create_api_key(user: &User, scopes: Vec<ApiKeyScope>) -> (ApiKey, String) {
+  let (api_key, key) = ApiKey::generate(user.id.unwrap(), "Bot".to_owned(), scopes);
+  let api_key = ApiKey::create(api_key).await.unwrap();
+  (api_key, key)
+}
+
+#[test]
+fn post_api_key_route() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .post("http://localhost:8088/v1/api-keys")
+      .header("Authorization", format!("Bearer {}", token))
+      .json(&json!({ "name": "Trading bot", "scopes": ["read", "write"] }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::CREATED;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<CreatedApiKey>().await.unwrap();
+    assert_eq!(body.api_key.name, "Trading bot");
+    assert_eq!(
+      body.api_key.scopes,
+      vec![ApiKeyScope::Read, ApiKeyScope::Write]
+    );
+    assert!(body
+      .key
+      .starts_with(&format!("dgn_{}_", body.api_key.prefix)));
+  });
+}
+
+#[test]
+fn get_api_keys_route_with_api_key() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let (_, key) = create_api_key(&user, vec![ApiKeyScope::Read]).await;
+
+    let client = reqwest::Client::new();
+    let res = client
+      .get("http://localhost:8088/v1/api-keys")
+      .header("X-Api-Key", &key)
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<Vec<PublicApiKey>>().await.unwrap();
+    assert_eq!(body.len(), 1);
+    assert!(
+      body[0].last_used_at.is_some(),
+      "Key usage should be recorded"
+    );
+  });
+}
+
+#[test]
+fn post_route_with_read_only_api_key() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let (_, key) = create_api_key(&user, vec![ApiKeyScope::Read]).await;
+
+    let client = reqwest::Client::new();
+    let res = client
+      .post("http://localhost:8088/v1/watchlists")
+      .header("X-Api-Key", &key)
+      .json(&json!({ "name": "Exchanges" }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::FORBIDDEN;
+    assert_eq!(actual, expected);
+  });
+}
+
+#[test]
+fn delete_api_key_route() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let (api_key, key) = create_api_key(&user, vec![ApiKeyScope::Read]).await;
+    let token = create_user_token(user).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .delete(format!(
+        "http://localhost:8088/v1/api-keys/{}",
+        api_key.id.unwrap()
+      ))
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::NO_CONTENT;
+    assert_eq!(actual, expected);
+
+    let res = client
+      .get("http://localhost:8088/v1/api-keys")
+      .header("X-Api-Key", &key)
+      .send()
+      .await
+      .unwrap();
+    assert_eq!(
+      res.status(),
+      StatusCode::UNAUTHORIZED,
+      "Revoked keys should be rejected"
+    );
+  });
+}
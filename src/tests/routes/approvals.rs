@@ -0,0 +1,107 @@
+use reqwest;
+use reqwest::StatusCode;
+
+use crate::routes::approvals::ApprovalReport;
+use crate::services::approvals::{Severity, TokenStandard};
+use crate::tests::mock_arkham::{FAILING_ADDRESS, RISKY_SPENDER};
+use crate::tests::setup::use_app;
+use crate::utils::address::to_checksum_address;
+
+const ADDRESS: &str = "0x00000000000000000000000000000000000000a1";
+
+#[test]
+fn get_approvals_route() {
+  use_app(async move {
+    let res = reqwest::get(format!("http://localhost:8088/v1/approvals/{}", ADDRESS))
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<ApprovalReport>().await.unwrap();
+    assert_eq!(body.chain, "ethereum");
+    assert_eq!(body.address, to_checksum_address(ADDRESS));
+    assert_eq!(body.unchecked, 0);
+
+    let severities = body
+      .approvals
+      .iter()
+      .map(|approval| approval.severity)
+      .collect::<Vec<Severity>>();
+    assert_eq!(
+      severities,
+      vec![
+        Severity::High,
+        Severity::Medium,
+        Severity::Medium,
+        Severity::Low
+      ],
+      "Revoked and transferred approvals should be left out"
+    );
+
+    let risky = &body.approvals[0];
+    assert_eq!(risky.spender, to_checksum_address(RISKY_SPENDER));
+    assert!(risky.risky);
+    assert!(!risky.unlimited);
+    assert_eq!(risky.allowance.as_deref(), Some("1000"));
+    assert_eq!(risky.spender_name.as_deref(), Some("Degen"));
+
+    let unlimited = &body.approvals[1];
+    assert_eq!(unlimited.standard, TokenStandard::Erc20);
+    assert!(unlimited.unlimited);
+    assert_eq!(
+      unlimited.allowance.as_deref(),
+      Some("115792089237316195423570985008687907853269984665640564039457584007913129639935")
+    );
+
+    let operator = &body.approvals[2];
+    assert_eq!(operator.standard, TokenStandard::Erc721);
+    assert!(operator.unlimited);
+    assert_eq!(operator.token_id, None);
+
+    let single = &body.approvals[3];
+    assert_eq!(single.standard, TokenStandard::Erc721);
+    assert_eq!(single.token_id.as_deref(), Some("7"));
+    assert!(!single.unlimited);
+  });
+}
+
+#[test]
+fn get_approvals_route_with_unsupported_chain() {
+  use_app(async move {
+    let url = format!(
+      "http://localhost:8088/v1/approvals/{}?chain=solana",
+      ADDRESS
+    );
+    let res = reqwest::get(url).await.unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::BAD_REQUEST;
+    assert_eq!(actual, expected);
+  });
+}
+
+#[test]
+fn get_approvals_route_with_failing_rpc() {
+  use_app(async move {
+    let res = reqwest::get(format!(
+      "http://localhost:8088/v1/approvals/{}",
+      FAILING_ADDRESS
+    ))
+    .await
+    .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::SERVICE_UNAVAILABLE;
+    assert_eq!(actual, expected);
+  });
+}
@@ -0,0 +1,1346 @@
+use bson::doc;
+use reqwest;
+use reqwest::StatusCode;
+use serde_json::Value as Json;
+use std::collections::HashMap;
+
+use crate::models::address_label::AddressLabel;
+use crate::models::usage::Usage;
+use crate::routes::arkham::parse_arkham_response;
+use crate::routes::arkham::AddressLookup;
+use crate::routes::arkham::ArkhamCacheStats;
+use crate::routes::arkham::ArkhamEntityDetail;
+use crate::routes::arkham::ArkhamPortfolio;
+use crate::routes::arkham::ArkhamResponse;
+use crate::routes::arkham::ArkhamTransfers;
+use crate::routes::arkham::BatchEntry;
+use crate::services::graph::{AddressGraph, NodeKind, Relation};
+use crate::settings::SETTINGS;
+use crate::tests::mock_arkham::address_payload;
+use crate::tests::mock_arkham::BITCOIN_ADDRESS;
+use crate::tests::mock_arkham::ENS_ADDRESS;
+use crate::tests::mock_arkham::ENS_NAME;
+use crate::tests::mock_arkham::ENTITY_ID;
+use crate::tests::mock_arkham::FAILING_ADDRESS;
+use crate::tests::mock_arkham::FLAKY_ADDRESS;
+use crate::tests::mock_arkham::HTML_ADDRESS;
+use crate::tests::mock_arkham::SLOW_ADDRESS;
+use crate::tests::mock_arkham::SOLANA_ADDRESS;
+use crate::tests::mock_arkham::SPOOFED_ENS_ADDRESS;
+use crate::tests::setup::use_app;
+use crate::tests::setup::use_fixture_app;
+use crate::tests::setup::FIXTURE_API_PORT;
+use crate::tests::utils::create_authenticated_client;
+use crate::tests::utils::create_user;
+use crate::tests::utils::create_user_token;
+use crate::utils::address::to_checksum_address;
+use crate::utils::models::ModelExt;
+
+const ADDRESS: &str = "0x00000000000000000000000000000000000000a1";
+
+// Only address and entity known by the intelligence fixtures, see
+// src/tests/fixtures/intelligence.json.
+const FIXTURE_ADDRESS: &str = "0x00000000000000000000000000000000000000b1";
+const FIXTURE_ENTITY_ID: &str = "fixture-fund";
+
+#[test]
+fn post_arkham_batch_route() {
+  use_app(async move {
+    let client = reqwest::Client::new();
+    let res = client
+      .post("http://localhost:8088/v1/arkham/batch")
+      .json(&vec![ADDRESS, FAILING_ADDRESS])
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<HashMap<String, BatchEntry>>().await.unwrap();
+    assert_eq!(body.len(), 2, "Should return an entry per address");
+
+    let entry = body.get(ADDRESS).unwrap();
+    assert!(entry.data.is_some(), "Lookup should succeed");
+    assert!(entry.error.is_none());
+
+    let entry = body.get(FAILING_ADDRESS).unwrap();
+    assert!(entry.data.is_none(), "Lookup should fail");
+    assert_eq!(entry.error.as_ref().unwrap().code, "upstream_unavailable");
+  });
+}
+
+#[test]
+fn post_arkham_batch_route_with_too_many_addresses() {
+  use_app(async move {
+    let addresses = (0..SETTINGS.arkham.batch_max_addresses + 1)
+      .map(|index| format!("0x{:040x}", index))
+      .collect::<Vec<String>>();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .post("http://localhost:8088/v1/arkham/batch")
+      .json(&addresses)
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::BAD_REQUEST;
+    assert_eq!(actual, expected);
+  });
+}
+
+#[test]
+fn post_arkham_batch_route_with_invalid_json() {
+  use_app(async move {
+    let client = reqwest::Client::new();
+    let res = client
+      .post("http://localhost:8088/v1/arkham/batch")
+      .header("Content-Type", "application/json")
+      .body(r#"["0x"#)
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::BAD_REQUEST;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<Json>().await.unwrap();
+    assert_eq!(body["code"], "malformed_payload");
+    assert!(body["message"].is_string());
+  });
+}
+
+#[test]
+fn post_arkham_batch_route_with_invalid_addresses_type() {
+  use_app(async move {
+    let client = reqwest::Client::new();
+    let res = client
+      .post("http://localhost:8088/v1/arkham/batch")
+      .json(&serde_json::json!({ "addresses": [] }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::UNPROCESSABLE_ENTITY;
+    assert_eq!(actual, expected);
+  });
+}
+
+#[test]
+fn post_arkham_batch_route_with_max_addresses() {
+  use_app(async move {
+    let addresses = (0..SETTINGS.arkham.batch_max_addresses)
+      .map(|index| format!("0x{:040x}", index + 0x100))
+      .collect::<Vec<String>>();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .post("http://localhost:8088/v1/arkham/batch")
+      .json(&addresses)
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<HashMap<String, BatchEntry>>().await.unwrap();
+    assert_eq!(
+      body.len(),
+      addresses.len(),
+      "Should return an entry per address"
+    );
+    assert!(
+      body.values().all(|entry| entry.data.is_some()),
+      "Every lookup should succeed"
+    );
+  });
+}
+
+#[test]
+fn get_arkham_route_normalizes_address() {
+  use_app(async move {
+    let address = "0x52908400098527886E0F7030069857D2E4169EE7";
+
+    let res = reqwest::get(format!("http://localhost:8088/v1/arkham/{}", address))
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<Json>().await.unwrap();
+    assert_eq!(
+      body["ethereum"]["address"], "0x52908400098527886e0f7030069857d2e4169ee7",
+      "Upstream should be queried with the lowercase address"
+    );
+  });
+}
+
+#[test]
+fn get_arkham_route_with_solana_address() {
+  use_app(async move {
+    let res = reqwest::get(format!(
+      "http://localhost:8088/v1/arkham/{}",
+      SOLANA_ADDRESS
+    ))
+    .await
+    .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<Json>().await.unwrap();
+    assert_eq!(body["address"], SOLANA_ADDRESS, "Case should be kept");
+    assert_eq!(body["solana"]["address"], SOLANA_ADDRESS);
+    assert_eq!(body["solana"]["arkhamLabel"]["chainType"], "solana");
+    assert_eq!(body["chains"], serde_json::json!(["solana"]));
+    assert!(body["ethereum"].is_null());
+    assert!(body["ens_name"].is_null());
+  });
+}
+
+#[test]
+fn get_arkham_route_with_bitcoin_address() {
+  use_app(async move {
+    let res = reqwest::get(format!(
+      "http://localhost:8088/v1/arkham/{}",
+      BITCOIN_ADDRESS.to_uppercase()
+    ))
+    .await
+    .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<Json>().await.unwrap();
+    assert_eq!(
+      body["address"], BITCOIN_ADDRESS,
+      "Bech32 should be lowercased"
+    );
+    assert_eq!(body["bitcoin"]["arkhamEntity"]["name"], "Degen");
+    assert!(body["ethereum"].is_null());
+  });
+}
+
+#[test]
+fn get_arkham_route_with_mismatched_chain() {
+  use_app(async move {
+    let res = reqwest::get(format!(
+      "http://localhost:8088/v1/arkham/{}?chain=solana",
+      ENS_ADDRESS
+    ))
+    .await
+    .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::BAD_REQUEST;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<Json>().await.unwrap();
+    assert_eq!(body["code"], "invalid_address");
+  });
+}
+
+#[test]
+fn get_arkham_route_with_ens_name() {
+  use_app(async move {
+    let res = reqwest::get("http://localhost:8088/v1/arkham/Degen.ETH")
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<AddressLookup>().await.unwrap();
+    assert_eq!(body.address, to_checksum_address(ENS_ADDRESS));
+    assert_eq!(body.ens_name.as_deref(), Some(ENS_NAME));
+  });
+}
+
+#[test]
+fn get_arkham_route_with_unknown_ens_name() {
+  use_app(async move {
+    let res = reqwest::get("http://localhost:8088/v1/arkham/unknown.eth")
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::NOT_FOUND;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<Json>().await.unwrap();
+    assert_eq!(body["code"], "not_found");
+  });
+}
+
+#[test]
+fn get_arkham_route_attaches_primary_ens_name() {
+  use_app(async move {
+    let url = |address: &str| format!("http://localhost:8088/v1/arkham/{}", address);
+
+    let res = reqwest::get(url(ENS_ADDRESS)).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = res.json::<AddressLookup>().await.unwrap();
+    assert_eq!(body.ens_name.as_deref(), Some(ENS_NAME));
+
+    // The reverse record doesn't match the name's address:
+    let res = reqwest::get(url(SPOOFED_ENS_ADDRESS)).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = res.json::<AddressLookup>().await.unwrap();
+    assert_eq!(body.ens_name, None, "Unverified names should be left out");
+  });
+}
+
+#[test]
+fn get_arkham_route_with_user_note() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user).await.unwrap();
+    let url = format!("http://localhost:8088/v1/arkham/{}", ENS_ADDRESS);
+
+    let client = reqwest::Client::new();
+    client
+      .put(format!(
+        "http://localhost:8088/v1/address-notes/{}",
+        ENS_ADDRESS
+      ))
+      .header("Authorization", format!("Bearer {}", token))
+      .json(&serde_json::json!({ "name": "Degen", "note": "Met at ETHDenver" }))
+      .send()
+      .await
+      .unwrap();
+
+    let res = client
+      .get(&url)
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Headers:
+    let cache_control = res
+      .headers()
+      .get("Cache-Control")
+      .unwrap()
+      .to_str()
+      .unwrap();
+    assert!(
+      cache_control.starts_with("private"),
+      "Notes should not be cached publicly"
+    );
+
+    // Body:
+    let body = res.json::<Json>().await.unwrap();
+    assert_eq!(body["user_note"]["name"], "Degen");
+    assert_eq!(body["user_note"]["note"], "Met at ETHDenver");
+
+    // Anonymous lookups have no note.
+    let res = reqwest::get(&url).await.unwrap();
+    let body = res.json::<Json>().await.unwrap();
+    assert!(body.get("user_note").is_none());
+  });
+}
+
+#[test]
+fn get_arkham_route_with_invalid_length_address() {
+  use_app(async move {
+    let res = reqwest::get("http://localhost:8088/v1/arkham/0x1234")
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::BAD_REQUEST;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<Json>().await.unwrap();
+    assert_eq!(body["code"], "invalid_address");
+  });
+}
+
+#[test]
+fn get_arkham_route_with_non_hex_address() {
+  use_app(async move {
+    let res =
+      reqwest::get("http://localhost:8088/v1/arkham/0xZZ908400098527886E0F7030069857D2E4169EE7")
+        .await
+        .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::BAD_REQUEST;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<Json>().await.unwrap();
+    assert_eq!(body["code"], "invalid_address");
+  });
+}
+
+#[test]
+fn get_arkham_route_cache_headers() {
+  use_app(async move {
+    // Not used by other tests, so the first request is never cached.
+    let url = "http://localhost:8088/v1/arkham/0x00000000000000000000000000000000000000c1";
+
+    let res = reqwest::get(url).await.unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Response headers:
+    let headers = res.headers();
+    assert_eq!(headers.get("X-Cache").unwrap(), "MISS");
+    assert!(headers.get("Last-Modified").is_some());
+    let cache_control = headers.get("Cache-Control").unwrap().to_str().unwrap();
+    assert!(cache_control.starts_with("public, max-age="));
+
+    let res = reqwest::get(url).await.unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Response headers:
+    let headers = res.headers();
+    assert_eq!(headers.get("X-Cache").unwrap(), "HIT");
+  });
+}
+
+#[test]
+fn parse_arkham_response_with_corrupt_chain() {
+  let mut payload = address_payload(ADDRESS);
+  payload["ethereum"]["contract"] = Json::from("not a boolean");
+
+  let response = parse_arkham_response(payload).unwrap();
+  let response = serde_json::to_value(response).unwrap();
+
+  assert!(
+    response["ethereum"].is_null(),
+    "Corrupt chain should be dropped"
+  );
+  for chain in ["bsc", "polygon", "arbitrum_one", "avalanche", "optimism"] {
+    assert_eq!(
+      response[chain]["address"], ADDRESS,
+      "Chain {} should be kept",
+      chain
+    );
+  }
+}
+
+#[test]
+fn parse_arkham_response_with_missing_and_new_chains() {
+  let mut payload = address_payload(ADDRESS);
+  let chains = payload.as_object_mut().unwrap();
+  chains.remove("avalanche");
+  chains.insert("optimism".to_owned(), Json::Null);
+  chains.insert("base".to_owned(), chains["ethereum"].clone());
+  chains["base"]["chain"] = Json::from("base");
+
+  let response = parse_arkham_response(payload).unwrap();
+
+  assert_eq!(
+    response.chain_names(),
+    ["arbitrum_one", "base", "bsc", "ethereum", "polygon"],
+    "Missing and null chains should be left out"
+  );
+  assert_eq!(
+    response.chain("base").and_then(|data| data.entity_name()),
+    Some("Degen")
+  );
+}
+
+#[test]
+fn arkham_response_reads_null_chains() {
+  // Stored the way snapshots were before chains became a map.
+  let data = serde_json::json!({ "bsc": null, "ethereum": address_payload(ADDRESS)["ethereum"] });
+
+  let response = serde_json::from_value::<ArkhamResponse>(data).unwrap();
+
+  assert_eq!(response.chain_names(), ["ethereum"]);
+}
+
+#[test]
+fn parse_arkham_response_with_string_balance() {
+  let mut payload = address_payload(ADDRESS);
+  payload["ethereum"]["balance"] = Json::from("1.5");
+  payload["ethereum"]["usdValue"] = Json::from(" 4200.25 ");
+
+  let response = parse_arkham_response(payload).unwrap();
+  let response = serde_json::to_value(response).unwrap();
+
+  assert_eq!(response["ethereum"]["balance"], 1.5);
+  assert_eq!(response["ethereum"]["usd_value"], 4200.25);
+}
+
+#[test]
+fn parse_arkham_response_with_number_balance() {
+  let mut payload = address_payload(ADDRESS);
+  payload["ethereum"]["balance"] = Json::from(2);
+  payload["ethereum"]["usd_value"] = Json::from(5600.5);
+
+  let response = parse_arkham_response(payload).unwrap();
+  let response = serde_json::to_value(response).unwrap();
+
+  assert_eq!(response["ethereum"]["balance"], 2.0);
+  assert_eq!(response["ethereum"]["usd_value"], 5600.5);
+}
+
+#[test]
+fn parse_arkham_response_without_balance() {
+  let payload = address_payload(ADDRESS);
+
+  let response = parse_arkham_response(payload).unwrap();
+  let response = serde_json::to_value(response).unwrap();
+
+  assert_eq!(response["ethereum"]["address"], ADDRESS);
+  assert!(response["ethereum"]["balance"].is_null());
+  assert!(response["ethereum"]["usd_value"].is_null());
+}
+
+#[test]
+fn get_arkham_entity_route() {
+  use_app(async move {
+    let res = reqwest::get(format!(
+      "http://localhost:8088/v1/arkham/entity/{}",
+      ENTITY_ID
+    ))
+    .await
+    .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<ArkhamEntityDetail>().await.unwrap();
+    assert_eq!(body.id.unwrap(), ENTITY_ID);
+    assert_eq!(body.name.unwrap(), "Degen");
+  });
+}
+
+#[test]
+fn get_arkham_entity_route_not_found() {
+  use_app(async move {
+    let res = reqwest::get("http://localhost:8088/v1/arkham/entity/unknown")
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::NOT_FOUND;
+    assert_eq!(actual, expected);
+  });
+}
+
+#[test]
+fn get_arkham_route_with_html_response() {
+  use_app(async move {
+    let client = reqwest::Client::new();
+    let res = client
+      .get(format!("http://localhost:8088/v1/arkham/{}", HTML_ADDRESS))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::BAD_GATEWAY;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<Json>().await.unwrap();
+    assert_eq!(body["code"], "upstream_invalid_response");
+    let message = body["message"].as_str().unwrap();
+    assert!(
+      message.contains("text/html"),
+      "Message should mention the upstream content type"
+    );
+    assert!(
+      !message.contains("<html>"),
+      "The upstream body should only be logged"
+    );
+  });
+}
+
+#[test]
+fn get_arkham_route_with_slow_upstream() {
+  use_app(async move {
+    let res = reqwest::get(format!("http://localhost:8088/v1/arkham/{}", SLOW_ADDRESS))
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::GATEWAY_TIMEOUT;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<Json>().await.unwrap();
+    assert_eq!(body["code"], "upstream_timeout");
+    assert_eq!(body["details"]["service"], "Arkham");
+  });
+}
+
+#[test]
+fn get_arkham_route_with_fresh_query() {
+  use_app(async move {
+    // Not used by other tests, so the first request is never cached.
+    let url = "http://localhost:8088/v1/arkham/0x00000000000000000000000000000000000000c2";
+
+    let res = reqwest::get(url).await.unwrap();
+    assert_eq!(res.headers().get("X-Cache").unwrap(), "MISS");
+
+    let res = reqwest::get(format!("{}?fresh=true", url)).await.unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Response headers:
+    let headers = res.headers();
+    assert_eq!(
+      headers.get("X-Cache").unwrap(),
+      "MISS",
+      "Fresh requests should bypass the cache"
+    );
+
+    let res = reqwest::get(url).await.unwrap();
+    assert_eq!(res.headers().get("X-Cache").unwrap(), "HIT");
+  });
+}
+
+#[test]
+fn get_arkham_cache_stats_route() {
+  use_app(async move {
+    let stats_url = "http://localhost:8088/v1/arkham/cache/stats";
+    // Not used by other tests, so the first request is never cached.
+    let url = "http://localhost:8088/v1/arkham/0x00000000000000000000000000000000000000c3";
+
+    let before = reqwest::get(stats_url)
+      .await
+      .unwrap()
+      .json::<ArkhamCacheStats>()
+      .await
+      .unwrap();
+
+    reqwest::get(url).await.unwrap();
+    reqwest::get(url).await.unwrap();
+
+    let res = reqwest::get(stats_url).await.unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let after = res.json::<ArkhamCacheStats>().await.unwrap();
+    assert_eq!(after.address.misses, before.address.misses + 1);
+    assert_eq!(after.address.hits, before.address.hits + 1);
+  });
+}
+
+#[test]
+fn get_arkham_transfers_route() {
+  use_app(async move {
+    let res = reqwest::get(format!(
+      "http://localhost:8088/v1/arkham/{}/transfers",
+      ADDRESS
+    ))
+    .await
+    .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<ArkhamTransfers>().await.unwrap();
+    assert_eq!(body.count, Some(3));
+    assert_eq!(body.transfers.len(), 3);
+
+    let body = serde_json::to_value(body).unwrap();
+    let transfer = &body["transfers"][0];
+    assert_eq!(transfer["fromAddress"]["address"], ADDRESS);
+    assert_eq!(transfer["unitValue"], 1.5);
+    assert_eq!(transfer["historicalUSD"], 4200.25);
+  });
+}
+
+#[test]
+fn get_arkham_transfers_route_raw() {
+  use_app(async move {
+    let res = reqwest::get(format!(
+      "http://localhost:8088/v1/arkham/{}/transfers?raw=true&limit=2",
+      ADDRESS
+    ))
+    .await
+    .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    // Arkham's own response, values aren't parsed.
+    let body = res.json::<Json>().await.unwrap();
+    assert_eq!(body["count"], 3);
+    assert_eq!(body["transfers"].as_array().unwrap().len(), 2);
+    let transfer = &body["transfers"][0];
+    assert_eq!(transfer["fromAddress"]["address"], ADDRESS);
+    assert_eq!(transfer["unitValue"], "1.5");
+  });
+}
+
+#[test]
+fn get_arkham_transfers_route_with_pagination_and_time_range() {
+  use_app(async move {
+    let res = reqwest::get(format!(
+      "http://localhost:8088/v1/arkham/{}/transfers?time_gte=2000&time_lte=3000&offset=1&limit=1",
+      ADDRESS
+    ))
+    .await
+    .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<ArkhamTransfers>().await.unwrap();
+    assert_eq!(body.count, Some(2), "Time range should be passed through");
+    assert_eq!(body.transfers.len(), 1, "Limit should be passed through");
+    assert_eq!(
+      body.transfers.first().unwrap().id.as_deref(),
+      Some("transfer-3"),
+      "Offset should be passed through"
+    );
+  });
+}
+
+#[test]
+fn get_arkham_transfers_route_with_invalid_address() {
+  use_app(async move {
+    let res = reqwest::get("http://localhost:8088/v1/arkham/0x1234/transfers")
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::BAD_REQUEST;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<Json>().await.unwrap();
+    assert_eq!(body["code"], "invalid_address");
+    assert_eq!(body["details"]["address"], "0x1234");
+  });
+}
+
+#[test]
+fn get_arkham_portfolio_route() {
+  use_app(async move {
+    let res = reqwest::get(format!(
+      "http://localhost:8088/v1/arkham/{}/portfolio",
+      ADDRESS
+    ))
+    .await
+    .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<ArkhamPortfolio>().await.unwrap();
+    assert_eq!(body.total_usd, 5505.0);
+    let chains: Vec<&str> = body.chains.keys().map(String::as_str).collect();
+    assert_eq!(
+      chains,
+      ["ethereum", "polygon"],
+      "Empty chains should be left out"
+    );
+
+    let ethereum = &body.chains["ethereum"];
+    assert_eq!(ethereum.total_usd, 5500.0);
+    let symbols: Vec<_> = ethereum
+      .holdings
+      .iter()
+      .map(|holding| holding.symbol.as_deref().unwrap())
+      .collect();
+    assert_eq!(
+      symbols,
+      ["ETH", "USDC"],
+      "Largest holdings should come first"
+    );
+    assert_eq!(ethereum.holdings[1].balance, Some(1000.0));
+  });
+}
+
+#[test]
+fn get_arkham_portfolio_route_with_invalid_address() {
+  use_app(async move {
+    let res = reqwest::get("http://localhost:8088/v1/arkham/0x1234/portfolio")
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::BAD_REQUEST;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<Json>().await.unwrap();
+    assert_eq!(body["code"], "invalid_address");
+  });
+}
+
+#[test]
+fn get_arkham_portfolio_route_with_failing_upstream() {
+  use_app(async move {
+    let res = reqwest::get(format!(
+      "http://localhost:8088/v1/arkham/{}/portfolio",
+      FAILING_ADDRESS
+    ))
+    .await
+    .unwrap();
+
+    // Status code:
+    // Portfolios aren't kept as stale fallbacks, the failure is returned.
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::SERVICE_UNAVAILABLE;
+    assert_eq!(actual, expected);
+  });
+}
+
+#[test]
+fn get_arkham_transfers_route_with_invalid_time_range() {
+  use_app(async move {
+    let res = reqwest::get(format!(
+      "http://localhost:8088/v1/arkham/{}/transfers?time_gte=3000&time_lte=1000",
+      ADDRESS
+    ))
+    .await
+    .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::BAD_REQUEST;
+    assert_eq!(actual, expected);
+  });
+}
+
+#[test]
+fn get_arkham_route_retries_unavailable_upstream() {
+  use_app(async move {
+    let res = reqwest::get(format!(
+      "http://localhost:8088/v1/arkham/{}?fresh=true",
+      FLAKY_ADDRESS
+    ))
+    .await
+    .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Response headers:
+    let headers = res.headers();
+    assert_eq!(headers.get("X-Cache").unwrap(), "MISS");
+  });
+}
+
+#[test]
+fn get_arkham_transfers_route_rate_limited() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user.clone()).await.unwrap();
+    let url = format!("http://localhost:8088/v1/arkham/{}/transfers", ADDRESS);
+
+    // The test configuration allows three requests on this route.
+    let client = reqwest::Client::new();
+    for remaining in ["2", "1", "0"] {
+      let res = client
+        .get(&url)
+        .header("Authorization", format!("Bearer {}", token))
+        .send()
+        .await
+        .unwrap();
+      assert_eq!(res.status(), StatusCode::OK);
+
+      let headers = res.headers();
+      assert_eq!(headers.get("X-RateLimit-Limit").unwrap(), "3");
+      assert_eq!(headers.get("X-RateLimit-Remaining").unwrap(), remaining);
+      assert!(headers.get("X-RateLimit-Reset").is_some());
+    }
+
+    let res = client
+      .get(&url)
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::TOO_MANY_REQUESTS;
+    assert_eq!(actual, expected);
+
+    // Response headers:
+    let retry_after = res.headers().get("Retry-After").unwrap().to_str().unwrap();
+    assert!(retry_after.parse::<u64>().unwrap() >= 1);
+    assert_eq!(res.headers().get("X-RateLimit-Remaining").unwrap(), "0");
+
+    // Body:
+    let body = res.json::<Json>().await.unwrap();
+    assert_eq!(body["code"], "rate_limited");
+
+    // Throttled requests don't count against the quota:
+    let usage = Usage::find_one(doc! { "user": user.id.unwrap() }, None)
+      .await
+      .unwrap()
+      .unwrap();
+    assert_eq!(usage.requests, 3);
+  });
+}
+
+#[test]
+fn get_arkham_route_public_tier() {
+  use_app(async move {
+    let url = format!("http://localhost:8088/v1/arkham/{}", ADDRESS);
+    let client = reqwest::Client::new();
+    let lookup = |ip: &'static str| client.get(&url).header("X-Forwarded-For", ip).send();
+
+    // Anonymous clients are limited by IP, with the public tier limits of
+    // the test configuration.
+    for remaining in ["999", "998"] {
+      let res = lookup("203.0.113.7").await.unwrap();
+      assert_eq!(res.status(), StatusCode::OK);
+      assert_eq!(res.headers().get("X-RateLimit-Limit").unwrap(), "1000");
+      assert_eq!(
+        res.headers().get("X-RateLimit-Remaining").unwrap(),
+        remaining
+      );
+    }
+
+    let res = lookup("198.51.100.7, 203.0.113.8").await.unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Response headers:
+    assert_eq!(
+      res.headers().get("X-RateLimit-Remaining").unwrap(),
+      "999",
+      "The last forwarded address should have its own bucket"
+    );
+
+    // Body:
+    let body = res.json::<Json>().await.unwrap();
+    let ethereum = &body["ethereum"];
+    assert_eq!(ethereum["arkhamEntity"]["name"], "Degen");
+    assert_eq!(ethereum["arkhamEntity"]["type"], "individual");
+    assert_eq!(ethereum["arkhamLabel"]["name"], "Degen Wallet");
+    assert_eq!(ethereum["contract"], false);
+    assert!(ethereum["arkhamLabel"]["address"].is_null());
+    assert!(ethereum["isUserAddress"].is_null());
+
+    // Authenticated users get the full data, with their own limits.
+    let user = create_user("nico@test.com").await.unwrap();
+    let client = create_authenticated_client(user).await.unwrap();
+    let res = client
+      .get(&url)
+      .header("X-Forwarded-For", "203.0.113.7")
+      .send()
+      .await
+      .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.headers().get("X-RateLimit-Limit").unwrap(), "30");
+
+    let body = res.json::<Json>().await.unwrap();
+    let ethereum = &body["ethereum"];
+    assert_eq!(ethereum["arkhamLabel"]["address"], ADDRESS);
+    assert_eq!(ethereum["isUserAddress"], false);
+  });
+}
+
+#[test]
+fn get_arkham_stream_route() {
+  use_app(async move {
+    let client = reqwest::Client::new();
+    let mut res = client
+      .get(format!(
+        "http://localhost:8088/v1/arkham/{}/stream",
+        ADDRESS
+      ))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Headers:
+    let content_type = res.headers().get("content-type").unwrap();
+    assert_eq!(content_type, "text/event-stream");
+
+    // Body:
+    let chunk = res.chunk().await.unwrap().unwrap();
+    let chunk = String::from_utf8(chunk.to_vec()).unwrap();
+    assert!(
+      chunk.starts_with("event: snapshot\ndata: {"),
+      "Should start with a snapshot of the address"
+    );
+  });
+}
+
+#[test]
+fn get_arkham_stream_route_with_invalid_address() {
+  use_app(async move {
+    let client = reqwest::Client::new();
+    let res = client
+      .get("http://localhost:8088/v1/arkham/not-an-address/stream")
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::BAD_REQUEST;
+    assert_eq!(actual, expected);
+  });
+}
+
+#[test]
+fn get_arkham_graph_route() {
+  use_app(async move {
+    let address = "0x00000000000000000000000000000000000000a2";
+    let co_labeled = "0x00000000000000000000000000000000000000a3";
+    for eth_address in [address, co_labeled] {
+      let label = AddressLabel::new(
+        eth_address.to_owned(),
+        "Binance 14".to_owned(),
+        "arkham".to_owned(),
+      );
+      AddressLabel::create(label).await.unwrap();
+    }
+
+    let url = format!("http://localhost:8088/v1/arkham/{}/graph", address);
+    let res = reqwest::get(url).await.unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<AddressGraph>().await.unwrap();
+    assert_eq!(body.address, to_checksum_address(address));
+    assert_eq!(body.depth, 2);
+    assert!(!body.truncated);
+
+    let entity = format!("entity:{}", ENTITY_ID);
+    let nodes = body
+      .nodes
+      .iter()
+      .map(|node| (node.id.clone(), node.kind, node.depth))
+      .collect::<Vec<(String, NodeKind, usize)>>();
+    assert_eq!(
+      nodes,
+      vec![
+        (to_checksum_address(address), NodeKind::Address, 0),
+        (entity.clone(), NodeKind::Entity, 1),
+        (to_checksum_address(co_labeled), NodeKind::Address, 1),
+        (to_checksum_address(ADDRESS), NodeKind::Address, 1),
+      ]
+    );
+    assert_eq!(body.nodes[0].label.as_deref(), Some("Binance 14"));
+    assert_eq!(body.nodes[1].label.as_deref(), Some("Degen"));
+
+    let edges = body
+      .edges
+      .iter()
+      .map(|edge| (edge.source.clone(), edge.target.clone(), edge.relation))
+      .collect::<Vec<(String, String, Relation)>>();
+    assert_eq!(
+      edges,
+      vec![
+        (
+          to_checksum_address(address),
+          entity.clone(),
+          Relation::Entity
+        ),
+        (
+          to_checksum_address(address),
+          to_checksum_address(co_labeled),
+          Relation::Label
+        ),
+        (
+          entity.clone(),
+          to_checksum_address(ADDRESS),
+          Relation::Entity
+        ),
+        (to_checksum_address(co_labeled), entity, Relation::Entity),
+      ],
+      "Edges found again from the other end should be left out"
+    );
+    assert_eq!(body.edges[1].label.as_deref(), Some("Binance 14"));
+  });
+}
+
+#[test]
+fn get_arkham_graph_route_with_invalid_depth() {
+  use_app(async move {
+    for depth in [0, SETTINGS.arkham.graph_max_depth + 1] {
+      let url = format!(
+        "http://localhost:8088/v1/arkham/{}/graph?depth={}",
+        ADDRESS, depth
+      );
+      let res = reqwest::get(url).await.unwrap();
+
+      // Status code:
+      let status_code = res.status();
+      let actual = status_code;
+      let expected = StatusCode::BAD_REQUEST;
+      assert_eq!(actual, expected, "Depth {} should be rejected", depth);
+    }
+  });
+}
+
+fn fixture_api_url(path: &str) -> String {
+  format!("http://localhost:{}/v1{}", FIXTURE_API_PORT, path)
+}
+
+#[test]
+fn get_arkham_route_with_fixture_provider() {
+  use_fixture_app(async move {
+    let res = reqwest::get(fixture_api_url(&format!("/arkham/{}", FIXTURE_ADDRESS)))
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<Json>().await.unwrap();
+    assert_eq!(body["address"], to_checksum_address(FIXTURE_ADDRESS));
+    assert_eq!(body["ethereum"]["arkhamEntity"]["id"], FIXTURE_ENTITY_ID);
+    assert!(body["bsc"].is_null(), "Only the fixture chains are served");
+  });
+}
+
+#[test]
+fn get_arkham_route_with_fixture_provider_unknown_address() {
+  use_fixture_app(async move {
+    // Known by the mock Arkham API, which the fixture API doesn't call.
+    let res = reqwest::get(fixture_api_url(&format!("/arkham/{}", ADDRESS)))
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::NOT_FOUND;
+    assert_eq!(actual, expected);
+  });
+}
+
+#[test]
+fn get_arkham_entity_route_with_fixture_provider() {
+  use_fixture_app(async move {
+    let res = reqwest::get(fixture_api_url(&format!(
+      "/arkham/entity/{}",
+      FIXTURE_ENTITY_ID
+    )))
+    .await
+    .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<ArkhamEntityDetail>().await.unwrap();
+    assert_eq!(body.name.as_deref(), Some("Fixture Fund"));
+    assert_eq!(body.addresses(), [FIXTURE_ADDRESS]);
+  });
+}
+
+#[test]
+fn get_arkham_transfers_route_with_fixture_provider() {
+  use_fixture_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let client = create_authenticated_client(user).await.unwrap();
+    let res = client
+      .get(fixture_api_url(&format!(
+        "/arkham/{}/transfers",
+        FIXTURE_ADDRESS
+      )))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<ArkhamTransfers>().await.unwrap();
+    assert_eq!(body.count, Some(1));
+    assert_eq!(
+      body.transfers.first().unwrap().id.as_deref(),
+      Some("fixture-transfer")
+    );
+  });
+}
+
+#[test]
+fn get_arkham_portfolio_route_with_fixture_provider() {
+  use_fixture_app(async move {
+    let res = reqwest::get(fixture_api_url(&format!(
+      "/arkham/{}/portfolio",
+      FIXTURE_ADDRESS
+    )))
+    .await
+    .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<ArkhamPortfolio>().await.unwrap();
+    assert_eq!(body.total_usd, 2500.0);
+    let holding = body.chains["ethereum"].holdings.first().unwrap();
+    assert_eq!(holding.symbol.as_deref(), Some("ETH"));
+  });
+}
+
+#[test]
+fn get_arkham_transfers_route_raw_with_fixture_provider() {
+  use_fixture_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let client = create_authenticated_client(user).await.unwrap();
+    let res = client
+      .get(fixture_api_url(&format!(
+        "/arkham/{}/transfers?raw=true",
+        FIXTURE_ADDRESS
+      )))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    // Without an upstream response to pass through, the usual JSON is
+    // returned.
+    let body = res.json::<ArkhamTransfers>().await.unwrap();
+    assert_eq!(body.count, Some(1));
+    assert_eq!(
+      body.transfers.first().unwrap().id.as_deref(),
+      Some("fixture-transfer")
+    );
+  });
+}
@@ -0,0 +1,483 @@
+use bson::doc;
+use chrono::{SecondsFormat, Utc};
+use k256::ecdsa::SigningKey;
+use k256::elliptic_curve::sec1::ToEncodedPoint;
+use reqwest;
+use reqwest::StatusCode;
+use serde_json::json;
+use sha3::{Digest, Keccak256};
+use std::time::Duration;
+
+use crate::models::account_token::{AccountToken, TokenPurpose};
+use crate::models::user::User;
+use crate::routes::auth::NonceResponse;
+use crate::routes::user::AuthenticateResponse;
+use crate::tests::setup::use_app;
+use crate::tests::utils::{create_authenticated_client, create_user};
+use crate::utils::date;
+use crate::utils::models::ModelExt;
+
+fn keccak256(data: &[u8]) -> Vec<u8> {
+  Keccak256::digest(data).to_vec()
+}
+
+// Test wallet, with its EIP-55 checksummed address.
+fn wallet() -> (SigningKey, String) {
+  let key = SigningKey::from_bytes(&[7_u8; 32].into()).unwrap();
+  let public_key = key.verifying_key().to_encoded_point(false);
+  let address = hex::encode(&keccak256(&public_key.as_bytes()[1..])[12..]);
+
+  let hash = hex::encode(keccak256(address.as_bytes()));
+  let checksummed = address
+    .chars()
+    .zip(hash.chars())
+    .map(|(c, h)| if h >= '8' { c.to_ascii_uppercase() } else { c })
+    .collect::<String>();
+
+  (key, format!("0x{}", checksummed))
+}
+
+fn siwe_message(domain: &str, address: &str, nonce: &str) -> String {
+  format!(
+    "{domain} wants you to sign in with your Ethereum account:\n{address}\n\n\
+     Sign in to Degen\n\nURI: http://{domain}\nVersion: 1\nChain ID: 1\n\
+     Nonce: {nonce}\nIssued At: {}",
+    Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true)
+  )
+}
+
+// EIP-191 personal signature, as made by wallets.
+fn sign(key: &SigningKey, message: &str) -> String {
+  let prefixed = format!("\x19Ethereum Signed Message:\n{}{}", message.len(), message);
+  let (signature, recovery_id) = key
+    .sign_prehash_recoverable(&keccak256(prefixed.as_bytes()))
+    .unwrap();
+
+  let mut bytes = signature.to_bytes().to_vec();
+  bytes.push(recovery_id.to_byte() + 27);
+  format!("0x{}", hex::encode(bytes))
+}
+
+async fn create_nonce(client: &reqwest::Client) -> String {
+  let res = client
+    .post("http://localhost:8088/v1/auth/siwe/nonce")
+    .send()
+    .await
+    .unwrap();
+  res.json::<NonceResponse>().await.unwrap().nonce
+}
+
+async fn authenticate(client: &reqwest::Client) -> AuthenticateResponse {
+  create_user("nahuel@gmail.com").await.unwrap();
+  let res = client
+    .post("http://localhost:8088/v1/users/authenticate")
+    .json(&json!({ "email": "nahuel@gmail.com", "password": "Password1" }))
+    .send()
+    .await
+    .unwrap();
+  res.json::<AuthenticateResponse>().await.unwrap()
+}
+
+// Stores an account token of the user, as emailed to them.
+async fn create_account_token(user: &User, purpose: TokenPurpose, ttl: Duration) -> String {
+  let (model, token) = AccountToken::generate(user.id.unwrap(), purpose, date::after(ttl));
+  AccountToken::create(model).await.unwrap();
+  token
+}
+
+async fn refresh(client: &reqwest::Client, refresh_token: &str) -> reqwest::Response {
+  client
+    .post("http://localhost:8088/v1/auth/refresh")
+    .json(&json!({ "refresh_token": refresh_token }))
+    .send()
+    .await
+    .unwrap()
+}
+
+#[test]
+fn post_siwe_nonce_route() {
+  use_app(async move {
+    let client = reqwest::Client::new();
+    let res = client
+      .post("http://localhost:8088/v1/auth/siwe/nonce")
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<NonceResponse>().await.unwrap();
+    assert!(body.nonce.len() >= 8);
+    assert!(body.nonce.chars().all(|c| c.is_ascii_alphanumeric()));
+  });
+}
+
+#[test]
+fn post_siwe_verify_route() {
+  use_app(async move {
+    let (key, address) = wallet();
+    let client = reqwest::Client::new();
+    let nonce = create_nonce(&client).await;
+    let message = siwe_message("localhost:8088", &address, &nonce);
+    let body = json!({ "message": message, "signature": sign(&key, &message) });
+
+    let res = client
+      .post("http://localhost:8088/v1/auth/siwe/verify")
+      .json(&body)
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<AuthenticateResponse>().await.unwrap();
+    assert!(!body.access_token.is_empty());
+    assert_eq!(body.user.wallet, Some(address.to_lowercase()));
+  });
+}
+
+#[test]
+fn post_siwe_verify_route_with_used_nonce() {
+  use_app(async move {
+    let (key, address) = wallet();
+    let client = reqwest::Client::new();
+    let nonce = create_nonce(&client).await;
+    let message = siwe_message("localhost:8088", &address, &nonce);
+    let body = json!({ "message": message, "signature": sign(&key, &message) });
+
+    let res = client
+      .post("http://localhost:8088/v1/auth/siwe/verify")
+      .json(&body)
+      .send()
+      .await
+      .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = client
+      .post("http://localhost:8088/v1/auth/siwe/verify")
+      .json(&body)
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::UNAUTHORIZED;
+    assert_eq!(actual, expected, "Nonces can only be used once");
+  });
+}
+
+#[test]
+fn post_siwe_verify_route_with_other_domain() {
+  use_app(async move {
+    let (key, address) = wallet();
+    let client = reqwest::Client::new();
+    let nonce = create_nonce(&client).await;
+    let message = siwe_message("phishing.example.com", &address, &nonce);
+
+    let res = client
+      .post("http://localhost:8088/v1/auth/siwe/verify")
+      .json(&json!({ "message": message, "signature": sign(&key, &message) }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::UNAUTHORIZED;
+    assert_eq!(actual, expected);
+  });
+}
+
+#[test]
+fn post_refresh_route() {
+  use_app(async move {
+    let client = reqwest::Client::new();
+    let tokens = authenticate(&client).await;
+
+    let res = refresh(&client, &tokens.refresh_token).await;
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<AuthenticateResponse>().await.unwrap();
+    assert!(!body.access_token.is_empty());
+    assert_ne!(
+      body.refresh_token, tokens.refresh_token,
+      "Tokens are rotated"
+    );
+    assert_eq!(body.user.email, "nahuel@gmail.com");
+
+    let res = client
+      .get("http://localhost:8088/v1/api-keys")
+      .bearer_auth(&body.access_token)
+      .send()
+      .await
+      .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+  });
+}
+
+#[test]
+fn post_refresh_route_with_reused_token() {
+  use_app(async move {
+    let client = reqwest::Client::new();
+    let tokens = authenticate(&client).await;
+
+    let res = refresh(&client, &tokens.refresh_token).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let rotated = res.json::<AuthenticateResponse>().await.unwrap();
+
+    let res = refresh(&client, &tokens.refresh_token).await;
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::UNAUTHORIZED;
+    assert_eq!(actual, expected, "Refresh tokens can only be used once");
+
+    let res = refresh(&client, &rotated.refresh_token).await;
+    assert_eq!(
+      res.status(),
+      StatusCode::UNAUTHORIZED,
+      "Reusing a token revokes its family"
+    );
+  });
+}
+
+#[test]
+fn post_refresh_route_with_unknown_token() {
+  use_app(async move {
+    let client = reqwest::Client::new();
+    let res = refresh(&client, "unknown").await;
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::UNAUTHORIZED;
+    assert_eq!(actual, expected);
+  });
+}
+
+#[test]
+fn post_logout_route() {
+  use_app(async move {
+    let client = reqwest::Client::new();
+    let tokens = authenticate(&client).await;
+
+    let res = client
+      .post("http://localhost:8088/v1/auth/logout")
+      .json(&json!({ "refresh_token": tokens.refresh_token }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::NO_CONTENT;
+    assert_eq!(actual, expected);
+
+    let res = refresh(&client, &tokens.refresh_token).await;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+  });
+}
+
+#[test]
+fn post_forgot_password_route() {
+  use_app(async move {
+    create_user("nahuel@gmail.com").await.unwrap();
+    let client = reqwest::Client::new();
+
+    for email in ["nahuel@gmail.com", "unknown@gmail.com"] {
+      let res = client
+        .post("http://localhost:8088/v1/auth/forgot-password")
+        .json(&json!({ "email": email }))
+        .send()
+        .await
+        .unwrap();
+
+      // Status code:
+      let status_code = res.status();
+      let actual = status_code;
+      let expected = StatusCode::NO_CONTENT;
+      assert_eq!(actual, expected, "Registered emails can't be told apart");
+    }
+  });
+}
+
+#[test]
+fn post_reset_password_route() {
+  use_app(async move {
+    let client = reqwest::Client::new();
+    let tokens = authenticate(&client).await;
+    let user = User::find_one(doc! { "email": "nahuel@gmail.com" }, None)
+      .await
+      .unwrap()
+      .unwrap();
+    let token = create_account_token(
+      &user,
+      TokenPurpose::PasswordReset,
+      Duration::from_secs(3600),
+    )
+    .await;
+
+    let res = client
+      .post("http://localhost:8088/v1/auth/reset-password")
+      .json(&json!({ "token": token, "password": "Password2" }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::NO_CONTENT;
+    assert_eq!(actual, expected);
+
+    let res = refresh(&client, &tokens.refresh_token).await;
+    assert_eq!(
+      res.status(),
+      StatusCode::UNAUTHORIZED,
+      "Sessions are revoked"
+    );
+
+    let res = client
+      .post("http://localhost:8088/v1/users/authenticate")
+      .json(&json!({ "email": "nahuel@gmail.com", "password": "Password2" }))
+      .send()
+      .await
+      .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = client
+      .post("http://localhost:8088/v1/auth/reset-password")
+      .json(&json!({ "token": token, "password": "Password3" }))
+      .send()
+      .await
+      .unwrap();
+    assert_eq!(
+      res.status(),
+      StatusCode::NOT_FOUND,
+      "Tokens can only be used once"
+    );
+  });
+}
+
+#[test]
+fn post_reset_password_route_with_expired_token() {
+  use_app(async move {
+    let user = create_user("nahuel@gmail.com").await.unwrap();
+    let (model, token) = AccountToken::generate(
+      user.id.unwrap(),
+      TokenPurpose::PasswordReset,
+      date::before(Duration::from_secs(60)),
+    );
+    AccountToken::create(model).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .post("http://localhost:8088/v1/auth/reset-password")
+      .json(&json!({ "token": token, "password": "Password2" }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::NOT_FOUND;
+    assert_eq!(actual, expected);
+  });
+}
+
+#[test]
+fn post_verify_email_route() {
+  use_app(async move {
+    let user = create_user("nahuel@gmail.com").await.unwrap();
+    let client = reqwest::Client::new();
+
+    let reset_token = create_account_token(
+      &user,
+      TokenPurpose::PasswordReset,
+      Duration::from_secs(3600),
+    )
+    .await;
+    let res = client
+      .post("http://localhost:8088/v1/auth/verify-email")
+      .json(&json!({ "token": reset_token }))
+      .send()
+      .await
+      .unwrap();
+    assert_eq!(
+      res.status(),
+      StatusCode::NOT_FOUND,
+      "Tokens are only valid for their purpose"
+    );
+
+    let token = create_account_token(
+      &user,
+      TokenPurpose::EmailVerification,
+      Duration::from_secs(3600),
+    )
+    .await;
+    let res = client
+      .post("http://localhost:8088/v1/auth/verify-email")
+      .json(&json!({ "token": token }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::NO_CONTENT;
+    assert_eq!(actual, expected);
+
+    let user = User::find_by_id(&user.id.unwrap()).await.unwrap().unwrap();
+    assert!(user.email_verified_at.is_some());
+  });
+}
+
+#[test]
+fn post_verify_email_resend_route_when_verified() {
+  use_app(async move {
+    let user = create_user("nahuel@gmail.com").await.unwrap();
+    User::update_one(
+      doc! { "_id": user.id.unwrap() },
+      doc! { "$set": { "email_verified_at": date::now() } },
+      None,
+    )
+    .await
+    .unwrap();
+    let client = create_authenticated_client(user).await.unwrap();
+
+    let res = client
+      .post("http://localhost:8088/v1/auth/verify-email/resend")
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::CONFLICT;
+    assert_eq!(actual, expected);
+  });
+}
@@ -0,0 +1,186 @@
+use reqwest;
+use reqwest::StatusCode;
+use serde_json::{json, Value as Json};
+
+use crate::services::balances::AddressBalances;
+use crate::tests::mock_arkham::{BITCOIN_XPUB, FAILING_ADDRESS, SOLANA_ADDRESS};
+use crate::tests::setup::use_app;
+use crate::utils::address::to_checksum_address;
+
+const ADDRESS: &str = "0x00000000000000000000000000000000000000a1";
+
+#[test]
+fn get_balances_route() {
+  use_app(async move {
+    let res = reqwest::get(format!("http://localhost:8088/v1/balances/{}", ADDRESS))
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<AddressBalances>().await.unwrap();
+    assert_eq!(body.address, to_checksum_address(ADDRESS));
+    assert_eq!(body.chains.len(), 6, "Every chain should be queried");
+    assert_eq!(body.usd_value, 6.0 * 3000.0 + 100.0);
+
+    let ethereum = body
+      .chains
+      .iter()
+      .find(|chain| chain.chain == "ethereum")
+      .unwrap();
+    assert_eq!(ethereum.usd_value, 3100.0);
+    assert_eq!(ethereum.tokens.len(), 2);
+    assert!(
+      ethereum.tokens[0].contract_address.is_none(),
+      "Native tokens have no contract"
+    );
+    assert_eq!(ethereum.tokens[1].symbol.as_deref(), Some("USDC"));
+  });
+}
+
+#[test]
+fn get_balances_route_with_solana_address() {
+  use_app(async move {
+    let res = reqwest::get(format!(
+      "http://localhost:8088/v1/balances/{}?chain=solana",
+      SOLANA_ADDRESS
+    ))
+    .await
+    .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<AddressBalances>().await.unwrap();
+    assert_eq!(body.address, SOLANA_ADDRESS);
+    assert_eq!(body.chains.len(), 1, "Only Solana should be queried");
+
+    let solana = &body.chains[0];
+    assert_eq!(solana.chain, "solana");
+    assert!(solana.error.is_none());
+    assert_eq!(solana.tokens.len(), 2);
+    assert_eq!(solana.tokens[0].symbol.as_deref(), Some("SOL"));
+    assert_eq!(solana.tokens[0].balance, "2500000000");
+    assert_eq!(
+      solana.tokens[1].contract_address.as_deref(),
+      Some("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v")
+    );
+    assert_eq!(solana.tokens[1].decimals, Some(6));
+  });
+}
+
+#[test]
+fn get_balances_route_with_bitcoin_xpub() {
+  use_app(async move {
+    let res = reqwest::get(format!(
+      "http://localhost:8088/v1/balances/{}",
+      BITCOIN_XPUB
+    ))
+    .await
+    .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<AddressBalances>().await.unwrap();
+    assert_eq!(body.address, BITCOIN_XPUB);
+    assert_eq!(body.usd_value, 90000.0);
+    assert_eq!(body.chains.len(), 1);
+
+    let bitcoin = &body.chains[0];
+    assert_eq!(bitcoin.chain, "bitcoin");
+    assert_eq!(bitcoin.tokens.len(), 1);
+    assert_eq!(bitcoin.tokens[0].symbol.as_deref(), Some("BTC"));
+    assert_eq!(bitcoin.tokens[0].balance, "150000000");
+    assert_eq!(bitcoin.tokens[0].decimals, Some(8));
+  });
+}
+
+#[test]
+fn get_balances_route_with_failing_chain() {
+  use_app(async move {
+    let res = reqwest::get(format!(
+      "http://localhost:8088/v1/balances/{}",
+      FAILING_ADDRESS
+    ))
+    .await
+    .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<AddressBalances>().await.unwrap();
+    assert_eq!(
+      body.usd_value,
+      5.0 * 3000.0 + 100.0,
+      "Failed chains should be left out of the total"
+    );
+
+    let bsc = body
+      .chains
+      .iter()
+      .find(|chain| chain.chain == "bsc")
+      .unwrap();
+    assert!(bsc.tokens.is_empty());
+    assert_eq!(
+      bsc.error.as_ref().map(|error| error.code.as_str()),
+      Some("upstream_unavailable")
+    );
+  });
+}
+
+#[test]
+fn get_balances_route_warns_about_failing_chain() {
+  use_app(async move {
+    let res = reqwest::get(format!(
+      "http://localhost:8088/v1/balances/{}",
+      FAILING_ADDRESS
+    ))
+    .await
+    .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<Json>().await.unwrap();
+    assert_eq!(body["warnings"], json!(["bsc balances unavailable"]));
+    assert!(body["meta"]["upstream_latency_ms"].is_u64());
+    assert!(body["meta"]["fresh_at"].is_string());
+  });
+}
+
+#[test]
+fn get_balances_route_with_invalid_address() {
+  use_app(async move {
+    let res = reqwest::get("http://localhost:8088/v1/balances/0x1234")
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::BAD_REQUEST;
+    assert_eq!(actual, expected);
+  });
+}
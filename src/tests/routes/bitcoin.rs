@@ -0,0 +1,104 @@
+use reqwest;
+use reqwest::StatusCode;
+use serde_json::Value as Json;
+
+use crate::routes::bitcoin::BitcoinTransactionPage;
+use crate::tests::mock_arkham::{BITCOIN_ADDRESS, BITCOIN_XPUB};
+use crate::tests::setup::use_app;
+
+#[test]
+fn get_bitcoin_transactions_route() {
+  use_app(async move {
+    let res = reqwest::get(format!(
+      "http://localhost:8088/v1/bitcoin/{}/transactions",
+      BITCOIN_ADDRESS
+    ))
+    .await
+    .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<BitcoinTransactionPage>().await.unwrap();
+    assert_eq!(body.address, BITCOIN_ADDRESS);
+    assert_eq!(body.transaction_count, 3);
+    assert!(!body.has_next);
+    assert_eq!(body.transactions.len(), 3);
+
+    let newest = &body.transactions[0];
+    assert_eq!(newest.block_number, Some(820003));
+    assert_eq!(newest.timestamp, "2024-01-03T00:00:00+00:00");
+    assert_eq!(body.transactions[1].balance_change, -50000000);
+  });
+}
+
+#[test]
+fn get_bitcoin_transactions_route_with_pagination() {
+  use_app(async move {
+    let url = format!(
+      "http://localhost:8088/v1/bitcoin/{}/transactions",
+      BITCOIN_ADDRESS
+    );
+
+    let res = reqwest::get(format!("{}?limit=2", url)).await.unwrap();
+    let body = res.json::<BitcoinTransactionPage>().await.unwrap();
+    assert_eq!(body.transactions.len(), 2);
+    assert!(body.has_next);
+
+    let res = reqwest::get(format!("{}?limit=2&page=2", url))
+      .await
+      .unwrap();
+    let body = res.json::<BitcoinTransactionPage>().await.unwrap();
+    assert_eq!(body.transactions.len(), 1);
+    assert_eq!(body.transactions[0].block_number, Some(820001));
+    assert!(!body.has_next);
+  });
+}
+
+#[test]
+fn get_bitcoin_transactions_route_with_xpub() {
+  use_app(async move {
+    let res = reqwest::get(format!(
+      "http://localhost:8088/v1/bitcoin/{}/transactions",
+      BITCOIN_XPUB
+    ))
+    .await
+    .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<BitcoinTransactionPage>().await.unwrap();
+    assert_eq!(body.address, BITCOIN_XPUB);
+    assert_eq!(body.transactions.len(), 3);
+  });
+}
+
+#[test]
+fn get_bitcoin_transactions_route_with_evm_address() {
+  use_app(async move {
+    let res = reqwest::get(
+      "http://localhost:8088/v1/bitcoin/0x00000000000000000000000000000000000000a1/transactions",
+    )
+    .await
+    .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::BAD_REQUEST;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<Json>().await.unwrap();
+    assert_eq!(body["code"], "invalid_address");
+  });
+}
@@ -1,12 +1,17 @@
+use bson::{doc, oid::ObjectId};
 use reqwest;
 use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
+use serde_json::json;
+use serde_json::Value as Json;
 
 use crate::models::cat::Cat;
 use crate::models::cat::PublicCat;
 use crate::tests::setup::use_app;
 use crate::tests::utils::create_user;
 use crate::tests::utils::create_user_token;
+use crate::utils::date;
+use crate::utils::date::Date;
 use crate::utils::models::ModelExt;
 
 #[test]
@@ -43,6 +48,58 @@ fn post_cat_route() {
     let body = res.json::<PublicCat>().await.unwrap();
     assert_eq!(body.name, "Tigrin");
     assert_eq!(body.user, user.id.unwrap(), "Cat should belong to user");
+
+    let path = format!("/v1/cats/{}", body.id);
+    assert_eq!(body.links["self"].href, path);
+    assert_eq!(body.links["self"].method, "GET");
+    assert_eq!(body.links["delete"].method, "DELETE");
+    assert_eq!(body.links["tags"].href, format!("{}/tags", path));
+  });
+}
+
+#[test]
+fn post_cat_route_with_idempotency_key() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user.clone()).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let create_cat = |name: &'static str| {
+      client
+        .post("http://localhost:8088/v1/cats")
+        .header("Authorization", format!("Bearer {}", token))
+        .header("Idempotency-Key", "c2a1e4d0-create-tigrin")
+        .json(&json!({ "name": name }))
+        .send()
+    };
+
+    let res = create_cat("Tigrin").await.unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED);
+    let created = res.json::<PublicCat>().await.unwrap();
+
+    // Retry:
+    let res = create_cat("Tigrin").await.unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::CREATED;
+    assert_eq!(actual, expected);
+
+    // Response headers:
+    assert_eq!(res.headers().get("idempotent-replayed").unwrap(), "true");
+
+    // Body:
+    let body = res.json::<PublicCat>().await.unwrap();
+    assert_eq!(body.id, created.id, "Retry should return the first cat");
+
+    // Cats from the database:
+    let count = Cat::count(doc! { "user": user.id.unwrap() }).await.unwrap();
+    assert_eq!(count, 1, "Retry should not create another cat");
+
+    // Same key, different request:
+    let res = create_cat("Michi").await.unwrap();
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
   });
 }
 
@@ -160,3 +217,2055 @@ fn remove_cat_by_id_route() {
     assert!(cat.is_none(), "Cat should be removed from the database");
   });
 }
+
+#[test]
+fn post_cat_route_with_missing_field() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .post("http://localhost:8088/v1/cats")
+      .header("Authorization", format!("Bearer {}", token))
+      .json(&json!({}))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::UNPROCESSABLE_ENTITY;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<Json>().await.unwrap();
+    assert_eq!(body["code"], "validation_failed");
+    let message = body["message"].as_str().unwrap();
+    assert!(
+      message.contains("missing field `name`"),
+      "Message should name the missing field: {}",
+      message
+    );
+  });
+}
+
+#[test]
+fn post_cat_route_with_invalid_fields() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user).await.unwrap();
+
+    let tags = (0..21)
+      .map(|index| format!("tag-{}", index))
+      .collect::<Vec<_>>();
+    let client = reqwest::Client::new();
+    let res = client
+      .post("http://localhost:8088/v1/cats")
+      .header("Authorization", format!("Bearer {}", token))
+      .json(&json!({ "name": "  ", "tags": tags }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::UNPROCESSABLE_ENTITY;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<Json>().await.unwrap();
+    assert_eq!(body["code"], "validation_failed");
+    let fields = &body["details"]["fields"];
+    assert_eq!(
+      fields["name"],
+      json!([{ "code": "blank", "message": "must not be blank" }])
+    );
+    assert_eq!(fields["tags"][0]["code"], "length");
+
+    // Cats from the database:
+    let count = Cat::count(doc! {}).await.unwrap();
+    assert_eq!(count, 0, "Invalid cats should not be stored");
+  });
+}
+
+#[test]
+fn post_cat_route_with_wrong_field_type() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .post("http://localhost:8088/v1/cats")
+      .header("Authorization", format!("Bearer {}", token))
+      .json(&json!({ "name": 1 }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::UNPROCESSABLE_ENTITY;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<Json>().await.unwrap();
+    assert_eq!(body["code"], "validation_failed");
+    let message = body["message"].as_str().unwrap();
+    assert!(
+      message.contains("name"),
+      "Message should name the offending field: {}",
+      message
+    );
+    assert!(
+      message.contains("expected a string"),
+      "Message should name the expected type: {}",
+      message
+    );
+  });
+}
+
+#[test]
+fn post_cat_route_with_invalid_json() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .post("http://localhost:8088/v1/cats")
+      .header("Authorization", format!("Bearer {}", token))
+      .header("Content-Type", "application/json")
+      .body(r#"{ "name": "#)
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::BAD_REQUEST;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<Json>().await.unwrap();
+    assert_eq!(body["code"], "malformed_payload");
+    assert!(body["message"].is_string());
+  });
+}
+
+#[test]
+fn created_cat_id_round_trip() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user.clone()).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .post("http://localhost:8088/v1/cats")
+      .header("Authorization", format!("Bearer {}", token))
+      .json(&json!({ "name": "Tigrin" }))
+      .send()
+      .await
+      .unwrap();
+
+    let status_code = res.status();
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    // The id should be a plain hex string, not an extended JSON `{"$oid": ...}`
+    // object.
+    let body = res.json::<Json>().await.unwrap();
+    let id = body["id"].as_str().expect("Cat id should be a string");
+    assert_eq!(id.len(), 24);
+    assert!(id.chars().all(|c| c.is_ascii_hexdigit()));
+    assert!(body["user"].is_string(), "Cat user should be a string");
+
+    let res = client
+      .get(format!("http://localhost:8088/v1/cats/{}", id))
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<Json>().await.unwrap();
+    assert_eq!(body["id"], id);
+    assert_eq!(body["name"], "Tigrin");
+  });
+}
+
+#[test]
+fn get_cats_route_as_csv() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user.clone()).await.unwrap();
+
+    let mut tigrin = Cat::new(user.id.unwrap(), "Tigrin".to_owned());
+    tigrin.tags = vec!["orange".to_owned(), "lazy".to_owned()];
+    Cat::create(tigrin).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .get("http://localhost:8088/v1/cats?fields=name,tags")
+      .header("Authorization", format!("Bearer {}", token))
+      .header("Accept", "text/csv")
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Response headers:
+    let headers = res.headers();
+    assert_eq!(
+      headers.get("Content-Type").unwrap(),
+      "text/csv; charset=utf-8"
+    );
+    assert_eq!(headers.get("X-Pagination-Count").unwrap(), "1");
+
+    // Body:
+    let body = res.text().await.unwrap();
+    let mut lines = body.lines();
+    assert_eq!(lines.next(), Some("id,name,tags"));
+    let row = lines.next().unwrap();
+    assert!(
+      row.ends_with(r#",Tigrin,"[""orange"",""lazy""]""#),
+      "Row should hold the cat fields: {}",
+      row
+    );
+    assert_eq!(lines.next(), None);
+
+    // The query parameter takes precedence over the header:
+    let res = client
+      .get("http://localhost:8088/v1/cats?format=json")
+      .header("Authorization", format!("Bearer {}", token))
+      .header("Accept", "text/csv")
+      .send()
+      .await
+      .unwrap();
+    let content_type = res.headers().get("Content-Type").unwrap();
+    assert_eq!(content_type, "application/json");
+
+    // Unknown formats are rejected:
+    let res = client
+      .get("http://localhost:8088/v1/cats?format=xml")
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+  });
+}
+
+#[test]
+fn get_cats_route_with_invalid_pagination() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .get("http://localhost:8088/v1/cats?limit=ten")
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::BAD_REQUEST;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<Json>().await.unwrap();
+    assert_eq!(body["code"], "invalid_query");
+  });
+}
+
+#[test]
+fn get_cat_by_id_route_with_request_id() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .get("http://localhost:8088/v1/cats/not-an-id")
+      .header("Authorization", format!("Bearer {}", token))
+      .header("X-Request-Id", "support-1234")
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::BAD_REQUEST;
+    assert_eq!(actual, expected);
+
+    // Headers:
+    let request_id = res.headers().get("x-request-id").unwrap();
+    assert_eq!(request_id, "support-1234");
+
+    // Body:
+    let body = res.json::<Json>().await.unwrap();
+    let expected = json!({
+      "code": "invalid_id",
+      "message": "Error parsing ObjectID not-an-id",
+      "details": { "id": "not-an-id" },
+      "request_id": "support-1234"
+    });
+    assert_eq!(body, expected);
+  });
+}
+
+#[test]
+fn remove_cat_by_id_route_returning_cat() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user.clone()).await.unwrap();
+
+    let tigrin = Cat::new(user.id.unwrap(), "Tigrin".to_owned());
+    let tigrin = Cat::create(tigrin).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .delete(format!(
+        "http://localhost:8088/v1/cats/{}?return=true",
+        tigrin.id.unwrap()
+      ))
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<PublicCat>().await.unwrap();
+    assert_eq!(body.id, tigrin.id.unwrap());
+    assert_eq!(body.name, "Tigrin");
+
+    // Cat from the database
+    let cat = Cat::find_by_id(&tigrin.id.unwrap()).await.unwrap();
+    assert!(cat.is_none(), "Cat should be removed from the database");
+  });
+}
+
+#[test]
+fn remove_other_user_cat_by_id_route() {
+  use_app(async move {
+    let owner = create_user("nico@test.com").await.unwrap();
+    let user = create_user("nahuel@test.com").await.unwrap();
+    let token = create_user_token(user).await.unwrap();
+
+    let tigrin = Cat::new(owner.id.unwrap(), "Tigrin".to_owned());
+    let tigrin = Cat::create(tigrin).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .delete(format!(
+        "http://localhost:8088/v1/cats/{}?return=true",
+        tigrin.id.unwrap()
+      ))
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::NOT_FOUND;
+    assert_eq!(actual, expected);
+
+    // Cat from the database
+    let cat = Cat::find_by_id(&tigrin.id.unwrap()).await.unwrap();
+    assert!(cat.is_some(), "Cat should not be removed from the database");
+  });
+}
+
+#[test]
+fn get_cat_by_id_route_with_camel_case() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user.clone()).await.unwrap();
+
+    let cholin = Cat::new(user.id.unwrap(), "Cholin".to_owned());
+    let cholin = Cat::create(cholin).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .get(format!(
+        "http://localhost:8088/v1/cats/{}",
+        cholin.id.unwrap()
+      ))
+      .header("Authorization", format!("Bearer {}", token))
+      .header("X-Json-Casing", "camel")
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<Json>().await.unwrap();
+    assert!(body["createdAt"].is_string());
+    assert!(body["updatedAt"].is_string());
+    assert!(body.get("created_at").is_none());
+  });
+}
+
+#[test]
+fn update_cat_by_id_route() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user.clone()).await.unwrap();
+
+    let tigrin = Cat::new(user.id.unwrap(), "Tigrin".to_owned());
+    let tigrin = Cat::create(tigrin).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .put(format!(
+        "http://localhost:8088/v1/cats/{}",
+        tigrin.id.unwrap()
+      ))
+      .header("Authorization", format!("Bearer {}", token))
+      .header("If-Match", format!("\"{}\"", tigrin.version))
+      .json(&json!({ "name": "Tigrincito" }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<PublicCat>().await.unwrap();
+    assert_eq!(body.name, "Tigrincito");
+    assert_eq!(body.version, tigrin.version + 1, "Version should increment");
+  });
+}
+
+#[test]
+fn update_cat_by_id_route_with_stale_version() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user.clone()).await.unwrap();
+
+    let tigrin = Cat::new(user.id.unwrap(), "Tigrin".to_owned());
+    let tigrin = Cat::create(tigrin).await.unwrap();
+    let url = format!("http://localhost:8088/v1/cats/{}", tigrin.id.unwrap());
+
+    let client = reqwest::Client::new();
+    let res = client
+      .put(&url)
+      .header("Authorization", format!("Bearer {}", token))
+      .header("If-Match", tigrin.version.to_string())
+      .json(&json!({ "name": "Tigrincito" }))
+      .send()
+      .await
+      .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    // Second update with the version the first one already consumed.
+    let res = client
+      .put(&url)
+      .header("Authorization", format!("Bearer {}", token))
+      .header("If-Match", tigrin.version.to_string())
+      .json(&json!({ "name": "Cholin" }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::CONFLICT;
+    assert_eq!(actual, expected);
+
+    // Cat from the database:
+    let cat = Cat::find_by_id(&tigrin.id.unwrap()).await.unwrap().unwrap();
+    assert_eq!(cat.name, "Tigrincito", "Stale update should not be applied");
+  });
+}
+
+#[test]
+fn update_cat_by_id_route_without_version() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user.clone()).await.unwrap();
+
+    let tigrin = Cat::new(user.id.unwrap(), "Tigrin".to_owned());
+    let tigrin = Cat::create(tigrin).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .put(format!(
+        "http://localhost:8088/v1/cats/{}",
+        tigrin.id.unwrap()
+      ))
+      .header("Authorization", format!("Bearer {}", token))
+      .json(&json!({ "name": "Tigrincito" }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::PRECONDITION_REQUIRED;
+    assert_eq!(actual, expected);
+  });
+}
+
+#[test]
+fn post_cat_route_with_tags() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user.clone()).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .post("http://localhost:8088/v1/cats")
+      .header("Authorization", format!("Bearer {}", token))
+      .json(&json!({ "name": "Tigrin", "tags": [" Orange ", "lazy", "orange", ""] }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::CREATED;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<PublicCat>().await.unwrap();
+    assert_eq!(
+      body.tags,
+      vec!["orange", "lazy"],
+      "Tags should be trimmed, lowercased and deduplicated"
+    );
+  });
+}
+
+#[test]
+fn get_cats_route_filtered_by_tag() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user.clone()).await.unwrap();
+
+    let mut tigrin = Cat::new(user.id.unwrap(), "Tigrin".to_owned());
+    tigrin.tags = vec!["orange".to_owned(), "lazy".to_owned()];
+    Cat::create(tigrin).await.unwrap();
+
+    let mut cielito = Cat::new(user.id.unwrap(), "Cielito".to_owned());
+    cielito.tags = vec!["black".to_owned(), "lazy".to_owned()];
+    Cat::create(cielito).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .get("http://localhost:8088/v1/cats?tags=Orange")
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Response pagination headers:
+    let headers = res.headers();
+    assert_eq!(headers.get("X-Pagination-Count").unwrap(), "1");
+
+    // Body:
+    let body = res.json::<Vec<PublicCat>>().await.unwrap();
+    assert_eq!(body.len(), 1, "Should return one cat");
+    assert_eq!(body.first().unwrap().name, "Tigrin");
+  });
+}
+
+#[test]
+fn get_cats_route_filtered_by_multiple_tags() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user.clone()).await.unwrap();
+
+    let mut tigrin = Cat::new(user.id.unwrap(), "Tigrin".to_owned());
+    tigrin.tags = vec!["orange".to_owned(), "lazy".to_owned()];
+    Cat::create(tigrin).await.unwrap();
+
+    let mut cielito = Cat::new(user.id.unwrap(), "Cielito".to_owned());
+    cielito.tags = vec!["black".to_owned(), "lazy".to_owned()];
+    Cat::create(cielito).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .get("http://localhost:8088/v1/cats?tags=lazy,black")
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<Vec<PublicCat>>().await.unwrap();
+    assert_eq!(body.len(), 1, "Cats should match all tags");
+    assert_eq!(body.first().unwrap().name, "Cielito");
+  });
+}
+
+#[test]
+fn get_cats_route_filtered_by_single_tag() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user.clone()).await.unwrap();
+
+    let mut tigrin = Cat::new(user.id.unwrap(), "Tigrin".to_owned());
+    tigrin.tags = vec!["orange".to_owned(), "lazy".to_owned()];
+    Cat::create(tigrin).await.unwrap();
+
+    let mut cielito = Cat::new(user.id.unwrap(), "Cielito".to_owned());
+    cielito.tags = vec!["black".to_owned(), "lazy".to_owned()];
+    Cat::create(cielito).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .get("http://localhost:8088/v1/cats?tag=Black")
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<Vec<PublicCat>>().await.unwrap();
+    assert_eq!(body.len(), 1, "Should return one cat");
+    assert_eq!(body.first().unwrap().name, "Cielito");
+  });
+}
+
+#[test]
+fn get_cat_tags_route() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user.clone()).await.unwrap();
+
+    let mut tigrin = Cat::new(user.id.unwrap(), "Tigrin".to_owned());
+    tigrin.tags = vec!["orange".to_owned(), "lazy".to_owned()];
+    Cat::create(tigrin).await.unwrap();
+
+    let mut cielito = Cat::new(user.id.unwrap(), "Cielito".to_owned());
+    cielito.tags = vec!["black".to_owned(), "lazy".to_owned()];
+    Cat::create(cielito).await.unwrap();
+
+    // Removed cats and cats of other users are not counted.
+    let mut removed = Cat::new(user.id.unwrap(), "Removed".to_owned());
+    removed.tags = vec!["black".to_owned()];
+    removed.deleted_at = Some(date::now());
+    Cat::create(removed).await.unwrap();
+
+    let mut other = Cat::new(ObjectId::new(), "Other".to_owned());
+    other.tags = vec!["orange".to_owned()];
+    Cat::create(other).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .get("http://localhost:8088/v1/cats/tags")
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<Json>().await.unwrap();
+    let expected = json!([
+      { "tag": "lazy", "count": 2 },
+      { "tag": "black", "count": 1 },
+      { "tag": "orange", "count": 1 }
+    ]);
+    assert_eq!(body, expected);
+  });
+}
+
+#[test]
+fn post_cat_tags_route() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user.clone()).await.unwrap();
+
+    let mut tigrin = Cat::new(user.id.unwrap(), "Tigrin".to_owned());
+    tigrin.tags = vec!["orange".to_owned()];
+    let tigrin = Cat::create(tigrin).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .post(format!(
+        "http://localhost:8088/v1/cats/{}/tags",
+        tigrin.id.unwrap()
+      ))
+      .header("Authorization", format!("Bearer {}", token))
+      .json(&json!({ "tags": ["Lazy", "orange"] }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<PublicCat>().await.unwrap();
+    assert_eq!(body.tags, vec!["orange", "lazy"]);
+  });
+}
+
+#[test]
+fn post_cat_tags_route_with_too_many_tags() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user.clone()).await.unwrap();
+
+    let mut tigrin = Cat::new(user.id.unwrap(), "Tigrin".to_owned());
+    tigrin.tags = (0..20).map(|index| format!("tag-{}", index)).collect();
+    let tigrin = Cat::create(tigrin).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .post(format!(
+        "http://localhost:8088/v1/cats/{}/tags",
+        tigrin.id.unwrap()
+      ))
+      .header("Authorization", format!("Bearer {}", token))
+      .json(&json!({ "tags": ["lazy"] }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::UNPROCESSABLE_ENTITY;
+    assert_eq!(actual, expected);
+
+    let cat = Cat::find_by_id(&tigrin.id.unwrap()).await.unwrap().unwrap();
+    assert_eq!(cat.tags.len(), 20);
+  });
+}
+
+#[test]
+fn remove_cat_tag_route() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user.clone()).await.unwrap();
+
+    let mut tigrin = Cat::new(user.id.unwrap(), "Tigrin".to_owned());
+    tigrin.tags = vec!["orange".to_owned(), "lazy".to_owned()];
+    let tigrin = Cat::create(tigrin).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .delete(format!(
+        "http://localhost:8088/v1/cats/{}/tags/Lazy",
+        tigrin.id.unwrap()
+      ))
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<PublicCat>().await.unwrap();
+    assert_eq!(body.tags, vec!["orange"]);
+  });
+}
+
+#[test]
+fn update_cat_by_id_route_with_tags() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user.clone()).await.unwrap();
+
+    let mut tigrin = Cat::new(user.id.unwrap(), "Tigrin".to_owned());
+    tigrin.tags = vec!["orange".to_owned()];
+    let tigrin = Cat::create(tigrin).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .put(format!(
+        "http://localhost:8088/v1/cats/{}",
+        tigrin.id.unwrap()
+      ))
+      .header("Authorization", format!("Bearer {}", token))
+      .header("If-Match", tigrin.version.to_string())
+      .json(&json!({ "name": "Tigrin", "tags": ["LAZY", " lazy"] }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<PublicCat>().await.unwrap();
+    assert_eq!(body.tags, vec!["lazy"]);
+  });
+}
+
+#[test]
+fn get_cats_route_with_no_cats() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user.clone()).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .get("http://localhost:8088/v1/cats")
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Response pagination headers:
+    let headers = res.headers();
+    assert_eq!(headers.get("X-Pagination-Count").unwrap(), "0");
+    assert_eq!(headers.get("X-Pagination-Offset").unwrap(), "0");
+    assert_eq!(headers.get("X-Pagination-Limit").unwrap(), "100");
+    assert_eq!(headers.get("X-Pagination-Total-Pages").unwrap(), "0");
+    assert_eq!(headers.get("X-Pagination-Has-Next").unwrap(), "false");
+
+    // Body:
+    let body = res.json::<Vec<PublicCat>>().await.unwrap();
+    assert!(body.is_empty(), "Should return an empty list");
+  });
+}
+
+#[test]
+fn get_cats_route_with_offset_out_of_range() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user.clone()).await.unwrap();
+
+    let tigrin = Cat::new(user.id.unwrap(), "Tigrin".to_owned());
+    Cat::create(tigrin).await.unwrap();
+
+    let cielito = Cat::new(user.id.unwrap(), "Cielito".to_owned());
+    Cat::create(cielito).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .get("http://localhost:8088/v1/cats?offset=10&limit=1")
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Response pagination headers:
+    let headers = res.headers();
+    assert_eq!(headers.get("X-Pagination-Count").unwrap(), "2");
+    assert_eq!(headers.get("X-Pagination-Offset").unwrap(), "10");
+    assert_eq!(headers.get("X-Pagination-Limit").unwrap(), "1");
+    assert_eq!(headers.get("X-Pagination-Total-Pages").unwrap(), "2");
+    assert_eq!(headers.get("X-Pagination-Has-Next").unwrap(), "false");
+
+    // Body:
+    let body = res.json::<Vec<PublicCat>>().await.unwrap();
+    assert!(body.is_empty(), "Should return an empty list");
+  });
+}
+
+#[test]
+fn get_cats_route_with_compression() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user.clone()).await.unwrap();
+
+    let cats = (0..30)
+      .map(|index| Cat::new(user.id.unwrap(), format!("Tigrin {}", index)))
+      .collect::<Vec<Cat>>();
+    Cat::create_many(cats).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .get("http://localhost:8088/v1/cats")
+      .header("Authorization", format!("Bearer {}", token))
+      .header("Accept-Encoding", "gzip")
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Response headers:
+    assert_eq!(res.headers().get("content-encoding").unwrap(), "gzip");
+
+    // Without accepted encodings:
+    let res = client
+      .get("http://localhost:8088/v1/cats")
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+    assert!(res.headers().get("content-encoding").is_none());
+  });
+}
+
+#[test]
+fn get_cats_route_with_cursor() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user.clone()).await.unwrap();
+
+    for name in ["Tigrin", "Cielito", "Mimi"] {
+      Cat::create(Cat::new(user.id.unwrap(), name.to_owned()))
+        .await
+        .unwrap();
+    }
+
+    let client = reqwest::Client::new();
+    let res = client
+      .get("http://localhost:8088/v1/cats?limit=2")
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Response pagination headers:
+    let cursor = res
+      .headers()
+      .get("X-Pagination-Next-Cursor")
+      .unwrap()
+      .to_str()
+      .unwrap()
+      .to_owned();
+
+    // Body:
+    let body = res.json::<Vec<PublicCat>>().await.unwrap();
+    assert_eq!(body.len(), 2);
+    assert_eq!(body[0].name, "Mimi");
+    assert_eq!(body[1].name, "Cielito");
+
+    let res = client
+      .get(format!(
+        "http://localhost:8088/v1/cats?limit=2&cursor={}",
+        cursor
+      ))
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Response pagination headers:
+    let headers = res.headers();
+    // The count covers every page, not only the ones after the cursor.
+    assert_eq!(headers.get("X-Pagination-Count").unwrap(), "3");
+    assert_eq!(headers.get("X-Pagination-Has-Next").unwrap(), "false");
+    assert!(headers.get("X-Pagination-Next-Cursor").is_none());
+
+    // Body:
+    let body = res.json::<Vec<PublicCat>>().await.unwrap();
+    assert_eq!(body.len(), 1);
+    assert_eq!(body[0].name, "Tigrin");
+  });
+}
+
+#[test]
+fn get_cats_route_with_invalid_cursor() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .get("http://localhost:8088/v1/cats?cursor=nope")
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::BAD_REQUEST;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<Json>().await.unwrap();
+    assert_eq!(body["code"], "invalid_query");
+  });
+}
+
+#[test]
+fn get_cats_route_sorted_and_filtered_by_name() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user.clone()).await.unwrap();
+
+    for name in ["Tigrin", "Cielito", "Tigre.Mimi", "Tiger"] {
+      Cat::create(Cat::new(user.id.unwrap(), name.to_owned()))
+        .await
+        .unwrap();
+    }
+
+    let client = reqwest::Client::new();
+    let res = client
+      .get("http://localhost:8088/v1/cats?sort=name&order=asc&name_contains=tig")
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<Vec<PublicCat>>().await.unwrap();
+    let names = body.iter().map(|cat| cat.name.as_str()).collect::<Vec<_>>();
+    assert_eq!(names, vec!["Tiger", "Tigre.Mimi", "Tigrin"]);
+
+    // The text is matched literally.
+    let res = client
+      .get("http://localhost:8088/v1/cats?name_contains=.")
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    let body = res.json::<Vec<PublicCat>>().await.unwrap();
+    assert_eq!(body.len(), 1);
+    assert_eq!(body[0].name, "Tigre.Mimi");
+  });
+}
+
+#[test]
+fn get_cats_route_with_unsupported_sort() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .get("http://localhost:8088/v1/cats?sort=user")
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::UNPROCESSABLE_ENTITY;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<Json>().await.unwrap();
+    assert_eq!(body["code"], "unsupported_field");
+    assert_eq!(
+      body["details"],
+      json!({
+        "parameter": "sort",
+        "field": "user",
+        "allowed": ["created_at", "updated_at", "name"]
+      })
+    );
+  });
+}
+
+#[test]
+fn get_cats_route_with_filters() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user.clone()).await.unwrap();
+
+    for (name, version) in [("Tigrin", 1), ("Cielito", 2), ("Mimi", 3)] {
+      let mut cat = Cat::new(user.id.unwrap(), name.to_owned());
+      cat.version = version;
+      Cat::create(cat).await.unwrap();
+    }
+
+    let client = reqwest::Client::new();
+    let res = client
+      .get("http://localhost:8088/v1/cats?sort=name&order=asc&filter[version][gte]=2")
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<Vec<PublicCat>>().await.unwrap();
+    let names = body.iter().map(|cat| cat.name.as_str()).collect::<Vec<_>>();
+    assert_eq!(names, vec!["Cielito", "Mimi"]);
+
+    let res = client
+      .get("http://localhost:8088/v1/cats?filter[name][in]=Tigrin,Mimi&filter[version][ne]=3")
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    let body = res.json::<Vec<PublicCat>>().await.unwrap();
+    assert_eq!(body.len(), 1);
+    assert_eq!(body[0].name, "Tigrin");
+  });
+}
+
+#[test]
+fn get_cats_route_with_unsupported_filter() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user).await.unwrap();
+    let other = create_user("other@test.com").await.unwrap();
+    Cat::create(Cat::new(other.id.unwrap(), "Tigrin".to_owned()))
+      .await
+      .unwrap();
+
+    // The owner of the cats isn't filterable, so the ownership filter can't
+    // be replaced.
+    let client = reqwest::Client::new();
+    let res = client
+      .get(format!(
+        "http://localhost:8088/v1/cats?filter[user]={}",
+        other.id.unwrap()
+      ))
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let body = res.json::<Json>().await.unwrap();
+    assert_eq!(body["code"], "unsupported_field");
+    assert_eq!(body["details"]["field"], "user");
+    assert_eq!(body["details"]["allowed"][1], "name");
+
+    // Values are converted to the type of the field, operators can't be
+    // injected.
+    let res = client
+      .get("http://localhost:8088/v1/cats?filter[version]={\"$gt\":0}")
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    let body = res.json::<Json>().await.unwrap();
+    assert_eq!(body["code"], "invalid_query");
+
+    let res = client
+      .get("http://localhost:8088/v1/cats?filter[name][regex]=.*")
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+  });
+}
+
+#[test]
+fn get_cats_route_created_after() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user.clone()).await.unwrap();
+
+    let mut tigrin = Cat::new(user.id.unwrap(), "Tigrin".to_owned());
+    tigrin.created_at = Date::from_millis(0);
+    Cat::create(tigrin).await.unwrap();
+    Cat::create(Cat::new(user.id.unwrap(), "Cielito".to_owned()))
+      .await
+      .unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .get("http://localhost:8088/v1/cats?created_after=2000-01-01T00:00:00Z")
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<Vec<PublicCat>>().await.unwrap();
+    assert_eq!(body.len(), 1);
+    assert_eq!(body[0].name, "Cielito");
+  });
+}
+
+#[test]
+fn get_cats_route_with_fields() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user.clone()).await.unwrap();
+
+    let tigrin = Cat::create(Cat::new(user.id.unwrap(), "Tigrin".to_owned()))
+      .await
+      .unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .get("http://localhost:8088/v1/cats?fields=name,user")
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<Json>().await.unwrap();
+    let expected = json!([{
+      "id": tigrin.id.unwrap().to_hex(),
+      "name": "Tigrin",
+      "user": user.id.unwrap().to_hex(),
+    }]);
+    assert_eq!(body, expected);
+
+    let res = client
+      .get("http://localhost:8088/v1/cats?fields=name,hash")
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::BAD_REQUEST;
+    assert_eq!(actual, expected);
+  });
+}
+
+#[test]
+fn post_cats_bulk_route() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user.clone()).await.unwrap();
+
+    let body = json!([
+      { "name": "Tigrin", "tags": ["Orange"] },
+      { "name": "Cielito", "organization": "not-an-id" },
+      { "name": "Mimi", "organization": ObjectId::new().to_hex() },
+      { "name": "Michi" }
+    ]);
+
+    let client = reqwest::Client::new();
+    let res = client
+      .post("http://localhost:8088/v1/cats/bulk")
+      .header("Authorization", format!("Bearer {}", token))
+      .json(&body)
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<Json>().await.unwrap();
+    let results = body.as_array().unwrap();
+    assert_eq!(results.len(), 4);
+    assert_eq!(results[0]["status"], 201);
+    assert_eq!(results[0]["cat"]["name"], "Tigrin");
+    assert_eq!(results[0]["cat"]["tags"], json!(["orange"]));
+    assert_eq!(results[1]["status"], 400);
+    assert_eq!(results[1]["error"]["code"], "invalid_id");
+    assert_eq!(results[2]["status"], 404);
+    assert_eq!(results[3]["status"], 201);
+    assert_eq!(results[3]["cat"]["name"], "Michi");
+
+    // Only the valid items are stored:
+    let cats = Cat::find(doc! { "user": user.id.unwrap() }, None)
+      .await
+      .unwrap();
+    assert_eq!(cats.len(), 2);
+    let id = results[3]["cat"]["id"].as_str().unwrap();
+    assert!(cats.iter().any(|cat| cat.id.unwrap().to_hex() == id));
+  });
+}
+
+#[test]
+fn post_cats_bulk_route_with_empty_payload() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .post("http://localhost:8088/v1/cats/bulk")
+      .header("Authorization", format!("Bearer {}", token))
+      .json(&json!([]))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::BAD_REQUEST;
+    assert_eq!(actual, expected);
+  });
+}
+
+#[test]
+fn remove_cats_bulk_route() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user.clone()).await.unwrap();
+    let other = create_user("other@test.com").await.unwrap();
+
+    let tigrin = Cat::create(Cat::new(user.id.unwrap(), "Tigrin".to_owned()))
+      .await
+      .unwrap();
+    let cielito = Cat::create(Cat::new(user.id.unwrap(), "Cielito".to_owned()))
+      .await
+      .unwrap();
+    let mimi = Cat::create(Cat::new(other.id.unwrap(), "Mimi".to_owned()))
+      .await
+      .unwrap();
+
+    let tigrin_id = tigrin.id.unwrap().to_hex();
+    let mimi_id = mimi.id.unwrap().to_hex();
+    let body = json!({ "ids": [tigrin_id, "not-an-id", mimi_id] });
+
+    let client = reqwest::Client::new();
+    let res = client
+      .delete("http://localhost:8088/v1/cats/bulk")
+      .header("Authorization", format!("Bearer {}", token))
+      .json(&body)
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Headers:
+    let request_id = res.headers().get("x-request-id").unwrap();
+    let request_id = request_id.to_str().unwrap().to_owned();
+
+    // Body:
+    let body = res.json::<Json>().await.unwrap();
+    let expected = json!([
+      { "id": tigrin_id, "status": 204 },
+      {
+        "id": "not-an-id",
+        "status": 400,
+        "error": {
+          "code": "invalid_id",
+          "message": "Error parsing ObjectID not-an-id",
+          "details": { "id": "not-an-id" },
+          "request_id": request_id
+        }
+      },
+      {
+        "id": mimi_id,
+        "status": 404,
+        "error": {
+          "code": "not_found",
+          "message": "Not found",
+          "details": null,
+          "request_id": request_id
+        }
+      }
+    ]);
+    assert_eq!(body, expected);
+
+    // Only the cat of the user is removed:
+    let remaining = Cat::find(doc! {}, None).await.unwrap();
+    let remaining = remaining
+      .iter()
+      .map(|cat| cat.id.unwrap())
+      .collect::<Vec<ObjectId>>();
+    assert_eq!(remaining.len(), 2);
+    assert!(remaining.contains(&cielito.id.unwrap()));
+    assert!(remaining.contains(&mimi.id.unwrap()));
+  });
+}
+
+#[test]
+fn restore_cat_by_id_route() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user.clone()).await.unwrap();
+
+    let tigrin = Cat::new(user.id.unwrap(), "Tigrin".to_owned());
+    let tigrin = Cat::create(tigrin).await.unwrap();
+    let id = tigrin.id.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .delete(format!("http://localhost:8088/v1/cats/{}", id))
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+    // Removed cats are kept in the database:
+    let query = doc! { "_id": id, "deleted_at": { "$ne": null } };
+    let removed = Cat::find_one(query, None).await.unwrap();
+    assert!(removed.is_some(), "Cat should be soft deleted");
+
+    let res = client
+      .get(format!("http://localhost:8088/v1/cats/{}", id))
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    let res = client
+      .post(format!("http://localhost:8088/v1/cats/{}/restore", id))
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<PublicCat>().await.unwrap();
+    assert_eq!(body.id, id);
+    assert_eq!(body.name, "Tigrin");
+
+    let cat = Cat::find_by_id(&id).await.unwrap();
+    assert!(cat.is_some(), "Cat should be restored");
+
+    // Cats that aren't removed can't be restored:
+    let res = client
+      .post(format!("http://localhost:8088/v1/cats/{}/restore", id))
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+  });
+}
+
+#[test]
+fn patch_cat_by_id_route() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user.clone()).await.unwrap();
+
+    let mut tigrin = Cat::new(user.id.unwrap(), "Tigrin".to_owned());
+    tigrin.tags = vec!["orange".to_owned()];
+    let tigrin = Cat::create(tigrin).await.unwrap();
+    let url = format!("http://localhost:8088/v1/cats/{}", tigrin.id.unwrap());
+
+    let client = reqwest::Client::new();
+    let res = client
+      .patch(&url)
+      .header("Authorization", format!("Bearer {}", token))
+      .header("If-Match", tigrin.version.to_string())
+      .header("Content-Type", "application/merge-patch+json")
+      .body(json!({ "name": "Tigrincito" }).to_string())
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<PublicCat>().await.unwrap();
+    assert_eq!(body.name, "Tigrincito");
+    assert_eq!(body.tags, vec!["orange"], "Tags should be left untouched");
+    assert_eq!(body.version, tigrin.version + 1, "Version should increment");
+
+    // Clearing the tags:
+    let res = client
+      .patch(&url)
+      .header("Authorization", format!("Bearer {}", token))
+      .header("If-Match", body.version.to_string())
+      .header("Content-Type", "application/merge-patch+json")
+      .body(json!({ "tags": null }).to_string())
+      .send()
+      .await
+      .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = res.json::<PublicCat>().await.unwrap();
+    assert_eq!(body.name, "Tigrincito");
+    assert!(body.tags.is_empty());
+  });
+}
+
+#[test]
+fn patch_cat_by_id_route_with_invalid_patch() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user.clone()).await.unwrap();
+
+    let tigrin = Cat::new(user.id.unwrap(), "Tigrin".to_owned());
+    let tigrin = Cat::create(tigrin).await.unwrap();
+    let url = format!("http://localhost:8088/v1/cats/{}", tigrin.id.unwrap());
+
+    let client = reqwest::Client::new();
+    for patch in [
+      json!({ "name": null }),
+      json!({ "name": " " }),
+      json!({ "user": "64b7f0f0f0f0f0f0f0f0f0f0" }),
+    ] {
+      let res = client
+        .patch(&url)
+        .header("Authorization", format!("Bearer {}", token))
+        .header("If-Match", tigrin.version.to_string())
+        .header("Content-Type", "application/merge-patch+json")
+        .body(patch.to_string())
+        .send()
+        .await
+        .unwrap();
+
+      // Status code:
+      let status_code = res.status();
+      let actual = status_code;
+      let expected = StatusCode::UNPROCESSABLE_ENTITY;
+      assert_eq!(actual, expected, "Patch: {}", patch);
+    }
+
+    // Cat from the database:
+    let cat = Cat::find_by_id(&tigrin.id.unwrap()).await.unwrap().unwrap();
+    assert_eq!(cat.name, "Tigrin");
+    assert_eq!(cat.version, tigrin.version);
+  });
+}
+
+#[test]
+fn patch_cat_by_id_route_with_stale_version() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user.clone()).await.unwrap();
+
+    let tigrin = Cat::new(user.id.unwrap(), "Tigrin".to_owned());
+    let tigrin = Cat::create(tigrin).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .patch(format!(
+        "http://localhost:8088/v1/cats/{}",
+        tigrin.id.unwrap()
+      ))
+      .header("Authorization", format!("Bearer {}", token))
+      .header("If-Match", (tigrin.version - 1).to_string())
+      .header("Content-Type", "application/merge-patch+json")
+      .body(json!({ "name": "Tigrincito" }).to_string())
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::CONFLICT;
+    assert_eq!(actual, expected);
+  });
+}
+
+#[test]
+fn post_cat_share_route() {
+  use_app(async move {
+    let owner = create_user("nico@test.com").await.unwrap();
+    let owner_token = create_user_token(owner.clone()).await.unwrap();
+    let friend = create_user("lucas@test.com").await.unwrap();
+    let friend_token = create_user_token(friend.clone()).await.unwrap();
+    let tigrin = Cat::new(owner.id.unwrap(), "Tigrin".to_owned());
+    let tigrin = Cat::create(tigrin).await.unwrap();
+    let url = format!("http://localhost:8088/v1/cats/{}", tigrin.id.unwrap());
+
+    let client = reqwest::Client::new();
+    let res = client
+      .post(format!("{}/shares", url))
+      .header("Authorization", format!("Bearer {}", owner_token))
+      .json(&json!({ "email": "lucas@test.com", "access": "read" }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::CREATED;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<Json>().await.unwrap();
+    assert_eq!(body["user"], friend.id.unwrap().to_hex());
+    assert_eq!(body["access"], "read");
+
+    // The cat can be looked up, but not changed.
+    let res = client
+      .get(&url)
+      .header("Authorization", format!("Bearer {}", friend_token))
+      .send()
+      .await
+      .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = client
+      .put(&url)
+      .header("Authorization", format!("Bearer {}", friend_token))
+      .header("If-Match", tigrin.version.to_string())
+      .json(&json!({ "name": "Tigris" }))
+      .send()
+      .await
+      .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+  });
+}
+
+#[test]
+fn post_cat_share_route_with_read_write_access() {
+  use_app(async move {
+    let owner = create_user("nico@test.com").await.unwrap();
+    let owner_token = create_user_token(owner.clone()).await.unwrap();
+    let friend = create_user("lucas@test.com").await.unwrap();
+    let friend_token = create_user_token(friend.clone()).await.unwrap();
+    let tigrin = Cat::new(owner.id.unwrap(), "Tigrin".to_owned());
+    let tigrin = Cat::create(tigrin).await.unwrap();
+    let url = format!("http://localhost:8088/v1/cats/{}", tigrin.id.unwrap());
+
+    let client = reqwest::Client::new();
+    let res = client
+      .post(format!("{}/shares", url))
+      .header("Authorization", format!("Bearer {}", owner_token))
+      .json(&json!({ "email": "lucas@test.com", "access": "read_write" }))
+      .send()
+      .await
+      .unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED);
+
+    let res = client
+      .put(&url)
+      .header("Authorization", format!("Bearer {}", friend_token))
+      .header("If-Match", tigrin.version.to_string())
+      .json(&json!({ "name": "Tigris" }))
+      .send()
+      .await
+      .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    // Only the owners can remove or share the cat.
+    let res = client
+      .delete(&url)
+      .header("Authorization", format!("Bearer {}", friend_token))
+      .send()
+      .await
+      .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    let res = client
+      .post(format!("{}/shares", url))
+      .header("Authorization", format!("Bearer {}", friend_token))
+      .json(&json!({ "email": "nico@test.com", "access": "read" }))
+      .send()
+      .await
+      .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+  });
+}
+
+#[test]
+fn remove_cat_share_route() {
+  use_app(async move {
+    let owner = create_user("nico@test.com").await.unwrap();
+    let owner_token = create_user_token(owner.clone()).await.unwrap();
+    let friend = create_user("lucas@test.com").await.unwrap();
+    let friend_token = create_user_token(friend.clone()).await.unwrap();
+    let tigrin = Cat::new(owner.id.unwrap(), "Tigrin".to_owned());
+    let tigrin = Cat::create(tigrin).await.unwrap();
+    let url = format!("http://localhost:8088/v1/cats/{}", tigrin.id.unwrap());
+
+    let client = reqwest::Client::new();
+    client
+      .post(format!("{}/shares", url))
+      .header("Authorization", format!("Bearer {}", owner_token))
+      .json(&json!({ "email": "lucas@test.com", "access": "read" }))
+      .send()
+      .await
+      .unwrap();
+
+    let res = client
+      .delete(format!("{}/shares/{}", url, friend.id.unwrap()))
+      .header("Authorization", format!("Bearer {}", owner_token))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::NO_CONTENT;
+    assert_eq!(actual, expected);
+
+    let res = client
+      .get(&url)
+      .header("Authorization", format!("Bearer {}", friend_token))
+      .send()
+      .await
+      .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+  });
+}
+
+#[test]
+fn search_cats_route() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user.clone()).await.unwrap();
+    let other = create_user("other@test.com").await.unwrap();
+
+    for name in ["Tigrin the Brave", "Cielito", "Tigrin"] {
+      Cat::create(Cat::new(user.id.unwrap(), name.to_owned()))
+        .await
+        .unwrap();
+    }
+    Cat::create(Cat::new(other.id.unwrap(), "Tigrin".to_owned()))
+      .await
+      .unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .get("http://localhost:8088/v1/cats/search?q=tigrin")
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Response pagination headers:
+    let headers = res.headers();
+    assert_eq!(headers.get("X-Pagination-Count").unwrap(), "2");
+
+    // Body:
+    let body = res.json::<Vec<PublicCat>>().await.unwrap();
+    assert!(body.iter().all(|cat| cat.user == user.id.unwrap()));
+    assert_eq!(body[0].name, "Tigrin", "Most relevant cat first");
+    assert_eq!(body[1].name, "Tigrin the Brave");
+
+    // Partial names fall back to the names starting with the text.
+    let res = client
+      .get("http://localhost:8088/v1/cats/search?q=cieli")
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    let body = res.json::<Vec<PublicCat>>().await.unwrap();
+    assert_eq!(body.len(), 1);
+    assert_eq!(body[0].name, "Cielito");
+  });
+}
+
+#[test]
+fn search_cats_route_without_text() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .get("http://localhost:8088/v1/cats/search?q=%20")
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::BAD_REQUEST;
+    assert_eq!(actual, expected);
+  });
+}
+
+#[test]
+fn cat_attachment_routes() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user.clone()).await.unwrap();
+    let tigrin = Cat::new(user.id.unwrap(), "Tigrin".to_owned());
+    let tigrin = Cat::create(tigrin).await.unwrap();
+    let url = format!("http://localhost:8088/v1/cats/{}", tigrin.id.unwrap());
+
+    let (content_type, body) = multipart_file("evidence/tigrin.csv", "text/csv", b"a,b\n1,2\n");
+    let client = reqwest::Client::new();
+    let res = client
+      .post(format!("{}/attachments", url))
+      .header("Authorization", format!("Bearer {}", token))
+      .header("Content-Type", content_type)
+      .body(body)
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::CREATED;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let attachment = res.json::<Json>().await.unwrap();
+    assert_eq!(attachment["filename"], "tigrin.csv");
+    assert_eq!(attachment["content_type"], "text/csv");
+    assert_eq!(attachment["size"], 8);
+    let attachment_id = attachment["id"].as_str().unwrap().to_owned();
+
+    // Attachments are listed on the cat.
+    let res = client
+      .get(&url)
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+    let cat = res.json::<PublicCat>().await.unwrap();
+    assert_eq!(cat.attachments.len(), 1);
+    assert_eq!(cat.attachments[0].id.to_hex(), attachment_id);
+
+    let attachment_url = format!("{}/attachments/{}", url, attachment_id);
+    let res = client
+      .get(&attachment_url)
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let headers = res.headers();
+    assert_eq!(headers.get("Content-Type").unwrap(), "text/csv");
+    assert_eq!(
+      headers.get("Content-Disposition").unwrap(),
+      "attachment; filename=\"tigrin.csv\""
+    );
+    assert_eq!(headers.get("X-Content-Type-Options").unwrap(), "nosniff");
+    assert_eq!(res.bytes().await.unwrap().as_ref(), b"a,b\n1,2\n");
+
+    let res = client
+      .delete(&attachment_url)
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+    let res = client
+      .get(&attachment_url)
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    let cat = Cat::find_by_id(&tigrin.id.unwrap()).await.unwrap().unwrap();
+    assert!(cat.attachments.is_empty());
+  });
+}
+
+#[test]
+fn post_cat_attachment_route_with_unsupported_type() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user.clone()).await.unwrap();
+    let tigrin = Cat::new(user.id.unwrap(), "Tigrin".to_owned());
+    let tigrin = Cat::create(tigrin).await.unwrap();
+
+    let (content_type, body) = multipart_file("tigrin.html", "text/html", b"<script></script>");
+    let client = reqwest::Client::new();
+    let res = client
+      .post(format!(
+        "http://localhost:8088/v1/cats/{}/attachments",
+        tigrin.id.unwrap()
+      ))
+      .header("Authorization", format!("Bearer {}", token))
+      .header("Content-Type", content_type)
+      .body(body)
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::UNPROCESSABLE_ENTITY;
+    assert_eq!(actual, expected);
+
+    let cat = Cat::find_by_id(&tigrin.id.unwrap()).await.unwrap().unwrap();
+    assert!(cat.attachments.is_empty());
+  });
+}
+
+#[test]
+fn post_cat_attachment_route_with_oversized_file() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user.clone()).await.unwrap();
+    let tigrin = Cat::new(user.id.unwrap(), "Tigrin".to_owned());
+    let tigrin = Cat::create(tigrin).await.unwrap();
+
+    // Over `attachments.max_size_bytes`, within the body limit of the route.
+    let content = vec![b'a'; 5000];
+    let (content_type, body) = multipart_file("tigrin.txt", "text/plain", &content);
+    let client = reqwest::Client::new();
+    let res = client
+      .post(format!(
+        "http://localhost:8088/v1/cats/{}/attachments",
+        tigrin.id.unwrap()
+      ))
+      .header("Authorization", format!("Bearer {}", token))
+      .header("Content-Type", content_type)
+      .body(body)
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::PAYLOAD_TOO_LARGE;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<Json>().await.unwrap();
+    assert_eq!(body["code"], "payload_too_large");
+    assert_eq!(body["details"]["limit_bytes"], 4096);
+
+    let cat = Cat::find_by_id(&tigrin.id.unwrap()).await.unwrap().unwrap();
+    assert!(cat.attachments.is_empty());
+  });
+}
+
+/// `multipart/form-data` body sending a file as the `file` field, with its
+/// content type.
+fn multipart_file(filename: &str, content_type: &str, content: &[u8]) -> (String, Vec<u8>) {
+  let boundary = "degen-test-boundary";
+  let mut body = format!(
+    "--{}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{}\"\r\nContent-Type: {}\r\n\r\n",
+    boundary, filename, content_type
+  )
+  .into_bytes();
+  body.extend_from_slice(content);
+  body.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+
+  (format!("multipart/form-data; boundary={}", boundary), body)
+}
@@ -0,0 +1,188 @@
+use reqwest;
+use reqwest::StatusCode;
+use serde_json::Value;
+use std::sync::Arc;
+
+use crate::models::cat::Cat;
+use crate::models::job::{JobKind, JobStatus, PublicJob};
+use crate::services::jobs;
+use crate::settings::SETTINGS;
+use crate::state::AppState;
+use crate::tests::setup::use_app;
+use crate::tests::utils::{create_authenticated_client, create_readonly_user, create_user};
+use crate::utils::models::ModelExt;
+
+#[test]
+fn post_me_export_route() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let other = create_user("john@test.com").await.unwrap();
+    Cat::create(Cat::new(user.id.unwrap(), "Tigrin".to_owned()))
+      .await
+      .unwrap();
+    Cat::create(Cat::new(other.id.unwrap(), "Cholin".to_owned()))
+      .await
+      .unwrap();
+
+    let client = create_authenticated_client(user).await.unwrap();
+    let res = client
+      .post("http://localhost:8088/v1/me/export")
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::ACCEPTED;
+    assert_eq!(actual, expected);
+
+    // Headers:
+    let location = res.headers()["location"].to_str().unwrap().to_owned();
+
+    // Body:
+    let body = res.json::<PublicJob>().await.unwrap();
+    assert_eq!(body.kind, JobKind::DataExport);
+    assert_eq!(body.status, JobStatus::Queued);
+    assert_eq!(location, format!("/v1/me/export/{}", body.id));
+
+    let res = client
+      .get(format!("http://localhost:8088{}", location))
+      .send()
+      .await
+      .unwrap();
+    assert_eq!(res.status(), StatusCode::ACCEPTED);
+
+    // Job run by the workers, which are not started in the tests:
+    let state = AppState::new(Arc::new(SETTINGS.clone()));
+    assert!(jobs::run_next(&state).await.unwrap());
+
+    let res = client
+      .get(format!("http://localhost:8088{}", location))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Headers:
+    assert_eq!(res.headers()["content-type"], "application/x-ndjson");
+    assert_eq!(
+      res.headers()["content-disposition"],
+      "attachment; filename=\"data-export.ndjson\""
+    );
+
+    // Body:
+    let body = res.text().await.unwrap();
+    let lines = body
+      .lines()
+      .map(|line| serde_json::from_str::<Value>(line).unwrap())
+      .collect::<Vec<_>>();
+    assert_eq!(lines[0]["collection"], "profile");
+    assert_eq!(lines[0]["document"]["email"], "nico@test.com");
+    assert!(lines[0]["document"].get("password").is_none());
+    let cats = lines
+      .iter()
+      .filter(|line| line["collection"] == "cats")
+      .collect::<Vec<_>>();
+    assert_eq!(cats.len(), 1, "Other users data should be left out");
+    assert_eq!(cats[0]["document"]["name"], "Tigrin");
+  });
+}
+
+#[test]
+fn post_me_export_route_with_json_format() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    Cat::create(Cat::new(user.id.unwrap(), "Tigrin".to_owned()))
+      .await
+      .unwrap();
+
+    let client = create_authenticated_client(user).await.unwrap();
+    let res = client
+      .post("http://localhost:8088/v1/me/export?format=json")
+      .send()
+      .await
+      .unwrap();
+    assert_eq!(res.status(), StatusCode::ACCEPTED);
+    let location = res.headers()["location"].to_str().unwrap().to_owned();
+
+    let state = AppState::new(Arc::new(SETTINGS.clone()));
+    assert!(jobs::run_next(&state).await.unwrap());
+
+    let res = client
+      .get(format!("http://localhost:8088{}", location))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Headers:
+    assert_eq!(res.headers()["content-type"], "application/json");
+
+    // Body:
+    let body = res.json::<Value>().await.unwrap();
+    assert_eq!(body["profile"]["email"], "nico@test.com");
+    assert_eq!(body["cats"].as_array().unwrap().len(), 1);
+    assert_eq!(body["cats"][0]["name"], "Tigrin");
+    assert_eq!(body["watchlists"].as_array().unwrap().len(), 0);
+  });
+}
+
+#[test]
+fn get_me_export_route_of_other_user() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let other = create_user("john@test.com").await.unwrap();
+
+    let client = create_authenticated_client(other).await.unwrap();
+    let res = client
+      .post("http://localhost:8088/v1/me/export")
+      .send()
+      .await
+      .unwrap();
+    let location = res.headers()["location"].to_str().unwrap().to_owned();
+
+    let client = create_authenticated_client(user).await.unwrap();
+    let res = client
+      .get(format!("http://localhost:8088{}", location))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::NOT_FOUND;
+    assert_eq!(actual, expected);
+  });
+}
+
+#[test]
+fn post_me_export_route_as_readonly() {
+  use_app(async move {
+    let user = create_readonly_user("reader@test.com").await.unwrap();
+    let client = create_authenticated_client(user).await.unwrap();
+
+    let res = client
+      .post("http://localhost:8088/v1/me/export")
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::ACCEPTED;
+    assert_eq!(actual, expected);
+  });
+}
@@ -0,0 +1,144 @@
+use reqwest;
+use reqwest::StatusCode;
+
+use crate::services::degen_score::{DegenFactorKind, DegenScoreReport, DegenTier};
+use crate::tests::mock_arkham::{FAILING_ADDRESS, MEMECOIN_HOLDER, RISKY_SPENDER};
+use crate::tests::setup::use_app;
+use crate::utils::address::to_checksum_address;
+
+const ADDRESS: &str = "0x00000000000000000000000000000000000000a1";
+
+fn points(body: &DegenScoreReport) -> Vec<(DegenFactorKind, u32)> {
+  body
+    .factors
+    .iter()
+    .map(|factor| (factor.kind, factor.points))
+    .collect()
+}
+
+#[test]
+fn get_degen_score_route() {
+  use_app(async move {
+    let url = format!("http://localhost:8088/v1/degen-score/{}", ADDRESS);
+    let res = reqwest::get(url).await.unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<DegenScoreReport>().await.unwrap();
+    assert_eq!(body.address, to_checksum_address(ADDRESS));
+    assert_eq!(body.chain, "ethereum");
+    assert!(body.unavailable_sources.is_empty());
+
+    // The mock transactions are from 2023, and only the `SPENDER` contract
+    // is verified.
+    assert_eq!(
+      points(&body),
+      vec![
+        (DegenFactorKind::TradeFrequency, 0),
+        (DegenFactorKind::MemecoinExposure, 0),
+        (DegenFactorKind::UnverifiedApprovals, 20),
+        (DegenFactorKind::EntityType, 10)
+      ]
+    );
+    assert_eq!(
+      body.factors[2].detail,
+      "2 approvals on ethereum to unverified contracts"
+    );
+    assert_eq!(
+      body.factors[3].detail,
+      "Arkham entity Degen is of type individual"
+    );
+    assert_eq!(body.score, 30);
+    assert_eq!(body.tier, DegenTier::Ape);
+  });
+}
+
+#[test]
+fn get_degen_score_route_with_memecoins() {
+  use_app(async move {
+    let url = format!("http://localhost:8088/v1/degen-score/{}", MEMECOIN_HOLDER);
+    let res = reqwest::get(url).await.unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<DegenScoreReport>().await.unwrap();
+    let memecoins = body
+      .factors
+      .iter()
+      .find(|factor| factor.kind == DegenFactorKind::MemecoinExposure)
+      .expect("The memecoins should be a factor");
+    assert_eq!(memecoins.points, 15, "Half of the balance is in PEPE");
+    assert_eq!(memecoins.max_points, 30);
+    assert_eq!(
+      memecoins.detail,
+      "50% of the balance is in memecoins, worth $18100.00"
+    );
+    assert_eq!(body.score, 45);
+  });
+}
+
+#[test]
+fn get_degen_score_route_with_entity_type() {
+  use_app(async move {
+    let url = format!("http://localhost:8088/v1/degen-score/{}", RISKY_SPENDER);
+    let res = reqwest::get(url).await.unwrap();
+
+    // Body:
+    let body = res.json::<DegenScoreReport>().await.unwrap();
+    let entity = body.factors.last().unwrap();
+    assert_eq!(entity.kind, DegenFactorKind::EntityType);
+    assert_eq!(entity.points, 20);
+    assert_eq!(body.score, 40);
+  });
+}
+
+#[test]
+fn get_degen_score_route_with_unavailable_sources() {
+  use_app(async move {
+    let url = format!("http://localhost:8088/v1/degen-score/{}", FAILING_ADDRESS);
+    let res = reqwest::get(url).await.unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<DegenScoreReport>().await.unwrap();
+    assert_eq!(
+      body.unavailable_sources,
+      vec!["explorer", "approvals", "arkham"]
+    );
+    assert_eq!(points(&body), vec![(DegenFactorKind::MemecoinExposure, 0)]);
+    assert_eq!(body.score, 0);
+    assert_eq!(body.tier, DegenTier::Normie);
+  });
+}
+
+#[test]
+fn get_degen_score_route_with_unsupported_chain() {
+  use_app(async move {
+    let url = format!(
+      "http://localhost:8088/v1/degen-score/{}?chain=polygon",
+      ADDRESS
+    );
+    let res = reqwest::get(url).await.unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::BAD_REQUEST;
+    assert_eq!(actual, expected, "Polygon has no explorer");
+  });
+}
@@ -0,0 +1,94 @@
+use reqwest;
+use reqwest::StatusCode;
+use serde_json::Value as Json;
+
+use crate::tests::setup::use_app;
+
+#[test]
+fn get_openapi_route() {
+  use_app(async {
+    let res = reqwest::get("http://localhost:8088/api-docs/openapi.json")
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<Json>().await.unwrap();
+    assert!(body["paths"]["/v1/cats"].is_object());
+    assert!(body["paths"]["/v1/cats/{id}"].is_object());
+    assert!(body["paths"]["/v1/arkham/{address}"].is_object());
+    assert!(body["components"]["schemas"]["Cat"].is_object());
+    assert!(body["components"]["securitySchemes"]["bearerAuth"].is_object());
+  });
+}
+
+#[test]
+fn get_openapi_route_with_every_route() {
+  use_app(async {
+    let res = reqwest::get("http://localhost:8088/openapi.json")
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<Json>().await.unwrap();
+    assert!(body["paths"]["/status"].is_object());
+    assert!(body["paths"]["/v1/users"].is_object());
+    assert!(body["paths"]["/v1/auth/refresh"].is_object());
+    assert!(body["components"]["schemas"]["User"].is_object());
+    assert!(body["components"]["schemas"]["CreateCat"].is_object());
+    assert!(body["components"]["schemas"]["ArkhamResponse"].is_object());
+    assert!(body["components"]["schemas"]["ErrorResponse"].is_object());
+  });
+}
+
+#[test]
+fn get_docs_route() {
+  use_app(async {
+    let res = reqwest::get("http://localhost:8088/docs/").await.unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.text().await.unwrap();
+    assert!(body.contains("swagger-ui"));
+  });
+}
+
+#[test]
+fn get_former_docs_route() {
+  use_app(async {
+    let client = reqwest::Client::builder()
+      .redirect(reqwest::redirect::Policy::none())
+      .build()
+      .unwrap();
+    let res = client
+      .get("http://localhost:8088/swagger-ui/")
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::PERMANENT_REDIRECT;
+    assert_eq!(actual, expected);
+
+    // Headers:
+    assert_eq!(res.headers()["location"], "/docs/");
+  });
+}
@@ -0,0 +1,209 @@
+use reqwest;
+use reqwest::StatusCode;
+use serde_json::json;
+use serde_json::Value as Json;
+
+use crate::services::dune::{DuneExecution, ExecutionState};
+use crate::tests::mock_arkham::{DUNE_COMPLETED_EXECUTION, DUNE_PENDING_EXECUTION, DUNE_QUERY_ID};
+use crate::tests::setup::use_app;
+use crate::tests::utils::create_user;
+use crate::tests::utils::create_user_token;
+
+#[test]
+fn post_dune_execute_route() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .post(format!(
+        "http://localhost:8088/v1/dune/query/{}/execute",
+        DUNE_QUERY_ID
+      ))
+      .header("Authorization", format!("Bearer {}", token))
+      .json(&json!({ "parameters": { "days": 7 } }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::ACCEPTED;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<DuneExecution>().await.unwrap();
+    assert_eq!(body.execution_id, DUNE_PENDING_EXECUTION);
+    assert_eq!(body.state, ExecutionState::Pending);
+    assert!(body.rows.is_empty());
+  });
+}
+
+#[test]
+fn post_dune_execute_route_with_unknown_query() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .post("http://localhost:8088/v1/dune/query/1/execute")
+      .header("Authorization", format!("Bearer {}", token))
+      .json(&json!({}))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::NOT_FOUND;
+    assert_eq!(actual, expected);
+  });
+}
+
+#[test]
+fn post_dune_execute_route_without_token() {
+  use_app(async move {
+    let client = reqwest::Client::new();
+    let res = client
+      .post(format!(
+        "http://localhost:8088/v1/dune/query/{}/execute",
+        DUNE_QUERY_ID
+      ))
+      .json(&json!({}))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::UNAUTHORIZED;
+    assert_eq!(actual, expected);
+  });
+}
+
+#[test]
+fn get_dune_execution_route() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .get(format!(
+        "http://localhost:8088/v1/dune/execution/{}",
+        DUNE_COMPLETED_EXECUTION
+      ))
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<DuneExecution>().await.unwrap();
+    assert_eq!(body.state, ExecutionState::Completed);
+    assert_eq!(body.query_id, Some(DUNE_QUERY_ID));
+    assert_eq!(body.columns.len(), 2);
+    assert_eq!(body.columns[1].name, "volume");
+    assert_eq!(body.columns[1].column_type.as_deref(), Some("double"));
+    assert_eq!(body.rows.len(), 2);
+    assert_eq!(body.rows[0]["project"], "uniswap");
+    assert_eq!(body.rows[0]["volume"], 1500.5);
+  });
+}
+
+#[test]
+fn get_dune_execution_route_raw() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .get(format!(
+        "http://localhost:8088/v1/dune/execution/{}?raw=true",
+        DUNE_COMPLETED_EXECUTION
+      ))
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    // Dune's own response, rows aren't moved out of the result.
+    let body = res.json::<Json>().await.unwrap();
+    assert_eq!(body["state"], "QUERY_STATE_COMPLETED");
+    assert_eq!(body["result"]["metadata"]["row_count"], 2);
+    assert_eq!(body["result"]["rows"][0]["project"], "uniswap");
+    assert!(body.get("rows").is_none());
+  });
+}
+
+#[test]
+fn get_dune_execution_route_while_executing() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .get(format!(
+        "http://localhost:8088/v1/dune/execution/{}",
+        DUNE_PENDING_EXECUTION
+      ))
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<DuneExecution>().await.unwrap();
+    assert_eq!(body.state, ExecutionState::Executing);
+    assert!(body.columns.is_empty());
+    assert!(body.rows.is_empty());
+  });
+}
+
+#[test]
+fn get_dune_execution_route_with_invalid_id() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .get("http://localhost:8088/v1/dune/execution/..%2Fquery")
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::BAD_REQUEST;
+    assert_eq!(actual, expected);
+  });
+}
@@ -0,0 +1,57 @@
+use reqwest;
+use reqwest::StatusCode;
+
+use crate::routes::gas::GasPrices;
+use crate::tests::setup::use_app;
+
+#[test]
+fn get_gas_route() {
+  use_app(async move {
+    let res = reqwest::get("http://localhost:8088/v1/gas").await.unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<GasPrices>().await.unwrap();
+    let chains = body
+      .chains
+      .iter()
+      .map(|chain| chain.chain.as_str())
+      .collect::<Vec<&str>>();
+    assert_eq!(
+      chains,
+      vec![
+        "arbitrum_one",
+        "avalanche",
+        "bsc",
+        "ethereum",
+        "optimism",
+        "polygon"
+      ]
+    );
+
+    let ethereum = body
+      .chains
+      .iter()
+      .find(|chain| chain.chain == "ethereum")
+      .unwrap();
+    assert_eq!(ethereum.base_fee_gwei, Some(2.0), "Next block base fee");
+    let priority_fee = ethereum.priority_fee_gwei.as_ref().unwrap();
+    assert_eq!(priority_fee.slow, 0.1);
+    assert_eq!(priority_fee.standard, 1.0);
+    assert_eq!(priority_fee.fast, 2.0);
+    assert!(ethereum.error.is_none());
+
+    // The avalanche provider is down in the tests.
+    let avalanche = &body.chains[1];
+    assert_eq!(avalanche.base_fee_gwei, None);
+    assert_eq!(
+      avalanche.error.as_ref().map(|error| error.code.as_str()),
+      Some("upstream_unavailable")
+    );
+  });
+}
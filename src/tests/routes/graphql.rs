@@ -0,0 +1,115 @@
+use reqwest;
+use reqwest::StatusCode;
+use serde_json::json;
+use serde_json::Value as Json;
+
+use crate::models::address_label::AddressLabel;
+use crate::models::cat::Cat;
+use crate::models::watched_address::WatchedAddress;
+use crate::models::watchlist::Watchlist;
+use crate::tests::setup::use_app;
+use crate::tests::utils::create_user;
+use crate::tests::utils::create_user_token;
+use crate::utils::address::to_checksum_address;
+use crate::utils::models::ModelExt;
+
+const ADDRESS: &str = "0x52908400098527886e0f7030069857d2e4169ee7";
+
+#[test]
+fn post_graphql_route() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user.clone()).await.unwrap();
+
+    Cat::create(Cat::new(user.id.unwrap(), "Tigrin".to_owned()))
+      .await
+      .unwrap();
+
+    let watchlist = Watchlist::new(user.id.unwrap(), "Whales".to_owned());
+    let watchlist = Watchlist::create(watchlist).await.unwrap();
+    let address = WatchedAddress::new(
+      watchlist.id.unwrap(),
+      user.id.unwrap(),
+      ADDRESS.to_owned(),
+      "ethereum".to_owned(),
+      None,
+    );
+    WatchedAddress::create(address).await.unwrap();
+
+    let label = AddressLabel::new(
+      ADDRESS.to_owned(),
+      "Binance 14".to_owned(),
+      "arkham".to_owned(),
+    );
+    AddressLabel::create(label).await.unwrap();
+
+    let query = "{ cats { name } watchlists { name addresses { address labels { name } } } }";
+    let client = reqwest::Client::new();
+    let res = client
+      .post("http://localhost:8088/graphql")
+      .header("Authorization", format!("Bearer {}", token))
+      .json(&json!({ "query": query }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<Json>().await.unwrap();
+    assert!(body["errors"].is_null(), "Query should succeed: {}", body);
+    assert_eq!(body["data"]["cats"][0]["name"], "Tigrin");
+
+    let watchlist = &body["data"]["watchlists"][0];
+    assert_eq!(watchlist["name"], "Whales");
+    assert_eq!(
+      watchlist["addresses"][0]["address"],
+      to_checksum_address(ADDRESS)
+    );
+    assert_eq!(watchlist["addresses"][0]["labels"][0]["name"], "Binance 14");
+  });
+}
+
+#[test]
+fn post_graphql_route_with_invalid_id() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .post("http://localhost:8088/graphql")
+      .header("Authorization", format!("Bearer {}", token))
+      .json(&json!({ "query": "{ cat(id: \"tigrin\") { name } }" }))
+      .send()
+      .await
+      .unwrap();
+
+    // Body:
+    let body = res.json::<Json>().await.unwrap();
+    assert_eq!(body["errors"][0]["extensions"]["code"], "invalid_id");
+  });
+}
+
+#[test]
+fn post_graphql_route_without_token() {
+  use_app(async move {
+    let client = reqwest::Client::new();
+    let res = client
+      .post("http://localhost:8088/graphql")
+      .json(&json!({ "query": "{ cats { name } }" }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::UNAUTHORIZED;
+    assert_eq!(actual, expected);
+  });
+}
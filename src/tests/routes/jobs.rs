@@ -0,0 +1,131 @@
+use bson::oid::ObjectId;
+use reqwest;
+use reqwest::StatusCode;
+use serde_json::json;
+use std::sync::Arc;
+
+use crate::models::job::{JobKind, JobStatus, PublicJob};
+use crate::services::jobs::{self, LabelImport};
+use crate::settings::SETTINGS;
+use crate::state::AppState;
+use crate::tests::setup::use_app;
+use crate::tests::utils::{create_admin_user, create_user, create_user_token};
+
+const ADDRESS: &str = "0x00000000000000000000000000000000000000a1";
+
+#[test]
+fn post_label_import_route_respond_async() {
+  use_app(async move {
+    let admin = create_admin_user("admin@test.com").await.unwrap();
+    let token = create_user_token(admin).await.unwrap();
+    let line = json!({ "eth_address": ADDRESS, "name": "Binance", "source": "arkham" });
+
+    let client = reqwest::Client::new();
+    let res = client
+      .post("http://localhost:8088/v1/labels/import")
+      .header("Authorization", format!("Bearer {}", token))
+      .header("Content-Type", "application/x-ndjson")
+      .header("Prefer", "respond-async, wait=10")
+      .body(line.to_string())
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::ACCEPTED;
+    assert_eq!(actual, expected);
+
+    // Headers:
+    let location = res.headers()["location"].to_str().unwrap().to_owned();
+    assert_eq!(res.headers()["preference-applied"], "respond-async");
+
+    // Body:
+    let body = res.json::<PublicJob>().await.unwrap();
+    assert_eq!(body.kind, JobKind::LabelImport);
+    assert_eq!(body.status, JobStatus::Queued);
+    assert_eq!(location, format!("/v1/jobs/{}", body.id));
+
+    // Job run by the workers, which are not started in the tests:
+    let state = AppState::new(Arc::new(SETTINGS.clone()));
+    jobs::run_next(&state).await.unwrap();
+
+    let res = client
+      .get(format!("http://localhost:8088{}", location))
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = res.json::<PublicJob>().await.unwrap();
+    assert_eq!(body.status, JobStatus::Succeeded);
+    assert!(body.finished_at.is_some());
+    assert_eq!(body.result.unwrap().get_i64("inserted").unwrap(), 1);
+  });
+}
+
+#[test]
+fn get_arkham_graph_route_respond_async() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user).await.unwrap();
+    let url = format!("http://localhost:8088/v1/arkham/{}/graph", ADDRESS);
+
+    let client = reqwest::Client::new();
+    let res = client
+      .get(&url)
+      .header("Authorization", format!("Bearer {}", token))
+      .header("Prefer", "respond-async")
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::ACCEPTED;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<PublicJob>().await.unwrap();
+    assert_eq!(body.kind, JobKind::GraphExpansion);
+
+    // Without token:
+    let res = client
+      .get(&url)
+      .header("Prefer", "respond-async")
+      .send()
+      .await
+      .unwrap();
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+  });
+}
+
+#[test]
+fn get_job_route_of_other_user() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user).await.unwrap();
+    let payload = LabelImport {
+      body: String::new(),
+    };
+    let job = jobs::enqueue(ObjectId::new(), JobKind::LabelImport, &payload)
+      .await
+      .unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .get(format!("http://localhost:8088/v1/jobs/{}", job.id.unwrap()))
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::NOT_FOUND;
+    assert_eq!(actual, expected);
+  });
+}
@@ -0,0 +1,704 @@
+use bson::doc;
+use reqwest;
+use reqwest::StatusCode;
+use serde_json::json;
+use serde_json::Value as Json;
+
+use crate::models::address_label::AddressLabel;
+use crate::models::address_label::LabelSuggestion;
+use crate::models::address_label::PublicAddressLabel;
+use crate::models::label_change::{LabelChange, PublicLabelChange};
+use crate::models::user_address_note::UserAddressNote;
+use crate::routes::label::ImportSummary;
+use crate::services::label_merge::MergedLabels;
+use crate::tests::mock_arkham::FAILING_ADDRESS;
+use crate::tests::setup::use_app;
+use crate::tests::utils::create_admin_user;
+use crate::tests::utils::create_user;
+use crate::tests::utils::create_user_token;
+use crate::utils::address::to_checksum_address;
+use crate::utils::date::Date;
+use crate::utils::models::ModelExt;
+
+const ADDRESS: &str = "0x00000000000000000000000000000000000000a1";
+
+#[test]
+fn post_label_route() {
+  use_app(async move {
+    let admin = create_admin_user("admin@test.com").await.unwrap();
+    let token = create_user_token(admin).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .post("http://localhost:8088/v1/labels")
+      .header("Authorization", format!("Bearer {}", token))
+      .json(&json!({
+        "eth_address": ADDRESS.to_uppercase().replace("0X", "0x"),
+        "name": " Binance Hot Wallet ",
+        "source": "arkham"
+      }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::CREATED;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<PublicAddressLabel>().await.unwrap();
+    assert_eq!(
+      body.eth_address,
+      to_checksum_address(ADDRESS),
+      "Address should be checksummed"
+    );
+    assert_eq!(body.name, "Binance Hot Wallet");
+    assert_eq!(body.source, "arkham");
+
+    let delete = &body.links["delete"];
+    assert_eq!(delete.href, format!("/v1/labels/{}", body.id));
+    assert_eq!(delete.method, "DELETE");
+    assert_eq!(body.links["labels"].href, format!("/v1/labels/{}", ADDRESS));
+  });
+}
+
+#[test]
+fn post_label_route_with_stored_label() {
+  use_app(async move {
+    let admin = create_admin_user("admin@test.com").await.unwrap();
+    let token = create_user_token(admin).await.unwrap();
+
+    let label = AddressLabel::new(
+      ADDRESS.to_owned(),
+      "Binance".to_owned(),
+      "arkham".to_owned(),
+    );
+    AddressLabel::create(label).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .post("http://localhost:8088/v1/labels")
+      .header("Authorization", format!("Bearer {}", token))
+      .json(&json!({ "eth_address": ADDRESS, "name": "Binance 14", "source": "arkham" }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::CONFLICT;
+    assert_eq!(actual, expected);
+  });
+}
+
+#[test]
+fn post_label_route_as_member() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .post("http://localhost:8088/v1/labels")
+      .header("Authorization", format!("Bearer {}", token))
+      .json(&json!({ "eth_address": ADDRESS, "name": "Binance", "source": "arkham" }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::FORBIDDEN;
+    assert_eq!(actual, expected);
+  });
+}
+
+#[test]
+fn post_label_import_route() {
+  use_app(async move {
+    let admin = create_admin_user("admin@test.com").await.unwrap();
+    let token = create_user_token(admin).await.unwrap();
+
+    let label = AddressLabel::new(
+      ADDRESS.to_owned(),
+      "Binance".to_owned(),
+      "arkham".to_owned(),
+    );
+    AddressLabel::create(label).await.unwrap();
+
+    let other_address = "0x00000000000000000000000000000000000000a2";
+    let lines = [
+      json!({ "eth_address": ADDRESS, "name": "Binance", "source": "arkham" }).to_string(),
+      json!({ "eth_address": ADDRESS, "name": "Binance 14", "source": "arkham" }).to_string(),
+      String::new(),
+      json!({ "eth_address": ADDRESS, "name": "Binance 14", "source": "arkham" }).to_string(),
+      String::from("not json"),
+      json!({ "eth_address": "0x123", "name": "Binance", "source": "arkham" }).to_string(),
+      json!({ "eth_address": other_address, "name": "Jump", "source": "arkham" }).to_string(),
+    ];
+
+    let client = reqwest::Client::new();
+    let res = client
+      .post("http://localhost:8088/v1/labels/import")
+      .header("Authorization", format!("Bearer {}", token))
+      .header("Content-Type", "application/x-ndjson")
+      .body(lines.join("\n"))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<ImportSummary>().await.unwrap();
+    assert_eq!(body.inserted, 1);
+    assert_eq!(body.updated, 1, "Stored label renamed by the last line");
+    assert_eq!(body.skipped, 3, "Blank line and repeated labels");
+    assert_eq!(body.failed, 2);
+    let failed_lines = body
+      .failures
+      .iter()
+      .map(|failure| failure.line)
+      .collect::<Vec<u64>>();
+    assert_eq!(failed_lines, vec![5, 6]);
+
+    // Labels from the database:
+    let count = AddressLabel::count(doc! {}).await.unwrap();
+    assert_eq!(count, 2);
+    let label = AddressLabel::find_one(doc! { "eth_address": ADDRESS }, None)
+      .await
+      .unwrap()
+      .unwrap();
+    assert_eq!(label.name, "Binance 14");
+  });
+}
+
+#[test]
+fn post_label_import_route_twice() {
+  use_app(async move {
+    let admin = create_admin_user("admin@test.com").await.unwrap();
+    let token = create_user_token(admin).await.unwrap();
+
+    let body = [
+      json!({ "eth_address": ADDRESS, "name": "Binance", "source": "arkham" }).to_string(),
+      json!({ "eth_address": ADDRESS, "name": "Binance 14", "source": "etherscan" }).to_string(),
+    ]
+    .join("\n");
+
+    let client = reqwest::Client::new();
+    let import = || {
+      client
+        .post("http://localhost:8088/v1/labels/import")
+        .header("Authorization", format!("Bearer {}", token))
+        .header("Content-Type", "application/x-ndjson")
+        .body(body.clone())
+        .send()
+    };
+    let first = import().await.unwrap();
+    assert_eq!(first.status(), StatusCode::OK);
+    let res = import().await.unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<ImportSummary>().await.unwrap();
+    assert_eq!(body.inserted, 0);
+    assert_eq!(body.updated, 0);
+    assert_eq!(body.skipped, 2, "Labels already stored");
+
+    // Labels from the database:
+    let count = AddressLabel::count(doc! {}).await.unwrap();
+    assert_eq!(count, 2);
+  });
+}
+
+#[test]
+fn post_label_import_route_with_too_large_body() {
+  use_app(async move {
+    let admin = create_admin_user("admin@test.com").await.unwrap();
+    let token = create_user_token(admin).await.unwrap();
+
+    // The test configuration limits imports to 4096 bytes.
+    let line = json!({ "eth_address": ADDRESS, "name": "Binance", "source": "arkham" });
+    let lines = vec![line.to_string(); 100];
+
+    let client = reqwest::Client::new();
+    let res = client
+      .post("http://localhost:8088/v1/labels/import")
+      .header("Authorization", format!("Bearer {}", token))
+      .header("Content-Type", "application/x-ndjson")
+      .body(lines.join("\n"))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::PAYLOAD_TOO_LARGE;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<Json>().await.unwrap();
+    assert_eq!(body["code"], "payload_too_large");
+    assert_eq!(body["details"]["limit_bytes"], 4096);
+
+    // Labels from the database:
+    let count = AddressLabel::count(doc! {}).await.unwrap();
+    assert_eq!(count, 0);
+  });
+}
+
+#[test]
+fn get_labels_by_address_route() {
+  use_app(async move {
+    let other_address = "0x00000000000000000000000000000000000000a2";
+    for (address, name, source) in [
+      (ADDRESS, "Binance", "arkham"),
+      (ADDRESS, "Binance 14", "etherscan"),
+      (other_address, "Jump", "arkham"),
+    ] {
+      let label = AddressLabel::new(address.to_owned(), name.to_owned(), source.to_owned());
+      AddressLabel::create(label).await.unwrap();
+    }
+
+    let res = reqwest::get(format!(
+      "http://localhost:8088/v1/labels/{}?limit=1",
+      ADDRESS
+    ))
+    .await
+    .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Response pagination headers:
+    let headers = res.headers();
+    assert_eq!(headers.get("X-Pagination-Count").unwrap(), "2");
+    assert_eq!(headers.get("X-Pagination-Limit").unwrap(), "1");
+
+    // Body:
+    let body = res.json::<Vec<PublicAddressLabel>>().await.unwrap();
+    assert_eq!(body.len(), 1);
+    assert_eq!(
+      body.first().unwrap().name,
+      "Binance 14",
+      "Newest label first"
+    );
+  });
+}
+
+#[test]
+fn get_merged_labels_route() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user.clone()).await.unwrap();
+
+    let label = AddressLabel::new(
+      ADDRESS.to_owned(),
+      "Binance 14".to_owned(),
+      "etherscan".to_owned(),
+    );
+    AddressLabel::create(label).await.unwrap();
+    let mut note = UserAddressNote::new(user.id.unwrap(), ADDRESS.to_owned());
+    note.name = Some("My exchange".to_owned());
+    UserAddressNote::create(note).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .get(format!(
+        "http://localhost:8088/v1/labels/{}/merged",
+        ADDRESS
+      ))
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<MergedLabels>().await.unwrap();
+    let name = body.name.unwrap();
+    assert_eq!(name.value, "My exchange");
+    assert_eq!(name.source, "user");
+    let entity = body.entity.unwrap();
+    assert_eq!(entity.value, "Degen");
+    assert_eq!(entity.source, "arkham");
+    let sources = body
+      .names
+      .iter()
+      .map(|name| name.source.as_str())
+      .collect::<Vec<&str>>();
+    assert_eq!(sources, vec!["user", "labels", "arkham"]);
+    assert!(body.unavailable_sources.is_empty());
+
+    // Without the note of the user:
+    let res = reqwest::get(format!(
+      "http://localhost:8088/v1/labels/{}/merged",
+      ADDRESS
+    ))
+    .await
+    .unwrap();
+    let body = res.json::<MergedLabels>().await.unwrap();
+    let name = body.name.unwrap();
+    assert_eq!(name.value, "Binance 14");
+    assert_eq!(name.label_source.as_deref(), Some("etherscan"));
+  });
+}
+
+#[test]
+fn get_merged_labels_route_with_arkham_down() {
+  use_app(async move {
+    let label = AddressLabel::new(
+      FAILING_ADDRESS.to_owned(),
+      "Exploiter".to_owned(),
+      "community".to_owned(),
+    );
+    AddressLabel::create(label).await.unwrap();
+
+    let res = reqwest::get(format!(
+      "http://localhost:8088/v1/labels/{}/merged",
+      FAILING_ADDRESS
+    ))
+    .await
+    .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<MergedLabels>().await.unwrap();
+    assert_eq!(body.name.unwrap().value, "Exploiter");
+    assert!(body.entity.is_none());
+    assert_eq!(body.unavailable_sources, vec!["arkham"]);
+  });
+}
+
+#[test]
+fn export_labels_route() {
+  use_app(async move {
+    let other_address = "0x00000000000000000000000000000000000000a2";
+    for (address, name) in [(ADDRESS, "Binance"), (other_address, "Jump")] {
+      let label = AddressLabel::new(address.to_owned(), name.to_owned(), "arkham".to_owned());
+      AddressLabel::create(label).await.unwrap();
+    }
+
+    let res = reqwest::get("http://localhost:8088/v1/labels/export")
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Response headers:
+    let headers = res.headers();
+    assert_eq!(headers.get("Content-Type").unwrap(), "application/x-ndjson");
+    assert!(headers.get("X-Pagination-Count").is_none());
+
+    // Body:
+    let body = res.text().await.unwrap();
+    let labels = body
+      .lines()
+      .map(|line| serde_json::from_str::<PublicAddressLabel>(line).unwrap())
+      .collect::<Vec<PublicAddressLabel>>();
+    assert_eq!(labels.len(), 2, "Every label should be exported");
+    assert_eq!(labels[0].name, "Binance", "Oldest label first");
+    assert_eq!(labels[1].name, "Jump");
+  });
+}
+
+#[test]
+fn search_labels_route() {
+  use_app(async move {
+    let other_address = "0x00000000000000000000000000000000000000a2";
+    for (address, name, source) in [
+      (ADDRESS, "Binance Hot Wallet", "arkham"),
+      (ADDRESS, "Binance 14", "etherscan"),
+      (other_address, "Jump Trading", "arkham"),
+    ] {
+      let label = AddressLabel::new(address.to_owned(), name.to_owned(), source.to_owned());
+      AddressLabel::create(label).await.unwrap();
+    }
+
+    let res = reqwest::get("http://localhost:8088/v1/labels/search?q=binance%20wallet")
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Response pagination headers:
+    let headers = res.headers();
+    assert_eq!(headers.get("X-Pagination-Count").unwrap(), "2");
+
+    // Body:
+    let body = res.json::<Vec<PublicAddressLabel>>().await.unwrap();
+    let names = body
+      .iter()
+      .map(|label| label.name.as_str())
+      .collect::<Vec<&str>>();
+    assert_eq!(
+      names,
+      vec!["Binance Hot Wallet", "Binance 14"],
+      "Most relevant label first"
+    );
+  });
+}
+
+#[test]
+fn search_labels_route_without_text() {
+  use_app(async move {
+    let res = reqwest::get("http://localhost:8088/v1/labels/search?q=%20")
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::BAD_REQUEST;
+    assert_eq!(actual, expected);
+  });
+}
+
+#[test]
+fn suggest_labels_route() {
+  use_app(async move {
+    let other_address = "0x00000000000000000000000000000000000000a2";
+    for (address, name, source) in [
+      (ADDRESS, "Binance Hot Wallet", "arkham"),
+      (ADDRESS, "binance 14", "etherscan"),
+      (ADDRESS, "Bitfinex", "arkham"),
+      (other_address, "Jump Trading", "arkham"),
+    ] {
+      let label = AddressLabel::new(address.to_owned(), name.to_owned(), source.to_owned());
+      AddressLabel::create(label).await.unwrap();
+    }
+
+    let res = reqwest::get("http://localhost:8088/v1/labels/suggest?q=BIN&limit=10")
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<Vec<LabelSuggestion>>().await.unwrap();
+    let names = body
+      .iter()
+      .map(|suggestion| suggestion.name.as_str())
+      .collect::<Vec<&str>>();
+    assert_eq!(names, vec!["binance 14", "Binance Hot Wallet"]);
+    assert_eq!(body[0].address, to_checksum_address(ADDRESS));
+    assert_eq!(body[0].source, "etherscan");
+
+    let res = reqwest::get("http://localhost:8088/v1/labels/suggest?q=b&limit=1")
+      .await
+      .unwrap();
+    let body = res.json::<Vec<LabelSuggestion>>().await.unwrap();
+    assert_eq!(body.len(), 1);
+  });
+}
+
+#[test]
+fn suggest_labels_route_without_text() {
+  use_app(async move {
+    let res = reqwest::get("http://localhost:8088/v1/labels/suggest?q=%20")
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::BAD_REQUEST;
+    assert_eq!(actual, expected);
+  });
+}
+
+#[test]
+fn remove_label_by_id_route() {
+  use_app(async move {
+    let admin = create_admin_user("admin@test.com").await.unwrap();
+    let token = create_user_token(admin).await.unwrap();
+
+    let label = AddressLabel::new(
+      ADDRESS.to_owned(),
+      "Binance".to_owned(),
+      "arkham".to_owned(),
+    );
+    let label = AddressLabel::create(label).await.unwrap();
+    let url = format!("http://localhost:8088/v1/labels/{}", label.id.unwrap());
+
+    let client = reqwest::Client::new();
+    let res = client
+      .delete(&url)
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::NO_CONTENT;
+    assert_eq!(actual, expected);
+
+    // Label from the database:
+    let label = AddressLabel::find_by_id(&label.id.unwrap()).await.unwrap();
+    assert!(label.is_none(), "Label should be removed");
+
+    let res = client
+      .delete(&url)
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+  });
+}
+
+#[test]
+fn label_history_route() {
+  use_app(async move {
+    let admin = create_admin_user("admin@test.com").await.unwrap();
+    let token = create_user_token(admin).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .post("http://localhost:8088/v1/labels")
+      .header("Authorization", format!("Bearer {}", token))
+      .json(&json!({ "eth_address": ADDRESS, "name": "Binance", "source": "arkham" }))
+      .send()
+      .await
+      .unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED);
+    let label = res.json::<Json>().await.unwrap();
+
+    let res = client
+      .delete(format!(
+        "http://localhost:8088/v1/labels/{}",
+        label["id"].as_str().unwrap()
+      ))
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+    let url = format!("http://localhost:8088/v1/labels/{}/history", ADDRESS);
+    let res = reqwest::get(url).await.unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let changes = res.json::<Vec<PublicLabelChange>>().await.unwrap();
+    assert_eq!(changes.len(), 2);
+    assert_eq!(changes[0].old_name.as_deref(), Some("Binance"));
+    assert_eq!(
+      changes[0].new_name, None,
+      "Removal should be the newest change"
+    );
+    assert_eq!(changes[1].old_name, None);
+    assert_eq!(changes[1].new_name.as_deref(), Some("Binance"));
+  });
+}
+
+#[test]
+fn label_history_route_as_of() {
+  use_app(async move {
+    let label = AddressLabel::new(
+      ADDRESS.to_owned(),
+      "Binance".to_owned(),
+      "arkham".to_owned(),
+    );
+    let removed = AddressLabel::new(
+      ADDRESS.to_owned(),
+      "Jump".to_owned(),
+      "etherscan".to_owned(),
+    );
+    let changes = [
+      (&label, None, Some("Binance"), 1_000),
+      (&removed, None, Some("Jump"), 2_000),
+      (&label, Some("Binance"), Some("Binance 14"), 3_000),
+      (&removed, Some("Jump"), None, 4_000),
+    ];
+    for (label, old_name, new_name, changed_at) in changes {
+      let mut change = LabelChange::new(
+        label,
+        old_name.map(ToOwned::to_owned),
+        new_name.map(ToOwned::to_owned),
+      );
+      change.changed_at = Date::from_millis(changed_at);
+      LabelChange::create(change).await.unwrap();
+    }
+
+    let names_as_of = |as_of: &'static str| async move {
+      let url = format!(
+        "http://localhost:8088/v1/labels/{}/history?as_of={}",
+        ADDRESS, as_of
+      );
+      let res = reqwest::get(url).await.unwrap();
+      assert_eq!(res.status(), StatusCode::OK);
+      res
+        .json::<Vec<PublicLabelChange>>()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|change| change.new_name.unwrap())
+        .collect::<Vec<String>>()
+    };
+
+    assert_eq!(
+      names_as_of("1970-01-01T00:00:02.500Z").await,
+      vec!["Jump", "Binance"]
+    );
+    assert_eq!(
+      names_as_of("1970-01-01T00:00:05Z").await,
+      vec!["Binance 14"],
+      "Removed labels should be left out"
+    );
+    assert!(names_as_of("1970-01-01T00:00:00Z").await.is_empty());
+
+    let url = format!(
+      "http://localhost:8088/v1/labels/{}/history?as_of=yesterday",
+      ADDRESS
+    );
+    let res = reqwest::get(url).await.unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+  });
+}
@@ -0,0 +1,74 @@
+use futures::StreamExt;
+use reqwest::StatusCode;
+use serde_json::json;
+use std::time::Duration;
+use tokio::time::timeout;
+use tokio_tungstenite::{connect_async, tungstenite};
+
+use crate::models::watchlist::Watchlist;
+use crate::services::live::LiveMessage;
+use crate::tests::setup::use_app;
+use crate::tests::utils::create_user;
+use crate::tests::utils::create_user_token;
+use crate::utils::address::to_checksum_address;
+use crate::utils::models::ModelExt;
+
+const ADDRESS: &str = "0x00000000000000000000000000000000000000a1";
+
+#[test]
+fn ws_route_requires_a_token() {
+  use_app(async move {
+    let res = connect_async("ws://localhost:8088/ws").await;
+
+    // Status code:
+    let actual = match res {
+      Err(tungstenite::Error::Http(res)) => res.status(),
+      _ => panic!("Expected the handshake to be rejected"),
+    };
+    let expected = StatusCode::UNAUTHORIZED;
+    assert_eq!(actual.as_u16(), expected.as_u16());
+  });
+}
+
+#[test]
+fn ws_route_streams_watchlist_updates() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user.clone()).await.unwrap();
+    let watchlist = Watchlist::new(user.id.unwrap(), "Exchanges".to_owned());
+    let watchlist = Watchlist::create(watchlist).await.unwrap();
+
+    let url = format!("ws://localhost:8088/ws?token={}&chains=ethereum", token);
+    let (mut socket, _) = connect_async(url).await.unwrap();
+
+    // Only the address on the filtered chain is streamed.
+    let client = reqwest::Client::new();
+    for chain in ["bsc", "ethereum"] {
+      let res = client
+        .post(format!(
+          "http://localhost:8088/v1/watchlists/{}/addresses",
+          watchlist.id.unwrap()
+        ))
+        .header("Authorization", format!("Bearer {}", token))
+        .json(&json!({ "address": ADDRESS, "chain": chain }))
+        .send()
+        .await
+        .unwrap();
+      assert_eq!(res.status(), StatusCode::CREATED);
+    }
+
+    let message = timeout(Duration::from_secs(5), socket.next())
+      .await
+      .expect("Timed out waiting for a message")
+      .unwrap()
+      .unwrap();
+    let message = serde_json::from_str::<LiveMessage>(message.to_text().unwrap()).unwrap();
+    match message {
+      LiveMessage::AddressWatched { data } => {
+        assert_eq!(data.address, to_checksum_address(ADDRESS));
+        assert_eq!(data.chain, "ethereum");
+      }
+      message => panic!("Unexpected message {:?}", message),
+    }
+  });
+}
@@ -0,0 +1,30 @@
+use reqwest;
+use reqwest::header::CONTENT_TYPE;
+use reqwest::StatusCode;
+
+use crate::tests::setup::use_app;
+
+#[test]
+fn get_metrics_route() {
+  use_app(async {
+    reqwest::get("http://localhost:8088/status").await.unwrap();
+
+    let res = reqwest::get("http://localhost:8088/metrics").await.unwrap();
+    let status_code = res.status();
+    let content_type = res.headers().get(CONTENT_TYPE).cloned().unwrap();
+    let body = res.text().await.unwrap();
+
+    // Status code:
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Headers:
+    assert!(content_type.to_str().unwrap().starts_with("text/plain"));
+
+    // Body:
+    assert!(body.contains("http_requests_total"));
+    assert!(body.contains(r#"route="/status""#));
+    assert!(body.contains("http_request_duration_seconds_bucket"));
+  });
+}
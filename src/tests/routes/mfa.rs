@@ -0,0 +1,281 @@
+use chrono::Utc;
+use data_encoding::BASE32_NOPAD;
+use reqwest;
+use reqwest::StatusCode;
+use serde_json::{json, Value as Json};
+
+use crate::models::user::User;
+use crate::routes::user::AuthenticateResponse;
+use crate::services::mfa::{RecoveryCodes, TotpEnrollment};
+use crate::tests::setup::use_app;
+use crate::tests::utils::create_authenticated_client;
+use crate::tests::utils::create_readonly_user;
+use crate::tests::utils::create_user;
+use crate::utils::models::ModelExt;
+use crate::utils::totp;
+
+const EMAIL: &str = "nahuel@gmail.com";
+
+// Code of the authenticator app, `offset` steps from now. Codes can only be
+// used once, later codes are within the accepted skew.
+fn code(secret: &str, offset: u64) -> String {
+  let secret = BASE32_NOPAD.decode(secret.as_bytes()).unwrap();
+  let step = totp::step(Utc::now().timestamp() as u64);
+  totp::code(&secret, step + offset)
+}
+
+async fn sign_in(client: &reqwest::Client, mfa_code: Option<&str>) -> reqwest::Response {
+  client
+    .post("http://localhost:8088/v1/users/authenticate")
+    .json(&json!({ "email": EMAIL, "password": "Password1", "mfa_code": mfa_code }))
+    .send()
+    .await
+    .unwrap()
+}
+
+/// Enables two-factor authentication, returning the secret, the code it was
+/// confirmed with and the recovery codes.
+async fn enable_mfa(client: &reqwest::Client) -> (String, String, Vec<String>) {
+  let res = client
+    .post("http://localhost:8088/v1/me/mfa/totp")
+    .send()
+    .await
+    .unwrap();
+  let enrollment = res.json::<TotpEnrollment>().await.unwrap();
+
+  let confirmed_code = code(&enrollment.secret, 0);
+  let res = client
+    .post("http://localhost:8088/v1/me/mfa/totp/confirm")
+    .json(&json!({ "code": &confirmed_code }))
+    .send()
+    .await
+    .unwrap();
+  let recovery_codes = res.json::<RecoveryCodes>().await.unwrap().recovery_codes;
+
+  (enrollment.secret, confirmed_code, recovery_codes)
+}
+
+#[test]
+fn post_totp_route() {
+  use_app(async move {
+    let user = create_user(EMAIL).await.unwrap();
+    let client = create_authenticated_client(user).await.unwrap();
+
+    let res = client
+      .post("http://localhost:8088/v1/me/mfa/totp")
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::CREATED;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<TotpEnrollment>().await.unwrap();
+    assert_eq!(body.secret.len(), 32);
+    assert!(body
+      .otpauth_uri
+      .starts_with("otpauth://totp/Degen:nahuel@gmail.com?secret="));
+
+    let user = User::find_one(bson::doc! { "email": EMAIL }, None)
+      .await
+      .unwrap()
+      .unwrap();
+    assert!(!user.mfa_enabled, "The factor should be pending");
+  });
+}
+
+#[test]
+fn post_totp_route_as_readonly() {
+  use_app(async move {
+    let user = create_readonly_user(EMAIL).await.unwrap();
+    let client = create_authenticated_client(user).await.unwrap();
+
+    let res = client
+      .post("http://localhost:8088/v1/me/mfa/totp")
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::CREATED;
+    assert_eq!(actual, expected);
+  });
+}
+
+#[test]
+fn post_totp_confirm_route() {
+  use_app(async move {
+    let user = create_user(EMAIL).await.unwrap();
+    let client = create_authenticated_client(user).await.unwrap();
+
+    let res = client
+      .post("http://localhost:8088/v1/me/mfa/totp")
+      .send()
+      .await
+      .unwrap();
+    let enrollment = res.json::<TotpEnrollment>().await.unwrap();
+
+    let valid_code = code(&enrollment.secret, 0).parse::<u32>().unwrap();
+    let res = client
+      .post("http://localhost:8088/v1/me/mfa/totp/confirm")
+      .json(&json!({ "code": format!("{:06}", (valid_code + 1) % 1_000_000) }))
+      .send()
+      .await
+      .unwrap();
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let res = client
+      .post("http://localhost:8088/v1/me/mfa/totp/confirm")
+      .json(&json!({ "code": code(&enrollment.secret, 1) }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<RecoveryCodes>().await.unwrap();
+    assert_eq!(body.recovery_codes.len(), 10);
+
+    let res = client
+      .post("http://localhost:8088/v1/me/mfa/totp")
+      .send()
+      .await
+      .unwrap();
+    assert_eq!(
+      res.status(),
+      StatusCode::CONFLICT,
+      "Enabled factors should be disabled before enrolling again"
+    );
+  });
+}
+
+#[test]
+fn post_authenticate_route_with_mfa() {
+  use_app(async move {
+    let user = create_user(EMAIL).await.unwrap();
+    let client = create_authenticated_client(user).await.unwrap();
+    let (secret, confirmed_code, recovery_codes) = enable_mfa(&client).await;
+
+    let anonymous = reqwest::Client::new();
+    let res = sign_in(&anonymous, None).await;
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::UNAUTHORIZED;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<Json>().await.unwrap();
+    assert_eq!(body["code"], "mfa_required");
+
+    let res = sign_in(&anonymous, Some(&confirmed_code)).await;
+    assert_eq!(
+      res.status(),
+      StatusCode::UNAUTHORIZED,
+      "Codes can only be used once"
+    );
+
+    let res = sign_in(&anonymous, Some(&code(&secret, 1))).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = res.json::<AuthenticateResponse>().await.unwrap();
+    assert!(body.user.mfa_enabled);
+
+    let res = sign_in(&anonymous, Some(&recovery_codes[0].to_uppercase())).await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = sign_in(&anonymous, Some(&recovery_codes[0])).await;
+    assert_eq!(
+      res.status(),
+      StatusCode::UNAUTHORIZED,
+      "Recovery codes can only be used once"
+    );
+  });
+}
+
+#[test]
+fn post_totp_disable_route() {
+  use_app(async move {
+    let user = create_user(EMAIL).await.unwrap();
+    let client = create_authenticated_client(user).await.unwrap();
+    let (_, _, recovery_codes) = enable_mfa(&client).await;
+
+    let res = client
+      .post("http://localhost:8088/v1/me/mfa/totp/disable")
+      .json(&json!({ "code": "not-a-recovery-code" }))
+      .send()
+      .await
+      .unwrap();
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let res = client
+      .post("http://localhost:8088/v1/me/mfa/totp/disable")
+      .json(&json!({ "code": recovery_codes[0] }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::NO_CONTENT;
+    assert_eq!(actual, expected);
+
+    let res = sign_in(&reqwest::Client::new(), None).await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = client
+      .post("http://localhost:8088/v1/me/mfa/totp/disable")
+      .json(&json!({ "code": recovery_codes[1] }))
+      .send()
+      .await
+      .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+  });
+}
+
+#[test]
+fn post_recovery_codes_route() {
+  use_app(async move {
+    let user = create_user(EMAIL).await.unwrap();
+    let client = create_authenticated_client(user).await.unwrap();
+    let (_, _, recovery_codes) = enable_mfa(&client).await;
+
+    let res = client
+      .post("http://localhost:8088/v1/me/mfa/recovery-codes")
+      .json(&json!({ "code": recovery_codes[0] }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<RecoveryCodes>().await.unwrap();
+    assert_eq!(body.recovery_codes.len(), 10);
+
+    let res = sign_in(&reqwest::Client::new(), Some(&recovery_codes[1])).await;
+    assert_eq!(
+      res.status(),
+      StatusCode::UNAUTHORIZED,
+      "Previous recovery codes should be replaced"
+    );
+    let res = sign_in(&reqwest::Client::new(), Some(&body.recovery_codes[0])).await;
+    assert_eq!(res.status(), StatusCode::OK);
+  });
+}
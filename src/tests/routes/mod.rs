@@ -1,3 +1,36 @@
+mod address;
+mod address_note;
+mod admin;
+mod alert;
+mod api_key;
+mod approvals;
+mod arkham;
+mod auth;
+mod balances;
+mod bitcoin;
 mod cat;
+mod data_export;
+mod degen_score;
+mod docs;
+mod dune;
+mod gas;
+mod graphql;
+mod jobs;
+mod label;
+mod live;
+mod metrics;
+mod mfa;
+mod nfts;
+mod notification;
+mod organization;
+mod portfolio;
+mod prices;
+mod risk;
+mod session;
+mod stats;
 mod status;
+mod transactions;
+mod usage;
 mod user;
+mod watchlist;
+mod webhook;
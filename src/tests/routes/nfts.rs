@@ -0,0 +1,124 @@
+use reqwest;
+use reqwest::StatusCode;
+
+use crate::routes::nfts::NftPage;
+use crate::tests::mock_arkham::FAILING_ADDRESS;
+use crate::tests::setup::use_app;
+use crate::utils::address::to_checksum_address;
+
+const ADDRESS: &str = "0x00000000000000000000000000000000000000a1";
+
+#[test]
+fn get_nfts_route() {
+  use_app(async move {
+    let res = reqwest::get(format!("http://localhost:8088/v1/nfts/{}", ADDRESS))
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<NftPage>().await.unwrap();
+    assert_eq!(body.address, to_checksum_address(ADDRESS));
+    assert_eq!(body.total, 3);
+    assert!(!body.has_next);
+    assert!(!body.truncated);
+    assert!(body.unavailable_chains.is_empty());
+
+    let chains = body
+      .collections
+      .iter()
+      .map(|collection| collection.chain.as_str())
+      .collect::<Vec<&str>>();
+    assert_eq!(chains, vec!["ethereum", "ethereum", "polygon"]);
+
+    // Tokens of a collection spread over pages are grouped.
+    let apes = &body.collections[0];
+    assert_eq!(apes.name.as_deref(), Some("Degen Apes"));
+    assert_eq!(apes.floor_price, Some(1.25));
+    let token_ids = apes
+      .tokens
+      .iter()
+      .map(|token| token.token_id.as_str())
+      .collect::<Vec<&str>>();
+    assert_eq!(token_ids, vec!["1", "2"]);
+    assert_eq!(
+      apes.tokens[1].image_url.as_deref(),
+      Some("ipfs://apes/2.png")
+    );
+
+    let items = &body.collections[1];
+    assert_eq!(items.floor_price, None);
+    assert_eq!(items.tokens[0].balance, "3");
+    assert_eq!(items.tokens[0].image_url, None);
+  });
+}
+
+#[test]
+fn get_nfts_route_with_pagination_and_chain() {
+  use_app(async move {
+    let url = format!("http://localhost:8088/v1/nfts/{}?limit=2&page=2", ADDRESS);
+    let res = reqwest::get(url).await.unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<NftPage>().await.unwrap();
+    assert_eq!(body.total, 3);
+    assert!(!body.has_next);
+    assert_eq!(body.collections.len(), 1);
+    assert_eq!(body.collections[0].chain, "polygon");
+
+    let url = format!("http://localhost:8088/v1/nfts/{}?chain=polygon", ADDRESS);
+    let body = reqwest::get(url)
+      .await
+      .unwrap()
+      .json::<NftPage>()
+      .await
+      .unwrap();
+    assert_eq!(body.total, 1);
+    assert_eq!(body.collections[0].name.as_deref(), Some("Degen Lands"));
+  });
+}
+
+#[test]
+fn get_nfts_route_with_failing_chain() {
+  use_app(async move {
+    let res = reqwest::get(format!("http://localhost:8088/v1/nfts/{}", FAILING_ADDRESS))
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<NftPage>().await.unwrap();
+    assert_eq!(body.total, 2);
+    assert_eq!(body.unavailable_chains, vec!["polygon"]);
+  });
+}
+
+#[test]
+fn get_nfts_route_with_unsupported_chain() {
+  use_app(async move {
+    let url = format!("http://localhost:8088/v1/nfts/{}?chain=solana", ADDRESS);
+    let res = reqwest::get(url).await.unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::BAD_REQUEST;
+    assert_eq!(actual, expected);
+  });
+}
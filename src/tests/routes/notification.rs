@@ -0,0 +1,137 @@
+use reqwest;
+use reqwest::StatusCode;
+use serde_json::json;
+
+use crate::models::notification_channel::{
+  ChannelKind, NotificationChannel, PublicNotificationChannel,
+};
+use crate::models::user::EmailPreferences;
+use crate::tests::setup::use_app;
+use crate::tests::utils::create_user;
+use crate::tests::utils::create_user_token;
+use crate::utils::models::ModelExt;
+
+#[test]
+fn post_notification_channel_route() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .post("http://localhost:8088/v1/notifications/channels")
+      .header("Authorization", format!("Bearer {}", token))
+      .json(&json!({ "kind": "telegram", "target": " 123456 " }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::CREATED;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<PublicNotificationChannel>().await.unwrap();
+    assert_eq!(body.kind, ChannelKind::Telegram);
+    assert_eq!(body.target, "123456");
+  });
+}
+
+#[test]
+fn post_notification_channel_route_with_invalid_target() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .post("http://localhost:8088/v1/notifications/channels")
+      .header("Authorization", format!("Bearer {}", token))
+      .json(&json!({ "kind": "telegram", "target": "not a chat" }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::BAD_REQUEST;
+    assert_eq!(actual, expected);
+  });
+}
+
+#[test]
+fn put_notification_channel_route() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let channel = NotificationChannel::new(
+      user.id.unwrap(),
+      ChannelKind::Discord,
+      "http://localhost:8089/api/webhooks/1/token".to_owned(),
+    );
+    let channel = NotificationChannel::create(channel).await.unwrap();
+    let token = create_user_token(user).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .put(format!(
+        "http://localhost:8088/v1/notifications/channels/{}",
+        channel.id.unwrap()
+      ))
+      .header("Authorization", format!("Bearer {}", token))
+      .json(&json!({ "enabled": false }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<PublicNotificationChannel>().await.unwrap();
+    assert!(!body.enabled, "Channel should be muted");
+  });
+}
+
+#[test]
+fn put_email_preferences_route() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .put("http://localhost:8088/v1/notifications/email")
+      .header("Authorization", format!("Bearer {}", token))
+      .json(&json!({ "alerts": true, "weekly_digest": false }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    let res = client
+      .get("http://localhost:8088/v1/notifications/email")
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    // Body:
+    let body = res.json::<EmailPreferences>().await.unwrap();
+    let expected = EmailPreferences {
+      alerts: true,
+      weekly_digest: false,
+    };
+    assert_eq!(body, expected);
+  });
+}
@@ -0,0 +1,308 @@
+use bson::doc;
+use reqwest;
+use reqwest::StatusCode;
+use serde_json::json;
+
+use crate::models::address_label::{AddressLabel, PublicAddressLabel};
+use crate::models::cat::PublicCat;
+use crate::models::membership::{Membership, PublicMembership};
+use crate::models::organization::{Organization, PublicOrganization};
+use crate::models::user::Role;
+use crate::routes::organization::CreatedInvite;
+use crate::tests::setup::use_app;
+use crate::tests::utils::create_user;
+use crate::tests::utils::create_user_token;
+use crate::utils::models::ModelExt;
+
+const ADDRESS: &str = "0x00000000000000000000000000000000000000a1";
+
+async fn create_organization(token: &str) -> PublicOrganization {
+  let client = reqwest::Client::new();
+  let res = client
+    .post("http://localhost:8088/v1/organizations")
+    .header("Authorization", format!("Bearer {}", token))
+    .json(&json!({ "name": "Degens" }))
+    .send()
+    .await
+    .unwrap();
+  res.json::<PublicOrganization>().await.unwrap()
+}
+
+#[test]
+fn post_organization_route() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user.clone()).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .post("http://localhost:8088/v1/organizations")
+      .header("Authorization", format!("Bearer {}", token))
+      .json(&json!({ "name": " Degens " }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::CREATED;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<PublicOrganization>().await.unwrap();
+    assert_eq!(body.name, "Degens");
+
+    // Memberships from the database:
+    let membership = Membership::find_one(doc! { "organization": body.id }, None)
+      .await
+      .unwrap()
+      .unwrap();
+    assert_eq!(membership.user, user.id.unwrap());
+    assert_eq!(membership.role, Role::Admin, "Creators are admins");
+
+    let res = client
+      .get("http://localhost:8088/v1/organizations")
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+    let body = res.json::<Vec<PublicOrganization>>().await.unwrap();
+    assert_eq!(body.len(), 1);
+  });
+}
+
+#[test]
+fn accept_invite_route() {
+  use_app(async move {
+    let nico = create_user("nico@test.com").await.unwrap();
+    let nico_token = create_user_token(nico).await.unwrap();
+    let nahuel = create_user("nahuel@test.com").await.unwrap();
+    let nahuel_token = create_user_token(nahuel.clone()).await.unwrap();
+    let organization = create_organization(&nico_token).await;
+
+    let client = reqwest::Client::new();
+    let res = client
+      .post(format!(
+        "http://localhost:8088/v1/organizations/{}/invites",
+        organization.id
+      ))
+      .header("Authorization", format!("Bearer {}", nico_token))
+      .json(&json!({ "email": "Nahuel@test.com", "role": "readonly" }))
+      .send()
+      .await
+      .unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED);
+    let invite = res.json::<CreatedInvite>().await.unwrap();
+    assert_eq!(invite.invite.email, "nahuel@test.com");
+
+    let res = client
+      .post("http://localhost:8088/v1/invites/accept")
+      .header("Authorization", format!("Bearer {}", nahuel_token))
+      .json(&json!({ "token": invite.token }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::CREATED;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<PublicMembership>().await.unwrap();
+    assert_eq!(body.organization, organization.id);
+    assert_eq!(body.user, nahuel.id.unwrap());
+    assert_eq!(body.role, Role::ReadOnly);
+
+    let res = client
+      .post("http://localhost:8088/v1/invites/accept")
+      .header("Authorization", format!("Bearer {}", nahuel_token))
+      .json(&json!({ "token": invite.token }))
+      .send()
+      .await
+      .unwrap();
+    assert_eq!(
+      res.status(),
+      StatusCode::NOT_FOUND,
+      "Invites can only be used once"
+    );
+  });
+}
+
+#[test]
+fn accept_invite_route_with_other_email() {
+  use_app(async move {
+    let nico = create_user("nico@test.com").await.unwrap();
+    let nico_token = create_user_token(nico).await.unwrap();
+    let nahuel = create_user("nahuel@test.com").await.unwrap();
+    let nahuel_token = create_user_token(nahuel).await.unwrap();
+    let organization = create_organization(&nico_token).await;
+
+    let client = reqwest::Client::new();
+    let res = client
+      .post(format!(
+        "http://localhost:8088/v1/organizations/{}/invites",
+        organization.id
+      ))
+      .header("Authorization", format!("Bearer {}", nico_token))
+      .json(&json!({ "email": "someone@test.com" }))
+      .send()
+      .await
+      .unwrap();
+    let invite = res.json::<CreatedInvite>().await.unwrap();
+
+    let res = client
+      .post("http://localhost:8088/v1/invites/accept")
+      .header("Authorization", format!("Bearer {}", nahuel_token))
+      .json(&json!({ "token": invite.token }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::FORBIDDEN;
+    assert_eq!(actual, expected);
+  });
+}
+
+#[test]
+fn shared_cats_route() {
+  use_app(async move {
+    let nico = create_user("nico@test.com").await.unwrap();
+    let nico_token = create_user_token(nico).await.unwrap();
+    let nahuel = create_user("nahuel@test.com").await.unwrap();
+    let nahuel_token = create_user_token(nahuel.clone()).await.unwrap();
+    let organization = create_organization(&nico_token).await;
+    let membership = Membership::new(organization.id, nahuel.id.unwrap(), Role::ReadOnly);
+    Membership::create(membership).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .post("http://localhost:8088/v1/cats")
+      .header("Authorization", format!("Bearer {}", nico_token))
+      .json(&json!({ "name": "Tigrin", "organization": organization.id.to_hex() }))
+      .send()
+      .await
+      .unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED);
+    let cat = res.json::<PublicCat>().await.unwrap();
+    assert_eq!(cat.organization, Some(organization.id));
+
+    let res = client
+      .get("http://localhost:8088/v1/cats")
+      .header("Authorization", format!("Bearer {}", nahuel_token))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<Vec<PublicCat>>().await.unwrap();
+    assert_eq!(body.len(), 1, "Members see the cats of the organization");
+
+    let res = client
+      .delete(format!("http://localhost:8088/v1/cats/{}", cat.id))
+      .header("Authorization", format!("Bearer {}", nahuel_token))
+      .send()
+      .await
+      .unwrap();
+    assert_eq!(
+      res.status(),
+      StatusCode::NOT_FOUND,
+      "Read only members can't remove shared cats"
+    );
+
+    let res = client
+      .post("http://localhost:8088/v1/cats")
+      .header("Authorization", format!("Bearer {}", nahuel_token))
+      .json(&json!({ "name": "Cholin", "organization": organization.id.to_hex() }))
+      .send()
+      .await
+      .unwrap();
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+  });
+}
+
+#[test]
+fn organization_labels_route() {
+  use_app(async move {
+    let nico = create_user("nico@test.com").await.unwrap();
+    let nico_token = create_user_token(nico).await.unwrap();
+    let organization = create_organization(&nico_token).await;
+
+    let client = reqwest::Client::new();
+    let res = client
+      .post("http://localhost:8088/v1/labels")
+      .header("Authorization", format!("Bearer {}", nico_token))
+      .json(&json!({
+        "eth_address": ADDRESS,
+        "name": "Our hot wallet",
+        "source": "team",
+        "organization": organization.id.to_hex()
+      }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::CREATED;
+    assert_eq!(actual, expected);
+
+    let url = format!("http://localhost:8088/v1/labels/{}", ADDRESS);
+    let res = client
+      .get(&url)
+      .header("Authorization", format!("Bearer {}", nico_token))
+      .send()
+      .await
+      .unwrap();
+    let body = res.json::<Vec<PublicAddressLabel>>().await.unwrap();
+    assert_eq!(body.len(), 1, "Members see the labels of the organization");
+
+    let res = client.get(&url).send().await.unwrap();
+    let body = res.json::<Vec<PublicAddressLabel>>().await.unwrap();
+    assert!(body.is_empty(), "Organization labels are not public");
+    assert_eq!(AddressLabel::count(doc! {}).await.unwrap(), 1);
+  });
+}
+
+#[test]
+fn remove_last_admin_route() {
+  use_app(async move {
+    let nico = create_user("nico@test.com").await.unwrap();
+    let nico_token = create_user_token(nico.clone()).await.unwrap();
+    let organization = create_organization(&nico_token).await;
+
+    let client = reqwest::Client::new();
+    let res = client
+      .delete(format!(
+        "http://localhost:8088/v1/organizations/{}/members/{}",
+        organization.id,
+        nico.id.unwrap()
+      ))
+      .header("Authorization", format!("Bearer {}", nico_token))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::BAD_REQUEST;
+    assert_eq!(actual, expected);
+    assert!(Organization::exists(doc! { "_id": organization.id })
+      .await
+      .unwrap());
+    assert_eq!(Membership::count(doc! {}).await.unwrap(), 1);
+  });
+}
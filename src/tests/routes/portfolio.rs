@@ -0,0 +1,69 @@
+use reqwest;
+use reqwest::StatusCode;
+
+use crate::services::portfolio::{Portfolio, PositionKind};
+use crate::tests::mock_arkham::FAILING_ADDRESS;
+use crate::tests::setup::use_app;
+use crate::utils::address::to_checksum_address;
+
+const ADDRESS: &str = "0x00000000000000000000000000000000000000a1";
+
+#[test]
+fn get_portfolio_route() {
+  use_app(async move {
+    let res = reqwest::get(format!("http://localhost:8088/v1/portfolio/{}", ADDRESS))
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<Portfolio>().await.unwrap();
+    assert_eq!(body.address, to_checksum_address(ADDRESS));
+    assert_eq!(body.usd_value, 3600.0, "Loans should be subtracted");
+
+    let protocols = body
+      .protocols
+      .iter()
+      .map(|protocol| (protocol.protocol.as_str(), protocol.chain.as_str()))
+      .collect::<Vec<(&str, &str)>>();
+    assert_eq!(
+      protocols,
+      vec![
+        ("Lido", "ethereum"),
+        ("Uniswap V3", "arbitrum_one"),
+        ("Aave V3", "ethereum")
+      ],
+      "Protocols should be sorted by value, across pages"
+    );
+
+    let aave = &body.protocols[2];
+    assert_eq!(aave.usd_value, 600.0);
+    assert_eq!(aave.positions[0].kind, PositionKind::Deposit);
+    assert_eq!(aave.positions[1].kind, PositionKind::Loan);
+    assert_eq!(aave.positions[1].symbol.as_deref(), Some("DAI"));
+    assert_eq!(body.protocols[0].positions[0].kind, PositionKind::Staked);
+  });
+}
+
+#[test]
+fn get_portfolio_route_with_upstream_failure() {
+  use_app(async move {
+    let res = reqwest::get(format!(
+      "http://localhost:8088/v1/portfolio/{}",
+      FAILING_ADDRESS
+    ))
+    .await
+    .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::SERVICE_UNAVAILABLE;
+    assert_eq!(actual, expected);
+  });
+}
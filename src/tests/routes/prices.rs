@@ -0,0 +1,113 @@
+use reqwest;
+use reqwest::StatusCode;
+use serde_json::Value as Json;
+use std::collections::HashMap;
+
+use crate::services::prices::TokenPrice;
+use crate::tests::mock_arkham::COINGECKO_REQUESTS;
+use crate::tests::setup::use_app;
+
+#[test]
+fn get_price_route() {
+  use_app(async move {
+    let res = reqwest::get("http://localhost:8088/v1/prices/Ethereum")
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<TokenPrice>().await.unwrap();
+    assert_eq!(body.id, "ethereum");
+    assert_eq!(body.usd, 3000.25);
+    assert_eq!(body.usd_24h_change, Some(-1.5));
+    assert_eq!(
+      body.updated_at.as_deref(),
+      Some("2023-11-14T22:13:20+00:00")
+    );
+  });
+}
+
+#[test]
+fn get_price_route_with_unknown_token() {
+  use_app(async move {
+    let res = reqwest::get("http://localhost:8088/v1/prices/unknown-token")
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::NOT_FOUND;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<Json>().await.unwrap();
+    assert_eq!(body["code"], "not_found");
+  });
+}
+
+#[test]
+fn get_price_route_is_cached() {
+  use_app(async move {
+    // Not used by other tests, so only this one fetches it.
+    let url = "http://localhost:8088/v1/prices/tether";
+    for _ in 0..2 {
+      let res = reqwest::get(url).await.unwrap();
+      assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    let requests = COINGECKO_REQUESTS
+      .lock()
+      .unwrap()
+      .iter()
+      .filter(|ids| ids.as_str() == "tether")
+      .count();
+    assert_eq!(requests, 1, "Second lookup should be served from the cache");
+  });
+}
+
+#[test]
+fn get_prices_route() {
+  use_app(async move {
+    let res =
+      reqwest::get("http://localhost:8088/v1/prices?ids=bitcoin,%20ethereum,unknown,bitcoin")
+        .await
+        .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<HashMap<String, TokenPrice>>().await.unwrap();
+    assert_eq!(body.len(), 2, "Unknown tokens should be left out");
+    assert_eq!(body["bitcoin"].usd, 60000.5);
+    assert_eq!(body["ethereum"].usd, 3000.25);
+  });
+}
+
+#[test]
+fn get_prices_route_with_invalid_ids() {
+  use_app(async move {
+    let urls = [
+      "http://localhost:8088/v1/prices",
+      "http://localhost:8088/v1/prices?ids=",
+      "http://localhost:8088/v1/prices?ids=bitcoin,,ethereum",
+      "http://localhost:8088/v1/prices?ids=bit$coin",
+      // More than `prices.max_ids` in config/test.json.
+      "http://localhost:8088/v1/prices?ids=a,b,c,d",
+    ];
+
+    for url in urls {
+      let res = reqwest::get(url).await.unwrap();
+      assert_eq!(res.status(), StatusCode::BAD_REQUEST, "{}", url);
+    }
+  });
+}
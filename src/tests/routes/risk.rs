@@ -0,0 +1,144 @@
+use reqwest;
+use reqwest::StatusCode;
+
+use crate::models::address_label::AddressLabel;
+use crate::services::risk::{RiskFactorKind, RiskLevel, RiskReport};
+use crate::tests::mock_arkham::{FAILING_ADDRESS, RISKY_SPENDER};
+use crate::tests::setup::use_app;
+use crate::utils::address::to_checksum_address;
+use crate::utils::models::ModelExt;
+
+const ADDRESS: &str = "0x00000000000000000000000000000000000000a1";
+
+#[test]
+fn get_risk_route() {
+  use_app(async move {
+    let res = reqwest::get(format!("http://localhost:8088/v1/risk/{}", ADDRESS))
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<RiskReport>().await.unwrap();
+    assert_eq!(body.address, to_checksum_address(ADDRESS));
+    assert_eq!(body.chain, "ethereum");
+    assert!(body.unavailable_sources.is_empty());
+
+    let factors = body
+      .factors
+      .iter()
+      .map(|factor| (factor.kind, factor.points))
+      .collect::<Vec<(RiskFactorKind, u32)>>();
+    assert_eq!(
+      factors,
+      vec![
+        (RiskFactorKind::RiskyApproval, 30),
+        (RiskFactorKind::UnlimitedApprovals, 10)
+      ]
+    );
+    assert_eq!(body.score, 40);
+    assert_eq!(body.level, RiskLevel::Medium);
+  });
+}
+
+#[test]
+fn get_risk_route_with_risky_label() {
+  use_app(async move {
+    let label = AddressLabel::new(
+      ADDRESS.to_owned(),
+      "Ronin Bridge Exploiter".to_owned(),
+      "community".to_owned(),
+    );
+    AddressLabel::create(label).await.unwrap();
+
+    let res = reqwest::get(format!("http://localhost:8088/v1/risk/{}", ADDRESS))
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<RiskReport>().await.unwrap();
+    let label = body
+      .factors
+      .iter()
+      .find(|factor| factor.kind == RiskFactorKind::RiskyLabel)
+      .expect("The label should be a factor");
+    assert_eq!(label.points, 40);
+    assert_eq!(
+      label.detail,
+      "Labelled \"Ronin Bridge Exploiter\" by community"
+    );
+    assert_eq!(body.score, 80);
+    assert_eq!(body.level, RiskLevel::High);
+  });
+}
+
+#[test]
+fn get_risk_route_with_risky_entity() {
+  use_app(async move {
+    let url = format!("http://localhost:8088/v1/risk/{}", RISKY_SPENDER);
+    let res = reqwest::get(url).await.unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<RiskReport>().await.unwrap();
+    assert_eq!(body.factors[0].kind, RiskFactorKind::RiskyEntity);
+    assert_eq!(body.factors[0].points, 60);
+    assert_eq!(
+      body.factors[0].detail,
+      "Arkham entity Degen is of type hacker"
+    );
+    assert_eq!(body.score, 100, "The score should be capped to 100");
+    assert_eq!(body.level, RiskLevel::High);
+  });
+}
+
+#[test]
+fn get_risk_route_with_unavailable_sources() {
+  use_app(async move {
+    let url = format!("http://localhost:8088/v1/risk/{}", FAILING_ADDRESS);
+    let res = reqwest::get(url).await.unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<RiskReport>().await.unwrap();
+    assert_eq!(body.unavailable_sources, vec!["arkham", "approvals"]);
+    assert!(body.factors.is_empty());
+    assert_eq!(body.score, 0);
+    assert_eq!(body.level, RiskLevel::Low);
+  });
+}
+
+#[test]
+fn get_risk_route_with_unsupported_chain() {
+  use_app(async move {
+    let url = format!("http://localhost:8088/v1/risk/{}?chain=solana", ADDRESS);
+    let res = reqwest::get(url).await.unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::BAD_REQUEST;
+    assert_eq!(actual, expected);
+  });
+}
@@ -0,0 +1,203 @@
+use reqwest;
+use reqwest::StatusCode;
+use serde_json::json;
+
+use crate::models::session::PublicSession;
+use crate::routes::user::AuthenticateResponse;
+use crate::tests::setup::use_app;
+use crate::tests::utils::{create_readonly_user, create_user};
+
+const DEVICE: &str = "degen-test/1.0";
+
+async fn sign_in(client: &reqwest::Client, email: &str, ip: &str) -> AuthenticateResponse {
+  let res = client
+    .post("http://localhost:8088/v1/users/authenticate")
+    .header("User-Agent", DEVICE)
+    .header("X-Forwarded-For", ip)
+    .json(&json!({ "email": email, "password": "Password1" }))
+    .send()
+    .await
+    .unwrap();
+  res.json::<AuthenticateResponse>().await.unwrap()
+}
+
+async fn query_sessions(client: &reqwest::Client, access_token: &str) -> reqwest::Response {
+  client
+    .get("http://localhost:8088/v1/me/sessions")
+    .bearer_auth(access_token)
+    .send()
+    .await
+    .unwrap()
+}
+
+async fn refresh(client: &reqwest::Client, refresh_token: &str) -> reqwest::Response {
+  client
+    .post("http://localhost:8088/v1/auth/refresh")
+    .json(&json!({ "refresh_token": refresh_token }))
+    .send()
+    .await
+    .unwrap()
+}
+
+#[test]
+fn get_sessions_route() {
+  use_app(async move {
+    create_user("nahuel@gmail.com").await.unwrap();
+    let client = reqwest::Client::new();
+    let laptop = sign_in(&client, "nahuel@gmail.com", "203.0.113.9").await;
+    sign_in(&client, "nahuel@gmail.com", "198.51.100.9").await;
+
+    let res = query_sessions(&client, &laptop.access_token).await;
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<Vec<PublicSession>>().await.unwrap();
+    assert_eq!(body.len(), 2);
+    assert!(body[0].current, "The session of the request was just used");
+    assert_eq!(body[0].device.as_deref(), Some(DEVICE));
+    assert_eq!(body[0].ip.as_deref(), Some("203.0.113.9"));
+    assert!(!body[1].current);
+    assert_eq!(body[1].ip.as_deref(), Some("198.51.100.9"));
+  });
+}
+
+#[test]
+fn delete_session_route() {
+  use_app(async move {
+    create_user("nahuel@gmail.com").await.unwrap();
+    let client = reqwest::Client::new();
+    let laptop = sign_in(&client, "nahuel@gmail.com", "203.0.113.9").await;
+    let phone = sign_in(&client, "nahuel@gmail.com", "198.51.100.9").await;
+
+    let res = query_sessions(&client, &phone.access_token).await;
+    let sessions = res.json::<Vec<PublicSession>>().await.unwrap();
+    let phone_session = sessions.iter().find(|session| session.current).unwrap();
+
+    let res = client
+      .delete(format!(
+        "http://localhost:8088/v1/me/sessions/{}",
+        phone_session.id
+      ))
+      .bearer_auth(&laptop.access_token)
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::NO_CONTENT;
+    assert_eq!(actual, expected);
+
+    let res = query_sessions(&client, &phone.access_token).await;
+    assert_eq!(
+      res.status(),
+      StatusCode::UNAUTHORIZED,
+      "Access tokens of the session should be revoked"
+    );
+    let res = refresh(&client, &phone.refresh_token).await;
+    assert_eq!(
+      res.status(),
+      StatusCode::UNAUTHORIZED,
+      "Refresh tokens of the session should be revoked"
+    );
+
+    let res = query_sessions(&client, &laptop.access_token).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let sessions = res.json::<Vec<PublicSession>>().await.unwrap();
+    assert_eq!(sessions.len(), 1);
+  });
+}
+
+#[test]
+fn delete_session_route_of_other_user() {
+  use_app(async move {
+    create_user("nahuel@gmail.com").await.unwrap();
+    create_user("nico@test.com").await.unwrap();
+    let client = reqwest::Client::new();
+    let nahuel = sign_in(&client, "nahuel@gmail.com", "203.0.113.9").await;
+    let nico = sign_in(&client, "nico@test.com", "198.51.100.9").await;
+
+    let res = query_sessions(&client, &nahuel.access_token).await;
+    let sessions = res.json::<Vec<PublicSession>>().await.unwrap();
+
+    let res = client
+      .delete(format!(
+        "http://localhost:8088/v1/me/sessions/{}",
+        sessions[0].id
+      ))
+      .bearer_auth(&nico.access_token)
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::NOT_FOUND;
+    assert_eq!(actual, expected);
+
+    let res = query_sessions(&client, &nahuel.access_token).await;
+    assert_eq!(res.status(), StatusCode::OK);
+  });
+}
+
+#[test]
+fn delete_sessions_route() {
+  use_app(async move {
+    create_user("nahuel@gmail.com").await.unwrap();
+    let client = reqwest::Client::new();
+    let laptop = sign_in(&client, "nahuel@gmail.com", "203.0.113.9").await;
+    let phone = sign_in(&client, "nahuel@gmail.com", "198.51.100.9").await;
+
+    let res = client
+      .delete("http://localhost:8088/v1/me/sessions")
+      .bearer_auth(&laptop.access_token)
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::NO_CONTENT;
+    assert_eq!(actual, expected);
+
+    for tokens in [&laptop, &phone] {
+      let res = query_sessions(&client, &tokens.access_token).await;
+      assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+      let res = refresh(&client, &tokens.refresh_token).await;
+      assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+  });
+}
+
+#[test]
+fn delete_sessions_route_as_readonly() {
+  use_app(async move {
+    create_readonly_user("reader@test.com").await.unwrap();
+    let client = reqwest::Client::new();
+    let reader = sign_in(&client, "reader@test.com", "203.0.113.9").await;
+
+    let res = client
+      .delete("http://localhost:8088/v1/me/sessions")
+      .bearer_auth(&reader.access_token)
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::NO_CONTENT;
+    assert_eq!(actual, expected);
+
+    let res = query_sessions(&client, &reader.access_token).await;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+  });
+}
@@ -0,0 +1,142 @@
+use chrono::Utc;
+use reqwest;
+use reqwest::StatusCode;
+
+use crate::models::address_label::AddressLabel;
+use crate::models::address_query::AddressQuery;
+use crate::models::cat::Cat;
+use crate::models::usage::Usage;
+use crate::models::user::User;
+use crate::models::watched_address::WatchedAddress;
+use crate::models::watchlist::Watchlist;
+use crate::services::stats::Stats;
+use crate::tests::setup::use_app;
+use crate::tests::utils::{create_authenticated_client, create_user};
+use crate::utils::date;
+use crate::utils::models::ModelExt;
+use crate::utils::usage;
+
+const ADDRESS: &str = "0xd8da6bf26964af9d7eed9e10c2c3f8d3b26b93d6";
+
+async fn create_address_query(user: &User, count: i64) {
+  let now = date::now();
+  let query = AddressQuery {
+    id: None,
+    user: user.id,
+    address: ADDRESS.to_owned(),
+    day: Utc::now().format("%Y-%m-%d").to_string(),
+    count,
+    updated_at: now,
+    created_at: now,
+  };
+
+  AddressQuery::create(query).await.unwrap();
+}
+
+#[test]
+fn get_me_stats_route() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let other = create_user("john@test.com").await.unwrap();
+    let user_id = user.id.unwrap();
+
+    Cat::create(Cat::new(user_id, "Tigrin".to_owned()))
+      .await
+      .unwrap();
+    Cat::create(Cat::new(user_id, "Cielito".to_owned()))
+      .await
+      .unwrap();
+    Cat::create(Cat::new(other.id.unwrap(), "Cholin".to_owned()))
+      .await
+      .unwrap();
+
+    let watchlist = Watchlist::create(Watchlist::new(user_id, "Whales".to_owned()))
+      .await
+      .unwrap();
+    let watched = WatchedAddress::new(
+      watchlist.id.unwrap(),
+      user_id,
+      ADDRESS.to_owned(),
+      "ethereum".to_owned(),
+      None,
+    );
+    WatchedAddress::create(watched).await.unwrap();
+
+    AddressLabel::create(AddressLabel::new(
+      ADDRESS.to_owned(),
+      "Vitalik".to_owned(),
+      "arkham".to_owned(),
+    ))
+    .await
+    .unwrap();
+
+    create_address_query(&user, 3).await;
+    create_address_query(&other, 5).await;
+
+    let now = date::now();
+    let usage = Usage {
+      id: None,
+      user: user_id,
+      period: usage::period(Utc::now()),
+      requests: 10,
+      upstream_credits: 4,
+      updated_at: now,
+      created_at: now,
+    };
+    Usage::create(usage).await.unwrap();
+
+    let client = create_authenticated_client(user).await.unwrap();
+    let res = client
+      .get("http://localhost:8088/v1/me/stats?days=7")
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<Stats>().await.unwrap();
+    assert_eq!(body.days, 7);
+    assert_eq!(body.cats_per_day.len(), 1);
+    assert_eq!(
+      body.cats_per_day[0].day,
+      Utc::now().format("%Y-%m-%d").to_string()
+    );
+    assert_eq!(body.cats_per_day[0].count, 2);
+    assert_eq!(body.labels_per_source.len(), 1);
+    assert_eq!(body.labels_per_source[0].source, "arkham");
+    assert_eq!(body.labels_per_source[0].count, 1);
+    assert_eq!(body.largest_watchlists.len(), 1);
+    assert_eq!(body.largest_watchlists[0].name, "Whales");
+    assert_eq!(body.largest_watchlists[0].addresses, 1);
+    assert_eq!(body.top_queried_addresses.len(), 1);
+    assert_eq!(body.top_queried_addresses[0].address, ADDRESS);
+    assert_eq!(body.top_queried_addresses[0].count, 3);
+    assert_eq!(body.upstream_credits.len(), 1);
+    assert_eq!(body.upstream_credits[0].upstream_credits, 4);
+  });
+}
+
+#[test]
+fn get_me_stats_route_with_invalid_days() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let client = create_authenticated_client(user).await.unwrap();
+
+    let res = client
+      .get("http://localhost:8088/v1/me/stats?days=0")
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::BAD_REQUEST;
+    assert_eq!(actual, expected);
+  });
+}
@@ -24,3 +24,116 @@ fn get_status_route() {
     assert_json_eq!(actual, expected);
   });
 }
+
+#[test]
+fn get_health_route() {
+  use_app(async {
+    let res = reqwest::get("http://localhost:8088/health").await.unwrap();
+    let status_code = res.status();
+    let body = res.json::<Json>().await.unwrap();
+
+    // Status code:
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let actual = body;
+    let expected = json!({ "status": "ok" });
+    assert_json_eq!(actual, expected);
+  });
+}
+
+#[test]
+fn get_ready_route() {
+  use_app(async {
+    let res = reqwest::get("http://localhost:8088/ready").await.unwrap();
+    let status_code = res.status();
+    let body = res.json::<Json>().await.unwrap();
+
+    // Status code:
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    assert_eq!(body["status"], "ok");
+    assert_eq!(body["checks"]["mongodb"]["status"], "ok");
+    assert_eq!(body["checks"]["arkham"]["status"], "ok");
+    assert!(body["checks"]["mongodb"]["latency_ms"].is_u64());
+  });
+}
+
+#[test]
+fn get_status_route_generates_request_id() {
+  use_app(async {
+    let res = reqwest::get("http://localhost:8088/status").await.unwrap();
+
+    // Status code:
+    let actual = res.status();
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Headers:
+    let request_id = res.headers().get("x-request-id").unwrap();
+    assert_eq!(request_id.len(), 32);
+  });
+}
+
+#[test]
+fn preflight_request_from_allowed_origin() {
+  use_app(async {
+    let client = reqwest::Client::new();
+    let res = client
+      .request(reqwest::Method::OPTIONS, "http://localhost:8088/status")
+      .header("Origin", "http://localhost:3000")
+      .header("Access-Control-Request-Method", "POST")
+      .header("Access-Control-Request-Headers", "authorization")
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let actual = res.status();
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Headers:
+    let headers = res.headers();
+    assert_eq!(
+      headers.get("access-control-allow-origin").unwrap(),
+      "http://localhost:3000"
+    );
+    assert_eq!(
+      headers.get("access-control-allow-credentials").unwrap(),
+      "true"
+    );
+    let methods = headers
+      .get("access-control-allow-methods")
+      .unwrap()
+      .to_str()
+      .unwrap();
+    assert!(methods.contains("POST"));
+  });
+}
+
+#[test]
+fn get_status_route_from_unknown_origin() {
+  use_app(async {
+    let client = reqwest::Client::new();
+    let res = client
+      .get("http://localhost:8088/status")
+      .header("Origin", "https://evil.example.com")
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let actual = res.status();
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Headers:
+    assert!(res.headers().get("access-control-allow-origin").is_none());
+  });
+}
@@ -0,0 +1,126 @@
+use reqwest;
+use reqwest::StatusCode;
+use serde_json::Value as Json;
+
+use crate::routes::transactions::TransactionPage;
+use crate::tests::mock_arkham::FAILING_ADDRESS;
+use crate::tests::setup::use_app;
+use crate::utils::address::to_checksum_address;
+
+const ADDRESS: &str = "0x00000000000000000000000000000000000000a1";
+
+#[test]
+fn get_transactions_route() {
+  use_app(async move {
+    let res = reqwest::get(format!("http://localhost:8088/v1/tx/ethereum/{}", ADDRESS))
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<TransactionPage>().await.unwrap();
+    assert_eq!(body.chain, "ethereum");
+    assert_eq!(body.address, to_checksum_address(ADDRESS));
+    assert!(!body.has_next);
+
+    let blocks = body
+      .transactions
+      .iter()
+      .map(|transaction| transaction.block_number)
+      .collect::<Vec<u64>>();
+    assert_eq!(blocks, vec![3, 2, 1], "Newest transactions first");
+
+    let transaction = &body.transactions[0];
+    assert_eq!(transaction.from, to_checksum_address(ADDRESS));
+    assert_eq!(transaction.value, "1500000000000000000");
+    assert_eq!(transaction.gas_used, 21000);
+    assert_eq!(transaction.timestamp, "2023-11-14T22:13:23+00:00");
+    assert!(transaction.contract_address.is_none());
+
+    let creation = &body.transactions[2];
+    assert!(
+      creation.to.is_none(),
+      "Contract creations have no recipient"
+    );
+    assert!(creation.contract_address.is_some());
+  });
+}
+
+#[test]
+fn get_transactions_route_with_pagination() {
+  use_app(async move {
+    let url = format!("http://localhost:8088/v1/tx/ethereum/{}", ADDRESS);
+
+    let res = reqwest::get(format!("{}?page=1&limit=2", url))
+      .await
+      .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = res.json::<TransactionPage>().await.unwrap();
+    assert_eq!(body.transactions.len(), 2);
+    assert!(body.has_next);
+
+    let res = reqwest::get(format!("{}?page=2&limit=2", url))
+      .await
+      .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = res.json::<TransactionPage>().await.unwrap();
+    assert_eq!(body.transactions.len(), 1);
+    assert_eq!(body.transactions[0].block_number, 1);
+    assert!(!body.has_next);
+
+    // Past the last transaction:
+    let res = reqwest::get(format!("{}?page=3&limit=2", url))
+      .await
+      .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = res.json::<TransactionPage>().await.unwrap();
+    assert!(body.transactions.is_empty());
+  });
+}
+
+#[test]
+fn get_transactions_route_with_invalid_parameters() {
+  use_app(async move {
+    let urls = [
+      format!("http://localhost:8088/v1/tx/solana/{}", ADDRESS),
+      "http://localhost:8088/v1/tx/ethereum/0x1234".to_owned(),
+      format!("http://localhost:8088/v1/tx/ethereum/{}?page=0", ADDRESS),
+      format!(
+        "http://localhost:8088/v1/tx/ethereum/{}?page=1000&limit=100",
+        ADDRESS
+      ),
+    ];
+
+    for url in urls {
+      let res = reqwest::get(&url).await.unwrap();
+      assert_eq!(res.status(), StatusCode::BAD_REQUEST, "{}", url);
+    }
+  });
+}
+
+#[test]
+fn get_transactions_route_with_failing_explorer() {
+  use_app(async move {
+    let res = reqwest::get(format!(
+      "http://localhost:8088/v1/tx/ethereum/{}",
+      FAILING_ADDRESS
+    ))
+    .await
+    .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::SERVICE_UNAVAILABLE;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<Json>().await.unwrap();
+    assert_eq!(body["code"], "upstream_unavailable");
+  });
+}
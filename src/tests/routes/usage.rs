@@ -0,0 +1,175 @@
+use chrono::Utc;
+use reqwest;
+use reqwest::StatusCode;
+use serde_json::Value as Json;
+
+use crate::models::usage::Usage;
+use crate::models::user::{Plan, User};
+use crate::routes::usage::UsageReport;
+use crate::tests::setup::use_app;
+use crate::tests::utils::{create_authenticated_client, create_user};
+use crate::utils::date;
+use crate::utils::models::ModelExt;
+use crate::utils::usage;
+
+async fn create_usage(user: &User, requests: i64, upstream_credits: i64) -> Usage {
+  let now = date::now();
+  let usage = Usage {
+    id: None,
+    user: user.id.unwrap(),
+    period: usage::period(Utc::now()),
+    requests,
+    upstream_credits,
+    updated_at: now,
+    created_at: now,
+  };
+
+  Usage::create(usage).await.unwrap()
+}
+
+#[test]
+fn get_usage_route() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let client = create_authenticated_client(user).await.unwrap();
+
+    let res = client
+      .get("http://localhost:8088/v1/me/usage")
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Headers:
+    let headers = res.headers();
+    assert_eq!(headers["x-quota-requests-limit"], "10000");
+    assert_eq!(headers["x-quota-requests-remaining"], "9999");
+    assert_eq!(headers["x-quota-credits-limit"], "1000");
+    assert_eq!(headers["x-quota-credits-remaining"], "1000");
+    let reset = usage::next_period_start(Utc::now()).timestamp().to_string();
+    assert_eq!(headers["x-quota-reset"], reset.as_str());
+
+    // Body:
+    let body = res.json::<UsageReport>().await.unwrap();
+    assert_eq!(body.plan, Plan::Free);
+    assert_eq!(body.period, usage::period(Utc::now()));
+    assert_eq!(body.requests.used, 1);
+    assert_eq!(body.requests.remaining, 9999);
+    assert_eq!(body.upstream_credits.used, 0);
+    assert_eq!(body.upstream_credits.limit, 1000);
+  });
+}
+
+#[test]
+fn get_usage_route_counts_requests() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let client = create_authenticated_client(user).await.unwrap();
+
+    for _ in 0..2 {
+      let res = client
+        .get("http://localhost:8088/v1/api-keys")
+        .send()
+        .await
+        .unwrap();
+      assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    let res = client
+      .get("http://localhost:8088/v1/me/usage")
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<UsageReport>().await.unwrap();
+    assert_eq!(body.requests.used, 3);
+  });
+}
+
+#[test]
+fn get_usage_route_without_token() {
+  use_app(async move {
+    let res = reqwest::get("http://localhost:8088/v1/me/usage")
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::UNAUTHORIZED;
+    assert_eq!(actual, expected);
+
+    // Headers:
+    assert!(res.headers().get("x-quota-requests-limit").is_none());
+  });
+}
+
+#[test]
+fn request_over_quota_is_rejected() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    create_usage(&user, 10000, 0).await;
+    let client = create_authenticated_client(user).await.unwrap();
+
+    let res = client
+      .get("http://localhost:8088/v1/api-keys")
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::TOO_MANY_REQUESTS;
+    assert_eq!(actual, expected);
+
+    // Headers:
+    assert_eq!(res.headers()["x-quota-requests-remaining"], "0");
+
+    // Body:
+    let body = res.json::<Json>().await.unwrap();
+    assert_eq!(body["code"], "quota_exceeded");
+    assert_eq!(body["details"]["quota"], "requests");
+  });
+}
+
+#[test]
+fn upstream_request_over_credit_quota_is_rejected() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    create_usage(&user, 0, 1000).await;
+    let client = create_authenticated_client(user).await.unwrap();
+
+    let res = client
+      .get("http://localhost:8088/v1/arkham/entity/over-quota")
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::TOO_MANY_REQUESTS;
+    assert_eq!(actual, expected);
+
+    // Headers:
+    assert_eq!(res.headers()["x-quota-credits-remaining"], "0");
+
+    // Body:
+    let body = res.json::<Json>().await.unwrap();
+    assert_eq!(body["code"], "quota_exceeded");
+    assert_eq!(body["details"]["quota"], "upstream_credits");
+  });
+}
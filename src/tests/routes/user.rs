@@ -1,11 +1,40 @@
+use bson::doc;
 use reqwest;
 use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::sync::Arc;
 
-use crate::models::user::PublicUser;
+use crate::models::api_key::{ApiKey, ApiKeyScope};
+use crate::models::cat::Cat;
+use crate::models::job::{Job, JobKind, JobStatus, PublicJob};
+use crate::models::user::{PublicUser, User};
+use crate::models::user_address_note::UserAddressNote;
+use crate::models::watchlist::Watchlist;
 use crate::routes::user::AuthenticateResponse;
+use crate::services::account::DeletionConfirmation;
+use crate::services::jobs;
+use crate::settings::SETTINGS;
+use crate::state::AppState;
 use crate::tests::setup::use_app;
-use crate::tests::utils::create_user;
+use crate::tests::utils::{create_authenticated_client, create_readonly_user, create_user};
+use crate::utils::date;
+use crate::utils::models::ModelExt;
+
+// Hands out a deletion confirmation token to the client.
+async fn request_deletion(client: &reqwest::Client) -> String {
+  let res = client
+    .post("http://localhost:8088/v1/me/deletion")
+    .send()
+    .await
+    .unwrap();
+  assert_eq!(res.status(), StatusCode::CREATED);
+  res
+    .json::<DeletionConfirmation>()
+    .await
+    .unwrap()
+    .confirmation_token
+}
 
 #[test]
 fn post_user_route() {
@@ -25,7 +54,7 @@ fn post_user_route() {
   use_app(async move {
     let client = reqwest::Client::new();
     let res = client
-      .post("http://localhost:8088/users")
+      .post("http://localhost:8088/v1/users")
       .json(&body)
       .send()
       .await
@@ -44,6 +73,30 @@ fn post_user_route() {
   });
 }
 
+#[test]
+fn post_user_route_with_wallet_email() {
+  use_app(async move {
+    let client = reqwest::Client::new();
+    let res = client
+      .post("http://localhost:8088/v1/users")
+      .json(&json!({
+        "name": "Nahuel",
+        "email": "0x00000000000000000000000000000000000000a1@Wallet.invalid",
+        "password": "Password1"
+      }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::UNPROCESSABLE_ENTITY;
+    assert_eq!(actual, expected);
+    assert_eq!(User::count(doc! {}).await.unwrap(), 0);
+  });
+}
+
 #[test]
 fn authenticate_user_route() {
   #[derive(Debug, Serialize, Deserialize)]
@@ -62,7 +115,7 @@ fn authenticate_user_route() {
 
     let client = reqwest::Client::new();
     let res = client
-      .post("http://localhost:8088/users/authenticate")
+      .post("http://localhost:8088/v1/users/authenticate")
       .json(&request_body)
       .send()
       .await
@@ -77,5 +130,436 @@ fn authenticate_user_route() {
     // Body:
     let body = res.json::<AuthenticateResponse>().await.unwrap();
     assert_eq!(body.user.email, "nahuel@gmail.com");
+    assert!(!body.refresh_token.is_empty());
+  });
+}
+
+#[test]
+fn get_me_route() {
+  use_app(async move {
+    let user = create_user("nahuel@gmail.com").await.unwrap();
+    let client = create_authenticated_client(user).await.unwrap();
+
+    let res = client
+      .get("http://localhost:8088/v1/me")
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<PublicUser>().await.unwrap();
+    assert_eq!(body.email, "nahuel@gmail.com");
+    assert!(!body.email_verified);
+  });
+}
+
+#[test]
+fn put_me_route() {
+  use_app(async move {
+    let user = create_user("nahuel@gmail.com").await.unwrap();
+    let user_id = user.id.unwrap();
+    User::update_one(
+      doc! { "_id": &user_id },
+      doc! { "$set": { "email_verified_at": date::now() } },
+      None,
+    )
+    .await
+    .unwrap();
+    let client = create_authenticated_client(user).await.unwrap();
+
+    let res = client
+      .put("http://localhost:8088/v1/me")
+      .json(&json!({
+        "name": "Nico",
+        "email": "nico@gmail.com",
+        "current_password": "Password1"
+      }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<PublicUser>().await.unwrap();
+    assert_eq!(body.name, "Nico");
+    assert_eq!(body.email, "nico@gmail.com");
+    assert!(!body.email_verified, "New emails must be verified again");
+
+    let res = client
+      .put("http://localhost:8088/v1/me")
+      .json(&json!({ "name": " " }))
+      .send()
+      .await
+      .unwrap();
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+  });
+}
+
+#[test]
+fn put_me_route_as_readonly() {
+  use_app(async move {
+    let user = create_readonly_user("reader@test.com").await.unwrap();
+    let client = create_authenticated_client(user).await.unwrap();
+
+    let res = client
+      .put("http://localhost:8088/v1/me")
+      .json(&json!({ "name": "Nico" }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<PublicUser>().await.unwrap();
+    assert_eq!(body.name, "Nico");
+  });
+}
+
+#[test]
+fn put_me_route_with_taken_email() {
+  use_app(async move {
+    let user = create_user("nahuel@gmail.com").await.unwrap();
+    create_user("nico@gmail.com").await.unwrap();
+    let client = create_authenticated_client(user).await.unwrap();
+
+    let res = client
+      .put("http://localhost:8088/v1/me")
+      .json(&json!({ "email": "nico@gmail.com", "current_password": "Password1" }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::CONFLICT;
+    assert_eq!(actual, expected);
+  });
+}
+
+#[test]
+fn put_me_route_with_wallet_email() {
+  use_app(async move {
+    let user = create_user("nahuel@gmail.com").await.unwrap();
+    let client = create_authenticated_client(user).await.unwrap();
+
+    let res = client
+      .put("http://localhost:8088/v1/me")
+      .json(&json!({
+        "email": "0x00000000000000000000000000000000000000a1@wallet.invalid",
+        "current_password": "Password1"
+      }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::UNPROCESSABLE_ENTITY;
+    assert_eq!(actual, expected);
+  });
+}
+
+#[test]
+fn put_me_route_without_current_password() {
+  use_app(async move {
+    let user = create_user("nahuel@gmail.com").await.unwrap();
+    let user_id = user.id.unwrap();
+    let client = create_authenticated_client(user).await.unwrap();
+
+    let res = client
+      .put("http://localhost:8088/v1/me")
+      .json(&json!({ "email": "nico@gmail.com" }))
+      .send()
+      .await
+      .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    let res = client
+      .put("http://localhost:8088/v1/me")
+      .json(&json!({ "email": "nico@gmail.com", "current_password": "Password2" }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::UNAUTHORIZED;
+    assert_eq!(actual, expected);
+
+    let user = User::find_by_id(&user_id).await.unwrap().unwrap();
+    assert_eq!(user.email, "nahuel@gmail.com");
+  });
+}
+
+#[test]
+fn put_me_route_with_api_key() {
+  use_app(async move {
+    let user = create_user("nahuel@gmail.com").await.unwrap();
+    let user_id = user.id.unwrap();
+    let (api_key, key) = ApiKey::generate(
+      user_id,
+      "Bot".to_owned(),
+      vec![ApiKeyScope::Read, ApiKeyScope::Write],
+    );
+    ApiKey::create(api_key).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .put("http://localhost:8088/v1/me")
+      .header("X-Api-Key", &key)
+      .json(&json!({ "email": "nico@gmail.com", "current_password": "Password1" }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::FORBIDDEN;
+    assert_eq!(actual, expected);
+
+    let user = User::find_by_id(&user_id).await.unwrap().unwrap();
+    assert_eq!(user.email, "nahuel@gmail.com");
+
+    let res = client
+      .put("http://localhost:8088/v1/me")
+      .header("X-Api-Key", &key)
+      .json(&json!({ "name": "Nico" }))
+      .send()
+      .await
+      .unwrap();
+    assert_eq!(
+      res.status(),
+      StatusCode::OK,
+      "API keys can still change the name"
+    );
+  });
+}
+
+#[test]
+fn put_me_route_revokes_other_sessions() {
+  use_app(async move {
+    create_user("nahuel@gmail.com").await.unwrap();
+    let client = reqwest::Client::new();
+    let mut tokens = Vec::new();
+    for _ in 0..2 {
+      let res = client
+        .post("http://localhost:8088/v1/users/authenticate")
+        .json(&json!({ "email": "nahuel@gmail.com", "password": "Password1" }))
+        .send()
+        .await
+        .unwrap();
+      tokens.push(res.json::<AuthenticateResponse>().await.unwrap());
+    }
+    let (laptop, phone) = (&tokens[0], &tokens[1]);
+
+    let res = client
+      .put("http://localhost:8088/v1/me")
+      .bearer_auth(&laptop.access_token)
+      .json(&json!({ "email": "nico@gmail.com", "current_password": "Password1" }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    let res = client
+      .get("http://localhost:8088/v1/me")
+      .bearer_auth(&phone.access_token)
+      .send()
+      .await
+      .unwrap();
+    assert_eq!(
+      res.status(),
+      StatusCode::UNAUTHORIZED,
+      "Other sessions should be revoked"
+    );
+    let res = client
+      .post("http://localhost:8088/v1/auth/refresh")
+      .json(&json!({ "refresh_token": &phone.refresh_token }))
+      .send()
+      .await
+      .unwrap();
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+    let res = client
+      .get("http://localhost:8088/v1/me")
+      .bearer_auth(&laptop.access_token)
+      .send()
+      .await
+      .unwrap();
+    assert_eq!(
+      res.status(),
+      StatusCode::OK,
+      "The session of the change is kept"
+    );
+  });
+}
+
+#[test]
+fn delete_me_route() {
+  use_app(async move {
+    let user = create_user("nahuel@gmail.com").await.unwrap();
+    let user_id = user.id.unwrap();
+    let other = create_user("nico@gmail.com").await.unwrap();
+    Cat::create(Cat::new(user_id, "Tigrin".to_owned()))
+      .await
+      .unwrap();
+    Cat::create(Cat::new(other.id.unwrap(), "Cholin".to_owned()))
+      .await
+      .unwrap();
+    Watchlist::create(Watchlist::new(user_id, "Exchanges".to_owned()))
+      .await
+      .unwrap();
+    UserAddressNote::create(UserAddressNote::new(
+      user_id,
+      "0x00000000000000000000000000000000000000a1".to_owned(),
+    ))
+    .await
+    .unwrap();
+    let client = create_authenticated_client(user).await.unwrap();
+
+    let res = client
+      .delete("http://localhost:8088/v1/me")
+      .json(&json!({ "confirmation_token": "unknown" }))
+      .send()
+      .await
+      .unwrap();
+    assert_eq!(
+      res.status(),
+      StatusCode::UNPROCESSABLE_ENTITY,
+      "Deletions must be confirmed"
+    );
+
+    let token = request_deletion(&client).await;
+    let res = client
+      .delete("http://localhost:8088/v1/me")
+      .json(&json!({ "confirmation_token": token }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::NO_CONTENT;
+    assert_eq!(actual, expected);
+
+    // User and their data from the database:
+    assert!(User::find_by_id(&user_id).await.unwrap().is_none());
+    let query = doc! { "user": &user_id };
+    assert_eq!(Cat::count(query.clone()).await.unwrap(), 0);
+    assert_eq!(Watchlist::count(query.clone()).await.unwrap(), 0);
+    assert_eq!(UserAddressNote::count(query).await.unwrap(), 0);
+    let count = Cat::count(doc! { "user": other.id.unwrap() })
+      .await
+      .unwrap();
+    assert_eq!(count, 1, "Other users data should be kept");
+  });
+}
+
+#[test]
+fn delete_me_route_with_api_key() {
+  use_app(async move {
+    let user = create_user("nahuel@gmail.com").await.unwrap();
+    let user_id = user.id.unwrap();
+    let (api_key, key) = ApiKey::generate(
+      user_id,
+      "Bot".to_owned(),
+      vec![ApiKeyScope::Read, ApiKeyScope::Write],
+    );
+    ApiKey::create(api_key).await.unwrap();
+    let client = reqwest::Client::new();
+
+    let res = client
+      .post("http://localhost:8088/v1/me/deletion")
+      .header("X-Api-Key", &key)
+      .send()
+      .await
+      .unwrap();
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    let token = request_deletion(&create_authenticated_client(user).await.unwrap()).await;
+    let res = client
+      .delete("http://localhost:8088/v1/me")
+      .header("X-Api-Key", &key)
+      .json(&json!({ "confirmation_token": token }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::FORBIDDEN;
+    assert_eq!(actual, expected);
+
+    assert!(User::find_by_id(&user_id).await.unwrap().is_some());
+  });
+}
+
+#[test]
+fn delete_me_route_respond_async() {
+  use_app(async move {
+    let user = create_user("nahuel@gmail.com").await.unwrap();
+    let user_id = user.id.unwrap();
+    Cat::create(Cat::new(user_id, "Tigrin".to_owned()))
+      .await
+      .unwrap();
+    let client = create_authenticated_client(user).await.unwrap();
+
+    let token = request_deletion(&client).await;
+    let res = client
+      .delete("http://localhost:8088/v1/me")
+      .header("Prefer", "respond-async")
+      .json(&json!({ "confirmation_token": token }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::ACCEPTED;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<PublicJob>().await.unwrap();
+    assert_eq!(body.kind, JobKind::AccountDeletion);
+
+    let user = User::find_by_id(&user_id).await.unwrap().unwrap();
+    assert!(user.locked_at.is_some(), "Locked until the job runs");
+
+    let state = AppState::new(Arc::new(SETTINGS.clone()));
+    assert!(jobs::run_next(&state).await.unwrap());
+
+    let job = Job::find_by_id(&body.id).await.unwrap().unwrap();
+    assert_eq!(job.status, JobStatus::Succeeded);
+    assert!(User::find_by_id(&user_id).await.unwrap().is_none());
+    let count = Cat::count(doc! { "user": &user_id }).await.unwrap();
+    assert_eq!(count, 0);
   });
 }
@@ -0,0 +1,482 @@
+use bson::doc;
+use reqwest;
+use reqwest::StatusCode;
+use serde_json::json;
+
+use crate::models::address_snapshot::{AddressSnapshot, PublicAddressSnapshot};
+use crate::models::watched_address::PublicWatchedAddress;
+use crate::models::watched_address::WatchedAddress;
+use crate::models::watchlist::PublicWatchlist;
+use crate::models::watchlist::Watchlist;
+use crate::routes::arkham::parse_arkham_response;
+use crate::routes::watchlist::WatchlistDetail;
+use crate::tests::mock_arkham::address_payload;
+use crate::tests::setup::use_app;
+use crate::tests::utils::create_readonly_user;
+use crate::tests::utils::create_user;
+use crate::tests::utils::create_user_token;
+use crate::utils::address::to_checksum_address;
+use crate::utils::models::ModelExt;
+
+const ADDRESS: &str = "0x00000000000000000000000000000000000000a1";
+
+#[test]
+fn post_watchlist_route() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user.clone()).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .post("http://localhost:8088/v1/watchlists")
+      .header("Authorization", format!("Bearer {}", token))
+      .json(&json!({ "name": " Exchanges " }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::CREATED;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<PublicWatchlist>().await.unwrap();
+    assert_eq!(body.name, "Exchanges");
+    assert_eq!(body.user, user.id.unwrap());
+
+    let path = format!("/v1/watchlists/{}", body.id);
+    assert_eq!(body.links["self"].href, path);
+    assert_eq!(body.links["update"].method, "PUT");
+    assert_eq!(body.links["history"].href, format!("{}/history", path));
+  });
+}
+
+#[test]
+fn post_watchlist_route_as_readonly() {
+  use_app(async move {
+    let user = create_readonly_user("reader@test.com").await.unwrap();
+    let token = create_user_token(user).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .post("http://localhost:8088/v1/watchlists")
+      .header("Authorization", format!("Bearer {}", token))
+      .json(&json!({ "name": "Exchanges" }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::FORBIDDEN;
+    assert_eq!(actual, expected);
+    assert_eq!(Watchlist::count(doc! {}).await.unwrap(), 0);
+  });
+}
+
+#[test]
+fn get_watchlist_by_id_route_as_readonly() {
+  use_app(async move {
+    let user = create_readonly_user("reader@test.com").await.unwrap();
+    let token = create_user_token(user.clone()).await.unwrap();
+    let watchlist = Watchlist::new(user.id.unwrap(), "Exchanges".to_owned());
+    let watchlist = Watchlist::create(watchlist).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .get(format!(
+        "http://localhost:8088/v1/watchlists/{}",
+        watchlist.id.unwrap()
+      ))
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+  });
+}
+
+#[test]
+fn post_watched_address_route() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user.clone()).await.unwrap();
+    let watchlist = Watchlist::new(user.id.unwrap(), "Exchanges".to_owned());
+    let watchlist = Watchlist::create(watchlist).await.unwrap();
+    let url = format!(
+      "http://localhost:8088/v1/watchlists/{}/addresses",
+      watchlist.id.unwrap()
+    );
+
+    let client = reqwest::Client::new();
+    let res = client
+      .post(&url)
+      .header("Authorization", format!("Bearer {}", token))
+      .json(&json!({ "address": ADDRESS.replace('a', "A"), "nickname": "Binance" }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::CREATED;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<PublicWatchedAddress>().await.unwrap();
+    assert_eq!(
+      body.address,
+      to_checksum_address(ADDRESS),
+      "Address should be checksummed"
+    );
+    assert_eq!(body.chain, "ethereum", "Chain should default to ethereum");
+    assert_eq!(body.nickname.as_deref(), Some("Binance"));
+
+    // Adding the same address twice:
+    let res = client
+      .post(&url)
+      .header("Authorization", format!("Bearer {}", token))
+      .json(&json!({ "address": ADDRESS, "chain": "ethereum" }))
+      .send()
+      .await
+      .unwrap();
+    assert_eq!(res.status(), StatusCode::CONFLICT);
+  });
+}
+
+#[test]
+fn post_watched_address_route_with_invalid_address() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user.clone()).await.unwrap();
+    let watchlist = Watchlist::new(user.id.unwrap(), "Exchanges".to_owned());
+    let watchlist = Watchlist::create(watchlist).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .post(format!(
+        "http://localhost:8088/v1/watchlists/{}/addresses",
+        watchlist.id.unwrap()
+      ))
+      .header("Authorization", format!("Bearer {}", token))
+      .json(&json!({ "address": "0x123" }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::UNPROCESSABLE_ENTITY;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<serde_json::Value>().await.unwrap();
+    assert_eq!(body["code"], "validation_failed");
+    assert_eq!(
+      body["details"]["fields"]["address"][0]["code"],
+      "evm_address"
+    );
+  });
+}
+
+#[test]
+fn post_watched_address_route_to_other_user_watchlist() {
+  use_app(async move {
+    let owner = create_user("owner@test.com").await.unwrap();
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user).await.unwrap();
+    let watchlist = Watchlist::new(owner.id.unwrap(), "Exchanges".to_owned());
+    let watchlist = Watchlist::create(watchlist).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .post(format!(
+        "http://localhost:8088/v1/watchlists/{}/addresses",
+        watchlist.id.unwrap()
+      ))
+      .header("Authorization", format!("Bearer {}", token))
+      .json(&json!({ "address": ADDRESS }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::NOT_FOUND;
+    assert_eq!(actual, expected);
+  });
+}
+
+#[test]
+fn get_watchlist_by_id_route() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user.clone()).await.unwrap();
+    let watchlist = Watchlist::new(user.id.unwrap(), "Exchanges".to_owned());
+    let watchlist = Watchlist::create(watchlist).await.unwrap();
+    let url = format!(
+      "http://localhost:8088/v1/watchlists/{}",
+      watchlist.id.unwrap()
+    );
+
+    let client = reqwest::Client::new();
+    for chain in ["ethereum", "arbitrum_one"] {
+      client
+        .post(format!("{}/addresses", url))
+        .header("Authorization", format!("Bearer {}", token))
+        .json(&json!({ "address": ADDRESS, "chain": chain }))
+        .send()
+        .await
+        .unwrap();
+    }
+
+    let res = client
+      .get(&url)
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<WatchlistDetail>().await.unwrap();
+    assert_eq!(body.watchlist.name, "Exchanges");
+    let chains = body
+      .addresses
+      .iter()
+      .map(|watched| watched.chain.as_str())
+      .collect::<Vec<&str>>();
+    assert_eq!(chains, vec!["ethereum", "arbitrum_one"]);
+  });
+}
+
+#[test]
+fn put_watchlist_by_id_route() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user.clone()).await.unwrap();
+    let watchlist = Watchlist::new(user.id.unwrap(), "Exchanges".to_owned());
+    let watchlist = Watchlist::create(watchlist).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .put(format!(
+        "http://localhost:8088/v1/watchlists/{}",
+        watchlist.id.unwrap()
+      ))
+      .header("Authorization", format!("Bearer {}", token))
+      .header("If-Match", format!("\"{}\"", watchlist.version))
+      .json(&json!({ "name": " Bridges " }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<PublicWatchlist>().await.unwrap();
+    assert_eq!(body.name, "Bridges");
+    assert_eq!(body.version, watchlist.version + 1);
+  });
+}
+
+#[test]
+fn put_watchlist_by_id_route_with_stale_version() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user.clone()).await.unwrap();
+    let watchlist = Watchlist::new(user.id.unwrap(), "Exchanges".to_owned());
+    let watchlist = Watchlist::create(watchlist).await.unwrap();
+    let url = format!(
+      "http://localhost:8088/v1/watchlists/{}",
+      watchlist.id.unwrap()
+    );
+
+    // Both clients read the same version, the second update is rejected.
+    let client = reqwest::Client::new();
+    for (name, expected) in [
+      ("Bridges", StatusCode::OK),
+      ("Mixers", StatusCode::CONFLICT),
+    ] {
+      let res = client
+        .put(&url)
+        .header("Authorization", format!("Bearer {}", token))
+        .header("If-Match", watchlist.version.to_string())
+        .json(&json!({ "name": name }))
+        .send()
+        .await
+        .unwrap();
+
+      // Status code:
+      let actual = res.status();
+      assert_eq!(actual, expected);
+    }
+
+    let watchlist = Watchlist::find_by_id(&watchlist.id.unwrap())
+      .await
+      .unwrap()
+      .unwrap();
+    assert_eq!(watchlist.name, "Bridges");
+  });
+}
+
+#[test]
+fn put_watchlist_by_id_route_without_if_match() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user.clone()).await.unwrap();
+    let watchlist = Watchlist::new(user.id.unwrap(), "Exchanges".to_owned());
+    let watchlist = Watchlist::create(watchlist).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .put(format!(
+        "http://localhost:8088/v1/watchlists/{}",
+        watchlist.id.unwrap()
+      ))
+      .header("Authorization", format!("Bearer {}", token))
+      .json(&json!({ "name": "Bridges" }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::PRECONDITION_REQUIRED;
+    assert_eq!(actual, expected);
+  });
+}
+
+#[test]
+fn post_watchlist_share_route() {
+  use_app(async move {
+    let owner = create_user("nico@test.com").await.unwrap();
+    let owner_token = create_user_token(owner.clone()).await.unwrap();
+    let friend = create_user("lucas@test.com").await.unwrap();
+    let friend_token = create_user_token(friend.clone()).await.unwrap();
+    let watchlist = Watchlist::new(owner.id.unwrap(), "Exchanges".to_owned());
+    let watchlist = Watchlist::create(watchlist).await.unwrap();
+    let url = format!(
+      "http://localhost:8088/v1/watchlists/{}",
+      watchlist.id.unwrap()
+    );
+
+    let client = reqwest::Client::new();
+    let res = client
+      .post(format!("{}/shares", url))
+      .header("Authorization", format!("Bearer {}", owner_token))
+      .json(&json!({ "email": "lucas@test.com", "access": "read" }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::CREATED;
+    assert_eq!(actual, expected);
+
+    let res = client
+      .get(&url)
+      .header("Authorization", format!("Bearer {}", friend_token))
+      .send()
+      .await
+      .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    // Sharing again changes the access of the grant.
+    let res = client
+      .post(format!("{}/shares", url))
+      .header("Authorization", format!("Bearer {}", owner_token))
+      .json(&json!({ "email": "lucas@test.com", "access": "read_write" }))
+      .send()
+      .await
+      .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = client
+      .get(format!("{}/shares", url))
+      .header("Authorization", format!("Bearer {}", owner_token))
+      .send()
+      .await
+      .unwrap();
+    let shares = res.json::<Vec<serde_json::Value>>().await.unwrap();
+    assert_eq!(shares.len(), 1);
+    assert_eq!(shares[0]["access"], "read_write");
+  });
+}
+
+#[test]
+fn get_watchlist_history_route() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user.clone()).await.unwrap();
+    let watchlist = Watchlist::new(user.id.unwrap(), "Exchanges".to_owned());
+    let watchlist = Watchlist::create(watchlist).await.unwrap();
+    let watched = WatchedAddress::new(
+      watchlist.id.unwrap(),
+      user.id.unwrap(),
+      ADDRESS.to_owned(),
+      "ethereum".to_owned(),
+      None,
+    );
+    WatchedAddress::create(watched).await.unwrap();
+
+    let data = parse_arkham_response(address_payload(ADDRESS)).unwrap();
+    AddressSnapshot::create(AddressSnapshot::new(ADDRESS.to_owned(), data.clone()))
+      .await
+      .unwrap();
+    // Snapshots of addresses outside the watchlist are left out.
+    let other = "0x00000000000000000000000000000000000000b2";
+    AddressSnapshot::create(AddressSnapshot::new(other.to_owned(), data))
+      .await
+      .unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .get(format!(
+        "http://localhost:8088/v1/watchlists/{}/history",
+        watchlist.id.unwrap()
+      ))
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Headers:
+    let headers = res.headers();
+    assert_eq!(headers.get("X-Pagination-Count").unwrap(), "1");
+
+    // Body:
+    let body = res.json::<Vec<PublicAddressSnapshot>>().await.unwrap();
+    assert_eq!(body.len(), 1);
+    assert_eq!(body[0].address, to_checksum_address(ADDRESS));
+  });
+}
@@ -0,0 +1,262 @@
+use bson::doc;
+use reqwest;
+use reqwest::StatusCode;
+use serde_json::json;
+
+use crate::models::alert_rule::AlertCondition;
+use crate::models::user::User;
+use crate::models::webhook_endpoint::{PublicWebhookEndpoint, WebhookEndpoint};
+use crate::routes::webhook::{CreatedWebhook, WebhookTest};
+use crate::tests::setup::use_app;
+use crate::tests::utils::create_user;
+use crate::tests::utils::create_user_token;
+use crate::utils::models::ModelExt;
+
+async fn create_endpoint(user: &User, path: &str) -> WebhookEndpoint {
+  let url = format!("http://localhost:8089/webhooks/{}", path);
+  let endpoint = WebhookEndpoint::new(user.id.unwrap(), url, Vec::new());
+  WebhookEndpoint::create(endpoint).await.unwrap()
+}
+
+#[test]
+fn post_webhook_route() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .post("http://localhost:8088/v1/webhooks")
+      .header("Authorization", format!("Bearer {}", token))
+      .json(&json!({ "url": "https://example.com/hooks/degen" }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::CREATED;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<PublicWebhookEndpoint>().await.unwrap();
+    assert_eq!(body.url, "https://example.com/hooks/degen");
+  });
+}
+
+#[test]
+fn post_webhook_route_with_events() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .post("http://localhost:8088/v1/webhooks")
+      .header("Authorization", format!("Bearer {}", token))
+      .json(&json!({
+        "url": "https://example.com/hooks/degen",
+        "events": ["entity_attached", "entity_attached"]
+      }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::CREATED;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<CreatedWebhook>().await.unwrap();
+    assert_eq!(body.webhook.events, vec![AlertCondition::EntityAttached]);
+    assert_eq!(body.secret.len(), 32);
+
+    // Webhook endpoint from the database:
+    let endpoint = WebhookEndpoint::find_by_id(&body.webhook.id)
+      .await
+      .unwrap()
+      .unwrap();
+    assert_eq!(endpoint.secret, Some(body.secret));
+  });
+}
+
+#[test]
+fn get_webhook_route() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user.clone()).await.unwrap();
+    let endpoint = create_endpoint(&user, "ok").await;
+
+    let client = reqwest::Client::new();
+    let res = client
+      .get(format!(
+        "http://localhost:8088/v1/webhooks/{}",
+        endpoint.id.unwrap()
+      ))
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<serde_json::Value>().await.unwrap();
+    assert_eq!(body["url"], endpoint.url);
+    assert!(
+      body.get("secret").is_none(),
+      "Secret should not be returned"
+    );
+  });
+}
+
+#[test]
+fn post_webhook_secret_route() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user.clone()).await.unwrap();
+    let endpoint = create_endpoint(&user, "ok").await;
+    // Created before signatures existed:
+    WebhookEndpoint::update_one(
+      doc! { "_id": endpoint.id.unwrap() },
+      doc! { "$unset": { "secret": "" } },
+      None,
+    )
+    .await
+    .unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .post(format!(
+        "http://localhost:8088/v1/webhooks/{}/secret",
+        endpoint.id.unwrap()
+      ))
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::OK;
+    assert_eq!(actual, expected);
+
+    // Body:
+    let body = res.json::<CreatedWebhook>().await.unwrap();
+    assert_eq!(body.webhook.id, endpoint.id.unwrap());
+    assert_eq!(body.secret.len(), 32);
+
+    // Webhook endpoint from the database:
+    let endpoint = WebhookEndpoint::find_by_id(&body.webhook.id)
+      .await
+      .unwrap()
+      .unwrap();
+    assert_eq!(endpoint.secret, Some(body.secret));
+  });
+}
+
+#[test]
+fn post_webhook_test_route() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user.clone()).await.unwrap();
+    let ok = create_endpoint(&user, "ok").await;
+    let failing = create_endpoint(&user, "failing").await;
+
+    let client = reqwest::Client::new();
+    let url = |endpoint: &WebhookEndpoint| {
+      format!(
+        "http://localhost:8088/v1/webhooks/{}/test",
+        endpoint.id.unwrap()
+      )
+    };
+
+    let res = client
+      .post(url(&ok))
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = res.json::<WebhookTest>().await.unwrap();
+    assert!(body.delivered);
+    assert!(body.error.is_none());
+
+    let res = client
+      .post(url(&failing))
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = res.json::<WebhookTest>().await.unwrap();
+    assert!(!body.delivered);
+    assert_eq!(
+      body.error.as_deref(),
+      Some("Received a 500 Internal Server Error status code")
+    );
+  });
+}
+
+#[test]
+fn post_webhook_route_with_invalid_url() {
+  use_app(async move {
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .post("http://localhost:8088/v1/webhooks")
+      .header("Authorization", format!("Bearer {}", token))
+      .json(&json!({ "url": "ftp://example.com/hooks" }))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::BAD_REQUEST;
+    assert_eq!(actual, expected);
+  });
+}
+
+#[test]
+fn get_webhook_deliveries_route_of_other_user() {
+  use_app(async move {
+    let owner = create_user("owner@test.com").await.unwrap();
+    let user = create_user("nico@test.com").await.unwrap();
+    let token = create_user_token(user).await.unwrap();
+    let endpoint = WebhookEndpoint::new(
+      owner.id.unwrap(),
+      "https://example.com".to_owned(),
+      Vec::new(),
+    );
+    let endpoint = WebhookEndpoint::create(endpoint).await.unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client
+      .get(format!(
+        "http://localhost:8088/v1/webhooks/{}/deliveries",
+        endpoint.id.unwrap()
+      ))
+      .header("Authorization", format!("Bearer {}", token))
+      .send()
+      .await
+      .unwrap();
+
+    // Status code:
+    let status_code = res.status();
+    let actual = status_code;
+    let expected = StatusCode::NOT_FOUND;
+    assert_eq!(actual, expected);
+  });
+}
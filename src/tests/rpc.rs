@@ -0,0 +1,63 @@
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+use crate::errors::Error;
+use crate::services::rpc::{encode_address, uint_to_decimal, RpcClient};
+use crate::settings::{Rpc, SETTINGS};
+use crate::tests::setup::use_app;
+
+#[test]
+fn uint_to_decimal_converts_words() {
+  assert_eq!(uint_to_decimal(&[0; 32]), "0");
+  assert_eq!(uint_to_decimal(&[0x03, 0xe8]), "1000");
+  assert_eq!(
+    uint_to_decimal(&[0xff; 32]),
+    "115792089237316195423570985008687907853269984665640564039457584007913129639935"
+  );
+}
+
+#[test]
+fn encode_address_pads_to_a_word() {
+  let encoded = encode_address("0x00000000000000000000000000000000000000A1");
+  assert_eq!(encoded.len(), 64);
+  assert!(encoded.ends_with("00a1"));
+  assert!(encoded.starts_with(&"0".repeat(24)));
+}
+
+#[test]
+fn rpc_circuit_opens_after_failures() {
+  use_app(async move {
+    let settings = Rpc {
+      chains: HashMap::from([(
+        "avalanche".to_owned(),
+        SETTINGS.rpc.chains["avalanche"].clone(),
+      )]),
+      circuit_failure_threshold: 1,
+      circuit_reset_secs: 60,
+    };
+    let client = RpcClient::new(reqwest::Client::new(), &settings);
+
+    // The avalanche provider is down in the tests.
+    let err = client
+      .request::<Value>("avalanche", "eth_blockNumber", json!([]))
+      .await
+      .unwrap_err();
+    assert!(matches!(
+      err,
+      Error::UpstreamUnavailable {
+        status: Some(502),
+        ..
+      }
+    ));
+
+    // The open circuit rejects requests without sending them.
+    let err = client
+      .request::<Value>("avalanche", "eth_blockNumber", json!([]))
+      .await
+      .unwrap_err();
+    assert!(matches!(
+      err,
+      Error::UpstreamUnavailable { status: None, .. }
+    ));
+  });
+}
@@ -0,0 +1,78 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::services::scheduler::{self, Scheduler};
+use crate::settings::{self, SETTINGS};
+use crate::state::AppState;
+use crate::tests::setup::use_app;
+
+fn job(name: &str) -> scheduler::Job {
+  scheduler::jobs()
+    .into_iter()
+    .find(|job| job.name == name)
+    .unwrap()
+}
+
+#[test]
+fn scheduler_lists_every_job() {
+  let settings = settings::Scheduler {
+    enabled: true,
+    jobs: HashMap::from([("usage_rollup".to_owned(), "0 0 * * * *".to_owned())]),
+  };
+  let scheduler = Scheduler::new(&settings);
+
+  let statuses = scheduler.statuses();
+  assert_eq!(statuses.len(), scheduler::jobs().len());
+
+  let rollup = statuses
+    .iter()
+    .find(|status| status.name == "usage_rollup")
+    .unwrap();
+  assert_eq!(rollup.schedule.as_deref(), Some("0 0 * * * *"));
+  assert!(rollup.last_run.is_none());
+
+  let sync = statuses
+    .iter()
+    .find(|status| status.name == "label_dataset_sync")
+    .unwrap();
+  assert!(sync.schedule.is_none());
+}
+
+#[test]
+fn scheduler_run_records_last_run() {
+  use_app(async move {
+    let state = AppState::new(Arc::new(SETTINGS.clone()));
+
+    state.scheduler.run(&state, job("usage_rollup")).await;
+
+    let statuses = state.scheduler.statuses();
+    let status = statuses
+      .iter()
+      .find(|status| status.name == "usage_rollup")
+      .unwrap();
+    assert!(!status.running);
+    let last_run = status.last_run.as_ref().unwrap();
+    assert!(last_run.succeeded);
+    assert!(last_run.error.is_none());
+  });
+}
+
+#[test]
+fn scheduler_run_records_failure() {
+  use_app(async move {
+    let mut settings = SETTINGS.clone();
+    settings.label_dataset.url = "http://localhost:8089/labels/missing".to_owned();
+    let state = AppState::new(Arc::new(settings));
+
+    state.scheduler.run(&state, job("label_dataset_sync")).await;
+
+    let statuses = state.scheduler.statuses();
+    let status = statuses
+      .iter()
+      .find(|status| status.name == "label_dataset_sync")
+      .unwrap();
+    let last_run = status.last_run.as_ref().unwrap();
+    assert!(!last_run.succeeded);
+    assert!(last_run.error.is_some());
+  });
+}
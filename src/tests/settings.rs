@@ -0,0 +1,277 @@
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use crate::settings::{
+  parse_port, profile, CacheBackend, IntelligenceBackend, LogFormat, Server, Settings,
+};
+
+#[test]
+fn parse_valid_port() {
+  assert_eq!(parse_port("3000").unwrap(), 3000);
+  assert_eq!(parse_port(" 8080 ").unwrap(), 8080);
+}
+
+#[test]
+fn parse_invalid_port() {
+  assert!(parse_port("").is_err());
+  assert!(parse_port("http").is_err());
+  assert!(parse_port("-1").is_err());
+  assert!(parse_port("65536").is_err());
+}
+
+#[test]
+fn server_address() {
+  let server = Server {
+    host: "127.0.0.1".to_owned(),
+    port: 3000,
+    shutdown_timeout_secs: 30,
+  };
+  let expected: SocketAddr = "127.0.0.1:3000".parse().unwrap();
+  assert_eq!(server.address().unwrap(), expected);
+
+  let server = Server {
+    host: "localhost:3000".to_owned(),
+    port: 3000,
+    shutdown_timeout_secs: 30,
+  };
+  assert!(server.address().is_err());
+}
+
+#[test]
+fn settings_read_arkham_api_key_from_environment() {
+  // No .env file is loaded by the tests, the value can only come from the
+  // process environment.
+  std::env::set_var("ARKHAM_API_KEY", "from-environment");
+  let settings = Settings::new().unwrap();
+  std::env::remove_var("ARKHAM_API_KEY");
+
+  assert_eq!(settings.arkham.api_key, "from-environment");
+}
+
+#[test]
+fn settings_profile_accepts_short_names() {
+  assert_eq!(profile("dev"), "development");
+  assert_eq!(profile("stage"), "staging");
+  assert_eq!(profile("prod"), "production");
+  assert_eq!(profile("staging"), "staging");
+  assert_eq!(profile("test"), "test");
+}
+
+#[test]
+fn settings_load_staging_profile() {
+  std::env::set_var("ARKHAM_API_KEY", "from-environment");
+  let settings = Settings::load("staging").unwrap();
+  std::env::remove_var("ARKHAM_API_KEY");
+
+  assert_eq!(settings.environment, "staging");
+  assert_eq!(settings.database.name, "rustapi-staging");
+  assert_eq!(settings.logger.level, "info");
+  assert_eq!(settings.logger.format, LogFormat::Json);
+  assert_eq!(settings.arkham.url, "http://localhost:8089");
+  assert_eq!(settings.arkham.cache_ttl_secs, 60);
+  // Not overridden by the profile:
+  assert_eq!(settings.arkham.batch_max_addresses, 50);
+}
+
+#[test]
+fn settings_validate_default_configuration() {
+  let settings = Settings::new().unwrap();
+  assert!(settings.validate().is_ok());
+}
+
+#[test]
+fn settings_validate_reports_every_invalid_value() {
+  let mut settings = Settings::new().unwrap();
+  settings.arkham.api_key = String::new();
+  settings.arkham.requests_per_sec = 0.0;
+  settings.pagination.max_limit = 0;
+
+  let err = settings.validate().unwrap_err().to_string();
+  assert!(err.contains("arkham.api_key"));
+  assert!(err.contains("arkham.requests_per_sec"));
+  assert!(err.contains("pagination.max_limit"));
+}
+
+#[test]
+fn settings_validate_telemetry() {
+  let mut settings = Settings::new().unwrap();
+  settings.telemetry.enabled = true;
+  settings.telemetry.otlp_endpoint = String::new();
+  settings.telemetry.sample_ratio = 1.5;
+
+  let err = settings.validate().unwrap_err().to_string();
+  assert!(err.contains("telemetry.otlp_endpoint"));
+  assert!(err.contains("telemetry.sample_ratio"));
+}
+
+#[test]
+fn settings_validate_database_pool() {
+  let mut settings = Settings::new().unwrap();
+  settings.database.max_pool_size = Some(0);
+  let err = settings.validate().unwrap_err().to_string();
+  assert!(err.contains("database.max_pool_size"));
+
+  settings.database.min_pool_size = Some(20);
+  settings.database.max_pool_size = Some(10);
+  let err = settings.validate().unwrap_err().to_string();
+  assert!(err.contains("database.min_pool_size"));
+
+  settings.database.max_pool_size = Some(20);
+  assert!(settings.validate().is_ok());
+}
+
+#[test]
+fn settings_validate_redis_cache() {
+  let mut settings = Settings::new().unwrap();
+  settings.cache.backend = CacheBackend::Redis;
+  settings.cache.redis_url = "localhost:6379".to_owned();
+
+  let err = settings.validate().unwrap_err().to_string();
+  assert!(err.contains("cache.redis_url"));
+
+  settings.cache.redis_url = "redis://localhost:6379".to_owned();
+  assert!(settings.validate().is_ok());
+}
+
+#[test]
+fn settings_validate_fixture_provider() {
+  let mut settings = Settings::new().unwrap();
+  settings.arkham.provider = IntelligenceBackend::Fixture;
+  settings.arkham.api_key = String::new();
+
+  let err = settings.validate().unwrap_err().to_string();
+  assert!(err.contains("arkham.fixtures_path"));
+  // The fixtures stand in for Arkham, no API key is needed.
+  assert!(!err.contains("arkham.api_key"));
+
+  settings.arkham.fixtures_path = "fixtures.json".to_owned();
+  assert!(settings.validate().is_ok());
+}
+
+#[test]
+fn settings_validate_scheduler() {
+  let mut settings = Settings::new().unwrap();
+  settings
+    .scheduler
+    .jobs
+    .insert("usage_rollup".to_owned(), "every hour".to_owned());
+  settings
+    .scheduler
+    .jobs
+    .insert("label_dataset_sync".to_owned(), "0 0 3 * * *".to_owned());
+
+  let err = settings.validate().unwrap_err().to_string();
+  assert!(err.contains("scheduler.jobs.usage_rollup"));
+  assert!(err.contains("label_dataset.url"));
+
+  settings
+    .scheduler
+    .jobs
+    .insert("usage_rollup".to_owned(), "0 0 * * * *".to_owned());
+  settings.label_dataset.url = "https://example.com/labels.ndjson".to_owned();
+  assert!(settings.validate().is_ok());
+}
+
+#[test]
+fn settings_validate_label_merge() {
+  let mut settings = Settings::new().unwrap();
+  settings.label_merge.priority =
+    vec!["arkham".to_owned(), "notes".to_owned(), "arkham".to_owned()];
+
+  let err = settings.validate().unwrap_err().to_string();
+  assert!(err.contains("label_merge.priority.notes is not a label source"));
+  assert!(err.contains("label_merge.priority.arkham is listed twice"));
+
+  settings.label_merge.priority = vec!["arkham".to_owned(), "user".to_owned()];
+  assert!(settings.validate().is_ok());
+}
+
+#[test]
+fn settings_validate_retention() {
+  let mut settings = Settings::new().unwrap();
+  settings.retention.alert_events_days = Some(0);
+
+  let err = settings.validate().unwrap_err().to_string();
+  assert!(err.contains("retention.alert_events_days must be at least 1"));
+
+  settings.retention.alert_events_days = None;
+  assert!(settings.validate().is_ok());
+}
+
+#[test]
+fn settings_timeouts_by_route_and_upstream() {
+  let settings = Settings::new().unwrap();
+  let timeouts = &settings.timeouts;
+
+  assert_eq!(
+    timeouts.request(Some("/v1/arkham/batch")),
+    Duration::from_millis(120000)
+  );
+  assert_eq!(
+    timeouts.request(Some("/v1/prices")),
+    Duration::from_millis(timeouts.request_ms)
+  );
+  assert_eq!(
+    timeouts.request(None),
+    Duration::from_millis(timeouts.request_ms)
+  );
+
+  assert_eq!(timeouts.upstream("arkham").total_ms, 1000);
+  assert_eq!(
+    timeouts.upstream("prices").total_ms,
+    timeouts.upstream.total_ms
+  );
+}
+
+#[test]
+fn settings_validate_timeouts() {
+  let mut settings = Settings::new().unwrap();
+  settings.timeouts.request_ms = 0;
+  let mut upstream = settings.timeouts.upstream.clone();
+  upstream.total_ms = upstream.connect_ms - 1;
+  settings
+    .timeouts
+    .upstreams
+    .insert("prices".to_owned(), upstream);
+  settings
+    .timeouts
+    .upstreams
+    .insert("coingecko".to_owned(), settings.timeouts.upstream.clone());
+
+  let err = settings.validate().unwrap_err().to_string();
+  assert!(err.contains("timeouts.request_ms"));
+  assert!(err.contains("timeouts prices"));
+  assert!(err.contains("timeouts.upstreams.coingecko"));
+}
+
+#[test]
+fn settings_validate_cors() {
+  let mut settings = Settings::new().unwrap();
+  settings.cors.allowed_origins = vec![
+    "https://app.example.com".to_owned(),
+    "*".to_owned(),
+    "https://example.com/dashboard".to_owned(),
+  ];
+  settings
+    .cors
+    .allowed_methods
+    .push("NOT A METHOD".to_owned());
+
+  let err = settings.validate().unwrap_err().to_string();
+  assert!(err.contains("cors.allowed_origins *"));
+  assert!(err.contains("cors.allowed_origins https://example.com/dashboard"));
+  assert!(err.contains("cors.allowed_methods NOT A METHOD"));
+}
+
+#[test]
+fn settings_body_limit_by_route() {
+  let settings = Settings::new().unwrap();
+  let body_limit = &settings.body_limit;
+
+  assert_eq!(body_limit.limit(Some("/v1/labels/import")), 4096);
+  assert_eq!(
+    body_limit.limit(Some("/v1/labels")),
+    body_limit.default_bytes
+  );
+  assert_eq!(body_limit.limit(None), body_limit.default_bytes);
+}
@@ -1,13 +1,46 @@
 use async_once::AsyncOnce;
-use bson::doc;
+use bson::{doc, Document};
 use lazy_static::lazy_static;
 use std::net::SocketAddr;
+use std::sync::Arc;
 use tokio::runtime::Runtime;
 
-use crate::app::create_app;
+use crate::app::{create_app, create_router};
+use crate::database::CONNECTION;
+use crate::models::account_token::AccountToken;
+use crate::models::address_label::AddressLabel;
+use crate::models::address_query::AddressQuery;
+use crate::models::address_snapshot::AddressSnapshot;
+use crate::models::alert_event::AlertEvent;
+use crate::models::alert_rule::AlertRule;
+use crate::models::api_key::ApiKey;
+use crate::models::audit_log::AuditLog;
 use crate::models::cat::Cat;
+use crate::models::feature_flag::FeatureFlag;
+use crate::models::job::Job;
+use crate::models::label_change::LabelChange;
+use crate::models::label_dataset::LabelDataset;
+use crate::models::membership::Membership;
+use crate::models::notification_channel::NotificationChannel;
+use crate::models::organization::Organization;
+use crate::models::organization_invite::OrganizationInvite;
+use crate::models::price_sample::PriceSample;
+use crate::models::refresh_token::RefreshToken;
+use crate::models::session::Session;
+use crate::models::share::Share;
+use crate::models::siwe_nonce::SiweNonce;
+use crate::models::totp_factor::TotpFactor;
+use crate::models::usage::Usage;
 use crate::models::user::User;
-use crate::settings::SETTINGS;
+use crate::models::user_address_note::UserAddressNote;
+use crate::models::watched_address::WatchedAddress;
+use crate::models::watchlist::Watchlist;
+use crate::models::webhook_dead_letter::WebhookDeadLetter;
+use crate::models::webhook_delivery::WebhookDelivery;
+use crate::models::webhook_endpoint::WebhookEndpoint;
+use crate::settings::{IntelligenceBackend, SETTINGS};
+use crate::state::AppState;
+use crate::tests::mock_arkham;
 use crate::utils::models::ModelExt;
 
 lazy_static! {
@@ -18,29 +51,113 @@ lazy_static! {
   pub static ref API: AsyncOnce<()> = AsyncOnce::new(async {
     std::env::set_var("RUN_MODE", "test");
 
+    tokio::spawn(mock_arkham::serve());
+
     let app = create_app().await;
-    let port = SETTINGS.server.port;
-    let address = SocketAddr::from(([0, 0, 0, 0], port));
+    let address = SETTINGS.server.address().unwrap();
 
     tokio::spawn(async move {
       axum::Server::bind(&address)
-        .serve(app.into_make_service())
+        .serve(app.into_make_service_with_connect_info::<SocketAddr>())
         .await
         .expect("Failed to start server");
     });
   });
 }
 
+// Port of the API served with the fixture intelligence provider.
+pub const FIXTURE_API_PORT: u16 = 8090;
+
+// Intelligence data served by the fixture API instead of the mock Arkham API.
+const FIXTURES_PATH: &str = "src/tests/fixtures/intelligence.json";
+
+lazy_static! {
+  // Same routes and database as `API`, with the address intelligence read
+  // from `FIXTURES_PATH`.
+  pub static ref FIXTURE_API: AsyncOnce<()> = AsyncOnce::new(async {
+    API.get().await;
+
+    let mut settings = SETTINGS.clone();
+    settings.arkham.provider = IntelligenceBackend::Fixture;
+    settings.arkham.fixtures_path = FIXTURES_PATH.to_owned();
+    let app = create_router(AppState::new(Arc::new(settings)));
+    let address = SocketAddr::from(([127, 0, 0, 1], FIXTURE_API_PORT));
+
+    tokio::spawn(async move {
+      axum::Server::bind(&address)
+        .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+        .await
+        .expect("Failed to start fixture server");
+    });
+  });
+}
+
 pub fn use_app<F>(test: F)
 where
   F: std::future::Future,
 {
   RUNTIME.block_on(async move {
     API.get().await;
+    clear_database().await;
+
+    test.await;
+  })
+}
 
-    Cat::delete_many(doc! {}).await.unwrap();
-    User::delete_many(doc! {}).await.unwrap();
+/// Like `use_app`, for tests against the API on `FIXTURE_API_PORT`, whose
+/// address intelligence comes from fixtures instead of the mock Arkham API.
+pub fn use_fixture_app<F>(test: F)
+where
+  F: std::future::Future,
+{
+  RUNTIME.block_on(async move {
+    FIXTURE_API.get().await;
+    clear_database().await;
 
     test.await;
   })
 }
+
+async fn clear_database() {
+  Cat::delete_many(doc! {}).await.unwrap();
+  User::delete_many(doc! {}).await.unwrap();
+  AddressLabel::delete_many(doc! {}).await.unwrap();
+  ApiKey::delete_many(doc! {}).await.unwrap();
+  Watchlist::delete_many(doc! {}).await.unwrap();
+  WatchedAddress::delete_many(doc! {}).await.unwrap();
+  AddressSnapshot::delete_many(doc! {}).await.unwrap();
+  AlertRule::delete_many(doc! {}).await.unwrap();
+  AlertEvent::delete_many(doc! {}).await.unwrap();
+  WebhookEndpoint::delete_many(doc! {}).await.unwrap();
+  WebhookDelivery::delete_many(doc! {}).await.unwrap();
+  WebhookDeadLetter::delete_many(doc! {}).await.unwrap();
+  NotificationChannel::delete_many(doc! {}).await.unwrap();
+  SiweNonce::delete_many(doc! {}).await.unwrap();
+  RefreshToken::delete_many(doc! {}).await.unwrap();
+  Session::delete_many(doc! {}).await.unwrap();
+  TotpFactor::delete_many(doc! {}).await.unwrap();
+  AccountToken::delete_many(doc! {}).await.unwrap();
+  Organization::delete_many(doc! {}).await.unwrap();
+  Membership::delete_many(doc! {}).await.unwrap();
+  OrganizationInvite::delete_many(doc! {}).await.unwrap();
+  AuditLog::delete_many(doc! {}).await.unwrap();
+  Usage::delete_many(doc! {}).await.unwrap();
+  Job::delete_many(doc! {}).await.unwrap();
+  Share::delete_many(doc! {}).await.unwrap();
+  UserAddressNote::delete_many(doc! {}).await.unwrap();
+  FeatureFlag::delete_many(doc! {}).await.unwrap();
+  LabelDataset::delete_many(doc! {}).await.unwrap();
+  LabelChange::delete_many(doc! {}).await.unwrap();
+  PriceSample::delete_many(doc! {}).await.unwrap();
+  AddressQuery::delete_many(doc! {}).await.unwrap();
+
+  // Attached files, see `services::gridfs`.
+  let database = CONNECTION.get().await;
+  for collection in ["attachments.files", "attachments.chunks"] {
+    database
+      .collection::<Document>(collection)
+      .delete_many(doc! {}, None)
+      .await
+      .unwrap();
+  }
+}
@@ -0,0 +1,60 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::runtime::Runtime;
+use tokio::time::sleep;
+
+use crate::utils::shutdown::Shutdown;
+
+#[test]
+fn shutdown_drain_waits_for_work_in_progress() {
+  let runtime = Runtime::new().unwrap();
+  runtime.block_on(async {
+    let shutdown = Shutdown::new();
+    let finished = Arc::new(AtomicBool::new(false));
+
+    let mut signal = shutdown.subscribe();
+    let worker_finished = finished.clone();
+    shutdown.track(tokio::spawn(async move {
+      signal.requested().await;
+      // Work still in progress when the shutdown is requested.
+      sleep(Duration::from_millis(20)).await;
+      worker_finished.store(true, Ordering::SeqCst);
+    }));
+
+    shutdown.drain(Duration::from_secs(5)).await;
+    assert!(finished.load(Ordering::SeqCst));
+  });
+}
+
+#[test]
+fn shutdown_drain_aborts_workers_after_timeout() {
+  let runtime = Runtime::new().unwrap();
+  runtime.block_on(async {
+    let shutdown = Shutdown::new();
+    let finished = Arc::new(AtomicBool::new(false));
+
+    let worker_finished = finished.clone();
+    let worker = tokio::spawn(async move {
+      sleep(Duration::from_secs(60)).await;
+      worker_finished.store(true, Ordering::SeqCst);
+    });
+    shutdown.track(worker);
+
+    shutdown.drain(Duration::from_millis(20)).await;
+    assert!(!finished.load(Ordering::SeqCst));
+  });
+}
+
+#[test]
+fn shutdown_signal_resolves_when_already_requested() {
+  let runtime = Runtime::new().unwrap();
+  runtime.block_on(async {
+    let shutdown = Shutdown::new();
+    shutdown.request();
+
+    let mut signal = shutdown.subscribe();
+    let requested = tokio::time::timeout(Duration::from_secs(1), signal.requested()).await;
+    assert!(requested.is_ok());
+  });
+}
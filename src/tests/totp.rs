@@ -0,0 +1,53 @@
+use crate::utils::totp;
+
+// RFC 6238 test secret, base32 encoded.
+const SECRET: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+
+#[test]
+fn totp_code_matches_rfc_6238_vectors() {
+  let secret = b"12345678901234567890";
+
+  assert_eq!(totp::code(secret, totp::step(59)), "287082");
+  assert_eq!(totp::code(secret, totp::step(1111111109)), "081804");
+  assert_eq!(totp::code(secret, totp::step(1234567890)), "005924");
+  assert_eq!(totp::code(secret, totp::step(2000000000)), "279037");
+}
+
+#[test]
+fn totp_verify_accepts_adjacent_steps() {
+  let timestamp = 1111111109;
+  let step = totp::step(timestamp);
+
+  assert_eq!(totp::verify(SECRET, "081804", timestamp), Some(step));
+  assert_eq!(
+    totp::verify(SECRET, "081804", timestamp + 30),
+    Some(step),
+    "Codes of the previous step should be accepted"
+  );
+  assert_eq!(totp::verify(SECRET, "081804", timestamp + 60), None);
+  assert_eq!(totp::verify(SECRET, "000000", timestamp), None);
+  assert_eq!(totp::verify(SECRET, "81804", timestamp), None);
+  assert_eq!(totp::verify("not base32!", "081804", timestamp), None);
+}
+
+#[test]
+fn totp_otpauth_uri() {
+  let uri = totp::otpauth_uri("Degen", "nico@test.com", SECRET);
+
+  assert_eq!(
+    uri,
+    "otpauth://totp/Degen:nico@test.com?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&issuer=Degen&algorithm=SHA1&digits=6&period=30"
+  );
+}
+
+#[test]
+fn totp_generated_secrets_are_base32() {
+  let secret = totp::generate_secret();
+
+  // 20 bytes, without padding.
+  assert_eq!(secret.len(), 32);
+  assert!(secret
+    .chars()
+    .all(|c| c.is_ascii_uppercase() || ('2'..='7').contains(&c)));
+  assert_ne!(secret, totp::generate_secret());
+}
@@ -1,6 +1,8 @@
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
+
 use crate::errors::Error;
 use crate::models::user::hash_password;
-use crate::models::user::User;
+use crate::models::user::{Role, User};
 use crate::settings::SETTINGS;
 use crate::utils::models::ModelExt;
 use crate::utils::token;
@@ -16,9 +18,47 @@ pub async fn create_user<T: AsRef<str>>(email: T) -> Result<User, Error> {
   Ok(user)
 }
 
+pub async fn create_admin_user<T: AsRef<str>>(email: T) -> Result<User, Error> {
+  let name = "Admin";
+  let password = "Password1";
+
+  let password_hash = hash_password(password).await?;
+  let mut user = User::new(name, email.as_ref(), password_hash);
+  user.role = Role::Admin;
+  let user = User::create(user).await?;
+
+  Ok(user)
+}
+
+pub async fn create_readonly_user<T: AsRef<str>>(email: T) -> Result<User, Error> {
+  let name = "Reader";
+  let password = "Password1";
+
+  let password_hash = hash_password(password).await?;
+  let mut user = User::new(name, email.as_ref(), password_hash);
+  user.role = Role::ReadOnly;
+  let user = User::create(user).await?;
+
+  Ok(user)
+}
+
 pub async fn create_user_token(user: User) -> Result<String, Error> {
   let secret = SETTINGS.auth.secret.as_str();
-  let token = token::create(user, secret).unwrap();
+  let token = token::create(user, None, secret).unwrap();
 
   Ok(token)
 }
+
+/// HTTP client sending the access token of `user` with every request.
+pub async fn create_authenticated_client(user: User) -> Result<reqwest::Client, Error> {
+  let token = create_user_token(user).await?;
+
+  let mut headers = HeaderMap::new();
+  let authorization = HeaderValue::from_str(&format!("Bearer {}", token)).unwrap();
+  headers.insert(AUTHORIZATION, authorization);
+  let client = reqwest::Client::builder()
+    .default_headers(headers)
+    .build()?;
+
+  Ok(client)
+}
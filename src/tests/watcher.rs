@@ -0,0 +1,83 @@
+use bson::doc;
+use bson::oid::ObjectId;
+use serde_json::json;
+use std::sync::Arc;
+
+use crate::models::address_snapshot::AddressSnapshot;
+use crate::models::watched_address::WatchedAddress;
+use crate::routes::arkham::parse_arkham_response;
+use crate::services::watcher;
+use crate::settings::SETTINGS;
+use crate::state::AppState;
+use crate::tests::mock_arkham::FAILING_ADDRESS;
+use crate::tests::setup::use_app;
+use crate::utils::models::ModelExt;
+
+const ADDRESS: &str = "0x00000000000000000000000000000000000000a1";
+
+async fn watch(address: &str, chain: &str) {
+  let watched = WatchedAddress::new(
+    ObjectId::new(),
+    ObjectId::new(),
+    address.to_owned(),
+    chain.to_owned(),
+    None,
+  );
+  WatchedAddress::create(watched).await.unwrap();
+}
+
+#[test]
+fn poll_records_a_snapshot_once_per_address() {
+  use_app(async move {
+    watch(ADDRESS, "ethereum").await;
+    watch(ADDRESS, "arbitrum_one").await;
+    watch(FAILING_ADDRESS, "ethereum").await;
+
+    let state = AppState::new(Arc::new(SETTINGS.clone()));
+    let mut changes = state.address_changes.subscribe();
+
+    watcher::poll(&state).await.unwrap();
+    let count = AddressSnapshot::count(doc! { "address": ADDRESS })
+      .await
+      .unwrap();
+    assert_eq!(count, 1, "A baseline snapshot should be recorded");
+    let count = AddressSnapshot::count(doc! { "address": FAILING_ADDRESS })
+      .await
+      .unwrap();
+    assert_eq!(count, 0, "Failed polls should be skipped");
+
+    // Polling unchanged data:
+    watcher::poll(&state).await.unwrap();
+    let count = AddressSnapshot::count(doc! { "address": ADDRESS })
+      .await
+      .unwrap();
+    assert_eq!(count, 1, "Unchanged data should not be recorded");
+    assert!(changes.try_recv().is_err(), "No change should be emitted");
+  });
+}
+
+#[test]
+fn poll_emits_changed_addresses() {
+  use_app(async move {
+    watch(ADDRESS, "ethereum").await;
+    let previous = parse_arkham_response(json!({})).unwrap();
+    AddressSnapshot::create(AddressSnapshot::new(ADDRESS.to_owned(), previous.clone()))
+      .await
+      .unwrap();
+
+    let state = AppState::new(Arc::new(SETTINGS.clone()));
+    let mut changes = state.address_changes.subscribe();
+
+    watcher::poll(&state).await.unwrap();
+
+    let count = AddressSnapshot::count(doc! { "address": ADDRESS })
+      .await
+      .unwrap();
+    assert_eq!(count, 2);
+
+    let change = changes.try_recv().unwrap();
+    assert_eq!(change.address, ADDRESS);
+    assert_eq!(change.previous, previous);
+    assert_ne!(change.current, previous);
+  });
+}
@@ -0,0 +1,239 @@
+use bson::doc;
+use bson::oid::ObjectId;
+use std::time::Duration;
+
+use crate::models::alert_event::AlertEvent;
+use crate::models::alert_rule::AlertCondition;
+use crate::models::webhook_dead_letter::WebhookDeadLetter;
+use crate::models::webhook_delivery::{DeliveryStatus, WebhookDelivery};
+use crate::models::webhook_endpoint::WebhookEndpoint;
+use crate::services::webhooks::{
+  deliver_due, enqueue, http_client, sign, signature_header, verify, SignatureError,
+};
+use crate::settings::SETTINGS;
+use crate::tests::setup::use_app;
+use crate::utils::models::ModelExt;
+
+async fn create_event(user: ObjectId) -> AlertEvent {
+  let event = AlertEvent::new(
+    user,
+    ObjectId::new(),
+    AlertCondition::EntityAttached,
+    "0x00000000000000000000000000000000000000a1".to_owned(),
+    "ethereum".to_owned(),
+    "Arkham entity Binance attached".to_owned(),
+  );
+  AlertEvent::create(event).await.unwrap()
+}
+
+async fn create_endpoint(user: ObjectId, path: &str) -> WebhookEndpoint {
+  create_endpoint_for_events(user, path, Vec::new()).await
+}
+
+async fn create_endpoint_for_events(
+  user: ObjectId,
+  path: &str,
+  events: Vec<AlertCondition>,
+) -> WebhookEndpoint {
+  let url = format!("http://localhost:8089/webhooks/{}", path);
+  WebhookEndpoint::create(WebhookEndpoint::new(user, url, events))
+    .await
+    .unwrap()
+}
+
+#[test]
+fn enqueue_queues_a_delivery_by_endpoint_of_the_user() {
+  use_app(async move {
+    let user = ObjectId::new();
+    create_endpoint(user, "ok").await;
+    create_endpoint(user, "failing").await;
+    create_endpoint(ObjectId::new(), "ok").await;
+    let event = create_event(user).await;
+
+    let count = enqueue(&[event]).await.unwrap();
+    assert_eq!(count, 2);
+  });
+}
+
+#[test]
+fn enqueue_skips_endpoints_filtering_out_the_event() {
+  use_app(async move {
+    let user = ObjectId::new();
+    create_endpoint_for_events(user, "ok", vec![AlertCondition::EntityAttached]).await;
+    create_endpoint_for_events(user, "ok", vec![AlertCondition::FlaggedAsContract]).await;
+    let event = create_event(user).await;
+
+    let count = enqueue(&[event]).await.unwrap();
+    assert_eq!(count, 1);
+  });
+}
+
+#[test]
+fn sign_payloads() {
+  let signature = sign("secret", "1700000000", br#"{"delivery":"1"}"#);
+  assert_eq!(signature.len(), 64);
+  assert_eq!(
+    signature,
+    sign("secret", "1700000000", br#"{"delivery":"1"}"#)
+  );
+  assert_ne!(
+    signature,
+    sign("other", "1700000000", br#"{"delivery":"1"}"#)
+  );
+  assert_ne!(
+    signature,
+    sign("secret", "1700000001", br#"{"delivery":"1"}"#)
+  );
+}
+
+const BODY: &[u8] = br#"{"delivery":"1"}"#;
+const TOLERANCE: Duration = Duration::from_secs(300);
+
+#[test]
+fn signature_header_carries_the_timestamp() {
+  let header = signature_header("secret", 1700000000, BODY);
+  assert_eq!(
+    header,
+    format!("t=1700000000,v1={}", sign("secret", "1700000000", BODY))
+  );
+}
+
+#[test]
+fn verify_accepts_signed_payloads() {
+  let header = signature_header("secret", 1700000000, BODY);
+  assert_eq!(
+    verify("secret", &header, BODY, 1700000060, TOLERANCE),
+    Ok(())
+  );
+}
+
+#[test]
+fn verify_rejects_tampered_payloads() {
+  let header = signature_header("secret", 1700000000, BODY);
+  assert_eq!(
+    verify(
+      "secret",
+      &header,
+      br#"{"delivery":"2"}"#,
+      1700000000,
+      TOLERANCE
+    ),
+    Err(SignatureError::Mismatch)
+  );
+  assert_eq!(
+    verify("other", &header, BODY, 1700000000, TOLERANCE),
+    Err(SignatureError::Mismatch)
+  );
+
+  // The timestamp is signed too:
+  let header = header.replace("t=1700000000", "t=1700000100");
+  assert_eq!(
+    verify("secret", &header, BODY, 1700000100, TOLERANCE),
+    Err(SignatureError::Mismatch)
+  );
+}
+
+#[test]
+fn verify_rejects_replayed_payloads() {
+  let header = signature_header("secret", 1700000000, BODY);
+  assert_eq!(
+    verify("secret", &header, BODY, 1700000301, TOLERANCE),
+    Err(SignatureError::Expired)
+  );
+}
+
+#[test]
+fn verify_rejects_malformed_headers() {
+  let without_timestamp = format!("v1={}", sign("secret", "1700000000", BODY));
+  for header in [
+    "",
+    "t=1700000000",
+    without_timestamp.as_str(),
+    "t=now,v1=00",
+  ] {
+    assert_eq!(
+      verify("secret", header, BODY, 1700000000, TOLERANCE),
+      Err(SignatureError::Malformed),
+      "{:?} should be malformed",
+      header
+    );
+  }
+}
+
+#[test]
+fn deliver_due_posts_deliveries() {
+  use_app(async move {
+    let user = ObjectId::new();
+    let endpoint = create_endpoint(user, "ok").await;
+    let event = create_event(user).await;
+    let delivery = WebhookDelivery::new(user, endpoint.id.unwrap(), event.id.unwrap());
+    let delivery = WebhookDelivery::create(delivery).await.unwrap();
+
+    let settings = &SETTINGS.webhooks;
+    let count = deliver_due(&http_client(settings), settings).await.unwrap();
+    assert_eq!(count, 1);
+
+    let delivery = WebhookDelivery::find_by_id(&delivery.id.unwrap())
+      .await
+      .unwrap()
+      .unwrap();
+    assert_eq!(delivery.status, DeliveryStatus::Delivered);
+    assert_eq!(delivery.attempts, 1);
+    assert!(delivery.delivered_at.is_some());
+
+    // Delivered webhooks are not sent again:
+    let count = deliver_due(&http_client(settings), settings).await.unwrap();
+    assert_eq!(count, 0);
+  });
+}
+
+#[test]
+fn deliver_due_moves_failing_deliveries_to_dead_letters() {
+  use_app(async move {
+    let user = ObjectId::new();
+    let endpoint = create_endpoint(user, "failing").await;
+    let event = create_event(user).await;
+    let delivery = WebhookDelivery::new(user, endpoint.id.unwrap(), event.id.unwrap());
+    let delivery = WebhookDelivery::create(delivery).await.unwrap();
+    let delivery_id = delivery.id.unwrap();
+
+    // The test configuration allows two attempts without delay.
+    let settings = &SETTINGS.webhooks;
+    deliver_due(&http_client(settings), settings).await.unwrap();
+    let delivery = WebhookDelivery::find_by_id(&delivery_id)
+      .await
+      .unwrap()
+      .unwrap();
+    assert_eq!(delivery.status, DeliveryStatus::Pending);
+    assert_eq!(delivery.attempts, 1);
+    assert_eq!(
+      delivery.last_error.as_deref(),
+      Some("Received a 500 Internal Server Error status code")
+    );
+
+    deliver_due(&http_client(settings), settings).await.unwrap();
+    let delivery = WebhookDelivery::find_by_id(&delivery_id).await.unwrap();
+    assert!(delivery.is_none(), "Delivery should be dead lettered");
+
+    let dead_letters = WebhookDeadLetter::find(doc! {}, None).await.unwrap();
+    let dead_letter = dead_letters.first().unwrap();
+    assert_eq!(dead_letter.event, event.id.unwrap());
+    assert_eq!(dead_letter.attempts, 2);
+  });
+}
+
+#[test]
+fn deliver_due_dead_letters_deliveries_of_removed_endpoints() {
+  use_app(async move {
+    let user = ObjectId::new();
+    let event = create_event(user).await;
+    let delivery = WebhookDelivery::new(user, ObjectId::new(), event.id.unwrap());
+    WebhookDelivery::create(delivery).await.unwrap();
+
+    let settings = &SETTINGS.webhooks;
+    deliver_due(&http_client(settings), settings).await.unwrap();
+
+    let count = WebhookDeadLetter::count(doc! {}).await.unwrap();
+    assert_eq!(count, 1);
+  });
+}
@@ -0,0 +1,397 @@
+use axum::{
+  async_trait,
+  extract::{FromRequestParts, Path},
+  http::request::Parts,
+};
+use serde::Deserialize;
+use sha2::Sha256;
+use sha3::{Digest, Keccak256};
+use std::collections::HashMap;
+use std::iter;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::errors::Error;
+use crate::utils::query::Query;
+
+const BASE58_ALPHABET: &[u8] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+const BECH32_CHARSET: &[u8] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+// Checksum constants of bech32 (BIP-173) and bech32m (BIP-350).
+const BECH32_CONST: u32 = 1;
+const BECH32M_CONST: u32 = 0x2bc8_30a3;
+
+// Base58check versions of mainnet P2PKH (`1...`) and P2SH (`3...`)
+// addresses.
+const P2PKH_VERSION: u8 = 0x00;
+const P2SH_VERSION: u8 = 0x05;
+
+// Versions of mainnet extended public keys: `xpub`, `ypub` and `zpub`.
+const XPUB_VERSIONS: [[u8; 4]; 3] = [
+  [0x04, 0x88, 0xb2, 0x1e],
+  [0x04, 0x9d, 0x7c, 0xb2],
+  [0x04, 0xb2, 0x47, 0x46],
+];
+
+/// Kind of chain an address belongs to, which decides its format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum AddressChain {
+  Evm,
+  Solana,
+  Bitcoin,
+}
+
+impl AddressChain {
+  /// Chain of an address, told apart by format: EVM addresses start with
+  /// `0x`, Bitcoin addresses have a checksum and Solana addresses are
+  /// base58 public keys.
+  pub fn detect(address: &str) -> Option<Self> {
+    let address = address.trim();
+    if address.starts_with("0x") || address.starts_with("0X") {
+      Some(Self::Evm)
+    } else if is_bitcoin_address(address) {
+      Some(Self::Bitcoin)
+    } else if is_solana_address(address) {
+      Some(Self::Solana)
+    } else {
+      None
+    }
+  }
+}
+
+/// Normalizes an EVM address (`0x` followed by 40 hex characters) to
+/// lowercase. Surrounding whitespace is ignored, anything else is rejected
+/// before it reaches an upstream API.
+pub fn normalize_evm_address<S: AsRef<str>>(address: S) -> Result<String, Error> {
+  let address = address.as_ref().trim();
+  let hex = address
+    .strip_prefix("0x")
+    .or_else(|| address.strip_prefix("0X"));
+
+  match hex {
+    Some(hex) if hex.len() == 40 && hex.chars().all(|c| c.is_ascii_hexdigit()) => {
+      Ok(format!("0x{}", hex.to_ascii_lowercase()))
+    }
+    _ => Err(Error::InvalidAddress(address.to_string())),
+  }
+}
+
+/// Validates a Solana address, the base58 encoding of a 32 bytes public key.
+/// Base58 is case-sensitive, so unlike EVM addresses the address is kept
+/// as is.
+pub fn normalize_solana_address<S: AsRef<str>>(address: S) -> Result<String, Error> {
+  let address = address.as_ref().trim();
+
+  if is_solana_address(address) {
+    Ok(address.to_owned())
+  } else {
+    Err(Error::InvalidAddress(address.to_string()))
+  }
+}
+
+/// Validates a mainnet Bitcoin address: legacy P2PKH and P2SH addresses in
+/// base58check, or segwit addresses in bech32 and bech32m. Segwit addresses
+/// are lowercased, legacy ones are case-sensitive and kept as is.
+pub fn normalize_bitcoin_address<S: AsRef<str>>(address: S) -> Result<String, Error> {
+  let address = address.as_ref().trim();
+
+  if is_segwit_address(address) {
+    Ok(address.to_ascii_lowercase())
+  } else if is_bitcoin_address(address) {
+    Ok(address.to_owned())
+  } else {
+    Err(Error::InvalidAddress(address.to_string()))
+  }
+}
+
+/// Normalizes an address of the given chain, or of the chain detected from
+/// its format when none is given.
+pub fn normalize_address<S: AsRef<str>>(
+  address: S,
+  chain: Option<AddressChain>,
+) -> Result<String, Error> {
+  let address = address.as_ref();
+
+  match chain.or_else(|| AddressChain::detect(address)) {
+    Some(AddressChain::Evm) => normalize_evm_address(address),
+    Some(AddressChain::Solana) => normalize_solana_address(address),
+    Some(AddressChain::Bitcoin) => normalize_bitcoin_address(address),
+    None => Err(Error::InvalidAddress(address.trim().to_string())),
+  }
+}
+
+/// Like `normalize_address`, also accepting Bitcoin extended public keys
+/// (`xpub`, `ypub` or `zpub`), which stand for every address of a wallet.
+pub fn normalize_wallet_address<S: AsRef<str>>(
+  address: S,
+  chain: Option<AddressChain>,
+) -> Result<String, Error> {
+  let address = address.as_ref().trim();
+
+  match chain {
+    None | Some(AddressChain::Bitcoin) if is_bitcoin_xpub(address) => Ok(address.to_owned()),
+    _ => normalize_address(address, chain),
+  }
+}
+
+pub fn is_solana_address(address: &str) -> bool {
+  (32..=44).contains(&address.len())
+    && decode_base58(address).map_or(false, |bytes| bytes.len() == 32)
+}
+
+pub fn is_bitcoin_address(address: &str) -> bool {
+  if is_segwit_address(address) {
+    return true;
+  }
+
+  (26..=35).contains(&address.len())
+    && decode_base58_check(address).map_or(false, |payload| {
+      payload.len() == 21 && [P2PKH_VERSION, P2SH_VERSION].contains(&payload[0])
+    })
+}
+
+pub fn is_bitcoin_xpub(address: &str) -> bool {
+  address.len() == 111
+    && decode_base58_check(address).map_or(false, |payload| {
+      payload.len() == 78 && XPUB_VERSIONS.iter().any(|version| payload[..4] == *version)
+    })
+}
+
+/// Whether an address is a mainnet segwit address, with a witness program
+/// of version 0 in bech32 or of a later version in bech32m.
+fn is_segwit_address(address: &str) -> bool {
+  let is_mixed_case = address.chars().any(|c| c.is_ascii_lowercase())
+    && address.chars().any(|c| c.is_ascii_uppercase());
+  if address.len() > 90 || is_mixed_case {
+    return false;
+  }
+
+  let address = address.to_ascii_lowercase();
+  let (hrp, data) = match address.rsplit_once('1') {
+    Some((hrp, data)) if hrp == "bc" && data.len() > 6 => (hrp, data),
+    _ => return false,
+  };
+  let values = data
+    .bytes()
+    .map(|c| {
+      BECH32_CHARSET
+        .iter()
+        .position(|&digit| digit == c)
+        .map(|value| value as u8)
+    })
+    .collect::<Option<Vec<u8>>>();
+  let values = match values {
+    Some(values) => values,
+    None => return false,
+  };
+
+  let hrp_values = hrp
+    .bytes()
+    .map(|c| c >> 5)
+    .chain(iter::once(0))
+    .chain(hrp.bytes().map(|c| c & 31));
+  let checksum = bech32_polymod(hrp_values.chain(values.iter().copied()));
+
+  let (version, program) = values[..values.len() - 6].split_first().unwrap();
+  let expected = if *version == 0 {
+    BECH32_CONST
+  } else {
+    BECH32M_CONST
+  };
+  if checksum != expected || *version > 16 {
+    return false;
+  }
+
+  // The program is regrouped from 5 to 8 bits, the leftover bits are zero
+  // padding.
+  let bits = program.len() * 5;
+  let padding = bits % 8;
+  let length = bits / 8;
+  let is_padded = padding < 5
+    && program
+      .last()
+      .map_or(true, |last| last & ((1 << padding) - 1) == 0);
+
+  is_padded && (2..=40).contains(&length) && (*version != 0 || length == 20 || length == 32)
+}
+
+fn bech32_polymod(values: impl Iterator<Item = u8>) -> u32 {
+  const GENERATOR: [u32; 5] = [
+    0x3b6a_57b2,
+    0x2650_8e6d,
+    0x1ea1_19fa,
+    0x3d42_33dd,
+    0x2a14_62b3,
+  ];
+
+  values.fold(1, |checksum, value| {
+    let top = checksum >> 25;
+    let checksum = ((checksum & 0x1ff_ffff) << 5) ^ value as u32;
+    GENERATOR
+      .iter()
+      .enumerate()
+      .filter(|(i, _)| (top >> i) & 1 == 1)
+      .fold(checksum, |checksum, (_, generator)| checksum ^ generator)
+  })
+}
+
+/// Decodes a base58check string, `None` unless its last 4 bytes are the
+/// double SHA-256 checksum of the payload before them.
+fn decode_base58_check(input: &str) -> Option<Vec<u8>> {
+  let bytes = decode_base58(input)?;
+  if bytes.len() < 4 {
+    return None;
+  }
+
+  let (payload, checksum) = bytes.split_at(bytes.len() - 4);
+  let hash = Sha256::digest(Sha256::digest(payload));
+
+  (hash[..4] == *checksum).then(|| payload.to_vec())
+}
+
+/// Decodes a base58 string, `None` when it has characters outside of the
+/// alphabet. Each leading `1` stands for a leading zero byte.
+fn decode_base58(input: &str) -> Option<Vec<u8>> {
+  // Little-endian digits of the decoded number, in base 256.
+  let mut bytes: Vec<u8> = Vec::new();
+
+  for c in input.bytes() {
+    let mut carry = BASE58_ALPHABET.iter().position(|&digit| digit == c)? as u32;
+    for byte in bytes.iter_mut() {
+      carry += *byte as u32 * 58;
+      *byte = carry as u8;
+      carry >>= 8;
+    }
+    while carry > 0 {
+      bytes.push(carry as u8);
+      carry >>= 8;
+    }
+  }
+
+  let zeros = input.bytes().take_while(|&c| c == b'1').count();
+  bytes.extend(iter::repeat(0).take(zeros));
+  bytes.reverse();
+
+  Some(bytes)
+}
+
+/// Mixed-case checksum encoding of a normalized address, as defined by
+/// EIP-55. Addresses are stored lowercased and returned in this form.
+/// Non-EVM addresses have no checksum encoding and are returned as is.
+pub fn to_checksum_address(address: &str) -> String {
+  let hex = match address.strip_prefix("0x") {
+    Some(hex) => hex,
+    None => return address.to_owned(),
+  };
+  let hash = hex::encode(Keccak256::digest(hex.as_bytes()));
+
+  let checksummed = hex
+    .chars()
+    .zip(hash.chars())
+    .map(|(c, nibble)| match nibble.to_digit(16) {
+      Some(nibble) if nibble >= 8 => c.to_ascii_uppercase(),
+      _ => c,
+    })
+    .collect::<String>();
+
+  format!("0x{}", checksummed)
+}
+
+/// EVM address taken from the `address` path parameter, normalized with
+/// `normalize_evm_address`. Malformed addresses are rejected with a 400
+/// before the handler runs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EvmAddress(pub String);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for EvmAddress
+where
+  S: Send + Sync,
+{
+  type Rejection = Error;
+
+  async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+    let address = address_param(parts, state).await?;
+    normalize_evm_address(address).map(Self)
+  }
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ChainQuery {
+  /// Chain of the address, detected from its format by default.
+  pub chain: Option<AddressChain>,
+}
+
+/// EVM, Solana or Bitcoin address taken from the `address` path parameter.
+/// The chain is detected from the address, unless set with the `chain` query
+/// parameter, e.g. `?chain=solana`. See `normalize_address`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainAddress {
+  pub address: String,
+  pub chain: AddressChain,
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for ChainAddress
+where
+  S: Send + Sync,
+{
+  type Rejection = Error;
+
+  async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+    let address = address_param(parts, state).await?;
+    let Query(query) = Query::<ChainQuery>::from_request_parts(parts, state).await?;
+
+    let address = normalize_address(address, query.chain)?;
+    // Normalized addresses always have a detectable format.
+    let chain = query
+      .chain
+      .or_else(|| AddressChain::detect(&address))
+      .unwrap();
+
+    Ok(Self { address, chain })
+  }
+}
+
+/// Like `ChainAddress`, also accepting Bitcoin extended public keys for
+/// the routes serving whole wallets. See `normalize_wallet_address`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WalletAddress {
+  pub address: String,
+  pub chain: AddressChain,
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for WalletAddress
+where
+  S: Send + Sync,
+{
+  type Rejection = Error;
+
+  async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+    let address = address_param(parts, state).await?;
+    let Query(query) = Query::<ChainQuery>::from_request_parts(parts, state).await?;
+
+    let address = normalize_wallet_address(address, query.chain)?;
+    // Extended public keys are the only ones without a detectable format.
+    let chain = query
+      .chain
+      .or_else(|| AddressChain::detect(&address))
+      .unwrap_or(AddressChain::Bitcoin);
+
+    Ok(Self { address, chain })
+  }
+}
+
+async fn address_param<S: Send + Sync>(parts: &mut Parts, state: &S) -> Result<String, Error> {
+  // Read by name, so routes can have other path parameters.
+  let Path(mut params) = Path::<HashMap<String, String>>::from_request_parts(parts, state)
+    .await
+    .map_err(|rejection| Error::General(rejection.body_text()))?;
+
+  params
+    .remove("address")
+    .ok_or_else(|| Error::General("Route has no address parameter".to_owned()))
+}
@@ -0,0 +1,77 @@
+use axum::{
+  extract::State,
+  http::{header, HeaderName, HeaderValue, Request},
+  middleware::Next,
+  response::Response,
+};
+
+use crate::settings::Deprecation;
+use crate::state::AppState;
+use crate::utils::date;
+
+/// Versions of the API, each served under `/{version}`. Versions are served
+/// side by side, so clients can move to a new one while the previous one is
+/// deprecated.
+pub const VERSIONS: &[&str] = &["v1"];
+
+// RFC 9745 and RFC 8594 headers, not defined by `http`.
+const DEPRECATION_HEADER: &str = "deprecation";
+const SUNSET_HEADER: &str = "sunset";
+
+/// Middleware announcing the removal of deprecated API versions, configured
+/// in `versions.deprecated`, on every response of their routes.
+pub async fn add_deprecation_headers<B>(
+  State(state): State<AppState>,
+  req: Request<B>,
+  next: Next<B>,
+) -> Response {
+  let deprecation = version(req.uri().path())
+    .and_then(|version| state.settings.versions.deprecated.get(version))
+    .cloned();
+
+  let mut res = next.run(req).await;
+  if let Some(deprecation) = deprecation {
+    // Appended, responses may already link to other pages.
+    for (name, value) in deprecation_headers(&deprecation) {
+      res.headers_mut().append(name, value);
+    }
+  }
+
+  res
+}
+
+/// API version of a request path, e.g. `v1` for `/v1/cats`.
+pub fn version(path: &str) -> Option<&'static str> {
+  let segment = path.trim_start_matches('/').split('/').next()?;
+  VERSIONS.iter().copied().find(|version| *version == segment)
+}
+
+/// `Deprecation` and `Sunset` headers of a deprecated version, with a `Link`
+/// to its migration guide.
+pub fn deprecation_headers(deprecation: &Deprecation) -> Vec<(HeaderName, HeaderValue)> {
+  let mut headers = Vec::new();
+
+  // A structured field date, the unix timestamp prefixed with `@`.
+  let deprecated_at = format!("@{}", deprecation.deprecated_at.timestamp());
+  headers.push((
+    HeaderName::from_static(DEPRECATION_HEADER),
+    HeaderValue::from_str(&deprecated_at).unwrap(),
+  ));
+
+  if let Some(sunset_at) = deprecation.sunset_at {
+    headers.push((
+      HeaderName::from_static(SUNSET_HEADER),
+      HeaderValue::from_str(&date::to_http_date(sunset_at)).unwrap(),
+    ));
+  }
+
+  let link = deprecation
+    .link
+    .as_ref()
+    .map(|link| format!("<{}>; rel=\"deprecation\"; type=\"text/html\"", link));
+  if let Some(Ok(link)) = link.map(|link| HeaderValue::from_str(&link)) {
+    headers.push((header::LINK, link));
+  }
+
+  headers
+}
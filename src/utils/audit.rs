@@ -0,0 +1,75 @@
+use axum::{extract::MatchedPath, http::Request, middleware::Next, response::Response};
+use std::sync::Mutex;
+use tracing::error;
+use wither::bson::{oid::ObjectId, Document};
+
+use crate::models::audit_log::{AuditAction, AuditLog};
+use crate::utils::models::ModelExt;
+
+tokio::task_local! {
+  static CONTEXT: AuditContext;
+}
+
+/// Request whose changes are recorded in the audit logs. Only changes made
+/// while handling API requests are recorded, background tasks (e.g. the
+/// watcher) have no context.
+#[derive(Debug)]
+struct AuditContext {
+  route: String,
+  // Set once the request is authenticated, see `set_actor`.
+  actor: Mutex<Option<ObjectId>>,
+}
+
+/// Middleware running the request inside an audit context, so the changes
+/// made by `ModelExt` are recorded along the route and user.
+pub async fn audit_requests<B>(req: Request<B>, next: Next<B>) -> Response {
+  let path = req
+    .extensions()
+    .get::<MatchedPath>()
+    .map(|path| path.as_str().to_owned())
+    .unwrap_or_else(|| req.uri().path().to_owned());
+
+  let context = AuditContext {
+    route: format!("{} {}", req.method(), path),
+    actor: Mutex::new(None),
+  };
+
+  CONTEXT.scope(context, next.run(req)).await
+}
+
+/// Records the authenticated user of the current request as the actor of its
+/// changes. Does nothing outside of requests.
+pub fn set_actor(actor: ObjectId) {
+  let _ = CONTEXT.try_with(|context| {
+    *context.actor.lock().unwrap() = Some(actor);
+  });
+}
+
+/// Whether changes are recorded, i.e. whether the code runs inside a
+/// request.
+pub fn is_recording() -> bool {
+  CONTEXT.try_with(|_| ()).is_ok()
+}
+
+/// Records changes of a collection, as `(before, after)` documents. Failures
+/// are logged without failing the request, the change is already applied.
+pub async fn record(
+  resource: &str,
+  action: AuditAction,
+  changes: Vec<(Option<Document>, Option<Document>)>,
+) {
+  let context = CONTEXT.try_with(|context| (context.route.clone(), *context.actor.lock().unwrap()));
+  let (route, actor) = match context {
+    Ok(context) => context,
+    Err(_) => return,
+  };
+
+  let logs = changes
+    .into_iter()
+    .map(|(before, after)| AuditLog::new(actor, route.clone(), resource, action, before, after))
+    .collect::<Vec<AuditLog>>();
+
+  if let Err(err) = AuditLog::insert_many(logs).await {
+    error!("Failed to record audit logs of {}: {:?}", resource, err);
+  }
+}
@@ -2,16 +2,32 @@ use axum::{
   async_trait,
   extract::{FromRequestParts, TypedHeader},
   headers::{authorization::Bearer, Authorization},
-  http::request::Parts,
+  http::{request::Parts, HeaderMap, Method},
   RequestPartsExt,
 };
+use bson::doc;
+use tracing::debug;
 
 use crate::errors::AuthenticateError;
 use crate::errors::Error;
+use crate::models::api_key::{ApiKey, ApiKeyScope};
+use crate::models::session::Session;
+use crate::models::user::{Role, User};
 use crate::settings::SETTINGS;
+use crate::utils::audit;
+use crate::utils::date;
+use crate::utils::models::ModelExt;
+use crate::utils::secret;
+use crate::utils::telemetry;
 use crate::utils::token;
-use crate::utils::token::TokenUser;
+use crate::utils::token::{AdminUser, MemberUser, TokenUser};
 
+// Header carrying API keys, see `ApiKey`.
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Authenticates requests with either an access token in the
+/// `Authorization` header or an API key in the `X-Api-Key` header. Roles are
+/// checked by the routes, see `MemberUser` and `AdminUser`.
 #[async_trait]
 impl<S> FromRequestParts<S> for TokenUser
 where
@@ -20,15 +36,139 @@ where
   type Rejection = Error;
 
   async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
-    let TypedHeader(Authorization(bearer)) = parts
-      .extract::<TypedHeader<Authorization<Bearer>>>()
-      .await
-      .map_err(|_| AuthenticateError::InvalidToken)?;
+    let user = match parts.headers.get(API_KEY_HEADER) {
+      Some(key) => {
+        let key = key.to_str().map_err(|_| AuthenticateError::InvalidToken)?;
+        authenticate_api_key(key, &parts.method).await?
+      }
+      None => {
+        let TypedHeader(Authorization(bearer)) = parts
+          .extract::<TypedHeader<Authorization<Bearer>>>()
+          .await
+          .map_err(|_| AuthenticateError::InvalidToken)?;
+
+        decode_user(bearer.token()).await?
+      }
+    };
+
+    audit::set_actor(user.id);
+    telemetry::record_user(user.id);
+    // Read by the extractors depending on the user, e.g. `RequireFeature`.
+    parts.extensions.insert(user.clone());
+
+    Ok(user)
+  }
+}
+
+/// Rejects users without the permissions of the role, for routes checking
+/// roles past the extractors.
+pub fn require_role(user: &TokenUser, role: Role) -> Result<(), Error> {
+  if !user.role.grants(role) {
+    return Err(Error::Authenticate(AuthenticateError::Forbidden));
+  }
+
+  Ok(())
+}
+
+/// Rejects requests authenticated with an API key, for the account changes
+/// the user has to make themselves: keys are shared with scripts and
+/// services, and can't prove who sends them.
+pub fn require_token(headers: &HeaderMap) -> Result<(), Error> {
+  if headers.contains_key(API_KEY_HEADER) {
+    debug!("Account change with an API key, returning 403 status code");
+    return Err(Error::Authenticate(AuthenticateError::Forbidden));
+  }
+
+  Ok(())
+}
+
+/// Resolves the user of an API key. Keys with the read scope only are limited
+/// to safe methods.
+async fn authenticate_api_key(key: &str, method: &Method) -> Result<TokenUser, Error> {
+  let api_key = ApiKey::find_one(doc! { "hash": secret::hash(key) }, None)
+    .await?
+    .ok_or(AuthenticateError::InvalidToken)?;
+
+  let scope = if method.is_safe() {
+    ApiKeyScope::Read
+  } else {
+    ApiKeyScope::Write
+  };
+  if !api_key.has_scope(scope) {
+    return Err(Error::Authenticate(AuthenticateError::Forbidden));
+  }
+
+  // Keys outlive tokens, so the user is loaded to apply locks and role
+  // changes right away.
+  let user = User::find_by_id(&api_key.user)
+    .await?
+    .ok_or(AuthenticateError::InvalidToken)?;
+  if user.locked_at.is_some() {
+    return Err(Error::Authenticate(AuthenticateError::Locked));
+  }
+
+  ApiKey::update_one(
+    doc! { "_id": api_key.id.unwrap() },
+    doc! { "$set": { "last_used_at": date::now() } },
+    None,
+  )
+  .await?;
+
+  Ok(TokenUser::from(user))
+}
+
+/// Reads the user of an access token, for routes that can't receive it in
+/// the `Authorization` header. Tokens of revoked or expired sessions are
+/// rejected, see `Session`.
+pub async fn decode_user(token: &str) -> Result<TokenUser, Error> {
+  let secret = SETTINGS.auth.secret.as_str();
+  let token_data = token::decode(token, secret).map_err(|_| AuthenticateError::InvalidToken)?;
+  let user = token_data.claims.user;
+
+  if let Some(session) = user.session {
+    let session = Session::find_one_and_update(
+      doc! {
+        "_id": session,
+        "user": &user.id,
+        "expires_at": { "$gt": date::now() }
+      },
+      doc! { "$set": { "last_used_at": date::now() } },
+    )
+    .await?;
+    if session.is_none() {
+      return Err(Error::Authenticate(AuthenticateError::InvalidToken));
+    }
+  }
+
+  Ok(user)
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for AdminUser
+where
+  S: Send + Sync,
+{
+  type Rejection = Error;
+
+  async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+    let user = TokenUser::from_request_parts(parts, state).await?;
+    require_role(&user, Role::Admin)?;
+
+    Ok(Self(user))
+  }
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for MemberUser
+where
+  S: Send + Sync,
+{
+  type Rejection = Error;
 
-    let secret = SETTINGS.auth.secret.as_str();
-    let token_data =
-      token::decode(bearer.token(), secret).map_err(|_| AuthenticateError::InvalidToken)?;
+  async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+    let user = TokenUser::from_request_parts(parts, state).await?;
+    require_role(&user, Role::Member)?;
 
-    Ok(token_data.claims.user)
+    Ok(Self(user))
   }
 }
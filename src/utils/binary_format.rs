@@ -0,0 +1,121 @@
+use axum::{
+  http::{header, HeaderMap, HeaderValue, Request},
+  middleware::Next,
+  response::Response,
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::errors::Error;
+
+/// Binary encodings of the JSON bodies, for programmatic clients calling the
+/// API at a high rate: they are smaller and cheaper to parse. Bodies have the
+/// same shape whatever the encoding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryFormat {
+  // Structs are encoded as maps keyed by the field names, like JSON objects.
+  MessagePack,
+  Cbor,
+}
+
+tokio::task_local! {
+  static ACCEPTED: Option<BinaryFormat>;
+}
+
+impl BinaryFormat {
+  /// Format of a media type, e.g. `application/msgpack`. Parameters are
+  /// ignored.
+  pub fn from_media_type(media_type: &str) -> Option<Self> {
+    let essence = media_type.split(';').next().unwrap_or_default().trim();
+    match essence.to_ascii_lowercase().as_str() {
+      "application/msgpack" | "application/x-msgpack" | "application/vnd.msgpack" => {
+        Some(BinaryFormat::MessagePack)
+      }
+      "application/cbor" => Some(BinaryFormat::Cbor),
+      _ => None,
+    }
+  }
+
+  /// Format of a request body, from its `Content-Type`. `None` for JSON and
+  /// anything else.
+  pub fn from_content_type(headers: &HeaderMap) -> Option<Self> {
+    headers
+      .get(header::CONTENT_TYPE)
+      .and_then(|value| value.to_str().ok())
+      .and_then(Self::from_media_type)
+  }
+
+  /// Format the client asks for in its `Accept` header, the first of the
+  /// listed media types that is either JSON or a binary format. `None` when
+  /// JSON comes first or none is listed.
+  pub fn from_accept(headers: &HeaderMap) -> Option<Self> {
+    headers
+      .get_all(header::ACCEPT)
+      .iter()
+      .filter_map(|value| value.to_str().ok())
+      .flat_map(|value| value.split(','))
+      .find_map(|media_type| {
+        let essence = media_type.split(';').next().unwrap_or_default().trim();
+        if essence.eq_ignore_ascii_case(mime::APPLICATION_JSON.as_ref()) {
+          Some(None)
+        } else {
+          Self::from_media_type(essence).map(Some)
+        }
+      })
+      .flatten()
+  }
+
+  pub fn content_type(&self) -> HeaderValue {
+    match self {
+      BinaryFormat::MessagePack => HeaderValue::from_static("application/msgpack"),
+      BinaryFormat::Cbor => HeaderValue::from_static("application/cbor"),
+    }
+  }
+
+  pub fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>, String> {
+    match self {
+      BinaryFormat::MessagePack => rmp_serde::to_vec_named(value).map_err(|err| err.to_string()),
+      BinaryFormat::Cbor => {
+        let mut bytes = Vec::new();
+        ciborium::into_writer(value, &mut bytes).map_err(|err| err.to_string())?;
+        Ok(bytes)
+      }
+    }
+  }
+
+  /// Decodes a request body. Like JSON bodies, the ones that can't be read
+  /// are malformed (400) and the ones not matching `T` are invalid (422).
+  pub fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, Error> {
+    match self {
+      BinaryFormat::MessagePack => rmp_serde::from_slice(bytes).map_err(|err| match err {
+        // Missing fields, wrong types...
+        rmp_serde::decode::Error::Syntax(message) => Error::InvalidPayload(message),
+        err => Error::MalformedPayload(format!("Failed to parse the MessagePack body: {}", err)),
+      }),
+      BinaryFormat::Cbor => ciborium::from_reader(bytes).map_err(|err| match err {
+        ciborium::de::Error::Semantic(_, message) => Error::InvalidPayload(message),
+        err => Error::MalformedPayload(format!("Failed to parse the CBOR body: {}", err)),
+      }),
+    }
+  }
+}
+
+/// Middleware reading the format the client accepts for the request, see
+/// `accepted`.
+pub async fn negotiate<B>(req: Request<B>, next: Next<B>) -> Response {
+  let format = BinaryFormat::from_accept(req.headers());
+
+  let mut res = ACCEPTED.scope(format, next.run(req)).await;
+  // Caches must not send the body of one format to clients asking for the
+  // other.
+  res
+    .headers_mut()
+    .append(header::VARY, HeaderValue::from_static("accept"));
+
+  res
+}
+
+/// Binary format the JSON bodies of the response, errors included, are sent
+/// in. `None` for JSON and outside of requests.
+pub fn accepted() -> Option<BinaryFormat> {
+  ACCEPTED.try_with(|format| *format).ok().flatten()
+}
@@ -0,0 +1,84 @@
+use axum::{
+  body::{Body, Bytes},
+  extract::{MatchedPath, State},
+  http::{header, Request},
+  middleware::Next,
+  response::{IntoResponse, Response},
+  BoxError,
+};
+use futures::StreamExt;
+use std::error::Error as StdError;
+use std::fmt;
+use tracing::debug;
+
+use crate::errors::Error;
+use crate::state::AppState;
+
+/// Error of a request body read past the limit of its route.
+#[derive(Debug)]
+pub struct BodyTooLarge {
+  pub limit: usize,
+}
+
+impl fmt::Display for BodyTooLarge {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "Request body is larger than {} bytes", self.limit)
+  }
+}
+
+impl StdError for BodyTooLarge {}
+
+/// Middleware rejecting request bodies larger than the limit of their route
+/// with a 413. Bodies announcing their length are rejected before being
+/// read, the others fail once they go over the limit, see `too_large`.
+pub async fn limit_body_size(
+  State(state): State<AppState>,
+  req: Request<Body>,
+  next: Next<Body>,
+) -> Response {
+  let route = req
+    .extensions()
+    .get::<MatchedPath>()
+    .map(|path| path.as_str().to_owned());
+  let limit = state.settings.body_limit.limit(route.as_deref());
+
+  let length = req
+    .headers()
+    .get(header::CONTENT_LENGTH)
+    .and_then(|length| length.to_str().ok())
+    .and_then(|length| length.parse::<u64>().ok());
+  if length.is_some_and(|length| length > limit as u64) {
+    debug!(
+      "Request body over {} bytes, returning 413 status code",
+      limit
+    );
+    return Error::PayloadTooLarge(limit).into_response();
+  }
+
+  let (parts, body) = req.into_parts();
+  let mut read = 0;
+  let body = body.map(move |chunk| -> Result<Bytes, BoxError> {
+    let chunk = chunk?;
+    read += chunk.len();
+    if read > limit {
+      return Err(Box::new(BodyTooLarge { limit }));
+    }
+    Ok(chunk)
+  });
+
+  next
+    .run(Request::from_parts(parts, Body::wrap_stream(body)))
+    .await
+}
+
+/// Limit of the request body when reading it failed for going over it.
+pub fn too_large(err: &(dyn StdError + 'static)) -> Option<usize> {
+  let mut source = Some(err);
+  while let Some(err) = source {
+    if let Some(err) = err.downcast_ref::<BodyTooLarge>() {
+      return Some(err.limit);
+    }
+    source = err.source();
+  }
+  None
+}
@@ -0,0 +1,338 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use moka::sync::Cache as MokaCache;
+use moka::Expiry;
+use redis::aio::ConnectionManager;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::OnceCell;
+use tracing::{error, warn};
+use utoipa::ToSchema;
+
+use crate::settings::{self, CacheBackend};
+
+// Maximum number of entries kept in a memory cache, least recently used
+// entries are evicted first.
+const MAX_CAPACITY: u64 = 10_000;
+// Expired entries are kept around for this long so they can still be served
+// when fresh data is unavailable.
+const STALE_RETENTION: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Storage of the caches, holding serialized values until their TTL.
+/// Failures of the backend are logged and read as misses, since caching
+/// must not fail the requests.
+#[async_trait]
+pub trait Cache: Send + Sync {
+  async fn get(&self, key: &str) -> Option<Vec<u8>>;
+  async fn set_with_ttl(&self, key: &str, value: Vec<u8>, ttl: Duration);
+  async fn invalidate(&self, key: &str);
+  /// `None` when the backend can't count its entries cheaply.
+  fn entry_count(&self) -> Option<u64>;
+}
+
+/// Backend of the cache named `namespace`, chosen by `cache.backend`. The
+/// namespace keeps the keys of the caches apart in a shared backend.
+pub fn backend(settings: &settings::Cache, namespace: &str) -> Arc<dyn Cache> {
+  match settings.backend {
+    CacheBackend::Memory => Arc::new(MemoryCache::new()),
+    CacheBackend::Redis => Arc::new(RedisCache::new(
+      &settings.redis_url,
+      format!("{}:{}", settings.key_prefix, namespace),
+    )),
+  }
+}
+
+/// Cache of a single instance, backed by `moka`, so it is bounded and evicts
+/// old entries on its own.
+pub struct MemoryCache {
+  entries: MokaCache<String, MemoryEntry>,
+}
+
+#[derive(Clone)]
+struct MemoryEntry {
+  value: Vec<u8>,
+  ttl: Duration,
+}
+
+struct MemoryExpiry;
+
+impl Expiry<String, MemoryEntry> for MemoryExpiry {
+  fn expire_after_create(
+    &self,
+    _key: &String,
+    entry: &MemoryEntry,
+    _created_at: Instant,
+  ) -> Option<Duration> {
+    Some(entry.ttl)
+  }
+
+  fn expire_after_update(
+    &self,
+    _key: &String,
+    entry: &MemoryEntry,
+    _updated_at: Instant,
+    _duration_until_expiry: Option<Duration>,
+  ) -> Option<Duration> {
+    Some(entry.ttl)
+  }
+}
+
+impl MemoryCache {
+  pub fn new() -> Self {
+    let entries = MokaCache::builder()
+      .max_capacity(MAX_CAPACITY)
+      .expire_after(MemoryExpiry)
+      .build();
+
+    Self { entries }
+  }
+}
+
+impl Default for MemoryCache {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+#[async_trait]
+impl Cache for MemoryCache {
+  async fn get(&self, key: &str) -> Option<Vec<u8>> {
+    self.entries.get(key).map(|entry| entry.value)
+  }
+
+  async fn set_with_ttl(&self, key: &str, value: Vec<u8>, ttl: Duration) {
+    self
+      .entries
+      .insert(key.to_owned(), MemoryEntry { value, ttl });
+  }
+
+  async fn invalidate(&self, key: &str) {
+    self.entries.invalidate(key);
+  }
+
+  fn entry_count(&self) -> Option<u64> {
+    Some(self.entries.entry_count())
+  }
+}
+
+/// Cache shared by the instances of a deployment. Connects on first use, so
+/// the app starts while Redis is unavailable.
+pub struct RedisCache {
+  url: String,
+  // Prefix of the keys, followed by `:`.
+  prefix: String,
+  connection: OnceCell<ConnectionManager>,
+}
+
+impl RedisCache {
+  pub fn new(url: &str, prefix: String) -> Self {
+    Self {
+      url: url.to_owned(),
+      prefix,
+      connection: OnceCell::new(),
+    }
+  }
+
+  async fn connection(&self) -> Option<ConnectionManager> {
+    let result = self
+      .connection
+      .get_or_try_init(|| async {
+        let client = redis::Client::open(self.url.as_str())?;
+        ConnectionManager::new(client).await
+      })
+      .await;
+
+    match result {
+      Ok(connection) => Some(connection.clone()),
+      Err(err) => {
+        error!("Failed to connect to Redis: {}", err);
+        None
+      }
+    }
+  }
+
+  fn key(&self, key: &str) -> String {
+    format!("{}:{}", self.prefix, key)
+  }
+}
+
+#[async_trait]
+impl Cache for RedisCache {
+  async fn get(&self, key: &str) -> Option<Vec<u8>> {
+    let mut connection = self.connection().await?;
+    let result: redis::RedisResult<Option<Vec<u8>>> = redis::cmd("GET")
+      .arg(self.key(key))
+      .query_async(&mut connection)
+      .await;
+
+    result.unwrap_or_else(|err| {
+      warn!("Failed to read the Redis cache: {}", err);
+      None
+    })
+  }
+
+  async fn set_with_ttl(&self, key: &str, value: Vec<u8>, ttl: Duration) {
+    let mut connection = match self.connection().await {
+      Some(connection) => connection,
+      None => return,
+    };
+    // Redis rejects a TTL of 0.
+    let ttl_ms = (ttl.as_millis() as u64).max(1);
+    let result: redis::RedisResult<()> = redis::cmd("SET")
+      .arg(self.key(key))
+      .arg(value)
+      .arg("PX")
+      .arg(ttl_ms)
+      .query_async(&mut connection)
+      .await;
+
+    if let Err(err) = result {
+      warn!("Failed to write the Redis cache: {}", err);
+    }
+  }
+
+  async fn invalidate(&self, key: &str) {
+    let mut connection = match self.connection().await {
+      Some(connection) => connection,
+      None => return,
+    };
+    let result: redis::RedisResult<()> = redis::cmd("DEL")
+      .arg(self.key(key))
+      .query_async(&mut connection)
+      .await;
+
+    if let Err(err) = result {
+      warn!("Failed to invalidate the Redis cache: {}", err);
+    }
+  }
+
+  fn entry_count(&self) -> Option<u64> {
+    None
+  }
+}
+
+/// Cache where entries are fresh for a fixed TTL, stored as JSON in a
+/// `Cache` backend. Entries are kept for a day after they expire, so they
+/// can still be served when fresh data is unavailable.
+pub struct TtlCache<V> {
+  ttl: Duration,
+  backend: Arc<dyn Cache>,
+  hits: AtomicU64,
+  misses: AtomicU64,
+  value: PhantomData<fn() -> V>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheEntry<V> {
+  pub value: V,
+  pub cached_at: DateTime<Utc>,
+  // Wall clock time, so entries shared between instances expire together.
+  expires_at: DateTime<Utc>,
+}
+
+/// Hit and miss counters of a cache, since the app started.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CacheStats {
+  pub hits: u64,
+  pub misses: u64,
+  // Not counted by the `redis` backend.
+  pub entries: Option<u64>,
+}
+
+impl<V> CacheEntry<V> {
+  pub fn is_expired(&self) -> bool {
+    Utc::now() >= self.expires_at
+  }
+
+  /// Time left until the entry expires.
+  pub fn remaining_ttl(&self) -> Duration {
+    (self.expires_at - Utc::now()).to_std().unwrap_or_default()
+  }
+}
+
+impl<V> TtlCache<V>
+where
+  V: Clone + Serialize + DeserializeOwned + Send + Sync + 'static,
+{
+  pub fn new(backend: Arc<dyn Cache>, ttl: Duration) -> Self {
+    Self {
+      ttl,
+      backend,
+      hits: AtomicU64::new(0),
+      misses: AtomicU64::new(0),
+      value: PhantomData,
+    }
+  }
+
+  pub fn ttl(&self) -> Duration {
+    self.ttl
+  }
+
+  /// Returns the entry for the given key if it has not expired yet.
+  pub async fn get(&self, key: &str) -> Option<CacheEntry<V>> {
+    let entry = self
+      .get_stale(key)
+      .await
+      .filter(|entry| !entry.is_expired());
+
+    let counter = match entry {
+      Some(_) => &self.hits,
+      None => &self.misses,
+    };
+    counter.fetch_add(1, Ordering::Relaxed);
+
+    entry
+  }
+
+  /// Returns the entry for the given key even if it has expired, as long as
+  /// it was not evicted yet. Does not count as a hit or a miss.
+  pub async fn get_stale(&self, key: &str) -> Option<CacheEntry<V>> {
+    let data = self.backend.get(key).await?;
+    match serde_json::from_slice(&data) {
+      Ok(entry) => Some(entry),
+      // Entries written by an older version of the app.
+      Err(err) => {
+        warn!("Ignoring an unreadable cache entry: {}", err);
+        None
+      }
+    }
+  }
+
+  pub async fn insert<K: Into<String>>(&self, key: K, value: V) -> CacheEntry<V> {
+    let cached_at = Utc::now();
+    let entry = CacheEntry {
+      value,
+      cached_at,
+      expires_at: cached_at + chrono::Duration::from_std(self.ttl).unwrap_or_default(),
+    };
+
+    match serde_json::to_vec(&entry) {
+      Ok(data) => {
+        let key = key.into();
+        self
+          .backend
+          .set_with_ttl(&key, data, self.ttl + STALE_RETENTION)
+          .await;
+      }
+      Err(err) => error!("Failed to serialize a cache entry: {}", err),
+    }
+
+    entry
+  }
+
+  pub async fn invalidate(&self, key: &str) {
+    self.backend.invalidate(key).await;
+  }
+
+  pub fn stats(&self) -> CacheStats {
+    CacheStats {
+      hits: self.hits.load(Ordering::Relaxed),
+      misses: self.misses.load(Ordering::Relaxed),
+      entries: self.backend.entry_count(),
+    }
+  }
+}
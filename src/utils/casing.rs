@@ -0,0 +1,141 @@
+use axum::{
+  body::{boxed, Full},
+  http::{
+    header::{self, HeaderValue},
+    Request, StatusCode,
+  },
+  middleware::Next,
+  response::{IntoResponse, Response},
+};
+use serde_json::{Map, Value};
+use tracing::error;
+
+/// Request header clients use to opt in to a response body casing.
+pub const CASING_HEADER: &str = "x-json-casing";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Casing {
+  Snake,
+  Camel,
+}
+
+impl Casing {
+  pub fn parse(value: &str) -> Option<Self> {
+    match value.trim().to_ascii_lowercase().as_str() {
+      "snake" | "snake_case" => Some(Casing::Snake),
+      "camel" | "camelcase" => Some(Casing::Camel),
+      _ => None,
+    }
+  }
+
+  /// Converts an object key. Keys that aren't identifiers (e.g. addresses
+  /// used as map keys) are kept as they are.
+  pub fn convert(&self, key: &str) -> String {
+    let is_identifier = key.starts_with(|c: char| c.is_ascii_alphabetic())
+      && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !is_identifier {
+      return key.to_owned();
+    }
+
+    match self {
+      Casing::Snake => to_snake_case(key),
+      Casing::Camel => to_camel_case(key),
+    }
+  }
+}
+
+fn to_snake_case(key: &str) -> String {
+  let mut result = String::with_capacity(key.len() + 4);
+  for c in key.chars() {
+    if c.is_ascii_uppercase() {
+      result.push('_');
+      result.push(c.to_ascii_lowercase());
+    } else {
+      result.push(c);
+    }
+  }
+  result
+}
+
+fn to_camel_case(key: &str) -> String {
+  let mut result = String::with_capacity(key.len());
+  let mut uppercase_next = false;
+  for c in key.chars() {
+    if c == '_' {
+      uppercase_next = !result.is_empty();
+    } else if uppercase_next {
+      result.push(c.to_ascii_uppercase());
+      uppercase_next = false;
+    } else {
+      result.push(c);
+    }
+  }
+  result
+}
+
+/// Recursively converts the keys of every object in a JSON value.
+pub fn convert_keys(value: Value, casing: Casing) -> Value {
+  match value {
+    Value::Object(object) => Value::Object(
+      object
+        .into_iter()
+        .map(|(key, value)| (casing.convert(&key), convert_keys(value, casing)))
+        .collect::<Map<String, Value>>(),
+    ),
+    Value::Array(values) => Value::Array(
+      values
+        .into_iter()
+        .map(|value| convert_keys(value, casing))
+        .collect(),
+    ),
+    value => value,
+  }
+}
+
+/// Middleware rewriting JSON response bodies to the casing requested through
+/// the `X-Json-Casing` header. Responses are left untouched when the header
+/// is absent, so existing clients keep the current field names.
+pub async fn convert_response_casing<B>(req: Request<B>, next: Next<B>) -> Response {
+  let casing = req
+    .headers()
+    .get(CASING_HEADER)
+    .and_then(|value| value.to_str().ok())
+    .and_then(Casing::parse);
+
+  let mut res = next.run(req).await;
+  res
+    .headers_mut()
+    .append(header::VARY, HeaderValue::from_static(CASING_HEADER));
+
+  let casing = match casing {
+    Some(casing) => casing,
+    None => return res,
+  };
+
+  let is_json = res
+    .headers()
+    .get(header::CONTENT_TYPE)
+    .and_then(|value| value.to_str().ok())
+    .map(|value| value.starts_with(mime::APPLICATION_JSON.as_ref()))
+    .unwrap_or(false);
+  if !is_json {
+    return res;
+  }
+
+  let (mut parts, body) = res.into_parts();
+  let bytes = match hyper::body::to_bytes(body).await {
+    Ok(bytes) => bytes,
+    Err(err) => {
+      error!("Error reading response body: {:?}", err);
+      return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    }
+  };
+
+  let body = match serde_json::from_slice::<Value>(&bytes) {
+    Ok(value) => serde_json::to_vec(&convert_keys(value, casing)).unwrap(),
+    Err(_) => bytes.to_vec(),
+  };
+
+  parts.headers.remove(header::CONTENT_LENGTH);
+  Response::from_parts(parts, boxed(Full::from(body)))
+}
@@ -0,0 +1,82 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+/// Stops calling an upstream service after `failure_threshold` consecutive
+/// failures, so requests fail fast instead of piling up on a service that is
+/// down. Once `reset_timeout` has passed, a single trial request is let
+/// through: its success closes the circuit, its failure opens it again.
+pub struct CircuitBreaker {
+  name: String,
+  failure_threshold: u32,
+  reset_timeout: Duration,
+  state: Mutex<CircuitState>,
+}
+
+#[derive(Default)]
+struct CircuitState {
+  consecutive_failures: u32,
+  // Set while the circuit is open.
+  opened_at: Option<Instant>,
+  // Whether the trial request of a half-open circuit is in flight.
+  trial_in_flight: bool,
+}
+
+impl CircuitBreaker {
+  pub fn new(name: impl Into<String>, failure_threshold: u32, reset_timeout: Duration) -> Self {
+    Self {
+      name: name.into(),
+      failure_threshold: failure_threshold.max(1),
+      reset_timeout,
+      state: Mutex::new(CircuitState::default()),
+    }
+  }
+
+  /// Whether a request may be sent. Callers report its outcome with
+  /// `record_success` or `record_failure`.
+  pub fn allow(&self) -> bool {
+    let mut state = self.state.lock().unwrap();
+    let opened_at = match state.opened_at {
+      Some(opened_at) => opened_at,
+      None => return true,
+    };
+
+    if opened_at.elapsed() < self.reset_timeout || state.trial_in_flight {
+      return false;
+    }
+
+    state.trial_in_flight = true;
+    true
+  }
+
+  pub fn record_success(&self) {
+    *self.state.lock().unwrap() = CircuitState::default();
+  }
+
+  pub fn record_failure(&self) {
+    let mut state = self.state.lock().unwrap();
+    state.consecutive_failures += 1;
+    state.trial_in_flight = false;
+
+    if state.opened_at.is_some() || state.consecutive_failures >= self.failure_threshold {
+      if state.opened_at.is_none() {
+        warn!(
+          "Opening the {} circuit after {} failures",
+          self.name, state.consecutive_failures
+        );
+      }
+      state.opened_at = Some(Instant::now());
+    }
+  }
+
+  /// Gives up on an allowed request that was never sent, e.g. one shed by a
+  /// rate limiter, so it doesn't hold the trial of a half-open circuit.
+  pub fn record_cancelled(&self) {
+    self.state.lock().unwrap().trial_in_flight = false;
+  }
+
+  #[cfg(test)]
+  pub fn is_open(&self) -> bool {
+    self.state.lock().unwrap().opened_at.is_some()
+  }
+}
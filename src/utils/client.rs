@@ -0,0 +1,59 @@
+use axum::{
+  async_trait,
+  extract::{ConnectInfo, FromRequestParts},
+  http::{header, request::Parts},
+};
+use std::convert::Infallible;
+use std::net::{IpAddr, SocketAddr};
+
+use crate::settings::SETTINGS;
+
+// Longest user agent kept, they are only shown to the user.
+const MAX_DEVICE_LENGTH: usize = 256;
+
+/// Client sending a request, recorded on the sessions it signs in to, see
+/// `Session`.
+#[derive(Debug, Clone, Default)]
+pub struct ClientInfo {
+  // User agent of the client.
+  pub device: Option<String>,
+  pub ip: Option<IpAddr>,
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for ClientInfo
+where
+  S: Send + Sync,
+{
+  type Rejection = Infallible;
+
+  async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+    let device = parts
+      .headers
+      .get(header::USER_AGENT)
+      .and_then(|value| value.to_str().ok())
+      .map(|device| device.chars().take(MAX_DEVICE_LENGTH).collect());
+    let ip = client_ip(parts, SETTINGS.rate_limit.anonymous.trust_forwarded_for);
+
+    Ok(Self { device, ip })
+  }
+}
+
+/// IP of the client, the last `X-Forwarded-For` address when trusted since
+/// that is the one added by the proxy, else the address of the connection.
+pub fn client_ip(parts: &Parts, trust_forwarded_for: bool) -> Option<IpAddr> {
+  let forwarded_for = parts
+    .headers
+    .get("x-forwarded-for")
+    .and_then(|value| value.to_str().ok())
+    .and_then(|value| value.rsplit(',').next())
+    .and_then(|ip| ip.trim().parse::<IpAddr>().ok());
+
+  match forwarded_for {
+    Some(ip) if trust_forwarded_for => Some(ip),
+    _ => parts
+      .extensions
+      .get::<ConnectInfo<SocketAddr>>()
+      .map(|ConnectInfo(address)| address.ip()),
+  }
+}
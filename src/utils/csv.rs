@@ -0,0 +1,59 @@
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::errors::Error;
+
+/// Serializes a list of items as CSV, with a header row of the item fields
+/// in the order they are first seen. Nested values (e.g. tags) are written
+/// as JSON, missing and null ones as empty cells. An empty list gives an
+/// empty body since there is no item to take the fields from.
+pub fn to_csv<T: Serialize>(items: &T) -> Result<Vec<u8>, Error> {
+  let rows = match serde_json::to_value(items).map_err(to_error)? {
+    Value::Array(items) => items.into_iter().map(into_row).collect::<Vec<_>>(),
+    item => vec![into_row(item)],
+  };
+
+  let mut columns: Vec<String> = Vec::new();
+  for row in &rows {
+    for column in row.keys() {
+      if !columns.contains(column) {
+        columns.push(column.clone());
+      }
+    }
+  }
+
+  let mut writer = ::csv::Writer::from_writer(Vec::new());
+  if !rows.is_empty() {
+    writer.write_record(&columns).map_err(to_error)?;
+  }
+  for row in &rows {
+    let record = columns
+      .iter()
+      .map(|column| to_cell(row.get(column)))
+      .collect::<Vec<String>>();
+    writer.write_record(&record).map_err(to_error)?;
+  }
+
+  writer
+    .into_inner()
+    .map_err(|err| Error::General(format!("Failed to write CSV: {}", err)))
+}
+
+fn into_row(item: Value) -> Map<String, Value> {
+  match item {
+    Value::Object(fields) => fields,
+    value => Map::from_iter([("value".to_owned(), value)]),
+  }
+}
+
+fn to_cell(value: Option<&Value>) -> String {
+  match value {
+    None | Some(Value::Null) => String::new(),
+    Some(Value::String(value)) => value.clone(),
+    Some(value) => value.to_string(),
+  }
+}
+
+fn to_error<E: std::fmt::Display>(err: E) -> Error {
+  Error::General(format!("Failed to write CSV: {}", err))
+}
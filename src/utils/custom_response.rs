@@ -3,23 +3,50 @@ use axum::{
   http::StatusCode,
   response::{IntoResponse, Response},
 };
-use bytes::{BufMut, BytesMut};
-use serde::Serialize;
+use bytes::{BufMut, Bytes, BytesMut};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 use tracing::error;
+use utoipa::ToSchema;
 
+use crate::utils::binary_format;
+use crate::utils::csv;
 use crate::utils::pagination::Pagination;
+use crate::utils::response_format::ResponseFormat;
 
 #[derive(Debug)]
 pub struct CustomResponse<T: Serialize> {
   pub body: Option<T>,
   pub status_code: StatusCode,
   pub pagination: Option<Pagination>,
+  pub format: ResponseFormat,
+  pub meta: Option<ResponseMeta>,
+  pub warnings: Vec<String>,
 }
 
 pub struct CustomResponseBuilder<T: Serialize> {
   pub body: Option<T>,
   pub status_code: StatusCode,
   pub pagination: Option<Pagination>,
+  pub format: ResponseFormat,
+  pub meta: Option<ResponseMeta>,
+  pub warnings: Vec<String>,
+}
+
+/// How the data of a response was obtained, sent in its `meta` object.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ResponseMeta {
+  // HIT, STALE or MISS, like the `x-cache` header.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub cache: Option<String>,
+  // Time spent waiting on the upstream services.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub upstream_latency_ms: Option<u64>,
+  // When the data was fetched from its source.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  #[schema(value_type = Option<String>, format = DateTime)]
+  pub fresh_at: Option<DateTime<Utc>>,
 }
 
 impl<T> Default for CustomResponseBuilder<T>
@@ -31,6 +58,9 @@ where
       body: None,
       status_code: StatusCode::OK,
       pagination: None,
+      format: ResponseFormat::Json,
+      meta: None,
+      warnings: Vec::new(),
     }
   }
 }
@@ -58,11 +88,33 @@ where
     self
   }
 
+  /// Format of the body, see `ResponseFormat`.
+  pub fn format(mut self, format: ResponseFormat) -> Self {
+    self.format = format;
+    self
+  }
+
+  /// Adds a `meta` object to the JSON body, see `envelope`.
+  pub fn meta(mut self, meta: ResponseMeta) -> Self {
+    self.meta = Some(meta);
+    self
+  }
+
+  /// Adds a warning to the JSON body, e.g. when part of the data is
+  /// unavailable and the request is still answered. See `envelope`.
+  pub fn warning(mut self, warning: impl Into<String>) -> Self {
+    self.warnings.push(warning.into());
+    self
+  }
+
   pub fn build(self) -> CustomResponse<T> {
     CustomResponse {
       body: self.body,
       status_code: self.status_code,
       pagination: self.pagination,
+      format: self.format,
+      meta: self.meta,
+      warnings: self.warnings,
     }
   }
 }
@@ -77,22 +129,60 @@ where
       None => return (self.status_code).into_response(),
     };
 
-    let mut bytes = BytesMut::new().writer();
-    if let Err(err) = serde_json::to_writer(&mut bytes, &body) {
-      error!("Error serializing response body as JSON: {:?}", err);
-      return (StatusCode::INTERNAL_SERVER_ERROR).into_response();
-    }
+    // JSON bodies are sent in the binary format the client accepts, if any.
+    let (bytes, content_type) = match (self.format, binary_format::accepted()) {
+      (ResponseFormat::Json, Some(format)) => {
+        let result = if self.meta.is_none() && self.warnings.is_empty() {
+          format.encode(&body)
+        } else {
+          envelope(&body, self.meta, self.warnings)
+            .map_err(|err| err.to_string())
+            .and_then(|body| format.encode(&body))
+        };
+        match result {
+          Ok(bytes) => (Bytes::from(bytes), format.content_type()),
+          Err(err) => {
+            error!("Error serializing response body as {:?}: {}", format, err);
+            return (StatusCode::INTERNAL_SERVER_ERROR).into_response();
+          }
+        }
+      }
+      (ResponseFormat::Json, None) => {
+        let mut bytes = BytesMut::new().writer();
+        let result = if self.meta.is_none() && self.warnings.is_empty() {
+          serde_json::to_writer(&mut bytes, &body)
+        } else {
+          envelope(&body, self.meta, self.warnings)
+            .and_then(|body| serde_json::to_writer(&mut bytes, &body))
+        };
+        if let Err(err) = result {
+          error!("Error serializing response body as JSON: {:?}", err);
+          return (StatusCode::INTERNAL_SERVER_ERROR).into_response();
+        }
+        let content_type = HeaderValue::from_static(mime::APPLICATION_JSON.as_ref());
+        (bytes.into_inner().freeze(), content_type)
+      }
+      (ResponseFormat::Csv, _) => match csv::to_csv(&body) {
+        Ok(bytes) => {
+          let content_type = HeaderValue::from_static(mime::TEXT_CSV_UTF_8.as_ref());
+          (Bytes::from(bytes), content_type)
+        }
+        Err(err) => {
+          error!("Error serializing response body as CSV: {:?}", err);
+          return (StatusCode::INTERNAL_SERVER_ERROR).into_response();
+        }
+      },
+    };
 
     match self.pagination {
       Some(pagination) => {
         let count = pagination.count.to_string();
         let offset = pagination.offset.to_string();
         let limit = pagination.limit.to_string();
+        let total_pages = pagination.total_pages().to_string();
+        let has_next = pagination.has_next().to_string();
         let headers = [
-          (
-            header::CONTENT_TYPE,
-            HeaderValue::from_static(mime::APPLICATION_JSON.as_ref()),
-          ),
+          (header::CONTENT_TYPE, content_type),
           (
             HeaderName::from_static("x-pagination-count"),
             HeaderValue::from_str(&count).unwrap(),
@@ -105,20 +195,69 @@ where
             HeaderName::from_static("x-pagination-limit"),
             HeaderValue::from_str(&limit).unwrap(),
           ),
+          (
+            HeaderName::from_static("x-pagination-total-pages"),
+            HeaderValue::from_str(&total_pages).unwrap(),
+          ),
+          (
+            HeaderName::from_static("x-pagination-has-next"),
+            HeaderValue::from_str(&has_next).unwrap(),
+          ),
         ];
 
-        let bytes = bytes.into_inner().freeze();
-        (self.status_code, headers, bytes).into_response()
+        let mut res = (self.status_code, headers, bytes).into_response();
+        if let Some(link) = link_header(&pagination) {
+          res.headers_mut().insert(header::LINK, link);
+        }
+        if let Some(cursor) = pagination.next_cursor {
+          res.headers_mut().insert(
+            HeaderName::from_static("x-pagination-next-cursor"),
+            HeaderValue::from_str(&cursor.encode()).unwrap(),
+          );
+        }
+        res
       }
       None => {
-        let headers = [(
-          header::CONTENT_TYPE,
-          HeaderValue::from_static(mime::APPLICATION_JSON.as_ref()),
-        )];
+        let headers = [(header::CONTENT_TYPE, content_type)];
 
-        let bytes = bytes.into_inner().freeze();
         (self.status_code, headers, bytes).into_response()
       }
     }
   }
 }
+
+/// JSON body with its `meta` and `warnings`, added to the fields of object
+/// bodies. Other bodies are sent in the `data` field of an object. Warnings
+/// are always sent along with `meta`, so routes setting it keep the same
+/// shape whether something went wrong or not.
+fn envelope<T: Serialize>(
+  body: &T,
+  meta: Option<ResponseMeta>,
+  warnings: Vec<String>,
+) -> serde_json::Result<Value> {
+  let mut object = match serde_json::to_value(body)? {
+    Value::Object(object) => object,
+    data => Map::from_iter([("data".to_owned(), data)]),
+  };
+  if let Some(meta) = meta {
+    object.insert("meta".to_owned(), serde_json::to_value(meta)?);
+  }
+  object.insert("warnings".to_owned(), Value::from(warnings));
+
+  Ok(Value::Object(object))
+}
+
+/// `Link` header to the other pages, e.g. `</v1/cats?offset=0>; rel="first"`.
+/// `None` when the request URI is unknown or can't be sent back in a header.
+fn link_header(pagination: &Pagination) -> Option<HeaderValue> {
+  let links = pagination
+    .links()
+    .into_iter()
+    .map(|(rel, uri)| format!("<{}>; rel=\"{}\"", uri, rel))
+    .collect::<Vec<String>>();
+  if links.is_empty() {
+    return None;
+  }
+
+  HeaderValue::from_str(&links.join(", ")).ok()
+}
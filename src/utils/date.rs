@@ -1,7 +1,26 @@
-use chrono::Utc;
+use chrono::{DateTime, Utc};
+use std::time::Duration;
 
 pub type Date = bson::DateTime;
 
 pub fn now() -> Date {
   Utc::now().into()
 }
+
+/// Date once `duration` has elapsed from now.
+pub fn after(duration: Duration) -> Date {
+  let millis = i64::try_from(duration.as_millis()).unwrap_or(i64::MAX);
+  Date::from_millis(now().timestamp_millis().saturating_add(millis))
+}
+
+/// Date `duration` ago.
+pub fn before(duration: Duration) -> Date {
+  let millis = i64::try_from(duration.as_millis()).unwrap_or(i64::MAX);
+  Date::from_millis(now().timestamp_millis().saturating_sub(millis))
+}
+
+/// Formats a date as an HTTP date (RFC 7231 IMF-fixdate), as used by the
+/// `Last-Modified` and `Retry-After` headers.
+pub fn to_http_date(date: DateTime<Utc>) -> String {
+  date.format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
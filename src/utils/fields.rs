@@ -0,0 +1,101 @@
+use serde::Serialize;
+use serde_json::{Map, Value};
+use wither::bson::{doc, Bson, Document};
+
+use crate::errors::Error;
+use crate::utils::pagination::Cursor;
+
+/// Fields requested with the `?fields=name,created_at` query parameter, so
+/// clients can skip the ones they don't need. Ids are always returned.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fields(Vec<String>);
+
+impl Fields {
+  /// Parses a comma separated list of fields, checked against the public
+  /// fields of the model so stored only fields (e.g. hashes) can't be
+  /// requested.
+  pub fn parse(fields: &str, allowed: &[&str]) -> Result<Self, Error> {
+    let mut selected = Vec::new();
+    for field in fields.split(',').map(str::trim) {
+      if field.is_empty() || selected.iter().any(|selected| selected == field) {
+        continue;
+      }
+      if !allowed.contains(&field) {
+        return Err(Error::InvalidQuery(format!(
+          "Unknown field `{}`, expected one of: {}",
+          field,
+          allowed.join(", ")
+        )));
+      }
+      selected.push(field.to_owned());
+    }
+
+    if selected.is_empty() {
+      return Err(Error::InvalidQuery("Empty fields parameter".to_owned()));
+    }
+
+    Ok(Self(selected))
+  }
+
+  /// MongoDB projection of the fields. The creation date is always fetched,
+  /// it is needed to build pagination cursors.
+  pub fn projection(&self) -> Document {
+    let mut projection = doc! { "_id": 1_i32, "created_at": 1_i32 };
+    for field in self.0.iter().filter(|field| *field != "id") {
+      projection.insert(field.as_str(), 1_i32);
+    }
+    projection
+  }
+
+  /// Cursor of a projected document, see `Fields::projection`.
+  pub fn cursor(document: &Document) -> Cursor {
+    Cursor::new(
+      *document.get_datetime("created_at").unwrap(),
+      document.get_object_id("_id").unwrap(),
+    )
+  }
+
+  /// Converts a projected document to its public representation, with hex
+  /// ids and RFC 3339 dates like the public models.
+  pub fn select(&self, mut document: Document) -> Map<String, Value> {
+    let mut selected = Map::new();
+    if let Some(id) = document.remove("_id") {
+      selected.insert("id".to_owned(), to_public_json(id));
+    }
+
+    for field in self.0.iter().filter(|field| *field != "id") {
+      let value = document.remove(field).unwrap_or(Bson::Null);
+      selected.insert(field.clone(), to_public_json(value));
+    }
+
+    selected
+  }
+}
+
+/// Response item, either the full public model or the requested fields.
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum Sparse<T> {
+  Full(T),
+  Partial(Map<String, Value>),
+}
+
+/// Converts a BSON value to JSON, with hex ids and RFC 3339 dates like the
+/// public models.
+pub fn to_public_json(value: Bson) -> Value {
+  match value {
+    Bson::ObjectId(id) => Value::String(id.to_hex()),
+    Bson::DateTime(date) => date
+      .try_to_rfc3339_string()
+      .map(Value::String)
+      .unwrap_or(Value::Null),
+    Bson::Array(values) => Value::Array(values.into_iter().map(to_public_json).collect()),
+    Bson::Document(document) => Value::Object(
+      document
+        .into_iter()
+        .map(|(key, value)| (key, to_public_json(value)))
+        .collect(),
+    ),
+    value => value.into_relaxed_extjson(),
+  }
+}
@@ -0,0 +1,236 @@
+use axum::{
+  body::{boxed, Body, Bytes, Full},
+  extract::{FromRequestParts, State},
+  http::{HeaderMap, HeaderValue, Request, StatusCode},
+  middleware::Next,
+  response::{IntoResponse, Response},
+};
+use moka::sync::Cache;
+use sha2::{Digest, Sha256};
+use std::time::Duration;
+use tracing::{debug, error};
+
+use crate::errors::Error;
+use crate::settings;
+use crate::state::AppState;
+use crate::utils::body_limit;
+use crate::utils::token::TokenUser;
+
+/// Request header clients set to safely retry mutating requests.
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+/// Response header set on replayed responses.
+pub const IDEMPOTENT_REPLAYED_HEADER: &str = "idempotent-replayed";
+
+// Longest accepted idempotency key, UUIDs and the like are expected.
+const MAX_KEY_LENGTH: usize = 255;
+// Maximum number of stored responses, least recently used ones are evicted
+// first.
+const MAX_CAPACITY: u64 = 10_000;
+
+/// Responses of mutating requests keyed by user and idempotency key, so
+/// retried requests return the first response instead of running again.
+pub struct IdempotencyStore {
+  entries: Cache<(String, String), Entry>,
+}
+
+#[derive(Clone)]
+enum Entry {
+  // The first request is still running.
+  InFlight {
+    fingerprint: String,
+  },
+  Completed {
+    fingerprint: String,
+    response: StoredResponse,
+  },
+}
+
+#[derive(Debug, Clone)]
+pub struct StoredResponse {
+  pub status: StatusCode,
+  pub headers: HeaderMap,
+  pub body: Bytes,
+}
+
+/// What to do with a request carrying an idempotency key.
+#[derive(Debug)]
+pub enum Claim {
+  // First request with the key, it runs and its response is stored.
+  Acquired,
+  // Retry of a completed request, the stored response is returned.
+  Replay(StoredResponse),
+  // Retry of a request still running.
+  InFlight,
+  // The key was used for a different request.
+  Mismatch,
+}
+
+impl Entry {
+  fn fingerprint(&self) -> &str {
+    match self {
+      Entry::InFlight { fingerprint } => fingerprint,
+      Entry::Completed { fingerprint, .. } => fingerprint,
+    }
+  }
+}
+
+impl IdempotencyStore {
+  pub fn new(settings: &settings::Idempotency) -> Self {
+    let entries = Cache::builder()
+      .max_capacity(MAX_CAPACITY)
+      .time_to_live(Duration::from_secs(settings.ttl_secs))
+      .build();
+
+    Self { entries }
+  }
+
+  /// Claims the key of a user for a request, identified by its fingerprint.
+  pub fn claim(&self, user: &str, key: &str, fingerprint: &str) -> Claim {
+    let entry = self
+      .entries
+      .entry((user.to_owned(), key.to_owned()))
+      .or_insert_with(|| Entry::InFlight {
+        fingerprint: fingerprint.to_owned(),
+      });
+
+    if entry.is_fresh() {
+      return Claim::Acquired;
+    }
+
+    match entry.into_value() {
+      entry if entry.fingerprint() != fingerprint => Claim::Mismatch,
+      Entry::InFlight { .. } => Claim::InFlight,
+      Entry::Completed { response, .. } => Claim::Replay(response),
+    }
+  }
+
+  /// Stores the response of a claimed key.
+  pub fn complete(&self, user: &str, key: &str, fingerprint: &str, response: StoredResponse) {
+    let entry = Entry::Completed {
+      fingerprint: fingerprint.to_owned(),
+      response,
+    };
+    self
+      .entries
+      .insert((user.to_owned(), key.to_owned()), entry);
+  }
+
+  /// Releases a claimed key, so the request can be retried.
+  pub fn release(&self, user: &str, key: &str) {
+    self.entries.invalidate(&(user.to_owned(), key.to_owned()));
+  }
+}
+
+/// Identifies a request by its method, URI and body, so a key reused for a
+/// different request is detected.
+pub fn fingerprint(method: &str, uri: &str, body: &[u8]) -> String {
+  let mut hasher = Sha256::new();
+  hasher.update(method.as_bytes());
+  hasher.update(b" ");
+  hasher.update(uri.as_bytes());
+  hasher.update(b"\n");
+  hasher.update(body);
+  hex::encode(hasher.finalize())
+}
+
+/// Middleware honoring the `Idempotency-Key` header of authenticated
+/// mutating requests. The first response is stored for the configured TTL
+/// and replayed on retries, except for server errors which can be retried.
+pub async fn replay_requests(
+  State(state): State<AppState>,
+  req: Request<Body>,
+  next: Next<Body>,
+) -> Response {
+  if req.method().is_safe() {
+    return next.run(req).await;
+  }
+
+  let key = match req.headers().get(IDEMPOTENCY_KEY_HEADER) {
+    Some(key) => key.to_str().map(str::to_owned).unwrap_or_default(),
+    None => return next.run(req).await,
+  };
+  if key.is_empty() || key.len() > MAX_KEY_LENGTH {
+    debug!("Invalid idempotency key, returning 400 status code");
+    return Error::bad_request().into_response();
+  }
+
+  let (mut parts, body) = req.into_parts();
+  // Anonymous requests are not handled since there is no user to key the
+  // responses by.
+  let user = match TokenUser::from_request_parts(&mut parts, &state).await {
+    Ok(user) => user.id.to_hex(),
+    Err(_) => return next.run(Request::from_parts(parts, body)).await,
+  };
+
+  let body = match hyper::body::to_bytes(body).await {
+    Ok(body) => body,
+    Err(err) => {
+      if let Some(limit) = body_limit::too_large(&err) {
+        debug!(
+          "Request body over {} bytes, returning 413 status code",
+          limit
+        );
+        return Error::PayloadTooLarge(limit).into_response();
+      }
+      error!("Error reading request body: {:?}", err);
+      return Error::bad_request().into_response();
+    }
+  };
+  let fingerprint = fingerprint(parts.method.as_str(), &parts.uri.to_string(), &body);
+  let store = &state.idempotency;
+
+  match store.claim(&user, &key, &fingerprint) {
+    Claim::Acquired => {}
+    Claim::Replay(response) => {
+      debug!("Replaying response of idempotency key");
+      return replay(response);
+    }
+    Claim::InFlight => {
+      debug!("Request with the same idempotency key running, returning 409 status code");
+      return Error::conflict().into_response();
+    }
+    Claim::Mismatch => {
+      debug!("Idempotency key reused for another request, returning 422 status code");
+      return Error::InvalidPayload(
+        "Idempotency key was already used for a different request".to_owned(),
+      )
+      .into_response();
+    }
+  }
+
+  let req = Request::from_parts(parts, Body::from(body));
+  let res = next.run(req).await;
+  if res.status().is_server_error() {
+    store.release(&user, &key);
+    return res;
+  }
+
+  let (parts, body) = res.into_parts();
+  let body = match hyper::body::to_bytes(body).await {
+    Ok(body) => body,
+    Err(err) => {
+      error!("Error reading response body: {:?}", err);
+      store.release(&user, &key);
+      return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    }
+  };
+
+  let response = StoredResponse {
+    status: parts.status,
+    headers: parts.headers.clone(),
+    body: body.clone(),
+  };
+  store.complete(&user, &key, &fingerprint, response);
+
+  Response::from_parts(parts, boxed(Full::from(body)))
+}
+
+fn replay(response: StoredResponse) -> Response {
+  let mut res = Response::new(boxed(Full::from(response.body)));
+  *res.status_mut() = response.status;
+  *res.headers_mut() = response.headers;
+  res
+    .headers_mut()
+    .insert(IDEMPOTENT_REPLAYED_HEADER, HeaderValue::from_static("true"));
+  res
+}
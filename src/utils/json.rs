@@ -0,0 +1,121 @@
+use axum::{
+  async_trait,
+  body::Bytes,
+  extract::{
+    rejection::{BytesRejection, JsonRejection},
+    FromRequest,
+  },
+  http::{header, Request, StatusCode},
+  response::{IntoResponse, Response},
+};
+use serde::{de::DeserializeOwned, Serialize};
+use tracing::error;
+use validator::Validate;
+
+use crate::errors::Error;
+use crate::utils::binary_format::{self, BinaryFormat};
+use crate::utils::body_limit;
+
+/// Drop-in replacement for `axum::Json`. Request bodies that can't be parsed
+/// are rejected with the API error format instead of axum's plain text
+/// responses, naming the offending field and the expected type.
+///
+/// Bodies sent as MessagePack or CBOR, per their `Content-Type`, are read
+/// the same way, see `BinaryFormat`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Json<T>(pub T);
+
+#[async_trait]
+impl<T, S, B> FromRequest<S, B> for Json<T>
+where
+  T: DeserializeOwned,
+  axum::Json<T>: FromRequest<S, B, Rejection = JsonRejection>,
+  Bytes: FromRequest<S, B, Rejection = BytesRejection>,
+  S: Send + Sync,
+  B: Send + 'static,
+{
+  type Rejection = Error;
+
+  async fn from_request(req: Request<B>, state: &S) -> Result<Self, Self::Rejection> {
+    let value = match BinaryFormat::from_content_type(req.headers()) {
+      Some(format) => {
+        let bytes = Bytes::from_request(req, state).await?;
+        format.decode(&bytes)?
+      }
+      None => {
+        let axum::Json(value) = axum::Json::<T>::from_request(req, state).await?;
+        value
+      }
+    };
+
+    Ok(Self(value))
+  }
+}
+
+/// `Json` validating the request body once parsed. Bodies breaking the
+/// constraints declared with `#[validate(...)]` are rejected with a 422
+/// listing the invalid fields.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ValidJson<T>(pub T);
+
+#[async_trait]
+impl<T, S, B> FromRequest<S, B> for ValidJson<T>
+where
+  T: Validate,
+  Json<T>: FromRequest<S, B, Rejection = Error>,
+  S: Send + Sync,
+  B: Send + 'static,
+{
+  type Rejection = Error;
+
+  async fn from_request(req: Request<B>, state: &S) -> Result<Self, Self::Rejection> {
+    let Json(value) = Json::<T>::from_request(req, state).await?;
+    value.validate()?;
+
+    Ok(Self(value))
+  }
+}
+
+impl<T> IntoResponse for Json<T>
+where
+  T: Serialize,
+{
+  fn into_response(self) -> Response {
+    let format = match binary_format::accepted() {
+      Some(format) => format,
+      None => return axum::Json(self.0).into_response(),
+    };
+
+    match format.encode(&self.0) {
+      Ok(bytes) => ([(header::CONTENT_TYPE, format.content_type())], bytes).into_response(),
+      Err(err) => {
+        error!("Error serializing response body as {:?}: {}", format, err);
+        (StatusCode::INTERNAL_SERVER_ERROR).into_response()
+      }
+    }
+  }
+}
+
+impl From<JsonRejection> for Error {
+  fn from(rejection: JsonRejection) -> Self {
+    if let Some(limit) = body_limit::too_large(&rejection) {
+      return Error::PayloadTooLarge(limit);
+    }
+
+    match rejection {
+      // The body is valid JSON but doesn't match the expected shape (missing
+      // fields, wrong types...).
+      JsonRejection::JsonDataError(err) => Error::InvalidPayload(err.body_text()),
+      rejection => Error::MalformedPayload(rejection.body_text()),
+    }
+  }
+}
+
+impl From<BytesRejection> for Error {
+  fn from(rejection: BytesRejection) -> Self {
+    match body_limit::too_large(&rejection) {
+      Some(limit) => Error::PayloadTooLarge(limit),
+      None => Error::MalformedPayload(rejection.body_text()),
+    }
+  }
+}
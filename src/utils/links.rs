@@ -0,0 +1,65 @@
+use axum::http::Method;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use utoipa::ToSchema;
+
+// Prefix the routes are nested under, see `app::create_router`.
+const API_PREFIX: &str = "/v1";
+
+/// Links of a resource keyed by relation, e.g. `self`, `update` or a related
+/// sub-resource, sent in its `links` field so clients can navigate the API
+/// without building URLs themselves.
+pub type Links = BTreeMap<String, Link>;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct Link {
+  // Path of the route, e.g. `/v1/cats/6523f0c3e8a0b6a2d1e4c5f7`.
+  pub href: String,
+  pub method: String,
+}
+
+/// Builds the links of a resource, from its path relative to the API
+/// prefix, e.g. `/cats/{id}`.
+pub struct LinkBuilder {
+  path: String,
+  links: Links,
+}
+
+impl LinkBuilder {
+  pub fn new(path: impl Into<String>) -> Self {
+    Self {
+      path: path.into(),
+      links: Links::new(),
+    }
+  }
+
+  /// Link to an action on the resource itself, e.g. `delete`.
+  pub fn link(self, rel: &str, method: Method) -> Self {
+    let path = self.path.clone();
+    self.insert(rel, method, &path)
+  }
+
+  /// Link to a sub-resource, e.g. `/tags`.
+  pub fn sub(self, rel: &str, method: Method, subpath: &str) -> Self {
+    let path = format!("{}{}", self.path, subpath);
+    self.insert(rel, method, &path)
+  }
+
+  /// Link to another resource, retrieved with a GET.
+  pub fn related(self, rel: &str, path: &str) -> Self {
+    self.insert(rel, Method::GET, path)
+  }
+
+  pub fn build(self) -> Links {
+    self.links
+  }
+
+  fn insert(mut self, rel: &str, method: Method, path: &str) -> Self {
+    let link = Link {
+      href: format!("{}{}", API_PREFIX, path),
+      method: method.to_string(),
+    };
+    self.links.insert(rel.to_owned(), link);
+    self
+  }
+}
@@ -0,0 +1,86 @@
+use axum::{
+  extract::{MatchedPath, State},
+  http::{header, HeaderValue, Request},
+  middleware::Next,
+  response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use tracing::debug;
+
+use crate::errors::Error;
+use crate::settings;
+use crate::state::AppState;
+
+// Routes still served in maintenance, so the load balancer keeps the
+// instances and admins can turn the maintenance off.
+const HEALTH_ROUTES: [&str; 4] = ["/status", "/health", "/ready", "/metrics"];
+const ADMIN_ROUTES_PREFIX: &str = "/v1/admin/";
+
+/// Maintenance mode of this instance, e.g. while running database
+/// migrations. Starts as configured and is toggled by admins with
+/// `PUT /admin/maintenance`, which only affects the instance serving it.
+pub struct Maintenance {
+  enabled: AtomicBool,
+  retry_after_secs: AtomicU64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MaintenanceStatus {
+  pub enabled: bool,
+  // Sent in `Retry-After` while in maintenance.
+  pub retry_after_secs: u64,
+}
+
+impl Maintenance {
+  pub fn new(settings: &settings::Maintenance) -> Self {
+    Self {
+      enabled: AtomicBool::new(settings.enabled),
+      retry_after_secs: AtomicU64::new(settings.retry_after_secs),
+    }
+  }
+
+  pub fn status(&self) -> MaintenanceStatus {
+    MaintenanceStatus {
+      enabled: self.enabled.load(Ordering::Relaxed),
+      retry_after_secs: self.retry_after_secs.load(Ordering::Relaxed),
+    }
+  }
+
+  pub fn set(&self, status: MaintenanceStatus) {
+    self
+      .retry_after_secs
+      .store(status.retry_after_secs, Ordering::Relaxed);
+    self.enabled.store(status.enabled, Ordering::Relaxed);
+  }
+}
+
+/// Whether a route is served in maintenance, by its matched path.
+pub fn is_allowed(route: &str) -> bool {
+  HEALTH_ROUTES.contains(&route) || route.starts_with(ADMIN_ROUTES_PREFIX)
+}
+
+/// Middleware answering 503 with a `Retry-After` to the requests of every
+/// route but the health and admin ones while in maintenance.
+pub async fn reject_during_maintenance<B>(
+  State(state): State<AppState>,
+  req: Request<B>,
+  next: Next<B>,
+) -> Response {
+  let status = state.maintenance.status();
+  let allowed = req
+    .extensions()
+    .get::<MatchedPath>()
+    .map_or(false, |route| is_allowed(route.as_str()));
+  if !status.enabled || allowed {
+    return next.run(req).await;
+  }
+
+  debug!("In maintenance, returning 503 status code");
+  let retry_after_secs = status.retry_after_secs;
+  let mut res = Error::Maintenance { retry_after_secs }.into_response();
+  res
+    .headers_mut()
+    .insert(header::RETRY_AFTER, HeaderValue::from(retry_after_secs));
+  res
+}
@@ -0,0 +1,50 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use validator::Validate;
+
+use crate::errors::Error;
+
+/// Applies a JSON merge patch to `target` (RFC 7396). Members of the patch
+/// replace the ones of the target, `null` members remove them, and objects
+/// are merged recursively. A patch that isn't an object replaces the target.
+pub fn merge(target: &mut Value, patch: Value) {
+  let patch = match patch {
+    Value::Object(patch) => patch,
+    patch => {
+      *target = patch;
+      return;
+    }
+  };
+
+  if !target.is_object() {
+    *target = Value::Object(Default::default());
+  }
+  let target = target.as_object_mut().unwrap();
+
+  for (name, value) in patch {
+    if value.is_null() {
+      target.remove(&name);
+    } else {
+      merge(target.entry(name).or_insert(Value::Null), value);
+    }
+  }
+}
+
+/// Patches the updatable fields of a document, e.g. `CatPatch`, through
+/// their JSON representation and validates the result. Removed fields take
+/// their serde default, the patch is rejected with a 422 when they have
+/// none.
+pub fn apply<T>(target: &T, patch: Value) -> Result<T, Error>
+where
+  T: Serialize + DeserializeOwned + Validate,
+{
+  let mut document = serde_json::to_value(target).map_err(|err| Error::General(err.to_string()))?;
+  merge(&mut document, patch);
+
+  let patched =
+    serde_json::from_value::<T>(document).map_err(|err| Error::InvalidPayload(err.to_string()))?;
+  patched.validate()?;
+
+  Ok(patched)
+}
@@ -0,0 +1,148 @@
+use axum::{
+  extract::{MatchedPath, State},
+  http::{header, HeaderValue, Request},
+  middleware::Next,
+  response::{IntoResponse, Response},
+};
+use lazy_static::lazy_static;
+use metrics::{counter, gauge, histogram};
+use metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle};
+use std::time::{Duration, Instant};
+
+use crate::state::AppState;
+use crate::utils::cache::CacheStats;
+use crate::utils::route_table::RouteTable;
+
+// Buckets of the latency histograms, in seconds.
+const LATENCY_BUCKETS: &[f64] = &[
+  0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+lazy_static! {
+  // The recorder is global, so it is installed once even when the app is
+  // created several times (e.g. in tests).
+  static ref HANDLE: PrometheusHandle = PrometheusBuilder::new()
+    .set_buckets(LATENCY_BUCKETS)
+    .expect("Invalid metrics buckets")
+    .install_recorder()
+    .expect("Failed to install metrics recorder");
+}
+
+/// Installs the metrics recorder. Metrics recorded before are lost.
+pub fn setup() {
+  lazy_static::initialize(&HANDLE);
+}
+
+pub fn create_route() -> RouteTable<AppState> {
+  RouteTable::new().get("/metrics", get_metrics)
+}
+
+/// Metrics in the Prometheus text format.
+async fn get_metrics(State(state): State<AppState>) -> impl IntoResponse {
+  record_cache_stats("address", state.address_cache.stats());
+  record_cache_stats("entity", state.entity_cache.stats());
+  record_cache_stats("portfolio", state.portfolio_cache.stats());
+
+  let headers = [(
+    header::CONTENT_TYPE,
+    HeaderValue::from_static("text/plain; version=0.0.4"),
+  )];
+  // Drains the histogram buckets, otherwise they grow until scraped.
+  HANDLE.run_upkeep();
+  (headers, HANDLE.render())
+}
+
+// Cache counters are kept by the caches, they are copied when scraped.
+fn record_cache_stats(cache: &'static str, stats: CacheStats) {
+  gauge!("arkham_cache_hits", "cache" => cache).set(stats.hits as f64);
+  gauge!("arkham_cache_misses", "cache" => cache).set(stats.misses as f64);
+  if let Some(entries) = stats.entries {
+    gauge!("arkham_cache_entries", "cache" => cache).set(entries as f64);
+  }
+
+  let lookups = stats.hits + stats.misses;
+  if lookups > 0 {
+    let ratio = stats.hits as f64 / lookups as f64;
+    gauge!("arkham_cache_hit_ratio", "cache" => cache).set(ratio);
+  }
+}
+
+/// Middleware counting requests and measuring their latency by route.
+/// Requests matching no route are grouped, so random paths can't create new
+/// series.
+pub async fn track_requests<B>(req: Request<B>, next: Next<B>) -> Response {
+  let route = req
+    .extensions()
+    .get::<MatchedPath>()
+    .map(|path| path.as_str().to_owned())
+    .unwrap_or_else(|| "unmatched".to_owned());
+  let method = req.method().to_string();
+
+  let started_at = Instant::now();
+  let res = next.run(req).await;
+  let elapsed = started_at.elapsed();
+
+  let status = res.status().as_u16().to_string();
+  counter!(
+    "http_requests_total",
+    "method" => method.clone(),
+    "route" => route.clone(),
+    "status" => status
+  )
+  .increment(1);
+  histogram!(
+    "http_request_duration_seconds",
+    "method" => method,
+    "route" => route
+  )
+  .record(elapsed.as_secs_f64());
+
+  res
+}
+
+/// Records an Arkham API call. The status is `error` when no response was
+/// received.
+pub fn record_arkham_request(endpoint: &'static str, status: Option<u16>, elapsed: Duration) {
+  let status = status
+    .map(|status| status.to_string())
+    .unwrap_or_else(|| "error".to_owned());
+
+  counter!(
+    "arkham_requests_total",
+    "endpoint" => endpoint,
+    "status" => status
+  )
+  .increment(1);
+  histogram!("arkham_request_duration_seconds", "endpoint" => endpoint)
+    .record(elapsed.as_secs_f64());
+}
+
+/// Records the duration of a MongoDB operation.
+pub fn record_mongo_operation(
+  collection: &'static str,
+  operation: &'static str,
+  elapsed: Duration,
+) {
+  histogram!(
+    "mongo_operation_duration_seconds",
+    "collection" => collection,
+    "operation" => operation
+  )
+  .record(elapsed.as_secs_f64());
+}
+
+/// Records a run of a scheduled job, see `services::scheduler`.
+pub fn record_job_run(job: &'static str, succeeded: bool, elapsed: Duration) {
+  let status = if succeeded { "succeeded" } else { "failed" };
+
+  counter!("scheduled_job_runs_total", "job" => job, "status" => status).increment(1);
+  histogram!("scheduled_job_duration_seconds", "job" => job).record(elapsed.as_secs_f64());
+}
+
+/// Records the usage of every user in the current month, rolled up by the
+/// `usage_rollup` job.
+pub fn record_usage_totals(users: u64, requests: u64, upstream_credits: u64) {
+  gauge!("usage_users").set(users as f64);
+  gauge!("usage_requests").set(requests as f64);
+  gauge!("usage_upstream_credits").set(upstream_credits as f64);
+}
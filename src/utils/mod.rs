@@ -1,8 +1,45 @@
+pub mod address;
+pub mod api_version;
+pub mod audit;
 pub mod authenticate_request;
+pub mod binary_format;
+pub mod body_limit;
+pub mod cache;
+pub mod casing;
+pub mod circuit_breaker;
+pub mod client;
+pub mod csv;
 pub mod custom_response;
 pub mod date;
+pub mod fields;
+pub mod idempotency;
+pub mod json;
+pub mod links;
+pub mod maintenance;
+pub mod merge_patch;
+pub mod metrics;
 pub mod models;
+pub mod multipart;
+pub mod ndjson;
 pub mod pagination;
+pub mod proxy;
+pub mod query;
+pub mod query_fields;
+pub mod rate_limit;
+pub mod redact;
+pub mod request_id;
 pub mod request_query;
+pub mod response_format;
+pub mod retry;
+pub mod route_table;
+pub mod secret;
+pub mod serde_helpers;
+pub mod shutdown;
+pub mod telemetry;
+pub mod timeout;
 pub mod to_object_id;
 pub mod token;
+pub mod totp;
+pub mod usage;
+pub mod validation;
+pub mod version;
@@ -1,12 +1,20 @@
 use async_trait::async_trait;
-use futures::stream::TryStreamExt;
+use futures::stream::{self, BoxStream, StreamExt, TryStreamExt};
+use lazy_static::lazy_static;
 use serde::{de::DeserializeOwned, ser::Serialize};
+use sha2::{Digest, Sha256};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, OnceCell};
+use tracing::{debug, debug_span, field, Instrument};
 use validator::Validate;
 use wither::bson::doc;
 use wither::bson::from_bson;
 use wither::bson::Bson;
 use wither::bson::Document;
 use wither::bson::{self, oid::ObjectId};
+use wither::mongodb::error::{TRANSIENT_TRANSACTION_ERROR, UNKNOWN_TRANSACTION_COMMIT_RESULT};
 use wither::mongodb::options::FindOneAndUpdateOptions;
 use wither::mongodb::options::FindOneOptions;
 use wither::mongodb::options::FindOptions;
@@ -14,11 +22,28 @@ use wither::mongodb::options::ReturnDocument;
 use wither::mongodb::options::UpdateOptions;
 use wither::mongodb::results::DeleteResult;
 use wither::mongodb::results::UpdateResult;
+use wither::mongodb::ClientSession;
 use wither::Model as WitherModel;
-use wither::ModelCursor;
+use wither::WitherError;
 
 use crate::database::CONNECTION;
 use crate::errors::Error;
+use crate::models::audit_log::{AuditAction, AuditLog};
+use crate::settings::SETTINGS;
+use crate::utils::audit;
+use crate::utils::cache::{self, Cache};
+use crate::utils::date;
+use crate::utils::metrics;
+
+tokio::task_local! {
+  static SESSION: Arc<Mutex<ClientSession>>;
+}
+
+// Attempts of a transaction, or of its commit, failing with a transient
+// error, e.g. a write conflict with a concurrent transaction.
+const TRANSACTION_ATTEMPTS: u32 = 3;
+
+static SUPPORTS_TRANSACTIONS: OnceCell<bool> = OnceCell::const_new();
 
 // This is the Model trait. All models that have a MongoDB collection should
 // implement this and therefore inherit theses methods.
@@ -26,19 +51,154 @@ use crate::errors::Error;
 pub trait ModelExt {
   type T: WitherModel + Send + Validate;
 
+  /// Whether the model is soft deleted, see `ModelExt::soft_delete`. Soft
+  /// deleted documents are excluded from the queries of this trait, except
+  /// for deletes and aggregations.
+  const SOFT_DELETE: bool = false;
+
+  /// Excludes soft deleted documents from a query, unless it already filters
+  /// on `deleted_at` (e.g. to restore a document).
+  fn exclude_deleted(mut query: Document) -> Document {
+    if Self::SOFT_DELETE && !query.contains_key("deleted_at") {
+      query.insert("deleted_at", Bson::Null);
+    }
+    query
+  }
+
+  /// Whether the changes of the model are recorded in the audit logs. Only
+  /// changes made by API requests are recorded, see `utils::audit`.
+  const AUDITED: bool = true;
+
+  fn is_audited() -> bool {
+    Self::AUDITED && audit::is_recording()
+  }
+
+  /// Whether the documents carry a `version` incremented by every update of
+  /// this trait, for optimistic concurrency control. Clients send the
+  /// version they read in `If-Match`, see `ModelExt::update_versioned`.
+  const VERSIONED: bool = false;
+
+  /// Increments the version of versioned documents along with an update,
+  /// unless the update sets the version itself.
+  fn with_version(mut update: Document) -> Document {
+    let sets_version = update.values().any(|fields| match fields {
+      Bson::Document(fields) => fields.contains_key("version"),
+      _ => false,
+    });
+    if !Self::VERSIONED || sets_version {
+      return update;
+    }
+
+    match update.get_document_mut("$inc") {
+      Ok(increments) => {
+        increments.insert("version", 1_i64);
+      }
+      Err(_) => {
+        update.insert("$inc", doc! { "version": 1_i64 });
+      }
+    }
+    update
+  }
+
   async fn create(mut model: Self::T) -> Result<Self::T, Error> {
     let connection = CONNECTION.get().await;
     model.validate().map_err(|_error| Error::bad_request())?;
-    model.save(connection, None).await.map_err(Error::Wither)?;
+    match transaction_session() {
+      Some(session) => {
+        let document = model.document_from_instance().map_err(Error::Wither)?;
+        let collection = Self::T::collection(connection);
+        let mut session = session.lock().await;
+        let result = traced::<Self::T, _, _>(
+          "create",
+          collection.insert_one_with_session(document, None, &mut session),
+        )
+        .await
+        .map_err(Error::Mongo)?;
+        if let Bson::ObjectId(id) = result.inserted_id {
+          model.set_id(id);
+        }
+      }
+      None => {
+        traced::<Self::T, _, _>("create", model.save(connection, None))
+          .await
+          .map_err(Error::Wither)?;
+      }
+    }
+
+    if Self::is_audited() {
+      let after = model.document_from_instance().ok();
+      audit::record(
+        Self::T::COLLECTION_NAME,
+        AuditAction::Create,
+        vec![(None, after)],
+      )
+      .await;
+    }
 
     Ok(model)
   }
 
-  async fn find_by_id(id: &ObjectId) -> Result<Option<Self::T>, Error> {
+  /// Validates and inserts the models in a single round trip, returning the
+  /// number of inserted documents.
+  async fn insert_many(models: Vec<Self::T>) -> Result<u64, Error> {
+    let models = Self::create_many(models).await?;
+    Ok(models.len() as u64)
+  }
+
+  /// Validates and inserts the models in a single round trip, returning
+  /// them with their ids set, in the given order.
+  async fn create_many(mut models: Vec<Self::T>) -> Result<Vec<Self::T>, Error> {
+    if models.is_empty() {
+      return Ok(models);
+    }
+
     let connection = CONNECTION.get().await;
-    Self::T::find_one(connection, doc! { "_id": id }, None)
-      .await
-      .map_err(Error::Wither)
+    let documents = models
+      .iter()
+      .map(|model| {
+        model.validate().map_err(|_error| Error::bad_request())?;
+        model.document_from_instance().map_err(Error::Wither)
+      })
+      .collect::<Result<Vec<Document>, Error>>()?;
+
+    let collection = Self::T::collection(connection);
+    let result = match transaction_session() {
+      Some(session) => {
+        let mut session = session.lock().await;
+        traced::<Self::T, _, _>(
+          "create_many",
+          collection.insert_many_with_session(documents.clone(), None, &mut session),
+        )
+        .await
+      }
+      None => {
+        traced::<Self::T, _, _>(
+          "create_many",
+          collection.insert_many(documents.clone(), None),
+        )
+        .await
+      }
+    }
+    .map_err(Error::Mongo)?;
+
+    let mut created = Vec::with_capacity(documents.len());
+    for (index, mut document) in documents.into_iter().enumerate() {
+      if let Some(Bson::ObjectId(id)) = result.inserted_ids.get(&index) {
+        models[index].set_id(*id);
+        document.insert("_id", *id);
+      }
+      created.push((None, Some(document)));
+    }
+
+    if Self::is_audited() {
+      audit::record(Self::T::COLLECTION_NAME, AuditAction::Create, created).await;
+    }
+
+    Ok(models)
+  }
+
+  async fn find_by_id(id: &ObjectId) -> Result<Option<Self::T>, Error> {
+    Self::find_one(doc! { "_id": id }, None).await
   }
 
   async fn find_one<O>(query: Document, options: O) -> Result<Option<Self::T>, Error>
@@ -46,9 +206,25 @@ pub trait ModelExt {
     O: Into<Option<FindOneOptions>> + Send,
   {
     let connection = CONNECTION.get().await;
-    Self::T::find_one(connection, query, options)
-      .await
-      .map_err(Error::Wither)
+    let query = Self::exclude_deleted(query);
+    match transaction_session() {
+      Some(session) => {
+        let collection = Self::T::collection(connection);
+        let mut session = session.lock().await;
+        traced::<Self::T, _, _>(
+          "find_one",
+          collection.find_one_with_session(query, options, &mut session),
+        )
+        .await
+        .map_err(Error::Mongo)?
+        .map(Self::T::instance_from_document)
+        .transpose()
+        .map_err(Error::Wither)
+      }
+      None => traced::<Self::T, _, _>("find_one", Self::T::find_one(connection, query, options))
+        .await
+        .map_err(Error::Wither),
+    }
   }
 
   async fn find<O>(query: Document, options: O) -> Result<Vec<Self::T>, Error>
@@ -56,6 +232,12 @@ pub trait ModelExt {
     O: Into<Option<FindOptions>> + Send,
   {
     let connection = CONNECTION.get().await;
+    let query = Self::exclude_deleted(query);
+    if let Some(session) = transaction_session() {
+      let documents = find_in_transaction::<Self::T>(&session, query, options.into()).await?;
+      return to_models::<Self::T>(documents);
+    }
+
     Self::T::find(connection, query, options)
       .await
       .map_err(Error::Wither)?
@@ -65,34 +247,158 @@ pub trait ModelExt {
   }
 
   async fn find_and_count<O>(query: Document, options: O) -> Result<(Vec<Self::T>, u64), Error>
+  where
+    O: Into<Option<FindOptions>> + Send,
+  {
+    Self::find_and_count_with(query, options, CountStrategy::Exact).await
+  }
+
+  /// Like `find_and_count`, finding the documents after a pagination cursor,
+  /// see `find_page_and_count_with`.
+  async fn find_page_and_count<O>(
+    query: Document,
+    after: Option<Document>,
+    options: O,
+  ) -> Result<(Vec<Self::T>, u64), Error>
+  where
+    O: Into<Option<FindOptions>> + Send,
+  {
+    Self::find_page_and_count_with(query, after, options, CountStrategy::Exact).await
+  }
+
+  /// Like `find_and_count`, counting with `strategy` instead of an exact
+  /// count of every matching document.
+  async fn find_and_count_with<O>(
+    query: Document,
+    options: O,
+    strategy: CountStrategy,
+  ) -> Result<(Vec<Self::T>, u64), Error>
+  where
+    O: Into<Option<FindOptions>> + Send,
+  {
+    Self::find_page_and_count_with(query, None, options, strategy).await
+  }
+
+  /// Like `find_and_count_with`, finding the documents after a pagination
+  /// cursor, see `PaginationBuilder::after_cursor`. The count leaves the
+  /// cursor out, so it's the same on every page.
+  async fn find_page_and_count_with<O>(
+    query: Document,
+    after: Option<Document>,
+    options: O,
+    strategy: CountStrategy,
+  ) -> Result<(Vec<Self::T>, u64), Error>
   where
     O: Into<Option<FindOptions>> + Send,
   {
     let connection = CONNECTION.get().await;
+    let query = Self::exclude_deleted(query);
 
-    let count = Self::T::collection(connection)
-      .count_documents(query.clone(), None)
-      .await
-      .map_err(Error::Mongo)?;
+    traced::<Self::T, _, _>("find_and_count", async move {
+      let count = count_with::<Self::T>(&query, strategy).await?;
 
-    let items = Self::T::find(connection, query, options.into())
-      .await
-      .map_err(Error::Wither)?
-      .try_collect::<Vec<Self::T>>()
-      .await
-      .map_err(Error::Wither)?;
+      let query = page_query(query, after);
+      let items = match transaction_session() {
+        Some(session) => {
+          let documents = find_in_transaction::<Self::T>(&session, query, options.into()).await?;
+          to_models::<Self::T>(documents)?
+        }
+        None => Self::T::find(connection, query, options.into())
+          .await
+          .map_err(Error::Wither)?
+          .try_collect::<Vec<Self::T>>()
+          .await
+          .map_err(Error::Wither)?,
+      };
 
-    Ok((items, count))
+      Ok((items, count))
+    })
+    .await
   }
 
-  async fn cursor<O>(query: Document, options: O) -> Result<ModelCursor<Self::T>, Error>
+  /// Like `find_and_count`, but returns the raw documents so options can set
+  /// a projection (see `Fields`), since projected documents can't be
+  /// deserialized into the model.
+  async fn find_documents_and_count<O>(
+    query: Document,
+    options: O,
+  ) -> Result<(Vec<Document>, u64), Error>
+  where
+    O: Into<Option<FindOptions>> + Send,
+  {
+    Self::find_documents_and_count_with(query, options, CountStrategy::Exact).await
+  }
+
+  /// Like `find_documents_and_count`, counting with `strategy`.
+  async fn find_documents_and_count_with<O>(
+    query: Document,
+    options: O,
+    strategy: CountStrategy,
+  ) -> Result<(Vec<Document>, u64), Error>
+  where
+    O: Into<Option<FindOptions>> + Send,
+  {
+    Self::find_documents_page_and_count_with(query, None, options, strategy).await
+  }
+
+  /// Like `find_page_and_count_with`, returning the raw documents.
+  async fn find_documents_page_and_count_with<O>(
+    query: Document,
+    after: Option<Document>,
+    options: O,
+    strategy: CountStrategy,
+  ) -> Result<(Vec<Document>, u64), Error>
   where
     O: Into<Option<FindOptions>> + Send,
   {
     let connection = CONNECTION.get().await;
-    Self::T::find(connection, query, options)
+    let collection = Self::T::collection(connection);
+    let query = Self::exclude_deleted(query);
+
+    traced::<Self::T, _, _>("find_documents_and_count", async move {
+      let count = count_with::<Self::T>(&query, strategy).await?;
+
+      let query = page_query(query, after);
+
+      let documents = match transaction_session() {
+        Some(session) => find_in_transaction::<Self::T>(&session, query, options.into()).await?,
+        None => collection
+          .find(query, options)
+          .await
+          .map_err(Error::Mongo)?
+          .try_collect::<Vec<Document>>()
+          .await
+          .map_err(Error::Mongo)?,
+      };
+
+      Ok((documents, count))
+    })
+    .await
+  }
+
+  /// Streams the models matching a query as they are read. In a
+  /// transaction, they are read upfront instead, so the session is free for
+  /// the changes made while going through them.
+  async fn cursor<O>(
+    query: Document,
+    options: O,
+  ) -> Result<BoxStream<'static, Result<Self::T, Error>>, Error>
+  where
+    Self::T: 'static,
+    O: Into<Option<FindOptions>> + Send,
+  {
+    let connection = CONNECTION.get().await;
+    let query = Self::exclude_deleted(query);
+    if let Some(session) = transaction_session() {
+      let documents = find_in_transaction::<Self::T>(&session, query, options.into()).await?;
+      let models = to_models::<Self::T>(documents)?;
+      return Ok(stream::iter(models.into_iter().map(Ok)).boxed());
+    }
+
+    let cursor = Self::T::find(connection, query, options)
       .await
-      .map_err(Error::Wither)
+      .map_err(Error::Wither)?;
+    Ok(cursor.map_err(Error::Wither).boxed())
   }
 
   async fn find_one_and_update(
@@ -104,9 +410,44 @@ pub trait ModelExt {
       .return_document(ReturnDocument::After)
       .build();
 
-    Self::T::find_one_and_update(connection, query, update, options)
+    let query = Self::exclude_deleted(query);
+    let update = Self::with_version(update);
+    let before = if Self::is_audited() {
+      find_documents::<Self::T>(query.clone(), Some(1))
+        .await?
+        .pop()
+    } else {
+      None
+    };
+
+    let model = match transaction_session() {
+      Some(session) => {
+        let collection = Self::T::collection(connection);
+        let mut session = session.lock().await;
+        traced::<Self::T, _, _>(
+          "find_one_and_update",
+          collection.find_one_and_update_with_session(query, update, options, &mut session),
+        )
+        .await
+        .map_err(Error::Mongo)?
+        .map(Self::T::instance_from_document)
+        .transpose()
+        .map_err(Error::Wither)?
+      }
+      None => traced::<Self::T, _, _>(
+        "find_one_and_update",
+        Self::T::find_one_and_update(connection, query, update, options),
+      )
       .await
-      .map_err(Error::Wither)
+      .map_err(Error::Wither)?,
+    };
+
+    if let (Some(before), Some(model)) = (before, model.as_ref()) {
+      let after = model.document_from_instance().map_err(Error::Wither)?;
+      record_updates::<Self::T>(vec![before], vec![after]).await;
+    }
+
+    Ok(model)
   }
 
   async fn update_one<O>(
@@ -118,10 +459,32 @@ pub trait ModelExt {
     O: Into<Option<UpdateOptions>> + Send,
   {
     let connection = CONNECTION.get().await;
-    Self::T::collection(connection)
-      .update_one(query, update, options)
-      .await
-      .map_err(Error::Mongo)
+    let query = Self::exclude_deleted(query);
+    let update = Self::with_version(update);
+    let before = if Self::is_audited() {
+      find_documents::<Self::T>(query.clone(), Some(1)).await?
+    } else {
+      vec![]
+    };
+
+    let collection = Self::T::collection(connection);
+    let result = match transaction_session() {
+      Some(session) => {
+        let mut session = session.lock().await;
+        collection
+          .update_one_with_session(query, update, options, &mut session)
+          .await
+      }
+      None => collection.update_one(query, update, options).await,
+    }
+    .map_err(Error::Mongo)?;
+
+    if !before.is_empty() && result.modified_count > 0 {
+      let after = find_documents::<Self::T>(ids_query(&before), None).await?;
+      record_updates::<Self::T>(before, after).await;
+    }
+
+    Ok(result)
   }
 
   async fn update_many<O>(
@@ -133,42 +496,181 @@ pub trait ModelExt {
     O: Into<Option<UpdateOptions>> + Send,
   {
     let connection = CONNECTION.get().await;
-    Self::T::collection(connection)
-      .update_many(query, update, options)
-      .await
-      .map_err(Error::Mongo)
+    let query = Self::exclude_deleted(query);
+    let update = Self::with_version(update);
+    let before = if Self::is_audited() {
+      find_documents::<Self::T>(query.clone(), None).await?
+    } else {
+      vec![]
+    };
+
+    let collection = Self::T::collection(connection);
+    let result = match transaction_session() {
+      Some(session) => {
+        let mut session = session.lock().await;
+        collection
+          .update_many_with_session(query, update, options, &mut session)
+          .await
+      }
+      None => collection.update_many(query, update, options).await,
+    }
+    .map_err(Error::Mongo)?;
+
+    if !before.is_empty() && result.modified_count > 0 {
+      let after = find_documents::<Self::T>(ids_query(&before), None).await?;
+      record_updates::<Self::T>(before, after).await;
+    }
+
+    Ok(result)
+  }
+
+  /// Updates the first document matching the query if it is still at
+  /// `version`. Fails with a 409 when another update changed it since, and
+  /// returns `None` when no document matches the query. Only for models
+  /// with `VERSIONED`.
+  async fn update_versioned(
+    query: Document,
+    version: i64,
+    update: Document,
+  ) -> Result<Option<Self::T>, Error> {
+    debug_assert!(Self::VERSIONED, "Model is not versioned");
+    let mut versioned_query = query.clone();
+    versioned_query.insert("version", version);
+    if let Some(model) = Self::find_one_and_update(versioned_query, update).await? {
+      return Ok(Some(model));
+    }
+
+    // Tell apart a missing document from a document updated by another
+    // request.
+    if Self::exists(query).await? {
+      return Err(Error::conflict());
+    }
+
+    Ok(None)
   }
 
-  async fn delete_many(query: Document) -> Result<DeleteResult, Error> {
+  /// Soft deletes the first document matching the query, returning it.
+  /// Only for models with `SOFT_DELETE`.
+  async fn soft_delete(query: Document) -> Result<Option<Self::T>, Error> {
+    debug_assert!(Self::SOFT_DELETE, "Model is not soft deleted");
+    Self::find_one_and_update(query, doc! { "$set": { "deleted_at": date::now() } }).await
+  }
+
+  /// Soft deletes the documents matching the query, returning their count.
+  async fn soft_delete_many(query: Document) -> Result<u64, Error> {
+    debug_assert!(Self::SOFT_DELETE, "Model is not soft deleted");
+    let update = doc! { "$set": { "deleted_at": date::now() } };
+    let result = Self::update_many(query, update, None).await?;
+    Ok(result.modified_count)
+  }
+
+  /// Restores the first soft deleted document matching the query, returning
+  /// it.
+  async fn restore(mut query: Document) -> Result<Option<Self::T>, Error> {
+    query.insert("deleted_at", doc! { "$ne": Bson::Null });
+    Self::find_one_and_update(query, doc! { "$set": { "deleted_at": Bson::Null } }).await
+  }
+
+  /// Deletes the documents matching the query, soft deleted ones included.
+  async fn delete_many(query: Document) -> Result<u64, Error> {
     let connection = CONNECTION.get().await;
-    Self::T::delete_many(connection, query, None)
-      .await
-      .map_err(Error::Wither)
+    let before = if Self::is_audited() {
+      find_documents::<Self::T>(query.clone(), None).await?
+    } else {
+      vec![]
+    };
+
+    let deleted_count = match transaction_session() {
+      Some(session) => {
+        let mut session = session.lock().await;
+        Self::T::collection(connection)
+          .delete_many_with_session(query, None, &mut session)
+          .await
+          .map_err(Error::Mongo)?
+          .deleted_count
+      }
+      None => Self::T::delete_many(connection, query, None)
+        .await
+        .map(|result| result.deleted_count)
+        .map_err(Error::Wither)?,
+    };
+
+    if !before.is_empty() && deleted_count > 0 {
+      record_deletes::<Self::T>(before).await;
+    }
+
+    Ok(deleted_count)
   }
 
   async fn delete_one(query: Document) -> Result<DeleteResult, Error> {
     let connection = CONNECTION.get().await;
-    Self::T::collection(connection)
-      .delete_one(query, None)
-      .await
-      .map_err(Error::Mongo)
+    let before = if Self::is_audited() {
+      find_documents::<Self::T>(query.clone(), Some(1)).await?
+    } else {
+      vec![]
+    };
+
+    let collection = Self::T::collection(connection);
+    let result = match transaction_session() {
+      Some(session) => {
+        let mut session = session.lock().await;
+        traced::<Self::T, _, _>(
+          "delete_one",
+          collection.delete_one_with_session(query, None, &mut session),
+        )
+        .await
+      }
+      None => traced::<Self::T, _, _>("delete_one", collection.delete_one(query, None)).await,
+    }
+    .map_err(Error::Mongo)?;
+
+    if !before.is_empty() && result.deleted_count > 0 {
+      record_deletes::<Self::T>(before).await;
+    }
+
+    Ok(result)
   }
 
-  async fn count(query: Document) -> Result<u64, Error> {
+  async fn find_one_and_delete(query: Document) -> Result<Option<Self::T>, Error> {
     let connection = CONNECTION.get().await;
-    Self::T::collection(connection)
-      .count_documents(query, None)
-      .await
-      .map_err(Error::Mongo)
+    let collection = Self::T::collection(connection);
+    let model = match transaction_session() {
+      Some(session) => {
+        let mut session = session.lock().await;
+        collection
+          .find_one_and_delete_with_session(query, None, &mut session)
+          .await
+      }
+      None => collection.find_one_and_delete(query, None).await,
+    }
+    .map_err(Error::Mongo)?;
+
+    if let (true, Some(model)) = (Self::is_audited(), model.as_ref()) {
+      let before = model.document_from_instance().map_err(Error::Wither)?;
+      record_deletes::<Self::T>(vec![before]).await;
+    }
+
+    Ok(model)
   }
 
-  async fn exists(query: Document) -> Result<bool, Error> {
+  async fn count(query: Document) -> Result<u64, Error> {
     let connection = CONNECTION.get().await;
-    let count = Self::T::collection(connection)
-      .count_documents(query, None)
-      .await
-      .map_err(Error::Mongo)?;
+    let collection = Self::T::collection(connection);
+    let query = Self::exclude_deleted(query);
+    match transaction_session() {
+      Some(session) => {
+        let mut session = session.lock().await;
+        collection
+          .count_documents_with_session(query, None, &mut session)
+          .await
+      }
+      None => collection.count_documents(query, None).await,
+    }
+    .map_err(Error::Mongo)
+  }
 
+  async fn exists(query: Document) -> Result<bool, Error> {
+    let count = Self::count(query).await?;
     Ok(count > 0)
   }
 
@@ -177,14 +679,28 @@ pub trait ModelExt {
     A: Serialize + DeserializeOwned,
   {
     let connection = CONNECTION.get().await;
+    let collection = Self::T::collection(connection);
 
-    let documents = Self::T::collection(connection)
-      .aggregate(pipeline, None)
-      .await
-      .map_err(Error::Mongo)?
-      .try_collect::<Vec<Document>>()
-      .await
-      .map_err(Error::Mongo)?;
+    let documents = match transaction_session() {
+      Some(session) => {
+        let mut session = session.lock().await;
+        collection
+          .aggregate_with_session(pipeline, None, &mut session)
+          .await
+          .map_err(Error::Mongo)?
+          .stream(&mut session)
+          .try_collect::<Vec<Document>>()
+          .await
+          .map_err(Error::Mongo)?
+      }
+      None => collection
+        .aggregate(pipeline, None)
+        .await
+        .map_err(Error::Mongo)?
+        .try_collect::<Vec<Document>>()
+        .await
+        .map_err(Error::Mongo)?,
+    };
 
     let documents = documents
       .into_iter()
@@ -200,3 +716,297 @@ pub trait ModelExt {
     Self::T::sync(connection).await.map_err(Error::Wither)
   }
 }
+
+/// Runs `operation` in a MongoDB transaction: the changes it makes through
+/// `ModelExt`, audit logs included, are applied together when it succeeds
+/// and discarded when it fails, and its reads see them before then. The
+/// operation runs again when the transaction fails with a transient error,
+/// so it must be safe to repeat.
+///
+/// Transactions need a replica set or a sharded cluster, on a standalone
+/// server the operation runs without one. Nested calls join the outer
+/// transaction.
+pub async fn transaction<F, Fut, R>(mut operation: F) -> Result<R, Error>
+where
+  F: FnMut() -> Fut,
+  Fut: Future<Output = Result<R, Error>>,
+{
+  if transaction_session().is_some() || !supports_transactions().await? {
+    return operation().await;
+  }
+
+  let connection = CONNECTION.get().await;
+  let session = connection.client().start_session(None).await?;
+  let session = Arc::new(Mutex::new(session));
+
+  let mut attempt = 1;
+  loop {
+    session.lock().await.start_transaction(None).await?;
+    let result = SESSION.scope(session.clone(), operation()).await;
+
+    let mut guard = session.lock().await;
+    let err = match result {
+      Ok(value) => match commit(&mut guard).await {
+        Ok(()) => return Ok(value),
+        Err(err) => err,
+      },
+      Err(err) => {
+        // Fails when the server already aborted the transaction.
+        let _ = guard.abort_transaction().await;
+        err
+      }
+    };
+
+    if attempt >= TRANSACTION_ATTEMPTS || !has_label(&err, TRANSIENT_TRANSACTION_ERROR) {
+      return Err(err);
+    }
+    debug!("Transaction attempt {} failed: {}. Retrying", attempt, err);
+    attempt += 1;
+  }
+}
+
+/// Commits the transaction, again when the server may not have applied it.
+async fn commit(session: &mut ClientSession) -> Result<(), Error> {
+  let mut attempt = 1;
+  loop {
+    match session.commit_transaction().await {
+      Ok(()) => return Ok(()),
+      Err(err)
+        if attempt < TRANSACTION_ATTEMPTS
+          && err.contains_label(UNKNOWN_TRANSACTION_COMMIT_RESULT) =>
+      {
+        attempt += 1;
+      }
+      Err(err) => return Err(Error::Mongo(err)),
+    }
+  }
+}
+
+/// Session of the transaction the current task runs in, see `transaction`.
+fn transaction_session() -> Option<Arc<Mutex<ClientSession>>> {
+  SESSION.try_with(Arc::clone).ok()
+}
+
+/// Whether the server is a replica set member or a `mongos` router, asked
+/// once.
+async fn supports_transactions() -> Result<bool, Error> {
+  let supported = SUPPORTS_TRANSACTIONS
+    .get_or_try_init(|| async {
+      let connection = CONNECTION.get().await;
+      let hello = connection.run_command(doc! { "hello": 1 }, None).await?;
+      let supported = hello.contains_key("setName") || hello.get_str("msg") == Ok("isdbgrid");
+      Ok::<bool, Error>(supported)
+    })
+    .await?;
+
+  Ok(*supported)
+}
+
+fn has_label(err: &Error, label: &str) -> bool {
+  match err {
+    Error::Mongo(err) | Error::Wither(WitherError::Mongo(err)) => err.contains_label(label),
+    _ => false,
+  }
+}
+
+/// Raw documents matching a query, the versions of changed documents
+/// recorded in the audit logs.
+async fn find_documents<M>(query: Document, limit: Option<i64>) -> Result<Vec<Document>, Error>
+where
+  M: WitherModel,
+{
+  let connection = CONNECTION.get().await;
+  let options = FindOptions::builder().limit(limit).build();
+  if let Some(session) = transaction_session() {
+    return find_in_transaction::<M>(&session, query, options).await;
+  }
+
+  M::collection(connection)
+    .find(query, options)
+    .await
+    .map_err(Error::Mongo)?
+    .try_collect::<Vec<Document>>()
+    .await
+    .map_err(Error::Mongo)
+}
+
+/// Query of a page, the documents matching `query` after the cursor filter
+/// `after`.
+fn page_query(query: Document, after: Option<Document>) -> Document {
+  match after {
+    Some(after) => doc! { "$and": [query, after] },
+    None => query,
+  }
+}
+
+/// Raw documents matching a query, read in the transaction of the current
+/// task so its own changes are seen.
+async fn find_in_transaction<M>(
+  session: &Mutex<ClientSession>,
+  query: Document,
+  options: Option<FindOptions>,
+) -> Result<Vec<Document>, Error>
+where
+  M: WitherModel,
+{
+  let collection = M::collection(CONNECTION.get().await);
+  let mut session = session.lock().await;
+  collection
+    .find_with_session(query, options, &mut session)
+    .await
+    .map_err(Error::Mongo)?
+    .stream(&mut session)
+    .try_collect::<Vec<Document>>()
+    .await
+    .map_err(Error::Mongo)
+}
+
+fn to_models<M>(documents: Vec<Document>) -> Result<Vec<M>, Error>
+where
+  M: WitherModel,
+{
+  documents
+    .into_iter()
+    .map(M::instance_from_document)
+    .collect::<Result<Vec<M>, WitherError>>()
+    .map_err(Error::Wither)
+}
+
+// Query matching the given documents by id.
+fn ids_query(documents: &[Document]) -> Document {
+  let ids = documents
+    .iter()
+    .filter_map(|document| document.get("_id").cloned())
+    .collect::<Vec<Bson>>();
+  doc! { "_id": { "$in": ids } }
+}
+
+/// Records the changed fields of updated documents, pairing both versions by
+/// id.
+async fn record_updates<M>(before: Vec<Document>, after: Vec<Document>)
+where
+  M: WitherModel,
+{
+  let changes = before
+    .iter()
+    .filter_map(|before| {
+      let after = after
+        .iter()
+        .find(|after| after.get("_id") == before.get("_id"))?;
+      AuditLog::diff(before, after)
+    })
+    .map(|(before, after)| (Some(before), Some(after)))
+    .collect::<Vec<_>>();
+
+  if !changes.is_empty() {
+    audit::record(M::COLLECTION_NAME, AuditAction::Update, changes).await;
+  }
+}
+
+async fn record_deletes<M>(before: Vec<Document>)
+where
+  M: WitherModel,
+{
+  let changes = before
+    .into_iter()
+    .map(|before| (Some(before), None))
+    .collect();
+  audit::record(M::COLLECTION_NAME, AuditAction::Delete, changes).await;
+}
+
+/// How `ModelExt::find_and_count_with` counts the documents matching a
+/// query. Exact counts scan every matching document, which is slow on large
+/// collections, so routes listing them pick a cheaper strategy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CountStrategy {
+  #[default]
+  Exact,
+  /// Count from the collection metadata, without scanning. Only applies to
+  /// unfiltered queries, filtered ones are counted exactly.
+  Estimated,
+  /// Exact count cached per filter for this long, so paging through the
+  /// same results counts once: pagination cursors are left out of the
+  /// filter, see `find_page_and_count_with`. Counts lag the writes until
+  /// they expire, a zero TTL counts exactly.
+  Cached(Duration),
+}
+
+lazy_static! {
+  static ref COUNT_CACHE: Arc<dyn Cache> = cache::backend(&SETTINGS.cache, "counts");
+}
+
+async fn count_with<M>(query: &Document, strategy: CountStrategy) -> Result<u64, Error>
+where
+  M: WitherModel,
+{
+  let collection = M::collection(CONNECTION.get().await);
+  // Counted exactly in a transaction, caches and estimates would miss its
+  // changes.
+  if let Some(session) = transaction_session() {
+    let mut session = session.lock().await;
+    return collection
+      .count_documents_with_session(query.clone(), None, &mut session)
+      .await
+      .map_err(Error::Mongo);
+  }
+
+  match strategy {
+    CountStrategy::Estimated if query.is_empty() => collection
+      .estimated_document_count(None)
+      .await
+      .map_err(Error::Mongo),
+    CountStrategy::Cached(ttl) if !ttl.is_zero() => {
+      let filter = bson::to_vec(query).map_err(|err| Error::General(err.to_string()))?;
+      let key = format!(
+        "{}:{}",
+        M::COLLECTION_NAME,
+        hex::encode(Sha256::digest(filter))
+      );
+      let cached = COUNT_CACHE
+        .get(&key)
+        .await
+        .and_then(|count| count.try_into().ok())
+        .map(u64::from_be_bytes);
+      if let Some(count) = cached {
+        return Ok(count);
+      }
+
+      let count = collection
+        .count_documents(query.clone(), None)
+        .await
+        .map_err(Error::Mongo)?;
+      COUNT_CACHE
+        .set_with_ttl(&key, count.to_be_bytes().to_vec(), ttl)
+        .await;
+      Ok(count)
+    }
+    _ => collection
+      .count_documents(query.clone(), None)
+      .await
+      .map_err(Error::Mongo),
+  }
+}
+
+/// Runs a MongoDB operation inside a debug span recording the collection,
+/// the operation and the elapsed time, also recorded in the metrics.
+async fn traced<M, F, R>(operation: &'static str, future: F) -> R
+where
+  M: WitherModel,
+  F: Future<Output = R>,
+{
+  let span = debug_span!(
+    "mongo",
+    collection = M::COLLECTION_NAME,
+    operation,
+    elapsed_ms = field::Empty
+  );
+
+  let started_at = Instant::now();
+  let result = future.instrument(span.clone()).await;
+  let elapsed = started_at.elapsed();
+
+  span.record("elapsed_ms", elapsed.as_millis() as u64);
+  metrics::record_mongo_operation(M::COLLECTION_NAME, operation, elapsed);
+
+  result
+}
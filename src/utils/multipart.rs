@@ -0,0 +1,28 @@
+use axum::extract::multipart::{Field, MultipartError, MultipartRejection};
+use bytes::Bytes;
+use futures::stream::{Stream, TryStreamExt};
+
+use crate::errors::Error;
+use crate::utils::body_limit;
+
+/// Content of a multipart field as it is read, failing with the API errors,
+/// e.g. a 413 once the body goes over the limit of its route.
+pub fn field_content(field: Field<'_>) -> impl Stream<Item = Result<Bytes, Error>> + '_ {
+  field.map_err(Error::from)
+}
+
+impl From<MultipartRejection> for Error {
+  fn from(rejection: MultipartRejection) -> Self {
+    // Missing or invalid `multipart/form-data` content type.
+    Error::MalformedPayload(rejection.body_text())
+  }
+}
+
+impl From<MultipartError> for Error {
+  fn from(err: MultipartError) -> Self {
+    match body_limit::too_large(&err) {
+      Some(limit) => Error::PayloadTooLarge(limit),
+      None => Error::MalformedPayload(err.body_text()),
+    }
+  }
+}
@@ -0,0 +1,52 @@
+use axum::{
+  body::StreamBody,
+  http::{header, HeaderValue},
+  response::{IntoResponse, Response},
+};
+use bytes::{BufMut, Bytes, BytesMut};
+use futures::stream::{Stream, StreamExt};
+use serde::Serialize;
+use tracing::error;
+
+use crate::errors::Error;
+
+pub const NDJSON_CONTENT_TYPE: &str = "application/x-ndjson";
+
+/// Streams the items of a cursor as NDJSON, one JSON document per line,
+/// converting them with `map` as they come. Nothing is buffered, so whole
+/// collections can be exported. The response is already sent when an error
+/// happens midway, so the body is cut instead and clients see a truncated
+/// export rather than a complete one.
+pub fn stream_ndjson<S, T, E, P, F>(cursor: S, map: F) -> Response
+where
+  S: Stream<Item = Result<T, E>> + Send + 'static,
+  E: Into<Error>,
+  P: Serialize,
+  F: Fn(T) -> P + Send + 'static,
+{
+  let lines = cursor.map(move |item| {
+    let item = item.map_err(Into::into).map(&map)?;
+    to_line(&item)
+  });
+  let lines = lines.inspect(|line| {
+    if let Err(err) = line {
+      error!("Failed to stream NDJSON export: {}", err);
+    }
+  });
+
+  let headers = [(
+    header::CONTENT_TYPE,
+    HeaderValue::from_static(NDJSON_CONTENT_TYPE),
+  )];
+  (headers, StreamBody::new(lines)).into_response()
+}
+
+fn to_line<P: Serialize>(item: &P) -> Result<Bytes, Error> {
+  let mut bytes = BytesMut::new().writer();
+  serde_json::to_writer(&mut bytes, item)
+    .map_err(|err| Error::General(format!("Failed to serialize item: {}", err)))?;
+
+  let mut bytes = bytes.into_inner();
+  bytes.put_u8(b'\n');
+  Ok(bytes.freeze())
+}
@@ -1,5 +1,9 @@
-use serde::Serialize;
+use axum::http::Uri;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use wither::bson::{doc, oid::ObjectId, Document};
 
+use crate::settings::SETTINGS;
+use crate::utils::date::Date;
 use crate::utils::request_query::RequestQuery;
 
 const LIMIT: u64 = 100;
@@ -10,31 +14,119 @@ pub struct Pagination {
   pub count: u64,
   pub offset: u64,
   pub limit: u64,
+  pub cursor: Option<Cursor>,
+  pub next_cursor: Option<Cursor>,
+  // URI of the request, the other pages are linked relative to it.
+  #[serde(skip)]
+  pub uri: Option<Uri>,
 }
 
 impl Pagination {
   pub fn build_from_request_query(query: RequestQuery) -> PaginationBuilder {
+    let max_limit = SETTINGS.pagination.max_limit.max(1);
+
     let limit = query
       .limit
-      // Make sure the requested limit is not greater than the maximum allowed
+      // Make sure the requested limit is between 1 and the maximum allowed
       // limit.
-      .map(|limit| std::cmp::min(limit, LIMIT))
-      .unwrap_or(LIMIT);
+      .map(|limit| limit.clamp(1, max_limit as i64) as u64)
+      .unwrap_or_else(|| LIMIT.min(max_limit));
 
-    let offset = query.offset.unwrap_or(OFFSET);
+    // Cursors replace the offset.
+    let offset = match query.cursor {
+      Some(_) => OFFSET,
+      None => query
+        .offset
+        .map(|offset| offset.max(0) as u64)
+        .unwrap_or(OFFSET),
+    };
 
     PaginationBuilder {
       count: None,
       offset,
       limit,
+      keyset: query.has_default_sort(),
+      cursor: query.cursor,
+      next_cursor: None,
+      uri: None,
+    }
+  }
+
+  /// Number of pages of `limit` items, zero for an empty collection.
+  pub fn total_pages(&self) -> u64 {
+    self.count.div_ceil(self.limit)
+  }
+
+  /// Whether there are items after the current page. False for offsets past
+  /// the end of the collection.
+  pub fn has_next(&self) -> bool {
+    match self.cursor {
+      // Counts cover every page, the position of a cursor is unknown.
+      Some(_) => self.next_cursor.is_some(),
+      None => self.offset.saturating_add(self.limit) < self.count,
+    }
+  }
+
+  /// Links to the other pages as `(rel, uri)` pairs, for the RFC 8288 `Link`
+  /// header. Pages are linked relative to the request URI, keeping its other
+  /// parameters. Pages resumed with a cursor only link to the first and next
+  /// ones, since cursors can't go back. Empty without a request URI.
+  pub fn links(&self) -> Vec<(&'static str, String)> {
+    let uri = match &self.uri {
+      Some(uri) => uri,
+      None => return Vec::new(),
+    };
+
+    let mut links = vec![("first", page_uri(uri, "offset", "0"))];
+    if self.cursor.is_some() {
+      if let Some(next_cursor) = &self.next_cursor {
+        links.push(("next", page_uri(uri, "cursor", &next_cursor.encode())));
+      }
+      return links;
+    }
+
+    let last_offset = self.total_pages().saturating_sub(1) * self.limit;
+    if self.offset > 0 {
+      let prev_offset = self.offset.saturating_sub(self.limit).min(last_offset);
+      links.push(("prev", page_uri(uri, "offset", &prev_offset.to_string())));
     }
+    if self.has_next() {
+      let next_offset = self.offset + self.limit;
+      links.push(("next", page_uri(uri, "offset", &next_offset.to_string())));
+    }
+    links.push(("last", page_uri(uri, "offset", &last_offset.to_string())));
+
+    links
   }
 }
 
+/// Path and query of `uri` with its position replaced by the given `offset`
+/// or `cursor` parameter.
+fn page_uri(uri: &Uri, param: &str, value: &str) -> String {
+  let mut params = uri
+    .query()
+    .unwrap_or_default()
+    .split('&')
+    .filter(|pair| {
+      let name = pair.split('=').next().unwrap_or_default();
+      !pair.is_empty() && name != "offset" && name != "cursor"
+    })
+    .collect::<Vec<&str>>();
+  let position = format!("{}={}", param, value);
+  params.push(&position);
+
+  format!("{}?{}", uri.path(), params.join("&"))
+}
+
 pub struct PaginationBuilder {
   pub count: Option<u64>,
   pub offset: u64,
   pub limit: u64,
+  // Whether items are sorted with `Cursor::sort`, so cursors can resume them.
+  pub keyset: bool,
+  pub cursor: Option<Cursor>,
+  pub next_cursor: Option<Cursor>,
+  pub uri: Option<Uri>,
 }
 
 impl Default for PaginationBuilder {
@@ -43,6 +135,10 @@ impl Default for PaginationBuilder {
       count: None,
       offset: OFFSET,
       limit: LIMIT,
+      keyset: true,
+      cursor: None,
+      next_cursor: None,
+      uri: None,
     }
   }
 }
@@ -53,11 +149,109 @@ impl PaginationBuilder {
     self
   }
 
+  /// URI of the request, so responses link to the other pages.
+  pub fn uri(mut self, uri: Uri) -> Self {
+    self.uri = Some(uri);
+    self
+  }
+
+  /// Filter of the items after the cursor, if any, for
+  /// `ModelExt::find_page_and_count_with`. Items must be sorted with
+  /// `Cursor::sort`. Counts leave it out, so they cover every page.
+  pub fn after_cursor(&self) -> Option<Document> {
+    self.cursor.as_ref().map(Cursor::filter)
+  }
+
+  /// Sets the cursor of the next page from the items of the current one.
+  /// Only full pages sorted newest first have a next cursor.
+  pub fn next_cursor<T, F>(mut self, items: &[T], cursor: F) -> Self
+  where
+    F: Fn(&T) -> Cursor,
+  {
+    if self.keyset && items.len() as u64 >= self.limit {
+      self.next_cursor = items.last().map(cursor);
+    }
+    self
+  }
+
   pub fn build(self) -> Pagination {
     Pagination {
       count: self.count.expect("Pagination count to be set"),
       offset: self.offset,
       limit: self.limit,
+      cursor: self.cursor,
+      next_cursor: self.next_cursor,
+      uri: self.uri,
+    }
+  }
+}
+
+/// Position of the last item of a page, for keyset pagination. Unlike
+/// offsets, cursors stay stable when items are inserted and don't make
+/// MongoDB skip over the previous pages. Sent to the clients as an opaque
+/// string.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cursor {
+  pub created_at: Date,
+  pub id: ObjectId,
+}
+
+impl Cursor {
+  pub fn new(created_at: Date, id: ObjectId) -> Self {
+    Self { created_at, id }
+  }
+
+  /// Sort of the collections paginated with cursors, newest first. The id
+  /// breaks ties between items created at the same millisecond.
+  pub fn sort() -> Document {
+    doc! { "created_at": -1_i32, "_id": -1_i32 }
+  }
+
+  /// Filter matching the items after the cursor.
+  pub fn filter(&self) -> Document {
+    doc! {
+      "$or": [
+        { "created_at": { "$lt": self.created_at } },
+        { "created_at": self.created_at, "_id": { "$lt": self.id } },
+      ]
     }
   }
+
+  pub fn encode(&self) -> String {
+    let cursor = format!(
+      "{}:{}",
+      self.created_at.timestamp_millis(),
+      self.id.to_hex()
+    );
+    hex::encode(cursor)
+  }
+
+  pub fn decode(cursor: &str) -> Option<Self> {
+    let cursor = String::from_utf8(hex::decode(cursor).ok()?).ok()?;
+    let (millis, id) = cursor.split_once(':')?;
+
+    Some(Self {
+      created_at: Date::from_millis(millis.parse().ok()?),
+      id: ObjectId::parse_str(id).ok()?,
+    })
+  }
+}
+
+impl Serialize for Cursor {
+  fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+  where
+    S: Serializer,
+  {
+    serializer.serialize_str(&self.encode())
+  }
+}
+
+impl<'de> Deserialize<'de> for Cursor {
+  fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+  where
+    D: Deserializer<'de>,
+  {
+    let cursor = String::deserialize(deserializer)?;
+    Cursor::decode(&cursor).ok_or_else(|| serde::de::Error::custom("invalid cursor"))
+  }
 }
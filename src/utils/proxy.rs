@@ -0,0 +1,82 @@
+use axum::{
+  body::StreamBody,
+  http::{header, HeaderValue},
+  response::{IntoResponse, Response},
+};
+use bytes::{Bytes, BytesMut};
+use futures::stream::{self, Stream, StreamExt};
+use serde::Deserialize;
+use tracing::error;
+use utoipa::IntoParams;
+
+/// Query flag of the routes able to pass large upstream responses through.
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct RawQuery {
+  /// Streams the upstream response body as is, in the upstream's format,
+  /// instead of the API's.
+  #[serde(default)]
+  pub raw: bool,
+}
+
+/// Streams the body of a successful upstream response to the client as it is
+/// received, with its content type, instead of buffering and deserializing
+/// it. Chunks are regrouped by `chunk_bytes`, or forwarded as received when
+/// it is 0. The response is already sent when the upstream fails midway, so
+/// the body is cut instead, like `ndjson::stream_ndjson`.
+pub fn stream_response(
+  service: &'static str,
+  res: reqwest::Response,
+  chunk_bytes: usize,
+) -> Response {
+  let content_type = res
+    .headers()
+    .get(reqwest::header::CONTENT_TYPE)
+    .and_then(|value| HeaderValue::from_bytes(value.as_bytes()).ok())
+    .unwrap_or_else(|| HeaderValue::from_static("application/json"));
+  let headers = [(header::CONTENT_TYPE, content_type)];
+
+  let chunks = res.bytes_stream().inspect(move |chunk| {
+    if let Err(err) = chunk {
+      error!("Failed to stream {} response: {}", service, err);
+    }
+  });
+
+  if chunk_bytes == 0 {
+    return (headers, StreamBody::new(chunks)).into_response();
+  }
+  (headers, StreamBody::new(rechunk(chunks, chunk_bytes))).into_response()
+}
+
+/// Regroups the chunks of a body in chunks of `chunk_bytes`, the last one
+/// being shorter. The stream ends after the first error.
+pub fn rechunk<S, E>(chunks: S, chunk_bytes: usize) -> impl Stream<Item = Result<Bytes, E>>
+where
+  S: Stream<Item = Result<Bytes, E>>,
+{
+  let chunk_bytes = chunk_bytes.max(1);
+  let state = (Box::pin(chunks), BytesMut::new(), false);
+
+  stream::unfold(state, move |(mut chunks, mut buffer, done)| async move {
+    if done {
+      return None;
+    }
+
+    loop {
+      if buffer.len() >= chunk_bytes {
+        let chunk = buffer.split_to(chunk_bytes).freeze();
+        return Some((Ok(chunk), (chunks, buffer, false)));
+      }
+
+      match chunks.next().await {
+        Some(Ok(chunk)) => buffer.extend_from_slice(&chunk),
+        Some(Err(err)) => return Some((Err(err), (chunks, buffer, true))),
+        None if buffer.is_empty() => return None,
+        None => {
+          let chunk = buffer.split().freeze();
+          return Some((Ok(chunk), (chunks, buffer, true)));
+        }
+      }
+    }
+  })
+}
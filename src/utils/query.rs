@@ -0,0 +1,31 @@
+use axum::{
+  async_trait,
+  extract::{rejection::QueryRejection, FromRequestParts},
+  http::request::Parts,
+};
+use serde::de::DeserializeOwned;
+
+use crate::errors::Error;
+
+/// Drop-in replacement for `axum::extract::Query`. Query strings that can't
+/// be parsed (e.g. a non numeric `limit`) are rejected with the API error
+/// format.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Query<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequestParts<S> for Query<T>
+where
+  T: DeserializeOwned,
+  S: Send + Sync,
+{
+  type Rejection = Error;
+
+  async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+    let axum::extract::Query(value) = axum::extract::Query::<T>::from_request_parts(parts, state)
+      .await
+      .map_err(|rejection: QueryRejection| Error::InvalidQuery(rejection.body_text()))?;
+
+    Ok(Self(value))
+  }
+}
@@ -0,0 +1,186 @@
+use axum::{async_trait, extract::FromRequestParts, http::request::Parts};
+use chrono::{DateTime, Utc};
+use wither::bson::{Bson, Document};
+
+use crate::errors::Error;
+use crate::utils::address::normalize_evm_address;
+use crate::utils::date::Date;
+use crate::utils::query::Query;
+use crate::utils::to_object_id::to_object_id;
+
+// Query parameters of the filters, e.g. `filter[name]` or `filter[age][gt]`.
+const FILTER_PREFIX: &str = "filter[";
+
+const OPERATORS: &[&str] = &["eq", "ne", "gt", "gte", "lt", "lte", "in"];
+
+/// Type of a field clients can filter on. Query values are converted to it
+/// before ending up in a MongoDB filter, so they can't carry operators.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldType {
+  String,
+  Integer,
+  Boolean,
+  // RFC 3339 dates.
+  Date,
+  ObjectId,
+  // EVM addresses, lowercased like the stored ones.
+  Address,
+}
+
+/// Filters sent as `filter[field]=value`, or `filter[field][op]=value` with
+/// `op` one of `eq`, `ne`, `gt`, `gte`, `lt`, `lte` and `in` (comma
+/// separated values). Fields are checked against the whitelist of the model
+/// when applied, see `Filters::apply`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Filters(Vec<Filter>);
+
+#[derive(Debug, Clone, PartialEq)]
+struct Filter {
+  field: String,
+  operator: String,
+  value: String,
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for Filters
+where
+  S: Send + Sync,
+{
+  type Rejection = Error;
+
+  async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+    let Query(params) = Query::<Vec<(String, String)>>::from_request_parts(parts, state).await?;
+
+    params
+      .into_iter()
+      .filter(|(name, _)| name.starts_with(FILTER_PREFIX))
+      .map(|(name, value)| Filter::parse(&name, value))
+      .collect::<Result<Vec<_>, _>>()
+      .map(Self)
+  }
+}
+
+impl Filter {
+  fn parse(name: &str, value: String) -> Result<Self, Error> {
+    let invalid = || Error::InvalidQuery(format!("Invalid filter parameter `{}`", name));
+
+    // `field]` or `field][op]`.
+    let rest = name[FILTER_PREFIX.len()..]
+      .strip_suffix(']')
+      .ok_or_else(invalid)?;
+    let (field, operator) = match rest.split_once("][") {
+      Some((field, operator)) => (field, operator),
+      None => (rest, "eq"),
+    };
+    if field.is_empty() || field.contains(['[', ']']) {
+      return Err(invalid());
+    }
+    if !OPERATORS.contains(&operator) {
+      return Err(Error::InvalidQuery(format!(
+        "Unsupported filter operator `{}`, expected one of: {}",
+        operator,
+        OPERATORS.join(", ")
+      )));
+    }
+
+    Ok(Self {
+      field: field.to_owned(),
+      operator: operator.to_owned(),
+      value,
+    })
+  }
+}
+
+impl Filters {
+  pub fn is_empty(&self) -> bool {
+    self.0.is_empty()
+  }
+
+  /// Adds the filters to a query. Fields missing from `allowed` are rejected
+  /// with a 422 listing the allowed ones. Fields already in the query (e.g.
+  /// the ownership filter) are narrowed, never replaced.
+  pub fn apply(&self, allowed: &[(&str, FieldType)], query: &mut Document) -> Result<(), Error> {
+    let mut conditions = Document::new();
+    for filter in &self.0 {
+      let field_type = allowed
+        .iter()
+        .find(|(field, _)| *field == filter.field)
+        .map(|(_, field_type)| *field_type)
+        .ok_or_else(|| Error::UnsupportedField {
+          parameter: "filter",
+          field: filter.field.clone(),
+          allowed: allowed.iter().map(|(field, _)| field.to_string()).collect(),
+        })?;
+
+      let value = if filter.operator == "in" {
+        let values = filter
+          .value
+          .split(',')
+          .map(|value| coerce(&filter.field, field_type, value))
+          .collect::<Result<Vec<_>, _>>()?;
+        Bson::Array(values)
+      } else {
+        coerce(&filter.field, field_type, &filter.value)?
+      };
+
+      let operator = format!("${}", filter.operator);
+      match conditions.get_document_mut(&filter.field) {
+        Ok(condition) => {
+          condition.insert(operator, value);
+        }
+        Err(_) => {
+          let mut condition = Document::new();
+          condition.insert(operator, value);
+          conditions.insert(filter.field.clone(), condition);
+        }
+      }
+    }
+
+    for (field, condition) in conditions {
+      if query.contains_key(&field) {
+        let mut narrowed = Document::new();
+        narrowed.insert(field, condition);
+        and(query, narrowed);
+      } else {
+        query.insert(field, condition);
+      }
+    }
+
+    Ok(())
+  }
+}
+
+/// Converts a query value to the type of its field.
+fn coerce(field: &str, field_type: FieldType, value: &str) -> Result<Bson, Error> {
+  let value = value.trim();
+  let invalid = |expected: &str| {
+    Error::InvalidQuery(format!(
+      "Invalid value `{}` for filter `{}`, expected {}",
+      value, field, expected
+    ))
+  };
+
+  let value = match field_type {
+    FieldType::String => Bson::String(value.to_owned()),
+    FieldType::Integer => Bson::Int64(value.parse().map_err(|_| invalid("an integer"))?),
+    FieldType::Boolean => Bson::Boolean(value.parse().map_err(|_| invalid("true or false"))?),
+    FieldType::Date => {
+      let date = DateTime::parse_from_rfc3339(value).map_err(|_| invalid("an RFC 3339 date"))?;
+      Bson::DateTime(Date::from_chrono(date.with_timezone(&Utc)))
+    }
+    FieldType::ObjectId => Bson::ObjectId(to_object_id(value)?),
+    FieldType::Address => Bson::String(normalize_evm_address(value)?),
+  };
+
+  Ok(value)
+}
+
+/// Appends a condition to the `$and` of a query.
+fn and(query: &mut Document, condition: Document) {
+  match query.get_array_mut("$and") {
+    Ok(conditions) => conditions.push(Bson::Document(condition)),
+    Err(_) => {
+      query.insert("$and", vec![condition]);
+    }
+  }
+}
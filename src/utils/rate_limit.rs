@@ -0,0 +1,226 @@
+use axum::{
+  extract::{FromRequestParts, MatchedPath, State},
+  http::{header, HeaderMap, HeaderName, HeaderValue, Request},
+  middleware::Next,
+  response::{IntoResponse, Response},
+};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tracing::debug;
+
+use crate::errors::Error;
+use crate::settings;
+use crate::state::AppState;
+use crate::utils::client::client_ip;
+use crate::utils::token::TokenUser;
+
+const LIMIT_HEADER: &str = "x-ratelimit-limit";
+const REMAINING_HEADER: &str = "x-ratelimit-remaining";
+const RESET_HEADER: &str = "x-ratelimit-reset";
+
+// Once this many buckets are tracked, full buckets are dropped since they are
+// equivalent to not having a bucket at all.
+const PRUNE_THRESHOLD: usize = 10_000;
+
+/// Token bucket rate limiter keyed by route and user, or by route and client
+/// IP for anonymous requests. Every route has a capacity of requests that
+/// refills at a fixed rate, routes without a specific limit use the default
+/// one.
+pub struct RateLimiter {
+  default_limit: settings::RouteRateLimit,
+  routes: HashMap<String, settings::RouteRateLimit>,
+  anonymous: settings::AnonymousRateLimit,
+  buckets: Mutex<HashMap<(String, String), Bucket>>,
+}
+
+struct Bucket {
+  tokens: f64,
+  capacity: f64,
+  refill_per_sec: f64,
+  updated_at: Instant,
+}
+
+impl Bucket {
+  fn refill(&mut self, now: Instant) {
+    let elapsed = now.duration_since(self.updated_at).as_secs_f64();
+    self.tokens = (self.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+    self.updated_at = now;
+  }
+
+  /// Time until the bucket has `tokens`. A bucket that never refills is
+  /// short of them for good.
+  fn time_until(&self, tokens: f64) -> Duration {
+    let missing = (tokens - self.tokens).max(0.0);
+    Duration::try_from_secs_f64(missing / self.refill_per_sec)
+      .unwrap_or(Duration::from_secs(u64::from(u32::MAX)))
+  }
+
+  fn status(&self) -> RateLimitStatus {
+    RateLimitStatus {
+      limit: self.capacity as u32,
+      remaining: self.tokens.max(0.0).floor() as u32,
+      reset: self.time_until(self.capacity),
+    }
+  }
+}
+
+/// State of the bucket of a user after a request, sent to clients so they
+/// can pace their requests.
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimitStatus {
+  pub limit: u32,
+  pub remaining: u32,
+  // Time until the bucket is full again.
+  pub reset: Duration,
+}
+
+/// Request rejected for the bucket being empty.
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimited {
+  pub status: RateLimitStatus,
+  // Time until a token is available.
+  pub retry_after: Duration,
+}
+
+impl RateLimiter {
+  pub fn new(settings: &settings::RateLimit) -> Self {
+    Self {
+      default_limit: settings.default.clone(),
+      routes: settings.routes.clone(),
+      anonymous: settings.anonymous.clone(),
+      buckets: Mutex::new(HashMap::new()),
+    }
+  }
+
+  /// Takes a token from the bucket of the given route and user. When the
+  /// bucket is empty, the request is rejected with how long until a token
+  /// is available.
+  pub fn acquire(&self, route: &str, user: &str) -> Result<RateLimitStatus, RateLimited> {
+    let limit = self.routes.get(route).unwrap_or(&self.default_limit);
+    self.take(limit, route, user.to_owned())
+  }
+
+  /// Like `acquire`, for an anonymous request from the given client IP,
+  /// with the limits of the public tier.
+  pub fn acquire_anonymous(&self, route: &str, ip: IpAddr) -> Result<RateLimitStatus, RateLimited> {
+    let limit = self
+      .anonymous
+      .routes
+      .get(route)
+      .unwrap_or(&self.anonymous.default);
+    // Prefixed so an IP can't share the bucket of a user.
+    self.take(limit, route, format!("ip:{}", ip))
+  }
+
+  fn take(
+    &self,
+    limit: &settings::RouteRateLimit,
+    route: &str,
+    key: String,
+  ) -> Result<RateLimitStatus, RateLimited> {
+    let capacity = f64::from(limit.capacity);
+    let now = Instant::now();
+
+    let mut buckets = self.buckets.lock().unwrap();
+    if buckets.len() >= PRUNE_THRESHOLD {
+      buckets.retain(|_, bucket| {
+        bucket.refill(now);
+        bucket.tokens < bucket.capacity
+      });
+    }
+
+    let bucket = buckets
+      .entry((route.to_owned(), key))
+      .or_insert_with(|| Bucket {
+        tokens: capacity,
+        capacity,
+        refill_per_sec: limit.refill_per_sec,
+        updated_at: now,
+      });
+    bucket.refill(now);
+
+    if bucket.tokens >= 1.0 {
+      bucket.tokens -= 1.0;
+      return Ok(bucket.status());
+    }
+
+    Err(RateLimited {
+      status: bucket.status(),
+      retry_after: bucket.time_until(1.0),
+    })
+  }
+}
+
+/// Middleware limiting the requests of every authenticated user per route,
+/// and of anonymous clients per route and IP with the stricter limits of the
+/// public tier. Responses carry the limit, the requests left and when the
+/// bucket is full again. Anonymous requests whose IP is unknown, e.g. when
+/// the server isn't run with its connect info, are not limited.
+pub async fn limit_requests<B>(
+  State(state): State<AppState>,
+  req: Request<B>,
+  next: Next<B>,
+) -> Response
+where
+  B: Send,
+{
+  let (mut parts, body) = req.into_parts();
+  let user = TokenUser::from_request_parts(&mut parts, &state).await.ok();
+  let route = parts
+    .extensions
+    .get::<MatchedPath>()
+    .map(|path| path.as_str().to_owned());
+  let ip = client_ip(&parts, state.rate_limiter.anonymous.trust_forwarded_for);
+  let req = Request::from_parts(parts, body);
+
+  let route = match route {
+    Some(route) => route,
+    None => return next.run(req).await,
+  };
+  let acquired = match (user, ip) {
+    (Some(user), _) => state.rate_limiter.acquire(&route, &user.id.to_hex()),
+    (None, Some(ip)) => state.rate_limiter.acquire_anonymous(&route, ip),
+    (None, None) => return next.run(req).await,
+  };
+
+  let status = match acquired {
+    Ok(status) => status,
+    Err(rejected) => {
+      debug!(
+        "Rate limit exceeded on {}, returning 429 status code",
+        route
+      );
+      // Round up, so clients retrying after the header value get a token.
+      let retry_after = rejected.retry_after.as_secs_f64().ceil() as u64;
+      let mut res = Error::too_many_requests().into_response();
+      res
+        .headers_mut()
+        .insert(header::RETRY_AFTER, HeaderValue::from(retry_after.max(1)));
+      set_rate_limit_headers(res.headers_mut(), rejected.status);
+      return res;
+    }
+  };
+
+  let mut res = next.run(req).await;
+  set_rate_limit_headers(res.headers_mut(), status);
+  res
+}
+
+fn set_rate_limit_headers(headers: &mut HeaderMap, status: RateLimitStatus) {
+  // Unix timestamp, rounded up like `Retry-After`.
+  let reset = (SystemTime::now() + status.reset)
+    .duration_since(UNIX_EPOCH)
+    .map(|reset| reset.as_secs_f64().ceil() as u64)
+    .unwrap_or_default();
+  let values = [
+    (LIMIT_HEADER, u64::from(status.limit)),
+    (REMAINING_HEADER, u64::from(status.remaining)),
+    (RESET_HEADER, reset),
+  ];
+
+  for (name, value) in values {
+    headers.insert(HeaderName::from_static(name), HeaderValue::from(value));
+  }
+}
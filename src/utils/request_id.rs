@@ -0,0 +1,54 @@
+use axum::{
+  http::{HeaderName, HeaderValue, Request},
+  middleware::Next,
+  response::Response,
+};
+use rand::RngCore;
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+// Longer IDs sent by clients are replaced, they end up in logs and bodies.
+const MAX_LENGTH: usize = 128;
+
+tokio::task_local! {
+  static REQUEST_ID: String;
+}
+
+/// Middleware identifying every request by its `X-Request-Id`. The ID sent by
+/// the client is kept when valid, otherwise one is generated. It is set on
+/// the request, so the request span records it, and echoed in the response.
+pub async fn assign_request_id<B>(mut req: Request<B>, next: Next<B>) -> Response {
+  let name = HeaderName::from_static(REQUEST_ID_HEADER);
+  let id = req
+    .headers()
+    .get(&name)
+    .and_then(|value| value.to_str().ok())
+    .filter(|id| is_valid(id))
+    .map(ToOwned::to_owned)
+    .unwrap_or_else(generate);
+
+  // Generated IDs and the valid ones are visible ASCII, so always valid
+  // header values.
+  let value = HeaderValue::from_str(&id).unwrap();
+  req.headers_mut().insert(name.clone(), value.clone());
+
+  let mut res = REQUEST_ID.scope(id, next.run(req)).await;
+  res.headers_mut().insert(name, value);
+
+  res
+}
+
+/// ID of the request being handled. `None` outside of requests.
+pub fn current() -> Option<String> {
+  REQUEST_ID.try_with(|id| id.clone()).ok()
+}
+
+fn is_valid(id: &str) -> bool {
+  !id.is_empty() && id.len() <= MAX_LENGTH && id.bytes().all(|byte| byte.is_ascii_graphic())
+}
+
+fn generate() -> String {
+  let mut bytes = [0u8; 16];
+  rand::thread_rng().fill_bytes(&mut bytes);
+  hex::encode(bytes)
+}
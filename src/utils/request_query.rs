@@ -1,10 +1,128 @@
 use serde::Deserialize;
+use utoipa::{IntoParams, ToSchema};
+use wither::bson::{doc, Document};
+
+use crate::errors::Error;
+use crate::utils::date::Date;
+use crate::utils::fields::Fields;
+use crate::utils::pagination::Cursor;
+use crate::utils::serde_helpers::deserialize_optional_rfc3339;
+
+// Field collections are sorted by when no other is requested.
+const DEFAULT_SORT: &str = "created_at";
 
 /// This struct is used to represent the query parameters that are sent to the
-/// server endpoints for pagination.
-#[derive(Debug, Deserialize)]
+/// server endpoints for pagination, sorting and filtering by creation date.
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct RequestQuery {
   pub from: Option<String>,
-  pub offset: Option<u64>,
-  pub limit: Option<u64>,
+  pub offset: Option<i64>,
+  pub limit: Option<i64>,
+  /// Cursor of the page to return, as sent in the `x-pagination-next-cursor`
+  /// header of the previous page. Replaces the offset. Only supported when
+  /// sorting by creation date, newest first.
+  #[param(value_type = Option<String>)]
+  pub cursor: Option<Cursor>,
+  /// Field to sort by, defaults to `created_at`. Each route lists the fields
+  /// it supports.
+  pub sort: Option<String>,
+  /// Defaults to `desc`.
+  pub order: Option<SortOrder>,
+  /// Only returns items created after this RFC 3339 date.
+  #[serde(default, deserialize_with = "deserialize_optional_rfc3339")]
+  #[param(value_type = Option<String>, format = DateTime)]
+  pub created_after: Option<Date>,
+  /// Only returns items created before this RFC 3339 date.
+  #[serde(default, deserialize_with = "deserialize_optional_rfc3339")]
+  #[param(value_type = Option<String>, format = DateTime)]
+  pub created_before: Option<Date>,
+  /// Comma separated list of fields to return, e.g. `name,created_at`.
+  /// Defaults to every field.
+  pub fields: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+  Asc,
+  Desc,
+}
+
+impl RequestQuery {
+  /// Whether items are sorted newest first, the only sort cursors can
+  /// resume.
+  pub fn has_default_sort(&self) -> bool {
+    let field = self.sort.as_deref().unwrap_or(DEFAULT_SORT);
+    field == DEFAULT_SORT && self.order != Some(SortOrder::Asc)
+  }
+
+  /// Sort of the query. Fields are checked against the ones the model
+  /// supports, since sorting on unindexed fields can be expensive, and
+  /// rejected with a 422 otherwise. Ties are broken by id so pages stay
+  /// stable.
+  pub fn sort(&self, fields: &[&str]) -> Result<Document, Error> {
+    if self.has_default_sort() {
+      return Ok(Cursor::sort());
+    }
+
+    let field = self.sort.as_deref().unwrap_or(DEFAULT_SORT);
+    if !fields.contains(&field) {
+      return Err(Error::UnsupportedField {
+        parameter: "sort",
+        field: field.to_owned(),
+        allowed: fields.iter().map(|field| field.to_string()).collect(),
+      });
+    }
+    if self.cursor.is_some() {
+      return Err(Error::InvalidQuery(
+        "Cursors are only supported when sorting by created_at in descending order".to_owned(),
+      ));
+    }
+
+    let order = match self.order.unwrap_or(SortOrder::Desc) {
+      SortOrder::Asc => 1_i32,
+      SortOrder::Desc => -1_i32,
+    };
+
+    Ok(doc! { field: order, "_id": order })
+  }
+
+  /// Fields requested by the client, checked against the public fields of
+  /// the route.
+  pub fn fields(&self, allowed: &[&str]) -> Result<Option<Fields>, Error> {
+    self
+      .fields
+      .as_deref()
+      .map(|fields| Fields::parse(fields, allowed))
+      .transpose()
+  }
+
+  /// Adds the creation date range to a filter.
+  pub fn filter_created(&self, query: &mut Document) {
+    let mut range = Document::new();
+    if let Some(after) = self.created_after {
+      range.insert("$gt", after);
+    }
+    if let Some(before) = self.created_before {
+      range.insert("$lt", before);
+    }
+
+    if !range.is_empty() {
+      query.insert("created_at", range);
+    }
+  }
+}
+
+/// Escapes the regular expression metacharacters of a text, so it can be
+/// matched literally with `$regex`.
+pub fn escape_regex(text: &str) -> String {
+  let mut escaped = String::with_capacity(text.len());
+  for char in text.chars() {
+    if r"\^$.|?*+()[]{}".contains(char) {
+      escaped.push('\\');
+    }
+    escaped.push(char);
+  }
+  escaped
 }
@@ -0,0 +1,61 @@
+use axum::{
+  async_trait,
+  extract::FromRequestParts,
+  http::{header, request::Parts},
+};
+use serde::Deserialize;
+use utoipa::IntoParams;
+
+use crate::errors::Error;
+use crate::utils::query::Query;
+
+/// Format of a collection response, negotiated with the `?format=` query
+/// parameter or else the `Accept` header. Defaults to JSON.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResponseFormat {
+  #[default]
+  Json,
+  // One row per item with a header row, see `utils::csv`.
+  Csv,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct FormatQuery {
+  /// `json` or `csv`, takes precedence over the `Accept` header.
+  #[param(value_type = Option<String>)]
+  pub format: Option<ResponseFormat>,
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for ResponseFormat
+where
+  S: Send + Sync,
+{
+  type Rejection = Error;
+
+  async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+    let Query(query) = Query::<FormatQuery>::from_request_parts(parts, state).await?;
+    if let Some(format) = query.format {
+      return Ok(format);
+    }
+
+    let accepts_csv = parts
+      .headers
+      .get_all(header::ACCEPT)
+      .iter()
+      .filter_map(|value| value.to_str().ok())
+      .flat_map(|value| value.split(','))
+      .any(|media_type| {
+        let essence = media_type.split(';').next().unwrap_or_default();
+        essence.trim().eq_ignore_ascii_case("text/csv")
+      });
+
+    if accepts_csv {
+      Ok(ResponseFormat::Csv)
+    } else {
+      Ok(ResponseFormat::Json)
+    }
+  }
+}
@@ -0,0 +1,71 @@
+use std::future::Future;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::time::sleep;
+use tracing::{debug_span, field, warn, Instrument};
+
+/// How many times, and how far apart, an operation is attempted.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+  pub max_attempts: u32,
+  pub base_delay: Duration,
+  pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+  /// Delay before the attempt following `attempt`. Doubles on every attempt
+  /// up to `max_delay`, and half of it is random so that clients failing at
+  /// the same time don't retry in lockstep.
+  pub fn delay(&self, attempt: u32) -> Duration {
+    let factor = 2_u32.saturating_pow(attempt.saturating_sub(1));
+    let delay = self.base_delay.saturating_mul(factor).min(self.max_delay);
+
+    delay / 2 + (delay / 2).mul_f64(jitter())
+  }
+}
+
+/// Runs `operation` until `should_retry` rejects its result or the policy
+/// runs out of attempts, returning the last result. Attempts run inside a
+/// `retry` span which records how many were made.
+pub async fn retry<T, F, Fut, P>(
+  name: &'static str,
+  policy: &RetryPolicy,
+  mut operation: F,
+  should_retry: P,
+) -> T
+where
+  F: FnMut() -> Fut,
+  Fut: Future<Output = T>,
+  P: Fn(&T) -> bool,
+{
+  let span = debug_span!("retry", operation = name, attempts = field::Empty);
+  let max_attempts = policy.max_attempts.max(1);
+  let mut attempt = 1;
+
+  loop {
+    let result = operation().instrument(span.clone()).await;
+
+    if attempt >= max_attempts || !should_retry(&result) {
+      span.record("attempts", attempt);
+      return result;
+    }
+
+    let delay = policy.delay(attempt);
+    warn!(
+      parent: &span,
+      "{} attempt {}/{} failed. Retrying in {:?}", name, attempt, max_attempts, delay
+    );
+    sleep(delay).await;
+    attempt += 1;
+  }
+}
+
+// Number between 0 and 1. It only spreads retries apart, so the sub-second
+// part of the clock is random enough and avoids a dependency.
+fn jitter() -> f64 {
+  let nanos = SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|duration| duration.subsec_nanos())
+    .unwrap_or_default();
+
+  f64::from(nanos) / 1_000_000_000.0
+}
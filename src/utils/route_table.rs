@@ -0,0 +1,144 @@
+use axum::handler::Handler;
+use axum::http::Method;
+use axum::routing::{self, MethodRouter};
+use axum::Router;
+use tracing::{info, warn};
+
+/// Collects the routes of the app before building the axum `Router`, so
+/// every registered route can be logged at startup and duplicate
+/// registrations are reported instead of making axum panic on overlapping
+/// routes.
+pub struct RouteTable<S = ()> {
+  routes: Vec<Route<S>>,
+  duplicates: Vec<RouteInfo>,
+}
+
+struct Route<S> {
+  info: RouteInfo,
+  handler: MethodRouter<S>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RouteInfo {
+  pub method: Method,
+  pub path: String,
+}
+
+impl<S> Default for RouteTable<S> {
+  fn default() -> Self {
+    Self {
+      routes: Vec::new(),
+      duplicates: Vec::new(),
+    }
+  }
+}
+
+impl<S> RouteTable<S>
+where
+  S: Clone + Send + Sync + 'static,
+{
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn get<H, T>(self, path: &str, handler: H) -> Self
+  where
+    H: Handler<T, S>,
+    T: 'static,
+  {
+    self.route(Method::GET, path, routing::get(handler))
+  }
+
+  pub fn post<H, T>(self, path: &str, handler: H) -> Self
+  where
+    H: Handler<T, S>,
+    T: 'static,
+  {
+    self.route(Method::POST, path, routing::post(handler))
+  }
+
+  pub fn put<H, T>(self, path: &str, handler: H) -> Self
+  where
+    H: Handler<T, S>,
+    T: 'static,
+  {
+    self.route(Method::PUT, path, routing::put(handler))
+  }
+
+  pub fn patch<H, T>(self, path: &str, handler: H) -> Self
+  where
+    H: Handler<T, S>,
+    T: 'static,
+  {
+    self.route(Method::PATCH, path, routing::patch(handler))
+  }
+
+  pub fn delete<H, T>(self, path: &str, handler: H) -> Self
+  where
+    H: Handler<T, S>,
+    T: 'static,
+  {
+    self.route(Method::DELETE, path, routing::delete(handler))
+  }
+
+  /// Adds the routes of another table, prefixing their paths.
+  pub fn nest(mut self, prefix: &str, other: RouteTable<S>) -> Self {
+    self
+      .duplicates
+      .extend(other.duplicates.into_iter().map(|info| RouteInfo {
+        method: info.method,
+        path: format!("{}{}", prefix, info.path),
+      }));
+
+    for route in other.routes {
+      let path = format!("{}{}", prefix, route.info.path);
+      self = self.route(route.info.method, &path, route.handler);
+    }
+
+    self
+  }
+
+  pub fn merge(self, other: RouteTable<S>) -> Self {
+    self.nest("", other)
+  }
+
+  /// Registrations that were dropped because the same method and path were
+  /// already registered.
+  pub fn duplicates(&self) -> &[RouteInfo] {
+    &self.duplicates
+  }
+
+  /// Builds the router, logging every registered route.
+  pub fn into_router(self) -> Router<S> {
+    for duplicate in &self.duplicates {
+      warn!(
+        "Duplicate route {} {} was ignored",
+        duplicate.method, duplicate.path
+      );
+    }
+
+    self
+      .routes
+      .into_iter()
+      .fold(Router::new(), |router, route| {
+        info!("Registered route {} {}", route.info.method, route.info.path);
+        router.route(&route.info.path, route.handler)
+      })
+  }
+
+  fn route(mut self, method: Method, path: &str, handler: MethodRouter<S>) -> Self {
+    let info = RouteInfo {
+      method,
+      path: path.to_owned(),
+    };
+
+    // The first registration wins, later ones would be unreachable.
+    if self.routes.iter().any(|route| route.info == info) {
+      self.duplicates.push(info);
+      return self;
+    }
+
+    self.routes.push(Route { info, handler });
+    self
+  }
+}
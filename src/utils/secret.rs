@@ -0,0 +1,14 @@
+use rand::distributions::{Alphanumeric, DistString};
+use sha2::{Digest, Sha256};
+
+/// Random alphanumeric string, e.g. the secret part of API keys and refresh
+/// tokens.
+pub fn generate(length: usize) -> String {
+  Alphanumeric.sample_string(&mut rand::thread_rng(), length)
+}
+
+/// SHA-256 hex digest of a secret. Generated secrets are random enough that
+/// a slow hash isn't needed.
+pub fn hash(secret: &str) -> String {
+  hex::encode(Sha256::digest(secret.as_bytes()))
+}
@@ -0,0 +1,89 @@
+use bson::oid::ObjectId;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Deserializer, Serializer};
+use serde_json::Value;
+
+use crate::utils::address::to_checksum_address;
+use crate::utils::date::Date;
+
+/// Deserializes an optional number sent either as a JSON number or as a
+/// numeric string. Anything else is treated as absent instead of failing the
+/// whole payload.
+pub fn deserialize_optional_number<'de, D>(deserializer: D) -> Result<Option<f64>, D::Error>
+where
+  D: Deserializer<'de>,
+{
+  let value = Option::<Value>::deserialize(deserializer)?;
+
+  let number = match value {
+    Some(Value::Number(number)) => number.as_f64(),
+    Some(Value::String(text)) => text.trim().parse::<f64>().ok(),
+    _ => None,
+  };
+
+  Ok(number)
+}
+
+/// Serializes an optional object id as a hex string, like
+/// `serialize_object_id_as_hex_string` does for required ones.
+pub fn serialize_optional_object_id_as_hex_string<S>(
+  id: &Option<ObjectId>,
+  serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+  S: Serializer,
+{
+  match id {
+    Some(id) => serializer.serialize_some(&id.to_hex()),
+    None => serializer.serialize_none(),
+  }
+}
+
+/// Serializes a stored (lowercase) EVM address in its EIP-55 checksummed
+/// form.
+pub fn serialize_checksum_address<S>(address: &str, serializer: S) -> Result<S::Ok, S::Error>
+where
+  S: Serializer,
+{
+  serializer.serialize_str(&to_checksum_address(address))
+}
+
+/// Same as `serialize_checksum_address`, for optional addresses.
+pub fn serialize_optional_checksum_address<S>(
+  address: &Option<String>,
+  serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+  S: Serializer,
+{
+  match address {
+    Some(address) => serializer.serialize_some(&to_checksum_address(address)),
+    None => serializer.serialize_none(),
+  }
+}
+
+/// Deserializes an optional RFC 3339 date, e.g. `2024-01-31T10:00:00Z`.
+/// Unlike `deserialize_optional_number`, invalid dates are rejected.
+pub fn deserialize_optional_rfc3339<'de, D>(deserializer: D) -> Result<Option<Date>, D::Error>
+where
+  D: Deserializer<'de>,
+{
+  let text = match Option::<String>::deserialize(deserializer)? {
+    Some(text) => text,
+    None => return Ok(None),
+  };
+
+  let date = DateTime::parse_from_rfc3339(text.trim())
+    .map_err(|_| serde::de::Error::custom(format!("invalid date `{}`", text)))?;
+
+  Ok(Some(Date::from_chrono(date.with_timezone(&Utc))))
+}
+
+/// Deserializes a required RFC 3339 date, see `deserialize_optional_rfc3339`.
+pub fn deserialize_rfc3339<'de, D>(deserializer: D) -> Result<Date, D::Error>
+where
+  D: Deserializer<'de>,
+{
+  deserialize_optional_rfc3339(deserializer)?
+    .ok_or_else(|| serde::de::Error::custom("missing date"))
+}
@@ -0,0 +1,107 @@
+use lazy_static::lazy_static;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tokio::time::timeout;
+use tracing::{info, warn};
+
+lazy_static! {
+  pub static ref SHUTDOWN: Shutdown = Shutdown::new();
+}
+
+/// Coordinates the shutdown of the background workers. Workers stop at
+/// their next tick once it is requested, after finishing the work in
+/// progress.
+pub struct Shutdown {
+  sender: watch::Sender<bool>,
+  workers: Mutex<Vec<JoinHandle<()>>>,
+}
+
+impl Shutdown {
+  pub fn new() -> Self {
+    Self {
+      sender: watch::channel(false).0,
+      workers: Mutex::new(Vec::new()),
+    }
+  }
+
+  pub fn subscribe(&self) -> ShutdownSignal {
+    ShutdownSignal(self.sender.subscribe())
+  }
+
+  /// Keeps the handle of a worker, so `drain` waits for it.
+  pub fn track(&self, worker: JoinHandle<()>) {
+    self.workers.lock().unwrap().push(worker);
+  }
+
+  pub fn request(&self) {
+    self.sender.send_replace(true);
+  }
+
+  /// Resolves once the shutdown is requested.
+  pub async fn requested(&self) {
+    self.subscribe().requested().await
+  }
+
+  /// Requests the shutdown and waits for the workers to stop, aborting the
+  /// ones still running after the timeout.
+  pub async fn drain(&self, limit: Duration) {
+    self.request();
+
+    let workers = std::mem::take(&mut *self.workers.lock().unwrap());
+    let aborts = workers
+      .iter()
+      .map(|worker| worker.abort_handle())
+      .collect::<Vec<_>>();
+
+    let stopped = timeout(limit, futures::future::join_all(workers)).await;
+    match stopped {
+      Ok(_) => info!("Background workers stopped"),
+      Err(_) => {
+        warn!(
+          "Background workers still running after {:?}, aborting them",
+          limit
+        );
+        aborts.iter().for_each(|abort| abort.abort());
+      }
+    }
+  }
+}
+
+/// Receiving end of the shutdown, held by a worker.
+#[derive(Clone)]
+pub struct ShutdownSignal(watch::Receiver<bool>);
+
+impl ShutdownSignal {
+  /// Resolves once the shutdown is requested, right away if it already is.
+  pub async fn requested(&mut self) {
+    // The sender lives in a static, so it is never dropped.
+    let _ = self.0.wait_for(|requested| *requested).await;
+  }
+}
+
+/// Resolves on SIGINT (Ctrl+C) or SIGTERM.
+pub async fn signal() {
+  let interrupt = async {
+    tokio::signal::ctrl_c()
+      .await
+      .expect("Failed to listen for SIGINT");
+  };
+
+  #[cfg(unix)]
+  let terminate = async {
+    tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+      .expect("Failed to listen for SIGTERM")
+      .recv()
+      .await;
+  };
+
+  #[cfg(not(unix))]
+  let terminate = std::future::pending::<()>();
+
+  tokio::select! {
+    _ = interrupt => info!("Received SIGINT, shutting down"),
+    _ = terminate => info!("Received SIGTERM, shutting down"),
+  }
+}
@@ -0,0 +1,146 @@
+use axum::body::BoxBody;
+use axum::extract::MatchedPath;
+use axum::http::{Request, Response};
+use axum::middleware::Next;
+use opentelemetry::propagation::{Extractor, Injector};
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry::{global, KeyValue};
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::{Sampler, Tracer, TracerProvider};
+use opentelemetry_sdk::{runtime, Resource};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use std::time::Duration;
+use tracing::{field, info, info_span, Span};
+use tracing_opentelemetry::{OpenTelemetryLayer, OpenTelemetrySpanExt};
+use tracing_subscriber::registry::LookupSpan;
+use wither::bson::oid::ObjectId;
+
+use crate::settings;
+use crate::utils::redact::redact;
+use crate::utils::request_id::REQUEST_ID_HEADER;
+
+/// Layer exporting spans to the configured OTLP collector (e.g. Jaeger or
+/// Tempo). Trace contexts are propagated with `traceparent` headers, see
+/// `make_span` and `trace_headers`.
+pub fn layer<S>(settings: &settings::Telemetry) -> OpenTelemetryLayer<S, Tracer>
+where
+  S: tracing::Subscriber + for<'span> LookupSpan<'span>,
+{
+  let exporter = opentelemetry_otlp::SpanExporter::builder()
+    .with_tonic()
+    .with_endpoint(&settings.otlp_endpoint)
+    .build()
+    .expect("Failed to create OTLP exporter");
+
+  // Requests traced by the caller are always sampled, others by ratio.
+  let sampler = Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(settings.sample_ratio)));
+  let resource = Resource::new([KeyValue::new("service.name", settings.service_name.clone())]);
+  let provider = TracerProvider::builder()
+    .with_batch_exporter(exporter, runtime::Tokio)
+    .with_sampler(sampler)
+    .with_resource(resource)
+    .build();
+
+  let tracer = provider.tracer(env!("CARGO_PKG_NAME"));
+  global::set_tracer_provider(provider);
+  global::set_text_map_propagator(TraceContextPropagator::new());
+
+  tracing_opentelemetry::layer().with_tracer(tracer)
+}
+
+/// Exports the spans still buffered. Called before the process exits.
+pub fn shutdown() {
+  global::shutdown_tracer_provider();
+}
+
+/// Root span of an incoming request, recording its `X-Request-Id` and
+/// continuing the trace of the caller when the request has a `traceparent`
+/// header. The Mongo and upstream spans of the request are its children, and
+/// its `route` and `user.id` are recorded once known, see `record_route` and
+/// `record_user`.
+pub fn make_span<B>(req: &Request<B>) -> Span {
+  let request_id = req
+    .headers()
+    .get(REQUEST_ID_HEADER)
+    .and_then(|value| value.to_str().ok())
+    .unwrap_or_default();
+  let span = info_span!(
+    "request",
+    method = %req.method(),
+    route = field::Empty,
+    uri = %redact(&req.uri().to_string()),
+    version = ?req.version(),
+    headers = ?req.headers(),
+    request_id,
+    user.id = field::Empty,
+  );
+  let context = global::get_text_map_propagator(|propagator| {
+    propagator.extract(&HeaderExtractor(req.headers()))
+  });
+  span.set_parent(context);
+
+  span
+}
+
+/// Middleware recording the matched route in the request span, added to the
+/// routes themselves so the matched path is known.
+pub async fn record_route<B>(req: Request<B>, next: Next<B>) -> Response<BoxBody> {
+  if let Some(route) = req.extensions().get::<MatchedPath>() {
+    Span::current().record("route", route.as_str());
+  }
+
+  next.run(req).await
+}
+
+/// Records the authenticated user in the request span. Does nothing outside
+/// of requests.
+pub fn record_user(user_id: ObjectId) {
+  Span::current().record("user.id", user_id.to_hex());
+}
+
+/// Logs the responses with their latency in milliseconds, as a number so log
+/// collectors can aggregate it, in the request span.
+pub fn on_response<B>(res: &Response<B>, latency: Duration, _span: &Span) {
+  info!(
+    status = res.status().as_u16(),
+    latency_ms = latency.as_millis() as u64,
+    "finished processing request"
+  );
+}
+
+/// `traceparent` header of the current span, to be added to outgoing
+/// requests. Empty when the export is disabled.
+pub fn trace_headers() -> HeaderMap {
+  let mut headers = HeaderMap::new();
+  let context = Span::current().context();
+  global::get_text_map_propagator(|propagator| {
+    propagator.inject_context(&context, &mut HeaderInjector(&mut headers))
+  });
+
+  headers
+}
+
+struct HeaderExtractor<'a>(&'a axum::http::HeaderMap);
+
+impl<'a> Extractor for HeaderExtractor<'a> {
+  fn get(&self, key: &str) -> Option<&str> {
+    self.0.get(key).and_then(|value| value.to_str().ok())
+  }
+
+  fn keys(&self) -> Vec<&str> {
+    self.0.keys().map(|key| key.as_str()).collect()
+  }
+}
+
+struct HeaderInjector<'a>(&'a mut HeaderMap);
+
+impl<'a> Injector for HeaderInjector<'a> {
+  fn set(&mut self, key: &str, value: String) {
+    let name = HeaderName::from_bytes(key.as_bytes());
+    let value = HeaderValue::from_str(&value);
+    if let (Ok(name), Ok(value)) = (name, value) {
+      self.0.insert(name, value);
+    }
+  }
+}
@@ -0,0 +1,37 @@
+use axum::{
+  extract::{MatchedPath, State},
+  http::Request,
+  middleware::Next,
+  response::{IntoResponse, Response},
+};
+use tracing::warn;
+
+use crate::errors::Error;
+use crate::state::AppState;
+
+/// Middleware failing the requests their route doesn't respond to within its
+/// timeout with a 504. Only the response head is waited for, streamed bodies
+/// (e.g. event streams) go on.
+pub async fn limit_duration<B>(
+  State(state): State<AppState>,
+  req: Request<B>,
+  next: Next<B>,
+) -> Response {
+  let route = req
+    .extensions()
+    .get::<MatchedPath>()
+    .map(|path| path.as_str().to_owned());
+  let limit = state.settings.timeouts.request(route.as_deref());
+
+  match tokio::time::timeout(limit, next.run(req)).await {
+    Ok(res) => res,
+    Err(_) => {
+      warn!(
+        "Request to {} timed out after {:?}, returning 504 status code",
+        route.as_deref().unwrap_or("unknown route"),
+        limit
+      );
+      Error::RequestTimeout.into_response()
+    }
+  }
+}
@@ -3,18 +3,29 @@ use jsonwebtoken::{errors::Error, DecodingKey, EncodingKey, Header, TokenData, V
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
 
-use crate::models::user::User;
+use crate::models::user::{Plan, Role, User};
+use crate::settings::SETTINGS;
 
 type TokenResult = Result<TokenData<Claims>, Error>;
 
 static VALIDATION: Lazy<Validation> = Lazy::new(Validation::default);
 static HEADER: Lazy<Header> = Lazy::new(Header::default);
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TokenUser {
   pub id: ObjectId,
   pub name: String,
   pub email: String,
+  // Tokens issued before roles existed don't carry one.
+  #[serde(default)]
+  pub role: Role,
+  // Like the role, plan changes apply once the user authenticates again.
+  #[serde(default)]
+  pub plan: Plan,
+  // Session the token was issued for, see `Session`. API keys and tokens
+  // issued before sessions existed have none, and can't be revoked.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub session: Option<ObjectId>,
 }
 
 impl From<User> for TokenUser {
@@ -23,10 +34,23 @@ impl From<User> for TokenUser {
       id: user.id.unwrap(),
       name: user.name.clone(),
       email: user.email,
+      role: user.role,
+      plan: user.plan,
+      session: None,
     }
   }
 }
 
+/// Authenticated user with the admin role. The role is read from the token
+/// claims, so role changes apply once the user authenticates again.
+#[derive(Debug)]
+pub struct AdminUser(pub TokenUser);
+
+/// Authenticated user with the member role at least, for the routes changing
+/// resources. Read only users can still manage their own account.
+#[derive(Debug)]
+pub struct MemberUser(pub TokenUser);
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
   pub exp: usize, // Expiration time (as UTC timestamp). validate_exp defaults to true in validation
@@ -35,18 +59,24 @@ pub struct Claims {
 }
 
 impl Claims {
-  pub fn new(user: User) -> Self {
+  pub fn new(user: User, session: Option<ObjectId>) -> Self {
+    let now = chrono::Local::now();
+    let ttl = chrono::Duration::seconds(SETTINGS.auth.access_token_ttl_secs as i64);
+
     Self {
-      exp: (chrono::Local::now() + chrono::Duration::days(30)).timestamp() as usize,
-      iat: chrono::Local::now().timestamp() as usize,
-      user: TokenUser::from(user),
+      exp: (now + ttl).timestamp() as usize,
+      iat: now.timestamp() as usize,
+      user: TokenUser {
+        session,
+        ..TokenUser::from(user)
+      },
     }
   }
 }
 
-pub fn create(user: User, secret: &str) -> Result<String, Error> {
+pub fn create(user: User, session: Option<ObjectId>, secret: &str) -> Result<String, Error> {
   let encoding_key = EncodingKey::from_secret(secret.as_ref());
-  let claims = Claims::new(user);
+  let claims = Claims::new(user, session);
 
   jsonwebtoken::encode(&HEADER, &claims, &encoding_key)
 }
@@ -0,0 +1,85 @@
+use data_encoding::BASE32_NOPAD;
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use reqwest::Url;
+use sha1::Sha1;
+
+// RFC 6238 defaults, the only parameters most authenticator apps support.
+const DIGITS: u32 = 6;
+const STEP_SECS: u64 = 30;
+// 160 bits, the size RFC 4226 recommends.
+const SECRET_BYTES: usize = 20;
+
+// Codes of the previous and next steps are accepted too, for clocks being
+// slightly off and codes typed as they change.
+const SKEW_STEPS: u64 = 1;
+
+/// Random secret, base32 encoded like authenticator apps expect it.
+pub fn generate_secret() -> String {
+  let mut secret = [0_u8; SECRET_BYTES];
+  rand::thread_rng().fill_bytes(&mut secret);
+  BASE32_NOPAD.encode(&secret)
+}
+
+/// `otpauth://` URI of a secret, encoded by clients as the QR code scanned
+/// by authenticator apps.
+pub fn otpauth_uri(issuer: &str, account: &str, secret: &str) -> String {
+  let mut uri = Url::parse("otpauth://totp/").unwrap();
+  uri.set_path(&format!("{}:{}", issuer, account));
+  uri
+    .query_pairs_mut()
+    .append_pair("secret", secret)
+    .append_pair("issuer", issuer)
+    .append_pair("algorithm", "SHA1")
+    .append_pair("digits", &DIGITS.to_string())
+    .append_pair("period", &STEP_SECS.to_string());
+
+  uri.to_string()
+}
+
+/// Time step of a Unix timestamp.
+pub fn step(timestamp: u64) -> u64 {
+  timestamp / STEP_SECS
+}
+
+/// Code of a raw secret at a time step, as defined by RFC 4226.
+pub fn code(secret: &[u8], step: u64) -> String {
+  let mut mac = Hmac::<Sha1>::new_from_slice(secret).expect("HMAC accepts keys of any size");
+  mac.update(&step.to_be_bytes());
+  let hash = mac.finalize().into_bytes();
+
+  // Dynamic truncation, 31 bits read at the offset given by the last nibble.
+  let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+  let value = u32::from_be_bytes([
+    hash[offset] & 0x7f,
+    hash[offset + 1],
+    hash[offset + 2],
+    hash[offset + 3],
+  ]);
+
+  format!(
+    "{:0width$}",
+    value % 10_u32.pow(DIGITS),
+    width = DIGITS as usize
+  )
+}
+
+/// Step whose code matches, around the step of `timestamp`. `None` when no
+/// code matches or the secret isn't valid base32.
+pub fn verify(secret: &str, code: &str, timestamp: u64) -> Option<u64> {
+  let secret = BASE32_NOPAD.decode(secret.as_bytes()).ok()?;
+  if !is_code(code) {
+    return None;
+  }
+  let code = code.trim();
+
+  let current = step(timestamp);
+  (current.saturating_sub(SKEW_STEPS)..=current + SKEW_STEPS)
+    .find(|&step| self::code(&secret, step) == code)
+}
+
+/// Whether the input looks like a TOTP code rather than a recovery code.
+pub fn is_code(input: &str) -> bool {
+  let input = input.trim();
+  input.len() == DIGITS as usize && input.chars().all(|c| c.is_ascii_digit())
+}
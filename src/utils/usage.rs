@@ -0,0 +1,216 @@
+use axum::{
+  extract::{FromRequestParts, State},
+  http::{HeaderMap, HeaderName, HeaderValue, Request, StatusCode},
+  middleware::Next,
+  response::{IntoResponse, Response},
+};
+use bson::doc;
+use chrono::{DateTime, Datelike, TimeZone, Utc};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tracing::{debug, error};
+use wither::bson::oid::ObjectId;
+use wither::mongodb::options::UpdateOptions;
+
+use crate::errors::Error;
+use crate::models::usage::Usage;
+use crate::models::user::Plan;
+use crate::settings;
+use crate::state::AppState;
+use crate::utils::date;
+use crate::utils::models::ModelExt;
+use crate::utils::token::TokenUser;
+
+const REQUESTS_LIMIT_HEADER: &str = "x-quota-requests-limit";
+const REQUESTS_REMAINING_HEADER: &str = "x-quota-requests-remaining";
+const CREDITS_LIMIT_HEADER: &str = "x-quota-credits-limit";
+const CREDITS_REMAINING_HEADER: &str = "x-quota-credits-remaining";
+const RESET_HEADER: &str = "x-quota-reset";
+
+tokio::task_local! {
+  static METER: Arc<Meter>;
+}
+
+/// Upstream credits spent by the request being handled.
+struct Meter {
+  credits: AtomicU64,
+  // Credits left in the quota of the user, `None` when quotas are not
+  // enforced.
+  available: Option<u64>,
+}
+
+impl Meter {
+  fn charge(&self) -> Result<(), Error> {
+    let charged = self.credits.fetch_add(1, Ordering::Relaxed) + 1;
+    match self.available {
+      Some(available) if charged > available => {
+        self.credits.fetch_sub(1, Ordering::Relaxed);
+        Err(Error::QuotaExceeded("upstream_credits"))
+      }
+      _ => Ok(()),
+    }
+  }
+}
+
+/// Charges a credit of the user making the request, before calling a paid
+/// upstream API on their behalf. Fails once the monthly quota of the user is
+/// spent. Calls made outside of requests, e.g. by the workers, are free.
+pub fn charge_upstream_credit() -> Result<(), Error> {
+  METER.try_with(|meter| meter.charge()).unwrap_or(Ok(()))
+}
+
+/// Monthly quota of a plan.
+pub fn quota(settings: &settings::Usage, plan: Plan) -> &settings::Quota {
+  match plan {
+    Plan::Free => &settings.free,
+    Plan::Pro => &settings.pro,
+    Plan::Enterprise => &settings.enterprise,
+  }
+}
+
+/// Period of the usage at the given date, its month in UTC.
+pub fn period(at: DateTime<Utc>) -> String {
+  at.format("%Y-%m").to_string()
+}
+
+/// Start of the period following the one of the given date, when quotas are
+/// reset.
+pub fn next_period_start(at: DateTime<Utc>) -> DateTime<Utc> {
+  let (year, month) = match at.month() {
+    12 => (at.year() + 1, 1),
+    month => (at.year(), month + 1),
+  };
+
+  Utc.with_ymd_and_hms(year, month, 1, 0, 0, 0).unwrap()
+}
+
+/// Usage of a user in the current period, zero when they made no requests
+/// yet.
+pub async fn current_usage(user: &ObjectId) -> Result<(u64, u64), Error> {
+  let usage = Usage::find_one(doc! { "user": user, "period": period(Utc::now()) }, None).await?;
+
+  Ok(
+    usage
+      .map(|usage| (to_count(usage.requests), to_count(usage.upstream_credits)))
+      .unwrap_or((0, 0)),
+  )
+}
+
+fn to_count(value: i64) -> u64 {
+  u64::try_from(value).unwrap_or(0)
+}
+
+/// Middleware metering the requests of every authenticated user and the
+/// upstream credits they spend, rejecting requests over the monthly quota of
+/// their plan. Responses carry the quotas and what is left of them.
+/// Anonymous requests are not metered, nor throttled ones: the rate limit
+/// middleware wraps this one, and requests shed further in with a 429 are
+/// left out too.
+pub async fn meter_requests<B>(
+  State(state): State<AppState>,
+  req: Request<B>,
+  next: Next<B>,
+) -> Response
+where
+  B: Send,
+{
+  let (mut parts, body) = req.into_parts();
+  let user = TokenUser::from_request_parts(&mut parts, &state).await.ok();
+  let req = Request::from_parts(parts, body);
+
+  let user = match user {
+    Some(user) => user,
+    None => return next.run(req).await,
+  };
+
+  // Metering is best effort, requests are not failed by the database.
+  let (requests, credits) = match current_usage(&user.id).await {
+    Ok(usage) => usage,
+    Err(err) => {
+      error!("Failed to read the usage of user {}: {}", user.id, err);
+      return next.run(req).await;
+    }
+  };
+
+  let settings = &state.settings.usage;
+  let quota = quota(settings, user.plan);
+  let now = Utc::now();
+
+  if settings.enforce_quotas && requests >= quota.requests {
+    debug!("Monthly request quota exceeded, returning 429 status code");
+    let mut res = Error::QuotaExceeded("requests").into_response();
+    set_quota_headers(res.headers_mut(), quota, requests, credits, now);
+    return res;
+  }
+
+  let meter = Arc::new(Meter {
+    credits: AtomicU64::new(0),
+    available: settings
+      .enforce_quotas
+      .then(|| quota.upstream_credits.saturating_sub(credits)),
+  });
+  let mut res = METER.scope(meter.clone(), next.run(req)).await;
+  if res.status() == StatusCode::TOO_MANY_REQUESTS {
+    return res;
+  }
+  let charged = meter.credits.load(Ordering::Relaxed);
+
+  if let Err(err) = record_usage(&user.id, &period(now), charged).await {
+    error!("Failed to record the usage of user {}: {}", user.id, err);
+  }
+
+  set_quota_headers(
+    res.headers_mut(),
+    quota,
+    requests + 1,
+    credits + charged,
+    now,
+  );
+
+  res
+}
+
+async fn record_usage(user: &ObjectId, period: &str, credits: u64) -> Result<(), Error> {
+  let now = date::now();
+  let credits = i64::try_from(credits).unwrap_or(i64::MAX);
+  let options = UpdateOptions::builder().upsert(true).build();
+
+  Usage::update_one(
+    doc! { "user": user, "period": period },
+    doc! {
+      "$inc": { "requests": 1_i64, "upstream_credits": credits },
+      "$set": { "updated_at": now },
+      "$setOnInsert": { "created_at": now },
+    },
+    options,
+  )
+  .await?;
+
+  Ok(())
+}
+
+fn set_quota_headers(
+  headers: &mut HeaderMap,
+  quota: &settings::Quota,
+  requests: u64,
+  credits: u64,
+  now: DateTime<Utc>,
+) {
+  let values = [
+    (REQUESTS_LIMIT_HEADER, quota.requests),
+    (
+      REQUESTS_REMAINING_HEADER,
+      quota.requests.saturating_sub(requests),
+    ),
+    (CREDITS_LIMIT_HEADER, quota.upstream_credits),
+    (
+      CREDITS_REMAINING_HEADER,
+      quota.upstream_credits.saturating_sub(credits),
+    ),
+    (RESET_HEADER, next_period_start(now).timestamp() as u64),
+  ];
+
+  for (name, value) in values {
+    headers.insert(HeaderName::from_static(name), HeaderValue::from(value));
+  }
+}
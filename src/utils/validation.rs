@@ -0,0 +1,26 @@
+use validator::ValidationError;
+
+use crate::utils::address::normalize_evm_address;
+
+// Custom validators for `#[validate(custom(function = "..."))]` attributes.
+// Optional fields are only validated when present.
+
+/// Rejects empty and whitespace only strings.
+pub fn not_blank(value: &str) -> Result<(), ValidationError> {
+  if value.trim().is_empty() {
+    return Err(error("blank", "must not be blank"));
+  }
+
+  Ok(())
+}
+
+/// Rejects anything but an EVM address, see `normalize_evm_address`.
+pub fn evm_address(value: &str) -> Result<(), ValidationError> {
+  normalize_evm_address(value)
+    .map(|_| ())
+    .map_err(|_| error("evm_address", "must be an EVM address"))
+}
+
+pub fn error(code: &'static str, message: &'static str) -> ValidationError {
+  ValidationError::new(code).with_message(message.into())
+}
@@ -0,0 +1,20 @@
+use axum::http::{header, HeaderMap};
+
+use crate::errors::Error;
+
+/// Reads the document version a client expects to update from the
+/// `If-Match` header. Both quoted (ETag style) and bare values are accepted.
+pub fn expected_version(headers: &HeaderMap) -> Result<i64, Error> {
+  let value = headers
+    .get(header::IF_MATCH)
+    .ok_or_else(Error::precondition_required)?
+    .to_str()
+    .map_err(|_| Error::bad_request())?;
+
+  value
+    .trim()
+    .trim_start_matches("W/")
+    .trim_matches('"')
+    .parse::<i64>()
+    .map_err(|_| Error::bad_request())
+}